@@ -0,0 +1,15 @@
+//! CTAPHID output reports, as the host sends them over USB HID
+
+#![no_main]
+
+use icesickle_core::ctaphid::{Assembler, REPORT_LEN};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut assembler = Assembler::default();
+    for chunk in data.chunks(REPORT_LEN) {
+        let mut report = [0u8; REPORT_LEN];
+        report[..chunk.len()].copy_from_slice(chunk);
+        let _ = assembler.push(&report);
+    }
+});
@@ -0,0 +1,31 @@
+//! One frame, as the device decodes a request and a host a response; what
+//! decodes must survive re-encoding
+
+#![no_main]
+
+use icesickle_core::protocol::{self, Request, Response, MAX_FRAME_LEN};
+use libfuzzer_sys::fuzz_target;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+fn round_trip<T: DeserializeOwned + Serialize + PartialEq + core::fmt::Debug>(data: &[u8]) {
+    let mut frame = data.to_vec();
+    let (seq, _, decoded) = protocol::decode_frame::<T>(&mut frame);
+    let Ok(message) = decoded else {
+        return;
+    };
+
+    let mut out = [0u8; MAX_FRAME_LEN + 1];
+    let Ok(encoded) = protocol::encode_frame(seq, &message, &mut out) else {
+        return;
+    };
+    let mut again = encoded[..encoded.len() - 1].to_vec();
+    let (seq_again, _, decoded_again) = protocol::decode_frame::<T>(&mut again);
+    assert_eq!(seq_again, seq);
+    assert_eq!(decoded_again.as_ref(), Ok(&message));
+}
+
+fuzz_target!(|data: &[u8]| {
+    round_trip::<Request>(data);
+    round_trip::<Response>(data);
+});
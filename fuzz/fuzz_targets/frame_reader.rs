@@ -0,0 +1,13 @@
+//! Bytes from the serial port, one at a time, as the device reads them
+
+#![no_main]
+
+use icesickle_core::protocol::FrameReader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = FrameReader::new();
+    for &byte in data.iter().chain(&[0]) {
+        let _ = reader.push(byte);
+    }
+});
@@ -0,0 +1,19 @@
+//! Device output as `icesickle-verify` reads it: fixed lines and compact
+//! records (bytes and text), each then verified
+
+#![no_main]
+
+use icesickle_core::attestation;
+use icesickle_core::protocol::AttestationRecord;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut records = vec![AttestationRecord::from_compact(data)];
+    if let Ok(text) = core::str::from_utf8(data) {
+        records.push(AttestationRecord::from_fixed_line(text));
+        records.push(AttestationRecord::from_compact_text(text));
+    }
+    for record in records.into_iter().flatten() {
+        let _ = attestation::verify(&record);
+    }
+});
@@ -0,0 +1,12 @@
+//! NMEA lines from the GPS UART, as the `gps` time source reads them
+
+#![no_main]
+
+use icesickle_core::wallclock;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(line) = core::str::from_utf8(data) {
+        let _ = wallclock::parse_rmc(line);
+    }
+});
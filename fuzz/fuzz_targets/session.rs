@@ -0,0 +1,80 @@
+//! Sealed requests in an encrypted session: forged frames as they arrive,
+//! and plaintexts sealed under the session's own key so the fuzzer also
+//! reaches the request decoder behind the AEAD
+
+#![no_main]
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use icesickle_core::entropy::HardwareRng;
+use icesickle_core::hal::mock::MockNoise;
+use icesickle_core::protocol::{SealedFrame, MAX_SEALED_LEN};
+use icesickle_core::session::Session;
+use libfuzzer_sys::fuzz_target;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const HOST_SECRET: [u8; 32] = [0x44; 32];
+const TAG_LEN: usize = 16;
+
+/// Host-to-device key, derived as `session` documents
+fn host_key(device_public: [u8; 32]) -> [u8; 32] {
+    let secret = StaticSecret::from(HOST_SECRET);
+    let host_public = PublicKey::from(&secret).to_bytes();
+    let shared = secret.diffie_hellman(&PublicKey::from(device_public));
+
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(&host_public);
+    salt[32..].copy_from_slice(&device_public);
+    let mut okm = [0u8; 64];
+    Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+        .expand(b"IceSickle session v1", &mut okm)
+        .unwrap();
+    okm[..32].try_into().unwrap()
+}
+
+fn seal(key: &[u8; 32], counter: u64, plaintext: &[u8]) -> SealedFrame {
+    let mut nonce = [0u8; 12];
+    nonce[0] = 0x01;
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+
+    let mut buf = [0u8; MAX_SEALED_LEN];
+    let len = plaintext.len().min(MAX_SEALED_LEN - TAG_LEN);
+    buf[..len].copy_from_slice(&plaintext[..len]);
+    let tag = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt_in_place_detached(Nonce::from_slice(&nonce), &[], &mut buf[..len])
+        .unwrap();
+
+    let mut ciphertext = heapless::Vec::new();
+    ciphertext.extend_from_slice(&buf[..len]).unwrap();
+    ciphertext.extend_from_slice(&tag).unwrap();
+    SealedFrame {
+        counter,
+        ciphertext,
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((counter, body)) = data.split_first_chunk::<8>() else {
+        return;
+    };
+    let counter = u64::from_le_bytes(*counter);
+
+    let rng = HardwareRng::from_source(MockNoise::new(0x5e)).unwrap();
+    let host_public = PublicKey::from(&StaticSecret::from(HOST_SECRET)).to_bytes();
+    let (mut session, device_public) = Session::respond(&rng, &host_public, 0).unwrap();
+
+    // As it arrives off the wire: almost always fails authentication
+    let mut forged = SealedFrame {
+        counter,
+        ciphertext: heapless::Vec::new(),
+    };
+    let len = body.len().min(MAX_SEALED_LEN);
+    forged.ciphertext.extend_from_slice(&body[..len]).unwrap();
+    let _ = session.open(&forged, 1);
+
+    // Authentic, so the plaintext reaches the request decoder
+    let sealed = seal(&host_key(device_public), counter, body);
+    let _ = session.open(&sealed, 2);
+});
@@ -0,0 +1,11 @@
+//! One `binary-stream` frame, as `icesickle-verify --stream` decodes it
+
+#![no_main]
+
+use icesickle_core::stream;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut frame = data.to_vec();
+    let _ = stream::decode(&mut frame);
+});
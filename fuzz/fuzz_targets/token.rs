@@ -0,0 +1,10 @@
+//! `LoadToken` bytes, as a host sends them
+
+#![no_main]
+
+use icesickle_core::auth::{Token, TOKEN_LEN};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    assert_eq!(Token::parse(data).is_some(), data.len() == TOKEN_LEN);
+});
@@ -0,0 +1,12 @@
+//! A reassembled push message, as a browser or host verifier splits it
+
+#![no_main]
+
+use icesickle_core::transport;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some((payload, _, _)) = transport::parse(data) {
+        assert!(2 + payload.len() + 32 + 64 <= data.len());
+    }
+});
@@ -0,0 +1,54 @@
+//! Host benchmarks of signing and payload encoding:
+//! `cargo bench -p icesickle-core --features mock --target x86_64-unknown-linux-gnu`
+//!
+//! Host figures only rank changes against each other; the device's own
+//! phase timings come from the `instrument` feature. `create` covers key
+//! generation, signing and zeroizing, as the signer task runs them. With
+//! `sign-p256` the same benches sign and verify with P-256.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use icesickle_core::attestation::{self, Attestation, AttestationEvent};
+use icesickle_core::entropy::HardwareRng;
+use icesickle_core::hal::mock::{MockNoise, MockTimer};
+use icesickle_core::protocol::AttestationRecord;
+
+const PRESS: AttestationEvent = AttestationEvent::ButtonPress { gpio: 0 };
+
+fn signing(c: &mut Criterion) {
+    let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+    let clock = MockTimer::at(1_000);
+
+    c.bench_function("create", |b| {
+        b.iter(|| Attestation::create(&rng, &clock, black_box(PRESS)).unwrap())
+    });
+    c.bench_function("create_with_challenge", |b| {
+        b.iter(|| {
+            Attestation::create_with_challenge(&rng, &clock, black_box(PRESS), Some([0x42; 32]))
+                .unwrap()
+        })
+    });
+
+    let record = AttestationRecord::from(&Attestation::create(&rng, &clock, PRESS).unwrap());
+    c.bench_function("verify", |b| {
+        b.iter(|| assert!(attestation::verify(black_box(&record))))
+    });
+}
+
+fn encoding(c: &mut Criterion) {
+    let rng = HardwareRng::from_source(MockNoise::new(0x5b)).unwrap();
+    let attestation = Attestation::create(&rng, &MockTimer::at(1_000), PRESS).unwrap();
+    let record = AttestationRecord::from(&attestation);
+
+    c.bench_function("payload_bytes", |b| {
+        b.iter(|| black_box(&attestation).payload_bytes())
+    });
+    c.bench_function("fixed_line", |b| b.iter(|| black_box(&record).fixed_line()));
+    c.bench_function("compact", |b| b.iter(|| black_box(&record).compact()));
+    c.bench_function("json", |b| b.iter(|| black_box(&record).json()));
+}
+
+criterion_group!(benches, signing, encoding);
+criterion_main!(benches);
@@ -0,0 +1,167 @@
+//! ATECC608A/B random numbers
+//!
+//! A Microchip ATECC608 on the I2C bus, used only as a second, independent
+//! silicon RNG whose output `entropy::MixedEntropy` hashes into every key.
+//! Nothing here makes the chip an identity or key store (see `auth`'s
+//! anti-patterns): the driver sends only Wake, Random and Sleep. It never
+//! reads the serial number or the configuration zone, never uses a key
+//! slot, and never signs. The wake reply it checks is the same on every
+//! chip.
+//!
+//! Random is split in two ([`start_random`], [`read_random`]) so the
+//! firmware does not block the event loop for its execution time. Every
+//! packet carries the chip's CRC-16, which is checked on replies.
+//!
+//! A chip whose configuration zone is still unlocked answers Random with a
+//! fixed `FF FF 00 00` pattern. That is refused like any other bad reply.
+
+use crate::error::{IceSickleError, Result};
+use crate::hal::{I2cBus, Timer};
+
+/// Default I2C address (0xC0 in the datasheet's 8-bit form)
+pub const ADDRESS: u8 = 0x60;
+
+/// Longest execution time of Random; read the result no earlier
+pub const RANDOM_EXEC_MS: u64 = 23;
+
+/// Word address of a command packet
+const WORD_COMMAND: u8 = 0x03;
+
+/// Word address that puts the chip to sleep
+const WORD_SLEEP: u8 = 0x01;
+
+/// Random opcode
+const OP_RANDOM: u8 = 0x1B;
+
+/// Reply to a successful wake
+const WAKE_REPLY: [u8; 4] = [0x04, 0x11, 0x33, 0x43];
+
+/// Random's output while the configuration zone is unlocked
+const UNLOCKED_PATTERN: [u8; 4] = [0xFF, 0xFF, 0x00, 0x00];
+
+/// Wake the chip and check its reply
+///
+/// The wake pulse is SDA held low for at least 60 us, made here by
+/// addressing 0x00, which nothing acknowledges.
+pub fn wake(bus: &mut dyn I2cBus, timer: &impl Timer) -> Result<()> {
+    let _ = bus.write(0x00, &[0x00]);
+    // tWHI: 1.5 ms before the chip answers
+    timer.delay_ms(2);
+    let mut reply = [0u8; 4];
+    bus.read(ADDRESS, &mut reply)?;
+    if reply != WAKE_REPLY {
+        return Err(IceSickleError::Sensor);
+    }
+    Ok(())
+}
+
+/// Send Random (mode 0: update the EEPROM seed as the datasheet advises)
+pub fn start_random(bus: &mut dyn I2cBus) -> Result<()> {
+    let mut packet = [WORD_COMMAND, 0x07, OP_RANDOM, 0x00, 0x00, 0x00, 0, 0];
+    let crc = crc16(&packet[1..6]);
+    packet[6..].copy_from_slice(&crc.to_le_bytes());
+    bus.write(ADDRESS, &packet)
+}
+
+/// Read the 32 bytes Random produced, at least [`RANDOM_EXEC_MS`] after
+/// [`start_random`]
+///
+/// The chip does not acknowledge reads while it is busy, so an early read
+/// fails without losing the result.
+pub fn read_random(bus: &mut dyn I2cBus) -> Result<[u8; 32]> {
+    let mut reply = [0u8; 35];
+    bus.read(ADDRESS, &mut reply)?;
+    // Anything else is a 4-byte status packet
+    if reply[0] != 35 || crc16(&reply[..33]).to_le_bytes() != reply[33..] {
+        return Err(IceSickleError::Sensor);
+    }
+    let mut random = [0u8; 32];
+    random.copy_from_slice(&reply[1..33]);
+    if random.chunks(4).all(|word| word == UNLOCKED_PATTERN) {
+        return Err(IceSickleError::Sensor);
+    }
+    Ok(random)
+}
+
+/// Put the chip to sleep until the next wake
+pub fn sleep(bus: &mut dyn I2cBus) -> Result<()> {
+    bus.write(ADDRESS, &[WORD_SLEEP])
+}
+
+/// The chip's CRC-16: polynomial 0x8005, data bits LSB first, no reflection
+/// of the result
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        for bit in 0..8 {
+            let data_bit = (byte >> bit) & 1 == 1;
+            let crc_bit = crc >> 15 == 1;
+            crc <<= 1;
+            if data_bit != crc_bit {
+                crc ^= 0x8005;
+            }
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{MockI2c, MockTimer};
+
+    fn reply(random: &[u8; 32]) -> Vec<u8> {
+        let mut reply = vec![35];
+        reply.extend_from_slice(random);
+        let crc = crc16(&reply);
+        reply.extend_from_slice(&crc.to_le_bytes());
+        reply
+    }
+
+    #[test]
+    fn test_crc16() {
+        // The wake reply carries its own CRC
+        assert_eq!(crc16(&WAKE_REPLY[..2]).to_le_bytes(), WAKE_REPLY[2..]);
+    }
+
+    #[test]
+    fn test_wake() {
+        let mut bus = MockI2c::default();
+        bus.respond(&WAKE_REPLY);
+        wake(&mut bus, &MockTimer::at(0)).unwrap();
+
+        // A status packet instead (e.g. still executing)
+        bus.respond(&[0x04, 0xEE, 0x03, 0x43]);
+        assert_eq!(
+            wake(&mut bus, &MockTimer::at(0)),
+            Err(IceSickleError::Sensor)
+        );
+    }
+
+    #[test]
+    fn test_random() {
+        let mut bus = MockI2c::default();
+        start_random(&mut bus).unwrap();
+        let (address, packet) = &bus.writes[0];
+        assert_eq!(*address, ADDRESS);
+        assert_eq!(packet[..6], [0x03, 0x07, 0x1B, 0x00, 0x00, 0x00]);
+        assert_eq!(crc16(&packet[1..6]).to_le_bytes(), packet[6..]);
+
+        let random: [u8; 32] = core::array::from_fn(|i| i as u8);
+        bus.respond(&reply(&random));
+        assert_eq!(read_random(&mut bus), Ok(random));
+
+        let mut corrupt = reply(&random);
+        corrupt[5] ^= 1;
+        bus.respond(&corrupt);
+        assert_eq!(read_random(&mut bus), Err(IceSickleError::Sensor));
+
+        // Unlocked configuration zone
+        let mut unlocked = [0u8; 32];
+        for word in unlocked.chunks_mut(4) {
+            word.copy_from_slice(&UNLOCKED_PATTERN);
+        }
+        bus.respond(&reply(&unlocked));
+        assert_eq!(read_random(&mut bus), Err(IceSickleError::Sensor));
+    }
+}
@@ -0,0 +1,961 @@
+//! Core attestation logic
+//!
+//! This module implements the ephemeral-key signing primitive:
+//! - Generate a fresh keypair per attestation (never reused), Ed25519 by
+//!   default (see `scheme`)
+//! - Sign a structured payload containing the event and timestamp, behind
+//!   the domain prefix [`SIGNING_DOMAIN`]
+//! - Zeroize the private key immediately after signing
+//!
+//! The keypair is NEVER persisted to flash or RAM beyond the signing operation.
+//!
+//! The signing path never touches the heap: the payload is encoded into a
+//! fixed stack buffer and hex output uses fixed-capacity strings. That rules
+//! out allocator failure mid-signing and keeps key-adjacent data from being
+//! copied into heap blocks that are freed without being cleared.
+//!
+//! The key seed is drawn into [`CRYPTO_WORKSPACE`], a fixed static region,
+//! so a panic mid-signing leaves it somewhere the fatal path knows to wipe.
+//! It has a slot for each task that may sign at once, and an [`Attestation`]
+//! is `Send`: a task with its own `HardwareRng` can create one and hand it
+//! to another (the firmware's `signer-core` signs on the second core).
+
+use icesickle_payload::attestation::AttestationPayload;
+use zeroize::ZeroizeOnDrop;
+
+use crate::auth::{Token, TokenProof};
+use crate::blind;
+use crate::companion::{self, Subject};
+use crate::context::Context;
+use crate::entropy::HardwareRng;
+use crate::error::{IceSickleError, Result};
+use crate::hal::{EntropySource, Timer};
+use crate::instrument::{self, Phase};
+use crate::multibase::DidKey;
+use crate::policy::{self, Policy};
+use crate::protocol::AttestationRecord;
+use crate::scheme::{self, Algorithm, SignatureScheme};
+#[cfg(not(any(test, all(feature = "mock", not(target_os = "espidf")))))]
+use crate::scrub::CRYPTO_WORKSPACE;
+#[cfg(any(test, all(feature = "mock", not(target_os = "espidf"))))]
+use crate::scrub::{WorkspacePool, SIGNING_TASKS};
+use crate::wallclock::WallTime;
+
+pub use icesickle_payload::attestation::{
+    hex_decode, hex_decode_array, hex_encode, signed_message, verify, AttestationEvent, Compact,
+    CompactText, FixedLine, JsonRecord, PayloadBytes, PublicKeyHex, SignatureHex, SignedMessage,
+    COMPACT_TEXT_PREFIX, MAX_COMPACT_LEN, MAX_EVENT_LEN, MAX_JSON_LEN, MAX_PAYLOAD_LEN,
+    MAX_SIGNED_LEN, PAYLOAD_VERSION, SIGNING_DOMAIN, SIGNING_DOMAIN_VERSION,
+};
+
+/// Seeds drawn before keygen gives up; only a stuck source fails them all
+const MAX_SEED_DRAWS: usize = 4;
+
+/// Wrapper for the signing key that guarantees zeroization
+#[derive(ZeroizeOnDrop)]
+struct EphemeralSigningKey {
+    #[zeroize(skip)] // every scheme's key zeroizes itself on drop
+    inner: <scheme::Active as SignatureScheme>::SigningKey,
+}
+
+/// Lend a [`CRYPTO_WORKSPACE`] slot to `f`; `None` if every slot is held
+#[cfg(not(any(test, all(feature = "mock", not(target_os = "espidf")))))]
+fn with_seed_workspace<R>(f: impl FnOnce(&mut [u8; 32]) -> R) -> Option<R> {
+    CRYPTO_WORKSPACE.with(f)
+}
+
+/// libtest signs on more threads at once than the device has signing
+/// tasks, so each test thread gets a pool of its own: in this crate's tests
+/// and in host tests of crates that enable `mock`
+#[cfg(any(test, all(feature = "mock", not(target_os = "espidf"))))]
+fn with_seed_workspace<R>(f: impl FnOnce(&mut [u8; 32]) -> R) -> Option<R> {
+    thread_local! {
+        static POOL: WorkspacePool<32, SIGNING_TASKS> = const { WorkspacePool::new() };
+    }
+    POOL.with(|pool| pool.with(f))
+}
+
+impl EphemeralSigningKey {
+    fn new<S: EntropySource>(rng: &HardwareRng<S>) -> Result<Self> {
+        // The seed lives only in the crypto workspace, which the fatal path
+        // can find and wipe; it is zeroed again as soon as this returns.
+        // Ed25519 takes any seed; a P-256 seed of zero or above the group
+        // order is drawn again.
+        let inner = with_seed_workspace(|seed| {
+            (0..MAX_SEED_DRAWS).find_map(|_| {
+                rng.fill_bytes(seed);
+                scheme::Active::from_seed(seed)
+            })
+        })
+        .ok_or(IceSickleError::Busy)?
+        .ok_or(IceSickleError::Rng)?;
+        Ok(Self { inner })
+    }
+
+    fn public_key(&self) -> [u8; 32] {
+        scheme::Active::public_key(&self.inner)
+    }
+
+    fn sign(&self, message: &[u8]) -> [u8; 64] {
+        scheme::Active::sign(&self.inner, message)
+    }
+}
+
+/// A completed attestation (public data only - private key already zeroized)
+pub struct Attestation {
+    version: u8,
+    event: AttestationEvent,
+    timestamp_ms: u64,
+    counter: u32,
+    wall_clock: Option<WallTime>,
+    policy: Policy,
+    suppressed: u32,
+    challenge: Option<[u8; 32]>,
+    token: Option<TokenProof>,
+    boot_nonce: Option<[u8; 16]>,
+    algorithm: Algorithm,
+    context: Option<Context>,
+    measurement: Option<[u8; 32]>,
+    public_key: [u8; 32],
+    signature: [u8; 64],
+    companions: companion::Signatures,
+}
+
+impl Attestation {
+    /// Create a new attestation for the given event
+    ///
+    /// This function:
+    /// 1. Generates a fresh ephemeral keypair
+    /// 2. Constructs and serializes the payload, which binds the key when a
+    ///    token is spent
+    /// 3. Signs the payload, then each enabled companion message
+    /// 4. Zeroizes the private key (automatic via Drop)
+    /// 5. Returns the attestation with public key + signature
+    pub fn create<S: EntropySource>(
+        rng: &HardwareRng<S>,
+        clock: &impl Timer,
+        event: AttestationEvent,
+    ) -> Result<Self> {
+        Self::create_with_challenge(rng, clock, event, None)
+    }
+
+    /// Create an attestation for `event` answering a verifier's `challenge`
+    /// (see `challenge`); [`Attestation::create`] signs none
+    pub fn create_with_challenge<S: EntropySource>(
+        rng: &HardwareRng<S>,
+        clock: &impl Timer,
+        event: AttestationEvent,
+        challenge: Option<[u8; 32]>,
+    ) -> Result<Self> {
+        Self::create_authorized(rng, clock, event, challenge, None, None)
+    }
+
+    /// Create an attestation that also spends `token`, signing its proof
+    /// (see `auth`), and signs the host's `context` (see `context`); the
+    /// token is zeroized whether or not signing succeeds
+    pub fn create_authorized<S: EntropySource>(
+        rng: &HardwareRng<S>,
+        clock: &impl Timer,
+        event: AttestationEvent,
+        challenge: Option<[u8; 32]>,
+        token: Option<Token>,
+        context: Option<Context>,
+    ) -> Result<Self> {
+        // Get current timestamp, as the policy signs it
+        let policy = policy::ACTIVE;
+        let timestamp_ms = policy.timestamp_ms(clock.now_ms());
+        let wall_clock = policy.wall_clock(clock.wall_clock());
+        let boot_nonce = policy.boot_nonce(|| boot_nonce(rng));
+        let measurement = MEASUREMENT.get().copied();
+        // This boot's counter offset comes from the RNG too, so it is drawn
+        // before the health check below covers it
+        if policy.contains(Policy::RANDOM_COUNTER) {
+            counter_offset(rng);
+        }
+
+        // Generate ephemeral keypair - exists only for this scope. Random
+        // delays around keygen and signing blind trace alignment (`blind`);
+        // they sit outside the spans so instrumented timings stay clean.
+        blind::jitter(rng);
+        let span = instrument::start(Phase::KeyGen);
+        let signing_key = EphemeralSigningKey::new(rng)?;
+        let public_key = signing_key.public_key();
+        span.finish();
+        blind::jitter(rng);
+
+        // Every byte drawn so far, the key's included, has been through the
+        // continuous health tests; sign nothing if any failed
+        rng.check()?;
+
+        // Taken only once keygen and the health check have passed, so a
+        // refused or failed attempt neither burns a counter value nor drops
+        // the suppressed presses
+        let counter = policy.counter(increment_counter(), || counter_offset(rng));
+        let suppressed = take_suppressed();
+
+        // A token's proof binds the key it is signed under
+        let token = token.map(|token| token.prove(&public_key));
+
+        // Build payload
+        let payload = AttestationPayload {
+            version: PAYLOAD_VERSION,
+            event,
+            timestamp_ms,
+            counter,
+            wall_clock,
+            policy,
+            suppressed,
+            challenge,
+            token,
+            boot_nonce,
+            algorithm: scheme::Active::ALGORITHM,
+            context: context.clone(),
+            measurement,
+        };
+
+        // Serialize payload (deterministic encoding)
+        let span = instrument::start(Phase::Serialize);
+        let mut payload_buf = [0u8; MAX_PAYLOAD_LEN];
+        let payload_bytes = postcard::to_slice(&payload, &mut payload_buf)?;
+        span.finish();
+
+        // Sign
+        let span = instrument::start(Phase::Sign);
+        let signature = signing_key.sign(&signed_message(payload_bytes));
+        let subject = Subject {
+            payload: payload_bytes,
+            version: PAYLOAD_VERSION,
+            event,
+            timestamp_ms,
+            counter,
+            wall_clock,
+            policy,
+            suppressed,
+            challenge,
+            token,
+            boot_nonce,
+            algorithm: scheme::Active::ALGORITHM,
+            context: context.as_deref(),
+            measurement,
+            public_key: &public_key,
+        };
+        let companions = companion::ENABLED
+            .iter()
+            .map(|c| signing_key.sign(&c.message(&subject)))
+            .collect();
+        span.finish();
+        blind::jitter(rng);
+
+        // signing_key is dropped and zeroized here
+
+        Ok(Self {
+            version: PAYLOAD_VERSION,
+            event,
+            timestamp_ms,
+            counter,
+            wall_clock,
+            policy,
+            suppressed,
+            challenge,
+            token,
+            boot_nonce,
+            algorithm: scheme::Active::ALGORITHM,
+            context,
+            measurement,
+            public_key,
+            signature,
+            companions,
+        })
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn event(&self) -> AttestationEvent {
+        self.event
+    }
+
+    pub fn timestamp_ms(&self) -> u64 {
+        self.timestamp_ms
+    }
+
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    pub fn wall_clock(&self) -> Option<WallTime> {
+        self.wall_clock
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    pub fn suppressed(&self) -> u32 {
+        self.suppressed
+    }
+
+    pub fn challenge(&self) -> Option<[u8; 32]> {
+        self.challenge
+    }
+
+    pub fn token(&self) -> Option<TokenProof> {
+        self.token
+    }
+
+    pub fn boot_nonce(&self) -> Option<[u8; 16]> {
+        self.boot_nonce
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn context(&self) -> Option<&Context> {
+        self.context.as_ref()
+    }
+
+    pub fn measurement(&self) -> Option<[u8; 32]> {
+        self.measurement
+    }
+
+    pub fn public_key_bytes(&self) -> &[u8; 32] {
+        &self.public_key
+    }
+
+    pub fn signature_bytes(&self) -> &[u8; 64] {
+        &self.signature
+    }
+
+    /// Companion signatures, in `companion::ENABLED` order
+    pub fn companions(&self) -> &companion::Signatures {
+        &self.companions
+    }
+
+    /// The encoded payload, bare; the signature covers it behind
+    /// [`SIGNING_DOMAIN`] (see [`signed_message`])
+    pub fn payload_bytes(&self) -> PayloadBytes {
+        let payload = AttestationPayload {
+            version: self.version,
+            event: self.event,
+            timestamp_ms: self.timestamp_ms,
+            counter: self.counter,
+            wall_clock: self.wall_clock,
+            policy: self.policy,
+            suppressed: self.suppressed,
+            challenge: self.challenge,
+            token: self.token,
+            boot_nonce: self.boot_nonce,
+            algorithm: self.algorithm,
+            context: self.context.clone(),
+            measurement: self.measurement,
+        };
+        let mut payload_buf = [0u8; MAX_PAYLOAD_LEN];
+        // Every payload fits the buffer it was first encoded into
+        postcard::to_slice(&payload, &mut payload_buf)
+            .ok()
+            .and_then(|b| PayloadBytes::from_slice(b).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn public_key_hex(&self) -> PublicKeyHex {
+        hex_encode(&self.public_key)
+    }
+
+    pub fn signature_hex(&self) -> SignatureHex {
+        hex_encode(&self.signature)
+    }
+
+    /// The public key as a `did:key` DID (see `multibase`)
+    pub fn public_key_did(&self) -> DidKey {
+        self.algorithm.did_key(&self.public_key)
+    }
+
+    /// Single-line rendering; see [`AttestationRecord::fixed_line`]
+    pub fn fixed_line(&self) -> FixedLine {
+        AttestationRecord::from(self).fixed_line()
+    }
+}
+
+/// Monotonic counter (resets on power cycle, survives soft resets)
+static COUNTER: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+fn increment_counter() -> u32 {
+    COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+}
+
+/// Number of attestations produced since boot
+pub fn count() -> u32 {
+    COUNTER.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+/// Presses refused by the cooldown since the last attestation
+static SUPPRESSED: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+/// Count a press the cooldown refused; the next attestation carries the
+/// count and restarts it
+pub fn record_suppressed() {
+    let _ = SUPPRESSED.fetch_update(
+        std::sync::atomic::Ordering::SeqCst,
+        std::sync::atomic::Ordering::SeqCst,
+        |n| n.checked_add(1),
+    );
+}
+
+fn take_suppressed() -> u32 {
+    SUPPRESSED.swap(0, std::sync::atomic::Ordering::SeqCst)
+}
+
+/// This boot's counter offset under `Policy::RANDOM_COUNTER`
+static COUNTER_OFFSET: std::sync::OnceLock<u32> = std::sync::OnceLock::new();
+
+/// Drawn at the first attestation; below 2^31, so the counter does not wrap
+/// within a boot
+fn counter_offset<S: EntropySource>(rng: &HardwareRng<S>) -> u32 {
+    *COUNTER_OFFSET.get_or_init(|| {
+        let mut bytes = [0u8; 4];
+        rng.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes) >> 1
+    })
+}
+
+/// This boot's nonce (see [`boot_nonce`])
+static BOOT_NONCE: std::sync::OnceLock<[u8; 16]> = std::sync::OnceLock::new();
+
+/// This boot's random nonce, drawn on the first call
+///
+/// The counter restarts at every power cycle, so "counter 3" alone does not
+/// say which boot it came from; every payload of a boot carries the same
+/// nonce, and a verifier compares counters only between payloads that
+/// share it. The nonce is never stored, so it identifies a boot and not
+/// the device. The firmware draws it at startup, once the RNG is up.
+pub fn boot_nonce<S: EntropySource>(rng: &HardwareRng<S>) -> [u8; 16] {
+    *BOOT_NONCE.get_or_init(|| {
+        let mut nonce = [0u8; 16];
+        rng.fill_bytes(&mut nonce);
+        nonce
+    })
+}
+
+/// The running firmware's measurement (see [`set_measurement`])
+static MEASUREMENT: std::sync::OnceLock<[u8; 32]> = std::sync::OnceLock::new();
+
+/// Sign `sha256`, the running firmware image's SHA-256, into every later
+/// payload; only the first call counts
+///
+/// A verifier holding the hashes of published builds learns which one
+/// produced a signature. Every device running that build signs the same
+/// measurement, so it identifies the firmware and not the device. The
+/// firmware measures its image at startup under the `measurement` feature;
+/// without it, payloads carry none and stay 32 bytes shorter.
+///
+/// The image measures itself, so modified firmware can sign any hash it
+/// likes; only secure boot, which refuses unsigned images, makes the value
+/// trustworthy.
+pub fn set_measurement(sha256: [u8; 32]) {
+    let _ = MEASUREMENT.set(sha256);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::TOKEN_LEN;
+    use crate::boot::ResetReason;
+    use crate::context::MAX_CONTEXT_LEN;
+    use crate::hal::mock::{MockNoise, MockTimer};
+    use crate::wallclock::TimeSource;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_fixed_line_layout() {
+        let attestation = Attestation {
+            version: 1,
+            event: AttestationEvent::ButtonPress { gpio: 0 },
+            timestamp_ms: 1234,
+            counter: 7,
+            wall_clock: None,
+            policy: Policy::COARSE_TIME,
+            suppressed: 3,
+            challenge: None,
+            token: None,
+            boot_nonce: None,
+            algorithm: Algorithm::Ed25519,
+            context: None,
+            measurement: None,
+            public_key: [0xab; 32],
+            signature: [0xcd; 64],
+            companions: Default::default(),
+        };
+        let line = attestation.fixed_line();
+        let fields: Vec<&str> = line.trim_end().split(' ').collect();
+        assert_eq!(
+            fields[..14],
+            ["ATT", "1", "7", "1234", "-", "1", "3", "-", "-", "-", "0", "-", "-", "0000"]
+        );
+        assert_eq!(fields[14], attestation.public_key_hex().as_str());
+        assert_eq!(fields[15], attestation.signature_hex().as_str());
+        assert!(line.ends_with('\n'));
+
+        let attestation = Attestation {
+            wall_clock: Some(WallTime {
+                unix_s: 1_709_251_140,
+                source: TimeSource::Gps,
+                stale: false,
+            }),
+            challenge: Some([0x0f; 32]),
+            token: Some(TokenProof {
+                nonce: [0x01; 16],
+                tag: [0x02; 16],
+            }),
+            boot_nonce: Some([0x03; 16]),
+            algorithm: Algorithm::P256,
+            context: Some(Context::from_slice(&[0x04; 3]).unwrap()),
+            measurement: Some([0x05; 32]),
+            ..attestation
+        };
+        let line = attestation.fixed_line();
+        assert_eq!(line.split(' ').nth(4), Some("1709251140:0:0"));
+        assert_eq!(line.split(' ').nth(7), Some(&*"0f".repeat(32)));
+        let token = "01".repeat(16) + &"02".repeat(16);
+        assert_eq!(line.split(' ').nth(8), Some(&*token));
+        assert_eq!(line.split(' ').nth(9), Some(&*"03".repeat(16)));
+        assert_eq!(line.split(' ').nth(10), Some("1"));
+        assert_eq!(line.split(' ').nth(11), Some("040404"));
+        assert_eq!(line.split(' ').nth(12), Some(&*"05".repeat(32)));
+    }
+
+    #[test]
+    fn test_fixed_line_round_trips() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let timer = MockTimer::at(1_000);
+        let event = AttestationEvent::DataDigest {
+            gpio: 0,
+            sha256: [0xFF; 32],
+            len: u64::MAX,
+        };
+        let token = Token::parse(&[0x24; TOKEN_LEN]);
+        let context = Context::from_slice(&[0xFF; MAX_CONTEXT_LEN]).ok();
+        let attestation =
+            Attestation::create_authorized(&rng, &timer, event, Some([0x42; 32]), token, context)
+                .unwrap();
+        let record = AttestationRecord::from(&attestation);
+        let parsed = AttestationRecord::from_fixed_line(&attestation.fixed_line()).unwrap();
+        assert_eq!(parsed, record);
+        assert!(verify(&parsed));
+
+        // The longest line also carries a measurement
+        let longest = AttestationRecord {
+            measurement: Some([0xFF; 32]),
+            ..record.clone()
+        };
+        let parsed = AttestationRecord::from_fixed_line(&longest.fixed_line());
+        assert_eq!(parsed, Some(longest));
+
+        let record = AttestationRecord {
+            wall_clock: Some(WallTime {
+                unix_s: 1_709_251_140,
+                source: TimeSource::Rtc,
+                stale: true,
+            }),
+            challenge: None,
+            token: None,
+            boot_nonce: None,
+            algorithm: Algorithm::P256,
+            context: None,
+            measurement: None,
+            ..record
+        };
+        let line = record.fixed_line();
+        assert_eq!(AttestationRecord::from_fixed_line(&line), Some(record));
+
+        // Truncated, mislabelled and overlong lines are refused
+        let parse = AttestationRecord::from_fixed_line;
+        assert_eq!(parse(line.trim_end_matches(|c| c != ' ')), None);
+        assert_eq!(parse(&line.replacen("ATT", "ATX", 1)), None);
+        assert_eq!(parse(&format!("{} 00", line.trim_end())), None);
+    }
+
+    #[test]
+    fn test_compact_round_trips() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let timer = MockTimer::at(1_000);
+        let event = AttestationEvent::DataDigest {
+            gpio: 0,
+            sha256: [0xFF; 32],
+            len: u64::MAX,
+        };
+        let attestation =
+            Attestation::create_with_challenge(&rng, &timer, event, Some([0x42; 32])).unwrap();
+        let record = AttestationRecord::from(&attestation);
+
+        let compact = record.compact();
+        let (payload, signed_by) = compact.split_at(compact.len() - 96);
+        assert_eq!(payload, &attestation.payload_bytes()[..]);
+        assert_eq!(signed_by[..32], record.public_key);
+
+        let parse = AttestationRecord::from_compact;
+        assert_eq!(parse(&compact), Some(record.clone()));
+        assert_eq!(parse(&compact[1..]), None);
+        assert_eq!(parse(&compact[..compact.len() - 1]), None);
+
+        // QR alphanumeric characters only
+        let text = record.compact_text();
+        assert!(text.starts_with(COMPACT_TEXT_PREFIX));
+        let alphanumeric = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+        assert!(text.bytes().all(|c| alphanumeric.contains(&c)));
+        let parsed = AttestationRecord::from_compact_text(&text).unwrap();
+        assert_eq!(parsed, record);
+        assert!(verify(&parsed));
+
+        // Room for the largest compact form
+        assert!(COMPACT_TEXT_PREFIX.len() + MAX_COMPACT_LEN * 3 / 2 <= text.capacity());
+    }
+
+    #[test]
+    fn test_verify_record() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let timer = MockTimer::at(1_000);
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let attestation = Attestation::create(&rng, &timer, event).unwrap();
+
+        let mut record = AttestationRecord::from(&attestation);
+        assert!(verify(&record));
+        record.timestamp_ms += 1;
+        assert!(!verify(&record));
+        record.timestamp_ms -= 1;
+        record.policy = Policy::COARSE_TIME;
+        assert!(!verify(&record));
+        record.policy = attestation.policy();
+        record.suppressed += 1;
+        assert!(!verify(&record));
+        record.suppressed -= 1;
+        record.challenge = Some([0; 32]);
+        assert!(!verify(&record));
+        record.challenge = None;
+        record.token = Some(TokenProof::from_bytes(&[0; 32]));
+        assert!(!verify(&record));
+        record.token = None;
+        record.boot_nonce = None;
+        assert!(!verify(&record));
+        record.boot_nonce = attestation.boot_nonce();
+        record.algorithm = Algorithm::P256;
+        assert!(!verify(&record));
+        record.algorithm = attestation.algorithm();
+        record.context = Context::from_slice(&[]).ok();
+        assert!(!verify(&record));
+        record.context = None;
+        record.measurement = Some([0; 32]);
+        assert!(!verify(&record));
+    }
+
+    #[test]
+    fn test_signature_is_domain_separated() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let attestation = Attestation::create(&rng, &MockTimer::at(1_000), event).unwrap();
+        let (public_key, signature) = (
+            attestation.public_key_bytes(),
+            attestation.signature_bytes(),
+        );
+
+        let payload = attestation.payload_bytes();
+        let message = signed_message(&payload);
+        assert_eq!(message[..SIGNING_DOMAIN.len()], *SIGNING_DOMAIN);
+        assert!(scheme::Active::verify(public_key, &message, signature));
+        assert!(!scheme::Active::verify(public_key, &payload, signature));
+    }
+
+    #[test]
+    fn test_challenge_is_signed() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let timer = MockTimer::at(1_000);
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let attestation =
+            Attestation::create_with_challenge(&rng, &timer, event, Some([0x42; 32])).unwrap();
+        assert_eq!(attestation.challenge(), Some([0x42; 32]));
+
+        let mut record = AttestationRecord::from(&attestation);
+        assert!(verify(&record));
+        record.challenge = Some([0x43; 32]);
+        assert!(!verify(&record));
+        record.challenge = None;
+        assert!(!verify(&record));
+    }
+
+    #[test]
+    fn test_context_is_signed() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let timer = MockTimer::at(1_000);
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let context = Context::from_slice(b"approve #42").ok();
+        let attestation =
+            Attestation::create_authorized(&rng, &timer, event, None, None, context.clone())
+                .unwrap();
+        assert_eq!(attestation.context(), context.as_ref());
+
+        let mut record = AttestationRecord::from(&attestation);
+        assert!(verify(&record));
+        record.context = Context::from_slice(b"approve #43").ok();
+        assert!(!verify(&record));
+        record.context = None;
+        assert!(!verify(&record));
+    }
+
+    #[test]
+    fn test_token_proof_is_signed_and_bound() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let timer = MockTimer::at(1_000);
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let token = Token::parse(&[[0x11; 16], [0x22; 16], [0x22; 16]].concat());
+        let attestation =
+            Attestation::create_authorized(&rng, &timer, event, None, token, None).unwrap();
+        let proof = attestation.token().unwrap();
+        assert_eq!(proof.nonce, [0x11; 16]);
+        assert!(proof.verify(&[0x22; 32], attestation.public_key_bytes()));
+
+        let mut record = AttestationRecord::from(&attestation);
+        assert!(verify(&record));
+        record.token = None;
+        assert!(!verify(&record));
+
+        // Without a token the field is simply absent
+        let attestation = Attestation::create(&rng, &timer, event).unwrap();
+        assert_eq!(attestation.token(), None);
+    }
+
+    #[cfg(feature = "p256")]
+    #[test]
+    fn test_p256_record_verifies() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let timer = MockTimer::at(1_000);
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let attestation = Attestation::create(&rng, &timer, event).unwrap();
+
+        // Re-sign the record's payload under P-256, as a `sign-p256` build would
+        let key = scheme::P256::from_seed(&[0x5a; 32]).unwrap();
+        let mut record = AttestationRecord {
+            algorithm: Algorithm::P256,
+            public_key: scheme::P256::public_key(&key),
+            ..AttestationRecord::from(&attestation)
+        };
+        let compact = record.compact();
+        let payload = &compact[..compact.len() - 96];
+        record.signature = scheme::P256::sign(&key, &signed_message(payload));
+        assert!(verify(&record));
+        assert!(record.fixed_line().contains(" 1 "));
+
+        record.algorithm = Algorithm::Ed25519;
+        assert!(!verify(&record));
+    }
+
+    #[test]
+    fn test_boot_nonce_is_shared_within_a_boot() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let timer = MockTimer::at(1_000);
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let first = Attestation::create(&rng, &timer, event).unwrap();
+        let second = Attestation::create(&rng, &timer, event).unwrap();
+        assert_eq!(first.boot_nonce(), Some(boot_nonce(&rng)));
+        assert_eq!(second.boot_nonce(), first.boot_nonce());
+        assert_ne!(second.counter(), first.counter());
+    }
+
+    #[test]
+    fn test_created_on_another_task() {
+        use std::sync::mpsc;
+
+        let (done, results) = mpsc::sync_channel(1);
+        let signer = std::thread::spawn(move || {
+            let rng = HardwareRng::from_source(MockNoise::new(0x3c)).unwrap();
+            let event = AttestationEvent::ButtonPress { gpio: 3 };
+            done.send(Attestation::create(&rng, &MockTimer::at(1_000), event))
+                .unwrap();
+        });
+        signer.join().unwrap();
+
+        let attestation = results.recv().unwrap().unwrap();
+        assert!(verify(&AttestationRecord::from(&attestation)));
+    }
+
+    #[test]
+    fn test_busy_when_every_workspace_slot_is_held() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let created = with_seed_workspace(|_| {
+            with_seed_workspace(|_| Attestation::create(&rng, &MockTimer::at(1_000), event))
+        });
+        let refused = created.flatten().and_then(|created| created.err());
+        assert_eq!(refused, Some(IceSickleError::Busy));
+        assert!(Attestation::create(&rng, &MockTimer::at(1_000), event).is_ok());
+    }
+
+    fn any_event() -> impl Strategy<Value = AttestationEvent> {
+        prop_oneof![
+            any::<u8>().prop_map(|gpio| AttestationEvent::ButtonPress { gpio }),
+            Just(AttestationEvent::Unknown),
+            (any::<u8>(), any::<[u8; 32]>(), any::<u64>())
+                .prop_map(|(gpio, sha256, len)| AttestationEvent::DataDigest { gpio, sha256, len }),
+            Just(AttestationEvent::Tamper),
+            any::<u8>().prop_map(|gpio| AttestationEvent::Presence { gpio }),
+            (any::<[u8; 32]>(), any::<u32>())
+                .prop_map(|(root, count)| AttestationEvent::WindowDigest { root, count }),
+            any::<[u8; 32]>().prop_map(|peer| AttestationEvent::Witness { peer }),
+            any::<u16>().prop_map(|count| AttestationEvent::CreditPulse { count }),
+            any::<[u8; 32]>().prop_map(|hash| AttestationEvent::KeypadEntry { hash }),
+            (any::<u8>(), any::<u8>(), any::<i32>()).prop_map(|(sensor, channel, value)| {
+                AttestationEvent::Sensor {
+                    sensor,
+                    channel,
+                    value,
+                }
+            }),
+            (any_reset_reason(), any::<[u8; 32]>()).prop_map(|(reset_reason, fw_hash)| {
+                AttestationEvent::Boot {
+                    reset_reason,
+                    fw_hash,
+                }
+            }),
+            (any::<i32>(), any::<u16>(), any::<u32>()).prop_map(
+                |(count, epsilon_milli, window_s)| AttestationEvent::UsageCount {
+                    count,
+                    epsilon_milli,
+                    window_s,
+                }
+            ),
+            (any::<u8>(), any::<u64>(), any::<u64>()).prop_map(|(gpio, mask, levels)| {
+                AttestationEvent::GpioSnapshot { gpio, mask, levels }
+            }),
+            any::<u8>().prop_map(|channel| AttestationEvent::TouchPad { channel }),
+            (any::<u8>(), any::<u32>())
+                .prop_map(|(gpio, duration_ms)| AttestationEvent::ButtonHold { gpio, duration_ms }),
+            any::<u8>().prop_map(|gpio| AttestationEvent::ButtonDoublePress { gpio }),
+            (any::<[u8; 32]>(), any::<u16>())
+                .prop_map(|(root, count)| AttestationEvent::BatchRoot { root, count }),
+            any::<u8>().prop_map(|gpio| AttestationEvent::TamperDetected { gpio }),
+            (any::<u8>(), any::<u8>(), any::<u32>()).prop_map(|(gpio_a, gpio_b, skew_ms)| {
+                AttestationEvent::DualPress {
+                    gpio_a,
+                    gpio_b,
+                    skew_ms,
+                }
+            }),
+        ]
+    }
+
+    fn any_reset_reason() -> impl Strategy<Value = ResetReason> {
+        prop_oneof![
+            Just(ResetReason::Unknown),
+            Just(ResetReason::PowerOn),
+            Just(ResetReason::External),
+            Just(ResetReason::Software),
+            Just(ResetReason::Panic),
+            Just(ResetReason::Watchdog),
+            Just(ResetReason::DeepSleep),
+            Just(ResetReason::Brownout),
+        ]
+    }
+
+    fn any_wall_clock() -> impl Strategy<Value = Option<WallTime>> {
+        let source = prop_oneof![Just(TimeSource::Gps), Just(TimeSource::Rtc)];
+        proptest::option::of((any::<u64>(), source, any::<bool>()).prop_map(
+            |(unix_s, source, stale)| WallTime {
+                unix_s,
+                source,
+                stale,
+            },
+        ))
+    }
+
+    fn any_payload() -> impl Strategy<Value = AttestationPayload> {
+        (
+            any::<u8>(),
+            any_event(),
+            any::<u64>(),
+            any::<u32>(),
+            any_wall_clock(),
+            any::<u8>().prop_map(Policy::from_bits),
+            any::<u32>(),
+            proptest::option::of(any::<[u8; 32]>()),
+            proptest::option::of(any::<[u8; 32]>().prop_map(|b| TokenProof::from_bytes(&b))),
+            proptest::option::of(any::<[u8; 16]>()),
+            prop_oneof![Just(Algorithm::Ed25519), Just(Algorithm::P256)],
+            (
+                proptest::option::of(
+                    proptest::collection::vec(any::<u8>(), 0..=MAX_CONTEXT_LEN)
+                        .prop_map(|b| Context::from_slice(&b).unwrap()),
+                ),
+                proptest::option::of(any::<[u8; 32]>()),
+            ),
+        )
+            .prop_map(
+                |(
+                    version,
+                    event,
+                    timestamp_ms,
+                    counter,
+                    wall_clock,
+                    policy,
+                    suppressed,
+                    challenge,
+                    token,
+                    boot_nonce,
+                    algorithm,
+                    (context, measurement),
+                )| {
+                    AttestationPayload {
+                        version,
+                        event,
+                        timestamp_ms,
+                        counter,
+                        wall_clock,
+                        policy,
+                        suppressed,
+                        challenge,
+                        token,
+                        boot_nonce,
+                        algorithm,
+                        context,
+                        measurement,
+                    }
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn payload_fits_signing_buffer(payload in any_payload()) {
+            let mut buf = [0u8; MAX_PAYLOAD_LEN];
+            prop_assert!(postcard::to_slice(&payload, &mut buf).is_ok());
+        }
+
+        #[test]
+        fn payload_round_trips(payload in any_payload()) {
+            let bytes = postcard::to_allocvec(&payload).unwrap();
+            let decoded: AttestationPayload = postcard::from_bytes(&bytes).unwrap();
+            prop_assert_eq!(decoded, payload);
+        }
+
+        #[test]
+        fn signature_verifies_over_reencoded_payload(
+            payload in any_payload(),
+            seed in any::<[u8; 32]>(),
+        ) {
+            let bytes = postcard::to_allocvec(&payload).unwrap();
+            let signing_key = scheme::Ed25519::from_seed(&seed).unwrap();
+            let signature = scheme::Ed25519::sign(&signing_key, &signed_message(&bytes));
+            let public_key = scheme::Ed25519::public_key(&signing_key);
+
+            // A verifier only ever sees the decoded fields; re-encoding them
+            // must reproduce exactly the bytes that were signed.
+            let decoded: AttestationPayload = postcard::from_bytes(&bytes).unwrap();
+            let reencoded = postcard::to_allocvec(&decoded).unwrap();
+            prop_assert_eq!(&reencoded, &bytes);
+            prop_assert!(scheme::Ed25519::verify(
+                &public_key,
+                &signed_message(&reencoded),
+                &signature
+            ));
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Authorization primitives
+//!
+//! # Philosophy
+//!
+//! Authorization in IceSickle is **capability-based**, not **identity-based**.
+//!
+//! This means:
+//! - No persistent device IDs
+//! - No linkable credentials across attestations
+//! - No "who are you?" — only "what can you do?"
+//!
+//! # Unlinkable one-time tokens
+//!
+//! A verifier that wants to accept only authorized attestations issues
+//! tokens; the device spends one per button press and signs a proof of it
+//! into the payload (`token`, payload version 7).
+//!
+//! 1. The host picks a random 16-byte nonce, blinds it and has the issuer
+//!    evaluate its VOPRF (RFC 9497, ristretto255-SHA512) on the blinded
+//!    value. Unblinded, the output truncated to 32 bytes is the token key.
+//!    The issuer never sees the nonce, so it cannot tell later which
+//!    issuance a token came from.
+//! 2. The host sends each [`Token`] (nonce ‖ key) with `LoadToken` inside
+//!    an encrypted session. The device keeps up to [`MAX_TOKENS`] in a
+//!    [`TokenJar`], in RAM only: a reset, a tamper wipe or the jar's drop
+//!    zeroizes them.
+//! 3. Each press takes one token and signs a [`TokenProof`]: the nonce and
+//!    a tag keyed by the token key over the attestation's ephemeral public
+//!    key. The token is zeroized as soon as the tag is computed.
+//! 4. The verifier asks the issuer to redeem the proof. Holding the VOPRF
+//!    secret, the issuer recomputes the key from the nonce, checks the tag
+//!    ([`TokenProof::verify`]) and refuses a nonce it has redeemed before.
+//!
+//! A proof shows that whoever signed the attestation held a token the
+//! issuer handed out, and nothing about which device that was: tokens are
+//! independent of each other and of the device, and a press after the jar
+//! runs dry is signed without one. The tag binds the ephemeral key, so a
+//! proof copied into another payload does not verify.
+//!
+//! Blinding and unblinding happen on the host, which speaks to the issuer;
+//! the device only stores and spends tokens, so it carries no VOPRF code.
+//!
+//! # Anti-patterns (DO NOT IMPLEMENT)
+//!
+//! The following are explicitly out of scope and should not be added:
+//!
+//! - Device serial numbers or unique identifiers
+//! - Persistent keypairs for device authentication
+//! - Certificates or PKI chains that link attestations
+//! - Any mechanism that allows correlating attestations to a single device
+//!
+//! If you need device identity, IceSickle is the wrong tool. Consider a
+//! traditional TPM or secure enclave solution instead.
+
+use core::mem;
+
+use icesickle_payload::auth::tag;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+pub use icesickle_payload::auth::TokenProof;
+
+/// A token on the wire: nonce then key
+pub const TOKEN_LEN: usize = 16 + 32;
+
+/// Most tokens held at once
+pub const MAX_TOKENS: usize = 32;
+
+/// An unspent authorization token; zeroized on drop
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Token {
+    nonce: [u8; 16],
+    key: [u8; 32],
+}
+
+impl Token {
+    /// Zeroes, for empty jar slots
+    const EMPTY: Self = Self {
+        nonce: [0; 16],
+        key: [0; 32],
+    };
+
+    /// Parse a token as `LoadToken` carries it; `None` unless exactly
+    /// [`TOKEN_LEN`] bytes
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != TOKEN_LEN {
+            return None;
+        }
+        let (nonce, key) = bytes.split_at(16);
+        Some(Self {
+            nonce: nonce.try_into().ok()?,
+            key: key.try_into().ok()?,
+        })
+    }
+
+    /// Spend the token on the attestation signed by `public_key`
+    pub fn prove(self, public_key: &[u8; 32]) -> TokenProof {
+        TokenProof {
+            nonce: self.nonce,
+            tag: tag(&self.key, &self.nonce, public_key),
+        }
+    }
+}
+
+/// Tokens loaded and not yet spent, in RAM only
+///
+/// Spent and dropped slots are overwritten with zeroes; the rest zeroize
+/// when the jar drops.
+pub struct TokenJar {
+    tokens: [Token; MAX_TOKENS],
+    len: usize,
+}
+
+impl TokenJar {
+    pub const fn new() -> Self {
+        Self {
+            tokens: [Token::EMPTY; MAX_TOKENS],
+            len: 0,
+        }
+    }
+
+    /// Keep `token` for a later press; false if the jar is full
+    pub fn load(&mut self, token: Token) -> bool {
+        let Some(slot) = self.tokens.get_mut(self.len) else {
+            return false;
+        };
+        *slot = token;
+        self.len += 1;
+        true
+    }
+
+    /// Take a token to spend, the most recently loaded first
+    pub fn take(&mut self) -> Option<Token> {
+        self.len = self.len.checked_sub(1)?;
+        Some(mem::replace(&mut self.tokens[self.len], Token::EMPTY))
+    }
+
+    /// Tokens left to spend
+    pub fn remaining(&self) -> usize {
+        self.len
+    }
+
+    /// Zeroize every token
+    pub fn clear(&mut self) {
+        self.tokens.iter_mut().for_each(Zeroize::zeroize);
+        self.len = 0;
+    }
+}
+
+impl Default for TokenJar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(byte: u8) -> Token {
+        Token::parse(&[byte; TOKEN_LEN]).unwrap()
+    }
+
+    #[test]
+    fn test_proof_binds_key_and_public_key() {
+        let proof = token(1).prove(&[0xAA; 32]);
+        assert_eq!(proof.nonce, [1; 16]);
+        assert!(proof.verify(&[1; 32], &[0xAA; 32]));
+        assert!(!proof.verify(&[2; 32], &[0xAA; 32]));
+        assert!(!proof.verify(&[1; 32], &[0xAB; 32]));
+
+        let moved = TokenProof {
+            nonce: [2; 16],
+            ..proof
+        };
+        assert!(!moved.verify(&[1; 32], &[0xAA; 32]));
+        assert_eq!(TokenProof::from_bytes(&proof.to_bytes()), proof);
+    }
+
+    #[test]
+    fn test_parse_refuses_wrong_lengths() {
+        assert!(Token::parse(&[0; TOKEN_LEN - 1]).is_none());
+        assert!(Token::parse(&[0; TOKEN_LEN + 1]).is_none());
+    }
+
+    #[test]
+    fn test_jar_spends_each_token_once() {
+        let mut jar = TokenJar::new();
+        assert!(jar.take().is_none());
+        for byte in 0..MAX_TOKENS as u8 {
+            assert!(jar.load(token(byte)));
+        }
+        assert!(!jar.load(token(0xFF)));
+        assert_eq!(jar.remaining(), MAX_TOKENS);
+
+        let last = jar.take().unwrap();
+        assert_eq!(last.nonce, [MAX_TOKENS as u8 - 1; 16]);
+        assert!(jar.tokens[MAX_TOKENS - 1].key.iter().all(|&b| b == 0));
+        assert_eq!(jar.remaining(), MAX_TOKENS - 1);
+
+        jar.clear();
+        assert_eq!(jar.remaining(), 0);
+        assert!(jar.take().is_none());
+        let zeroed = |t: &Token| t.nonce == [0; 16] && t.key == [0; 32];
+        assert!(jar.tokens.iter().all(zeroed));
+    }
+}
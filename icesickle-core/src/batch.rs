@@ -0,0 +1,166 @@
+//! Batch attestations: one signature over many presses
+//!
+//! A verifier that wants proof of N distinct presses does not need N keys
+//! and N signatures. With the firmware's `batch` feature a short press is
+//! not signed on its own: its [`Press`] is hashed into a leaf and kept in
+//! RAM, up to [`MAX_PRESSES`]. A long press, or a host's `SignBatch`
+//! request, closes the batch with one `BatchRoot { root, count }`
+//! attestation over the Merkle root of the leaves.
+//!
+//! The tree is the one `merkle` builds for window digests (RFC 6962), with
+//! each leaf:
+//!
+//! - leaf = SHA-256(0x00 || gpio || uptime_ms as u64 LE)
+//!
+//! The firmware outputs each press and its leaf as it joins the batch, and
+//! the leaves again with the batch's attestation, so a collector can
+//! rebuild the root. The signature covers the root only: a leaf proves a
+//! press only through a root that was signed.
+//!
+//! Presses of a batch are as linkable as a window digest's attestations,
+//! and the batch is lost on reset.
+
+use sha2::{Digest, Sha256};
+
+use crate::attestation::AttestationEvent;
+use crate::merkle::Accumulator;
+
+/// Presses a batch holds
+pub const MAX_PRESSES: usize = 32;
+
+/// Domain separation prefix for leaves (RFC 6962)
+const LEAF_PREFIX: u8 = 0x00;
+
+/// A press in a batch: what its leaf is hashed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Press {
+    pub gpio: u8,
+    /// Milliseconds since boot when the press was recognized
+    pub uptime_ms: u64,
+}
+
+impl Press {
+    /// The press's leaf hash
+    pub fn leaf(&self) -> [u8; 32] {
+        Sha256::new()
+            .chain_update([LEAF_PREFIX, self.gpio])
+            .chain_update(self.uptime_ms.to_le_bytes())
+            .finalize()
+            .into()
+    }
+}
+
+/// Presses awaiting a batch attestation
+#[derive(Debug, Default)]
+pub struct Batch {
+    presses: heapless::Vec<Press, MAX_PRESSES>,
+    close_requested: bool,
+}
+
+impl Batch {
+    pub const fn new() -> Self {
+        Self {
+            presses: heapless::Vec::new(),
+            close_requested: false,
+        }
+    }
+
+    /// Add a press; false if the batch is full
+    pub fn add(&mut self, press: Press) -> bool {
+        self.presses.push(press).is_ok()
+    }
+
+    /// Presses in the batch, oldest first
+    pub fn presses(&self) -> &[Press] {
+        &self.presses
+    }
+
+    /// Ask for the batch to be signed; false if there is nothing to sign
+    pub fn request_close(&mut self) -> bool {
+        self.close_requested = !self.presses.is_empty();
+        self.close_requested
+    }
+
+    /// A close was requested and has not been taken
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    /// The event to sign for the batch so far
+    pub fn event(&self) -> AttestationEvent {
+        let mut acc = Accumulator::new();
+        for press in &self.presses {
+            acc.push(press.leaf());
+        }
+        AttestationEvent::BatchRoot {
+            root: acc.root(),
+            count: self.presses.len() as u16,
+        }
+    }
+
+    /// Start a new batch
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle;
+
+    #[test]
+    fn test_root_matches_merkle_tree() {
+        let mut batch = Batch::new();
+        assert!(!batch.request_close());
+        let presses = [
+            Press {
+                gpio: 0,
+                uptime_ms: 1_000,
+            },
+            Press {
+                gpio: 0,
+                uptime_ms: 2_500,
+            },
+            Press {
+                gpio: 3,
+                uptime_ms: 2_600,
+            },
+        ];
+        for press in presses {
+            assert!(batch.add(press));
+        }
+
+        let mut acc = merkle::Accumulator::new();
+        for press in &presses {
+            acc.push(press.leaf());
+        }
+        assert_eq!(
+            batch.event(),
+            AttestationEvent::BatchRoot {
+                root: acc.root(),
+                count: 3
+            }
+        );
+        assert_ne!(presses[0].leaf(), presses[1].leaf());
+
+        assert!(batch.request_close());
+        assert!(batch.close_requested());
+        batch.clear();
+        assert!(batch.presses().is_empty());
+        assert!(!batch.close_requested());
+    }
+
+    #[test]
+    fn test_full_batch_refuses_presses() {
+        let mut batch = Batch::new();
+        for uptime_ms in 0..MAX_PRESSES as u64 {
+            assert!(batch.add(Press { gpio: 0, uptime_ms }));
+        }
+        assert!(!batch.add(Press {
+            gpio: 0,
+            uptime_ms: 99_999
+        }));
+        assert_eq!(batch.presses().len(), MAX_PRESSES);
+    }
+}
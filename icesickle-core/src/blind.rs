@@ -0,0 +1,58 @@
+//! Randomized delay blinding
+//!
+//! Key generation and signing start a fixed time after the button press, so
+//! someone with a probe near the device can line up power/EM traces of the
+//! secret-dependent operations across many attestations. [`jitter`] runs
+//! before and after each of them and shifts everything that follows by a
+//! random amount, so the traces no longer align without realignment work.
+//!
+//! This is a cheap mitigation aimed at an attacker with brief physical
+//! proximity, not a countermeasure against a lab. The delay is a busy loop
+//! rather than a timer sleep: it has to be far shorter than a tick, and an
+//! idle CPU would mark where it ends.
+//!
+//! The spin counts are drawn from the hardware RNG. They are public timing
+//! noise, not secrets.
+
+use core::hint::black_box;
+
+use crate::entropy::HardwareRng;
+use crate::hal::EntropySource;
+
+/// Spin counts are uniform in `0..=SPIN_MASK` (about 0-50 µs at 240 MHz)
+pub const SPIN_MASK: u16 = 0x0fff;
+
+/// Busy-wait for a random number of iterations; returns the count
+pub fn jitter<S: EntropySource>(rng: &HardwareRng<S>) -> u32 {
+    let mut bytes = [0u8; 2];
+    rng.fill_bytes(&mut bytes);
+    let spins = spins(bytes);
+    spin(spins);
+    spins
+}
+
+fn spins(bytes: [u8; 2]) -> u32 {
+    (u16::from_le_bytes(bytes) & SPIN_MASK) as u32
+}
+
+#[inline(never)]
+fn spin(n: u32) {
+    let mut acc = 0u32;
+    for i in 0..n {
+        // black_box keeps the loop from being folded away
+        acc = black_box(acc.wrapping_add(i));
+    }
+    black_box(acc);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spins_bounded() {
+        assert_eq!(spins([0, 0]), 0);
+        assert_eq!(spins([0xff, 0xff]), SPIN_MASK as u32);
+        assert_eq!(spins([0x34, 0x12]), 0x0234);
+    }
+}
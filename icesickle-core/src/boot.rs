@@ -0,0 +1,61 @@
+//! Boot attestations
+//!
+//! With firmware feature `boot-attestation`, the device signs one
+//! `Boot { reset_reason, fw_hash }` attestation [`BOOT_DELAY_MS`] after
+//! startup, so a collector sees every restart and which firmware came up.
+//! The delay lets a time reference anchor first; the event is signed once
+//! per boot, with a fresh key like any other, and the firmware hash is the
+//! same for every device running that image, so it identifies the build,
+//! not the device.
+
+pub use icesickle_payload::boot::ResetReason;
+
+use crate::attestation::AttestationEvent;
+
+/// Time after startup at which the boot attestation is due
+pub const BOOT_DELAY_MS: u64 = 2_000;
+
+/// The boot event, waiting until it is due
+#[derive(Debug)]
+pub struct BootReport {
+    event: Option<AttestationEvent>,
+}
+
+impl BootReport {
+    /// `fw_hash` is the SHA-256 of the running firmware image
+    pub fn new(reset_reason: ResetReason, fw_hash: [u8; 32]) -> Self {
+        Self {
+            event: Some(AttestationEvent::Boot {
+                reset_reason,
+                fw_hash,
+            }),
+        }
+    }
+
+    /// The boot event once [`BOOT_DELAY_MS`] has passed, exactly once
+    pub fn due(&mut self, now_ms: u64) -> Option<AttestationEvent> {
+        if now_ms < BOOT_DELAY_MS {
+            return None;
+        }
+        self.event.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_event_due_once() {
+        let mut report = BootReport::new(ResetReason::Watchdog, [0x42; 32]);
+        assert_eq!(report.due(BOOT_DELAY_MS - 1), None);
+        assert_eq!(
+            report.due(BOOT_DELAY_MS),
+            Some(AttestationEvent::Boot {
+                reset_reason: ResetReason::Watchdog,
+                fw_hash: [0x42; 32],
+            })
+        );
+        assert_eq!(report.due(BOOT_DELAY_MS + 1), None);
+    }
+}
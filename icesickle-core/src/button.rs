@@ -0,0 +1,265 @@
+//! Button debouncing, independent of the board
+//!
+//! [`Button`] turns the raw level of an active-low input into one report
+//! per press, ignoring contact bounce inside [`DEBOUNCE_MS`]. [`Buttons`]
+//! polls several, each press reported with the GPIO it came from. Both
+//! reach the hardware only through [`hal::InputPin`] and [`Timer`], so a
+//! port supplies its pins and clock (the ESP32 ones are in the firmware's
+//! `hal.rs`; see [`hal::embedded`] for `embedded-hal` drivers) and keeps
+//! this logic unchanged.
+//!
+//! Buttons are polled rather than interrupt-driven, for simplicity and
+//! determinism.
+
+use crate::hal::{self, Timer};
+use crate::Result;
+
+/// Debounce time in milliseconds
+pub const DEBOUNCE_MS: u32 = 50;
+
+/// Button state machine
+pub struct Button<P, T> {
+    pin: P,
+    timer: T,
+    last_state: bool,
+    last_change_ms: u32,
+}
+
+impl<P, T> Button<P, T>
+where
+    P: hal::InputPin,
+    T: Timer,
+{
+    /// Create a button from an already-configured pin and time source
+    pub fn with_timer(pin: P, timer: T) -> Self {
+        Self {
+            pin,
+            timer,
+            last_state: false,
+            last_change_ms: 0,
+        }
+    }
+
+    /// Poll for a button press (returns true once per press, after debounce)
+    pub fn poll_pressed(&mut self) -> Result<bool> {
+        Ok(self.poll_edge()? == Some(true))
+    }
+
+    /// Poll for a change after debounce: `Some(true)` as the button goes
+    /// down, `Some(false)` as it comes back up
+    pub fn poll_edge(&mut self) -> Result<Option<bool>> {
+        let now = self.millis();
+        let current_raw = self.pin.is_low(); // Active low
+
+        // Debounce: only register state change after stable period
+        if current_raw != self.last_state && now.wrapping_sub(self.last_change_ms) >= DEBOUNCE_MS {
+            self.last_state = current_raw;
+            self.last_change_ms = now;
+            return Ok(Some(current_raw));
+        }
+
+        Ok(None)
+    }
+
+    /// Block until the button is released (with debounce)
+    pub fn wait_release(&mut self) -> Result<()> {
+        // Wait for raw release
+        while self.pin.is_low() {
+            self.timer.delay_ms(10);
+        }
+
+        // Debounce delay
+        self.timer.delay_ms(DEBOUNCE_MS);
+
+        // Update state
+        self.last_state = false;
+        self.last_change_ms = self.millis();
+
+        Ok(())
+    }
+
+    /// Check if button is currently pressed (raw, no debounce)
+    pub fn is_pressed(&self) -> bool {
+        self.pin.is_low()
+    }
+
+    /// Get current time in milliseconds (wraps at u32::MAX)
+    fn millis(&self) -> u32 {
+        self.timer.now_ms() as u32
+    }
+}
+
+/// Every configured button, polled together
+pub struct Buttons<P, T> {
+    buttons: Vec<(u8, Button<P, T>)>,
+}
+
+impl<P, T> Buttons<P, T>
+where
+    P: hal::InputPin,
+    T: Timer,
+{
+    /// Watch already-built buttons, each with its GPIO
+    pub fn with_buttons(buttons: Vec<(u8, Button<P, T>)>) -> Self {
+        Self { buttons }
+    }
+
+    /// GPIO of every button, in config order
+    pub fn gpios(&self) -> impl Iterator<Item = u8> + '_ {
+        self.buttons.iter().map(|(gpio, _)| *gpio)
+    }
+
+    /// Poll every button; the GPIO of a new press, the first in config
+    /// order if several. The others are reported on later polls
+    pub fn poll_pressed(&mut self) -> Result<Option<u8>> {
+        for (gpio, button) in &mut self.buttons {
+            if button.poll_pressed()? {
+                return Ok(Some(*gpio));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Poll every button; the GPIO and direction (true = down) of a
+    /// change after debounce, the first in config order if several
+    pub fn poll_edge(&mut self) -> Result<Option<(u8, bool)>> {
+        for (gpio, button) in &mut self.buttons {
+            if let Some(down) = button.poll_edge()? {
+                return Ok(Some((*gpio, down)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Block until the button on `gpio` is released (with debounce)
+    pub fn wait_release(&mut self, gpio: u8) -> Result<()> {
+        match self.buttons.iter_mut().find(|(pin, _)| *pin == gpio) {
+            Some((_, button)) => button.wait_release(),
+            None => Ok(()),
+        }
+    }
+
+    /// True if the button on `gpio` is held down right now (raw, no
+    /// debounce)
+    pub fn is_held(&self, gpio: u8) -> bool {
+        self.buttons
+            .iter()
+            .any(|(pin, button)| *pin == gpio && button.is_pressed())
+    }
+
+    /// GPIO of a button held down right now (raw, no debounce), the first
+    /// in config order if several
+    pub fn held(&self) -> Option<u8> {
+        self.buttons
+            .iter()
+            .find(|(_, button)| button.is_pressed())
+            .map(|(gpio, _)| *gpio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{MockPin, MockTimer};
+
+    #[test]
+    fn test_press_reported_once() {
+        let pin = MockPin::default();
+        let timer = MockTimer::at(1_000);
+        let mut button = Button::with_timer(&pin, &timer);
+
+        pin.set_low(true);
+        assert!(button.poll_pressed().unwrap());
+        timer.advance(10);
+        assert!(!button.poll_pressed().unwrap());
+    }
+
+    #[test]
+    fn test_bounce_within_debounce_window_ignored() {
+        let pin = MockPin::default();
+        let timer = MockTimer::at(1_000);
+        let mut button = Button::with_timer(&pin, &timer);
+
+        pin.set_low(true);
+        assert!(button.poll_pressed().unwrap());
+
+        // Contact bounce: brief release and re-press inside the window
+        timer.advance(10);
+        pin.set_low(false);
+        assert!(!button.poll_pressed().unwrap());
+        timer.advance(10);
+        pin.set_low(true);
+        assert!(!button.poll_pressed().unwrap());
+    }
+
+    #[test]
+    fn test_second_press_after_release() {
+        let pin = MockPin::default();
+        let timer = MockTimer::at(1_000);
+        let mut button = Button::with_timer(&pin, &timer);
+
+        pin.set_low(true);
+        assert!(button.poll_pressed().unwrap());
+
+        pin.set_low(false);
+        button.wait_release().unwrap();
+        assert_eq!(timer.now_ms(), 1_000 + DEBOUNCE_MS as u64);
+
+        timer.advance(DEBOUNCE_MS as u64);
+        pin.set_low(true);
+        assert!(button.poll_pressed().unwrap());
+    }
+
+    #[test]
+    fn test_debounce_across_millis_wraparound() {
+        let pin = MockPin::default();
+        let timer = MockTimer::at(u64::from(u32::MAX) - 19);
+        let mut button = Button::with_timer(&pin, &timer);
+
+        pin.set_low(true);
+        assert!(button.poll_pressed().unwrap());
+
+        // `millis` has wrapped to 20: still inside the window
+        timer.advance(40);
+        pin.set_low(false);
+        assert!(!button.poll_pressed().unwrap());
+        assert!(button.last_state);
+
+        timer.advance(10);
+        assert!(!button.poll_pressed().unwrap());
+        assert!(!button.last_state);
+
+        timer.advance(DEBOUNCE_MS as u64);
+        pin.set_low(true);
+        assert!(button.poll_pressed().unwrap());
+    }
+
+    #[test]
+    fn test_each_button_reports_its_gpio() {
+        let (boot, external) = (MockPin::default(), MockPin::default());
+        let timer = MockTimer::at(1_000);
+        let mut buttons = Buttons::with_buttons(vec![
+            (0, Button::with_timer(&boot, &timer)),
+            (38, Button::with_timer(&external, &timer)),
+        ]);
+        assert_eq!(buttons.gpios().collect::<Vec<_>>(), [0, 38]);
+        assert_eq!(buttons.poll_pressed().unwrap(), None);
+
+        external.set_low(true);
+        assert_eq!(buttons.poll_pressed().unwrap(), Some(38));
+        assert_eq!(buttons.held(), Some(38));
+
+        // Held through the next press, which is still reported on its own
+        timer.advance(10);
+        boot.set_low(true);
+        assert_eq!(buttons.poll_pressed().unwrap(), Some(0));
+        assert_eq!(buttons.poll_pressed().unwrap(), None);
+        assert_eq!(buttons.held(), Some(0));
+        assert!(buttons.is_held(38));
+
+        boot.set_low(false);
+        assert!(!buttons.is_held(0));
+        buttons.wait_release(0).unwrap();
+        assert_eq!(buttons.held(), Some(38));
+    }
+}
@@ -0,0 +1,108 @@
+//! Deterministic CBOR and the COSE pieces shared by `cwt` and `cose`
+//!
+//! Just enough of RFC 8949 to write the few structures IceSickle signs:
+//! item heads in their shortest form, byte and text strings, and the
+//! EdDSA `COSE_Sign1` building blocks of RFC 9052. Callers write map keys
+//! in byte order themselves, so the output is core deterministic encoding
+//! and a verifier that re-encodes gets the signed bytes back.
+
+use crate::companion::Message;
+
+pub const MAJOR_UINT: u8 = 0;
+pub const MAJOR_NINT: u8 = 1;
+pub const MAJOR_BYTES: u8 = 2;
+pub const MAJOR_TEXT: u8 = 3;
+pub const MAJOR_ARRAY: u8 = 4;
+pub const MAJOR_MAP: u8 = 5;
+pub const MAJOR_TAG: u8 = 6;
+
+pub const FALSE: u8 = 0xF4;
+pub const TRUE: u8 = 0xF5;
+
+pub const TAG_COSE_SIGN1: u64 = 18;
+
+const KEY_KTY: u64 = 1;
+const KEY_OKP: u64 = 1;
+/// `crv` and `x` are labels -1 and -2, encoded as their CBOR argument
+const KEY_CRV_NINT: u64 = 0;
+const KEY_X_NINT: u64 = 1;
+const CRV_ED25519: u64 = 6;
+
+/// Protected header: `{1 (alg): -8 (EdDSA)}`
+pub const PROTECTED: [u8; 3] = [0xA1, 0x01, 0x27];
+
+/// Item head in its shortest form; capacities are sized so pushes succeed
+pub fn head<const N: usize>(out: &mut heapless::Vec<u8, N>, major: u8, n: u64) {
+    let (info, len) = match n {
+        0..=23 => (n as u8, 0),
+        24..=0xFF => (24, 1),
+        0x100..=0xFFFF => (25, 2),
+        0x1_0000..=0xFFFF_FFFF => (26, 4),
+        _ => (27, 8),
+    };
+    let _ = out.push((major << 5) | info);
+    let _ = out.extend_from_slice(&n.to_be_bytes()[8 - len..]);
+}
+
+pub fn bytes<const N: usize>(out: &mut heapless::Vec<u8, N>, b: &[u8]) {
+    head(out, MAJOR_BYTES, b.len() as u64);
+    let _ = out.extend_from_slice(b);
+}
+
+pub fn text<const N: usize>(out: &mut heapless::Vec<u8, N>, s: &str) {
+    head(out, MAJOR_TEXT, s.len() as u64);
+    let _ = out.extend_from_slice(s.as_bytes());
+}
+
+/// `public_key` as an OKP `COSE_Key`: `{1 (kty): 1, -1 (crv): 6, -2 (x)}`
+pub fn cose_key<const N: usize>(out: &mut heapless::Vec<u8, N>, public_key: &[u8; 32]) {
+    head(out, MAJOR_MAP, 3);
+    head(out, MAJOR_UINT, KEY_KTY);
+    head(out, MAJOR_UINT, KEY_OKP);
+    head(out, MAJOR_NINT, KEY_CRV_NINT);
+    head(out, MAJOR_UINT, CRV_ED25519);
+    head(out, MAJOR_NINT, KEY_X_NINT);
+    bytes(out, public_key);
+}
+
+/// The `Sig_structure` a `COSE_Sign1` with [`PROTECTED`] and no external
+/// data signs over `payload`
+///
+/// It starts with the text `Signature1`, so it cannot pass for a payload
+/// or another companion message.
+pub fn sig_structure(payload: &[u8]) -> Message {
+    let mut message = Message::new();
+    head(&mut message, MAJOR_ARRAY, 4);
+    text(&mut message, "Signature1");
+    bytes(&mut message, &PROTECTED);
+    bytes(&mut message, &[]);
+    bytes(&mut message, payload);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_head_shortest_form() {
+        let mut out = heapless::Vec::<u8, 16>::new();
+        head(&mut out, MAJOR_UINT, 23);
+        head(&mut out, MAJOR_UINT, 24);
+        head(&mut out, MAJOR_NINT, 0);
+        head(&mut out, MAJOR_UINT, 0x1_0000);
+        assert_eq!(out, [0x17, 0x18, 0x18, 0x20, 0x1A, 0x00, 0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_cose_key_and_sig_structure() {
+        let mut key = heapless::Vec::<u8, 64>::new();
+        cose_key(&mut key, &[0x11; 32]);
+        assert_eq!(key[..8], [0xA3, 0x01, 0x01, 0x20, 0x06, 0x21, 0x58, 0x20]);
+        assert_eq!(key[8..], [0x11; 32]);
+
+        let message = sig_structure(b"payload");
+        assert!(message.starts_with(b"\x84\x6ASignature1\x43\xA1\x01\x27\x40"));
+        assert!(message.ends_with(b"\x47payload"));
+    }
+}
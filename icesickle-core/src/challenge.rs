@@ -0,0 +1,77 @@
+//! Challenge-response attestations
+//!
+//! A signature over a timestamp and a counter shows when, by the device's
+//! own clock, a press happened; it does not show that the press happened
+//! for *this* verifier. A replayed attestation from yesterday verifies just
+//! as well. Challenge-response closes that gap:
+//!
+//! 1. The verifier sends `ArmChallenge` with 32 fresh random bytes, arming a
+//!    [`PressChallenge`] for [`CHALLENGE_WINDOW_MS`].
+//! 2. The operator presses the button within the window.
+//! 3. The device signs the challenge into that attestation's payload
+//!    (`challenge`, payload version 6) and drops it.
+//!
+//! A verifier that sees its own challenge under a valid signature knows the
+//! attestation was made after it sent the challenge. One challenge answers
+//! one press; a press made after the window closes is signed without one.
+//! Only button presses (plain, data digests and GPIO snapshots) take the
+//! challenge: device-initiated attestations never answer one.
+//!
+//! The keypad's `SetChallenge` is separate. That challenge salts the entry
+//! hash and is never signed in the clear.
+
+/// An armed challenge expires if no press comes within this time
+pub const CHALLENGE_WINDOW_MS: u64 = 60_000;
+
+/// Verifier challenge awaiting a button press
+#[derive(Debug, Default)]
+pub struct PressChallenge {
+    /// Challenge and when it was armed
+    armed: Option<([u8; 32], u64)>,
+}
+
+impl PressChallenge {
+    pub const fn new() -> Self {
+        Self { armed: None }
+    }
+
+    /// Arm `challenge`, replacing any earlier one
+    pub fn arm(&mut self, challenge: [u8; 32], now_ms: u64) {
+        self.armed = Some((challenge, now_ms));
+    }
+
+    /// True if a challenge is armed and its window is still open
+    pub fn is_armed(&self, now_ms: u64) -> bool {
+        self.armed.is_some_and(|(_, armed_at_ms)| {
+            now_ms.saturating_sub(armed_at_ms) < CHALLENGE_WINDOW_MS
+        })
+    }
+
+    /// Take the armed challenge, if its window is still open
+    pub fn take(&mut self, now_ms: u64) -> Option<[u8; 32]> {
+        let (challenge, armed_at_ms) = self.armed.take()?;
+        (now_ms.saturating_sub(armed_at_ms) < CHALLENGE_WINDOW_MS).then_some(challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_challenge_is_single_use_and_expires() {
+        let mut challenge = PressChallenge::new();
+        assert!(!challenge.is_armed(0));
+        assert_eq!(challenge.take(0), None);
+
+        challenge.arm([1; 32], 1_000);
+        assert!(challenge.is_armed(2_000));
+        assert_eq!(challenge.take(2_000), Some([1; 32]));
+        assert_eq!(challenge.take(2_000), None);
+
+        challenge.arm([2; 32], 1_000);
+        challenge.arm([3; 32], 1_500);
+        assert!(!challenge.is_armed(1_500 + CHALLENGE_WINDOW_MS));
+        assert_eq!(challenge.take(1_500 + CHALLENGE_WINDOW_MS), None);
+    }
+}
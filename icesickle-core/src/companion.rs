@@ -0,0 +1,226 @@
+//! Companion signatures for existing verification tools
+//!
+//! An IceSickle signature covers the postcard payload behind
+//! `attestation::SIGNING_DOMAIN`, which only an IceSickle verifier can
+//! rebuild. Tools such as gpg and ssh-keygen verify a
+//! signature over their own framing of the data instead. For each companion
+//! format compiled in, the ephemeral key therefore signs one more message,
+//! built from the same payload bytes, before it is zeroized. Every companion message binds
+//! the payload, so it attests the same event and nothing else; a format's
+//! framing (its magic, type octets or namespace) keeps one message from
+//! ever being valid as another.
+//!
+//! Companions are chosen by crate feature, and [`ENABLED`] lists them in
+//! signing order. Without any such feature the list is empty and signing
+//! costs nothing extra.
+
+use crate::attestation::{Attestation, AttestationEvent};
+use crate::auth::TokenProof;
+#[cfg(feature = "cose")]
+use crate::cose;
+#[cfg(feature = "cwt")]
+use crate::cwt;
+#[cfg(feature = "dsse")]
+use crate::dsse;
+#[cfg(feature = "openpgp")]
+use crate::openpgp;
+use crate::policy::Policy;
+use crate::scheme::Algorithm;
+#[cfg(feature = "sshsig")]
+use crate::sshsig;
+use crate::wallclock::WallTime;
+
+/// Most companion signatures per attestation
+pub const MAX_COMPANIONS: usize = 6;
+
+/// Longest companion message (a DSSE statement, see `dsse`)
+pub const MAX_MESSAGE_LEN: usize = 1088;
+
+/// One extra message the ephemeral key signs
+///
+/// Only the companions of enabled features exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Companion {
+    /// OpenPGP self-certification of the ephemeral key (see `openpgp`)
+    #[cfg(feature = "openpgp")]
+    OpenPgpKey,
+    /// OpenPGP detached signature over the payload
+    #[cfg(feature = "openpgp")]
+    OpenPgpData,
+    /// OpenSSH `sshsig` signature over the payload (see `sshsig`)
+    #[cfg(feature = "sshsig")]
+    SshSig,
+    /// DSSE envelope around an in-toto statement (see `dsse`)
+    #[cfg(feature = "dsse")]
+    DsseStatement,
+    /// CWT claims in a `COSE_Sign1` (see `cwt`)
+    #[cfg(feature = "cwt")]
+    Cwt,
+    /// `COSE_Sign1` around the payload (see `cose`)
+    #[cfg(feature = "cose")]
+    CoseSign1,
+}
+
+/// Companions compiled in, in signing order
+pub const ENABLED: &[Companion] = &[
+    #[cfg(feature = "openpgp")]
+    Companion::OpenPgpKey,
+    #[cfg(feature = "openpgp")]
+    Companion::OpenPgpData,
+    #[cfg(feature = "sshsig")]
+    Companion::SshSig,
+    #[cfg(feature = "dsse")]
+    Companion::DsseStatement,
+    #[cfg(feature = "cwt")]
+    Companion::Cwt,
+    #[cfg(feature = "cose")]
+    Companion::CoseSign1,
+];
+
+const _: () = assert!(ENABLED.len() <= MAX_COMPANIONS);
+
+/// Signatures in [`ENABLED`] order
+pub type Signatures = heapless::Vec<[u8; 64], MAX_COMPANIONS>;
+
+/// Message bytes to sign
+pub type Message = heapless::Vec<u8, MAX_MESSAGE_LEN>;
+
+/// What every companion message is built from: the signed payload, in
+/// encoded and decoded form, and the key
+#[derive(Debug, Clone, Copy)]
+pub struct Subject<'a> {
+    /// The encoded payload the IceSickle signature covers
+    pub payload: &'a [u8],
+    pub version: u8,
+    pub event: AttestationEvent,
+    pub timestamp_ms: u64,
+    pub counter: u32,
+    pub wall_clock: Option<WallTime>,
+    pub policy: Policy,
+    pub suppressed: u32,
+    pub challenge: Option<[u8; 32]>,
+    pub token: Option<TokenProof>,
+    pub boot_nonce: Option<[u8; 16]>,
+    pub algorithm: Algorithm,
+    pub context: Option<&'a [u8]>,
+    pub measurement: Option<[u8; 32]>,
+    pub public_key: &'a [u8; 32],
+}
+
+impl<'a> Subject<'a> {
+    /// `attestation`, whose encoded payload is `payload`
+    pub fn of(attestation: &'a Attestation, payload: &'a [u8]) -> Self {
+        Self {
+            payload,
+            version: attestation.version(),
+            event: attestation.event(),
+            timestamp_ms: attestation.timestamp_ms(),
+            counter: attestation.counter(),
+            wall_clock: attestation.wall_clock(),
+            policy: attestation.policy(),
+            suppressed: attestation.suppressed(),
+            challenge: attestation.challenge(),
+            token: attestation.token(),
+            boot_nonce: attestation.boot_nonce(),
+            algorithm: attestation.algorithm(),
+            context: attestation.context().map(|c| &c[..]),
+            measurement: attestation.measurement(),
+            public_key: attestation.public_key_bytes(),
+        }
+    }
+}
+
+impl Companion {
+    /// The message this companion's signature covers
+    #[allow(unused_variables)]
+    pub fn message(self, subject: &Subject) -> Message {
+        match self {
+            #[cfg(feature = "openpgp")]
+            Companion::OpenPgpKey => message(&openpgp::key_digest(subject)),
+            #[cfg(feature = "openpgp")]
+            Companion::OpenPgpData => message(&openpgp::data_digest(subject)),
+            #[cfg(feature = "sshsig")]
+            Companion::SshSig => sshsig::message(subject),
+            #[cfg(feature = "dsse")]
+            Companion::DsseStatement => dsse::message(subject),
+            #[cfg(feature = "cwt")]
+            Companion::Cwt => cwt::message(subject),
+            #[cfg(feature = "cose")]
+            Companion::CoseSign1 => cose::message(subject),
+        }
+    }
+
+    /// This companion's signature among `signatures`, if it is enabled
+    pub fn find(self, signatures: &Signatures) -> Option<&[u8; 64]> {
+        ENABLED
+            .iter()
+            .position(|&c| c == self)
+            .and_then(|i| signatures.get(i))
+    }
+}
+
+/// Standard base64 with padding, as the companion formats' text forms use
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = |i: usize| chunk.get(i).copied().unwrap_or(0);
+        let n = u32::from_be_bytes([0, b(0), b(1), b(2)]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Every companion message fits [`MAX_MESSAGE_LEN`], so nothing is truncated
+#[cfg(feature = "openpgp")]
+fn message(bytes: &[u8]) -> Message {
+    Message::from_slice(bytes).unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A button-press subject over `payload`, for the format modules' tests
+    #[cfg(any(
+        feature = "openpgp",
+        feature = "sshsig",
+        feature = "dsse",
+        feature = "cwt",
+        feature = "cose"
+    ))]
+    pub(crate) fn subject(payload: &[u8]) -> Subject<'_> {
+        Subject {
+            payload,
+            version: 12,
+            event: AttestationEvent::ButtonPress { gpio: 0 },
+            timestamp_ms: 1_234,
+            counter: 7,
+            wall_clock: None,
+            policy: Policy::NONE,
+            suppressed: 0,
+            challenge: None,
+            token: None,
+            boot_nonce: None,
+            algorithm: Algorithm::Ed25519,
+            context: None,
+            measurement: None,
+            public_key: &[0x11; 32],
+        }
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,71 @@
+//! Application context signed into a press
+//!
+//! An attestation says a button was pressed; it does not say what the
+//! press was *for*. An application that asks for one (approve this
+//! transfer, open that door) can bind its own meaning into the signature:
+//!
+//! 1. The host sends `SetContext` with up to [`MAX_CONTEXT_LEN`] bytes,
+//!    arming a [`PressContext`] for [`CONTEXT_WINDOW_MS`].
+//! 2. The operator presses the button within the window.
+//! 3. The device signs the bytes into that attestation's payload
+//!    (`context`, payload version 10) and drops them.
+//!
+//! The bytes are opaque to the device: typically a hash of the request the
+//! press approves, which keeps them inside the bound whatever the request
+//! is. The bound is set by the display, whose largest QR symbol must still
+//! hold the largest record. As with a challenge, only button presses take
+//! the context, one press per context.
+//!
+//! The context is public and chosen by whoever holds the serial port. A
+//! signature over it shows the press came after the host set it, not that
+//! the operator saw or agreed to it: an application that needs that shows
+//! the operator its request on a screen it controls.
+
+pub use icesickle_payload::context::{Context, MAX_CONTEXT_LEN};
+
+/// An armed context expires if no press comes within this time
+pub const CONTEXT_WINDOW_MS: u64 = 60_000;
+
+/// Host context awaiting a button press
+#[derive(Debug, Default)]
+pub struct PressContext {
+    /// Context and when it was armed
+    armed: Option<(Context, u64)>,
+}
+
+impl PressContext {
+    pub const fn new() -> Self {
+        Self { armed: None }
+    }
+
+    /// Arm `context`, replacing any earlier one
+    pub fn arm(&mut self, context: Context, now_ms: u64) {
+        self.armed = Some((context, now_ms));
+    }
+
+    /// Take the armed context, if its window is still open
+    pub fn take(&mut self, now_ms: u64) -> Option<Context> {
+        let (context, armed_at_ms) = self.armed.take()?;
+        (now_ms.saturating_sub(armed_at_ms) < CONTEXT_WINDOW_MS).then_some(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_is_single_use_and_expires() {
+        let mut context = PressContext::new();
+        assert_eq!(context.take(0), None);
+
+        let approve = Context::from_slice(b"approve #42").unwrap();
+        context.arm(approve.clone(), 1_000);
+        assert_eq!(context.take(2_000), Some(approve));
+        assert_eq!(context.take(2_000), None);
+
+        context.arm(Context::from_slice(&[1]).unwrap(), 1_000);
+        context.arm(Context::from_slice(&[2]).unwrap(), 1_500);
+        assert_eq!(context.take(1_500 + CONTEXT_WINDOW_MS), None);
+    }
+}
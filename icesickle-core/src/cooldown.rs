@@ -0,0 +1,522 @@
+//! Physical rate limiting (cooldown enforcement)
+//!
+//! This module enforces a minimum time interval between attestations, on
+//! average: a token bucket that holds a burst of presses and refills one
+//! per cooldown. The goal is spam resistance via physics, not network-based
+//! rate limiting.
+//!
+//! # Design Rationale
+//!
+//! Rate limiting happens at the device level, not at a server. This means:
+//! - No network round-trip required to enforce limits
+//! - No centralized state to attack or bypass
+//! - Cooldown is bounded by physical time, not software policy
+//!
+//! # Threat Model
+//!
+//! An attacker with physical access can still:
+//! - Replace firmware to remove cooldown (but this is already in threat model)
+//! - Wait out the cooldown (this is the point)
+//!
+//! An attacker without physical access cannot:
+//! - Trigger attestations faster than the cooldown allows
+//! - Accumulate "credits" for future rapid-fire signing beyond the burst
+//!
+//! # Policy
+//!
+//! How long the cooldown lasts is a [`CooldownPolicy`] fixed at build time:
+//! a base interval, and optionally an exponential [`Backoff`]. With backoff,
+//! every press the cooldown refuses doubles it, up to a cap, so someone
+//! hammering the button is held off for longer and longer. Once no press
+//! has arrived for the quiet period, the cooldown is back at the base.
+//! [`Cooldown::current_ms`] reports where it stands.
+//!
+//! # Burst
+//!
+//! The policy's `burst` is the bucket's capacity: after a quiet spell that
+//! many presses count back to back, for a workflow that needs two or three
+//! attestations at once, and then one per cooldown as the bucket refills.
+//! A burst of one is a strict interval. The bucket is one timestamp, when
+//! it was last empty, and holds a press for every cooldown since, up to the
+//! burst; [`Cooldown::level`] reports how many. A backed-off cooldown
+//! refills it more slowly, including presses already on their way back.
+//!
+//! # Sleep
+//!
+//! The cooldown is measured on [`Timer::now_ms`], which counts through
+//! light sleep, so a press that wakes the device is judged on the real time
+//! since the last attestation. A clock that stopped while asleep would only
+//! stretch the cooldown, never shorten it.
+//!
+//! # Fault Resistance
+//!
+//! The bucket's timestamp is the one word in RAM whose corruption silently
+//! disables a security check: zero it and the cooldown is gone. It
+//! is therefore held as a [`Stamp`]: the value, an inverted copy and a
+//! checksum. A single flipped bit or glitched store leaves two of the three
+//! agreeing, and their value wins (and is written back). If nothing agrees
+//! the cooldown fails closed: it restarts from now.
+
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::error::{IceSickleError, Result};
+use crate::hal::Timer;
+use crate::harden;
+
+/// Minimum milliseconds between attestations
+pub const COOLDOWN_MS: u64 = 1000; // 1 second default
+
+/// How long the cooldown lasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CooldownPolicy {
+    /// Cooldown after each attestation
+    pub base_ms: u64,
+    /// Lengthening under rapid pressing, if any
+    pub backoff: Option<Backoff>,
+    /// Presses allowed back to back once the bucket is full (1 or more)
+    pub burst: u8,
+}
+
+impl CooldownPolicy {
+    /// [`COOLDOWN_MS`], without backoff or burst
+    pub const DEFAULT: Self = Self {
+        base_ms: COOLDOWN_MS,
+        backoff: None,
+        burst: 1,
+    };
+}
+
+/// Exponential backoff of the cooldown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Longest the cooldown grows to (never below the base)
+    pub max_ms: u64,
+    /// Time without a press after which it is back at the base
+    pub quiet_ms: u64,
+}
+
+/// Result of a cooldown check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CooldownResult {
+    /// Attestation allowed, cooldown has elapsed
+    Ready,
+    /// Attestation blocked, must wait
+    Wait { remaining_ms: u64 },
+}
+
+/// Seed for [`checksum`], so that all-zero memory is never a valid stamp
+const CHECK_SEED: u32 = 0x5A3C_C3A5;
+
+/// A timestamp held redundantly: value, inverted copy, checksum
+struct Stamp {
+    value: AtomicU64,
+    inverted: AtomicU64,
+    check: AtomicU32,
+}
+
+impl Stamp {
+    const fn new(value: u64) -> Self {
+        Self {
+            value: AtomicU64::new(value),
+            inverted: AtomicU64::new(!value),
+            check: AtomicU32::new(checksum(value)),
+        }
+    }
+
+    fn store(&self, value: u64) {
+        self.value.store(value, Ordering::SeqCst);
+        self.inverted.store(!value, Ordering::SeqCst);
+        self.check.store(checksum(value), Ordering::SeqCst);
+    }
+
+    /// The value at least two witnesses agree on, and whether all three do
+    fn load(&self) -> Option<(u64, bool)> {
+        let value = self.value.load(Ordering::SeqCst);
+        let copy = !self.inverted.load(Ordering::SeqCst);
+        let check = self.check.load(Ordering::SeqCst);
+
+        let value_ok = checksum(value) == check;
+        let copy_ok = checksum(copy) == check;
+        match (value_ok, copy_ok) {
+            (true, true) => Some((value, true)),
+            (true, false) => Some((value, false)),
+            (false, true) => Some((copy, false)),
+            // Only the checksum is damaged
+            (false, false) if value == copy => Some((value, false)),
+            (false, false) => None,
+        }
+    }
+}
+
+const fn checksum(value: u64) -> u32 {
+    (value as u32) ^ ((value >> 32) as u32).rotate_left(13) ^ CHECK_SEED
+}
+
+/// Cooldown state: the token bucket, as the time it was last empty
+///
+/// The firmware keeps a single static instance (in RTC memory); separate
+/// instances exist so the logic can be tested in isolation.
+pub struct Cooldown {
+    policy: CooldownPolicy,
+    /// When the bucket last held no press; with a burst of one, the last
+    /// attestation
+    drained_ms: Stamp,
+    /// Cooldown after backoff; only read within the policy's bounds
+    backoff_ms: AtomicU64,
+    /// Latest press, allowed or refused, for the quiet period
+    last_press_ms: AtomicU64,
+}
+
+impl Cooldown {
+    /// [`CooldownPolicy::DEFAULT`]
+    pub const fn new() -> Self {
+        Self::with_policy(CooldownPolicy::DEFAULT)
+    }
+
+    pub const fn with_policy(policy: CooldownPolicy) -> Self {
+        Self {
+            policy,
+            drained_ms: Stamp::new(0),
+            backoff_ms: AtomicU64::new(policy.base_ms),
+            last_press_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub fn policy(&self) -> CooldownPolicy {
+        self.policy
+    }
+
+    /// The cooldown now in force, backoff included
+    pub fn current_ms(&self, timer: &impl Timer) -> u64 {
+        self.current(timer.now_ms())
+    }
+
+    /// The cooldown at `now`: the base unless backoff has raised it and no
+    /// quiet period has passed since. A damaged backoff word is clamped to
+    /// the policy, so it can never shorten the base
+    fn current(&self, now: u64) -> u64 {
+        let base = self.policy.base_ms;
+        match self.policy.backoff {
+            Some(backoff)
+                if now.saturating_sub(self.last_press_ms.load(Ordering::SeqCst))
+                    < backoff.quiet_ms =>
+            {
+                let raised = self.backoff_ms.load(Ordering::SeqCst);
+                raised.clamp(base, backoff.max_ms.max(base))
+            }
+            _ => base,
+        }
+    }
+
+    /// Note a press at `now`; the cooldown it is judged by
+    fn press(&self, now: u64) -> u64 {
+        let cooldown = self.current(now);
+        if cooldown == self.policy.base_ms {
+            self.backoff_ms.store(cooldown, Ordering::SeqCst);
+        }
+        self.last_press_ms.store(now, Ordering::SeqCst);
+        cooldown
+    }
+
+    /// Double the cooldown after a refused press, up to the cap; the new
+    /// cooldown
+    fn back_off(&self, cooldown: u64) -> u64 {
+        let Some(backoff) = self.policy.backoff else {
+            return cooldown;
+        };
+        let raised = cooldown.saturating_mul(2).min(backoff.max_ms.max(cooldown));
+        self.backoff_ms.store(raised, Ordering::SeqCst);
+        raised
+    }
+
+    /// When the bucket was last empty, repairing or failing closed
+    fn drained(&self, now: u64) -> u64 {
+        match self.drained_ms.load() {
+            Some((drained, true)) => drained,
+            Some((drained, false)) => {
+                log::warn!("Cooldown timestamp damaged, repaired");
+                self.drained_ms.store(drained);
+                drained
+            }
+            None => {
+                log::warn!("Cooldown timestamp unrecoverable, restarting cooldown");
+                self.drained_ms.store(now);
+                now
+            }
+        }
+    }
+
+    /// The bucket's capacity; a burst of 0 is taken as 1
+    fn capacity(&self) -> u64 {
+        u64::from(self.policy.burst.max(1))
+    }
+
+    /// When the bucket is empty after a press at `now` takes one of its
+    /// presses: a cooldown later than it was, measured from no earlier than
+    /// a full bucket
+    fn take(&self, drained: u64, now: u64, cooldown: u64) -> u64 {
+        let full = now.saturating_sub(cooldown.saturating_mul(self.capacity()));
+        drained.max(full).saturating_add(cooldown)
+    }
+
+    /// Presses the bucket holds now, up to the burst
+    pub fn level(&self, timer: &impl Timer) -> u8 {
+        let now = timer.now_ms();
+        let elapsed = now.saturating_sub(self.drained(now));
+        let presses = elapsed / self.current(now).max(1);
+        // At most the burst, which is a u8
+        presses.min(self.capacity()) as u8
+    }
+
+    /// Check if the bucket holds a press
+    pub fn check(&self, timer: &impl Timer) -> CooldownResult {
+        let now = timer.now_ms();
+        let drained = self.drained(now);
+        let cooldown = self.current(now);
+
+        let elapsed = now.saturating_sub(drained);
+
+        if elapsed >= cooldown {
+            CooldownResult::Ready
+        } else {
+            CooldownResult::Wait {
+                remaining_ms: cooldown - elapsed,
+            }
+        }
+    }
+
+    /// Record that an attestation was just produced, taking a press from
+    /// the bucket
+    pub fn record_attestation(&self, timer: &impl Timer) {
+        let now = timer.now_ms();
+        let drained = self.drained(now);
+        self.drained_ms
+            .store(self.take(drained, now, self.current(now)));
+    }
+
+    /// Check and record atomically
+    ///
+    /// Hardened against glitches (see `harden`): the comparison is made
+    /// twice and re-verified inside the allowed branch. Refusal is
+    /// `IceSickleError::Cooldown`, with the time left under the cooldown
+    /// the refusal has backed off to; disagreement is
+    /// `IceSickleError::Glitch`.
+    pub fn gate(&self, timer: &impl Timer) -> Result<()> {
+        let now = timer.now_ms();
+        let drained = self.drained(now);
+        let cooldown = self.press(now);
+        let elapsed = now.saturating_sub(drained);
+
+        if !harden::decide(|| elapsed >= cooldown)? {
+            return Err(IceSickleError::Cooldown {
+                remaining_ms: self.back_off(cooldown) - elapsed,
+            });
+        }
+        // A skipped branch above lands here too; check again from scratch
+        let drained = self.drained(now);
+        if !harden::decide(|| now.saturating_sub(drained) >= cooldown)? {
+            return Err(IceSickleError::Glitch);
+        }
+        self.drained_ms.store(self.take(drained, now, cooldown));
+        Ok(())
+    }
+}
+
+impl Default for Cooldown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::MockTimer;
+
+    #[test]
+    fn test_cooldown_result_variants() {
+        // Just verify the enum is well-formed
+        let ready = CooldownResult::Ready;
+        let wait = CooldownResult::Wait { remaining_ms: 500 };
+        assert_ne!(ready, wait);
+    }
+
+    #[test]
+    fn test_gate_blocks_until_cooldown_elapses() {
+        let cooldown = Cooldown::new();
+        let timer = MockTimer::at(5_000);
+
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+
+        timer.advance(400);
+        assert_eq!(
+            cooldown.gate(&timer),
+            Err(IceSickleError::Cooldown {
+                remaining_ms: COOLDOWN_MS - 400
+            })
+        );
+
+        timer.advance(COOLDOWN_MS - 400);
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+    }
+
+    #[test]
+    fn test_blocked_press_does_not_extend_cooldown() {
+        let cooldown = Cooldown::new();
+        let timer = MockTimer::at(5_000);
+
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+        timer.advance(900);
+        assert!(cooldown.gate(&timer).is_err());
+        timer.advance(100);
+        assert_eq!(cooldown.check(&timer), CooldownResult::Ready);
+    }
+
+    const BACKOFF: CooldownPolicy = CooldownPolicy {
+        base_ms: 1_000,
+        backoff: Some(Backoff {
+            max_ms: 4_000,
+            quiet_ms: 10_000,
+        }),
+        burst: 1,
+    };
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap_and_resets_after_quiet() {
+        let cooldown = Cooldown::with_policy(BACKOFF);
+        let timer = MockTimer::at(5_000);
+
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+        assert_eq!(cooldown.current_ms(&timer), 1_000);
+        for (remaining_ms, current_ms) in [(1_600, 2_000), (3_200, 4_000), (2_800, 4_000)] {
+            timer.advance(400);
+            assert_eq!(
+                cooldown.gate(&timer),
+                Err(IceSickleError::Cooldown { remaining_ms })
+            );
+            assert_eq!(cooldown.current_ms(&timer), current_ms);
+        }
+
+        // Presses keep coming, so the cooldown stays backed off
+        timer.set(9_000);
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+        timer.advance(3_000);
+        assert!(cooldown.gate(&timer).is_err());
+
+        // Quiet since the last press: back to the base
+        timer.advance(10_000);
+        assert_eq!(cooldown.current_ms(&timer), 1_000);
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+        timer.advance(1_000);
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+    }
+
+    #[test]
+    fn test_burst_then_one_press_per_cooldown() {
+        let cooldown = Cooldown::with_policy(CooldownPolicy {
+            burst: 3,
+            ..CooldownPolicy::DEFAULT
+        });
+        let timer = MockTimer::at(5_000);
+        assert_eq!(cooldown.level(&timer), 3);
+        for level in [2, 1, 0] {
+            assert_eq!(cooldown.gate(&timer), Ok(()));
+            assert_eq!(cooldown.level(&timer), level);
+        }
+        assert_eq!(
+            cooldown.gate(&timer),
+            Err(IceSickleError::Cooldown {
+                remaining_ms: COOLDOWN_MS
+            })
+        );
+
+        // One press back per cooldown, up to the burst
+        timer.advance(COOLDOWN_MS);
+        assert_eq!(cooldown.level(&timer), 1);
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+        assert_eq!(
+            cooldown.check(&timer),
+            CooldownResult::Wait {
+                remaining_ms: COOLDOWN_MS
+            }
+        );
+        timer.advance(2 * COOLDOWN_MS + 500);
+        assert_eq!(cooldown.level(&timer), 2);
+        timer.advance(10 * COOLDOWN_MS);
+        assert_eq!(cooldown.level(&timer), 3);
+    }
+
+    #[test]
+    fn test_damaged_backoff_stays_within_policy() {
+        let cooldown = Cooldown::with_policy(BACKOFF);
+        let timer = MockTimer::at(5_000);
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+
+        cooldown.backoff_ms.store(0, Ordering::SeqCst);
+        assert_eq!(cooldown.current_ms(&timer), 1_000);
+        cooldown.backoff_ms.store(u64::MAX, Ordering::SeqCst);
+        assert_eq!(cooldown.current_ms(&timer), 4_000);
+
+        // Without backoff the policy's base is all there is
+        let fixed = Cooldown::new();
+        fixed.backoff_ms.store(u64::MAX, Ordering::SeqCst);
+        assert_eq!(fixed.current_ms(&timer), COOLDOWN_MS);
+        assert_eq!(fixed.policy(), CooldownPolicy::DEFAULT);
+    }
+
+    #[test]
+    fn test_single_corruption_is_outvoted() {
+        let cooldown = Cooldown::new();
+        let timer = MockTimer::at(5_000);
+        cooldown.record_attestation(&timer);
+        timer.advance(400);
+
+        // Zeroing the primary word must not reset the cooldown
+        cooldown.drained_ms.value.store(0, Ordering::SeqCst);
+        assert!(cooldown.gate(&timer).is_err());
+        assert_eq!(cooldown.drained_ms.load(), Some((5_000, true)));
+
+        cooldown
+            .drained_ms
+            .inverted
+            .fetch_xor(1 << 40, Ordering::SeqCst);
+        assert!(cooldown.gate(&timer).is_err());
+        cooldown.drained_ms.check.store(0, Ordering::SeqCst);
+        assert!(cooldown.gate(&timer).is_err());
+        assert_eq!(cooldown.drained_ms.load(), Some((5_000, true)));
+    }
+
+    #[test]
+    fn test_unrecoverable_stamp_fails_closed() {
+        let cooldown = Cooldown::new();
+        let timer = MockTimer::at(5_000);
+        let stamp = &cooldown.drained_ms;
+        stamp.value.store(0, Ordering::SeqCst);
+        stamp.inverted.store(0, Ordering::SeqCst);
+        stamp.check.store(0, Ordering::SeqCst);
+
+        assert_eq!(
+            cooldown.gate(&timer),
+            Err(IceSickleError::Cooldown {
+                remaining_ms: COOLDOWN_MS
+            })
+        );
+        timer.advance(COOLDOWN_MS);
+        assert_eq!(cooldown.gate(&timer), Ok(()));
+    }
+
+    #[test]
+    fn test_clock_going_backwards_blocks() {
+        let cooldown = Cooldown::new();
+        let timer = MockTimer::at(5_000);
+
+        cooldown.record_attestation(&timer);
+        timer.set(100);
+        assert_eq!(
+            cooldown.check(&timer),
+            CooldownResult::Wait {
+                remaining_ms: COOLDOWN_MS
+            }
+        );
+    }
+}
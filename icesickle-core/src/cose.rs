@@ -0,0 +1,92 @@
+//! COSE_Sign1 output (feature `cose`)
+//!
+//! Wraps the signed payload itself in a tagged `COSE_Sign1` (RFC 9052),
+//! for verifiers built on COSE libraries rather than on IceSickle's code:
+//!
+//! ```text
+//! 18([
+//!   h'A10127',                       / protected: {1 (alg): -8 (EdDSA)} /
+//!   {-65537: {1: 1, -1: 6, -2: pk}}, / unprotected: the key, OKP Ed25519 /
+//!   payload,                         / the postcard payload bytes /
+//!   signature                        / EdDSA over the Sig_structure /
+//! ])
+//! ```
+//!
+//! The ephemeral key signs the canonical `Sig_structure` over the payload,
+//! a companion message of its own (see `companion`), so the COSE signature
+//! is valid next to the IceSickle one without either standing in for the
+//! other. The key travels as a `COSE_Key` in the unprotected header, under
+//! a private-use label (RFC 9052 reserves labels below -65536), since no
+//! registered header parameter carries a bare key. Leaving it unprotected
+//! costs nothing: a substituted key does not verify the signature, and
+//! the private key is gone once the attestation is output.
+//!
+//! Unlike `cwt`, which restates the payload fields as claims, the payload
+//! here is the postcard bytes, which a COSE verifier checks but only an
+//! IceSickle decoder reads.
+
+use crate::attestation::{Attestation, MAX_PAYLOAD_LEN};
+use crate::cbor::{
+    self, bytes, head, MAJOR_ARRAY, MAJOR_MAP, MAJOR_NINT, MAJOR_TAG, TAG_COSE_SIGN1,
+};
+use crate::companion::{Companion, Message, Subject};
+
+/// Unprotected header label for the key, -65537, as its CBOR argument
+const HEADER_KEY_NINT: u64 = 65_536;
+
+/// Largest tagged `COSE_Sign1`: the payload plus 128 bytes of framing,
+/// key and signature (see the tests)
+const MAX_SIGN1_LEN: usize = MAX_PAYLOAD_LEN + 128;
+
+/// Encoded, tagged `COSE_Sign1`
+pub type Sign1 = heapless::Vec<u8, MAX_SIGN1_LEN>;
+
+/// Render `attestation` as a `COSE_Sign1`; `None` unless it carries the
+/// COSE companion
+pub fn render(attestation: &Attestation) -> Option<Sign1> {
+    let signature = Companion::CoseSign1.find(attestation.companions())?;
+    let payload = attestation.payload_bytes();
+    Some(sign1(&payload, attestation.public_key_bytes(), signature))
+}
+
+fn sign1(payload: &[u8], public_key: &[u8; 32], signature: &[u8; 64]) -> Sign1 {
+    let mut out = Sign1::new();
+    head(&mut out, MAJOR_TAG, TAG_COSE_SIGN1);
+    head(&mut out, MAJOR_ARRAY, 4);
+    bytes(&mut out, &cbor::PROTECTED);
+    head(&mut out, MAJOR_MAP, 1);
+    head(&mut out, MAJOR_NINT, HEADER_KEY_NINT);
+    cbor::cose_key(&mut out, public_key);
+    bytes(&mut out, payload);
+    bytes(&mut out, signature);
+    out
+}
+
+/// COSE `Sig_structure` over the payload: what the key signs
+pub(crate) fn message(subject: &Subject) -> Message {
+    cbor::sig_structure(subject.payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::companion::tests::subject;
+
+    #[test]
+    fn test_sign1_layout() {
+        let sign1 = sign1(b"payload", &[0x11; 32], &[0x22; 64]);
+        assert!(sign1.starts_with(b"\xD2\x84\x43\xA1\x01\x27\xA1\x3A\x00\x01\x00\x00\xA3"));
+        assert!(sign1.ends_with(&[b"\x47payload\x58\x40".as_slice(), &[0x22; 64]].concat()));
+
+        let message = message(&subject(b"payload"));
+        assert_eq!(message, cbor::sig_structure(b"payload"));
+    }
+
+    #[test]
+    fn test_largest_sign1_fits() {
+        let payload = [0xFF; MAX_PAYLOAD_LEN];
+        let sign1 = sign1(&payload, &[0xFF; 32], &[0xFF; 64]);
+        assert!(sign1.ends_with(&[0xFF; 64]));
+        assert!(sign1.len() < MAX_SIGN1_LEN);
+    }
+}
@@ -0,0 +1,80 @@
+//! Coin/credit pulse bursts
+//!
+//! Coin acceptors and bill validators report a payment as a burst of pulses
+//! on one output: one pulse per credit unit, 20-100 ms each, with gaps of
+//! about the same length. The firmware (feature `credit`) counts edges in an
+//! interrupt; [`BurstCounter`] groups them into bursts. A burst ends once
+//! the line has been quiet for [`BURST_GAP_MS`], and its total becomes one
+//! `CreditPulse { count }` attestation.
+//!
+//! A burst is never dropped. If the cooldown refuses it, it stays pending
+//! and is signed as soon as the cooldown allows, merged with any pulses
+//! that arrived meanwhile.
+
+/// Quiet time that ends a burst
+pub const BURST_GAP_MS: u64 = 500;
+
+/// Pulses awaiting attestation
+#[derive(Debug, Default)]
+pub struct BurstCounter {
+    pending: u32,
+    last_edge_ms: u64,
+}
+
+impl BurstCounter {
+    pub const fn new() -> Self {
+        Self {
+            pending: 0,
+            last_edge_ms: 0,
+        }
+    }
+
+    /// Record `pulses` new edges, the latest at `last_edge_ms`
+    pub fn add(&mut self, pulses: u32, last_edge_ms: u64) {
+        if pulses > 0 {
+            self.pending = self.pending.saturating_add(pulses);
+            self.last_edge_ms = last_edge_ms;
+        }
+    }
+
+    /// True once a burst is pending and the line has gone quiet
+    pub fn is_ready(&self, now_ms: u64) -> bool {
+        self.pending > 0 && now_ms.saturating_sub(self.last_edge_ms) >= BURST_GAP_MS
+    }
+
+    /// Take the pending burst's count, saturated to the event field
+    pub fn take(&mut self) -> u16 {
+        let count = core::mem::take(&mut self.pending);
+        u16::try_from(count).unwrap_or(u16::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_ends_after_gap() {
+        let mut counter = BurstCounter::new();
+        assert!(!counter.is_ready(10_000));
+
+        counter.add(1, 1_000);
+        counter.add(2, 1_200);
+        counter.add(0, 1_400);
+        assert!(!counter.is_ready(1_200 + BURST_GAP_MS - 1));
+        assert!(counter.is_ready(1_200 + BURST_GAP_MS));
+        assert_eq!(counter.take(), 3);
+        assert!(!counter.is_ready(5_000));
+    }
+
+    #[test]
+    fn test_deferred_burst_merges() {
+        let mut counter = BurstCounter::new();
+        counter.add(2, 1_000);
+        // Not taken (cooldown); a second payment starts before the retry
+        counter.add(1, 2_000);
+        assert!(!counter.is_ready(2_100));
+        assert!(counter.is_ready(2_000 + BURST_GAP_MS));
+        assert_eq!(counter.take(), 3);
+    }
+}
@@ -0,0 +1,490 @@
+//! CTAPHID-framed user-presence interface
+//!
+//! Lets a host use existing FIDO/security-key HID plumbing to ask for an
+//! attestation: the host sends a vendor `ATTEST` command, the device answers
+//! with `KEEPALIVE(UPNEEDED)` until the button is pressed, then returns the
+//! attestation record. Framing, channel allocation and the standard commands
+//! (`INIT`, `PING`, `WINK`, `CANCEL`) follow the CTAPHID section of the
+//! CTAP 2.1 specification; nothing FIDO-specific (credentials, CBOR
+//! authenticator API) is implemented.
+//!
+//! # Unlinkability
+//!
+//! Channel IDs are drawn from the hardware RNG on every `INIT` and are only
+//! valid until the device resets, so they never act as a device identifier.
+//!
+//! # ATTEST command (0x41)
+//!
+//! Request body is empty (presence only). Challenge binding arrives with
+//! challenge support in the signed payload; until then a non-empty body is
+//! rejected with `ERR_INVALID_LEN` rather than silently ignored.
+//!
+//! Response body is `status: u8` followed by, for [`AttestStatus::Ok`], the
+//! postcard-encoded [`AttestationRecord`].
+
+use rand_core::RngCore;
+
+use crate::protocol::AttestationRecord;
+
+/// HID report size (both directions)
+pub const REPORT_LEN: usize = 64;
+
+/// Largest message this device assembles or sends
+pub const MAX_MESSAGE_LEN: usize = 352;
+
+const INIT_DATA_LEN: usize = REPORT_LEN - 7;
+const CONT_DATA_LEN: usize = REPORT_LEN - 5;
+
+/// Broadcast channel, only valid for `INIT`
+const BROADCAST_CID: u32 = 0xFFFF_FFFF;
+
+/// How many allocated channels are remembered
+const MAX_CHANNELS: usize = 4;
+
+/// How often to remind the host we are waiting for the button
+const KEEPALIVE_INTERVAL_MS: u64 = 100;
+
+/// How long a presence request waits for the button
+const PRESENCE_TIMEOUT_MS: u64 = 30_000;
+
+/// CTAPHID command codes
+pub mod cmd {
+    pub const PING: u8 = 0x01;
+    pub const INIT: u8 = 0x06;
+    pub const WINK: u8 = 0x08;
+    pub const CANCEL: u8 = 0x11;
+    pub const KEEPALIVE: u8 = 0x3B;
+    pub const ERROR: u8 = 0x3F;
+    /// Vendor command: request an attestation after user presence
+    pub const ATTEST: u8 = 0x41;
+}
+
+/// CTAPHID error codes (body of an `ERROR` response)
+pub mod err {
+    pub const INVALID_CMD: u8 = 0x01;
+    pub const INVALID_LEN: u8 = 0x03;
+    pub const INVALID_SEQ: u8 = 0x04;
+    pub const CHANNEL_BUSY: u8 = 0x06;
+    pub const INVALID_CHANNEL: u8 = 0x0B;
+}
+
+/// `KEEPALIVE` status: waiting for user presence
+const STATUS_UPNEEDED: u8 = 0x02;
+
+/// `INIT` capability flags: WINK supported, no CBOR, no MSG
+const CAPABILITIES: u8 = 0x01;
+
+/// First byte of an `ATTEST` response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AttestStatus {
+    Ok = 0,
+    Cancelled = 1,
+    Timeout = 2,
+    Failed = 3,
+}
+
+/// Something that can transmit one HID input report
+pub trait ReportSink {
+    fn send_report(&self, report: &[u8; REPORT_LEN]);
+}
+
+/// A fully reassembled request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub cid: u32,
+    pub cmd: u8,
+    pub data: heapless::Vec<u8, MAX_MESSAGE_LEN>,
+}
+
+/// A framing error to report on `cid`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameError {
+    pub cid: u32,
+    pub code: u8,
+}
+
+/// Reassembles init + continuation packets into messages (one at a time)
+#[derive(Default)]
+pub struct Assembler {
+    current: Option<Partial>,
+}
+
+struct Partial {
+    cid: u32,
+    cmd: u8,
+    len: usize,
+    next_seq: u8,
+    data: heapless::Vec<u8, MAX_MESSAGE_LEN>,
+}
+
+impl Assembler {
+    /// Feed one output report from the host
+    pub fn push(&mut self, report: &[u8; REPORT_LEN]) -> Option<Result<Message, FrameError>> {
+        let cid = u32::from_be_bytes([report[0], report[1], report[2], report[3]]);
+        let kind = report[4];
+
+        if kind & 0x80 != 0 {
+            // Initialization packet
+            if let Some(partial) = &self.current {
+                if partial.cid != cid {
+                    return Some(Err(FrameError {
+                        cid,
+                        code: err::CHANNEL_BUSY,
+                    }));
+                }
+            }
+
+            let len = u16::from_be_bytes([report[5], report[6]]) as usize;
+            if len > MAX_MESSAGE_LEN {
+                self.current = None;
+                return Some(Err(FrameError {
+                    cid,
+                    code: err::INVALID_LEN,
+                }));
+            }
+
+            let mut data = heapless::Vec::new();
+            let take = len.min(INIT_DATA_LEN);
+            let _ = data.extend_from_slice(&report[7..7 + take]);
+            self.current = Some(Partial {
+                cid,
+                cmd: kind & 0x7F,
+                len,
+                next_seq: 0,
+                data,
+            });
+        } else {
+            // Continuation packet; spurious ones are ignored per spec
+            let partial = self.current.as_mut().filter(|p| p.cid == cid)?;
+            if kind != partial.next_seq {
+                self.current = None;
+                return Some(Err(FrameError {
+                    cid,
+                    code: err::INVALID_SEQ,
+                }));
+            }
+
+            let take = (partial.len - partial.data.len()).min(CONT_DATA_LEN);
+            let _ = partial.data.extend_from_slice(&report[5..5 + take]);
+            partial.next_seq += 1;
+        }
+
+        let partial = self.current.as_ref()?;
+        if partial.data.len() < partial.len {
+            return None;
+        }
+
+        let partial = self.current.take()?;
+        Some(Ok(Message {
+            cid: partial.cid,
+            cmd: partial.cmd,
+            data: partial.data,
+        }))
+    }
+
+    /// Drop any half-received message on `cid`
+    fn abort(&mut self, cid: u32) {
+        if self.current.as_ref().is_some_and(|p| p.cid == cid) {
+            self.current = None;
+        }
+    }
+}
+
+/// Split a response into init + continuation reports and send them
+pub fn send_message(sink: &impl ReportSink, cid: u32, cmd: u8, data: &[u8]) {
+    let mut report = [0u8; REPORT_LEN];
+    report[..4].copy_from_slice(&cid.to_be_bytes());
+    report[4] = 0x80 | cmd;
+    report[5..7].copy_from_slice(&(data.len() as u16).to_be_bytes());
+
+    let (first, mut rest) = data.split_at(data.len().min(INIT_DATA_LEN));
+    report[7..7 + first.len()].copy_from_slice(first);
+    sink.send_report(&report);
+
+    let mut seq = 0u8;
+    while !rest.is_empty() {
+        let (chunk, tail) = rest.split_at(rest.len().min(CONT_DATA_LEN));
+        report[4] = seq;
+        report[5..].fill(0);
+        report[5..5 + chunk.len()].copy_from_slice(chunk);
+        sink.send_report(&report);
+        seq += 1;
+        rest = tail;
+    }
+}
+
+fn send_error(sink: &impl ReportSink, cid: u32, code: u8) {
+    send_message(sink, cid, cmd::ERROR, &[code]);
+}
+
+/// An `ATTEST` request waiting for the button
+struct Pending {
+    cid: u32,
+    started_ms: u64,
+    last_keepalive_ms: u64,
+}
+
+/// CTAPHID protocol state: channels, reassembly, pending presence request
+#[derive(Default)]
+pub struct CtapHid {
+    assembler: Assembler,
+    channels: heapless::Deque<u32, MAX_CHANNELS>,
+    pending: Option<Pending>,
+}
+
+impl CtapHid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True while a host is waiting for a button press
+    pub fn presence_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Handle one received output report
+    pub fn handle_report(
+        &mut self,
+        sink: &impl ReportSink,
+        rng: &mut impl RngCore,
+        report: &[u8; REPORT_LEN],
+        now_ms: u64,
+    ) {
+        match self.assembler.push(report) {
+            None => {}
+            Some(Err(e)) => send_error(sink, e.cid, e.code),
+            Some(Ok(message)) => self.dispatch(sink, rng, message, now_ms),
+        }
+    }
+
+    /// Send keepalives for, and expire, a pending presence request
+    pub fn tick(&mut self, sink: &impl ReportSink, now_ms: u64) {
+        let Some(pending) = &mut self.pending else {
+            return;
+        };
+
+        if now_ms.saturating_sub(pending.started_ms) >= PRESENCE_TIMEOUT_MS {
+            let cid = pending.cid;
+            self.pending = None;
+            send_message(sink, cid, cmd::ATTEST, &[AttestStatus::Timeout as u8]);
+        } else if now_ms.saturating_sub(pending.last_keepalive_ms) >= KEEPALIVE_INTERVAL_MS {
+            pending.last_keepalive_ms = now_ms;
+            send_message(sink, pending.cid, cmd::KEEPALIVE, &[STATUS_UPNEEDED]);
+        }
+    }
+
+    /// Answer the pending presence request with an attestation
+    pub fn complete(&mut self, sink: &impl ReportSink, record: &AttestationRecord) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+
+        let mut body = [0u8; MAX_MESSAGE_LEN];
+        body[0] = AttestStatus::Ok as u8;
+        match postcard::to_slice(record, &mut body[1..]) {
+            Ok(encoded) => {
+                let len = 1 + encoded.len();
+                send_message(sink, pending.cid, cmd::ATTEST, &body[..len]);
+            }
+            Err(_) => send_message(
+                sink,
+                pending.cid,
+                cmd::ATTEST,
+                &[AttestStatus::Failed as u8],
+            ),
+        }
+    }
+
+    /// Answer the pending presence request with a failure
+    pub fn fail(&mut self, sink: &impl ReportSink) {
+        if let Some(pending) = self.pending.take() {
+            send_message(
+                sink,
+                pending.cid,
+                cmd::ATTEST,
+                &[AttestStatus::Failed as u8],
+            );
+        }
+    }
+
+    fn dispatch(
+        &mut self,
+        sink: &impl ReportSink,
+        rng: &mut impl RngCore,
+        message: Message,
+        now_ms: u64,
+    ) {
+        let cid = message.cid;
+
+        if message.cmd == cmd::INIT {
+            return self.init(sink, rng, &message);
+        }
+        if cid == BROADCAST_CID || !self.channels.iter().any(|&c| c == cid) {
+            return send_error(sink, cid, err::INVALID_CHANNEL);
+        }
+
+        match message.cmd {
+            cmd::PING => send_message(sink, cid, cmd::PING, &message.data),
+            cmd::WINK => send_message(sink, cid, cmd::WINK, &[]),
+            cmd::CANCEL => {
+                // CANCEL itself gets no reply; the cancelled request does
+                if self.pending.as_ref().is_some_and(|p| p.cid == cid) {
+                    self.pending = None;
+                    send_message(sink, cid, cmd::ATTEST, &[AttestStatus::Cancelled as u8]);
+                }
+            }
+            cmd::ATTEST => {
+                if !message.data.is_empty() {
+                    send_error(sink, cid, err::INVALID_LEN);
+                } else if self.pending.as_ref().is_some_and(|p| p.cid != cid) {
+                    send_error(sink, cid, err::CHANNEL_BUSY);
+                } else {
+                    self.pending = Some(Pending {
+                        cid,
+                        started_ms: now_ms,
+                        last_keepalive_ms: now_ms,
+                    });
+                    send_message(sink, cid, cmd::KEEPALIVE, &[STATUS_UPNEEDED]);
+                }
+            }
+            _ => send_error(sink, cid, err::INVALID_CMD),
+        }
+    }
+
+    fn init(&mut self, sink: &impl ReportSink, rng: &mut impl RngCore, message: &Message) {
+        let Ok(nonce) = <[u8; 8]>::try_from(&message.data[..]) else {
+            return send_error(sink, message.cid, err::INVALID_LEN);
+        };
+
+        let cid = if message.cid == BROADCAST_CID {
+            self.allocate_channel(rng)
+        } else {
+            // INIT on an existing channel resynchronizes it
+            self.assembler.abort(message.cid);
+            if self.pending.as_ref().is_some_and(|p| p.cid == message.cid) {
+                self.pending = None;
+            }
+            message.cid
+        };
+
+        let mut body = [0u8; 17];
+        body[..8].copy_from_slice(&nonce);
+        body[8..12].copy_from_slice(&cid.to_be_bytes());
+        body[12] = 2; // CTAPHID protocol version
+        body[13..16].copy_from_slice(&version_triplet());
+        body[16] = CAPABILITIES;
+        send_message(sink, message.cid, cmd::INIT, &body);
+    }
+
+    fn allocate_channel(&mut self, rng: &mut impl RngCore) -> u32 {
+        let cid = loop {
+            let cid = rng.next_u32();
+            if cid != 0 && cid != BROADCAST_CID {
+                break cid;
+            }
+        };
+
+        if self.channels.is_full() {
+            self.channels.pop_front();
+        }
+        let _ = self.channels.push_back(cid);
+        cid
+    }
+}
+
+/// Firmware major/minor/patch from the crate version
+fn version_triplet() -> [u8; 3] {
+    let parse = |s: &str| s.parse::<u8>().unwrap_or(0);
+    [
+        parse(env!("CARGO_PKG_VERSION_MAJOR")),
+        parse(env!("CARGO_PKG_VERSION_MINOR")),
+        parse(env!("CARGO_PKG_VERSION_PATCH")),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entropy::HardwareRng;
+    use crate::hal::mock::MockEntropy;
+    use core::cell::RefCell;
+
+    #[derive(Default)]
+    struct Capture(RefCell<std::vec::Vec<[u8; REPORT_LEN]>>);
+
+    impl ReportSink for Capture {
+        fn send_report(&self, report: &[u8; REPORT_LEN]) {
+            self.0.borrow_mut().push(*report);
+        }
+    }
+
+    fn collect(capture: &Capture) -> std::vec::Vec<Message> {
+        let mut assembler = Assembler::default();
+        capture
+            .0
+            .borrow()
+            .iter()
+            .filter_map(|r| assembler.push(r))
+            .map(|m| m.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_fragment_and_reassemble() {
+        let capture = Capture::default();
+        let data: std::vec::Vec<u8> = (0..200).collect();
+        send_message(&capture, 0x1234_5678, cmd::PING, &data);
+        assert_eq!(capture.0.borrow().len(), 4);
+
+        let messages = collect(&capture);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].cid, 0x1234_5678);
+        assert_eq!(&messages[0].data[..], &data[..]);
+    }
+
+    #[test]
+    fn test_init_then_attest_flow() {
+        let capture = Capture::default();
+        let rng = HardwareRng::from_source(MockEntropy(0x5a)).unwrap();
+        let mut ctap = CtapHid::new();
+
+        send_message(&capture, BROADCAST_CID, cmd::INIT, &[7; 8]);
+        let init = capture.0.borrow_mut().pop().unwrap();
+        ctap.handle_report(&capture, &mut &rng, &init, 0);
+        let reply = collect(&capture).pop().unwrap();
+        assert_eq!(reply.cmd, cmd::INIT);
+        assert_eq!(&reply.data[..8], &[7; 8]);
+        let cid = u32::from_be_bytes(reply.data[8..12].try_into().unwrap());
+        assert_eq!(cid, 0x5a5a_5a5a);
+
+        capture.0.borrow_mut().clear();
+        send_message(&capture, cid, cmd::ATTEST, &[]);
+        let attest = capture.0.borrow_mut().pop().unwrap();
+        ctap.handle_report(&capture, &mut &rng, &attest, 0);
+        assert!(ctap.presence_pending());
+        assert_eq!(collect(&capture).pop().unwrap().cmd, cmd::KEEPALIVE);
+
+        capture.0.borrow_mut().clear();
+        ctap.tick(&capture, PRESENCE_TIMEOUT_MS);
+        let reply = collect(&capture).pop().unwrap();
+        assert_eq!(reply.cmd, cmd::ATTEST);
+        assert_eq!(&reply.data[..], &[AttestStatus::Timeout as u8]);
+        assert!(!ctap.presence_pending());
+    }
+
+    #[test]
+    fn test_unallocated_channel_rejected() {
+        let capture = Capture::default();
+        let rng = HardwareRng::from_source(MockEntropy(0x5a)).unwrap();
+        let mut ctap = CtapHid::new();
+
+        send_message(&capture, 0xCAFE_F00D, cmd::PING, &[1, 2, 3]);
+        let ping = capture.0.borrow_mut().pop().unwrap();
+        ctap.handle_report(&capture, &mut &rng, &ping, 0);
+
+        let reply = collect(&capture).pop().unwrap();
+        assert_eq!(reply.cmd, cmd::ERROR);
+        assert_eq!(&reply.data[..], &[err::INVALID_CHANNEL]);
+    }
+}
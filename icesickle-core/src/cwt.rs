@@ -0,0 +1,228 @@
+//! CWT output (feature `cwt`)
+//!
+//! Renders an attestation as a CBOR Web Token (RFC 8392): a `COSE_Sign1`
+//! with EdDSA over the claims, tagged CWT, and signed with the ephemeral
+//! key. Claims use standard keys where one exists and text keys otherwise:
+//!
+//! | Key     | Claim                                                   |
+//! |---------|---------------------------------------------------------|
+//! | `6`     | `iat`: UTC seconds, only with an absolute time          |
+//! | `8`     | `cnf`: the ephemeral key as an OKP `COSE_Key`           |
+//! | `bnc`   | boot nonce (see `attestation`), unless the policy omits |
+//! | `chl`   | verifier challenge (see `challenge`), only if answered  |
+//! | `ctr`   | attestation counter                                     |
+//! | `ctx`   | application context (see `context`), only if set        |
+//! | `evt`   | postcard encoding of the event, as in the fixed line    |
+//! | `fwm`   | firmware measurement (see `attestation`), only if set   |
+//! | `pol`   | privacy policy bits (see `policy`)                      |
+//! | `src`   | `iat` source, `gps` or `rtc` (with `iat`)               |
+//! | `sup`   | presses refused by the cooldown since the last one      |
+//! | `tms`   | milliseconds since boot                                 |
+//! | `tok`   | token proof, nonce then tag (see `auth`), only if spent |
+//! | `ver`   | payload version                                         |
+//! | `stale` | the source doubted `iat` (with `iat`)                   |
+//!
+//! The claims carry every payload field, so they attest exactly what the
+//! payload does; the signature algorithm is the protected header's EdDSA,
+//! as a CWT is only made under Ed25519 (see `scheme`). There is no `iss`, `sub` or `exp`: the device has no
+//! identity, and nothing about an attestation expires. Encoding is CBOR
+//! core deterministic (shortest forms, map keys in byte order), so a
+//! verifier that re-encodes the claims gets the signed bytes back. The
+//! signature covers the COSE `Sig_structure`, which starts with the text
+//! `Signature1` and so cannot pass for a payload or another companion
+//! message.
+
+use crate::attestation::{Attestation, MAX_EVENT_LEN};
+use crate::cbor::{
+    self, bytes, head, text, FALSE, MAJOR_ARRAY, MAJOR_MAP, MAJOR_TAG, MAJOR_UINT, PROTECTED,
+    TAG_COSE_SIGN1, TRUE,
+};
+use crate::companion::{Companion, Message, Subject};
+use crate::wallclock::TimeSource;
+
+const TAG_CWT: u64 = 61;
+
+const CLAIM_IAT: u64 = 6;
+const CLAIM_CNF: u64 = 8;
+
+/// `cnf` member holding a `COSE_Key`
+const CNF_COSE_KEY: u64 = 1;
+
+/// Largest claims map (see the tests)
+const MAX_CLAIMS_LEN: usize = 352;
+
+/// Largest tagged `COSE_Sign1`
+const MAX_TOKEN_LEN: usize = MAX_CLAIMS_LEN + 80;
+
+/// Encoded claims map
+pub type Claims = heapless::Vec<u8, MAX_CLAIMS_LEN>;
+
+/// Encoded, tagged CWT
+pub type Token = heapless::Vec<u8, MAX_TOKEN_LEN>;
+
+/// Render `attestation` as a token; `None` unless it carries the CWT
+/// companion
+pub fn render(attestation: &Attestation) -> Option<Token> {
+    let signature = Companion::Cwt.find(attestation.companions())?;
+    let payload = attestation.payload_bytes();
+    let claims = claims(&Subject::of(attestation, &payload));
+
+    let mut token = Token::new();
+    head(&mut token, MAJOR_TAG, TAG_CWT);
+    head(&mut token, MAJOR_TAG, TAG_COSE_SIGN1);
+    head(&mut token, MAJOR_ARRAY, 4);
+    bytes(&mut token, &PROTECTED);
+    head(&mut token, MAJOR_MAP, 0);
+    bytes(&mut token, &claims);
+    bytes(&mut token, signature);
+    Some(token)
+}
+
+/// COSE `Sig_structure` over the claims: what the key signs
+pub(crate) fn message(subject: &Subject) -> Message {
+    cbor::sig_structure(&claims(subject))
+}
+
+/// The claims map for `subject`
+pub fn claims(subject: &Subject) -> Claims {
+    let mut event_buf = [0u8; MAX_EVENT_LEN];
+    let event = postcard::to_slice(&subject.event, &mut event_buf).map_or(&[][..], |b| &*b);
+
+    let mut out = Claims::new();
+    let mut entries = if subject.wall_clock.is_some() { 10 } else { 7 };
+    entries += u64::from(subject.challenge.is_some());
+    entries += u64::from(subject.token.is_some());
+    entries += u64::from(subject.boot_nonce.is_some());
+    entries += u64::from(subject.context.is_some());
+    entries += u64::from(subject.measurement.is_some());
+    head(&mut out, MAJOR_MAP, entries);
+    if let Some(wall) = subject.wall_clock {
+        head(&mut out, MAJOR_UINT, CLAIM_IAT);
+        head(&mut out, MAJOR_UINT, wall.unix_s);
+    }
+
+    head(&mut out, MAJOR_UINT, CLAIM_CNF);
+    head(&mut out, MAJOR_MAP, 1);
+    head(&mut out, MAJOR_UINT, CNF_COSE_KEY);
+    cbor::cose_key(&mut out, subject.public_key);
+
+    // Text keys: shorter first, then bytewise
+    if let Some(nonce) = &subject.boot_nonce {
+        text(&mut out, "bnc");
+        bytes(&mut out, nonce);
+    }
+    if let Some(challenge) = &subject.challenge {
+        text(&mut out, "chl");
+        bytes(&mut out, challenge);
+    }
+    text(&mut out, "ctr");
+    head(&mut out, MAJOR_UINT, subject.counter.into());
+    if let Some(context) = subject.context {
+        text(&mut out, "ctx");
+        bytes(&mut out, context);
+    }
+    text(&mut out, "evt");
+    bytes(&mut out, event);
+    if let Some(measurement) = &subject.measurement {
+        text(&mut out, "fwm");
+        bytes(&mut out, measurement);
+    }
+    text(&mut out, "pol");
+    head(&mut out, MAJOR_UINT, subject.policy.bits().into());
+    if let Some(wall) = subject.wall_clock {
+        text(&mut out, "src");
+        text(&mut out, source_name(wall.source));
+    }
+    text(&mut out, "sup");
+    head(&mut out, MAJOR_UINT, subject.suppressed.into());
+    text(&mut out, "tms");
+    head(&mut out, MAJOR_UINT, subject.timestamp_ms);
+    if let Some(token) = &subject.token {
+        text(&mut out, "tok");
+        bytes(&mut out, &token.to_bytes());
+    }
+    text(&mut out, "ver");
+    head(&mut out, MAJOR_UINT, subject.version.into());
+    if let Some(wall) = subject.wall_clock {
+        text(&mut out, "stale");
+        let _ = out.push(if wall.stale { TRUE } else { FALSE });
+    }
+    out
+}
+
+fn source_name(source: TimeSource) -> &'static str {
+    match source {
+        TimeSource::Gps => "gps",
+        TimeSource::Rtc => "rtc",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::AttestationEvent;
+    use crate::auth::TokenProof;
+    use crate::companion::tests::subject;
+    use crate::context::MAX_CONTEXT_LEN;
+    use crate::policy::Policy;
+    use crate::wallclock::WallTime;
+
+    #[test]
+    fn test_claims_layout() {
+        let claims = claims(&subject(b"payload"));
+        // Seven entries; `cnf` first, with the key after its three labels
+        assert_eq!(claims[..4], [0xA7, 0x08, 0xA1, 0x01]);
+        assert_eq!(claims[4..8], [0xA3, 0x01, 0x01, 0x20]);
+        assert_eq!(claims[8..12], [0x06, 0x21, 0x58, 0x20]);
+        assert_eq!(claims[12..44], [0x11; 32]);
+        assert!(claims.ends_with(b"cpol\x00csup\x00ctms\x19\x04\xD2cver\x0B"));
+
+        let message = message(&subject(b"payload"));
+        assert!(message.starts_with(b"\x84\x6ASignature1\x43\xA1\x01\x27\x40"));
+        assert!(message.ends_with(&claims));
+    }
+
+    #[test]
+    fn test_largest_claims_fit() {
+        let subject = Subject {
+            event: AttestationEvent::DataDigest {
+                gpio: u8::MAX,
+                sha256: [0xFF; 32],
+                len: u64::MAX,
+            },
+            timestamp_ms: u64::MAX,
+            counter: u32::MAX,
+            wall_clock: Some(WallTime {
+                unix_s: u64::MAX,
+                source: TimeSource::Gps,
+                stale: true,
+            }),
+            policy: Policy::from_bits(u8::MAX),
+            suppressed: u32::MAX,
+            challenge: Some([0xFF; 32]),
+            token: Some(TokenProof::from_bytes(&[0xFF; 32])),
+            boot_nonce: Some([0xFF; 16]),
+            context: Some(&[0xFF; MAX_CONTEXT_LEN]),
+            measurement: Some([0xFF; 32]),
+            ..subject(b"payload")
+        };
+        let claims = claims(&subject);
+        // Fifteen entries, the boot nonce first of the text keys, the
+        // challenge ahead of the counter, the context after it, the
+        // measurement after the event and the token between the uptime and
+        // the version
+        assert_eq!(claims[0], 0xAF);
+        let bnc = [b"cbnc\x50".as_slice(), &[0xFF; 16], b"cchl"].concat();
+        assert!(claims.windows(bnc.len()).any(|w| w == bnc));
+        let chl = [b"cchl\x58\x20".as_slice(), &[0xFF; 32], b"cctr"].concat();
+        assert!(claims.windows(chl.len()).any(|w| w == chl));
+        let ctx = [b"cctx\x58\x20".as_slice(), &[0xFF; 32], b"cevt"].concat();
+        assert!(claims.windows(ctx.len()).any(|w| w == ctx));
+        let fwm = [b"cfwm\x58\x20".as_slice(), &[0xFF; 32], b"cpol"].concat();
+        assert!(claims.windows(fwm.len()).any(|w| w == fwm));
+        let tok = [b"ctok\x58\x20".as_slice(), &[0xFF; 32], b"cver"].concat();
+        assert!(claims.windows(tok.len()).any(|w| w == tok));
+        assert!(claims.ends_with(b"estale\xF5"));
+        assert!(message(&subject).ends_with(&claims));
+    }
+}
@@ -0,0 +1,180 @@
+//! Retry queue for attestations pushed to a server
+//!
+//! With `https-push` the firmware POSTs every attestation to a verifier
+//! endpoint. Networks fail, so bodies wait here until a POST succeeds: in
+//! RAM only, up to `N` of them, oldest first. A full queue drops its oldest
+//! body to take a new one, so an endpoint that stays down loses the oldest
+//! attestations rather than the newest (the device's history keeps the last
+//! 16 either way).
+//!
+//! After a failed attempt the next waits [`FIRST_RETRY_MS`], doubling with
+//! each further failure up to [`MAX_RETRY_MS`]; a delivery resets the wait.
+//! The wait belongs to the endpoint, not the body, so dropping or
+//! discarding a body does not reset it. Each body carries a [`Ticket`], so
+//! an attempt that finishes after its body was dropped cannot remove the
+//! body behind it.
+
+/// Wait after the first failed attempt
+pub const FIRST_RETRY_MS: u64 = 1_000;
+
+/// Longest wait between attempts
+pub const MAX_RETRY_MS: u64 = 300_000;
+
+/// A queued body, across an attempt to deliver it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ticket(u32);
+
+/// Bodies waiting for delivery, and the backoff between attempts
+#[derive(Debug)]
+pub struct Delivery<T, const N: usize> {
+    queue: heapless::Deque<(Ticket, T), N>,
+    next_ticket: u32,
+    /// Wait after the last failure, 0 after a delivery
+    retry_ms: u64,
+    /// No attempt before this
+    not_before_ms: u64,
+    /// Bodies dropped or discarded undelivered this boot
+    lost: u32,
+}
+
+impl<T, const N: usize> Delivery<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            queue: heapless::Deque::new(),
+            next_ticket: 0,
+            retry_ms: 0,
+            not_before_ms: 0,
+            lost: 0,
+        }
+    }
+
+    /// Queue `body`; false if the oldest body was dropped to make room
+    pub fn push(&mut self, body: T) -> bool {
+        let kept = !self.queue.is_full();
+        if !kept {
+            self.queue.pop_front();
+            self.lost = self.lost.saturating_add(1);
+        }
+        let ticket = Ticket(self.next_ticket);
+        self.next_ticket = self.next_ticket.wrapping_add(1);
+        // Room was made above
+        let _ = self.queue.push_back((ticket, body));
+        kept
+    }
+
+    /// The oldest body, if an attempt is due at `now_ms`
+    pub fn due(&self, now_ms: u64) -> Option<(Ticket, &T)> {
+        if now_ms < self.not_before_ms {
+            return None;
+        }
+        self.queue.front().map(|(ticket, body)| (*ticket, body))
+    }
+
+    /// `ticket` was delivered: remove it, and attempt the next at once
+    pub fn delivered(&mut self, ticket: Ticket) {
+        self.remove(ticket);
+        self.retry_ms = 0;
+        self.not_before_ms = 0;
+    }
+
+    /// The endpoint refused `ticket` for good: remove it undelivered
+    pub fn discard(&mut self, ticket: Ticket) {
+        if self.remove(ticket) {
+            self.lost = self.lost.saturating_add(1);
+        }
+    }
+
+    /// An attempt failed at `now_ms`: wait before the next
+    pub fn failed(&mut self, now_ms: u64) {
+        self.retry_ms = (self.retry_ms * 2).clamp(FIRST_RETRY_MS, MAX_RETRY_MS);
+        self.not_before_ms = now_ms.saturating_add(self.retry_ms);
+    }
+
+    /// Bodies waiting
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Bodies dropped or discarded undelivered this boot
+    pub fn lost(&self) -> u32 {
+        self.lost
+    }
+
+    /// Remove the head if it is `ticket`
+    fn remove(&mut self, ticket: Ticket) -> bool {
+        let head = self.queue.front().is_some_and(|(t, _)| *t == ticket);
+        if head {
+            self.queue.pop_front();
+        }
+        head
+    }
+}
+
+impl<T, const N: usize> Default for Delivery<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_until_delivered() {
+        let mut delivery = Delivery::<u8, 4>::new();
+        assert_eq!(delivery.due(0), None);
+        assert!(delivery.push(1));
+
+        let mut now_ms = 10;
+        let mut waits = Vec::new();
+        for _ in 0..12 {
+            let (ticket, _) = delivery.due(now_ms).unwrap();
+            delivery.failed(now_ms);
+            let mut wait = 1;
+            while delivery.due(now_ms + wait).is_none() {
+                wait += 1;
+            }
+            assert_eq!(delivery.due(now_ms + wait).unwrap().0, ticket);
+            waits.push(wait);
+            now_ms += wait;
+        }
+        assert_eq!(waits[..4], [1_000, 2_000, 4_000, 8_000]);
+        assert_eq!(waits[9..], [MAX_RETRY_MS; 3]);
+
+        // A delivery sends the next straight away
+        assert!(delivery.push(2));
+        let (ticket, &body) = delivery.due(now_ms).unwrap();
+        assert_eq!(body, 1);
+        delivery.delivered(ticket);
+        assert_eq!(delivery.due(now_ms), Some((Ticket(1), &2)));
+        delivery.failed(now_ms);
+        assert!(delivery.due(now_ms + FIRST_RETRY_MS - 1).is_none());
+        assert!(delivery.due(now_ms + FIRST_RETRY_MS).is_some());
+    }
+
+    #[test]
+    fn test_full_queue_drops_oldest() {
+        let mut delivery = Delivery::<u8, 2>::new();
+        assert!(delivery.push(1));
+        assert!(delivery.push(2));
+        let (stale, _) = delivery.due(0).unwrap();
+
+        // The attempt at 1 outlives it
+        assert!(!delivery.push(3));
+        assert_eq!(delivery.len(), 2);
+        assert_eq!(delivery.lost(), 1);
+        delivery.delivered(stale);
+        delivery.discard(stale);
+        assert_eq!(delivery.due(0), Some((Ticket(1), &2)));
+
+        delivery.discard(Ticket(1));
+        assert_eq!(delivery.lost(), 2);
+        delivery.delivered(Ticket(2));
+        assert!(delivery.is_empty());
+    }
+}
@@ -0,0 +1,443 @@
+//! QR code display (feature `display`)
+//!
+//! Shows each attestation as a QR code on a 128x64 SSD1306 OLED, for
+//! scanning with a phone straight after the press. The code holds the
+//! record's compact text form (see [`AttestationRecord::compact_text`]):
+//! the payload, key and signature as base45, which a QR code stores in its
+//! alphanumeric mode. The fixed-format line would need a symbol too large
+//! for 64 rows.
+//!
+//! The largest record needs version 11 (61 modules) at error correction
+//! level L, drawn one pixel per module, which is also the largest allowed.
+//! A firmware measurement would not fit beside everything else, so the
+//! firmware refuses the `measurement` feature with this one.
+//! A lit screen does not crease or fade like paper, so the lowest level is
+//! enough, and the encoder raises it when the record leaves room. Dark
+//! modules are unlit pixels on a lit screen, and the rest of the screen is
+//! the quiet zone: at version 11 only a pixel above and two below, which
+//! phone scanners accept from a lit panel's dark bezel.
+//!
+//! [`Ssd1306::poll`] writes the frame one page (8 rows) per call, so the
+//! I2C bus it shares is never held for long, and blanks the screen
+//! [`SHOW_MS`] after the code went up.
+//!
+//! When the cooldown refuses a press, [`Ssd1306::countdown`] adds the
+//! seconds left to the right of the code, in the quiet zone the centred
+//! symbol leaves, redrawn as they fall. The code stays scannable beside it.
+//! A dark screen lights up for the countdown alone and goes dark again
+//! when it ends.
+
+use qrcodegen_no_heap::{QrCode, QrCodeEcc, Version};
+
+use crate::error::Result;
+use crate::hal::I2cBus;
+use crate::protocol::AttestationRecord;
+
+/// Screen width in pixels
+pub const WIDTH: usize = 128;
+
+/// Screen height in pixels
+pub const HEIGHT: usize = 64;
+
+/// Rows of 8 pixels, each byte one column of a page (LSB at the top)
+const PAGES: usize = HEIGHT / 8;
+
+/// A full screen in the controller's page layout
+pub type Frame = [u8; WIDTH * PAGES];
+
+/// Largest symbol drawn: 61 modules, inside 64 rows
+const MAX_VERSION: Version = Version::new(11);
+
+/// Encoder buffers for symbols up to [`MAX_VERSION`]
+const QR_BUFFER_LEN: usize = MAX_VERSION.buffer_len();
+
+/// Address with SA0 tied low (`0x3D` with SA0 high)
+pub const DEFAULT_ADDRESS: u8 = 0x3C;
+
+/// How long a code stays on screen
+pub const SHOW_MS: u64 = 60_000;
+
+/// Control byte before commands
+const COMMAND: u8 = 0x00;
+
+/// Control byte before display RAM data
+const DATA: u8 = 0x40;
+
+/// Display RAM bytes per I2C write
+const CHUNK_LEN: usize = 32;
+
+/// Power-on setup for a 128x64 panel with the internal charge pump, left
+/// dark until the first frame is written
+const INIT: [u8; 26] = [
+    COMMAND, 0xAE, // display off
+    0xD5, 0x80, // clock divide and oscillator, reset value
+    0xA8, 0x3F, // 64 rows
+    0xD3, 0x00, // no vertical offset
+    0x40, // start at RAM row 0
+    0x8D, 0x14, // charge pump on
+    0x20, 0x00, // horizontal addressing
+    0xA1, // column 127 at SEG0 (panel mounted rotated)
+    0xC8, // scan rows bottom up
+    0xDA, 0x12, // alternative COM pin layout
+    0x81, 0xCF, // contrast
+    0xD9, 0xF1, // pre-charge period
+    0xDB, 0x40, // VCOMH deselect level
+    0xA4, // show RAM contents
+    0xA6, // lit pixels are 1s
+];
+
+/// Countdown digits: 3x5 glyphs, top row first, MSB on the left
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Pixels per glyph dot
+const DIGIT_SCALE: usize = 3;
+
+/// Gap after the countdown's last digit and between its two digits
+const DIGIT_GAP: usize = 3;
+
+/// Largest countdown shown; longer waits show this until they get there
+const MAX_SECONDS: u64 = 99;
+
+/// Countdown width: two digits, each followed by a gap
+const COUNTDOWN_WIDTH: usize = 2 * (3 * DIGIT_SCALE + DIGIT_GAP);
+
+// It fits the margin right of the largest symbol (61 modules), four pixels
+// clear of it
+const _: () = assert!(COUNTDOWN_WIDTH + 4 <= (WIDTH - 61) / 2);
+
+const DISPLAY_OFF: [u8; 2] = [COMMAND, 0xAE];
+const DISPLAY_ON: [u8; 2] = [COMMAND, 0xAF];
+
+/// Render `record` as a QR code, centred; `None` if its compact text does
+/// not fit [`MAX_VERSION`], which no record reaches (see the tests)
+pub fn render(record: &AttestationRecord) -> Option<Frame> {
+    let text = record.compact_text();
+    let mut temp = [0u8; QR_BUFFER_LEN];
+    let mut modules = [0u8; QR_BUFFER_LEN];
+    let qr = QrCode::encode_text(
+        &text,
+        &mut temp,
+        &mut modules,
+        QrCodeEcc::Low,
+        Version::MIN,
+        MAX_VERSION,
+        None,
+        true,
+    )
+    .ok()?;
+
+    // All lit, so the margins are quiet zone; larger modules when they fit
+    let mut frame = [0xFF; WIDTH * PAGES];
+    let size = qr.size() as usize;
+    let scale = (HEIGHT / size).max(1);
+    let left = (WIDTH - size * scale) / 2;
+    let top = (HEIGHT - size * scale) / 2;
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let (Some(mx), Some(my)) = (
+                x.checked_sub(left).map(|x| x / scale),
+                y.checked_sub(top).map(|y| y / scale),
+            ) else {
+                continue;
+            };
+            if mx < size && my < size && qr.get_module(mx as i32, my as i32) {
+                frame[y / 8 * WIDTH + x] &= !(1 << (y % 8));
+            }
+        }
+    }
+    Some(frame)
+}
+
+/// Draw `seconds` (one or two digits) dark, right-aligned at the screen's
+/// right edge and centred vertically
+fn draw_seconds(frame: &mut Frame, seconds: u64) {
+    let top = (HEIGHT - 5 * DIGIT_SCALE) / 2;
+    let mut right = WIDTH - DIGIT_GAP;
+    let mut rest = seconds;
+    loop {
+        let left = right - 3 * DIGIT_SCALE;
+        for (row, bits) in DIGITS[(rest % 10) as usize].iter().enumerate() {
+            for col in (0..3).filter(|col| bits & (0b100 >> col) != 0) {
+                for y in top + row * DIGIT_SCALE..top + (row + 1) * DIGIT_SCALE {
+                    for x in left + col * DIGIT_SCALE..left + (col + 1) * DIGIT_SCALE {
+                        frame[y / 8 * WIDTH + x] &= !(1 << (y % 8));
+                    }
+                }
+            }
+        }
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+        right = left - DIGIT_GAP;
+    }
+}
+
+/// What a frame shows: a code or not, and the seconds of any countdown
+type View = (bool, Option<u64>);
+
+/// Nothing: the screen is dark
+const DARK: View = (false, None);
+
+/// SSD1306 on one I2C address
+#[derive(Debug)]
+pub struct Ssd1306 {
+    address: u8,
+    /// The code to show, until [`SHOW_MS`] after it went up
+    code: Option<Frame>,
+    /// When `code` went up, once it has
+    shown_ms: Option<u64>,
+    /// When the cooldown ends, while a countdown runs
+    countdown_until_ms: Option<u64>,
+    /// What `frame` shows; `None` when a new code must be written
+    view: Option<View>,
+    frame: Frame,
+    /// Next page of `frame` to write, while it is being written
+    next_page: Option<usize>,
+}
+
+impl Ssd1306 {
+    pub fn new(address: u8) -> Self {
+        Self {
+            address,
+            code: None,
+            shown_ms: None,
+            countdown_until_ms: None,
+            view: Some(DARK),
+            frame: [0; WIDTH * PAGES],
+            next_page: None,
+        }
+    }
+
+    /// Set the controller up, screen dark; false if nothing answers
+    pub fn probe(&self, bus: &mut dyn I2cBus) -> bool {
+        bus.write(self.address, &INIT).is_ok()
+    }
+
+    /// Replace whatever code is on screen with `frame`, from the next poll
+    pub fn show(&mut self, frame: Frame) {
+        self.code = Some(frame);
+        self.shown_ms = None;
+        self.view = None;
+    }
+
+    /// Count down the seconds until `until_ms` beside the code, from the
+    /// next poll
+    pub fn countdown(&mut self, until_ms: u64) {
+        self.countdown_until_ms = Some(until_ms);
+    }
+
+    /// Write one page of a pending frame, switching the screen on after
+    /// the last; start a new frame when the countdown ticks or ends, and
+    /// blank the screen once [`SHOW_MS`] has passed with nothing left on it
+    ///
+    /// A failed write leaves the page pending, to retry on the next poll.
+    pub fn poll(&mut self, bus: &mut dyn I2cBus, now_ms: u64) -> Result<()> {
+        if self
+            .shown_ms
+            .is_some_and(|shown_ms| now_ms.saturating_sub(shown_ms) >= SHOW_MS)
+        {
+            self.code = None;
+            self.shown_ms = None;
+        }
+        let seconds = self
+            .countdown_until_ms
+            .map(|until_ms| until_ms.saturating_sub(now_ms).div_ceil(1_000))
+            .filter(|&seconds| seconds > 0);
+        if seconds.is_none() {
+            self.countdown_until_ms = None;
+        }
+
+        let view = (self.code.is_some(), seconds.map(|s| s.min(MAX_SECONDS)));
+        if self.view != Some(view) {
+            if view == DARK {
+                bus.write(self.address, &DISPLAY_OFF)?;
+                self.next_page = None;
+                self.view = Some(view);
+                return Ok(());
+            }
+            // A countdown alone goes on a lit screen
+            self.frame = self.code.unwrap_or([0xFF; WIDTH * PAGES]);
+            if let Some(seconds) = view.1 {
+                draw_seconds(&mut self.frame, seconds);
+            }
+            self.next_page = Some(0);
+            self.view = Some(view);
+        }
+
+        if let Some(page) = self.next_page {
+            // Column and page range, then the page's RAM in chunks
+            let window = [COMMAND, 0x21, 0, 127, 0x22, page as u8, page as u8];
+            bus.write(self.address, &window)?;
+            for chunk in self.frame[page * WIDTH..][..WIDTH].chunks(CHUNK_LEN) {
+                let mut data = [DATA; CHUNK_LEN + 1];
+                data[1..].copy_from_slice(chunk);
+                bus.write(self.address, &data)?;
+            }
+            if page + 1 < PAGES {
+                self.next_page = Some(page + 1);
+            } else {
+                bus.write(self.address, &DISPLAY_ON)?;
+                self.next_page = None;
+                if self.code.is_some() && self.shown_ms.is_none() {
+                    self.shown_ms = Some(now_ms);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::AttestationEvent;
+    use crate::auth::TokenProof;
+    use crate::context::{Context, MAX_CONTEXT_LEN};
+    use crate::hal::mock::MockI2c;
+    use crate::policy::Policy;
+    use crate::scheme::Algorithm;
+    use crate::wallclock::{TimeSource, WallTime};
+
+    fn lit(frame: &Frame, x: usize, y: usize) -> bool {
+        frame[y / 8 * WIDTH + x] & (1 << (y % 8)) != 0
+    }
+
+    #[test]
+    fn test_largest_record_fits() {
+        let record = AttestationRecord {
+            version: u8::MAX,
+            event: AttestationEvent::DataDigest {
+                gpio: u8::MAX,
+                sha256: [0xFF; 32],
+                len: u64::MAX,
+            },
+            timestamp_ms: u64::MAX,
+            counter: u32::MAX,
+            public_key: [0xFF; 32],
+            signature: [0xFF; 64],
+            wall_clock: Some(WallTime {
+                unix_s: u64::MAX,
+                source: TimeSource::Gps,
+                stale: true,
+            }),
+            policy: Policy::from_bits(u8::MAX),
+            suppressed: u32::MAX,
+            challenge: Some([0xFF; 32]),
+            token: Some(TokenProof::from_bytes(&[0xFF; 32])),
+            boot_nonce: Some([0xFF; 16]),
+            algorithm: Algorithm::Ed25519,
+            context: Some(Context::from_slice(&[0xFF; MAX_CONTEXT_LEN]).unwrap()),
+            // The firmware refuses `measurement` with `display`
+            measurement: None,
+        };
+        let frame = render(&record).unwrap();
+
+        // The first dark pixel is the top left of the centred symbol, whose
+        // corners are 7x7 finder patterns: a dark ring, a lit ring and a
+        // dark 3x3 square
+        let first = (0..WIDTH * HEIGHT).find(|&i| !lit(&frame, i % WIDTH, i / WIDTH));
+        let (left, top) = first.map(|i| (i % WIDTH, i / WIDTH)).unwrap();
+        let size = HEIGHT - 2 * top - 1;
+        assert_eq!(WIDTH - 2 * left - 1, size);
+        assert_eq!(size, 61);
+        for (x, y) in [(left, top), (left + size - 7, top), (left, top + size - 7)] {
+            assert!(!lit(&frame, x + 6, y + 6) && !lit(&frame, x + 3, y + 3));
+            assert!(lit(&frame, x + 1, y + 1) && lit(&frame, x + 5, y + 1));
+        }
+        assert!(lit(&frame, left - 1, top) && lit(&frame, left, top - 1));
+    }
+
+    #[test]
+    fn test_pages_then_on_then_blank() {
+        let mut screen = Ssd1306::new(DEFAULT_ADDRESS);
+        let mut bus = MockI2c::default();
+        assert!(screen.probe(&mut bus));
+        assert_eq!(bus.writes[0], (DEFAULT_ADDRESS, INIT.to_vec()));
+
+        let mut frame = [0; WIDTH * PAGES];
+        frame[WIDTH * 7 + 127] = 0x80;
+        screen.show(frame);
+        for page in 0..PAGES {
+            bus.writes.clear();
+            screen.poll(&mut bus, 1_000).unwrap();
+            assert_eq!(bus.writes[0].1[5..], [page as u8, page as u8]);
+            let writes = 1 + WIDTH / CHUNK_LEN + usize::from(page == PAGES - 1);
+            assert_eq!(bus.writes.len(), writes);
+        }
+        assert_eq!(bus.writes[4].1.last(), Some(&0x80));
+        assert_eq!(bus.writes[5].1, DISPLAY_ON);
+
+        bus.writes.clear();
+        screen.poll(&mut bus, 1_000 + SHOW_MS - 1).unwrap();
+        assert!(bus.writes.is_empty());
+        screen.poll(&mut bus, 1_000 + SHOW_MS).unwrap();
+        screen.poll(&mut bus, 1_000 + 2 * SHOW_MS).unwrap();
+        assert_eq!(bus.writes, [(DEFAULT_ADDRESS, DISPLAY_OFF.to_vec())]);
+    }
+
+    #[test]
+    fn test_countdown_beside_code() {
+        let mut screen = Ssd1306::new(DEFAULT_ADDRESS);
+        let mut bus = MockI2c::default();
+        let mut code = [0xFF; WIDTH * PAGES];
+        code[0] = 0xFE;
+        screen.show(code);
+        for _ in 0..PAGES {
+            screen.poll(&mut bus, 0).unwrap();
+        }
+
+        // Top left pixel of the last digit
+        let (x, y) = (
+            WIDTH - DIGIT_GAP - 3 * DIGIT_SCALE,
+            (HEIGHT - 5 * DIGIT_SCALE) / 2,
+        );
+        screen.countdown(2_500);
+        bus.writes.clear();
+        screen.poll(&mut bus, 0).unwrap();
+        assert_eq!(bus.writes[0].1[5..], [0, 0]);
+        assert!(!lit(&screen.frame, 0, 0));
+        assert!(!lit(&screen.frame, x, y) && lit(&screen.frame, x, y + 3));
+        assert!(lit(&screen.frame, x, y + 9));
+        for _ in 1..PAGES {
+            screen.poll(&mut bus, 0).unwrap();
+        }
+
+        // 3 until a whole second has passed, then 2, then the code alone
+        bus.writes.clear();
+        screen.poll(&mut bus, 499).unwrap();
+        assert!(bus.writes.is_empty());
+        screen.poll(&mut bus, 500).unwrap();
+        assert!(!lit(&screen.frame, x, y + 9));
+        for _ in 1..PAGES {
+            screen.poll(&mut bus, 500).unwrap();
+        }
+        screen.poll(&mut bus, 2_500).unwrap();
+        assert_eq!(screen.frame, code);
+
+        // Alone, on a lit screen that goes dark when it ends
+        let mut screen = Ssd1306::new(DEFAULT_ADDRESS);
+        screen.countdown(1_000);
+        for _ in 0..PAGES {
+            screen.poll(&mut bus, 0).unwrap();
+        }
+        assert!(lit(&screen.frame, x, y) && !lit(&screen.frame, x + DIGIT_SCALE, y));
+        assert!(lit(&screen.frame, 0, 0));
+        bus.writes.clear();
+        screen.poll(&mut bus, 999).unwrap();
+        assert!(bus.writes.is_empty());
+        screen.poll(&mut bus, 1_000).unwrap();
+        assert_eq!(bus.writes, [(DEFAULT_ADDRESS, DISPLAY_OFF.to_vec())]);
+    }
+}
@@ -0,0 +1,245 @@
+//! DSSE/in-toto statement output (feature `dsse`)
+//!
+//! Renders an attestation as a DSSE envelope around an in-toto Statement v1,
+//! signed with the ephemeral key, for supply-chain pipelines that ingest
+//! in-toto attestations. The statement's subject is the encoded payload
+//! (`payload.bin`, by SHA-256); its predicate, of type [`PREDICATE_TYPE`],
+//! describes the event in JSON:
+//!
+//! ```text
+//! {"payloadVersion":12,
+//!  "event":{"type":"ButtonPress","postcard":"0000"},
+//!  "counter":7,"timestampMs":1234,
+//!  "wallClock":{"unixS":1709251140,"source":"gps","stale":false},
+//!  "policy":0,"suppressed":0,"challenge":null,"token":null,
+//!  "bootNonce":"<hex>","context":null,"measurement":null,
+//!  "algorithm":"Ed25519","publicKey":"<hex>"}
+//! ```
+//!
+//! `wallClock` is `null` without an absolute time. `postcard` is the hex of
+//! the event's encoding, as in the fixed output line. `policy` holds the
+//! privacy policy bits (see `policy`). The JSON Schema is
+//! `icesickle-verify/schemas/physical-event-v1.json`, and
+//! `icesickle_verify::dsse` reads envelopes and checks statements against
+//! it.
+//!
+//! The envelope's `keyid` is the key's `did:key` DID, and the key is also
+//! printed as a PEM `PUBLIC KEY` for tools that load one. The signature is
+//! over DSSE's pre-authentication encoding, which starts `DSSEv1`, so it
+//! cannot pass for a payload or another companion message.
+
+use core::fmt::Write;
+
+use sha2::{Digest, Sha256};
+
+use crate::attestation::{hex_encode, Attestation, MAX_EVENT_LEN};
+use crate::companion::{self, Companion, Message, Subject, MAX_MESSAGE_LEN};
+use crate::multibase;
+use crate::wallclock::TimeSource;
+
+/// Predicate type of IceSickle statements
+pub const PREDICATE_TYPE: &str = "https://github.com/AltaiNewsRadio/IceSickle/physical-event/v1";
+
+/// Envelope payload type
+pub const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// Statement type
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+
+/// Statement subject name for the payload bytes
+const SUBJECT_NAME: &str = "payload.bin";
+
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo
+const SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2A, 0x30, 0x05, 0x06, 0x03, 0x2B, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Longest statement; the PAE header fits in the rest of `MAX_MESSAGE_LEN`
+const MAX_STATEMENT_LEN: usize = 1040;
+
+/// Statement JSON
+pub type Statement = heapless::String<MAX_STATEMENT_LEN>;
+
+/// PEM public key and JSON envelope
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enveloped {
+    pub public_key: String,
+    pub envelope: String,
+}
+
+/// Render `attestation`; `None` unless it carries the DSSE companion
+pub fn render(attestation: &Attestation) -> Option<Enveloped> {
+    let signature = Companion::DsseStatement.find(attestation.companions())?;
+    let payload = attestation.payload_bytes();
+    let subject = Subject::of(attestation, &payload);
+
+    let mut spki = SPKI_PREFIX.to_vec();
+    spki.extend(subject.public_key);
+
+    Some(Enveloped {
+        public_key: format!(
+            "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+            companion::base64(&spki)
+        ),
+        envelope: format!(
+            "{{\"payloadType\":\"{PAYLOAD_TYPE}\",\"payload\":\"{}\",\
+             \"signatures\":[{{\"keyid\":\"{}\",\"sig\":\"{}\"}}]}}\n",
+            companion::base64(statement(&subject).as_bytes()),
+            multibase::did_key(subject.public_key),
+            companion::base64(signature),
+        ),
+    })
+}
+
+/// DSSE pre-authentication encoding of the statement: what the key signs
+pub(crate) fn message(subject: &Subject) -> Message {
+    let statement = statement(subject);
+    let mut pae = heapless::String::<MAX_MESSAGE_LEN>::new();
+    let _ = write!(
+        pae,
+        "DSSEv1 {} {PAYLOAD_TYPE} {} {statement}",
+        PAYLOAD_TYPE.len(),
+        statement.len()
+    );
+    pae.into_bytes()
+}
+
+/// The in-toto statement for `subject`
+///
+/// Capacity covers the longest event and largest numbers, so nothing is
+/// truncated (see the tests).
+pub fn statement(subject: &Subject) -> Statement {
+    let digest: [u8; 32] = Sha256::digest(subject.payload).into();
+    let mut event_buf = [0u8; MAX_EVENT_LEN];
+    let event = postcard::to_slice(&subject.event, &mut event_buf).map_or(&[][..], |b| &*b);
+
+    let mut out = Statement::new();
+    let _ = write!(
+        out,
+        "{{\"_type\":\"{STATEMENT_TYPE}\",\
+         \"subject\":[{{\"name\":\"{SUBJECT_NAME}\",\"digest\":{{\"sha256\":\"{}\"}}}}],\
+         \"predicateType\":\"{PREDICATE_TYPE}\",\
+         \"predicate\":{{\"payloadVersion\":{},\
+         \"event\":{{\"type\":\"{}\",\"postcard\":\"{}\"}},\
+         \"counter\":{},\"timestampMs\":{},\"wallClock\":",
+        hex_encode::<64>(&digest),
+        subject.version,
+        subject.event.name(),
+        hex_encode::<{ 2 * MAX_EVENT_LEN }>(event),
+        subject.counter,
+        subject.timestamp_ms,
+    );
+    let _ = match subject.wall_clock {
+        Some(wall) => write!(
+            out,
+            "{{\"unixS\":{},\"source\":\"{}\",\"stale\":{}}}",
+            wall.unix_s,
+            source_name(wall.source),
+            wall.stale
+        ),
+        None => write!(out, "null"),
+    };
+    let _ = write!(
+        out,
+        ",\"policy\":{},\"suppressed\":{},\"challenge\":",
+        subject.policy.bits(),
+        subject.suppressed,
+    );
+    let _ = match &subject.challenge {
+        Some(challenge) => write!(out, "\"{}\"", hex_encode::<64>(challenge)),
+        None => write!(out, "null"),
+    };
+    let _ = write!(out, ",\"token\":");
+    let _ = match &subject.token {
+        Some(token) => write!(out, "\"{}\"", hex_encode::<64>(&token.to_bytes())),
+        None => write!(out, "null"),
+    };
+    let _ = write!(out, ",\"bootNonce\":");
+    let _ = match &subject.boot_nonce {
+        Some(nonce) => write!(out, "\"{}\"", hex_encode::<32>(nonce)),
+        None => write!(out, "null"),
+    };
+    let _ = write!(out, ",\"context\":");
+    let _ = match subject.context {
+        Some(context) => write!(out, "\"{}\"", hex_encode::<64>(context)),
+        None => write!(out, "null"),
+    };
+    let _ = write!(out, ",\"measurement\":");
+    let _ = match &subject.measurement {
+        Some(measurement) => write!(out, "\"{}\"", hex_encode::<64>(measurement)),
+        None => write!(out, "null"),
+    };
+    let _ = write!(out, ",\"algorithm\":\"{}\"", subject.algorithm.name());
+    let _ = write!(
+        out,
+        ",\"publicKey\":\"{}\"}}}}",
+        hex_encode::<64>(subject.public_key)
+    );
+    out
+}
+
+fn source_name(source: TimeSource) -> &'static str {
+    match source {
+        TimeSource::Gps => "gps",
+        TimeSource::Rtc => "rtc",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::AttestationEvent;
+    use crate::auth::TokenProof;
+    use crate::companion::tests::subject;
+    use crate::context::MAX_CONTEXT_LEN;
+    use crate::policy::Policy;
+    use crate::wallclock::WallTime;
+
+    #[test]
+    fn test_statement_layout() {
+        let statement = statement(&subject(b"payload"));
+        assert!(statement.starts_with("{\"_type\":\"https://in-toto.io/Statement/v1\""));
+        assert!(statement.contains(
+            "\"event\":{\"type\":\"ButtonPress\",\"postcard\":\"0000\"},\
+             \"counter\":7,\"timestampMs\":1234,\"wallClock\":null,\"policy\":0,\"suppressed\":0,\
+             \"challenge\":null,\"token\":null,\"bootNonce\":null,\"context\":null,\
+             \"measurement\":null,\"algorithm\":\"Ed25519\","
+        ));
+        assert!(statement.ends_with("\"}}"));
+
+        let message = message(&subject(b"payload"));
+        let header = format!("DSSEv1 28 {PAYLOAD_TYPE} {} ", statement.len());
+        assert_eq!(message[..header.len()], *header.as_bytes());
+        assert_eq!(message[header.len()..], *statement.as_bytes());
+    }
+
+    #[test]
+    fn test_longest_statement_fits() {
+        let subject = Subject {
+            event: AttestationEvent::DataDigest {
+                gpio: u8::MAX,
+                sha256: [0xFF; 32],
+                len: u64::MAX,
+            },
+            timestamp_ms: u64::MAX,
+            counter: u32::MAX,
+            wall_clock: Some(WallTime {
+                unix_s: u64::MAX,
+                source: TimeSource::Gps,
+                stale: false,
+            }),
+            policy: Policy::from_bits(u8::MAX),
+            suppressed: u32::MAX,
+            challenge: Some([0xFF; 32]),
+            token: Some(TokenProof::from_bytes(&[0xFF; 32])),
+            boot_nonce: Some([0xFF; 16]),
+            context: Some(&[0xFF; MAX_CONTEXT_LEN]),
+            measurement: Some([0xFF; 32]),
+            ..subject(b"payload")
+        };
+        let statement = statement(&subject);
+        assert!(statement.ends_with("\"}}"));
+        let message = message(&subject);
+        assert!(message.ends_with(statement.as_bytes()));
+    }
+}
@@ -0,0 +1,147 @@
+//! Two-person rule
+//!
+//! For approvals no one person should be able to make alone, the firmware's
+//! `dual-control` feature signs nothing for a single press. [`DualControl`]
+//! waits for presses on two different buttons, mounted apart, within a
+//! window of each other, and the pair is signed as one `DualPress { gpio_a,
+//! gpio_b, skew_ms }`: the button pressed first, the second, and the time
+//! between them.
+//!
+//! A first press whose window runs out is dropped (and reported by
+//! [`DualControl::tick`], so the device can say so). Pressing the same
+//! button again does not complete the pair; it starts the window again.
+//! The device enforces the timing, not that two people pressed: buttons
+//! within one arm's reach defeat the rule.
+
+use crate::attestation::AttestationEvent;
+
+/// Presses on two buttons within the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DualPress {
+    /// Pressed first
+    pub gpio_a: u8,
+    /// Pressed second
+    pub gpio_b: u8,
+    /// Time from the first press to the second
+    pub skew_ms: u32,
+}
+
+impl DualPress {
+    /// The event signed for this pair
+    pub fn event(self) -> AttestationEvent {
+        AttestationEvent::DualPress {
+            gpio_a: self.gpio_a,
+            gpio_b: self.gpio_b,
+            skew_ms: self.skew_ms,
+        }
+    }
+}
+
+/// Pairs presses on different buttons
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DualControl {
+    window_ms: u64,
+    /// The press waiting for its partner, and when it came
+    first: Option<(u8, u64)>,
+}
+
+impl DualControl {
+    /// Pair presses at most `window_ms` apart
+    pub const fn new(window_ms: u64) -> Self {
+        Self {
+            window_ms,
+            first: None,
+        }
+    }
+
+    /// A button went down at `now_ms` (debounced); the pair it completes
+    pub fn press(&mut self, gpio: u8, now_ms: u64) -> Option<DualPress> {
+        match self.first {
+            Some((gpio_a, at_ms))
+                if gpio_a != gpio && now_ms.saturating_sub(at_ms) <= self.window_ms =>
+            {
+                self.first = None;
+                let skew_ms = now_ms.saturating_sub(at_ms);
+                Some(DualPress {
+                    gpio_a,
+                    gpio_b: gpio,
+                    skew_ms: u32::try_from(skew_ms).unwrap_or(u32::MAX),
+                })
+            }
+            // Alone, again, or too late: this press waits instead
+            _ => {
+                self.first = Some((gpio, now_ms));
+                None
+            }
+        }
+    }
+
+    /// Call on every poll: the GPIO of a first press whose window has
+    /// passed at `now_ms`, now dropped
+    pub fn tick(&mut self, now_ms: u64) -> Option<u8> {
+        match self.first {
+            Some((gpio, at_ms)) if now_ms.saturating_sub(at_ms) > self.window_ms => {
+                self.first = None;
+                Some(gpio)
+            }
+            _ => None,
+        }
+    }
+
+    /// No press waiting for its partner
+    pub fn is_idle(&self) -> bool {
+        self.first.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW_MS: u64 = 3_000;
+
+    #[test]
+    fn test_two_buttons_within_window() {
+        let mut dual = DualControl::new(WINDOW_MS);
+        assert_eq!(dual.press(3, 1_000), None);
+        assert!(!dual.is_idle());
+        assert_eq!(dual.tick(1_000 + WINDOW_MS), None);
+        let pair = dual.press(46, 1_000 + WINDOW_MS).unwrap();
+        assert_eq!(
+            pair.event(),
+            AttestationEvent::DualPress {
+                gpio_a: 3,
+                gpio_b: 46,
+                skew_ms: 3_000
+            }
+        );
+        assert!(dual.is_idle());
+
+        // Nothing is left over for a third press to pair with
+        assert_eq!(dual.press(3, 4_500), None);
+    }
+
+    #[test]
+    fn test_lone_and_repeated_presses_sign_nothing() {
+        let mut dual = DualControl::new(WINDOW_MS);
+        assert_eq!(dual.press(3, 1_000), None);
+        assert_eq!(dual.tick(1_001 + WINDOW_MS), Some(3));
+        assert!(dual.is_idle());
+        assert_eq!(dual.tick(10_000), None);
+
+        // The same button twice restarts the window
+        assert_eq!(dual.press(3, 20_000), None);
+        assert_eq!(dual.press(3, 22_000), None);
+        assert_eq!(dual.tick(20_001 + WINDOW_MS), None);
+        assert_eq!(
+            dual.press(46, 22_000 + WINDOW_MS).map(|pair| pair.skew_ms),
+            Some(3_000)
+        );
+
+        // Too late, with no tick in between: the late press waits in turn
+        assert_eq!(dual.press(3, 30_000), None);
+        assert_eq!(dual.press(46, 30_001 + WINDOW_MS), None);
+        assert_eq!(dual.tick(34_000), None);
+        assert!(dual.press(0, 34_000).is_some());
+    }
+}
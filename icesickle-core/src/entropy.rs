@@ -0,0 +1,609 @@
+//! Hardware Random Number Generator wrapper
+//!
+//! The ESP32-S3 has a true hardware RNG that sources entropy from:
+//! - RF noise (when WiFi/BT enabled)
+//! - Thermal noise (always available)
+//!
+//! We use the ESP-IDF `esp_fill_random()` which handles the underlying
+//! hardware and provides cryptographically suitable randomness.
+//!
+//! IMPORTANT: We disable WiFi/BT in this project, so entropy comes solely
+//! from thermal noise. This is still considered cryptographically secure
+//! per Espressif documentation, but the rate is lower. Only the firmware's
+//! opt-in `ble` feature turns a radio on.
+//!
+//! The ESP source itself (`EspEntropy`) lives in the firmware crate, and
+//! other boards' RNG drivers come in through `hal::embedded::RngEntropy`;
+//! this wrapper only adds the sanity checks, the health tests and
+//! `rand_core` glue.
+//!
+//! # Continuous Health Tests
+//!
+//! Every draw from [`HardwareRng`] also runs the two continuous tests of
+//! NIST SP 800-90B (section 4.4) over as many raw bytes from the generator:
+//! the repetition count test, which catches a generator stuck on one value,
+//! and the adaptive proportion test, which catches one value coming up far
+//! too often within a window. Cutoffs assume 4 bits of min-entropy per byte,
+//! well under what the TRNG delivers, with a false-alarm rate of 2^-20 per
+//! test. A failure is latched until reset: the bytes already drawn are still
+//! handed out (`rand_core` has no way to refuse), but [`HardwareRng::check`]
+//! fails from then on, so `Attestation::create` refuses rather than sign
+//! with a key from a broken generator. The failure is counted in
+//! `telemetry`, and heartbeats report `entropy_ok: false`.
+//!
+//! With the `test-vectors` feature, [`SeededEntropy`] stands in for the
+//! hardware: a ChaCha20 stream from a fixed seed, so the simulator and HIL
+//! runs produce byte-identical keys and signatures (given the same clock and
+//! counter). Every key it produces is known to anyone with the seed. The
+//! feature refuses to build with optimisations (`debug_assertions` off), and
+//! the firmware drops `EspEntropy` when it is on, so a test-vector image can
+//! never be mistaken for, or linked into, a production one.
+//!
+//! [`MixedEntropy`] hashes a seed from a second generator (an ATECC608, see
+//! `atecc`) into everything the primary one produces, for those who would
+//! rather not trust one vendor's TRNG alone. Health checks keep sampling
+//! the primary directly (`EntropySource::fill_raw`).
+//!
+//! [`Drbg`] goes further: an HKDF-SHA256 generator keyed from the primary,
+//! which absorbs fresh primary bytes into every request and is reseeded
+//! from any number of secondary sources (in the firmware, ADC noise on a
+//! floating pin and timer jitter; see its `noise` module). Output stays
+//! unpredictable while any one input is, and a key drawn from it says
+//! nothing about the keys before it. The raw generator stays reachable
+//! through `fill_raw`, for the health tests and for comparison.
+
+use core::cell::{Cell, RefCell};
+
+use hkdf::Hkdf;
+#[cfg(feature = "test-vectors")]
+use rand_chacha::ChaCha20Rng;
+#[cfg(feature = "test-vectors")]
+use rand_core::SeedableRng;
+use rand_core::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use crate::error::IceSickleError;
+use crate::hal::EntropySource;
+use crate::telemetry::{self, Counter};
+
+/// Consecutive identical bytes that fail the repetition count test:
+/// 1 + ⌈20 / H⌉ for H = 4 bits per byte
+pub const RCT_CUTOFF: u32 = 6;
+
+/// Window of the adaptive proportion test, in bytes (non-binary samples)
+pub const APT_WINDOW: u32 = 512;
+
+/// Occurrences of a window's first byte within the window that fail the
+/// adaptive proportion test (SP 800-90B table 2, H = 4)
+pub const APT_CUTOFF: u32 = 62;
+
+/// SP 800-90B continuous health tests over a stream of raw bytes
+///
+/// State carries across calls, so runs and windows span draws. Once
+/// either test fails, the failure stays latched.
+#[derive(Debug, Default)]
+pub struct HealthTests {
+    /// Repetition count test: the last byte and how often in a row
+    last: Cell<u8>,
+    run: Cell<u32>,
+    /// Adaptive proportion test: the window's first byte, its count so
+    /// far and the bytes seen in the window (0 = a new window starts)
+    first: Cell<u8>,
+    count: Cell<u32>,
+    seen: Cell<u32>,
+    failed: Cell<bool>,
+}
+
+impl HealthTests {
+    pub const fn new() -> Self {
+        Self {
+            last: Cell::new(0),
+            run: Cell::new(0),
+            first: Cell::new(0),
+            count: Cell::new(0),
+            seen: Cell::new(0),
+            failed: Cell::new(false),
+        }
+    }
+
+    /// Run both tests over `samples`; false once either has failed
+    pub fn feed(&self, samples: &[u8]) -> bool {
+        for &sample in samples {
+            if self.run.get() > 0 && sample == self.last.get() {
+                self.run.set(self.run.get() + 1);
+                if self.run.get() >= RCT_CUTOFF {
+                    self.failed.set(true);
+                }
+            } else {
+                self.last.set(sample);
+                self.run.set(1);
+            }
+
+            if self.seen.get() == 0 {
+                self.first.set(sample);
+                self.count.set(1);
+            } else if sample == self.first.get() {
+                self.count.set(self.count.get() + 1);
+                if self.count.get() >= APT_CUTOFF {
+                    self.failed.set(true);
+                }
+            }
+            self.seen.set((self.seen.get() + 1) % APT_WINDOW);
+        }
+        !self.failed.get()
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed.get()
+    }
+}
+
+/// Hardware RNG backed by a true random number generator
+pub struct HardwareRng<S> {
+    source: S,
+    health: HealthTests,
+}
+
+impl<S: EntropySource> HardwareRng<S> {
+    /// Wrap an entropy source after a basic sanity check
+    pub fn from_source(source: S) -> crate::Result<Self> {
+        // Verify RNG is functional by reading a test value
+        let mut test = [0u8; 4];
+
+        source.fill_raw(&mut test);
+
+        // Basic sanity check (not all zeros - would indicate RNG failure)
+        if test == [0, 0, 0, 0] {
+            return Err(IceSickleError::Rng);
+        }
+
+        Ok(Self {
+            source,
+            health: HealthTests::new(),
+        })
+    }
+
+    /// Runtime health check: a fresh sample must not be stuck at one value,
+    /// and the continuous tests must not have failed
+    ///
+    /// Cheap enough to run on every heartbeat. The sample catches a dead or
+    /// stuck generator between draws; subtler failures are left to the
+    /// continuous tests.
+    pub fn is_healthy(&self) -> bool {
+        let mut sample = [0u8; 16];
+        self.source.fill_raw(&mut sample);
+        !self.health.failed() && sample.iter().any(|&b| b != sample[0])
+    }
+
+    /// `IceSickleError::Rng` once a continuous health test has failed
+    pub fn check(&self) -> crate::Result<()> {
+        if self.health.failed() {
+            return Err(IceSickleError::Rng);
+        }
+        Ok(())
+    }
+
+    /// Fill a buffer with random bytes from hardware RNG, running the
+    /// continuous health tests over as many raw bytes
+    pub fn fill_bytes(&self, dest: &mut [u8]) {
+        self.source.fill(dest);
+
+        let mut raw = [0u8; 32];
+        for chunk in dest.chunks(raw.len()) {
+            let raw = &mut raw[..chunk.len()];
+            self.source.fill_raw(raw);
+            if self.health.failed() {
+                break;
+            }
+            if !self.health.feed(raw) {
+                telemetry::record(Counter::RngHealth);
+            }
+        }
+        raw.zeroize();
+    }
+
+    /// The wrapped source (to reseed a [`MixedEntropy`])
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+}
+
+// Implement rand_core traits for compatibility with ed25519-dalek
+impl<S: EntropySource> RngCore for HardwareRng<S> {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0u8; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        HardwareRng::fill_bytes(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// Mark as cryptographically secure
+impl<S: EntropySource> CryptoRng for HardwareRng<S> {}
+
+// Also implement for &HardwareRng so we can use shared references
+impl<S: EntropySource> RngCore for &HardwareRng<S> {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0u8; 4];
+        HardwareRng::fill_bytes(self, &mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        HardwareRng::fill_bytes(self, &mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        HardwareRng::fill_bytes(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        HardwareRng::fill_bytes(self, dest);
+        Ok(())
+    }
+}
+
+impl<S: EntropySource> CryptoRng for &HardwareRng<S> {}
+
+/// A source that absorbs bytes from a supplementary generator
+pub trait Reseed {
+    fn reseed(&self, supplement: &[u8]);
+}
+
+/// Domain separation for [`MixedEntropy`] output blocks
+const MIX_DOMAIN: &[u8] = b"IceSickle entropy mix v1";
+
+/// A primary source with a supplementary seed hashed into its output
+///
+/// Each 32-byte block is SHA-256 over the seed, a block counter and 32
+/// fresh bytes from the primary, so it is unpredictable to anyone who does
+/// not know both the primary's output and the seed. [`reseed`] absorbs new
+/// bytes from the supplementary generator into the seed. Until the first
+/// reseed the seed is zero and output is as strong as the primary alone.
+///
+/// [`reseed`]: MixedEntropy::reseed
+pub struct MixedEntropy<P> {
+    primary: P,
+    seed: RefCell<[u8; 32]>,
+    block: Cell<u64>,
+}
+
+impl<P: EntropySource> MixedEntropy<P> {
+    pub fn new(primary: P) -> Self {
+        Self {
+            primary,
+            seed: RefCell::new([0; 32]),
+            block: Cell::new(0),
+        }
+    }
+}
+
+impl<P> Reseed for MixedEntropy<P> {
+    /// Absorb bytes from the supplementary generator
+    fn reseed(&self, supplement: &[u8]) {
+        let mut seed = self.seed.borrow_mut();
+        let next: [u8; 32] = Sha256::new()
+            .chain_update(MIX_DOMAIN)
+            .chain_update(*seed)
+            .chain_update(supplement)
+            .finalize()
+            .into();
+        *seed = next;
+    }
+}
+
+impl<P: EntropySource> EntropySource for MixedEntropy<P> {
+    fn fill(&self, dest: &mut [u8]) {
+        let seed = self.seed.borrow();
+        let mut raw = [0u8; 32];
+        for chunk in dest.chunks_mut(32) {
+            self.primary.fill(&mut raw);
+            let block = self.block.get();
+            self.block.set(block.wrapping_add(1));
+            let mut out: [u8; 32] = Sha256::new()
+                .chain_update(MIX_DOMAIN)
+                .chain_update(*seed)
+                .chain_update(block.to_le_bytes())
+                .chain_update(raw)
+                .finalize()
+                .into();
+            chunk.copy_from_slice(&out[..chunk.len()]);
+            out.zeroize();
+        }
+        raw.zeroize();
+    }
+
+    fn fill_raw(&self, dest: &mut [u8]) {
+        self.primary.fill_raw(dest)
+    }
+}
+
+impl<P> Drop for MixedEntropy<P> {
+    fn drop(&mut self) {
+        self.seed.get_mut().zeroize();
+    }
+}
+
+/// HKDF info strings for [`Drbg`]: its output, its next key, a reseed
+const DRBG_OUTPUT: &[u8] = b"IceSickle DRBG v1 output";
+const DRBG_RATCHET: &[u8] = b"IceSickle DRBG v1 ratchet";
+const DRBG_RESEED: &[u8] = b"IceSickle DRBG v1 reseed";
+
+/// Most HKDF-SHA256 can expand from one key
+const MAX_EXPAND: usize = 255 * 32;
+
+/// HKDF-SHA256 generator over a primary source and secondary reseeds
+///
+/// Instantiated from 32 primary bytes. Each request is expanded from a
+/// key extracted from the current key and 32 fresh primary bytes, then the
+/// current key is replaced by a further expansion and the old one is gone:
+/// the output depends on the primary's latest bytes (prediction
+/// resistance) and on every earlier input, and past output cannot be
+/// recovered from the state (backtracking resistance). [`Reseed::reseed`]
+/// extracts supplementary bytes into the key.
+pub struct Drbg<P> {
+    primary: P,
+    key: RefCell<[u8; 32]>,
+    reseeds: Cell<u32>,
+}
+
+impl<P: EntropySource> Drbg<P> {
+    pub fn new(primary: P) -> Self {
+        let drbg = Self {
+            primary,
+            key: RefCell::new([0; 32]),
+            reseeds: Cell::new(0),
+        };
+        let mut seed = [0u8; 32];
+        drbg.primary.fill(&mut seed);
+        drbg.absorb(&seed, DRBG_RESEED);
+        seed.zeroize();
+        drbg
+    }
+
+    /// Secondary reseeds since instantiation
+    pub fn reseeds(&self) -> u32 {
+        self.reseeds.get()
+    }
+
+    /// The generator without the DRBG, for comparison
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    /// Replace the key with one extracted from it and `input`
+    fn absorb(&self, input: &[u8], info: &[u8]) {
+        let mut key = self.key.borrow_mut();
+        let hkdf = Hkdf::<Sha256>::new(Some(&key[..]), input);
+        hkdf.expand(info, &mut key[..])
+            .expect("32 bytes is within HKDF-Expand's limit");
+    }
+}
+
+impl<P: EntropySource> Reseed for Drbg<P> {
+    fn reseed(&self, supplement: &[u8]) {
+        self.absorb(supplement, DRBG_RESEED);
+        self.reseeds.set(self.reseeds.get().wrapping_add(1));
+    }
+}
+
+impl<P: EntropySource> EntropySource for Drbg<P> {
+    fn fill(&self, dest: &mut [u8]) {
+        let mut fresh = [0u8; 32];
+        self.primary.fill(&mut fresh);
+        {
+            let mut key = self.key.borrow_mut();
+            let hkdf = Hkdf::<Sha256>::new(Some(&key[..]), &fresh);
+            for (block, chunk) in (0u32..).zip(dest.chunks_mut(MAX_EXPAND)) {
+                hkdf.expand_multi_info(&[DRBG_OUTPUT, &block.to_le_bytes()], chunk)
+                    .expect("chunks are within HKDF-Expand's limit");
+            }
+            hkdf.expand(DRBG_RATCHET, &mut key[..])
+                .expect("32 bytes is within HKDF-Expand's limit");
+        }
+        fresh.zeroize();
+    }
+
+    fn fill_raw(&self, dest: &mut [u8]) {
+        self.primary.fill_raw(dest)
+    }
+}
+
+impl<P> Drop for Drbg<P> {
+    fn drop(&mut self) {
+        self.key.get_mut().zeroize();
+    }
+}
+
+/// Seed for reproducible runs; public, so nothing signed with it proves anything
+#[cfg(feature = "test-vectors")]
+pub const TEST_VECTOR_SEED: [u8; 32] = *b"IceSickle test vectors, not keys";
+
+/// Deterministic ChaCha20 entropy for test vectors (feature `test-vectors`)
+#[cfg(feature = "test-vectors")]
+pub struct SeededEntropy(RefCell<ChaCha20Rng>);
+
+#[cfg(feature = "test-vectors")]
+impl SeededEntropy {
+    pub fn new(seed: [u8; 32]) -> Self {
+        Self(RefCell::new(ChaCha20Rng::from_seed(seed)))
+    }
+}
+
+#[cfg(feature = "test-vectors")]
+impl EntropySource for SeededEntropy {
+    fn fill(&self, dest: &mut [u8]) {
+        self.0.borrow_mut().fill_bytes(dest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::MockEntropy;
+
+    #[test]
+    fn test_sanity_check_rejects_all_zeros() {
+        assert!(HardwareRng::from_source(MockEntropy(0)).is_err());
+        assert!(HardwareRng::from_source(MockEntropy(0x5a)).is_ok());
+    }
+
+    #[test]
+    fn test_health_check_flags_stuck_source() {
+        struct Counting(core::cell::Cell<u8>);
+        impl EntropySource for Counting {
+            fn fill(&self, dest: &mut [u8]) {
+                for b in dest {
+                    self.0.set(self.0.get().wrapping_add(1));
+                    *b = self.0.get();
+                }
+            }
+        }
+
+        assert!(!HardwareRng::from_source(MockEntropy(0x5a))
+            .unwrap()
+            .is_healthy());
+        let rng = HardwareRng::from_source(Counting(core::cell::Cell::new(0))).unwrap();
+        assert!(rng.is_healthy());
+    }
+
+    #[test]
+    fn test_repetition_count_test() {
+        let health = HealthTests::new();
+        assert!(health.feed(&[7; RCT_CUTOFF as usize - 1]));
+        // The run spans calls
+        assert!(health.feed(&[8, 7, 7]));
+        assert!(!health.feed(&[7; RCT_CUTOFF as usize - 2]));
+
+        // Latched
+        assert!(health.failed());
+        assert!(!health.feed(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_adaptive_proportion_test() {
+        // The window's first byte at every other position: never a run,
+        // but far too frequent
+        let alternating = |n: u32| -> Vec<u8> {
+            (0..n)
+                .map(|i| if i % 2 == 0 { 0xAA } else { i as u8 | 1 })
+                .collect()
+        };
+        let filler = |n: u32| -> Vec<u8> { (0..n).map(|i| i as u8 | 1).collect() };
+
+        let health = HealthTests::new();
+        assert!(health.feed(&alternating(2 * (APT_CUTOFF - 1) - 1)));
+        assert!(health.feed(&filler(APT_WINDOW - 2 * (APT_CUTOFF - 1) + 1)));
+        // A fresh window
+        assert!(!health.feed(&alternating(2 * APT_CUTOFF - 1)));
+    }
+
+    #[test]
+    fn test_failed_health_test_refuses_attestations() {
+        use crate::attestation::{Attestation, AttestationEvent};
+        use crate::hal::mock::{MockNoise, MockTimer};
+
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let rng = HardwareRng::from_source(MockEntropy(0x5a)).unwrap();
+        assert_eq!(rng.check(), Ok(()));
+        rng.fill_bytes(&mut [0u8; 32]);
+        assert_eq!(rng.check(), Err(IceSickleError::Rng));
+        assert!(!rng.is_healthy());
+        let attestation = Attestation::create(&rng, &MockTimer::at(1_000), event);
+        assert!(matches!(attestation, Err(IceSickleError::Rng)));
+
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        rng.fill_bytes(&mut [0u8; 4_096]);
+        assert_eq!(rng.check(), Ok(()));
+        assert!(Attestation::create(&rng, &MockTimer::at(1_000), event).is_ok());
+    }
+
+    #[test]
+    fn test_mixed_entropy() {
+        let mixed = MixedEntropy::new(MockEntropy(0x5a));
+        let fill = || {
+            let mut out = [0u8; 40];
+            mixed.fill(&mut out);
+            out
+        };
+
+        // Blocks differ even from a stuck primary, and each reseed changes
+        // the stream
+        let first = fill();
+        assert_ne!(first[..8], first[32..]);
+        mixed.reseed(&[1; 32]);
+        let second = fill();
+        assert_ne!(first, second);
+        assert_ne!(first, [0x5a; 40]);
+
+        // Health checks still see the stuck primary
+        let rng = HardwareRng::from_source(mixed).unwrap();
+        assert!(!rng.is_healthy());
+        assert!(HardwareRng::from_source(MixedEntropy::new(MockEntropy(0))).is_err());
+    }
+
+    #[test]
+    fn test_drbg() {
+        let drbg = Drbg::new(MockEntropy(0x5a));
+        let fill = |len: usize| {
+            let mut out = vec![0u8; len];
+            drbg.fill(&mut out);
+            out
+        };
+
+        // Each request differs from the last, even from a stuck primary,
+        // and output longer than one expansion does not repeat
+        let first = fill(32);
+        assert_ne!(first, fill(32));
+        let long = fill(MAX_EXPAND + 32);
+        assert_ne!(long[..32], long[MAX_EXPAND..]);
+
+        // Reseeding changes what follows
+        let twin = Drbg::new(MockEntropy(0x5a));
+        let mut reseeded = [0u8; 32];
+        twin.reseed(&[1; 32]);
+        twin.fill(&mut reseeded);
+        assert_eq!(twin.reseeds(), 1);
+        assert_ne!(first[..], reseeded[..]);
+
+        // The raw generator is still there, for the health checks
+        let rng = HardwareRng::from_source(drbg).unwrap();
+        assert!(!rng.is_healthy());
+        let mut raw = [0u8; 4];
+        rng.source().primary().fill(&mut raw);
+        assert_eq!(raw, [0x5a; 4]);
+    }
+
+    #[cfg(feature = "test-vectors")]
+    #[test]
+    fn test_seeded_entropy_is_reproducible() {
+        use crate::attestation::{Attestation, AttestationEvent};
+        use crate::hal::mock::MockTimer;
+
+        let sign = |seed| {
+            let rng = HardwareRng::from_source(SeededEntropy::new(seed)).unwrap();
+            let event = AttestationEvent::ButtonPress { gpio: 0 };
+            let attestation = Attestation::create(&rng, &MockTimer::at(1_000), event).unwrap();
+            *attestation.public_key_bytes()
+        };
+        assert_eq!(sign(TEST_VECTOR_SEED), sign(TEST_VECTOR_SEED));
+        assert_ne!(sign(TEST_VECTOR_SEED), sign([0; 32]));
+    }
+}
@@ -0,0 +1,105 @@
+//! Crate-wide error type
+//!
+//! One flat enum instead of `anyhow`, so callers can branch on the class of
+//! failure: the firmware maps classes to LED blink codes and to signed
+//! failure reports, and none of that works on an opaque error. Variants
+//! carry only plain data - never a message string, never anything derived
+//! from key material.
+
+use core::fmt;
+
+/// Error classes surfaced by IceSickle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IceSickleError {
+    /// Hardware RNG failed its sanity check
+    Rng,
+    /// GPIO or other peripheral driver failure (ESP-IDF `esp_err_t`)
+    Gpio(i32),
+    /// A payload or frame could not be encoded into its buffer
+    Serialize,
+    /// Attestation refused while the cooldown runs
+    Cooldown { remaining_ms: u64 },
+    /// Authorization token missing or rejected
+    Token,
+    /// An output channel could not accept data
+    Sink,
+    /// Redundant security check disagreed with itself (fault injection)
+    Glitch,
+    /// An external sensor answered wrongly (bad ID, checksum or no data)
+    Sensor,
+    /// Every crypto workspace slot is held by another signer; retry later
+    Busy,
+}
+
+/// Result alias used throughout IceSickle
+pub type Result<T> = core::result::Result<T, IceSickleError>;
+
+impl IceSickleError {
+    /// Stable numeric code for the error class (LED blink count, reports)
+    ///
+    /// Codes are part of the device's external interface: never renumber.
+    pub fn code(&self) -> u8 {
+        match self {
+            IceSickleError::Rng => 1,
+            IceSickleError::Gpio(_) => 2,
+            IceSickleError::Serialize => 3,
+            IceSickleError::Cooldown { .. } => 4,
+            IceSickleError::Token => 5,
+            IceSickleError::Sink => 6,
+            IceSickleError::Glitch => 7,
+            IceSickleError::Sensor => 8,
+            IceSickleError::Busy => 9,
+        }
+    }
+}
+
+impl fmt::Display for IceSickleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IceSickleError::Rng => f.write_str("hardware RNG sanity check failed"),
+            IceSickleError::Gpio(code) => write!(f, "peripheral driver error {}", code),
+            IceSickleError::Serialize => f.write_str("encoding does not fit buffer"),
+            IceSickleError::Cooldown { remaining_ms } => {
+                write!(f, "cooldown active ({}ms remaining)", remaining_ms)
+            }
+            IceSickleError::Token => f.write_str("authorization token rejected"),
+            IceSickleError::Sink => f.write_str("output channel unavailable"),
+            IceSickleError::Glitch => f.write_str("control-flow integrity check failed"),
+            IceSickleError::Sensor => f.write_str("sensor returned invalid data"),
+            IceSickleError::Busy => f.write_str("no crypto workspace free"),
+        }
+    }
+}
+
+impl std::error::Error for IceSickleError {}
+
+impl From<postcard::Error> for IceSickleError {
+    fn from(_: postcard::Error) -> Self {
+        IceSickleError::Serialize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_distinct() {
+        let all = [
+            IceSickleError::Rng,
+            IceSickleError::Gpio(-1),
+            IceSickleError::Serialize,
+            IceSickleError::Cooldown { remaining_ms: 1 },
+            IceSickleError::Token,
+            IceSickleError::Sink,
+            IceSickleError::Glitch,
+            IceSickleError::Sensor,
+            IceSickleError::Busy,
+        ];
+        for (i, a) in all.iter().enumerate() {
+            for b in &all[i + 1..] {
+                assert_ne!(a.code(), b.code());
+            }
+        }
+    }
+}
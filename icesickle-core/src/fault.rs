@@ -0,0 +1,189 @@
+//! Fault records
+//!
+//! Every fatal exit (a panic, an error escaping the event loop, a task
+//! watchdog timeout or a stack overflow) scrubs secrets and resets (see the
+//! firmware's `fatal.rs`). The console is not drained on the way down, and
+//! the watchdog and overflow hooks cannot log at all, so the fatal path
+//! keeps a [`Fault`] in RTC memory, which survives the reset, and the next
+//! boot prints it as one line:
+//!
+//! ```text
+//! FAULT <kind> <code> <uptime_ms>
+//! ```
+//!
+//! `kind` is `panic`, `error`, `watchdog` or `stack-overflow`; `code` is the
+//! error's code (see [`IceSickleError::code`]) or the panic's source line,
+//! and 0 otherwise; `uptime_ms` is when the previous run died. Like the
+//! fatal log, the record carries no message or value: either could be
+//! derived from key material.
+//!
+//! [`IceSickleError::code`]: crate::IceSickleError::code
+
+use core::fmt::Write;
+
+/// Marks a stored record; RTC memory holds noise after a power-on
+const MAGIC: u32 = 0x464C_5431; // "FLT1"
+
+/// Words a record is stored in (see [`Fault::to_words`])
+pub const FAULT_WORDS: usize = 6;
+
+/// Fault line (see module docs)
+pub type FaultLine = heapless::String<64>;
+
+/// How the previous run died
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    Panic = 0,
+    Error = 1,
+    Watchdog = 2,
+    StackOverflow = 3,
+}
+
+impl FaultKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            FaultKind::Panic => "panic",
+            FaultKind::Error => "error",
+            FaultKind::Watchdog => "watchdog",
+            FaultKind::StackOverflow => "stack-overflow",
+        }
+    }
+
+    fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(FaultKind::Panic),
+            1 => Some(FaultKind::Error),
+            2 => Some(FaultKind::Watchdog),
+            3 => Some(FaultKind::StackOverflow),
+            _ => None,
+        }
+    }
+}
+
+/// A fatal exit (see module docs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    pub kind: FaultKind,
+    pub code: u32,
+    pub uptime_ms: u64,
+}
+
+impl Fault {
+    /// The record as stored in RTC memory: the magic, the fields, then a
+    /// check word
+    pub fn to_words(&self) -> [u32; FAULT_WORDS] {
+        let mut words = [
+            MAGIC,
+            self.kind as u32,
+            self.code,
+            self.uptime_ms as u32,
+            (self.uptime_ms >> 32) as u32,
+            0,
+        ];
+        words[FAULT_WORDS - 1] = check(&words);
+        words
+    }
+
+    /// Parse stored words; `None` if they do not hold a record
+    pub fn from_words(words: &[u32; FAULT_WORDS]) -> Option<Self> {
+        if words[0] != MAGIC || words[FAULT_WORDS - 1] != check(words) {
+            return None;
+        }
+        Some(Self {
+            kind: FaultKind::from_code(words[1])?,
+            code: words[2],
+            uptime_ms: u64::from(words[3]) | (u64::from(words[4]) << 32),
+        })
+    }
+
+    /// The fault line, with its newline
+    pub fn fixed_line(&self) -> FaultLine {
+        let mut line = FaultLine::new();
+        // The longest line is 53 bytes
+        let _ = writeln!(
+            line,
+            "FAULT {} {} {}",
+            self.kind.name(),
+            self.code,
+            self.uptime_ms
+        );
+        line
+    }
+
+    /// Parse a fault line; the trailing newline is optional
+    pub fn from_fixed_line(line: &str) -> Option<Self> {
+        let mut fields = line.trim_end().split(' ');
+        if fields.next()? != "FAULT" {
+            return None;
+        }
+        let kind = match fields.next()? {
+            "panic" => FaultKind::Panic,
+            "error" => FaultKind::Error,
+            "watchdog" => FaultKind::Watchdog,
+            "stack-overflow" => FaultKind::StackOverflow,
+            _ => return None,
+        };
+        let code = fields.next()?.parse().ok()?;
+        let uptime_ms = fields.next()?.parse().ok()?;
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            kind,
+            code,
+            uptime_ms,
+        })
+    }
+}
+
+/// XOR of every word but the last, which is where it is kept
+fn check(words: &[u32; FAULT_WORDS]) -> u32 {
+    words[..FAULT_WORDS - 1]
+        .iter()
+        .fold(!MAGIC, |acc, w| acc ^ w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_line_round_trips() {
+        let fault = Fault {
+            kind: FaultKind::StackOverflow,
+            code: u32::MAX,
+            uptime_ms: u64::MAX,
+        };
+        let line = fault.fixed_line();
+        assert_eq!(
+            line,
+            "FAULT stack-overflow 4294967295 18446744073709551615\n"
+        );
+        assert_eq!(Fault::from_fixed_line(&line), Some(fault));
+
+        assert_eq!(Fault::from_fixed_line("FAULT reboot 0 0"), None);
+        assert_eq!(Fault::from_fixed_line("FAULT panic 0"), None);
+        assert_eq!(Fault::from_fixed_line("ATT panic 0 0"), None);
+    }
+
+    #[test]
+    fn test_stored_words_are_checked() {
+        let fault = Fault {
+            kind: FaultKind::Watchdog,
+            code: 0,
+            uptime_ms: (1 << 40) | 1_234,
+        };
+        let words = fault.to_words();
+        assert_eq!(Fault::from_words(&words), Some(fault));
+
+        // Noise left in RTC memory by a power-on, or a damaged record
+        assert_eq!(Fault::from_words(&[0; FAULT_WORDS]), None);
+        let mut damaged = words;
+        damaged[2] ^= 1;
+        assert_eq!(Fault::from_words(&damaged), None);
+        let mut unknown = words;
+        unknown[1] = 9;
+        unknown[FAULT_WORDS - 1] = check(&unknown);
+        assert_eq!(Fault::from_words(&unknown), None);
+    }
+}
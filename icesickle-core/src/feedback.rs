@@ -0,0 +1,209 @@
+//! Status LED and buzzer patterns
+//!
+//! The firmware's status LED and optional piezo (feature `feedback`) show
+//! what the event loop just did, so an operator standing at the device
+//! knows whether a press counted:
+//!
+//! - `Signing`: LED solid until the next signal, buzzer silent
+//! - `Success`: two short flashes, a chirp with the first
+//! - `Cooldown` (a press refused): three fast flickers over one long buzz,
+//!   then a short blip with each whole second left, the last one second
+//!   before the next press can count
+//! - `Fault(error)`: `error.code()` blinks and a pause, repeated; the
+//!   buzzer sounds with the first round's blinks only
+//!
+//! `Fault` repeats until reset, matching the state machine's absorbing
+//! `Fault` state; an RNG health failure blinks once per round. This module
+//! only computes levels; [`Player::output`] is polled by the firmware, which
+//! drives the pins.
+
+use crate::error::IceSickleError;
+
+/// Success flash and gap length
+const FLASH_MS: u64 = 100;
+
+/// Refused-press flicker and gap length
+const FLICKER_MS: u64 = 60;
+
+/// Refused-press buzz length
+const BUZZ_MS: u64 = 300;
+
+/// Countdown blip length, once per second left
+const TICK_MS: u64 = 50;
+
+/// Fault blink and gap length
+const BLINK_MS: u64 = 250;
+
+/// Dark pause between rounds of a fault code
+const PAUSE_MS: u64 = 1_500;
+
+/// Something the operator should see
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// A press was accepted; the key exists
+    Signing,
+    /// The attestation was written out
+    Success,
+    /// A press was refused by the cooldown, which allows the next one
+    /// `remaining_ms` later
+    Cooldown { remaining_ms: u64 },
+    /// The device stopped on `error` (RNG health, signing failure)
+    Fault(IceSickleError),
+}
+
+/// Pin levels at one instant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Output {
+    pub led: bool,
+    pub tone: bool,
+}
+
+impl Output {
+    pub const DARK: Output = Output {
+        led: false,
+        tone: false,
+    };
+}
+
+impl Signal {
+    /// Levels `elapsed_ms` into the pattern, or `None` once it has ended
+    pub fn output(self, elapsed_ms: u64) -> Option<Output> {
+        match self {
+            Signal::Signing => Some(Output {
+                led: true,
+                tone: false,
+            }),
+            Signal::Success => (elapsed_ms < 4 * FLASH_MS).then_some(Output {
+                led: (elapsed_ms / FLASH_MS).is_multiple_of(2),
+                tone: elapsed_ms < FLASH_MS,
+            }),
+            Signal::Cooldown { remaining_ms } => {
+                let refused_ms = BUZZ_MS.max(6 * FLICKER_MS);
+                (elapsed_ms < refused_ms.max(remaining_ms)).then(|| {
+                    // Time since the seconds left last fell to a whole number
+                    let left_ms = remaining_ms.saturating_sub(elapsed_ms);
+                    let tick_ms = (1_000 - left_ms % 1_000) % 1_000;
+                    Output {
+                        led: if elapsed_ms < 6 * FLICKER_MS {
+                            (elapsed_ms / FLICKER_MS).is_multiple_of(2)
+                        } else {
+                            left_ms > 0 && tick_ms < TICK_MS
+                        },
+                        tone: elapsed_ms < BUZZ_MS,
+                    }
+                })
+            }
+            Signal::Fault(error) => {
+                let blinks = 2 * BLINK_MS * u64::from(error.code());
+                let round = blinks + PAUSE_MS;
+                let at = elapsed_ms % round;
+                let on = at < blinks && (at / BLINK_MS).is_multiple_of(2);
+                Some(Output {
+                    led: on,
+                    tone: on && elapsed_ms < round,
+                })
+            }
+        }
+    }
+}
+
+/// The signal being shown and when it started
+#[derive(Debug, Clone, Default)]
+pub struct Player {
+    current: Option<(Signal, u64)>,
+}
+
+impl Player {
+    pub const fn new() -> Self {
+        Self { current: None }
+    }
+
+    /// Replace whatever is showing with `signal`, from `now_ms`
+    ///
+    /// A fault is never replaced: only a reset leaves it.
+    pub fn play(&mut self, signal: Signal, now_ms: u64) {
+        if !matches!(self.current, Some((Signal::Fault(_), _))) {
+            self.current = Some((signal, now_ms));
+        }
+    }
+
+    /// Levels at `now_ms`; dark once a one-shot signal has ended
+    pub fn output(&mut self, now_ms: u64) -> Output {
+        let Some((signal, since_ms)) = self.current else {
+            return Output::DARK;
+        };
+        match signal.output(now_ms.saturating_sub(since_ms)) {
+            Some(output) => output,
+            None => {
+                self.current = None;
+                Output::DARK
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_success_ends_dark() {
+        let mut player = Player::new();
+        assert_eq!(player.output(0), Output::DARK);
+        player.play(Signal::Signing, 1_000);
+        assert!(player.output(60_000).led);
+        player.play(Signal::Success, 1_000);
+        let start = player.output(1_000);
+        assert!(start.led && start.tone);
+        let gap = player.output(1_000 + FLASH_MS);
+        assert!(!gap.led && !gap.tone);
+        assert!(player.output(1_000 + 2 * FLASH_MS).led);
+        assert_eq!(player.output(1_000 + 4 * FLASH_MS), Output::DARK);
+        assert_eq!(player.output(2_000), Output::DARK);
+    }
+
+    #[test]
+    fn test_cooldown_differs_from_success() {
+        let refused = Signal::Cooldown { remaining_ms: 0 };
+        for elapsed in [0, FLICKER_MS, 2 * FLICKER_MS, BUZZ_MS - 1] {
+            assert!(refused.output(elapsed).unwrap().tone);
+        }
+        assert!(!Signal::Success.output(FLASH_MS).unwrap().tone);
+        assert_eq!(refused.output(BUZZ_MS.max(6 * FLICKER_MS)), None);
+    }
+
+    #[test]
+    fn test_cooldown_blips_seconds_left() {
+        // Refused with 3.5 s to go: blips with 3, 2 and 1 s left, then dark
+        let signal = Signal::Cooldown {
+            remaining_ms: 3_500,
+        };
+        let blips: Vec<u64> = (6 * FLICKER_MS..3_500)
+            .filter(|&t| signal.output(t).unwrap().led && !signal.output(t - 1).unwrap().led)
+            .collect();
+        assert_eq!(blips, [500, 1_500, 2_500]);
+        assert!(!signal.output(500 + TICK_MS).unwrap().led);
+        assert!(!signal.output(BUZZ_MS).unwrap().tone);
+        assert_eq!(signal.output(3_500), None);
+    }
+
+    #[test]
+    fn test_fault_blinks_code_and_sticks() {
+        let error = IceSickleError::Sink;
+        let blinks = (0..20_000)
+            .step_by(BLINK_MS as usize)
+            .take_while(|&t| t < 2 * BLINK_MS * 6 + PAUSE_MS)
+            .filter(|&t| Signal::Fault(error).output(t).unwrap().led)
+            .count();
+        assert_eq!(blinks, usize::from(error.code()));
+
+        let mut player = Player::new();
+        player.play(Signal::Fault(IceSickleError::Rng), 0);
+        player.play(Signal::Success, 10);
+        assert!(player.output(10).led);
+        assert_eq!(player.output(2 * BLINK_MS + PAUSE_MS - 1), Output::DARK);
+        // Later rounds blink silently
+        let second = player.output(2 * BLINK_MS + PAUSE_MS);
+        assert!(second.led && !second.tone);
+    }
+}
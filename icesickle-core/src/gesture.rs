@@ -0,0 +1,193 @@
+//! Button gestures
+//!
+//! One button, one event is limiting. A [`Recognizer`] turns a button's
+//! debounced edges into one of three gestures, each signed as its own
+//! event (firmware feature `gestures`):
+//!
+//! - a short press, released within [`HOLD_MS`] and not followed by another
+//!   press within [`DOUBLE_MS`]: `ButtonPress { gpio }`
+//! - a long press, held for [`HOLD_MS`] or more: `ButtonHold { gpio,
+//!   duration_ms }`, reported on release with how long it was held
+//! - a double press, a second press starting within [`DOUBLE_MS`] of a short
+//!   press's release: `ButtonDoublePress { gpio }`, reported as the second
+//!   press starts
+//!
+//! A short press is only known to be single once the double-press window
+//! has passed, so with gestures a press is signed [`DOUBLE_MS`] after its
+//! release rather than as it starts.
+
+use crate::attestation::AttestationEvent;
+
+/// Shortest long press
+pub const HOLD_MS: u64 = 1_500;
+
+/// Longest gap between the presses of a double press
+pub const DOUBLE_MS: u64 = 400;
+
+/// What a button did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    Press,
+    Hold { duration_ms: u32 },
+    DoublePress,
+}
+
+impl Gesture {
+    /// The event signed for this gesture on `gpio`
+    pub fn event(self, gpio: u8) -> AttestationEvent {
+        match self {
+            Gesture::Press => AttestationEvent::ButtonPress { gpio },
+            Gesture::Hold { duration_ms } => AttestationEvent::ButtonHold { gpio, duration_ms },
+            Gesture::DoublePress => AttestationEvent::ButtonDoublePress { gpio },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    /// Pressed at `since_ms`
+    Down {
+        since_ms: u64,
+    },
+    /// A short press released at `at_ms`; a second may follow
+    Released {
+        at_ms: u64,
+    },
+    /// The second press of a double press, already reported
+    SecondDown,
+}
+
+/// Gesture state of one button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recognizer {
+    state: State,
+}
+
+impl Recognizer {
+    pub const fn new() -> Self {
+        Self { state: State::Idle }
+    }
+
+    /// The button went down at `now_ms` (debounced)
+    pub fn press(&mut self, now_ms: u64) -> Option<Gesture> {
+        match self.state {
+            State::Released { at_ms } if now_ms.saturating_sub(at_ms) < DOUBLE_MS => {
+                self.state = State::SecondDown;
+                Some(Gesture::DoublePress)
+            }
+            // Missed tick: the first press was single after all
+            State::Released { .. } => {
+                self.state = State::Down { since_ms: now_ms };
+                Some(Gesture::Press)
+            }
+            _ => {
+                self.state = State::Down { since_ms: now_ms };
+                None
+            }
+        }
+    }
+
+    /// The button came up at `now_ms` (debounced)
+    pub fn release(&mut self, now_ms: u64) -> Option<Gesture> {
+        match self.state {
+            State::Down { since_ms } => {
+                let held = now_ms.saturating_sub(since_ms);
+                if held >= HOLD_MS {
+                    self.state = State::Idle;
+                    let duration_ms = u32::try_from(held).unwrap_or(u32::MAX);
+                    Some(Gesture::Hold { duration_ms })
+                } else {
+                    self.state = State::Released { at_ms: now_ms };
+                    None
+                }
+            }
+            _ => {
+                self.state = State::Idle;
+                None
+            }
+        }
+    }
+
+    /// Call on every poll: a short press once its double-press window
+    /// has passed
+    pub fn tick(&mut self, now_ms: u64) -> Option<Gesture> {
+        match self.state {
+            State::Released { at_ms } if now_ms.saturating_sub(at_ms) >= DOUBLE_MS => {
+                self.state = State::Idle;
+                Some(Gesture::Press)
+            }
+            _ => None,
+        }
+    }
+
+    /// Between gestures: nothing held, nothing waiting on the clock
+    pub fn is_idle(&self) -> bool {
+        self.state == State::Idle
+    }
+}
+
+impl Default for Recognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_press_reported_after_double_window() {
+        let mut button = Recognizer::new();
+        assert_eq!(button.press(1_000), None);
+        assert_eq!(button.release(1_200), None);
+        assert_eq!(button.tick(1_200 + DOUBLE_MS - 1), None);
+        assert!(!button.is_idle());
+        assert_eq!(button.tick(1_200 + DOUBLE_MS), Some(Gesture::Press));
+        assert!(button.is_idle());
+        assert_eq!(button.tick(5_000), None);
+    }
+
+    #[test]
+    fn test_hold_reports_duration_on_release() {
+        let mut button = Recognizer::new();
+        assert_eq!(button.press(1_000), None);
+        assert_eq!(button.tick(1_000 + HOLD_MS), None);
+        assert_eq!(
+            button.release(1_000 + HOLD_MS + 250),
+            Some(Gesture::Hold { duration_ms: 1_750 })
+        );
+        assert!(button.is_idle());
+        assert_eq!(
+            Gesture::Hold { duration_ms: 1_750 }.event(3),
+            AttestationEvent::ButtonHold {
+                gpio: 3,
+                duration_ms: 1_750
+            }
+        );
+    }
+
+    #[test]
+    fn test_double_press() {
+        let mut button = Recognizer::new();
+        button.press(1_000);
+        button.release(1_100);
+        assert_eq!(
+            button.press(1_100 + DOUBLE_MS - 1),
+            Some(Gesture::DoublePress)
+        );
+        // Its release, however late, is not a gesture of its own
+        assert_eq!(button.release(5_000), None);
+        assert!(button.is_idle());
+        assert_eq!(button.tick(10_000), None);
+
+        // Too late for a double: two presses, the first reported late if
+        // no tick came in between
+        button.press(20_000);
+        button.release(20_100);
+        assert_eq!(button.press(20_100 + DOUBLE_MS), Some(Gesture::Press));
+        assert_eq!(button.release(20_200 + DOUBLE_MS), None);
+        assert_eq!(button.tick(21_000), Some(Gesture::Press));
+    }
+}
@@ -0,0 +1,375 @@
+//! Thin hardware abstraction traits
+//!
+//! Everything in this crate reaches the hardware only through the traits in
+//! this module. The ESP implementations live in the firmware crate as
+//! zero-cost wrappers around ESP-IDF calls; the mock implementations (test
+//! builds and the `mock` feature) let the debounce state machine, cooldown
+//! logic and RNG sanity check run as ordinary host unit tests.
+//!
+//! Other boards (RP2040, nRF52, ...) need no implementations of their own:
+//! with feature `embedded-hal`, [`embedded`] adapts their `embedded-hal`
+//! 1.0 pins, delays and I2C buses, and their `rand_core` RNG drivers, to
+//! these traits.
+//!
+//! Keep these traits minimal. They exist to make the logic testable and
+//! portable, not to be a general-purpose HAL.
+
+use crate::error::Result;
+use crate::wallclock::WallTime;
+
+/// A digital input pin
+pub trait InputPin {
+    /// Returns true if the pin currently reads low
+    fn is_low(&self) -> bool;
+}
+
+/// Monotonic time source with a blocking delay
+pub trait Timer {
+    /// Milliseconds since boot, including any time spent asleep
+    fn now_ms(&self) -> u64;
+
+    /// Block the calling task for at least `ms` milliseconds
+    fn delay_ms(&self, ms: u32);
+
+    /// UTC time from an external reference, if one is locked (see `wallclock`)
+    fn wall_clock(&self) -> Option<WallTime> {
+        None
+    }
+}
+
+/// An I2C bus master; addresses are 7-bit
+pub trait I2cBus {
+    /// Write `bytes` to the device at `address`
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<()>;
+
+    /// Read `buf.len()` bytes from the device at `address`
+    fn read(&mut self, address: u8, buf: &mut [u8]) -> Result<()>;
+
+    /// Write `bytes`, then read `buf.len()` bytes after a repeated start
+    fn write_read(&mut self, address: u8, bytes: &[u8], buf: &mut [u8]) -> Result<()>;
+}
+
+/// Source of random bytes
+pub trait EntropySource {
+    /// Fill `dest` entirely with random bytes
+    fn fill(&self, dest: &mut [u8]);
+
+    /// Fill `dest` from the physical generator alone, for health checks
+    ///
+    /// Differs from `fill` only for sources that condition their output,
+    /// which would hide a stuck generator.
+    fn fill_raw(&self, dest: &mut [u8]) {
+        self.fill(dest)
+    }
+}
+
+impl<T: InputPin + ?Sized> InputPin for &T {
+    fn is_low(&self) -> bool {
+        (**self).is_low()
+    }
+}
+
+impl<T: Timer + ?Sized> Timer for &T {
+    fn now_ms(&self) -> u64 {
+        (**self).now_ms()
+    }
+
+    fn delay_ms(&self, ms: u32) {
+        (**self).delay_ms(ms)
+    }
+
+    fn wall_clock(&self) -> Option<WallTime> {
+        (**self).wall_clock()
+    }
+}
+
+impl<T: EntropySource + ?Sized> EntropySource for &T {
+    fn fill(&self, dest: &mut [u8]) {
+        (**self).fill(dest)
+    }
+
+    fn fill_raw(&self, dest: &mut [u8]) {
+        (**self).fill_raw(dest)
+    }
+}
+
+/// Adapters from `embedded-hal` 1.0 and `rand_core` drivers (feature
+/// `embedded-hal`)
+///
+/// `embedded-hal` takes `&mut self` where these traits take `&self`, so
+/// each adapter keeps its driver in a `RefCell`. It has no clock trait
+/// either: a port hands [`EhTimer`] a function reading its own monotonic
+/// timer.
+#[cfg(feature = "embedded-hal")]
+pub mod embedded {
+    use core::cell::RefCell;
+
+    use embedded_hal::delay::DelayNs;
+    use embedded_hal::{digital, i2c};
+    use rand_core::RngCore;
+
+    use super::{EntropySource, I2cBus, InputPin, Timer};
+    use crate::error::{IceSickleError, Result};
+
+    /// Code of a failed `embedded-hal` bus transfer (ESP-IDF's `ESP_FAIL`,
+    /// which means the same)
+    pub const EH_FAIL: i32 = -1;
+
+    /// An `embedded-hal` input pin; a pin that fails to read is released
+    pub struct EhPin<P>(RefCell<P>);
+
+    impl<P: digital::InputPin> EhPin<P> {
+        /// Wrap a pin already configured as an input, with its pull-up
+        pub fn new(pin: P) -> Self {
+            Self(RefCell::new(pin))
+        }
+    }
+
+    impl<P: digital::InputPin> InputPin for EhPin<P> {
+        fn is_low(&self) -> bool {
+            self.0.borrow_mut().is_low().unwrap_or(false)
+        }
+    }
+
+    /// A monotonic millisecond clock and an `embedded-hal` delay
+    pub struct EhTimer<C, D> {
+        now_ms: C,
+        delay: RefCell<D>,
+    }
+
+    impl<C: Fn() -> u64, D: DelayNs> EhTimer<C, D> {
+        /// `now_ms` must count from boot and never go backwards
+        pub fn new(now_ms: C, delay: D) -> Self {
+            Self {
+                now_ms,
+                delay: RefCell::new(delay),
+            }
+        }
+    }
+
+    impl<C: Fn() -> u64, D: DelayNs> Timer for EhTimer<C, D> {
+        fn now_ms(&self) -> u64 {
+            (self.now_ms)()
+        }
+
+        fn delay_ms(&self, ms: u32) {
+            self.delay.borrow_mut().delay_ms(ms);
+        }
+    }
+
+    /// An `embedded-hal` I2C master with 7-bit addresses
+    pub struct EhI2c<I>(pub I);
+
+    impl<I: i2c::I2c> I2cBus for EhI2c<I> {
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<()> {
+            self.0.write(address, bytes).map_err(bus_err)
+        }
+
+        fn read(&mut self, address: u8, buf: &mut [u8]) -> Result<()> {
+            self.0.read(address, buf).map_err(bus_err)
+        }
+
+        fn write_read(&mut self, address: u8, bytes: &[u8], buf: &mut [u8]) -> Result<()> {
+            self.0.write_read(address, bytes, buf).map_err(bus_err)
+        }
+    }
+
+    fn bus_err(_: impl i2c::Error) -> IceSickleError {
+        IceSickleError::Gpio(EH_FAIL)
+    }
+
+    /// A hardware RNG driver behind `rand_core`, such as the nRF52 RNG
+    /// peripheral or the RP2040's ring oscillator. The health tests in
+    /// `entropy` still run over it
+    pub struct RngEntropy<R>(RefCell<R>);
+
+    impl<R: RngCore> RngEntropy<R> {
+        pub fn new(rng: R) -> Self {
+            Self(RefCell::new(rng))
+        }
+    }
+
+    impl<R: RngCore> EntropySource for RngEntropy<R> {
+        fn fill(&self, dest: &mut [u8]) {
+            self.0.borrow_mut().fill_bytes(dest);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use core::cell::Cell;
+        use core::convert::Infallible;
+
+        use super::*;
+        use crate::button::{Button, DEBOUNCE_MS};
+
+        struct Level<'a>(&'a Cell<bool>);
+
+        impl digital::ErrorType for Level<'_> {
+            type Error = Infallible;
+        }
+
+        impl digital::InputPin for Level<'_> {
+            fn is_high(&mut self) -> core::result::Result<bool, Infallible> {
+                Ok(!self.0.get())
+            }
+
+            fn is_low(&mut self) -> core::result::Result<bool, Infallible> {
+                Ok(self.0.get())
+            }
+        }
+
+        struct Sleep<'a>(&'a Cell<u64>);
+
+        impl DelayNs for Sleep<'_> {
+            fn delay_ns(&mut self, ns: u32) {
+                self.0.set(self.0.get() + u64::from(ns) / 1_000_000);
+            }
+
+            fn delay_ms(&mut self, ms: u32) {
+                self.0.set(self.0.get() + u64::from(ms));
+            }
+        }
+
+        #[test]
+        fn test_button_on_embedded_hal() {
+            let (low, clock) = (Cell::new(false), Cell::new(1_000));
+            let timer = EhTimer::new(|| clock.get(), Sleep(&clock));
+            let mut button = Button::with_timer(EhPin::new(Level(&low)), &timer);
+
+            low.set(true);
+            assert!(button.poll_pressed().unwrap());
+            low.set(false);
+            button.wait_release().unwrap();
+            assert_eq!(timer.now_ms(), 1_000 + u64::from(DEBOUNCE_MS));
+        }
+    }
+}
+
+/// Mock implementations for host unit tests
+#[cfg(any(test, feature = "mock"))]
+pub mod mock {
+    use core::cell::Cell;
+    use std::collections::VecDeque;
+
+    use super::{EntropySource, I2cBus, InputPin, Timer};
+    use crate::error::{IceSickleError, Result};
+
+    /// Input pin whose level is set by the test
+    #[derive(Debug, Default)]
+    pub struct MockPin {
+        low: Cell<bool>,
+    }
+
+    impl MockPin {
+        pub fn set_low(&self, low: bool) {
+            self.low.set(low);
+        }
+    }
+
+    impl InputPin for MockPin {
+        fn is_low(&self) -> bool {
+            self.low.get()
+        }
+    }
+
+    /// Manually advanced clock; `delay_ms` advances it instead of sleeping
+    #[derive(Debug, Default)]
+    pub struct MockTimer {
+        now_ms: Cell<u64>,
+    }
+
+    impl MockTimer {
+        pub fn at(now_ms: u64) -> Self {
+            Self {
+                now_ms: Cell::new(now_ms),
+            }
+        }
+
+        pub fn set(&self, now_ms: u64) {
+            self.now_ms.set(now_ms);
+        }
+
+        pub fn advance(&self, ms: u64) {
+            self.now_ms.set(self.now_ms.get() + ms);
+        }
+    }
+
+    impl Timer for MockTimer {
+        fn now_ms(&self) -> u64 {
+            self.now_ms.get()
+        }
+
+        fn delay_ms(&self, ms: u32) {
+            self.advance(ms as u64);
+        }
+    }
+
+    /// I2C bus that records writes and replays scripted reads
+    #[derive(Debug, Default)]
+    pub struct MockI2c {
+        /// Address and bytes of every write, in order
+        pub writes: Vec<(u8, Vec<u8>)>,
+        /// Data for successive reads; an empty queue fails the read
+        pub reads: VecDeque<Vec<u8>>,
+    }
+
+    impl MockI2c {
+        /// Queue the data for the next read
+        pub fn respond(&mut self, data: &[u8]) {
+            self.reads.push_back(data.to_vec());
+        }
+    }
+
+    impl I2cBus for MockI2c {
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<()> {
+            self.writes.push((address, bytes.to_vec()));
+            Ok(())
+        }
+
+        fn read(&mut self, _address: u8, buf: &mut [u8]) -> Result<()> {
+            let data = self.reads.pop_front().ok_or(IceSickleError::Sensor)?;
+            buf.copy_from_slice(&data);
+            Ok(())
+        }
+
+        fn write_read(&mut self, address: u8, bytes: &[u8], buf: &mut [u8]) -> Result<()> {
+            self.write(address, bytes)?;
+            self.read(address, buf)
+        }
+    }
+
+    /// Entropy source that repeats a single byte
+    #[derive(Debug, Clone, Copy)]
+    pub struct MockEntropy(pub u8);
+
+    impl EntropySource for MockEntropy {
+        fn fill(&self, dest: &mut [u8]) {
+            dest.fill(self.0);
+        }
+    }
+
+    /// Entropy source that passes the continuous health tests: a xorshift
+    /// stream from a seed byte, the same on every run
+    #[derive(Debug)]
+    pub struct MockNoise(Cell<u32>);
+
+    impl MockNoise {
+        pub fn new(seed: u8) -> Self {
+            Self(Cell::new(0x9E37_79B9 ^ u32::from(seed)))
+        }
+    }
+
+    impl EntropySource for MockNoise {
+        fn fill(&self, dest: &mut [u8]) {
+            for b in dest {
+                let mut x = self.0.get();
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                self.0.set(x);
+                *b = x as u8;
+            }
+        }
+    }
+}
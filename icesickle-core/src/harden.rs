@@ -0,0 +1,135 @@
+//! Glitch countermeasures for security decisions
+//!
+//! A voltage or clock glitch can skip one instruction or flip one word.
+//! Against that, a decision that gates signing must not hinge on a single
+//! branch or a single boolean. Following common smartcard practice:
+//!
+//! - **Non-trivial encodings**: a decision is a [`Verdict`]. Allow and deny
+//!   are 32-bit patterns that differ in every bit; any other value is
+//!   treated as a fault, never as "allow".
+//! - **Redundant evaluation**: [`decide`] computes the condition twice, the
+//!   second time through `black_box` so the compiler cannot merge the two,
+//!   and requires both results to agree.
+//! - **Flow counters**: a [`Flow`] counts the checks passed on the way to a
+//!   sensitive operation, which runs only if the count is exactly right. A
+//!   skipped check leaves the count short.
+//!
+//! Any mismatch yields [`IceSickleError::Glitch`]; the firmware treats that
+//! as fatal (scrub and reset), not as a refusal to retry.
+//!
+//! Protected decision points:
+//!
+//! | Decision | Where |
+//! |----------|-------|
+//! | Cooldown gate | `Cooldown::gate` (double check, re-verified after the branch; timestamp stored redundantly) |
+//! | Press accepted → gate passed → sign | flow counter in the firmware event loop |
+//!
+//! Token scope checks and a self-verify gate must use this module when they
+//! land.
+
+use core::hint::black_box;
+
+use crate::error::{IceSickleError, Result};
+
+const ALLOW: u32 = 0x3CA5_5AC3;
+const DENY: u32 = !ALLOW;
+
+/// A redundantly encoded yes/no decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verdict(u32);
+
+impl Verdict {
+    pub const ALLOW: Verdict = Verdict(ALLOW);
+    pub const DENY: Verdict = Verdict(DENY);
+
+    pub fn from_bool(allowed: bool) -> Self {
+        if allowed {
+            Self::ALLOW
+        } else {
+            Self::DENY
+        }
+    }
+
+    /// Decode, rejecting anything that is neither pattern
+    pub fn check(self) -> Result<bool> {
+        match black_box(self.0) {
+            ALLOW if black_box(self.0) ^ DENY == u32::MAX => Ok(true),
+            DENY if black_box(self.0) ^ ALLOW == u32::MAX => Ok(false),
+            _ => Err(IceSickleError::Glitch),
+        }
+    }
+}
+
+/// Evaluate `condition` twice; both results must agree
+///
+/// `condition` must be a pure function of values captured once (never read
+/// a clock inside it), or honest evaluations could disagree.
+pub fn decide(condition: impl Fn() -> bool) -> Result<bool> {
+    let first = Verdict::from_bool(condition());
+    let second = Verdict::from_bool(black_box(&condition)());
+    if black_box(first) != second {
+        return Err(IceSickleError::Glitch);
+    }
+    first.check()
+}
+
+/// Counts the checks passed on the way to a sensitive operation
+#[derive(Debug, Default)]
+pub struct Flow {
+    steps: u32,
+}
+
+impl Flow {
+    pub const fn new() -> Self {
+        Self { steps: 0 }
+    }
+
+    /// Record that one more check passed
+    pub fn step(&mut self) {
+        self.steps = black_box(self.steps) + 1;
+    }
+
+    /// Require exactly `steps` checks to have passed (compared twice)
+    pub fn expect(&self, steps: u32) -> Result<()> {
+        if black_box(self.steps) != steps || black_box(steps) != self.steps {
+            return Err(IceSickleError::Glitch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict_rejects_corrupted_encoding() {
+        assert_eq!(Verdict::ALLOW.check(), Ok(true));
+        assert_eq!(Verdict::DENY.check(), Ok(false));
+        assert_eq!(Verdict(ALLOW ^ 1).check(), Err(IceSickleError::Glitch));
+        assert_eq!(Verdict(0).check(), Err(IceSickleError::Glitch));
+        assert_eq!(Verdict(u32::MAX).check(), Err(IceSickleError::Glitch));
+    }
+
+    #[test]
+    fn test_decide_requires_agreement() {
+        assert_eq!(decide(|| true), Ok(true));
+        assert_eq!(decide(|| false), Ok(false));
+
+        let calls = core::cell::Cell::new(0);
+        let flaky = || {
+            calls.set(calls.get() + 1);
+            calls.get() == 1
+        };
+        assert_eq!(decide(flaky), Err(IceSickleError::Glitch));
+    }
+
+    #[test]
+    fn test_flow_detects_skipped_step() {
+        let mut flow = Flow::new();
+        flow.step();
+        assert_eq!(flow.expect(2), Err(IceSickleError::Glitch));
+        flow.step();
+        assert_eq!(flow.expect(2), Ok(()));
+    }
+}
@@ -0,0 +1,119 @@
+//! Recent attestation history
+//!
+//! The device keeps the public fields of its last [`MAX_HISTORY`]
+//! attestations in RAM, so a host that connects after the fact can replay
+//! what was emitted while nothing was listening (`GetHistory`). Nothing here
+//! is secret: every record was already printed when it was signed, and the
+//! keys behind it are gone. The buffer starts empty each boot.
+//!
+//! Replay is keyed by the attestation counter rather than by position, so
+//! attestations signed during a replay do not shift it. A host asks for the
+//! oldest record at or after a counter, then for the one after that, until
+//! `NotFound`. A jump in the counters means older records were overwritten
+//! before the host asked. Counters signed as 0 (`policy`, `no-counter`)
+//! cannot be paged through: only the oldest record is reachable this way,
+//! and `GetLastAttestation` still returns the newest.
+
+use crate::protocol::AttestationRecord;
+
+/// Records kept; the oldest is dropped when a new one arrives
+pub const MAX_HISTORY: usize = 16;
+
+/// Ring buffer of recent attestation records, oldest first
+#[derive(Debug, Default)]
+pub struct History {
+    records: heapless::Deque<AttestationRecord, MAX_HISTORY>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep `record`, dropping the oldest if full
+    pub fn push(&mut self, record: AttestationRecord) {
+        if self.records.is_full() {
+            self.records.pop_front();
+        }
+        let _ = self.records.push_back(record);
+    }
+
+    /// The most recent record
+    pub fn last(&self) -> Option<&AttestationRecord> {
+        self.records.back()
+    }
+
+    /// The oldest record with a counter of at least `counter`
+    pub fn since(&self, counter: u32) -> Option<&AttestationRecord> {
+        self.records.iter().find(|record| record.counter >= counter)
+    }
+
+    /// Every record, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &AttestationRecord> {
+        self.records.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::AttestationEvent;
+    use crate::policy::Policy;
+    use crate::scheme::Algorithm;
+
+    fn record(counter: u32) -> AttestationRecord {
+        AttestationRecord {
+            version: 12,
+            event: AttestationEvent::ButtonPress { gpio: 0 },
+            timestamp_ms: u64::from(counter) * 1_000,
+            counter,
+            public_key: [0; 32],
+            signature: [0; 64],
+            wall_clock: None,
+            policy: Policy::NONE,
+            suppressed: 0,
+            challenge: None,
+            token: None,
+            boot_nonce: None,
+            algorithm: Algorithm::Ed25519,
+            context: None,
+            measurement: None,
+        }
+    }
+
+    #[test]
+    fn test_replay_by_counter() {
+        let mut history = History::new();
+        assert_eq!(history.last(), None);
+        assert_eq!(history.since(0), None);
+
+        for counter in [3, 4, 6] {
+            history.push(record(counter));
+        }
+        let replayed: Vec<u32> =
+            std::iter::successors(history.since(0), |r| history.since(r.counter + 1))
+                .map(|r| r.counter)
+                .collect();
+        assert_eq!(replayed, [3, 4, 6]);
+        assert_eq!(history.since(5).map(|r| r.counter), Some(6));
+        assert_eq!(history.since(7), None);
+        assert_eq!(history.last().map(|r| r.counter), Some(6));
+    }
+
+    #[test]
+    fn test_oldest_record_is_dropped_when_full() {
+        let mut history = History::new();
+        for counter in 0..MAX_HISTORY as u32 + 2 {
+            history.push(record(counter));
+        }
+        assert_eq!(history.since(0).map(|r| r.counter), Some(2));
+        assert_eq!(
+            history.last().map(|r| r.counter),
+            Some(MAX_HISTORY as u32 + 1)
+        );
+        assert!(history
+            .iter()
+            .map(|r| r.counter)
+            .eq(2..MAX_HISTORY as u32 + 2));
+    }
+}
@@ -0,0 +1,125 @@
+//! Per-attestation phase timing
+//!
+//! With the `instrument` feature enabled, each phase of producing an
+//! attestation (key generation, serialization, signing, output) is timed with
+//! the Xtensa CCOUNT cycle counter and the most recent figures are kept in
+//! RAM for reporting. Without the feature every call here compiles to nothing,
+//! so the call sites in the signing path stay unconditional.
+//!
+//! Only cycle counts are recorded - never anything derived from key material.
+//! Timings are coarse enough (tens of microseconds) that exposing them does
+//! not add a meaningful side channel beyond what a scope on the power rail
+//! already gives a physically present attacker.
+
+#[cfg(feature = "instrument")]
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// A measured phase of attestation production
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    KeyGen,
+    Serialize,
+    Sign,
+    Output,
+}
+
+#[cfg(feature = "instrument")]
+impl Phase {
+    const COUNT: usize = 4;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Cycle counts of the most recent attestation, one entry per phase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseCycles {
+    pub keygen: u32,
+    pub serialize: u32,
+    pub sign: u32,
+    pub output: u32,
+}
+
+#[cfg(feature = "instrument")]
+static LAST_CYCLES: [AtomicU32; Phase::COUNT] = [const { AtomicU32::new(0) }; Phase::COUNT];
+
+/// An in-progress measurement; call [`Span::finish`] when the phase ends
+#[must_use]
+pub struct Span {
+    #[cfg(feature = "instrument")]
+    phase: Phase,
+    #[cfg(feature = "instrument")]
+    start: u32,
+}
+
+/// Start timing `phase`
+#[cfg_attr(not(feature = "instrument"), allow(unused_variables))]
+pub fn start(phase: Phase) -> Span {
+    Span {
+        #[cfg(feature = "instrument")]
+        phase,
+        #[cfg(feature = "instrument")]
+        start: cycle_count(),
+    }
+}
+
+impl Span {
+    /// Stop timing and record the elapsed cycles for this phase
+    pub fn finish(self) {
+        #[cfg(feature = "instrument")]
+        {
+            let elapsed = cycle_count().wrapping_sub(self.start);
+            LAST_CYCLES[self.phase.index()].store(elapsed, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Cycle counts recorded for the most recent attestation
+///
+/// All zeros when the `instrument` feature is disabled.
+pub fn last() -> PhaseCycles {
+    #[cfg(feature = "instrument")]
+    {
+        let load = |phase: Phase| LAST_CYCLES[phase.index()].load(Ordering::Relaxed);
+        PhaseCycles {
+            keygen: load(Phase::KeyGen),
+            serialize: load(Phase::Serialize),
+            sign: load(Phase::Sign),
+            output: load(Phase::Output),
+        }
+    }
+
+    #[cfg(not(feature = "instrument"))]
+    PhaseCycles::default()
+}
+
+/// Log the most recent phase timings (no-op without `instrument`)
+#[cfg_attr(not(feature = "instrument"), allow(unused_variables))]
+pub fn log_last(cpu_mhz: u32) {
+    #[cfg(feature = "instrument")]
+    {
+        let cycles = last();
+        let mhz = cpu_mhz;
+        log::info!(
+            "Timing: keygen={}us serialize={}us sign={}us output={}us ({} MHz)",
+            cycles.keygen / mhz,
+            cycles.serialize / mhz,
+            cycles.sign / mhz,
+            cycles.output / mhz,
+            mhz
+        );
+    }
+}
+
+/// Read the Xtensa CCOUNT register (wraps every ~18 s at 240 MHz)
+#[cfg(feature = "instrument")]
+fn cycle_count() -> u32 {
+    let count: u32;
+    unsafe {
+        core::arch::asm!("rsr.ccount {0}", out(reg) count, options(nomem, nostack));
+    }
+    count
+}
@@ -0,0 +1,185 @@
+//! Keypad entry attestations
+//!
+//! With the firmware's `keypad` feature, a 4x4 matrix keypad lets an
+//! operator key in a procedure code at the device. The attestation never
+//! carries the code. It carries [`entry_hash`]: SHA-256 over a domain
+//! prefix, a 32-byte challenge from the verifier, and the keyed code. A
+//! verifier that knows the expected code recomputes the hash and compares.
+//!
+//! 1. The verifier sends `SetChallenge`, arming a [`Challenge`].
+//! 2. The operator keys the code and ends it with `#`; `*` starts over.
+//! 3. The device signs a `KeypadEntry { hash }` attestation and drops the
+//!    challenge. One challenge allows one entry attempt, signed or not.
+//!
+//! Without a live challenge an entry is discarded: an unsalted hash of a
+//! short code is a lookup table away from the code itself. The salt only
+//! stops precomputation, though. Anyone who sees both the challenge and the
+//! attestation can try every short code, so send challenges inside an
+//! encrypted session when the code matters.
+//!
+//! Codes longer than [`MAX_CODE_LEN`] keys are discarded at `#`. The
+//! partial code is zeroized whenever an entry ends or is dropped.
+
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Domain separation prefix for entry hashes
+const DOMAIN: &[u8] = b"IceSickle keypad entry v1";
+
+/// Longest code accepted, in keys
+pub const MAX_CODE_LEN: usize = 16;
+
+/// An armed challenge expires if no entry is completed within this time
+pub const CHALLENGE_TIMEOUT_MS: u64 = 300_000;
+
+/// Key labels by row and column, as printed on common 4x4 keypads
+pub const KEYMAP: [[u8; 4]; 4] = [*b"123A", *b"456B", *b"789C", *b"*0#D"];
+
+/// Key that ends an entry
+const ENTER: u8 = b'#';
+
+/// Key that discards the entry so far
+const CLEAR: u8 = b'*';
+
+/// SHA-256(domain || challenge || code): what a `KeypadEntry` carries
+pub fn entry_hash(challenge: &[u8; 32], code: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(DOMAIN)
+        .chain_update(challenge)
+        .chain_update(code)
+        .finalize()
+        .into()
+}
+
+/// Code keyed so far; zeroized on drop
+#[derive(Default, ZeroizeOnDrop)]
+pub struct Entry {
+    code: [u8; MAX_CODE_LEN],
+    len: usize,
+    overflowed: bool,
+}
+
+impl Entry {
+    pub const fn new() -> Self {
+        Self {
+            code: [0; MAX_CODE_LEN],
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Record one key; true once a valid entry is complete
+    ///
+    /// Empty and overlong entries are discarded at `#`.
+    pub fn press(&mut self, key: u8) -> bool {
+        match key {
+            ENTER => {
+                let complete = self.len > 0 && !self.overflowed;
+                if !complete {
+                    self.clear();
+                }
+                complete
+            }
+            CLEAR => {
+                self.clear();
+                false
+            }
+            key => {
+                if self.len < MAX_CODE_LEN {
+                    self.code[self.len] = key;
+                    self.len += 1;
+                } else {
+                    self.overflowed = true;
+                }
+                false
+            }
+        }
+    }
+
+    /// Hash the entry under `challenge`, then clear it
+    pub fn finish(&mut self, challenge: &[u8; 32]) -> [u8; 32] {
+        let hash = entry_hash(challenge, &self.code[..self.len]);
+        self.clear();
+        hash
+    }
+
+    /// Discard the entry so far
+    pub fn clear(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Zeroize for Entry {
+    fn zeroize(&mut self) {
+        self.code.zeroize();
+        self.len = 0;
+        self.overflowed = false;
+    }
+}
+
+/// Verifier challenge awaiting a keypad entry
+#[derive(Debug, Default)]
+pub struct Challenge {
+    /// Challenge and when it was armed
+    armed: Option<([u8; 32], u64)>,
+}
+
+impl Challenge {
+    pub const fn new() -> Self {
+        Self { armed: None }
+    }
+
+    /// Arm `challenge`, replacing any earlier one
+    pub fn arm(&mut self, challenge: [u8; 32], now_ms: u64) {
+        self.armed = Some((challenge, now_ms));
+    }
+
+    /// Take the armed challenge, if it has not expired
+    pub fn take(&mut self, now_ms: u64) -> Option<[u8; 32]> {
+        let (challenge, armed_at_ms) = self.armed.take()?;
+        (now_ms.saturating_sub(armed_at_ms) < CHALLENGE_TIMEOUT_MS).then_some(challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_in(entry: &mut Entry, keys: &[u8]) -> bool {
+        keys.iter().fold(false, |_, &key| entry.press(key))
+    }
+
+    #[test]
+    fn test_entry_hashes_code_under_challenge() {
+        let mut entry = Entry::new();
+        assert!(key_in(&mut entry, b"12*4711#"));
+        let hash = entry.finish(&[0x42; 32]);
+        assert_eq!(hash, entry_hash(&[0x42; 32], b"4711"));
+        assert_ne!(hash, entry_hash(&[0x43; 32], b"4711"));
+
+        // Finishing cleared the entry
+        assert!(!entry.press(ENTER));
+    }
+
+    #[test]
+    fn test_overlong_entry_is_discarded() {
+        let mut entry = Entry::new();
+        assert!(!key_in(&mut entry, &[b'7'; MAX_CODE_LEN + 1]));
+        assert!(!entry.press(ENTER));
+        assert!(key_in(&mut entry, b"0#"));
+        assert_eq!(entry.finish(&[0; 32]), entry_hash(&[0; 32], b"0"));
+    }
+
+    #[test]
+    fn test_challenge_is_single_use_and_expires() {
+        let mut challenge = Challenge::new();
+        assert_eq!(challenge.take(0), None);
+
+        challenge.arm([1; 32], 1_000);
+        assert_eq!(challenge.take(2_000), Some([1; 32]));
+        assert_eq!(challenge.take(2_000), None);
+
+        challenge.arm([2; 32], 1_000);
+        assert_eq!(challenge.take(1_000 + CHALLENGE_TIMEOUT_MS), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! IceSickle core - hardware-agnostic attestation logic
+//!
+//! Everything that defines what an attestation *is* lives here: the signed
+//! payload and its encoding, the ephemeral-key signing path, cooldown
+//! policy, the host command protocol and its session encryption, and the
+//! CTAPHID framing. None of it touches ESP-IDF; hardware is reached only
+//! through the traits in [`hal`].
+//!
+//! The firmware crate supplies ESP32 implementations of those traits. The
+//! verifier, simulator and future ports link this crate so they run exactly
+//! the code that runs on the device.
+//!
+//! The payload types, the record forms and `attestation::verify` live in
+//! the `no_std`, allocation-free `icesickle-payload` crate, for verifiers
+//! without an OS. They are re-exported here at the same paths, so the
+//! device signs with the code verifiers check with.
+
+// CCOUNT is read with inline asm, which is still unstable on Xtensa
+#![cfg_attr(
+    all(feature = "instrument", target_arch = "xtensa"),
+    feature(asm_experimental_arch)
+)]
+
+// Test vectors make every key predictable; they never ship
+#[cfg(all(feature = "test-vectors", not(debug_assertions)))]
+compile_error!("the `test-vectors` feature is refused in release builds");
+
+// Companion formats name EdDSA or carry Ed25519 key encodings
+#[cfg(all(
+    feature = "sign-p256",
+    any(
+        feature = "openpgp",
+        feature = "sshsig",
+        feature = "dsse",
+        feature = "cwt",
+        feature = "cose"
+    )
+))]
+compile_error!("the `sign-p256` feature cannot be combined with companion formats");
+
+pub mod atecc;
+pub mod attestation;
+pub mod auth;
+pub mod batch;
+pub mod blind;
+pub mod boot;
+pub mod button;
+#[cfg(any(feature = "cwt", feature = "cose"))]
+mod cbor;
+pub mod challenge;
+pub mod companion;
+pub mod context;
+pub mod cooldown;
+#[cfg(feature = "cose")]
+pub mod cose;
+pub mod credit;
+pub mod ctaphid;
+#[cfg(feature = "cwt")]
+pub mod cwt;
+pub mod delivery;
+#[cfg(feature = "display")]
+pub mod display;
+#[cfg(feature = "dsse")]
+pub mod dsse;
+pub mod dual;
+pub mod entropy;
+pub mod error;
+pub mod fault;
+pub mod feedback;
+pub mod gesture;
+pub mod hal;
+pub mod harden;
+pub mod history;
+pub mod instrument;
+pub mod keypad;
+pub mod merkle;
+#[cfg(feature = "nfc")]
+pub mod nfc;
+#[cfg(feature = "openpgp")]
+pub mod openpgp;
+pub mod policy;
+pub mod presence;
+pub mod protocol;
+#[cfg(feature = "receipt")]
+pub mod receipt;
+pub mod redact;
+pub mod relay;
+pub mod rtc;
+pub mod scheme;
+pub mod scrub;
+pub mod sensor;
+pub mod session;
+pub mod sha512;
+pub mod snapshot;
+#[cfg(feature = "sshsig")]
+pub mod sshsig;
+pub mod state;
+pub mod stream;
+pub mod telemetry;
+pub mod touch;
+pub mod transport;
+pub mod usage;
+#[cfg(feature = "volume")]
+pub mod volume;
+pub mod wallclock;
+pub mod witness;
+
+pub use error::{IceSickleError, Result};
+pub use icesickle_payload::{base45, ct, multibase};
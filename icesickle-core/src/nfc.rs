@@ -0,0 +1,322 @@
+//! NFC tag output (feature `nfc`)
+//!
+//! Writes each attestation into an ST25DV dynamic NFC tag over I2C, so a
+//! phone reads it with a tap and verifies it with no link to the device at
+//! all. The tag holds one NDEF record of media type [`MEDIA_TYPE`] whose
+//! payload is the attestation's push message (see `transport`): the signed
+//! payload, the public key and the signature, which Web NFC hands to a page
+//! as raw bytes.
+//!
+//! The tag's EEPROM is laid out as an NFC Forum Type 5 tag: a capability
+//! container, the NDEF message in a TLV, a terminator TLV, then zeros to
+//! [`IMAGE_LEN`], so no byte of an earlier message survives a shorter one.
+//! [`St25dv::poll`] writes the image one row (16 bytes) per call and waits
+//! out the row's programming time before the next, so the I2C bus it
+//! shares is never held for long. Once the expiry given to [`St25dv::new`]
+//! has passed, the tag is overwritten with an empty NDEF message, and a tap
+//! finds nothing rather than a stale attestation.
+//!
+//! A phone that taps while a row is being written can read the new image's
+//! start over the old one's end; the signature check fails on the mix, and
+//! a second tap reads the finished image.
+
+use crate::error::Result;
+use crate::hal::I2cBus;
+use crate::transport::MAX_MESSAGE_LEN;
+
+/// User memory address with E2 low (`0x57` is the system area)
+pub const DEFAULT_ADDRESS: u8 = 0x53;
+
+/// How long an attestation stays on the tag unless configured otherwise
+pub const DEFAULT_EXPIRY_MS: u64 = 60_000;
+
+/// NDEF media type of the record
+pub const MEDIA_TYPE: &[u8] = b"application/vnd.icesickle.attestation";
+
+/// User memory of the smallest part (ST25DV04K), declared in the capability
+/// container whatever the part
+const MEMORY_LEN: usize = 512;
+
+/// Capability container: NDEF magic, mapping version 1.0 with read and
+/// write access, memory size in 8-byte units, no optional features
+const CC: [u8; 4] = [0xE1, 0x40, (MEMORY_LEN / 8) as u8, 0x00];
+
+/// TLV holding the NDEF message
+const NDEF_TLV: u8 = 0x03;
+
+/// TLV ending the tag's contents
+const TERMINATOR_TLV: u8 = 0xFE;
+
+/// TLV length byte announcing a two-byte length
+const LONG_TLV: u8 = 0xFF;
+
+/// Record header: first and last record of the message, media type (TNF 2)
+const RECORD_HEADER: u8 = 0xC2;
+
+/// Record header flag for a one-byte payload length instead of four
+const SHORT_RECORD: u8 = 0x10;
+
+/// EEPROM bytes per I2C write: one row of four 4-byte blocks
+const CHUNK_LEN: usize = 16;
+
+/// Programming time of a row, 5 ms per block, while the tag ignores I2C
+const WRITE_MS: u64 = 20;
+
+/// Bytes written per image: the capability container, a long TLV header,
+/// a long record header and the type, the longest message and the
+/// terminator, in whole rows
+pub const IMAGE_LEN: usize =
+    (CC.len() + 4 + 6 + MEDIA_TYPE.len() + MAX_MESSAGE_LEN + 1).next_multiple_of(CHUNK_LEN);
+
+const _: () = assert!(IMAGE_LEN <= MEMORY_LEN);
+
+/// Tag memory from address 0
+pub type Image = [u8; IMAGE_LEN];
+
+/// Tag image holding `message` as one NDEF record; `message` is at most
+/// [`MAX_MESSAGE_LEN`] bytes
+pub fn image(message: &[u8]) -> Image {
+    let short = message.len() <= usize::from(u8::MAX);
+    let mut record = heapless::Vec::<u8, IMAGE_LEN>::new();
+    // Lengths are bounded by IMAGE_LEN
+    let _ = record.push(RECORD_HEADER | if short { SHORT_RECORD } else { 0 });
+    let _ = record.push(MEDIA_TYPE.len() as u8);
+    if short {
+        let _ = record.push(message.len() as u8);
+    } else {
+        let _ = record.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    }
+    let _ = record.extend_from_slice(MEDIA_TYPE);
+    let _ = record.extend_from_slice(message);
+    tlv_image(&record)
+}
+
+/// Tag image with an empty NDEF message
+fn blank() -> Image {
+    tlv_image(&[])
+}
+
+/// Capability container, `ndef` in its TLV and the terminator, zero-padded
+fn tlv_image(ndef: &[u8]) -> Image {
+    let mut image = [0; IMAGE_LEN];
+    image[..CC.len()].copy_from_slice(&CC);
+    let mut at = CC.len();
+    image[at] = NDEF_TLV;
+    at += 1;
+    if ndef.len() < usize::from(LONG_TLV) {
+        image[at] = ndef.len() as u8;
+        at += 1;
+    } else {
+        image[at] = LONG_TLV;
+        image[at + 1..at + 3].copy_from_slice(&(ndef.len() as u16).to_be_bytes());
+        at += 3;
+    }
+    image[at..at + ndef.len()].copy_from_slice(ndef);
+    image[at + ndef.len()] = TERMINATOR_TLV;
+    image
+}
+
+/// ST25DV user memory on one I2C address
+#[derive(Debug)]
+pub struct St25dv {
+    address: u8,
+    expiry_ms: u64,
+    image: Image,
+    /// The image holds an attestation, to clear once written and expired
+    holds_message: bool,
+    /// Offset of the next row of `image` to write, while it is being written
+    next: Option<usize>,
+    /// When the last row was written, until it is programmed
+    written_ms: Option<u64>,
+    /// When the current attestation was written, until it is cleared
+    shown_ms: Option<u64>,
+}
+
+impl St25dv {
+    /// Tag at `address`, clearing each attestation `expiry_ms` after it is
+    /// written. Whatever a previous boot left on it is cleared from the
+    /// first poll
+    pub fn new(address: u8, expiry_ms: u64) -> Self {
+        Self {
+            address,
+            expiry_ms,
+            image: blank(),
+            holds_message: false,
+            next: Some(0),
+            written_ms: None,
+            shown_ms: None,
+        }
+    }
+
+    /// Read the capability container; false if nothing answers
+    pub fn probe(&self, bus: &mut dyn I2cBus) -> bool {
+        let mut cc = [0u8; CC.len()];
+        bus.write_read(self.address, &[0, 0], &mut cc).is_ok()
+    }
+
+    /// Replace whatever is on the tag with `message`, from the next poll
+    pub fn show(&mut self, message: &[u8]) {
+        self.image = image(message);
+        self.holds_message = true;
+        self.next = Some(0);
+        self.shown_ms = None;
+    }
+
+    /// Write the next row of a pending image once the last is programmed,
+    /// or clear an attestation once it has expired
+    ///
+    /// A failed write leaves the row pending, to retry on the next poll.
+    pub fn poll(&mut self, bus: &mut dyn I2cBus, now_ms: u64) -> Result<()> {
+        if self
+            .written_ms
+            .is_some_and(|written_ms| now_ms.saturating_sub(written_ms) < WRITE_MS)
+        {
+            return Ok(());
+        }
+        self.written_ms = None;
+        if let Some(offset) = self.next {
+            // Big-endian memory address, then the row
+            let mut write = [0u8; 2 + CHUNK_LEN];
+            write[..2].copy_from_slice(&(offset as u16).to_be_bytes());
+            write[2..].copy_from_slice(&self.image[offset..][..CHUNK_LEN]);
+            bus.write(self.address, &write)?;
+            self.written_ms = Some(now_ms);
+            if offset + CHUNK_LEN < IMAGE_LEN {
+                self.next = Some(offset + CHUNK_LEN);
+            } else {
+                self.next = None;
+                self.shown_ms = self.holds_message.then_some(now_ms);
+            }
+            return Ok(());
+        }
+        if self
+            .shown_ms
+            .is_some_and(|shown_ms| now_ms.saturating_sub(shown_ms) >= self.expiry_ms)
+        {
+            self.image = blank();
+            self.holds_message = false;
+            self.next = Some(0);
+            self.shown_ms = None;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::{self, Attestation, AttestationEvent};
+    use crate::entropy::HardwareRng;
+    use crate::hal::mock::{MockI2c, MockNoise, MockTimer};
+    use crate::transport;
+
+    /// Payload of the image's only NDEF record
+    fn record_payload(image: &Image) -> &[u8] {
+        assert_eq!(image[..4], CC);
+        assert_eq!(image[4], NDEF_TLV);
+        let (ndef_len, ndef) = match image[5] {
+            LONG_TLV => (
+                u16::from_be_bytes([image[6], image[7]]) as usize,
+                &image[8..],
+            ),
+            len => (usize::from(len), &image[6..]),
+        };
+        assert_eq!(ndef[ndef_len], TERMINATOR_TLV);
+        assert!(ndef[ndef_len + 1..].iter().all(|&b| b == 0));
+
+        let (payload_len, rest) = if ndef[0] & SHORT_RECORD != 0 {
+            (usize::from(ndef[2]), &ndef[3..])
+        } else {
+            let len = u32::from_be_bytes([ndef[2], ndef[3], ndef[4], ndef[5]]);
+            (len as usize, &ndef[6..])
+        };
+        assert_eq!(ndef[0] & !SHORT_RECORD, RECORD_HEADER);
+        assert_eq!(&rest[..usize::from(ndef[1])], MEDIA_TYPE);
+        let payload = &rest[MEDIA_TYPE.len()..][..payload_len];
+        assert_eq!(
+            rest.len() - MEDIA_TYPE.len() - payload_len,
+            ndef.len() - ndef_len
+        );
+        payload
+    }
+
+    #[test]
+    fn test_image_holds_message() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let event = AttestationEvent::DataDigest {
+            gpio: 0,
+            sha256: [0xFF; 32],
+            len: u64::MAX,
+        };
+        let attestation = Attestation::create(&rng, &MockTimer::at(1_000), event).unwrap();
+        let message = transport::message(&attestation);
+
+        // A phone reads the same message the other transports push
+        let image = image(&message);
+        let (payload, public_key, signature) = transport::parse(record_payload(&image)).unwrap();
+        assert_eq!(payload, &attestation.payload_bytes()[..]);
+        let message = attestation::signed_message(payload);
+        assert!(attestation
+            .algorithm()
+            .verify(public_key, &message, signature));
+
+        // Short records and TLVs for short messages
+        let image = super::image(&[0xAB; 3]);
+        assert_eq!(image[5], 3 + 3 + MEDIA_TYPE.len() as u8);
+        assert_eq!(record_payload(&image), [0xAB; 3]);
+        let image = super::image(&[0xAB; MAX_MESSAGE_LEN]);
+        assert_eq!(record_payload(&image), [0xAB; MAX_MESSAGE_LEN]);
+
+        assert_eq!(blank()[4..7], [NDEF_TLV, 0, TERMINATOR_TLV]);
+    }
+
+    /// Poll `tag` through one image from `start_ms`, each row written once
+    /// the last is programmed; the bytes written
+    fn write_image(tag: &mut St25dv, bus: &mut MockI2c, start_ms: u64) -> Vec<u8> {
+        bus.writes.clear();
+        let rows = IMAGE_LEN / CHUNK_LEN;
+        for row in 0..rows as u64 {
+            tag.poll(bus, start_ms + row * WRITE_MS).unwrap();
+            tag.poll(bus, start_ms + row * WRITE_MS + WRITE_MS - 1)
+                .unwrap();
+        }
+        assert_eq!(bus.writes.len(), rows);
+        let mut written = Vec::new();
+        for (row, (address, write)) in bus.writes.iter().enumerate() {
+            assert_eq!(*address, DEFAULT_ADDRESS);
+            assert_eq!(write[..2], ((row * CHUNK_LEN) as u16).to_be_bytes());
+            written.extend_from_slice(&write[2..]);
+        }
+        written
+    }
+
+    #[test]
+    fn test_rows_then_clear_after_expiry() {
+        let mut tag = St25dv::new(DEFAULT_ADDRESS, DEFAULT_EXPIRY_MS);
+        let mut bus = MockI2c::default();
+        bus.respond(&CC);
+        assert!(tag.probe(&mut bus));
+        bus.writes.clear();
+
+        // Leftovers from a previous boot are cleared first
+        assert_eq!(write_image(&mut tag, &mut bus, 0), blank());
+
+        tag.show(&[0xAB; 3]);
+        let written = write_image(&mut tag, &mut bus, 1_000);
+        assert_eq!(written, image(&[0xAB; 3]));
+        let done_ms = 1_000 + (IMAGE_LEN / CHUNK_LEN - 1) as u64 * WRITE_MS;
+
+        bus.writes.clear();
+        tag.poll(&mut bus, done_ms + DEFAULT_EXPIRY_MS - 1).unwrap();
+        assert!(bus.writes.is_empty());
+        tag.poll(&mut bus, done_ms + DEFAULT_EXPIRY_MS).unwrap();
+        let start_ms = done_ms + DEFAULT_EXPIRY_MS;
+        assert_eq!(write_image(&mut tag, &mut bus, start_ms), blank());
+
+        // A cleared tag stays cleared
+        bus.writes.clear();
+        tag.poll(&mut bus, start_ms + 10 * DEFAULT_EXPIRY_MS)
+            .unwrap();
+        assert!(bus.writes.is_empty());
+    }
+}
@@ -0,0 +1,276 @@
+//! OpenPGP output (feature `openpgp`)
+//!
+//! Renders an attestation for gpg: the ephemeral key as an armored public
+//! key, and a detached binary-document signature over the payload bytes.
+//!
+//! ```text
+//! gpg --import key.asc
+//! base64 -d payload.b64 > payload.bin
+//! gpg --verify sig.asc payload.bin
+//! ```
+//!
+//! The key is a v4 EdDSA (Ed25519) key whose user ID says what it is. Its
+//! self-certification carries key flags that allow data signatures only,
+//! so the key cannot certify anything, and a one-second expiry, so gpg
+//! shows it as expired from the start; a signature made at its creation
+//! still verifies. Creation times are the signed wall-clock time, or 0
+//! (1970) without one, so the key's fingerprint follows from the
+//! attestation alone. Both signatures are companions (see `companion`):
+//! the device signs their SHA-256 digests with the ephemeral key.
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::attestation::Attestation;
+use crate::companion::{self, Companion, Subject};
+
+/// User ID bound to every ephemeral key
+pub const USER_ID: &str = "IceSickle ephemeral attestation key";
+
+/// Key lifetime after creation, in seconds
+const KEY_EXPIRY_S: u32 = 1;
+
+const TAG_SIGNATURE: u8 = 2;
+const TAG_PUBLIC_KEY: u8 = 6;
+const TAG_USER_ID: u8 = 13;
+
+const SIG_BINARY: u8 = 0x00;
+const SIG_POSITIVE_CERT: u8 = 0x13;
+
+const ALGO_EDDSA: u8 = 22;
+const HASH_SHA256: u8 = 8;
+
+/// OID 1.3.6.1.4.1.11591.15.1 (Ed25519), length-prefixed
+const ED25519_OID: [u8; 10] = [9, 0x2B, 0x06, 0x01, 0x04, 0x01, 0xDA, 0x47, 0x0F, 0x01];
+
+const SUB_CREATED: u8 = 2;
+const SUB_KEY_EXPIRY: u8 = 9;
+const SUB_ISSUER: u8 = 16;
+const SUB_KEY_FLAGS: u8 = 27;
+const SUB_ISSUER_FINGERPRINT: u8 = 33;
+
+/// Key flag: may sign data (and nothing else is set)
+const KEY_FLAG_SIGN: u8 = 0x02;
+
+/// Public-key packet body: version through the key MPI
+const KEY_BODY_LEN: usize = 51;
+
+/// Longest hashed signature part (see [`hashed_part`])
+const MAX_HASHED_LEN: usize = 48;
+
+/// Armored key and signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Armored {
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Render `attestation`; `None` unless it carries the OpenPGP companions
+pub fn render(attestation: &Attestation) -> Option<Armored> {
+    let signatures = attestation.companions();
+    let key_signature = Companion::OpenPgpKey.find(signatures)?;
+    let data_signature = Companion::OpenPgpData.find(signatures)?;
+
+    let payload = attestation.payload_bytes();
+    let subject = Subject::of(attestation, &payload);
+
+    let mut key = packet(TAG_PUBLIC_KEY, &key_body(&subject));
+    key.extend(packet(TAG_USER_ID, USER_ID.as_bytes()));
+    key.extend(signature_packet(
+        SIG_POSITIVE_CERT,
+        &subject,
+        &key_digest(&subject),
+        key_signature,
+    ));
+    let signature = signature_packet(SIG_BINARY, &subject, &data_digest(&subject), data_signature);
+
+    Some(Armored {
+        public_key: armor("PUBLIC KEY BLOCK", &key),
+        signature: armor("SIGNATURE", &signature),
+    })
+}
+
+/// SHA-256 digest the self-certification signs
+pub(crate) fn key_digest(subject: &Subject) -> [u8; 32] {
+    let body = key_body(subject);
+    let hashed = hashed_part(SIG_POSITIVE_CERT, subject);
+    Sha256::new()
+        .chain_update([0x99])
+        .chain_update((KEY_BODY_LEN as u16).to_be_bytes())
+        .chain_update(body)
+        .chain_update([0xB4])
+        .chain_update((USER_ID.len() as u32).to_be_bytes())
+        .chain_update(USER_ID)
+        .chain_update(&hashed)
+        .chain_update(trailer(hashed.len()))
+        .finalize()
+        .into()
+}
+
+/// SHA-256 digest the detached signature signs
+pub(crate) fn data_digest(subject: &Subject) -> [u8; 32] {
+    let hashed = hashed_part(SIG_BINARY, subject);
+    Sha256::new()
+        .chain_update(subject.payload)
+        .chain_update(&hashed)
+        .chain_update(trailer(hashed.len()))
+        .finalize()
+        .into()
+}
+
+/// Key and signature creation time
+fn created(subject: &Subject) -> [u8; 4] {
+    let unix_s = subject.wall_clock.map_or(0, |wall| wall.unix_s);
+    u32::try_from(unix_s).unwrap_or(u32::MAX).to_be_bytes()
+}
+
+fn key_body(subject: &Subject) -> [u8; KEY_BODY_LEN] {
+    let mut body = [0u8; KEY_BODY_LEN];
+    body[0] = 4;
+    body[1..5].copy_from_slice(&created(subject));
+    body[5] = ALGO_EDDSA;
+    body[6..16].copy_from_slice(&ED25519_OID);
+    // MPI of 0x40 || point: 263 bits
+    body[16..18].copy_from_slice(&263u16.to_be_bytes());
+    body[18] = 0x40;
+    body[19..].copy_from_slice(subject.public_key);
+    body
+}
+
+fn fingerprint(subject: &Subject) -> [u8; 20] {
+    Sha1::new()
+        .chain_update([0x99])
+        .chain_update((KEY_BODY_LEN as u16).to_be_bytes())
+        .chain_update(key_body(subject))
+        .finalize()
+        .into()
+}
+
+/// Signature packet from its version octet through the hashed subpackets
+fn hashed_part(sig_type: u8, subject: &Subject) -> heapless::Vec<u8, MAX_HASHED_LEN> {
+    let mut subpackets = heapless::Vec::<u8, MAX_HASHED_LEN>::new();
+    let _ = subpackets.extend_from_slice(&[5, SUB_CREATED]);
+    let _ = subpackets.extend_from_slice(&created(subject));
+    if sig_type == SIG_POSITIVE_CERT {
+        let _ = subpackets.extend_from_slice(&[2, SUB_KEY_FLAGS, KEY_FLAG_SIGN]);
+        let _ = subpackets.extend_from_slice(&[5, SUB_KEY_EXPIRY]);
+        let _ = subpackets.extend_from_slice(&KEY_EXPIRY_S.to_be_bytes());
+    }
+    let _ = subpackets.extend_from_slice(&[22, SUB_ISSUER_FINGERPRINT, 4]);
+    let _ = subpackets.extend_from_slice(&fingerprint(subject));
+
+    let mut hashed = heapless::Vec::new();
+    let _ = hashed.extend_from_slice(&[4, sig_type, ALGO_EDDSA, HASH_SHA256]);
+    let _ = hashed.extend_from_slice(&(subpackets.len() as u16).to_be_bytes());
+    let _ = hashed.extend_from_slice(&subpackets);
+    hashed
+}
+
+/// v4 signature trailer over a hashed part of `len` bytes
+fn trailer(len: usize) -> [u8; 6] {
+    let [a, b, c, d] = (len as u32).to_be_bytes();
+    [4, 0xFF, a, b, c, d]
+}
+
+fn signature_packet(
+    sig_type: u8,
+    subject: &Subject,
+    digest: &[u8; 32],
+    signature: &[u8; 64],
+) -> Vec<u8> {
+    let mut body = hashed_part(sig_type, subject).to_vec();
+    // Unhashed: the issuer key ID, for older gpg versions
+    body.extend([0, 10, 9, SUB_ISSUER]);
+    body.extend(&fingerprint(subject)[12..]);
+    body.extend(&digest[..2]);
+    mpi(&mut body, &signature[..32]);
+    mpi(&mut body, &signature[32..]);
+    packet(TAG_SIGNATURE, &body)
+}
+
+/// Big-endian multiprecision integer: bit count, then the significant bytes
+fn mpi(out: &mut Vec<u8>, bytes: &[u8]) {
+    let bytes = &bytes[bytes.iter().take_while(|&&b| b == 0).count()..];
+    let bits = bytes
+        .first()
+        .map_or(0, |&b| bytes.len() * 8 - b.leading_zeros() as usize);
+    out.extend((bits as u16).to_be_bytes());
+    out.extend(bytes);
+}
+
+/// New-format packet; every packet here is shorter than 192 bytes
+fn packet(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0xC0 | tag, body.len() as u8];
+    packet.extend(body);
+    packet
+}
+
+/// ASCII armor with a CRC-24 checksum
+fn armor(label: &str, data: &[u8]) -> String {
+    let mut out = format!("-----BEGIN PGP {label}-----\n\n");
+    let encoded = companion::base64(data);
+    for line in encoded.as_bytes().chunks(64) {
+        out.push_str(core::str::from_utf8(line).unwrap_or_default());
+        out.push('\n');
+    }
+    out.push('=');
+    out.push_str(&companion::base64(&crc24(data).to_be_bytes()[1..]));
+    out.push_str(&format!("\n-----END PGP {label}-----\n"));
+    out
+}
+
+/// CRC-24 as OpenPGP armor uses it
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xB7_04CE;
+    for &b in data {
+        crc ^= u32::from(b) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= 0x186_4CFB;
+            }
+        }
+    }
+    crc & 0xFF_FFFF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::companion::tests::subject;
+
+    #[test]
+    fn test_crc24() {
+        assert_eq!(crc24(b""), 0xB7_04CE);
+        assert_eq!(crc24(b"123456789"), 0x21_CF02);
+    }
+
+    #[test]
+    fn test_mpi_strips_leading_zeros() {
+        let mut out = Vec::new();
+        mpi(&mut out, &[0x00, 0x01, 0xFF]);
+        assert_eq!(out, [0x00, 0x09, 0x01, 0xFF]);
+    }
+
+    #[test]
+    fn test_packets_bind_the_key_and_payload() {
+        let subject = subject(b"payload");
+        let body = key_body(&subject);
+        assert_eq!(body[..6], [4, 0, 0, 0, 0, ALGO_EDDSA]);
+        assert_eq!(body[19..], *subject.public_key);
+
+        // Certification and data signature never sign the same digest
+        assert_ne!(key_digest(&subject), data_digest(&subject));
+        let other = Subject {
+            payload: b"other",
+            ..subject
+        };
+        assert_ne!(data_digest(&subject), data_digest(&other));
+
+        let hashed = hashed_part(SIG_POSITIVE_CERT, &subject);
+        assert_eq!(hashed.len(), MAX_HASHED_LEN - 4);
+        assert!(hashed
+            .windows(3)
+            .any(|w| w == [2, SUB_KEY_FLAGS, KEY_FLAG_SIGN]));
+    }
+}
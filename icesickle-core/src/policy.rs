@@ -0,0 +1,47 @@
+//! Privacy policy flags carried in the payload
+//!
+//! Some build options give up precision in the signed payload for privacy.
+//! An exact press time, for example, lets anyone who also watched the
+//! device (a camera, a door log) match each proof to the person who
+//! pressed. A payload records the trade-offs it was signed under as a
+//! [`Policy`] (payload version 4), so a verifier reads a rounded time as
+//! rounded rather than as a press that happened on the bucket boundary.
+//!
+//! Policies are chosen by crate feature, like the companion formats;
+//! [`ACTIVE`] is what this build signs with:
+//!
+//! - `coarse-time` ([`Policy::COARSE_TIME`]): `timestamp_ms` and the wall
+//!   clock are rounded down to [`TIME_QUANTUM_MS`], so the milliseconds
+//!   since boot no longer pin down the press. Attestations in one bucket
+//!   are still ordered by their counter.
+//! - `random-counter` ([`Policy::RANDOM_COUNTER`]): the counter starts each
+//!   boot at a random offset below 2^31 instead of 0. A counter from 0
+//!   tells anyone collecting proofs where each boot began and roughly how
+//!   many attestations followed; an offset counter still orders one boot's
+//!   attestations and shows gaps.
+//! - `no-counter` ([`Policy::NO_COUNTER`]): the counter is signed as 0.
+//!   Nothing orders attestations within a time bucket any more, and a
+//!   collector cannot spot gaps. The boot nonce (payload version 8) is
+//!   omitted too: it would link a boot's attestations as surely as a
+//!   counter. Not combinable with `random-counter`.
+//!
+//! Verifiers check the signature over whatever counter was signed, so
+//! every mode verifies alike; the policy only tells them how far to read
+//! meaning into the number.
+
+pub use icesickle_payload::policy::{Policy, TIME_QUANTUM_MS};
+
+/// The policy compiled in
+pub const ACTIVE: Policy = {
+    let mut policy = Policy::NONE;
+    if cfg!(feature = "coarse-time") {
+        policy = policy.union(Policy::COARSE_TIME);
+    }
+    if cfg!(feature = "random-counter") {
+        policy = policy.union(Policy::RANDOM_COUNTER);
+    }
+    if cfg!(feature = "no-counter") {
+        policy = policy.union(Policy::NO_COUNTER);
+    }
+    policy
+};
@@ -0,0 +1,111 @@
+//! Continuous-presence heartbeat mode
+//!
+//! A single attestation proves someone was at the device at one instant.
+//! Some tasks need evidence that the operator stayed for their duration. In
+//! presence mode the device keeps emitting `Presence` attestations every
+//! [`INTERVAL_MS`] for as long as the operator holds the button or taps it
+//! at least once per [`GRACE_MS`]. Each one still goes through the cooldown
+//! gate.
+//!
+//! This trades unlinkability for continuity: a fixed-interval series from
+//! one device is easy to group even though every attestation has a fresh
+//! key. The mode is therefore compiled in only with the firmware's
+//! `presence` feature, and never starts without a touch.
+
+/// Time between presence attestations while the mode is active
+pub const INTERVAL_MS: u64 = 10_000;
+
+/// The mode ends if the button is not touched for this long
+pub const GRACE_MS: u64 = 30_000;
+
+/// Tracks whether the operator is present and when the next attestation is due
+#[derive(Debug, Default)]
+pub struct Presence {
+    last_touch_ms: Option<u64>,
+    last_emit_ms: Option<u64>,
+}
+
+impl Presence {
+    pub const fn new() -> Self {
+        Self {
+            last_touch_ms: None,
+            last_emit_ms: None,
+        }
+    }
+
+    /// The operator is holding or has just tapped the button
+    pub fn touch(&mut self, now_ms: u64) {
+        if !self.is_active(now_ms) {
+            // A new series starts one interval after the first touch
+            self.last_emit_ms = Some(now_ms);
+        }
+        self.last_touch_ms = Some(now_ms);
+    }
+
+    /// True while touches keep arriving within the grace period
+    pub fn is_active(&self, now_ms: u64) -> bool {
+        self.last_touch_ms
+            .is_some_and(|t| now_ms.saturating_sub(t) < GRACE_MS)
+    }
+
+    /// Claim the next presence attestation, if one is due
+    ///
+    /// A claimed slot is spent whether or not signing succeeds, so a refused
+    /// or failed attempt is retried one interval later, not every loop.
+    pub fn take_due(&mut self, now_ms: u64) -> bool {
+        if !self.is_active(now_ms) {
+            self.last_emit_ms = None;
+            return false;
+        }
+        let due = match self.last_emit_ms {
+            Some(t) => now_ms.saturating_sub(t) >= INTERVAL_MS,
+            None => true,
+        };
+        if due {
+            self.last_emit_ms = Some(now_ms);
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inactive_without_touch() {
+        let mut presence = Presence::new();
+        assert!(!presence.take_due(0));
+        assert!(!presence.take_due(INTERVAL_MS * 5));
+    }
+
+    #[test]
+    fn test_held_button_emits_every_interval() {
+        let mut presence = Presence::new();
+        presence.touch(1_000);
+        assert!(!presence.take_due(1_000));
+
+        let mut now = 1_000;
+        for _ in 0..3 {
+            now += INTERVAL_MS;
+            presence.touch(now);
+            assert!(presence.take_due(now));
+            assert!(!presence.take_due(now + 1));
+        }
+    }
+
+    #[test]
+    fn test_mode_ends_after_grace() {
+        let mut presence = Presence::new();
+        presence.touch(0);
+        assert!(presence.take_due(INTERVAL_MS));
+        assert!(presence.take_due(2 * INTERVAL_MS));
+        assert!(!presence.take_due(GRACE_MS));
+        assert!(!presence.is_active(GRACE_MS));
+
+        // A fresh touch starts a new series, not an immediate attestation
+        presence.touch(GRACE_MS + 5_000);
+        assert!(!presence.take_due(GRACE_MS + 5_000));
+        assert!(presence.take_due(GRACE_MS + 5_000 + INTERVAL_MS));
+    }
+}
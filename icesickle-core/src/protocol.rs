@@ -0,0 +1,873 @@
+//! Versioned serial command protocol
+//!
+//! Hosts talk to the device with framed request/response messages over the
+//! serial port. Each frame on the wire is:
+//!
+//! ```text
+//! COBS( protocol_version: u8 || seq: u8 || postcard(message) || crc16_le ) || 0x00
+//! ```
+//!
+//! - COBS guarantees the payload contains no zero bytes, so `0x00` is an
+//!   unambiguous frame delimiter and a receiver can resynchronize after
+//!   garbage (e.g. interleaved log text) by discarding up to the next zero.
+//! - The version byte comes first and is checked before anything else, so a
+//!   device can reject a host speaking a different protocol revision instead
+//!   of misinterpreting its bytes.
+//! - `crc16` is CRC-16/CCITT-FALSE over everything before it. A frame that
+//!   fails the check is answered with [`ErrorCode::Checksum`] and otherwise
+//!   ignored; it never reaches a command handler.
+//! - `seq` is the request's correlation ID: chosen by the host, incremented
+//!   for every new request, and echoed in the response. A host that times out
+//!   resends the *same* frame; the device recognizes the repeat and replays
+//!   its cached response rather than executing the command twice.
+//! - Message bodies are postcard-encoded enums. Variant order is the wire
+//!   command code: variants are append-only and must never be reordered.
+//!
+//! # Pipelining
+//!
+//! A host may have up to [`MAX_IN_FLIGHT`] requests outstanding and match
+//! replies by `seq`. Requests are executed and answered strictly in arrival
+//! order, and the device caches that many replies for retransmission. A
+//! damaged frame's `seq` cannot be trusted, so a host that receives a
+//! `Checksum` or `Malformed` error it cannot match should resend every
+//! outstanding request; the cache makes that safe.
+//!
+//! # Resynchronization
+//!
+//! A host that loses track of the link sends a lone `0x00`: any partial frame
+//! is discarded with it, and empty frames are otherwise ignored. The device
+//! also drops a partial frame on its own if the link goes quiet mid-frame
+//! for [`FRAME_TIMEOUT_MS`].
+//!
+//! Only public data ever crosses this interface. There is no command that
+//! reads key material, and there is nothing to read: keys are gone before
+//! any response is built. The one secret that travels the other way, an
+//! authorization token in [`Request::LoadToken`], is refused outside an
+//! encrypted session.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::attestation::{self, Attestation};
+use crate::context::Context;
+use crate::error::IceSickleError;
+use crate::instrument::PhaseCycles;
+
+pub use icesickle_payload::attestation::AttestationRecord;
+
+/// Current protocol revision (first byte of every frame)
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// Oldest host protocol revision accepted in [`Request::Hello`]
+///
+/// Raise this when a security-relevant protocol change ships so hosts that
+/// predate it are refused instead of talking to the device unaware.
+pub const MIN_HOST_PROTOCOL_VERSION: u8 = 2;
+
+/// Interval between unsolicited [`Response::Heartbeat`] frames
+pub const HEARTBEAT_INTERVAL_MS: u64 = 5_000;
+
+/// Requests a host may have outstanding at once (see module docs)
+pub const MAX_IN_FLIGHT: u8 = 4;
+
+/// A partial frame is dropped after this long without another byte
+pub const FRAME_TIMEOUT_MS: u64 = 500;
+
+/// Largest encoded frame accepted or produced, excluding the delimiter
+pub const MAX_FRAME_LEN: usize = 384;
+
+/// Most tasks reported in [`Memory::stacks`] (one per [`Task`])
+pub const MAX_TASKS: usize = 6;
+
+/// Largest opaque blob carried in a request (tokens, config values)
+pub const MAX_BLOB_LEN: usize = 64;
+
+/// Largest sealed (encrypted + tagged) message body
+pub const MAX_SEALED_LEN: usize = 352;
+
+/// Largest data chunk in a [`Request::DigestUpdate`]
+pub const MAX_CHUNK_LEN: usize = 160;
+
+/// Longest firmware version string in a [`Version`]
+pub const MAX_VERSION_LEN: usize = 24;
+
+/// Frame delimiter
+pub(crate) const DELIMITER: u8 = 0x00;
+
+/// Host → device requests
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Request {
+    /// Uptime, attestation count and cooldown state
+    GetStatus,
+    /// Public fields of the most recent attestation this boot
+    GetLastAttestation,
+    /// Arm a verifier challenge; salts the next keypad entry (feature `keypad`)
+    SetChallenge { challenge: [u8; 32] },
+    /// Load a one-time authorization token for a later press to spend
+    /// (see `auth`); accepted only inside a session
+    LoadToken {
+        token: heapless::Vec<u8, MAX_BLOB_LEN>,
+    },
+    /// Change a configuration value (provisioning mode only)
+    SetConfig {
+        key: u8,
+        value: heapless::Vec<u8, MAX_BLOB_LEN>,
+    },
+    /// Start an encrypted session (see `session.rs`)
+    Handshake { host_public: [u8; 32] },
+    /// A request encrypted under the current session
+    Sealed(SealedFrame),
+    /// Tear down the current session and zeroize its keys
+    EndSession,
+    /// Start hashing host data for hash-then-sign (see `digest.rs`)
+    DigestBegin,
+    /// Next chunk of host data
+    DigestUpdate {
+        chunk: heapless::Vec<u8, MAX_CHUNK_LEN>,
+    },
+    /// Finish hashing and arm the digest for the next button press
+    DigestFinish,
+    /// Open a command session, advertising the newest protocol and payload
+    /// versions the host understands
+    Hello {
+        protocol_version: u8,
+        payload_version: u8,
+    },
+    /// The oldest kept attestation with a counter of at least `counter`
+    /// (see `history`)
+    GetHistory { counter: u32 },
+    /// Arm a verifier challenge for the next button press to sign (see
+    /// `challenge`)
+    ArmChallenge { challenge: [u8; 32] },
+    /// Firmware version and the wire versions it speaks
+    GetVersion,
+    /// Sign the presses batched so far as one attestation (see `batch`)
+    SignBatch,
+    /// Set application context for the next button press to sign (see
+    /// `context`)
+    SetContext { context: Context },
+}
+
+/// Device → host responses
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Response {
+    /// Request accepted, nothing to return
+    Ok,
+    Status(Status),
+    Attestation(AttestationRecord),
+    Error(ErrorCode),
+    /// Device half of the session handshake
+    Handshake {
+        device_public: [u8; 32],
+    },
+    /// A response encrypted under the current session
+    Sealed(SealedFrame),
+    /// Digest armed for approval by [`Request::DigestFinish`]
+    Digest {
+        sha256: [u8; 32],
+        len: u64,
+    },
+    /// Unsolicited, unsigned liveness report (see [`Heartbeat`])
+    Heartbeat(Heartbeat),
+    /// Versions the device speaks, in reply to [`Request::Hello`]
+    Hello {
+        protocol_version: u8,
+        min_protocol_version: u8,
+        payload_version: u8,
+        max_in_flight: u8,
+    },
+    Version(Version),
+}
+
+/// An encrypted message: `ciphertext` is the AEAD output including its tag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedFrame {
+    pub counter: u64,
+    pub ciphertext: heapless::Vec<u8, MAX_SEALED_LEN>,
+}
+
+/// Reply to [`Request::GetStatus`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    /// Milliseconds since boot
+    pub uptime_ms: u64,
+    /// Attestations produced since boot
+    pub attestations: u32,
+    /// Milliseconds until the next attestation is allowed (0 = ready)
+    pub cooldown_remaining_ms: u64,
+    /// Cycle counts of the last attestation (`instrument` builds only)
+    pub timing: Option<PhaseCycles>,
+    /// Serial link error counters since boot
+    pub link: LinkErrors,
+    /// Device health counters since boot
+    pub telemetry: Telemetry,
+    /// JTAG lockdown state
+    pub debug: DebugInterfaces,
+    /// Stack and heap high-water marks
+    pub memory: Memory,
+    /// Cooldown now in force, backoff included
+    pub cooldown_ms: u64,
+    /// The tamper loop opened, this boot or before it (see the firmware's
+    /// `tamper`): the device is locked out until a jumper reset
+    pub tampered: bool,
+    /// Presses the cooldown's bucket holds now, up to its burst
+    pub cooldown_level: u8,
+}
+
+/// Reply to [`Request::GetVersion`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Version {
+    /// Firmware crate version (`CARGO_PKG_VERSION`)
+    pub firmware: heapless::String<MAX_VERSION_LEN>,
+    /// [`PROTOCOL_VERSION`]
+    pub protocol_version: u8,
+    /// Attestation payload version signed by this firmware
+    pub payload_version: u8,
+}
+
+impl Version {
+    /// This protocol and payload revision, running `firmware` (truncated to
+    /// [`MAX_VERSION_LEN`] bytes)
+    pub fn new(firmware: &str) -> Self {
+        let mut end = firmware.len().min(MAX_VERSION_LEN);
+        while !firmware.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut version = heapless::String::new();
+        // Cannot fail: `end` is within capacity
+        let _ = version.push_str(&firmware[..end]);
+        Self {
+            firmware: version,
+            protocol_version: PROTOCOL_VERSION,
+            payload_version: attestation::PAYLOAD_VERSION,
+        }
+    }
+}
+
+/// Periodic device health report
+///
+/// Heartbeats are **unsigned** and carry nothing a verifier could mistake
+/// for evidence: only attestations (the `Attestation` response) are signed.
+/// They are sent unprompted with sequence number 0 and are told apart from
+/// replies by their variant tag, never by `seq`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    /// Milliseconds since boot
+    pub uptime_ms: u64,
+    /// Milliseconds until the next attestation is allowed (0 = ready)
+    pub cooldown_remaining_ms: u64,
+    /// Requests waiting on a button press (armed digest or challenge, CTAPHID
+    /// presence)
+    pub queue_depth: u8,
+    /// Result of the hardware RNG runtime health check
+    pub entropy_ok: bool,
+}
+
+/// Serial link error counters (saturating, reset on boot)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkErrors {
+    /// Frames that failed the CRC
+    pub checksum: u32,
+    /// Frames that failed COBS, version or message decoding
+    pub malformed: u32,
+    /// Frames dropped for exceeding the size limit
+    pub overflow: u32,
+    /// Partial frames dropped after the link went quiet
+    pub timeout: u32,
+    /// Retransmitted requests answered from the response cache
+    pub retransmit: u32,
+    /// Heartbeats skipped because the host was not reading output
+    pub dropped: u32,
+}
+
+impl LinkErrors {
+    /// Count a receive error against the matching counter
+    pub fn record(&mut self, code: ErrorCode) {
+        let counter = match code {
+            ErrorCode::Checksum => &mut self.checksum,
+            ErrorCode::FrameTooLong => &mut self.overflow,
+            _ => &mut self.malformed,
+        };
+        *counter = counter.saturating_add(1);
+    }
+}
+
+/// Device health counters (saturating, reset on boot; see `telemetry`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Telemetry {
+    /// Button presses refused by the cooldown
+    pub cooldown_rejected: u32,
+    /// Responses or reports that could not be delivered
+    pub sink_errors: u32,
+    /// Failed hardware RNG health checks
+    pub rng_health_failures: u32,
+}
+
+/// Which debug paths are closed, and how
+///
+/// Fused paths are closed permanently; `usb_jtag_cut` means the firmware
+/// disconnected USB-Serial-JTAG at boot (release builds only).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DebugInterfaces {
+    /// `DIS_PAD_JTAG` eFuse burnt
+    pub pad_jtag_fused: bool,
+    /// `DIS_USB_JTAG` eFuse burnt
+    pub usb_jtag_fused: bool,
+    /// USB-Serial-JTAG pads disabled by firmware for this boot
+    pub usb_jtag_cut: bool,
+}
+
+/// A task whose stack is reported in [`Memory`] (append-only)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Task {
+    /// The event loop: signing, the command protocol, every driver
+    Main,
+    /// ESP-IDF timer callbacks
+    Timer,
+    /// Inter-processor calls to core 0
+    Ipc0,
+    /// Inter-processor calls to core 1
+    Ipc1,
+    /// TinyUSB device stack and its callbacks (`usb-hid`, `usb-msc`)
+    Usb,
+    /// Press signing on core 1 (`signer-core`)
+    Signer,
+}
+
+/// Least untouched stack a task has had since boot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackMark {
+    pub task: Task,
+    /// Bytes of stack never used
+    pub headroom: u32,
+}
+
+/// Stack and heap high-water marks since boot
+///
+/// Marks only ever fall, so a headroom or free heap that keeps shrinking
+/// over days of uptime points at a leak or a path that runs deeper than
+/// expected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Memory {
+    /// Tasks running in this build
+    pub stacks: heapless::Vec<StackMark, MAX_TASKS>,
+    /// Free heap now, in bytes
+    pub heap_free: u32,
+    /// Least free heap since boot; the total less this is the peak use
+    pub heap_min_free: u32,
+}
+
+impl From<&Attestation> for AttestationRecord {
+    fn from(attestation: &Attestation) -> Self {
+        Self {
+            version: attestation.version(),
+            event: attestation.event(),
+            timestamp_ms: attestation.timestamp_ms(),
+            counter: attestation.counter(),
+            public_key: *attestation.public_key_bytes(),
+            signature: *attestation.signature_bytes(),
+            wall_clock: attestation.wall_clock(),
+            policy: attestation.policy(),
+            suppressed: attestation.suppressed(),
+            challenge: attestation.challenge(),
+            token: attestation.token(),
+            boot_nonce: attestation.boot_nonce(),
+            algorithm: attestation.algorithm(),
+            context: attestation.context().cloned(),
+            measurement: attestation.measurement(),
+        }
+    }
+}
+
+/// Error codes returned in [`Response::Error`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+    /// Frame carried a protocol version this firmware does not speak
+    UnsupportedVersion,
+    /// Frame failed COBS or message decoding
+    Malformed,
+    /// Frame exceeded [`MAX_FRAME_LEN`] and was discarded
+    FrameTooLong,
+    /// Nothing to return (e.g. no attestation yet this boot)
+    NotFound,
+    /// Command is defined but not available in this build
+    Unsupported,
+    /// Command requires provisioning mode
+    Locked,
+    /// Sealed frame received with no session established
+    NoSession,
+    /// Sealed frame failed authentication or reused a counter
+    Decrypt,
+    /// Plaintext command received while a session is active
+    SessionRequired,
+    /// Command not valid in the current state (e.g. update before begin)
+    InvalidState,
+    /// Frame failed its CRC check
+    Checksum,
+    /// Command sent before the [`Request::Hello`] exchange
+    HelloRequired,
+}
+
+/// Answer a host's [`Request::Hello`]
+///
+/// Refuses hosts older than [`MIN_HOST_PROTOCOL_VERSION`], and hosts that
+/// could not parse the attestation payloads this firmware signs.
+pub fn negotiate(host_protocol_version: u8, host_payload_version: u8) -> Response {
+    if host_protocol_version < MIN_HOST_PROTOCOL_VERSION
+        || host_payload_version < attestation::PAYLOAD_VERSION
+    {
+        return Response::Error(ErrorCode::UnsupportedVersion);
+    }
+
+    Response::Hello {
+        protocol_version: PROTOCOL_VERSION,
+        min_protocol_version: MIN_HOST_PROTOCOL_VERSION,
+        payload_version: attestation::PAYLOAD_VERSION,
+        max_in_flight: MAX_IN_FLIGHT,
+    }
+}
+
+/// A frame received from the host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inbound {
+    /// Sequence number to echo (0 if the frame was too damaged to carry one)
+    pub seq: u8,
+    /// Frame CRC; a repeated `(seq, crc)` pair marks a retransmission
+    pub crc: u16,
+    pub request: Result<Request, ErrorCode>,
+}
+
+/// Encode `message` as a complete frame (including the trailing delimiter)
+pub fn encode_frame<'a, T: Serialize>(
+    seq: u8,
+    message: &T,
+    out: &'a mut [u8],
+) -> crate::Result<&'a [u8]> {
+    let mut raw = [0u8; MAX_FRAME_LEN];
+    raw[0] = PROTOCOL_VERSION;
+    raw[1] = seq;
+    let body_len = postcard::to_slice(message, &mut raw[2..MAX_FRAME_LEN - 2])?.len();
+
+    let crc_at = 2 + body_len;
+    let crc = crc16(&raw[..crc_at]);
+    raw[crc_at..crc_at + 2].copy_from_slice(&crc.to_le_bytes());
+
+    let len = cobs_encode(&raw[..crc_at + 2], out)
+        .filter(|&len| len < out.len())
+        .ok_or(IceSickleError::Serialize)?;
+    out[len] = DELIMITER;
+
+    Ok(&out[..len + 1])
+}
+
+/// Decode a single frame (without its delimiter) in place
+pub fn decode_frame<T: DeserializeOwned>(frame: &mut [u8]) -> (u8, u16, Result<T, ErrorCode>) {
+    let Some(len) = cobs_decode_in_place(frame) else {
+        return (0, 0, Err(ErrorCode::Malformed));
+    };
+    let raw = &frame[..len];
+
+    // version + seq + crc is the smallest possible frame
+    if len < 4 {
+        return (0, 0, Err(ErrorCode::Malformed));
+    }
+    let seq = raw[1];
+    if raw[0] != PROTOCOL_VERSION {
+        return (seq, 0, Err(ErrorCode::UnsupportedVersion));
+    }
+
+    let (content, crc_bytes) = raw.split_at(len - 2);
+    let crc = u16::from_le_bytes([crc_bytes[0], crc_bytes[1]]);
+    if crc16(content) != crc {
+        return (seq, crc, Err(ErrorCode::Checksum));
+    }
+
+    let message = postcard::from_bytes(&content[2..]).map_err(|_| ErrorCode::Malformed);
+    (seq, crc, message)
+}
+
+/// Accumulates received bytes into frames
+///
+/// Bytes are buffered until a delimiter arrives. Frames longer than
+/// [`MAX_FRAME_LEN`] are dropped whole (reported once, at their delimiter)
+/// so a runaway sender can never grow memory use or wedge the parser.
+pub struct FrameReader {
+    buf: [u8; MAX_FRAME_LEN],
+    len: usize,
+    overflowed: bool,
+}
+
+impl FrameReader {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; MAX_FRAME_LEN],
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Feed one received byte
+    ///
+    /// Returns `Some` when the byte completes a frame: the decoded request,
+    /// or the error to report back to the host.
+    pub fn push(&mut self, byte: u8) -> Option<Inbound> {
+        if byte != DELIMITER {
+            if self.len < MAX_FRAME_LEN {
+                self.buf[self.len] = byte;
+                self.len += 1;
+            } else {
+                self.overflowed = true;
+            }
+            return None;
+        }
+
+        let len = core::mem::take(&mut self.len);
+        if core::mem::take(&mut self.overflowed) {
+            return Some(Inbound {
+                seq: 0,
+                crc: 0,
+                request: Err(ErrorCode::FrameTooLong),
+            });
+        }
+        if len == 0 {
+            // Back-to-back delimiters are harmless (hosts send one to resync)
+            return None;
+        }
+
+        let (seq, crc, request) = decode_frame(&mut self.buf[..len]);
+        Some(Inbound { seq, crc, request })
+    }
+
+    /// Drop a partially received frame; true if there was one
+    pub fn discard_partial(&mut self) -> bool {
+        let had_partial = self.len > 0 || self.overflowed;
+        self.len = 0;
+        self.overflowed = false;
+        had_partial
+    }
+}
+
+impl Default for FrameReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF, no reflection)
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &b in data {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// COBS-encode `src` into `dst` (no delimiter); `None` if `dst` is too small
+pub(crate) fn cobs_encode(src: &[u8], dst: &mut [u8]) -> Option<usize> {
+    let mut code_idx = 0;
+    let mut out = 1;
+    let mut code: u8 = 1;
+
+    for &b in src {
+        if b != 0 {
+            *dst.get_mut(out)? = b;
+            out += 1;
+            code += 1;
+        }
+        if b == 0 || code == 0xFF {
+            *dst.get_mut(code_idx)? = code;
+            code_idx = out;
+            out += 1;
+            code = 1;
+        }
+    }
+
+    *dst.get_mut(code_idx)? = code;
+    Some(out)
+}
+
+/// COBS-decode `buf` in place; returns the decoded length
+pub(crate) fn cobs_decode_in_place(buf: &mut [u8]) -> Option<usize> {
+    let mut read = 0;
+    let mut write = 0;
+
+    while read < buf.len() {
+        let code = buf[read];
+        if code == 0 {
+            return None;
+        }
+        read += 1;
+
+        for _ in 1..code {
+            let b = *buf.get(read)?;
+            if b == 0 {
+                return None;
+            }
+            buf[write] = b;
+            write += 1;
+            read += 1;
+        }
+
+        if code != 0xFF && read < buf.len() {
+            buf[write] = 0;
+            write += 1;
+        }
+    }
+
+    Some(write)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::AttestationEvent;
+    use crate::auth::TokenProof;
+    use crate::context::MAX_CONTEXT_LEN;
+    use crate::policy::Policy;
+    use crate::scheme::Algorithm;
+    use crate::wallclock::WallTime;
+
+    fn cobs_round_trip(data: &[u8]) {
+        let mut encoded = [0u8; 600];
+        let len = cobs_encode(data, &mut encoded).unwrap();
+        assert!(!encoded[..len].contains(&0));
+        let decoded = cobs_decode_in_place(&mut encoded[..len]).unwrap();
+        assert_eq!(&encoded[..decoded], data);
+    }
+
+    #[test]
+    fn test_cobs_round_trip() {
+        cobs_round_trip(&[]);
+        cobs_round_trip(&[0]);
+        cobs_round_trip(&[0, 0, 1, 0]);
+        cobs_round_trip(&[0x11, 0x22, 0x00, 0x33]);
+        cobs_round_trip(&[0xAB; 254]);
+        cobs_round_trip(&[0xAB; 255]);
+        cobs_round_trip(&[0xAB; 520]);
+    }
+
+    fn feed(reader: &mut FrameReader, bytes: &[u8]) -> Option<Result<Request, ErrorCode>> {
+        let mut result = None;
+        for &b in bytes {
+            if let Some(inbound) = reader.push(b) {
+                result = Some(inbound.request);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_crc16_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let request = Request::SetChallenge {
+            challenge: [0x42; 32],
+        };
+        let frame = encode_frame(7, &request, &mut out).unwrap();
+
+        let mut reader = FrameReader::new();
+        let mut inbound = None;
+        for &b in frame {
+            inbound = inbound.or(reader.push(b));
+        }
+        let inbound = inbound.unwrap();
+        assert_eq!(inbound.seq, 7);
+        assert_eq!(inbound.request, Ok(request));
+    }
+
+    #[test]
+    fn test_version_reply() {
+        let version = Version::new("0.1.0");
+        assert_eq!(version.firmware.as_str(), "0.1.0");
+        assert_eq!(version.payload_version, attestation::PAYLOAD_VERSION);
+        let long = Version::new("1.2.3-rc.4+0123456789abcdef");
+        assert_eq!(long.firmware.len(), MAX_VERSION_LEN);
+
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let frame = encode_frame(3, &Response::Version(version), &mut out).unwrap();
+        assert!(frame.len() <= MAX_FRAME_LEN + 1);
+    }
+
+    #[test]
+    fn test_resyncs_after_garbage() {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let frame = encode_frame(1, &Request::GetStatus, &mut out).unwrap();
+
+        let mut reader = FrameReader::new();
+        assert_eq!(
+            feed(&mut reader, b"I (123) log line\n\0"),
+            Some(Err(ErrorCode::Malformed))
+        );
+        assert_eq!(feed(&mut reader, frame), Some(Ok(Request::GetStatus)));
+    }
+
+    #[test]
+    fn test_corrupted_frame_fails_checksum() {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let request = Request::SetChallenge {
+            challenge: [0x42; 32],
+        };
+        let len = encode_frame(3, &request, &mut out).unwrap().len();
+
+        // Flip a bit inside the challenge (never produces a zero byte here)
+        out[10] ^= 0x01;
+        let mut reader = FrameReader::new();
+        assert_eq!(
+            feed(&mut reader, &out[..len]),
+            Some(Err(ErrorCode::Checksum))
+        );
+    }
+
+    #[test]
+    fn test_rejects_other_protocol_version() {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let len = encode_frame(1, &Request::GetStatus, &mut out)
+            .unwrap()
+            .len();
+
+        // Frame is [code, version, seq, ...]; bump the version byte
+        out[1] = PROTOCOL_VERSION + 1;
+        let mut reader = FrameReader::new();
+        assert_eq!(
+            feed(&mut reader, &out[..len]),
+            Some(Err(ErrorCode::UnsupportedVersion))
+        );
+    }
+
+    #[test]
+    fn test_oversized_frame_dropped() {
+        let mut reader = FrameReader::new();
+        let garbage = [0x01u8; MAX_FRAME_LEN + 10];
+        assert_eq!(feed(&mut reader, &garbage), None);
+        assert_eq!(
+            reader.push(0).map(|inbound| inbound.request),
+            Some(Err(ErrorCode::FrameTooLong))
+        );
+
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let frame = encode_frame(2, &Request::GetStatus, &mut out).unwrap();
+        assert_eq!(feed(&mut reader, frame), Some(Ok(Request::GetStatus)));
+    }
+
+    #[test]
+    fn test_largest_status_fits_a_sealed_response() {
+        let mark = StackMark {
+            task: Task::Usb,
+            headroom: u32::MAX,
+        };
+        let status = Response::Status(Status {
+            uptime_ms: u64::MAX,
+            attestations: u32::MAX,
+            cooldown_remaining_ms: u64::MAX,
+            timing: Some(PhaseCycles {
+                keygen: u32::MAX,
+                serialize: u32::MAX,
+                sign: u32::MAX,
+                output: u32::MAX,
+            }),
+            link: LinkErrors {
+                checksum: u32::MAX,
+                malformed: u32::MAX,
+                overflow: u32::MAX,
+                timeout: u32::MAX,
+                retransmit: u32::MAX,
+                dropped: u32::MAX,
+            },
+            telemetry: Telemetry {
+                cooldown_rejected: u32::MAX,
+                sink_errors: u32::MAX,
+                rng_health_failures: u32::MAX,
+            },
+            debug: DebugInterfaces::default(),
+            memory: Memory {
+                stacks: heapless::Vec::from_slice(&[mark; MAX_TASKS]).unwrap(),
+                heap_free: u32::MAX,
+                heap_min_free: u32::MAX,
+            },
+            cooldown_ms: u64::MAX,
+            tampered: true,
+            cooldown_level: u8::MAX,
+        });
+        // Sealing appends a 16-byte tag
+        let mut buf = [0u8; MAX_SEALED_LEN - 16];
+        assert!(postcard::to_slice(&status, &mut buf).is_ok());
+    }
+
+    #[test]
+    fn test_largest_attestation_fits_a_sealed_response() {
+        let attestation = Response::Attestation(AttestationRecord {
+            version: u8::MAX,
+            event: AttestationEvent::DataDigest {
+                gpio: u8::MAX,
+                sha256: [0xFF; 32],
+                len: u64::MAX,
+            },
+            timestamp_ms: u64::MAX,
+            counter: u32::MAX,
+            public_key: [0xFF; 32],
+            signature: [0xFF; 64],
+            wall_clock: Some(WallTime {
+                unix_s: u64::MAX,
+                source: crate::wallclock::TimeSource::Gps,
+                stale: true,
+            }),
+            policy: Policy::from_bits(u8::MAX),
+            suppressed: u32::MAX,
+            challenge: Some([0xFF; 32]),
+            token: Some(TokenProof::from_bytes(&[0xFF; 32])),
+            boot_nonce: Some([0xFF; 16]),
+            algorithm: Algorithm::Ed25519,
+            context: Some(Context::from_slice(&[0xFF; MAX_CONTEXT_LEN]).unwrap()),
+            measurement: Some([0xFF; 32]),
+        });
+        let mut buf = [0u8; MAX_SEALED_LEN - 16];
+        assert!(postcard::to_slice(&attestation, &mut buf).is_ok());
+
+        // The sealed frame still fits the link
+        let sealed = Response::Sealed(SealedFrame {
+            counter: u64::MAX,
+            ciphertext: heapless::Vec::from_slice(&[0xFF; MAX_SEALED_LEN]).unwrap(),
+        });
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        assert!(encode_frame(u8::MAX, &sealed, &mut out).is_ok());
+    }
+
+    #[test]
+    fn test_hello_refuses_downlevel_hosts() {
+        let payload = attestation::PAYLOAD_VERSION;
+        assert!(matches!(
+            negotiate(PROTOCOL_VERSION, payload),
+            Response::Hello { .. }
+        ));
+        assert_eq!(
+            negotiate(MIN_HOST_PROTOCOL_VERSION - 1, payload),
+            Response::Error(ErrorCode::UnsupportedVersion)
+        );
+        assert_eq!(
+            negotiate(PROTOCOL_VERSION, payload - 1),
+            Response::Error(ErrorCode::UnsupportedVersion)
+        );
+    }
+
+    #[test]
+    fn test_discard_partial_frame() {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let frame = encode_frame(4, &Request::GetStatus, &mut out).unwrap();
+
+        let mut reader = FrameReader::new();
+        assert_eq!(feed(&mut reader, &frame[..2]), None);
+        assert!(reader.discard_partial());
+        assert_eq!(feed(&mut reader, frame), Some(Ok(Request::GetStatus)));
+    }
+}
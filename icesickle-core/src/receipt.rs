@@ -0,0 +1,183 @@
+//! Thermal receipt output (feature `receipt`)
+//!
+//! Renders an attestation as an ESC/POS print job for a serial thermal
+//! receipt printer: a short human-readable summary, then a QR code of the
+//! fixed-format line (see [`Attestation::fixed_line`]), which carries every
+//! signed field, the key and the signature. The slip is a physical receipt
+//! of the physical event. The summary is for the person holding it; a
+//! verifier scans the code and checks the signature, and trusts nothing
+//! else printed.
+//!
+//! The printer draws the QR code itself (`GS ( k`, implemented by Epson
+//! TM-series printers and most 58/80 mm clones), so the device carries no
+//! QR encoder. The longest line (593 bytes) needs version 19 at level M:
+//! 93 modules at [`QR_MODULE_DOTS`] is 372 dots, inside a 58 mm printer's
+//! 384. A firmware measurement adds 65 bytes and a version too wide for
+//! the paper, so the firmware refuses the `measurement` feature with this
+//! one.
+
+use core::fmt::{self, Write};
+
+use crate::attestation::Attestation;
+use crate::wallclock::{self, TimeSource};
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+
+/// Reset the printer to its power-on modes
+const INIT: [u8; 2] = [ESC, b'@'];
+const ALIGN_LEFT: [u8; 3] = [ESC, b'a', 0];
+const ALIGN_CENTRE: [u8; 3] = [ESC, b'a', 1];
+const BOLD_ON: [u8; 3] = [ESC, b'E', 1];
+const BOLD_OFF: [u8; 3] = [ESC, b'E', 0];
+
+/// QR model 2
+const QR_MODEL: [u8; 9] = [GS, b'(', b'k', 4, 0, b'1', b'A', b'2', 0];
+
+/// Dots per QR module
+pub const QR_MODULE_DOTS: u8 = 4;
+
+/// QR error correction level M (15%), for creased or faded paper
+const QR_ERROR_CORRECTION: [u8; 8] = [GS, b'(', b'k', 3, 0, b'1', b'E', b'1'];
+
+/// Print the stored QR symbol
+const QR_PRINT: [u8; 8] = [GS, b'(', b'k', 3, 0, b'1', b'Q', b'0'];
+
+/// Feed four lines so the code clears the tear bar
+const FEED: [u8; 3] = [ESC, b'd', 4];
+
+/// Feed to the cutter and cut, leaving a hinge (ignored without a cutter)
+const CUT: [u8; 4] = [GS, b'V', 66, 0];
+
+/// Largest print job: the summary and a full-length fixed line
+pub const MAX_RECEIPT_LEN: usize = 1152;
+
+/// ESC/POS print job
+pub type Receipt = heapless::Vec<u8, MAX_RECEIPT_LEN>;
+
+/// Render `attestation` as a print job
+pub fn render(attestation: &Attestation) -> Receipt {
+    let mut out = Receipt::new();
+    // Capacity covers the longest summary and fixed line (see the tests)
+    let _ = out.extend_from_slice(&INIT);
+    let _ = out.extend_from_slice(&ALIGN_CENTRE);
+    let _ = out.extend_from_slice(&BOLD_ON);
+    let _ = writeln!(Text(&mut out), "ICESICKLE ATTESTATION");
+    let _ = out.extend_from_slice(&BOLD_OFF);
+    let _ = out.extend_from_slice(&ALIGN_LEFT);
+
+    let mut text = Text(&mut out);
+    let _ = writeln!(text, "Event:   {}", attestation.event().name());
+    let _ = writeln!(text, "Counter: {}", attestation.counter());
+    let _ = writeln!(text, "Uptime:  {} ms", attestation.timestamp_ms());
+    let _ = match attestation.wall_clock() {
+        Some(wall) => writeln!(
+            text,
+            "UTC:     {} ({}{})",
+            date_time(wall.unix_s),
+            match wall.source {
+                TimeSource::Gps => "gps",
+                TimeSource::Rtc => "rtc",
+            },
+            if wall.stale { ", STALE" } else { "" }
+        ),
+        None => writeln!(text, "UTC:     unknown"),
+    };
+    let _ = writeln!(text, "Key:\n{}", attestation.public_key_hex());
+    let _ = writeln!(text, "Signature:\n{}", attestation.signature_hex());
+
+    let _ = out.extend_from_slice(&ALIGN_CENTRE);
+    qr(&mut out, attestation.fixed_line().trim_end().as_bytes());
+    let _ = writeln!(Text(&mut out), "Scan to verify");
+    let _ = out.extend_from_slice(&FEED);
+    let _ = out.extend_from_slice(&CUT);
+    out
+}
+
+/// Store `data` as the QR symbol and print it
+fn qr(out: &mut Receipt, data: &[u8]) {
+    let _ = out.extend_from_slice(&QR_MODEL);
+    let _ = out.extend_from_slice(&[GS, b'(', b'k', 3, 0, b'1', b'C', QR_MODULE_DOTS]);
+    let _ = out.extend_from_slice(&QR_ERROR_CORRECTION);
+    // Length counts the three function bytes after it
+    let [low, high] = ((data.len() + 3) as u16).to_le_bytes();
+    let _ = out.extend_from_slice(&[GS, b'(', b'k', low, high, b'1', b'P', b'0']);
+    let _ = out.extend_from_slice(data);
+    let _ = out.extend_from_slice(&QR_PRINT);
+}
+
+/// `YYYY-MM-DD hh:mm:ss` for UTC seconds
+fn date_time(unix_s: u64) -> heapless::String<32> {
+    let (year, month, day) = wallclock::civil_from_days(unix_s / 86_400);
+    let second = unix_s % 86_400;
+    let mut out = heapless::String::new();
+    let _ = write!(
+        out,
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        second / 3_600,
+        second / 60 % 60,
+        second % 60
+    );
+    out
+}
+
+/// Text written straight into the print job
+struct Text<'a>(&'a mut Receipt);
+
+impl Write for Text<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0
+            .extend_from_slice(s.as_bytes())
+            .map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::{AttestationEvent, FixedLine};
+    use crate::entropy::HardwareRng;
+    use crate::hal::mock::{MockNoise, MockTimer};
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn test_date_time() {
+        assert_eq!(date_time(0), "1970-01-01 00:00:00");
+        assert_eq!(date_time(1_709_251_199), "2024-02-29 23:59:59");
+        assert!(date_time(u64::MAX).ends_with(":15"));
+    }
+
+    #[test]
+    fn test_receipt_layout() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let event = AttestationEvent::DataDigest {
+            gpio: 0,
+            sha256: [0xFF; 32],
+            len: u64::MAX,
+        };
+        let attestation = Attestation::create(&rng, &MockTimer::at(1_000), event).unwrap();
+        let receipt = render(&attestation);
+
+        assert!(receipt.starts_with(&INIT));
+        assert!(receipt.ends_with(&CUT));
+        assert!(contains(&receipt, b"Event:   DataDigest\nCounter: "));
+        assert!(contains(&receipt, b"Uptime:  1000 ms\nUTC:     unknown\n"));
+
+        // The symbol holds the fixed line, newline dropped
+        let line = attestation.fixed_line();
+        let data = line.trim_end().as_bytes();
+        let [low, high] = ((data.len() + 3) as u16).to_le_bytes();
+        let mut store = vec![GS, b'(', b'k', low, high, b'1', b'P', b'0'];
+        store.extend_from_slice(data);
+        store.extend_from_slice(&QR_PRINT);
+        assert!(contains(&receipt, &store));
+
+        // A full fixed line still fits, with room for the longest counter,
+        // uptime and UTC line (58 bytes more than these)
+        let summary = receipt.len() - data.len();
+        assert!(summary + FixedLine::new().capacity() + 64 <= MAX_RECEIPT_LEN);
+    }
+}
@@ -0,0 +1,226 @@
+//! Log redaction layer
+//!
+//! [`Redactor`] wraps the platform logger so every line passes through one
+//! filter before it reaches the UART:
+//!
+//! - lines from the crypto modules ([`CRYPTO_TARGETS`]) that contain a byte
+//!   buffer dump (`[12, 250, 7, ...]`) are dropped;
+//! - any hex run longer than the longest public output (a signature, 128
+//!   characters) is truncated.
+//!
+//! Either case means someone is logging something they should not, so it
+//! also trips a `debug_assert!`: tests and debug builds fail loudly, release
+//! builds quietly redact. The filter works on the formatted line, so it
+//! holds no matter how the value reached the format string.
+
+use core::fmt::Write;
+
+/// Modules that handle key material, seeds or tokens
+pub const CRYPTO_TARGETS: [&str; 5] = [
+    "icesickle_core::attestation",
+    "icesickle_core::auth",
+    "icesickle_core::entropy",
+    "icesickle_core::scrub",
+    "icesickle_core::session",
+];
+
+/// Longest hex run allowed through (a hex-encoded Ed25519 signature)
+pub const MAX_HEX_RUN: usize = 128;
+
+/// Hex characters kept from an over-long run
+const HEX_PREFIX: usize = 8;
+
+/// Comma-separated numbers that make a line look like a byte dump
+const BYTE_DUMP_MIN: usize = 8;
+
+/// Longest log line passed on; longer lines are cut
+pub const MAX_LINE_LEN: usize = 256;
+
+/// A log line after redaction
+#[derive(Debug, PartialEq, Eq)]
+pub enum Redacted {
+    /// Line may be emitted as is
+    Clean(heapless::String<MAX_LINE_LEN>),
+    /// Hex was truncated; emit the edited line
+    Truncated(heapless::String<MAX_LINE_LEN>),
+    /// Byte buffer from a crypto module; emit nothing
+    Dropped,
+}
+
+/// Apply the redaction rules to one formatted line from `target`
+pub fn redact(target: &str, text: &str) -> Redacted {
+    if is_crypto_target(target) && has_byte_dump(text) {
+        return Redacted::Dropped;
+    }
+
+    let mut out = heapless::String::new();
+    let mut truncated = false;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let run = rest.bytes().take_while(|b| b.is_ascii_hexdigit()).count();
+        if run > MAX_HEX_RUN {
+            push_str(&mut out, &rest[..HEX_PREFIX]);
+            push_str(&mut out, "...");
+            truncated = true;
+            rest = &rest[run..];
+        } else if run > 0 {
+            push_str(&mut out, &rest[..run]);
+            rest = &rest[run..];
+        } else {
+            let len = rest.chars().next().map_or(1, char::len_utf8);
+            push_str(&mut out, &rest[..len]);
+            rest = &rest[len..];
+        }
+    }
+
+    if truncated {
+        Redacted::Truncated(out)
+    } else {
+        Redacted::Clean(out)
+    }
+}
+
+fn is_crypto_target(target: &str) -> bool {
+    CRYPTO_TARGETS
+        .iter()
+        .any(|module| target.starts_with(module))
+}
+
+/// True if `text` contains a run of comma-separated decimal bytes
+fn has_byte_dump(text: &str) -> bool {
+    let mut count = 0;
+    for token in text.split([',', '[', ']']) {
+        match token.trim().parse::<u8>() {
+            Ok(_) => {
+                count += 1;
+                if count >= BYTE_DUMP_MIN {
+                    return true;
+                }
+            }
+            Err(_) => count = 0,
+        }
+    }
+    false
+}
+
+/// Append what fits; log lines are cut, never rejected, when too long
+fn push_str(out: &mut heapless::String<MAX_LINE_LEN>, s: &str) {
+    for c in s.chars() {
+        if out.push(c).is_err() {
+            return;
+        }
+    }
+}
+
+/// Logger wrapper applying [`redact`] to every record
+pub struct Redactor<L> {
+    inner: L,
+}
+
+impl<L> Redactor<L> {
+    pub const fn new(inner: L) -> Self {
+        Self { inner }
+    }
+}
+
+impl<L: log::Log> log::Log for Redactor<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut line = heapless::String::<MAX_LINE_LEN>::new();
+        // A full buffer just cuts the line short
+        let _ = write!(line, "{}", record.args());
+
+        let text = match redact(record.target(), &line) {
+            Redacted::Clean(text) => text,
+            Redacted::Truncated(text) => {
+                debug_assert!(false, "over-long hex logged from {}", record.target());
+                text
+            }
+            Redacted::Dropped => {
+                debug_assert!(false, "byte buffer logged from {}", record.target());
+                return;
+            }
+        };
+
+        self.inner.log(
+            &log::Record::builder()
+                .args(format_args!("{}", text))
+                .metadata(record.metadata().clone())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION: &str = "icesickle_core::session";
+
+    #[test]
+    fn test_byte_dump_from_crypto_module_dropped() {
+        let line = "key: [1, 2, 3, 4, 5, 6, 7, 8, 9]";
+        assert_eq!(redact(SESSION, line), Redacted::Dropped);
+        assert!(matches!(
+            redact("icesickle::serial", line),
+            Redacted::Clean(_)
+        ));
+        assert!(matches!(
+            redact(SESSION, "handshake step [1, 2]"),
+            Redacted::Clean(_)
+        ));
+    }
+
+    #[test]
+    fn test_long_hex_truncated() {
+        let signature = "ab".repeat(64);
+        let Redacted::Clean(kept) = redact(SESSION, &signature) else {
+            panic!("signature-length hex must pass");
+        };
+        assert_eq!(kept.as_str(), signature);
+
+        let line = format!("seed={} done", "cd".repeat(65));
+        let Redacted::Truncated(cut) = redact("icesickle::main", &line) else {
+            panic!("over-long hex must be truncated");
+        };
+        assert_eq!(cut.as_str(), "seed=cdcdcdcd... done");
+    }
+
+    struct Sink;
+
+    impl log::Log for Sink {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, _: &log::Record) {}
+        fn flush(&self) {}
+    }
+
+    #[test]
+    #[should_panic(expected = "byte buffer logged")]
+    fn test_violation_trips_debug_assertion() {
+        use log::Log;
+
+        let seed = [7u8; 32];
+        Redactor::new(Sink).log(
+            &log::Record::builder()
+                .args(format_args!("seed {:?}", seed))
+                .target("icesickle_core::attestation")
+                .build(),
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Relay (daisy-chain) mode
+//!
+//! Devices wired in a chain (firmware feature `relay`), each one's UART1 TX
+//! to the next one's RX, pass their attestations along to the head of the
+//! chain, whose own outputs then carry every device's records:
+//!
+//! 1. A device sends each attestation it emits onward as
+//!    [`PeerMessage::Relayed`](crate::witness::PeerMessage::Relayed) with
+//!    `hops` 1.
+//! 2. A device receiving one checks the frame and the signature, outputs the
+//!    record, and sends it onward with `hops` one higher.
+//!
+//! Nothing is re-signed: the head outputs exactly what the originating
+//! device signed, and a verifier checks it like any other attestation.
+//! Because every hop verifies before forwarding, a damaged or forged record
+//! goes no further than the first device that sees it.
+//!
+//! Two guards end loops (a chain wired into a ring, a cable back to its own
+//! port): a record that has crossed [`MAX_HOPS`] links is not taken further,
+//! and a device drops records it has recently sent or accepted, recognised
+//! by their public key, which is fresh for every attestation.
+//!
+//! `hops` is not signed. It exists to end loops, not as evidence of the
+//! path a record took.
+
+use crate::protocol::AttestationRecord;
+
+/// Links a record may cross; a chain can be `MAX_HOPS + 1` devices long
+pub const MAX_HOPS: u8 = 8;
+
+/// Public keys remembered for loop detection
+pub const RECENT_KEYS: usize = 32;
+
+/// Why a relayed record was not accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejected {
+    /// It has already crossed more than [`MAX_HOPS`] links
+    TooManyHops,
+    /// It was recently sent or accepted here, so the chain has a loop
+    Duplicate,
+}
+
+/// Loop detection for one device in a chain
+#[derive(Debug, Default)]
+pub struct Relay {
+    recent: heapless::Deque<[u8; 32], RECENT_KEYS>,
+}
+
+impl Relay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note one of this device's own records as it is sent, so it is
+    /// dropped if it comes back
+    pub fn sent(&mut self, record: &AttestationRecord) {
+        self.remember(record.public_key);
+    }
+
+    /// Decide whether to take `record`, which arrived after `hops` links; if
+    /// taken, it goes onward with `hops + 1`
+    pub fn accept(&mut self, record: &AttestationRecord, hops: u8) -> Result<(), Rejected> {
+        if hops > MAX_HOPS {
+            return Err(Rejected::TooManyHops);
+        }
+        if self.recent.iter().any(|key| *key == record.public_key) {
+            return Err(Rejected::Duplicate);
+        }
+        self.remember(record.public_key);
+        Ok(())
+    }
+
+    fn remember(&mut self, public_key: [u8; 32]) {
+        if self.recent.is_full() {
+            self.recent.pop_front();
+        }
+        let _ = self.recent.push_back(public_key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::AttestationEvent;
+    use crate::policy::Policy;
+    use crate::scheme::Algorithm;
+
+    fn record(key: u8) -> AttestationRecord {
+        AttestationRecord {
+            version: 12,
+            event: AttestationEvent::ButtonPress { gpio: 0 },
+            timestamp_ms: 1_000,
+            counter: 1,
+            public_key: [key; 32],
+            signature: [0; 64],
+            wall_clock: None,
+            policy: Policy::NONE,
+            suppressed: 0,
+            challenge: None,
+            token: None,
+            boot_nonce: None,
+            algorithm: Algorithm::Ed25519,
+            context: None,
+            measurement: None,
+        }
+    }
+
+    #[test]
+    fn test_records_pass_once() {
+        let mut relay = Relay::new();
+        assert_eq!(relay.accept(&record(1), 1), Ok(()));
+        assert_eq!(relay.accept(&record(1), 3), Err(Rejected::Duplicate));
+
+        // Our own records coming back round a ring
+        relay.sent(&record(2));
+        assert_eq!(relay.accept(&record(2), 4), Err(Rejected::Duplicate));
+    }
+
+    #[test]
+    fn test_hop_limit() {
+        let mut relay = Relay::new();
+        assert_eq!(relay.accept(&record(1), MAX_HOPS), Ok(()));
+        assert_eq!(
+            relay.accept(&record(2), MAX_HOPS + 1),
+            Err(Rejected::TooManyHops)
+        );
+        assert_eq!(
+            relay.accept(&record(3), u8::MAX),
+            Err(Rejected::TooManyHops)
+        );
+    }
+
+    #[test]
+    fn test_oldest_key_is_forgotten() {
+        let mut relay = Relay::new();
+        for key in 0..=RECENT_KEYS as u8 {
+            relay.sent(&record(key));
+        }
+        assert_eq!(relay.accept(&record(0), 1), Ok(()));
+        assert_eq!(
+            relay.accept(&record(RECENT_KEYS as u8), 1),
+            Err(Rejected::Duplicate)
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Battery-backed RTC chips
+//!
+//! Calendar time from a DS3231 or PCF8563 on the I2C bus, for
+//! `wallclock::RtcClock`. The device only ever reads the chip: set it with
+//! any RTC tool before fitting it, which also clears its oscillator-stop
+//! flag. Both chips keep two-digit years, read here as 2000-2099, in 24-hour
+//! UTC (a DS3231 left in 12-hour mode is converted).
+//!
+//! A register value that is not a valid calendar time is an error rather
+//! than a guess.
+
+use crate::error::{IceSickleError, Result};
+use crate::hal::I2cBus;
+use crate::wallclock;
+
+/// Supported RTC chips
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcChip {
+    /// Maxim DS3231 (TCXO, ±2 ppm)
+    Ds3231,
+    /// NXP PCF8563 (external crystal, typically ±20 ppm)
+    Pcf8563,
+}
+
+impl RtcChip {
+    /// Fixed I2C address
+    pub const fn address(self) -> u8 {
+        match self {
+            RtcChip::Ds3231 => 0x68,
+            RtcChip::Pcf8563 => 0x51,
+        }
+    }
+
+    /// Name for logs
+    pub const fn name(self) -> &'static str {
+        match self {
+            RtcChip::Ds3231 => "DS3231",
+            RtcChip::Pcf8563 => "PCF8563",
+        }
+    }
+
+    /// The first supported chip that answers on the bus
+    pub fn probe(bus: &mut dyn I2cBus) -> Option<Self> {
+        [RtcChip::Ds3231, RtcChip::Pcf8563]
+            .into_iter()
+            .find(|chip| chip.read(bus).is_ok())
+    }
+
+    /// Current time as Unix seconds, and false if the chip reports that its
+    /// oscillator stopped since it was last set
+    pub fn read(self, bus: &mut dyn I2cBus) -> Result<(u64, bool)> {
+        match self {
+            RtcChip::Ds3231 => {
+                // Time at 0x00-0x06, status (OSF in bit 7) at 0x0F
+                let mut regs = [0u8; 16];
+                bus.write_read(self.address(), &[0x00], &mut regs)?;
+                let hour = match regs[2] & 0x40 {
+                    0 => bcd(regs[2] & 0x3F)?,
+                    // 12-hour mode: bit 5 is PM, 12 o'clock reads as 12
+                    _ => bcd(regs[2] & 0x1F)? % 12 + u64::from(regs[2] & 0x20 != 0) * 12,
+                };
+                let unix_s = unix(
+                    bcd(regs[6])?,
+                    bcd(regs[5] & 0x1F)?,
+                    bcd(regs[4] & 0x3F)?,
+                    hour,
+                    bcd(regs[1] & 0x7F)?,
+                    bcd(regs[0] & 0x7F)?,
+                )?;
+                Ok((unix_s, regs[15] & 0x80 == 0))
+            }
+            RtcChip::Pcf8563 => {
+                // Seconds (VL in bit 7) through years at 0x02-0x08
+                let mut regs = [0u8; 7];
+                bus.write_read(self.address(), &[0x02], &mut regs)?;
+                let unix_s = unix(
+                    bcd(regs[6])?,
+                    bcd(regs[5] & 0x1F)?,
+                    bcd(regs[3] & 0x3F)?,
+                    bcd(regs[2] & 0x3F)?,
+                    bcd(regs[1] & 0x7F)?,
+                    bcd(regs[0] & 0x7F)?,
+                )?;
+                Ok((unix_s, regs[0] & 0x80 == 0))
+            }
+        }
+    }
+}
+
+/// One packed BCD byte
+fn bcd(byte: u8) -> Result<u64> {
+    let (tens, ones) = (byte >> 4, byte & 0x0F);
+    if tens > 9 || ones > 9 {
+        return Err(IceSickleError::Sensor);
+    }
+    Ok(u64::from(tens * 10 + ones))
+}
+
+/// Unix seconds from a two-digit year and the rest of the calendar
+fn unix(yy: u64, month: u64, day: u64, hour: u64, minute: u64, second: u64) -> Result<u64> {
+    let valid = (1..=12).contains(&month)
+        && (1..=31).contains(&day)
+        && hour < 24
+        && minute < 60
+        && second < 60;
+    if !valid {
+        return Err(IceSickleError::Sensor);
+    }
+    let days = wallclock::days_from_civil(2000 + yy, month, day);
+    Ok(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::MockI2c;
+
+    /// 2024-02-29 23:59:59 UTC
+    const LEAP_EVE: u64 = 1_709_251_199;
+
+    #[test]
+    fn test_ds3231_read() {
+        let mut bus = MockI2c::default();
+        let mut regs = [0u8; 16];
+        regs[..7].copy_from_slice(&[0x59, 0x59, 0x23, 0x04, 0x29, 0x02, 0x24]);
+        bus.respond(&regs);
+        assert_eq!(RtcChip::Ds3231.read(&mut bus), Ok((LEAP_EVE, true)));
+
+        // 12-hour mode (11 PM) with the oscillator-stop flag set
+        regs[2] = 0x40 | 0x20 | 0x11;
+        regs[15] = 0x80;
+        bus.respond(&regs);
+        assert_eq!(RtcChip::Ds3231.read(&mut bus), Ok((LEAP_EVE, false)));
+
+        regs[5] = 0x13;
+        bus.respond(&regs);
+        assert_eq!(RtcChip::Ds3231.read(&mut bus), Err(IceSickleError::Sensor));
+    }
+
+    #[test]
+    fn test_pcf8563_read() {
+        let mut bus = MockI2c::default();
+        bus.respond(&[0x59, 0x59, 0x23, 0x29, 0x04, 0x02, 0x24]);
+        assert_eq!(RtcChip::Pcf8563.read(&mut bus), Ok((LEAP_EVE, true)));
+        assert_eq!(bus.writes, [(0x51, vec![0x02])]);
+
+        // Voltage-low flag: the time did not survive
+        bus.respond(&[0x80 | 0x59, 0x59, 0x23, 0x29, 0x04, 0x02, 0x24]);
+        assert_eq!(RtcChip::Pcf8563.read(&mut bus), Ok((LEAP_EVE, false)));
+
+        bus.respond(&[0x5A, 0x59, 0x23, 0x29, 0x04, 0x02, 0x24]);
+        assert_eq!(RtcChip::Pcf8563.read(&mut bus), Err(IceSickleError::Sensor));
+    }
+}
@@ -0,0 +1,189 @@
+//! Signature schemes for the ephemeral key
+//!
+//! Every attestation is signed by a fresh key of one [`SignatureScheme`],
+//! chosen by crate feature like the companion formats; [`Active`] is what
+//! this build signs with, and the payload names it as an [`Algorithm`]
+//! (payload version 9), so a verifier knows how to check the signature:
+//!
+//! - Ed25519 (default): RFC 8032, checked with `verify_strict`. Signing
+//!   hashes with `sha512::Sha512`, so a registered SHA-512 accelerator
+//!   runs it; the signatures are the same either way.
+//! - P-256 (`sign-p256`): ECDSA over NIST P-256 with SHA-256 (ES256), for
+//!   verifier ecosystems that cannot consume Ed25519. Nonces are derived
+//!   per RFC 6979, so signing needs no randomness beyond the key. The key
+//!   is carried as its 32-byte x-coordinate: keygen negates the secret
+//!   when needed so that y is even, and a verifier rebuilds the SEC1
+//!   compressed key as `0x02 || x`. Signatures are `r || s`, 64 bytes.
+//!
+//! Both fit the 32-byte key and 64-byte signature that every record,
+//! frame and QR code is sized for. The feature `p256` builds the P-256
+//! backend for verification only (the host verifier enables it), so any
+//! build checks both algorithms whichever it signs with. The companion
+//! formats are defined over Ed25519 and are not combinable with
+//! `sign-p256`.
+//!
+//! Code 2 is reserved for ML-DSA-44 (FIPS 204), whose 1312-byte keys and
+//! 2420-byte signatures fit none of the fixed-size outputs (see
+//! `docs/ROADMAP.md`).
+
+use ed25519_dalek::hazmat::{raw_sign, ExpandedSecretKey};
+use ed25519_dalek::SigningKey;
+use icesickle_payload::scheme;
+use zeroize::ZeroizeOnDrop;
+
+use crate::sha512::Sha512;
+
+pub use icesickle_payload::scheme::{
+    compressed, Algorithm, PUBLIC_KEY_LEN, SEC1_EVEN, SIGNATURE_LEN,
+};
+
+/// A way to make, use and check an ephemeral key
+pub trait SignatureScheme {
+    /// What the payload calls this scheme
+    const ALGORITHM: Algorithm;
+
+    /// Private key; wiped when dropped
+    type SigningKey: ZeroizeOnDrop;
+
+    /// Key from 32 uniformly random bytes; `None` if they are not one, in
+    /// which case the caller draws again
+    fn from_seed(seed: &[u8; 32]) -> Option<Self::SigningKey>;
+
+    /// Encoded public key
+    fn public_key(key: &Self::SigningKey) -> [u8; PUBLIC_KEY_LEN];
+
+    /// Encoded signature over `message`
+    fn sign(key: &Self::SigningKey, message: &[u8]) -> [u8; SIGNATURE_LEN];
+
+    /// Check an encoded signature; false for a malformed key or signature
+    fn verify(
+        public_key: &[u8; PUBLIC_KEY_LEN],
+        message: &[u8],
+        signature: &[u8; SIGNATURE_LEN],
+    ) -> bool;
+}
+
+/// The scheme this build signs with
+#[cfg(not(feature = "sign-p256"))]
+pub type Active = Ed25519;
+
+/// The scheme this build signs with
+#[cfg(feature = "sign-p256")]
+pub type Active = P256;
+
+/// Ed25519 (RFC 8032)
+pub struct Ed25519;
+
+impl SignatureScheme for Ed25519 {
+    const ALGORITHM: Algorithm = Algorithm::Ed25519;
+
+    // ed25519_dalek::SigningKey zeroizes itself on drop
+    type SigningKey = SigningKey;
+
+    fn from_seed(seed: &[u8; 32]) -> Option<SigningKey> {
+        Some(SigningKey::from_bytes(seed))
+    }
+
+    fn public_key(key: &SigningKey) -> [u8; PUBLIC_KEY_LEN] {
+        key.verifying_key().to_bytes()
+    }
+
+    fn sign(key: &SigningKey, message: &[u8]) -> [u8; SIGNATURE_LEN] {
+        // dalek's own signing, with both hashes over the message on
+        // `sha512::Sha512` so an accelerator can run them
+        let expanded = ExpandedSecretKey::from(key.as_bytes());
+        raw_sign::<Sha512>(&expanded, message, &key.verifying_key()).to_bytes()
+    }
+
+    fn verify(
+        public_key: &[u8; PUBLIC_KEY_LEN],
+        message: &[u8],
+        signature: &[u8; SIGNATURE_LEN],
+    ) -> bool {
+        scheme::verify_ed25519(public_key, message, signature)
+    }
+}
+
+/// ECDSA P-256 with SHA-256, x-only keys (feature `p256`)
+#[cfg(feature = "p256")]
+pub struct P256;
+
+#[cfg(feature = "p256")]
+impl SignatureScheme for P256 {
+    const ALGORITHM: Algorithm = Algorithm::P256;
+
+    // p256::ecdsa::SigningKey zeroizes its scalar on drop
+    type SigningKey = p256::ecdsa::SigningKey;
+
+    fn from_seed(seed: &[u8; 32]) -> Option<Self::SigningKey> {
+        let key = p256::ecdsa::SigningKey::from_slice(seed).ok()?;
+        // Negating the secret negates the point: same x, even y
+        if key.verifying_key().to_encoded_point(true).as_bytes()[0] == SEC1_EVEN {
+            Some(key)
+        } else {
+            Some(p256::ecdsa::SigningKey::from(-*key.as_nonzero_scalar()))
+        }
+    }
+
+    fn public_key(key: &Self::SigningKey) -> [u8; PUBLIC_KEY_LEN] {
+        let point = key.verifying_key().to_encoded_point(true);
+        let mut x = [0u8; PUBLIC_KEY_LEN];
+        x.copy_from_slice(&point.as_bytes()[1..]);
+        x
+    }
+
+    fn sign(key: &Self::SigningKey, message: &[u8]) -> [u8; SIGNATURE_LEN] {
+        use p256::ecdsa::signature::Signer;
+
+        let signature: p256::ecdsa::Signature = key.sign(message);
+        let mut out = [0u8; SIGNATURE_LEN];
+        out.copy_from_slice(&signature.to_bytes());
+        out
+    }
+
+    fn verify(
+        public_key: &[u8; PUBLIC_KEY_LEN],
+        message: &[u8],
+        signature: &[u8; SIGNATURE_LEN],
+    ) -> bool {
+        scheme::verify_p256(public_key, message, signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<S: SignatureScheme>() {
+        let key = S::from_seed(&[0x5a; 32]).unwrap();
+        let public_key = S::public_key(&key);
+        let signature = S::sign(&key, b"payload");
+        assert!(S::verify(&public_key, b"payload", &signature));
+        assert!(!S::verify(&public_key, b"payload!", &signature));
+        assert!(S::ALGORITHM.verify(&public_key, b"payload", &signature));
+        assert_eq!(S::sign(&key, b"payload"), signature);
+    }
+
+    #[test]
+    fn test_ed25519_round_trips() {
+        round_trip::<Ed25519>();
+        // A P-256 signature is not accepted as Ed25519 or the reverse
+        let key = Ed25519::from_seed(&[0x5a; 32]).unwrap();
+        let signature = Ed25519::sign(&key, b"payload");
+        assert!(!Algorithm::P256.verify(&Ed25519::public_key(&key), b"payload", &signature));
+    }
+
+    #[cfg(feature = "p256")]
+    #[test]
+    fn test_p256_round_trips_with_even_y() {
+        round_trip::<P256>();
+        for seed in [[0x5a; 32], [0x01; 32], [0xa5; 32]] {
+            let key = P256::from_seed(&seed).unwrap();
+            let point = key.verifying_key().to_encoded_point(true);
+            assert_eq!(*point.as_bytes(), compressed(&P256::public_key(&key)));
+        }
+        // Zero and the group order are not keys
+        assert!(P256::from_seed(&[0; 32]).is_none());
+        assert!(P256::from_seed(&[0xFF; 32]).is_none());
+    }
+}
@@ -0,0 +1,185 @@
+//! Memory scrubbing for fatal paths
+//!
+//! Normal code relies on `zeroize` and `ZeroizeOnDrop`. That does nothing
+//! when the firmware dies mid-signing: a panic aborts without running
+//! destructors, and SRAM keeps its contents across a soft reset. The fatal
+//! path (see the firmware's `fatal.rs`) therefore wipes memory explicitly
+//! before resetting:
+//!
+//! - the **crypto workspace**, a fixed static region that holds the key seed
+//!   while it exists, so its location is always known (one slot per task
+//!   that may sign at once); and
+//! - the dead and abandoned parts of the stack, via [`zero_range`].
+//!
+//! Writes are volatile and fenced so they cannot be optimised away even
+//! though nothing reads the memory again.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{compiler_fence, AtomicBool, Ordering};
+
+/// Fixed-location scratch buffer for secret material
+///
+/// Access is exclusive ([`Workspace::with`]) and the buffer is zeroed after
+/// every use; [`Workspace::scrub`] wipes it unconditionally for fatal paths.
+pub struct Workspace<const N: usize> {
+    buf: UnsafeCell<[u8; N]>,
+    busy: AtomicBool,
+}
+
+// SAFETY: all access to `buf` is serialized by `busy`, except `scrub`, which
+// only ever writes zeros and is used when the device is about to reset.
+unsafe impl<const N: usize> Sync for Workspace<N> {}
+
+impl<const N: usize> Workspace<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: UnsafeCell::new([0; N]),
+            busy: AtomicBool::new(false),
+        }
+    }
+
+    /// Run `f` with exclusive use of the buffer, zeroing it afterwards
+    ///
+    /// Returns `None` if the workspace is already in use (re-entry is a bug).
+    pub fn with<R>(&self, f: impl FnOnce(&mut [u8; N]) -> R) -> Option<R> {
+        if self.busy.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: `busy` grants exclusive access until it is released below
+        let buf = unsafe { &mut *self.buf.get() };
+        let result = f(buf);
+        zero(buf);
+        self.busy.store(false, Ordering::Release);
+        Some(result)
+    }
+
+    /// Zero the buffer regardless of who holds it (fatal paths only)
+    pub fn scrub(&self) {
+        // SAFETY: the region is valid for N bytes; concurrent users can only
+        // observe zeros, and the caller is about to reset the device
+        unsafe {
+            let start = self.buf.get() as *mut u8;
+            zero_range(start, start.add(N));
+        }
+    }
+}
+
+impl<const N: usize> Default for Workspace<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A fixed set of [`Workspace`]s, for tasks that may each hold one at once
+///
+/// [`WorkspacePool::with`] lends the first free slot, so two tasks signing
+/// on different cores never wait for each other; only more users at once
+/// than there are slots is refused. [`WorkspacePool::scrub`] wipes them all.
+pub struct WorkspacePool<const N: usize, const SLOTS: usize> {
+    slots: [Workspace<N>; SLOTS],
+}
+
+impl<const N: usize, const SLOTS: usize> WorkspacePool<N, SLOTS> {
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { Workspace::new() }; SLOTS],
+        }
+    }
+
+    /// Run `f` in the first free slot, zeroing it afterwards
+    ///
+    /// Returns `None` if every slot is in use.
+    pub fn with<R>(&self, f: impl FnOnce(&mut [u8; N]) -> R) -> Option<R> {
+        let mut f = Some(f);
+        self.slots
+            .iter()
+            .find_map(|slot| slot.with(|buf| f.take().map(|f| f(buf))).flatten())
+    }
+
+    /// Zero every slot regardless of who holds it (fatal paths only)
+    pub fn scrub(&self) {
+        for slot in &self.slots {
+            slot.scrub();
+        }
+    }
+}
+
+impl<const N: usize, const SLOTS: usize> Default for WorkspacePool<N, SLOTS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tasks that may derive a signing key at once: the firmware's event loop,
+/// and with `signer-core` its signer task on the other core
+pub const SIGNING_TASKS: usize = 2;
+
+/// Holds the ephemeral key seed while a signing key is being derived
+///
+/// On the device it is placed in RTC fast memory (ESP-IDF's
+/// `RTC_FAST_ATTR` section), which no GDMA channel can address: a broken
+/// transport driver or a wild DMA descriptor cannot read the seed out.
+#[cfg_attr(target_os = "espidf", link_section = ".rtc.force_fast")]
+pub static CRYPTO_WORKSPACE: WorkspacePool<32, SIGNING_TASKS> = WorkspacePool::new();
+
+/// Zero `buf` with writes the compiler must keep
+pub fn zero(buf: &mut [u8]) {
+    let range = buf.as_mut_ptr_range();
+    // SAFETY: the range comes from a live mutable slice
+    unsafe { zero_range(range.start, range.end) }
+}
+
+/// Zero every byte in `[start, end)` with volatile writes
+///
+/// # Safety
+///
+/// The range must be valid for writes and must not overlap memory the
+/// caller (or anything it returns to) still relies on.
+#[inline(always)]
+pub unsafe fn zero_range(start: *mut u8, end: *mut u8) {
+    let mut p = start;
+    while p < end {
+        core::ptr::write_volatile(p, 0);
+        p = p.add(1);
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_zeroed_after_use() {
+        let workspace = Workspace::<8>::new();
+        let copied = workspace.with(|buf| {
+            buf.fill(0xAA);
+            *buf
+        });
+        assert_eq!(copied, Some([0xAA; 8]));
+        assert_eq!(workspace.with(|buf| *buf), Some([0; 8]));
+    }
+
+    #[test]
+    fn test_workspace_rejects_reentry() {
+        let workspace = Workspace::<8>::new();
+        let inner = workspace.with(|_| workspace.with(|_| ()));
+        assert_eq!(inner, Some(None));
+    }
+
+    #[test]
+    fn test_pool_lends_each_user_a_slot() {
+        let pool = WorkspacePool::<8, 2>::new();
+        let nested = pool.with(|outer| {
+            outer.fill(0xAA);
+            let inner = pool.with(|inner| {
+                inner.fill(0x55);
+                pool.with(|_| ())
+            });
+            (*outer, inner)
+        });
+        assert_eq!(nested, Some(([0xAA; 8], Some(None))));
+        assert_eq!(pool.with(|buf| *buf), Some([0; 8]));
+        pool.with(|_| assert_eq!(pool.with(|buf| *buf), Some([0; 8])));
+    }
+}
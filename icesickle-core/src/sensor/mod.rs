@@ -0,0 +1,306 @@
+//! I2C sensor events
+//!
+//! A [`SensorDriver`] turns an I2C sensor into readings; [`Sensors`] watches
+//! the readings against configured [`Threshold`]s and produces a
+//! `Sensor { sensor, channel, value }` attestation event when one is crossed.
+//! Drivers reach the bus only through [`I2cBus`], so a new sensor needs a
+//! driver type and one `register` call in the firmware (feature `sensors`),
+//! and nothing in this crate changes.
+//!
+//! Readings are integers in a unit each driver documents per channel (m°C,
+//! mm, ...). A threshold fires once when crossed and re-arms only after the
+//! value has moved back past it by the hysteresis, so a reading hovering at
+//! the limit produces one event, not one per poll. An event waits in
+//! [`Sensors`] until the firmware signs it, so a crossing refused by the
+//! cooldown is signed later rather than lost. Sensors are not polled while
+//! an event waits.
+//!
+//! Sensor IDs are part of the wire format. In-tree drivers:
+//!
+//! | ID | Driver | Channels |
+//! |----|--------|----------|
+//! | 1 | [`sht31::Sht31`] | 0: temperature (m°C), 1: relative humidity (m%) |
+//! | 2 | [`vl53l0x::Vl53l0x`] | 0: distance (mm) |
+//!
+//! IDs `0x80..=0xFF` are reserved for out-of-tree drivers.
+
+pub mod sht31;
+pub mod vl53l0x;
+
+use crate::attestation::AttestationEvent;
+use crate::error::Result;
+use crate::hal::I2cBus;
+
+/// Most channels one poll can report
+pub const MAX_CHANNELS: usize = 4;
+
+/// Most drivers in one [`Sensors`]
+pub const MAX_SENSORS: usize = 4;
+
+/// Most thresholds per driver
+pub const MAX_THRESHOLDS: usize = 4;
+
+/// One channel's value from one poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reading {
+    pub channel: u8,
+    /// In the channel's unit (see the driver)
+    pub value: i32,
+}
+
+/// Readings from one poll
+pub type Readings = heapless::Vec<Reading, MAX_CHANNELS>;
+
+/// Which side of the limit fires
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Fires when the value rises above the limit
+    Above(i32),
+    /// Fires when the value falls below the limit
+    Below(i32),
+}
+
+/// A limit on one channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Threshold {
+    pub channel: u8,
+    pub trigger: Trigger,
+    /// How far back past the limit the value must move to re-arm
+    pub hysteresis: u32,
+}
+
+/// An I2C sensor
+pub trait SensorDriver {
+    /// Stable ID carried in `Sensor` events (see the table above)
+    fn id(&self) -> u8;
+
+    /// Name for logs
+    fn name(&self) -> &'static str;
+
+    /// Check the sensor is present and set it up; `Ok(false)` if absent
+    fn probe(&mut self, bus: &mut dyn I2cBus, now_ms: u64) -> Result<bool>;
+
+    /// Return new readings, if any are due; never blocks for long
+    fn poll(&mut self, bus: &mut dyn I2cBus, now_ms: u64) -> Result<Readings>;
+
+    /// Thresholds used when none are given at registration
+    fn default_thresholds(&self) -> &'static [Threshold] {
+        &[]
+    }
+}
+
+/// A registered driver and its threshold state
+struct Monitor {
+    driver: Box<dyn SensorDriver>,
+    thresholds: heapless::Vec<Threshold, MAX_THRESHOLDS>,
+    /// Per threshold: fired and not yet re-armed
+    tripped: [bool; MAX_THRESHOLDS],
+}
+
+impl Monitor {
+    /// True if `reading` newly crosses a threshold; updates re-arm state
+    fn check(&mut self, reading: Reading) -> bool {
+        let mut fired = false;
+        for (threshold, tripped) in self.thresholds.iter().zip(&mut self.tripped) {
+            if threshold.channel != reading.channel {
+                continue;
+            }
+            let value = i64::from(reading.value);
+            let hysteresis = i64::from(threshold.hysteresis);
+            let (beyond, rearmed) = match threshold.trigger {
+                Trigger::Above(limit) => {
+                    let limit = i64::from(limit);
+                    (value > limit, value <= limit - hysteresis)
+                }
+                Trigger::Below(limit) => {
+                    let limit = i64::from(limit);
+                    (value < limit, value >= limit + hysteresis)
+                }
+            };
+            if beyond && !*tripped {
+                *tripped = true;
+                fired = true;
+            } else if rearmed {
+                *tripped = false;
+            }
+        }
+        fired
+    }
+}
+
+/// Registered sensors and the event waiting to be signed
+#[derive(Default)]
+pub struct Sensors {
+    monitors: heapless::Vec<Monitor, MAX_SENSORS>,
+    pending: Option<AttestationEvent>,
+}
+
+impl Sensors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Probe `driver` and register it with its default thresholds
+    ///
+    /// Returns false (and drops the driver) if the sensor is absent, fails
+    /// its probe, or the registry is full.
+    pub fn register(
+        &mut self,
+        bus: &mut dyn I2cBus,
+        now_ms: u64,
+        driver: Box<dyn SensorDriver>,
+    ) -> bool {
+        let thresholds = driver.default_thresholds();
+        self.register_with(bus, now_ms, driver, thresholds)
+    }
+
+    /// Probe `driver` and register it with `thresholds` (extras are ignored)
+    pub fn register_with(
+        &mut self,
+        bus: &mut dyn I2cBus,
+        now_ms: u64,
+        mut driver: Box<dyn SensorDriver>,
+        thresholds: &[Threshold],
+    ) -> bool {
+        match driver.probe(bus, now_ms) {
+            Ok(true) => {}
+            Ok(false) => {
+                log::info!("Sensor {} not found", driver.name());
+                return false;
+            }
+            Err(e) => {
+                log::warn!("Sensor {} probe failed: {}", driver.name(), e);
+                return false;
+            }
+        }
+        let name = driver.name();
+        let monitor = Monitor {
+            driver,
+            thresholds: thresholds.iter().copied().take(MAX_THRESHOLDS).collect(),
+            tripped: [false; MAX_THRESHOLDS],
+        };
+        if self.monitors.push(monitor).is_err() {
+            log::warn!("Sensor {} not registered: too many sensors", name);
+            return false;
+        }
+        log::info!("Sensor {} registered", name);
+        true
+    }
+
+    /// Poll the sensors; true once a threshold event is waiting
+    pub fn poll(&mut self, bus: &mut dyn I2cBus, now_ms: u64) -> bool {
+        if self.pending.is_some() {
+            return true;
+        }
+        for monitor in &mut self.monitors {
+            let readings = match monitor.driver.poll(bus, now_ms) {
+                Ok(readings) => readings,
+                Err(e) => {
+                    log::warn!("Sensor {} read failed: {}", monitor.driver.name(), e);
+                    continue;
+                }
+            };
+            for reading in readings {
+                if monitor.check(reading) && self.pending.is_none() {
+                    self.pending = Some(AttestationEvent::Sensor {
+                        sensor: monitor.driver.id(),
+                        channel: reading.channel,
+                        value: reading.value,
+                    });
+                }
+            }
+        }
+        self.pending.is_some()
+    }
+
+    /// Take the waiting event, once it is to be signed
+    pub fn take(&mut self) -> Option<AttestationEvent> {
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::MockI2c;
+
+    /// Reports a scripted value on channel 0 at every poll
+    struct Scripted {
+        values: std::vec::IntoIter<i32>,
+    }
+
+    impl SensorDriver for Scripted {
+        fn id(&self) -> u8 {
+            0x80
+        }
+
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        fn probe(&mut self, _bus: &mut dyn I2cBus, _now_ms: u64) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn poll(&mut self, _bus: &mut dyn I2cBus, _now_ms: u64) -> Result<Readings> {
+            Ok(self
+                .values
+                .next()
+                .map(|value| Reading { channel: 0, value })
+                .into_iter()
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_threshold_fires_once_until_rearmed() {
+        let mut bus = MockI2c::default();
+        let mut sensors = Sensors::new();
+        let driver = Scripted {
+            values: vec![10, 31, 40, 28, 31, 24, 31].into_iter(),
+        };
+        let threshold = Threshold {
+            channel: 0,
+            trigger: Trigger::Above(30),
+            hysteresis: 5,
+        };
+        assert!(sensors.register_with(&mut bus, 0, Box::new(driver), &[threshold]));
+
+        let fired: Vec<_> = (0..7)
+            .map(|t| {
+                sensors.poll(&mut bus, t);
+                sensors.take()
+            })
+            .collect();
+        let event = |value| {
+            Some(AttestationEvent::Sensor {
+                sensor: 0x80,
+                channel: 0,
+                value,
+            })
+        };
+        // 28 and 31 stay within the hysteresis; 24 re-arms
+        assert_eq!(fired, [None, event(31), None, None, None, None, event(31)]);
+    }
+
+    #[test]
+    fn test_event_waits_until_taken() {
+        let mut bus = MockI2c::default();
+        let mut sensors = Sensors::new();
+        let driver = Scripted {
+            values: vec![5, 50].into_iter(),
+        };
+        let threshold = Threshold {
+            channel: 0,
+            trigger: Trigger::Below(10),
+            hysteresis: 0,
+        };
+        sensors.register_with(&mut bus, 0, Box::new(driver), &[threshold]);
+
+        assert!(sensors.poll(&mut bus, 0));
+        // Not taken (cooldown): still waiting, and the sensor is not polled
+        assert!(sensors.poll(&mut bus, 1));
+        assert!(sensors.take().is_some());
+        assert!(!sensors.poll(&mut bus, 2));
+    }
+}
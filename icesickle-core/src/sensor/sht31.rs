@@ -0,0 +1,203 @@
+//! Sensirion SHT31 temperature and humidity sensor
+//!
+//! Single-shot measurements at high repeatability, without clock
+//! stretching: a poll starts a measurement, and a poll at least
+//! [`MEASUREMENT_MS`] later reads it. Every word the sensor returns carries
+//! a CRC-8, checked before it is used.
+//!
+//! Channels: 0 is temperature in m°C, 1 is relative humidity in m%. By
+//! default an event fires when the temperature rises above 50 °C.
+
+use super::{Reading, Readings, SensorDriver, Threshold, Trigger};
+use crate::error::{IceSickleError, Result};
+use crate::hal::I2cBus;
+
+/// Address with ADDR tied low (`0x45` with ADDR high)
+pub const DEFAULT_ADDRESS: u8 = 0x44;
+
+/// Time between measurements
+pub const INTERVAL_MS: u64 = 2_000;
+
+/// Worst-case single-shot measurement time at high repeatability
+const MEASUREMENT_MS: u64 = 16;
+
+/// Single shot, high repeatability, clock stretching disabled
+const CMD_MEASURE: [u8; 2] = [0x24, 0x00];
+
+/// Read the status register
+const CMD_STATUS: [u8; 2] = [0xF3, 0x2D];
+
+/// Temperature channel (m°C)
+pub const CHANNEL_TEMPERATURE: u8 = 0;
+
+/// Relative humidity channel (m%)
+pub const CHANNEL_HUMIDITY: u8 = 1;
+
+/// Above 50 °C, re-armed below 48 °C
+const DEFAULT_THRESHOLDS: &[Threshold] = &[Threshold {
+    channel: CHANNEL_TEMPERATURE,
+    trigger: Trigger::Above(50_000),
+    hysteresis: 2_000,
+}];
+
+/// SHT31 on one I2C address
+#[derive(Debug)]
+pub struct Sht31 {
+    address: u8,
+    /// When the running measurement started, if one is running
+    started_ms: Option<u64>,
+    next_ms: u64,
+}
+
+impl Sht31 {
+    pub fn new(address: u8) -> Self {
+        Self {
+            address,
+            started_ms: None,
+            next_ms: 0,
+        }
+    }
+}
+
+impl SensorDriver for Sht31 {
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn name(&self) -> &'static str {
+        "SHT31"
+    }
+
+    fn probe(&mut self, bus: &mut dyn I2cBus, now_ms: u64) -> Result<bool> {
+        let mut status = [0u8; 3];
+        if bus
+            .write_read(self.address, &CMD_STATUS, &mut status)
+            .is_err()
+        {
+            return Ok(false);
+        }
+        word(&status)?;
+        self.next_ms = now_ms;
+        Ok(true)
+    }
+
+    fn poll(&mut self, bus: &mut dyn I2cBus, now_ms: u64) -> Result<Readings> {
+        let mut readings = Readings::new();
+        match self.started_ms {
+            None if now_ms >= self.next_ms => {
+                bus.write(self.address, &CMD_MEASURE)?;
+                self.started_ms = Some(now_ms);
+            }
+            Some(started_ms) if now_ms.saturating_sub(started_ms) >= MEASUREMENT_MS => {
+                self.started_ms = None;
+                self.next_ms = started_ms + INTERVAL_MS;
+                let mut data = [0u8; 6];
+                bus.read(self.address, &mut data)?;
+                let temperature = i64::from(word(&data[..3])?);
+                let humidity = i64::from(word(&data[3..])?);
+                // T = -45 + 175 * raw / (2^16 - 1) °C; RH = 100 * raw / (2^16 - 1) %
+                readings.extend([
+                    Reading {
+                        channel: CHANNEL_TEMPERATURE,
+                        value: (-45_000 + 175_000 * temperature / 65_535) as i32,
+                    },
+                    Reading {
+                        channel: CHANNEL_HUMIDITY,
+                        value: (100_000 * humidity / 65_535) as i32,
+                    },
+                ]);
+            }
+            _ => {}
+        }
+        Ok(readings)
+    }
+
+    fn default_thresholds(&self) -> &'static [Threshold] {
+        DEFAULT_THRESHOLDS
+    }
+}
+
+/// A 16-bit word followed by its CRC-8
+fn word(bytes: &[u8]) -> Result<u16> {
+    if crc8(&bytes[..2]) != bytes[2] {
+        return Err(IceSickleError::Sensor);
+    }
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// CRC-8, polynomial 0x31, initial value 0xFF (datasheet section 4.12)
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0xFF, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x31
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::MockI2c;
+
+    #[test]
+    fn test_crc_matches_datasheet() {
+        assert_eq!(crc8(&[0xBE, 0xEF]), 0x92);
+    }
+
+    #[test]
+    fn test_measurement_cycle() {
+        let mut bus = MockI2c::default();
+        let mut sensor = Sht31::new(DEFAULT_ADDRESS);
+        bus.respond(&[0x00, 0x00, crc8(&[0x00, 0x00])]);
+        assert!(sensor.probe(&mut bus, 0).unwrap());
+
+        assert!(sensor.poll(&mut bus, 0).unwrap().is_empty());
+        assert_eq!(
+            bus.writes.last(),
+            Some(&(DEFAULT_ADDRESS, CMD_MEASURE.to_vec()))
+        );
+        assert!(sensor
+            .poll(&mut bus, MEASUREMENT_MS - 1)
+            .unwrap()
+            .is_empty());
+
+        // 0x6666 is exactly 25 °C; 0x8000 is 50 % after rounding down
+        let temperature = [0x66, 0x66, crc8(&[0x66, 0x66])];
+        let humidity = [0x80, 0x00, crc8(&[0x80, 0x00])];
+        bus.respond(&[temperature, humidity].concat());
+        let readings = sensor.poll(&mut bus, MEASUREMENT_MS).unwrap();
+        assert_eq!(
+            &readings[..],
+            [
+                Reading {
+                    channel: CHANNEL_TEMPERATURE,
+                    value: 25_000
+                },
+                Reading {
+                    channel: CHANNEL_HUMIDITY,
+                    value: 50_000
+                },
+            ]
+        );
+
+        // Next measurement waits out the interval
+        assert!(sensor.poll(&mut bus, INTERVAL_MS - 1).unwrap().is_empty());
+        let writes = bus.writes.len();
+        sensor.poll(&mut bus, INTERVAL_MS).unwrap();
+        assert_eq!(bus.writes.len(), writes + 1);
+    }
+
+    #[test]
+    fn test_bad_crc_is_rejected() {
+        let mut bus = MockI2c::default();
+        let mut sensor = Sht31::new(DEFAULT_ADDRESS);
+        bus.respond(&[0x00, 0x00, 0x00]);
+        assert_eq!(sensor.probe(&mut bus, 0), Err(IceSickleError::Sensor));
+    }
+}
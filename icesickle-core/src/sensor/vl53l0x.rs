@@ -0,0 +1,193 @@
+//! ST VL53L0X time-of-flight distance sensor
+//!
+//! A minimal bring-up: check the model ID, latch the sensor's stop variable
+//! the way ST's API does, and start back-to-back continuous ranging with
+//! the power-on defaults. A poll reads the range once the sensor flags a
+//! new one. ST's full initialisation (SPAD selection, reference and offset
+//! calibration) is not done, so distances are coarse: good enough to see
+//! that something moved into or out of range, not to measure it. Readings
+//! with no target in range are skipped.
+//!
+//! Channel 0 is distance in mm. By default an event fires when something
+//! comes closer than 300 mm.
+
+use super::{Reading, Readings, SensorDriver, Threshold, Trigger};
+use crate::error::{IceSickleError, Result};
+use crate::hal::I2cBus;
+
+/// Fixed I2C address after power-on
+pub const DEFAULT_ADDRESS: u8 = 0x29;
+
+/// Distance channel (mm)
+pub const CHANNEL_DISTANCE: u8 = 0;
+
+const REG_SYSRANGE_START: u8 = 0x00;
+const REG_SYSTEM_INTERRUPT_CLEAR: u8 = 0x0B;
+const REG_RESULT_INTERRUPT_STATUS: u8 = 0x13;
+const REG_RESULT_RANGE_MM: u8 = 0x1E;
+const REG_MODEL_ID: u8 = 0xC0;
+
+/// Value of `REG_MODEL_ID` on every VL53L0X
+const MODEL_ID: u8 = 0xEE;
+
+/// Back-to-back continuous ranging
+const MODE_CONTINUOUS: u8 = 0x02;
+
+/// Ranges at or above this mean no target
+const OUT_OF_RANGE_MM: u16 = 8_190;
+
+/// Closer than 300 mm, re-armed beyond 350 mm
+const DEFAULT_THRESHOLDS: &[Threshold] = &[Threshold {
+    channel: CHANNEL_DISTANCE,
+    trigger: Trigger::Below(300),
+    hysteresis: 50,
+}];
+
+/// VL53L0X on one I2C address
+#[derive(Debug)]
+pub struct Vl53l0x {
+    address: u8,
+}
+
+impl Vl53l0x {
+    pub fn new(address: u8) -> Self {
+        Self { address }
+    }
+
+    fn read_u8(&self, bus: &mut dyn I2cBus, register: u8) -> Result<u8> {
+        let mut value = [0u8; 1];
+        bus.write_read(self.address, &[register], &mut value)?;
+        Ok(value[0])
+    }
+
+    fn write_u8(&self, bus: &mut dyn I2cBus, register: u8, value: u8) -> Result<()> {
+        bus.write(self.address, &[register, value])
+    }
+
+    /// Run `writes` (register, value) in order
+    fn write_all(&self, bus: &mut dyn I2cBus, writes: &[(u8, u8)]) -> Result<()> {
+        writes
+            .iter()
+            .try_for_each(|&(register, value)| self.write_u8(bus, register, value))
+    }
+}
+
+impl SensorDriver for Vl53l0x {
+    fn id(&self) -> u8 {
+        2
+    }
+
+    fn name(&self) -> &'static str {
+        "VL53L0X"
+    }
+
+    fn probe(&mut self, bus: &mut dyn I2cBus, _now_ms: u64) -> Result<bool> {
+        match self.read_u8(bus, REG_MODEL_ID) {
+            Ok(MODEL_ID) => {}
+            Ok(_) => return Err(IceSickleError::Sensor),
+            Err(_) => return Ok(false),
+        }
+
+        // Read the stop variable from the private register page, then
+        // write it back there before starting (ST API `DataInit`/`StartMeasurement`)
+        self.write_all(
+            bus,
+            &[(0x88, 0x00), (0x80, 0x01), (0xFF, 0x01), (0x00, 0x00)],
+        )?;
+        let stop_variable = self.read_u8(bus, 0x91)?;
+        self.write_all(
+            bus,
+            &[
+                (0x00, 0x01),
+                (0xFF, 0x00),
+                (0x80, 0x00),
+                (0x80, 0x01),
+                (0xFF, 0x01),
+                (0x00, 0x00),
+                (0x91, stop_variable),
+                (0x00, 0x01),
+                (0xFF, 0x00),
+                (0x80, 0x00),
+                (REG_SYSRANGE_START, MODE_CONTINUOUS),
+            ],
+        )?;
+        Ok(true)
+    }
+
+    fn poll(&mut self, bus: &mut dyn I2cBus, _now_ms: u64) -> Result<Readings> {
+        if self.read_u8(bus, REG_RESULT_INTERRUPT_STATUS)? & 0x07 == 0 {
+            return Ok(Readings::new());
+        }
+        let mut range = [0u8; 2];
+        bus.write_read(self.address, &[REG_RESULT_RANGE_MM], &mut range)?;
+        self.write_u8(bus, REG_SYSTEM_INTERRUPT_CLEAR, 0x01)?;
+
+        let mm = u16::from_be_bytes(range);
+        Ok((mm < OUT_OF_RANGE_MM)
+            .then_some(Reading {
+                channel: CHANNEL_DISTANCE,
+                value: i32::from(mm),
+            })
+            .into_iter()
+            .collect())
+    }
+
+    fn default_thresholds(&self) -> &'static [Threshold] {
+        DEFAULT_THRESHOLDS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::MockI2c;
+
+    #[test]
+    fn test_probe_checks_model_id() {
+        let mut bus = MockI2c::default();
+        let mut sensor = Vl53l0x::new(DEFAULT_ADDRESS);
+        bus.respond(&[0x00]);
+        assert_eq!(sensor.probe(&mut bus, 0), Err(IceSickleError::Sensor));
+
+        // No answer at all: absent
+        assert_eq!(sensor.probe(&mut bus, 0), Ok(false));
+
+        bus.respond(&[MODEL_ID]);
+        bus.respond(&[0x3C]);
+        assert_eq!(sensor.probe(&mut bus, 0), Ok(true));
+        assert!(bus.writes.contains(&(DEFAULT_ADDRESS, vec![0x91, 0x3C])));
+        assert_eq!(
+            bus.writes.last(),
+            Some(&(DEFAULT_ADDRESS, vec![REG_SYSRANGE_START, MODE_CONTINUOUS]))
+        );
+    }
+
+    #[test]
+    fn test_poll_reads_new_ranges_only() {
+        let mut bus = MockI2c::default();
+        let mut sensor = Vl53l0x::new(DEFAULT_ADDRESS);
+
+        bus.respond(&[0x00]);
+        assert!(sensor.poll(&mut bus, 0).unwrap().is_empty());
+
+        bus.respond(&[0x04]);
+        bus.respond(&[0x01, 0x2C]);
+        let readings = sensor.poll(&mut bus, 0).unwrap();
+        assert_eq!(
+            &readings[..],
+            [Reading {
+                channel: CHANNEL_DISTANCE,
+                value: 300
+            }]
+        );
+
+        // Out of range: cleared but not reported
+        bus.respond(&[0x04]);
+        bus.respond(&[0x1F, 0xFE]);
+        assert!(sensor.poll(&mut bus, 0).unwrap().is_empty());
+        assert_eq!(
+            bus.writes.last(),
+            Some(&(DEFAULT_ADDRESS, vec![REG_SYSTEM_INTERRUPT_CLEAR, 0x01]))
+        );
+    }
+}
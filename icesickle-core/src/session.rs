@@ -0,0 +1,247 @@
+//! Encrypted command sessions
+//!
+//! A host may wrap the command protocol in an encrypted session so that a
+//! passive eavesdropper on the cable (or radio, for wireless transports)
+//! cannot read challenges, tokens or responses in transit.
+//!
+//! # Handshake
+//!
+//! ```text
+//! host   → device : Handshake { host_public }          (X25519, ephemeral)
+//! device → host   : Handshake { device_public }        (X25519, ephemeral)
+//! both            : k_h2d || k_d2h = HKDF-SHA256(
+//!                       ikm  = X25519(shared),
+//!                       salt = host_public || device_public,
+//!                       info = "IceSickle session v1")
+//! ```
+//!
+//! Afterwards requests and responses travel as `Sealed { counter, ciphertext }`
+//! where `ciphertext = ChaCha20-Poly1305(postcard(message))` under the key for
+//! that direction, with the 64-bit counter as nonce. Counters must strictly
+//! increase, so a recorded frame cannot be replayed into the session.
+//!
+//! # Limits
+//!
+//! The handshake is unauthenticated: the device has no long-term key to
+//! authenticate with (see the `auth` module anti-patterns), so an active
+//! man-in-the-middle can still sit between host and device. This protects
+//! confidentiality against passive observers only.
+//!
+//! Both ECDH secrets are ephemeral and consumed by the exchange. Session keys
+//! are zeroized when the session ends: on `EndSession`, on a new handshake,
+//! or after [`SESSION_IDLE_TIMEOUT_MS`] without traffic.
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::entropy::HardwareRng;
+use crate::hal::EntropySource;
+use crate::protocol::{ErrorCode, Request, Response, SealedFrame, MAX_SEALED_LEN};
+
+/// A session with no sealed traffic for this long is torn down
+pub const SESSION_IDLE_TIMEOUT_MS: u64 = 60_000;
+
+const KDF_INFO: &[u8] = b"IceSickle session v1";
+const TAG_LEN: usize = 16;
+
+/// Nonce direction prefixes (keys differ too; this is belt and braces)
+const DIR_HOST_TO_DEVICE: u8 = 0x01;
+const DIR_DEVICE_TO_HOST: u8 = 0x02;
+
+/// Established session state; keys are zeroized on drop
+#[derive(ZeroizeOnDrop)]
+pub struct Session {
+    rx_key: [u8; 32],
+    tx_key: [u8; 32],
+    #[zeroize(skip)]
+    rx_counter: Option<u64>,
+    #[zeroize(skip)]
+    tx_counter: u64,
+    #[zeroize(skip)]
+    last_activity_ms: u64,
+}
+
+impl Session {
+    /// Complete a host-initiated handshake
+    ///
+    /// Returns the new session and the device's ephemeral public key.
+    pub fn respond<S: EntropySource>(
+        rng: &HardwareRng<S>,
+        host_public: &[u8; 32],
+        now_ms: u64,
+    ) -> Result<(Self, [u8; 32]), ErrorCode> {
+        let secret = EphemeralSecret::random_from_rng(rng);
+        let device_public = PublicKey::from(&secret).to_bytes();
+        let shared = secret.diffie_hellman(&PublicKey::from(*host_public));
+
+        // Reject low-order host keys, which would force a known shared secret
+        if !shared.was_contributory() {
+            return Err(ErrorCode::Malformed);
+        }
+
+        let mut salt = [0u8; 64];
+        salt[..32].copy_from_slice(host_public);
+        salt[32..].copy_from_slice(&device_public);
+
+        let mut okm = [0u8; 64];
+        Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+            .expand(KDF_INFO, &mut okm)
+            .map_err(|_| ErrorCode::Malformed)?;
+
+        let mut session = Self {
+            rx_key: [0; 32],
+            tx_key: [0; 32],
+            rx_counter: None,
+            tx_counter: 0,
+            last_activity_ms: now_ms,
+        };
+        session.rx_key.copy_from_slice(&okm[..32]);
+        session.tx_key.copy_from_slice(&okm[32..]);
+        okm.zeroize();
+
+        Ok((session, device_public))
+    }
+
+    /// True if the session has been idle past its timeout
+    pub fn expired(&self, now_ms: u64) -> bool {
+        now_ms.saturating_sub(self.last_activity_ms) >= SESSION_IDLE_TIMEOUT_MS
+    }
+
+    /// Decrypt and decode a sealed request
+    pub fn open(&mut self, sealed: &SealedFrame, now_ms: u64) -> Result<Request, ErrorCode> {
+        if self.rx_counter.is_some_and(|last| sealed.counter <= last) {
+            return Err(ErrorCode::Decrypt);
+        }
+
+        let len = sealed.ciphertext.len();
+        if len < TAG_LEN {
+            return Err(ErrorCode::Decrypt);
+        }
+
+        let mut buf = [0u8; MAX_SEALED_LEN];
+        let (body, tag) = sealed.ciphertext.split_at(len - TAG_LEN);
+        let plaintext = &mut buf[..body.len()];
+        plaintext.copy_from_slice(body);
+
+        ChaCha20Poly1305::new(Key::from_slice(&self.rx_key))
+            .decrypt_in_place_detached(
+                &nonce(DIR_HOST_TO_DEVICE, sealed.counter),
+                &[],
+                plaintext,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| ErrorCode::Decrypt)?;
+
+        // Only authenticated frames advance the window or keep the session alive
+        self.rx_counter = Some(sealed.counter);
+        self.last_activity_ms = now_ms;
+
+        let request = postcard::from_bytes(plaintext).map_err(|_| ErrorCode::Malformed);
+        buf.zeroize();
+        request
+    }
+
+    /// Encode and encrypt a response
+    pub fn seal(&mut self, response: &Response) -> Result<SealedFrame, ErrorCode> {
+        let mut buf = [0u8; MAX_SEALED_LEN];
+        let len = postcard::to_slice(response, &mut buf[..MAX_SEALED_LEN - TAG_LEN])
+            .map_err(|_| ErrorCode::Malformed)?
+            .len();
+
+        let counter = self.tx_counter;
+        self.tx_counter += 1;
+
+        let tag = ChaCha20Poly1305::new(Key::from_slice(&self.tx_key))
+            .encrypt_in_place_detached(&nonce(DIR_DEVICE_TO_HOST, counter), &[], &mut buf[..len])
+            .map_err(|_| ErrorCode::Malformed)?;
+
+        let mut ciphertext = heapless::Vec::new();
+        // Capacity is MAX_SEALED_LEN and len + TAG_LEN <= MAX_SEALED_LEN
+        let _ = ciphertext.extend_from_slice(&buf[..len]);
+        let _ = ciphertext.extend_from_slice(&tag);
+
+        Ok(SealedFrame {
+            counter,
+            ciphertext,
+        })
+    }
+}
+
+fn nonce(direction: u8, counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[0] = direction;
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    *Nonce::from_slice(&nonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::MockEntropy;
+
+    /// Host side of the handshake, mirroring the device derivation
+    fn host_keys(host_secret: [u8; 32], device_public: [u8; 32]) -> ([u8; 32], [u8; 32]) {
+        let secret = x25519_dalek::StaticSecret::from(host_secret);
+        let host_public = PublicKey::from(&secret).to_bytes();
+        let shared = secret.diffie_hellman(&PublicKey::from(device_public));
+
+        let mut salt = [0u8; 64];
+        salt[..32].copy_from_slice(&host_public);
+        salt[32..].copy_from_slice(&device_public);
+        let mut okm = [0u8; 64];
+        Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+            .expand(KDF_INFO, &mut okm)
+            .unwrap();
+
+        (okm[..32].try_into().unwrap(), okm[32..].try_into().unwrap())
+    }
+
+    fn seal_request(key: &[u8; 32], counter: u64, request: &Request) -> SealedFrame {
+        let mut buf = [0u8; MAX_SEALED_LEN];
+        let len = postcard::to_slice(request, &mut buf).unwrap().len();
+        let tag = ChaCha20Poly1305::new(Key::from_slice(key))
+            .encrypt_in_place_detached(&nonce(DIR_HOST_TO_DEVICE, counter), &[], &mut buf[..len])
+            .unwrap();
+        let mut ciphertext = heapless::Vec::new();
+        ciphertext.extend_from_slice(&buf[..len]).unwrap();
+        ciphertext.extend_from_slice(&tag).unwrap();
+        SealedFrame {
+            counter,
+            ciphertext,
+        }
+    }
+
+    #[test]
+    fn test_sealed_request_round_trip_and_replay() {
+        let rng = HardwareRng::from_source(MockEntropy(0x33)).unwrap();
+        let host_secret = [0x44; 32];
+        let host_public =
+            PublicKey::from(&x25519_dalek::StaticSecret::from(host_secret)).to_bytes();
+
+        let (mut session, device_public) = Session::respond(&rng, &host_public, 0).unwrap();
+        let (h2d, _d2h) = host_keys(host_secret, device_public);
+
+        let sealed = seal_request(&h2d, 1, &Request::GetStatus);
+        assert_eq!(session.open(&sealed, 10), Ok(Request::GetStatus));
+
+        // Same counter again is a replay
+        assert_eq!(session.open(&sealed, 20), Err(ErrorCode::Decrypt));
+
+        // Tampered ciphertext fails authentication
+        let mut tampered = seal_request(&h2d, 2, &Request::GetStatus);
+        tampered.ciphertext[0] ^= 1;
+        assert_eq!(session.open(&tampered, 30), Err(ErrorCode::Decrypt));
+    }
+
+    #[test]
+    fn test_idle_session_expires() {
+        let rng = HardwareRng::from_source(MockEntropy(0x33)).unwrap();
+        let (session, _) = Session::respond(&rng, &[9; 32], 1_000).unwrap();
+        assert!(!session.expired(1_000 + SESSION_IDLE_TIMEOUT_MS - 1));
+        assert!(session.expired(1_000 + SESSION_IDLE_TIMEOUT_MS));
+    }
+}
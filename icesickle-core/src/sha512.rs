@@ -0,0 +1,216 @@
+//! SHA-512 for Ed25519 signing, in software or on an accelerator
+//!
+//! An Ed25519 signature hashes the message twice: SHA-512(prefix ||
+//! message) for the nonce, then SHA-512(R || A || message) for the
+//! challenge. In software that is most of the signing time, and it grows
+//! with the payload. A port with a SHA-512 peripheral registers it once
+//! with [`set_backend`] (the firmware does under `hw-sha512`: the ESP32-S3
+//! SHA accelerator through mbedTLS), and every later [`Sha512`] runs on it;
+//! until then, or without one, [`Sha512`] is `sha2`'s software hash.
+//!
+//! Signatures are plain Ed25519 either way: the backend changes where the
+//! hash runs, not what it computes, so verifiers need nothing new. The key
+//! expansion, one block over the seed, stays in software inside
+//! `ed25519-dalek`. The nonce hash does carry the secret prefix, so a
+//! backend must not leave it in the peripheral once it finishes.
+
+use std::sync::OnceLock;
+
+use sha2::digest::consts::U64;
+use sha2::digest::{FixedOutput, HashMarker, Output, OutputSizeUser, Update};
+
+/// A SHA-512 accelerator, computing in a context it owns and reuses
+///
+/// One computation runs at a time: [`Backend::start`] claims the context
+/// and [`Backend::finish`] releases it. Nothing is allocated per hash, so
+/// signing stays off the heap with or without a backend.
+pub trait Backend: Sync {
+    /// Claim the context for a computation; false while another holds it
+    fn start(&self) -> bool;
+
+    fn update(&self, data: &[u8]);
+
+    /// The digest, releasing the context; nothing hashed may remain in the
+    /// peripheral afterwards
+    fn finish(&self) -> [u8; 64];
+}
+
+/// The accelerator, once registered
+static BACKEND: OnceLock<&'static dyn Backend> = OnceLock::new();
+
+/// Run every later [`Sha512`] on `backend`; only the first call counts
+///
+/// Register a backend only once it has been checked against a known
+/// answer: a wrong hash makes every signature fail to verify.
+pub fn set_backend(backend: &'static dyn Backend) {
+    let _ = BACKEND.set(backend);
+}
+
+/// True once a backend is registered
+pub fn is_accelerated() -> bool {
+    BACKEND.get().is_some()
+}
+
+/// A computation holding a backend's context, released however it ends
+struct Claimed(&'static dyn Backend);
+
+impl Claimed {
+    fn finish(self) -> [u8; 64] {
+        let digest = self.0.finish();
+        core::mem::forget(self);
+        digest
+    }
+}
+
+impl Drop for Claimed {
+    fn drop(&mut self) {
+        // Abandoned mid-hash: release the context, clearing what it held
+        self.0.finish();
+    }
+}
+
+// Boxing the software state would put every hash on the heap, and it is
+// the common case
+#[allow(clippy::large_enum_variant)]
+enum Inner {
+    Software(sha2::Sha512),
+    Backend(Claimed),
+}
+
+/// SHA-512 on the registered backend, or in software without one
+///
+/// A hash that finds the backend's context in use (the other core is
+/// signing) runs in software instead of waiting.
+pub struct Sha512(Inner);
+
+impl Sha512 {
+    /// In software, whatever is registered (for comparisons and checks)
+    pub fn software() -> Self {
+        Self(Inner::Software(sha2::Sha512::default()))
+    }
+
+    /// On `backend` rather than the registered one, or in software if its
+    /// context is in use
+    pub fn on(backend: &'static dyn Backend) -> Self {
+        if backend.start() {
+            Self(Inner::Backend(Claimed(backend)))
+        } else {
+            Self::software()
+        }
+    }
+}
+
+impl Default for Sha512 {
+    fn default() -> Self {
+        match BACKEND.get() {
+            Some(backend) => Self::on(*backend),
+            None => Self::software(),
+        }
+    }
+}
+
+impl HashMarker for Sha512 {}
+
+impl OutputSizeUser for Sha512 {
+    type OutputSize = U64;
+}
+
+impl Update for Sha512 {
+    fn update(&mut self, data: &[u8]) {
+        match &mut self.0 {
+            Inner::Software(hash) => Update::update(hash, data),
+            Inner::Backend(hash) => hash.0.update(data),
+        }
+    }
+}
+
+impl FixedOutput for Sha512 {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        match self.0 {
+            Inner::Software(hash) => FixedOutput::finalize_into(hash, out),
+            Inner::Backend(hash) => out.copy_from_slice(&hash.finish()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use sha2::Digest;
+    use std::sync::Mutex;
+
+    /// SHA-512("abc"), FIPS 180-2 appendix C.1
+    const ABC: [u8; 64] = [
+        0xdd, 0xaf, 0x35, 0xa1, 0x93, 0x61, 0x7a, 0xba, 0xcc, 0x41, 0x73, 0x49, 0xae, 0x20, 0x41,
+        0x31, 0x12, 0xe6, 0xfa, 0x4e, 0x89, 0xa9, 0x7e, 0xa2, 0x0a, 0x9e, 0xee, 0xe6, 0x4b, 0x55,
+        0xd3, 0x9a, 0x21, 0x92, 0x99, 0x2a, 0x27, 0x4f, 0xc1, 0xa8, 0x36, 0xba, 0x3c, 0x23, 0xa3,
+        0xfe, 0xeb, 0xbd, 0x45, 0x4d, 0x44, 0x23, 0x64, 0x3c, 0xe8, 0x0e, 0x2a, 0x9a, 0xc9, 0x4f,
+        0xa5, 0x4c, 0xa4, 0x9f,
+    ];
+
+    /// Software underneath, counting the computations started
+    struct Counted {
+        hash: Mutex<Option<sha2::Sha512>>,
+        started: AtomicUsize,
+    }
+
+    impl Counted {
+        const fn new() -> Self {
+            Self {
+                hash: Mutex::new(None),
+                started: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    impl Backend for Counted {
+        fn start(&self) -> bool {
+            let mut hash = self.hash.lock().unwrap();
+            if hash.is_some() {
+                return false;
+            }
+            *hash = Some(sha2::Sha512::default());
+            self.started.fetch_add(1, Ordering::SeqCst);
+            true
+        }
+
+        fn update(&self, data: &[u8]) {
+            let mut hash = self.hash.lock().unwrap();
+            Update::update(hash.as_mut().unwrap(), data);
+        }
+
+        fn finish(&self) -> [u8; 64] {
+            self.hash.lock().unwrap().take().unwrap().finalize().into()
+        }
+    }
+
+    #[test]
+    fn test_software_known_answer() {
+        assert_eq!(Sha512::software().chain_update(b"abc").finalize()[..], ABC);
+    }
+
+    #[test]
+    fn test_backend_reused_and_released() {
+        static COUNTED: Counted = Counted::new();
+        for _ in 0..2 {
+            let digest = Sha512::on(&COUNTED).chain_update(b"abc").finalize();
+            assert_eq!(digest[..], ABC);
+        }
+        assert_eq!(COUNTED.started.load(Ordering::SeqCst), 2);
+
+        // Abandoned hashes release the context too
+        drop(Sha512::on(&COUNTED).chain_update(b"a"));
+        assert!(COUNTED.hash.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_busy_backend_falls_back_to_software() {
+        static COUNTED: Counted = Counted::new();
+        let held = Sha512::on(&COUNTED);
+        let beside = Sha512::on(&COUNTED).chain_update(b"abc").finalize();
+        assert_eq!(beside[..], ABC);
+        assert_eq!(COUNTED.started.load(Ordering::SeqCst), 1);
+        drop(held);
+    }
+}
@@ -0,0 +1,85 @@
+//! GPIO level snapshots
+//!
+//! Machine integrations want proof not only that the button was pressed
+//! but of the interlock and switch states at that instant. With the
+//! firmware's `gpio-snapshot` feature a press is signed as
+//! `GpioSnapshot { gpio, mask, levels }`: bit n of `mask` is set for each
+//! sampled GPIO n, and the same bit of `levels` holds its level (1 = high).
+//! Carrying the mask lets a verifier tell a low pin from one that was not
+//! sampled, and keeps old attestations readable when the pin set changes.
+//!
+//! Pins are read one after another as soon as the press is detected, so the
+//! snapshot spans microseconds, not a single instant. GPIOs above 63 cannot
+//! be represented and are skipped.
+
+use crate::attestation::AttestationEvent;
+use crate::hal::InputPin;
+
+/// Levels of a set of GPIOs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Bit n set: GPIO n was sampled
+    pub mask: u64,
+    /// Bit n set: GPIO n read high
+    pub levels: u64,
+}
+
+impl Snapshot {
+    /// Sample `pins`, each given with its GPIO number
+    pub fn capture<'a, P: InputPin + 'a>(pins: impl IntoIterator<Item = (u8, &'a P)>) -> Self {
+        let mut snapshot = Self::default();
+        for (gpio, pin) in pins {
+            let Some(bit) = 1u64.checked_shl(gpio.into()) else {
+                continue;
+            };
+            snapshot.mask |= bit;
+            if !pin.is_low() {
+                snapshot.levels |= bit;
+            }
+        }
+        snapshot
+    }
+
+    /// Level of `gpio` (true = high), if it was sampled
+    pub fn level(&self, gpio: u8) -> Option<bool> {
+        let bit = 1u64.checked_shl(gpio.into())?;
+        (self.mask & bit != 0).then_some(self.levels & bit != 0)
+    }
+
+    /// The event for a press on `gpio`
+    pub fn event(self, gpio: u8) -> AttestationEvent {
+        AttestationEvent::GpioSnapshot {
+            gpio,
+            mask: self.mask,
+            levels: self.levels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::MockPin;
+
+    #[test]
+    fn test_capture() {
+        let (interlock, door, spare) = (MockPin::default(), MockPin::default(), MockPin::default());
+        door.set_low(true);
+        let snapshot = Snapshot::capture([(5, &interlock), (21, &door), (64, &spare)]);
+
+        assert_eq!(snapshot.mask, 1 << 5 | 1 << 21);
+        assert_eq!(snapshot.levels, 1 << 5);
+        assert_eq!(snapshot.level(5), Some(true));
+        assert_eq!(snapshot.level(21), Some(false));
+        assert_eq!(snapshot.level(6), None);
+        assert_eq!(snapshot.level(64), None);
+        assert_eq!(
+            snapshot.event(0),
+            AttestationEvent::GpioSnapshot {
+                gpio: 0,
+                mask: 1 << 5 | 1 << 21,
+                levels: 1 << 5
+            }
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! OpenSSH signature output (feature `sshsig`)
+//!
+//! Renders an attestation for `ssh-keygen -Y verify`: the ephemeral key as
+//! an authorized_keys line, and an armored `sshsig` signature over the
+//! payload bytes in the [`NAMESPACE`] namespace.
+//!
+//! ```text
+//! echo "icesickle $(cat key.pub)" > allowed_signers
+//! base64 -d payload.b64 > payload.bin
+//! ssh-keygen -Y verify -f allowed_signers -I icesickle -n icesickle \
+//!     -s payload.sig < payload.bin
+//! ```
+//!
+//! The principal (`icesickle` above) is the verifier's own label; the
+//! signature does not carry one. The namespace is signed, so the
+//! signature cannot be replayed as a git commit, file or other sshsig use.
+//! The signed blob starts with the `SSHSIG` magic, which no payload or
+//! other companion message does (see `companion`).
+
+use sha2::{Digest, Sha512};
+
+use crate::attestation::Attestation;
+use crate::companion::{self, Companion, Message, Subject};
+
+/// Signature namespace (`ssh-keygen -n`)
+pub const NAMESPACE: &str = "icesickle";
+
+/// Comment on the authorized_keys line
+pub const KEY_COMMENT: &str = "icesickle-ephemeral";
+
+const MAGIC: &[u8; 6] = b"SSHSIG";
+const SIG_VERSION: u32 = 1;
+const KEY_TYPE: &str = "ssh-ed25519";
+const HASH_ALGORITHM: &str = "sha512";
+
+/// Armored signature line width, as ssh-keygen writes it
+const ARMOR_WIDTH: usize = 70;
+
+/// authorized_keys line and armored signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signed {
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Render `attestation`; `None` unless it carries the sshsig companion
+pub fn render(attestation: &Attestation) -> Option<Signed> {
+    let signature = Companion::SshSig.find(attestation.companions())?;
+    let public_key = key_blob(attestation.public_key_bytes());
+
+    let mut signature_blob = Vec::new();
+    put_string(&mut signature_blob, KEY_TYPE.as_bytes());
+    put_string(&mut signature_blob, signature);
+
+    let mut blob = MAGIC.to_vec();
+    blob.extend(SIG_VERSION.to_be_bytes());
+    put_string(&mut blob, &public_key);
+    put_string(&mut blob, NAMESPACE.as_bytes());
+    put_string(&mut blob, b"");
+    put_string(&mut blob, HASH_ALGORITHM.as_bytes());
+    put_string(&mut blob, &signature_blob);
+
+    let mut armored = String::from("-----BEGIN SSH SIGNATURE-----\n");
+    let encoded = companion::base64(&blob);
+    for line in encoded.as_bytes().chunks(ARMOR_WIDTH) {
+        armored.push_str(core::str::from_utf8(line).unwrap_or_default());
+        armored.push('\n');
+    }
+    armored.push_str("-----END SSH SIGNATURE-----\n");
+
+    let key = companion::base64(&public_key);
+    Some(Signed {
+        public_key: format!("{KEY_TYPE} {key} {KEY_COMMENT}\n"),
+        signature: armored,
+    })
+}
+
+/// The blob the ephemeral key signs
+pub(crate) fn message(subject: &Subject) -> Message {
+    let digest = Sha512::digest(subject.payload);
+    let mut message = Message::new();
+    // 6 + 4+9 + 4 + 4+6 + 4+64 bytes: fits `MAX_MESSAGE_LEN`
+    let _ = message.extend_from_slice(MAGIC);
+    let mut put = |field: &[u8]| {
+        let _ = message.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        let _ = message.extend_from_slice(field);
+    };
+    put(NAMESPACE.as_bytes());
+    put(&[]);
+    put(HASH_ALGORITHM.as_bytes());
+    put(&digest);
+    message
+}
+
+/// Wire-format public key: key type and point
+fn key_blob(public_key: &[u8; 32]) -> Vec<u8> {
+    let mut blob = Vec::new();
+    put_string(&mut blob, KEY_TYPE.as_bytes());
+    put_string(&mut blob, public_key);
+    blob
+}
+
+/// SSH `string`: length-prefixed bytes
+fn put_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend((bytes.len() as u32).to_be_bytes());
+    out.extend(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::companion::tests::subject;
+
+    #[test]
+    fn test_message_layout() {
+        let message = message(&subject(b"payload"));
+        assert_eq!(message.len(), 101);
+        assert!(message.starts_with(b"SSHSIG\0\0\0\x09icesickle\0\0\0\0\0\0\0\x06sha512"));
+        assert_eq!(message[37..], Sha512::digest(b"payload")[..]);
+    }
+}
@@ -0,0 +1,249 @@
+//! Device state machine
+//!
+//! The firmware's behaviour is modelled as one explicit state plus a single
+//! pure [`transition`] function. The event loop turns what it observes
+//! (button presses, cooldown expiry, armed requests, failures) into
+//! [`Event`]s and feeds them through [`Machine::handle`], which logs every
+//! state change. Nothing else decides what state the device is in.
+//!
+//! ```text
+//!          Tick(armed)
+//!   Idle ───────────────► ChallengePending
+//!    ▲ ◄───────────────         │
+//!    │      Tick(!armed)        │ Pressed (also from Idle)
+//!    │                          ▼
+//!    │ Tick(ready)           Signing ── Failed ──► Fault
+//!    │                          │ Signed
+//!  Cooldown ◄── Emitted ──── Emitting
+//! ```
+//!
+//! A press refused by the cooldown moves to (or stays in) `Cooldown`; one
+//! that found every crypto workspace slot held (`Refused`) goes back from
+//! `Signing` to `Idle`, where the next press signs as usual.
+//! `Fault` is absorbing: only a reset leaves it. `Provisioning` is entered
+//! from `Idle` only and never signs. `Tampered` moves every state, `Fault`
+//! included, to `Lockout`, which nothing but a jumper-reset boot leaves.
+
+use crate::error::IceSickleError;
+
+/// What the device is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Waiting for a press
+    Idle,
+    /// A host request (armed digest or challenge, presence check) awaits the
+    /// next press
+    ChallengePending,
+    /// A press was just handled; further presses are refused until it ends
+    Cooldown,
+    /// Ephemeral key exists; producing the signature
+    Signing,
+    /// Key is gone; attestation is being written to the output channels
+    Emitting,
+    /// Unrecoverable failure; presses are refused until reset
+    Fault(IceSickleError),
+    /// Configuration may change; presses are refused
+    Provisioning,
+    /// Tamper detected; secrets wiped, presses refused until a jumper reset
+    Lockout,
+}
+
+/// Something the event loop observed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Periodic check; `cooldown_ready` once another attestation is allowed
+    /// and `armed` while a host request awaits a press
+    Tick { cooldown_ready: bool, armed: bool },
+    /// Button press accepted by the cooldown
+    Pressed,
+    /// Button press refused by the cooldown
+    Blocked,
+    /// Signature produced
+    Signed,
+    /// Attestation written out
+    Emitted,
+    /// Signing was refused for now ([`IceSickleError::Busy`]); the press is
+    /// dropped unsigned and another may follow
+    Refused,
+    /// Something failed that the device cannot continue past
+    Failed(IceSickleError),
+    /// Operator entered provisioning mode
+    EnterProvisioning,
+    /// Operator left provisioning mode
+    ExitProvisioning,
+    /// Tamper input fired (or a latched lockout was found at boot)
+    Tampered,
+}
+
+impl State {
+    /// True if a button press should be offered to the cooldown gate
+    pub fn accepts_press(&self) -> bool {
+        matches!(
+            self,
+            State::Idle | State::ChallengePending | State::Cooldown
+        )
+    }
+}
+
+/// The transition function; events that make no sense in `state` leave it
+/// unchanged
+pub fn transition(state: State, event: Event) -> State {
+    use Event::*;
+    use State::*;
+
+    match (state, event) {
+        (Lockout, _) | (_, Tampered) => Lockout,
+        (Fault(reason), _) => Fault(reason),
+        (_, Failed(reason)) => Fault(reason),
+        (
+            _,
+            Tick {
+                cooldown_ready,
+                armed,
+            },
+        ) => tick(state, cooldown_ready, armed),
+
+        (Idle | ChallengePending, Pressed) => Signing,
+        (Idle | ChallengePending | Cooldown, Blocked) => Cooldown,
+        (Signing, Signed) => Emitting,
+        (Signing, Refused) => Idle,
+        (Emitting, Emitted) => Cooldown,
+
+        (Idle, EnterProvisioning) => Provisioning,
+        (Provisioning, ExitProvisioning) => Idle,
+
+        (state, _) => state,
+    }
+}
+
+/// Waiting states follow the cooldown and armed-request levels
+fn tick(state: State, cooldown_ready: bool, armed: bool) -> State {
+    match state {
+        State::Cooldown if !cooldown_ready => State::Cooldown,
+        State::Idle | State::ChallengePending | State::Cooldown if armed => State::ChallengePending,
+        State::Idle | State::ChallengePending | State::Cooldown => State::Idle,
+        other => other,
+    }
+}
+
+/// Current state plus transition logging
+#[derive(Debug)]
+pub struct Machine {
+    state: State,
+}
+
+impl Machine {
+    pub const fn new() -> Self {
+        Self { state: State::Idle }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Apply `event`, logging the change if the state moves
+    pub fn handle(&mut self, event: Event) -> State {
+        let next = transition(self.state, event);
+        if next != self.state {
+            log::info!("State: {:?} -> {:?} ({:?})", self.state, next, event);
+            self.state = next;
+        }
+        next
+    }
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const READY: Event = Event::Tick {
+        cooldown_ready: true,
+        armed: false,
+    };
+
+    #[test]
+    fn test_press_cycle_returns_to_idle() {
+        let mut machine = Machine::new();
+        assert_eq!(machine.handle(Event::Pressed), State::Signing);
+        assert_eq!(machine.handle(Event::Signed), State::Emitting);
+        assert_eq!(machine.handle(Event::Emitted), State::Cooldown);
+        assert_eq!(machine.handle(Event::Pressed), State::Cooldown);
+        assert_eq!(machine.handle(READY), State::Idle);
+    }
+
+    #[test]
+    fn test_fault_is_absorbing() {
+        let mut machine = Machine::new();
+        machine.handle(Event::Pressed);
+        let fault = State::Fault(IceSickleError::Rng);
+        assert_eq!(machine.handle(Event::Failed(IceSickleError::Rng)), fault);
+        assert_eq!(machine.handle(READY), fault);
+        assert_eq!(machine.handle(Event::Pressed), fault);
+        assert!(!machine.state().accepts_press());
+    }
+
+    #[test]
+    fn test_busy_press_is_refused_not_fault() {
+        let mut machine = Machine::new();
+        machine.handle(Event::Pressed);
+        assert_eq!(machine.handle(Event::Refused), State::Idle);
+        assert!(machine.state().accepts_press());
+        assert_eq!(machine.handle(Event::Pressed), State::Signing);
+        assert_eq!(transition(State::Cooldown, Event::Refused), State::Cooldown);
+    }
+
+    #[test]
+    fn test_tamper_overrides_everything() {
+        let fault = State::Fault(IceSickleError::Rng);
+        assert_eq!(transition(fault, Event::Tampered), State::Lockout);
+        assert_eq!(transition(State::Signing, Event::Tampered), State::Lockout);
+        assert_eq!(transition(State::Lockout, READY), State::Lockout);
+        assert_eq!(
+            transition(State::Lockout, Event::Failed(IceSickleError::Rng)),
+            State::Lockout
+        );
+        assert!(!State::Lockout.accepts_press());
+    }
+
+    #[test]
+    fn test_armed_request_survives_cooldown() {
+        let armed = Event::Tick {
+            cooldown_ready: false,
+            armed: true,
+        };
+        assert_eq!(transition(State::Cooldown, armed), State::Cooldown);
+        assert_eq!(
+            transition(
+                State::Cooldown,
+                Event::Tick {
+                    cooldown_ready: true,
+                    armed: true
+                }
+            ),
+            State::ChallengePending
+        );
+        assert_eq!(transition(State::Idle, armed), State::ChallengePending);
+    }
+
+    #[test]
+    fn test_provisioning_only_from_idle() {
+        assert_eq!(
+            transition(State::Idle, Event::EnterProvisioning),
+            State::Provisioning
+        );
+        assert_eq!(
+            transition(State::Cooldown, Event::EnterProvisioning),
+            State::Cooldown
+        );
+        assert_eq!(
+            transition(State::Provisioning, Event::Pressed),
+            State::Provisioning
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Binary attestation stream
+//!
+//! The console's attestation line is text for people to read: it has no
+//! check of its own, and log output landing in the middle of it garbles
+//! it. With the firmware's `binary-stream` feature every attestation also
+//! goes out on a UART of its own, which carries nothing else, as frames a
+//! program can check:
+//!
+//! ```text
+//! COBS( STREAM_VERSION: u8 || postcard(AttestationRecord) || crc32_le ) || 0x00
+//! ```
+//!
+//! - Framing is the command protocol's: COBS leaves no zero byte inside a
+//!   frame, so `0x00` ends each one, and a receiver that starts listening
+//!   mid-frame is back in step at the next.
+//! - The record is the one `GetLastAttestation` returns, so every frame
+//!   carries all the signed fields and verifies on its own.
+//! - `crc32` is CRC-32/ISO-HDLC (as in zip and Ethernet) over everything
+//!   before it. It catches a damaged line; the signature inside is what
+//!   stops a forger.
+//!
+//! The stream only goes out: there are no requests, sequence numbers or
+//! retransmissions, and a frame lost on the line stays in the device's
+//! history for `GetHistory`. [`decode`] is the host side, which
+//! `icesickle-verify --stream` runs.
+
+use crate::error::{IceSickleError, Result};
+use crate::protocol::{self, AttestationRecord, ErrorCode};
+
+/// Stream revision (first byte of every decoded frame)
+pub const STREAM_VERSION: u8 = 1;
+
+/// Largest frame before COBS: the version, a record no longer than a
+/// command protocol frame, and the CRC
+const MAX_RAW_LEN: usize = 1 + protocol::MAX_FRAME_LEN + 4;
+
+/// Largest encoded frame, excluding the delimiter (COBS adds a byte per
+/// 254)
+pub const MAX_FRAME_LEN: usize = MAX_RAW_LEN + MAX_RAW_LEN / 254 + 1;
+
+/// Encode `record` as a complete frame (including the trailing delimiter)
+pub fn encode<'a>(
+    record: &AttestationRecord,
+    out: &'a mut [u8; MAX_FRAME_LEN + 1],
+) -> Result<&'a [u8]> {
+    let mut raw = [0u8; MAX_RAW_LEN];
+    raw[0] = STREAM_VERSION;
+    let record_len = postcard::to_slice(record, &mut raw[1..MAX_RAW_LEN - 4])?.len();
+
+    let crc_at = 1 + record_len;
+    let crc = crc32(&raw[..crc_at]);
+    raw[crc_at..crc_at + 4].copy_from_slice(&crc.to_le_bytes());
+
+    let len = protocol::cobs_encode(&raw[..crc_at + 4], &mut out[..MAX_FRAME_LEN])
+        .ok_or(IceSickleError::Serialize)?;
+    out[len] = protocol::DELIMITER;
+
+    Ok(&out[..len + 1])
+}
+
+/// Decode a single frame (without its delimiter) in place
+pub fn decode(frame: &mut [u8]) -> core::result::Result<AttestationRecord, ErrorCode> {
+    let len = protocol::cobs_decode_in_place(frame).ok_or(ErrorCode::Malformed)?;
+    let raw = &frame[..len];
+
+    // version + crc is the smallest possible frame
+    if len < 5 {
+        return Err(ErrorCode::Malformed);
+    }
+    if raw[0] != STREAM_VERSION {
+        return Err(ErrorCode::UnsupportedVersion);
+    }
+
+    let (content, crc) = raw.split_at(len - 4);
+    if crc32(content).to_le_bytes() != crc {
+        return Err(ErrorCode::Checksum);
+    }
+
+    postcard::from_bytes(&content[1..]).map_err(|_| ErrorCode::Malformed)
+}
+
+/// CRC-32/ISO-HDLC (reflected poly 0xEDB88320, init and final XOR all ones)
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &b in data {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::AttestationEvent;
+    use crate::auth::TokenProof;
+    use crate::context::{Context, MAX_CONTEXT_LEN};
+    use crate::policy::Policy;
+    use crate::scheme::Algorithm;
+    use crate::wallclock::{TimeSource, WallTime};
+
+    fn largest_record() -> AttestationRecord {
+        AttestationRecord {
+            version: u8::MAX,
+            event: AttestationEvent::DataDigest {
+                gpio: u8::MAX,
+                sha256: [0xFF; 32],
+                len: u64::MAX,
+            },
+            timestamp_ms: u64::MAX,
+            counter: u32::MAX,
+            public_key: [0x11; 32],
+            signature: [0xFF; 64],
+            wall_clock: Some(WallTime {
+                unix_s: u64::MAX,
+                source: TimeSource::Gps,
+                stale: true,
+            }),
+            policy: Policy::from_bits(u8::MAX),
+            suppressed: u32::MAX,
+            challenge: Some([0xFF; 32]),
+            token: Some(TokenProof::from_bytes(&[0xFF; 32])),
+            boot_nonce: Some([0xFF; 16]),
+            algorithm: Algorithm::Ed25519,
+            context: Some(Context::from_slice(&[0xFF; MAX_CONTEXT_LEN]).unwrap()),
+            measurement: Some([0xFF; 32]),
+        }
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_largest_record_round_trips() {
+        let record = largest_record();
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let frame = encode(&record, &mut out).unwrap();
+        let (last, body) = frame.split_last().unwrap();
+        assert_eq!(*last, 0);
+        assert!(!body.contains(&0));
+
+        let mut body = body.to_vec();
+        assert_eq!(decode(&mut body), Ok(record));
+    }
+
+    #[test]
+    fn test_damage_is_caught() {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let len = encode(&largest_record(), &mut out).unwrap().len() - 1;
+
+        // Flip a bit inside the public key (never produces a zero byte)
+        let key = out.windows(32).position(|w| w == [0x11; 32]).unwrap();
+        let mut damaged = out;
+        damaged[key + 16] ^= 0x01;
+        assert_eq!(decode(&mut damaged[..len]), Err(ErrorCode::Checksum));
+
+        // Another stream revision, with a valid CRC
+        let mut raw = vec![STREAM_VERSION + 1];
+        raw.extend(postcard::to_allocvec(&largest_record()).unwrap());
+        raw.extend(crc32(&raw).to_le_bytes());
+        let mut frame = [0u8; MAX_FRAME_LEN];
+        let len = protocol::cobs_encode(&raw, &mut frame).unwrap();
+        assert_eq!(
+            decode(&mut frame[..len]),
+            Err(ErrorCode::UnsupportedVersion)
+        );
+
+        assert_eq!(decode(&mut [0x02, 0x01]), Err(ErrorCode::Malformed));
+    }
+}
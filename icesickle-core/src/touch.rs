@@ -0,0 +1,158 @@
+//! Capacitive touch pads
+//!
+//! A touch pad is a solid-state trigger: no contacts to wear out, nothing
+//! to jam. The ESP32-S3's touch sensor measures each pad's capacitance as
+//! a raw count, which rises while a finger is on it. How far it rises
+//! depends on the pad, its trace and the enclosure, so [`TouchPad`] is
+//! calibrated at boot: the mean of untouched readings is the baseline, and
+//! thresholds are fractions above it.
+//!
+//! A reading [`PRESS_PERMILLE`] above the baseline starts a touch, and
+//! [`RELEASE_PERMILLE`] above it ends one. The gap between the two keeps
+//! noise near a single threshold from registering as a string of touches.
+//! Either change counts only once it has held for [`DEBOUNCE_MS`]. The
+//! firmware (feature `touch`) signs each touch as `TouchPad { channel }`.
+//!
+//! The baseline is not tracked afterwards. A pad calibrated with a finger
+//! on it, or one whose surroundings change a lot (a damp enclosure), stays
+//! off until the next boot.
+
+use crate::attestation::AttestationEvent;
+
+/// Untouched readings averaged into the baseline
+pub const CALIBRATION_SAMPLES: usize = 16;
+
+/// Rise over the baseline that starts a touch, in thousandths
+pub const PRESS_PERMILLE: u64 = 200;
+
+/// Rise over the baseline below which a touch ends, in thousandths
+pub const RELEASE_PERMILLE: u64 = 100;
+
+/// How long a reading must stay past a threshold to count
+pub const DEBOUNCE_MS: u64 = 50;
+
+/// One calibrated touch channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TouchPad {
+    channel: u8,
+    baseline: u32,
+    press: u32,
+    release: u32,
+    touched: bool,
+    /// When the reading first crossed the threshold it is now past
+    crossed_ms: Option<u64>,
+}
+
+impl TouchPad {
+    /// Calibrate `channel` from readings taken with no finger on the pad;
+    /// `None` for no readings or a zero mean (nothing measured)
+    pub fn calibrate(channel: u8, samples: impl IntoIterator<Item = u32>) -> Option<Self> {
+        let (sum, count) = samples.into_iter().fold((0u64, 0u64), |(sum, count), raw| {
+            (sum + u64::from(raw), count + 1)
+        });
+        let baseline = sum.checked_div(count).filter(|&mean| mean > 0)?;
+        let above = |permille: u64| {
+            u32::try_from(baseline + baseline * permille / 1000).unwrap_or(u32::MAX)
+        };
+        Some(Self {
+            channel,
+            baseline: baseline as u32,
+            press: above(PRESS_PERMILLE),
+            release: above(RELEASE_PERMILLE),
+            touched: false,
+            crossed_ms: None,
+        })
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Mean untouched reading
+    pub fn baseline(&self) -> u32 {
+        self.baseline
+    }
+
+    /// Feed the latest reading; true once per touch, after it has held
+    /// for [`DEBOUNCE_MS`]
+    pub fn update(&mut self, raw: u32, now_ms: u64) -> bool {
+        let past = if self.touched {
+            raw < self.release
+        } else {
+            raw >= self.press
+        };
+        if !past {
+            self.crossed_ms = None;
+            return false;
+        }
+        let crossed_ms = *self.crossed_ms.get_or_insert(now_ms);
+        if now_ms.saturating_sub(crossed_ms) < DEBOUNCE_MS {
+            return false;
+        }
+        self.crossed_ms = None;
+        self.touched = !self.touched;
+        self.touched
+    }
+
+    /// The event for a touch on this pad
+    pub fn event(&self) -> AttestationEvent {
+        AttestationEvent::TouchPad {
+            channel: self.channel,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pad() -> TouchPad {
+        TouchPad::calibrate(3, [9_900, 10_100].repeat(CALIBRATION_SAMPLES / 2)).unwrap()
+    }
+
+    #[test]
+    fn test_calibration_sets_thresholds() {
+        let pad = pad();
+        assert_eq!(pad.baseline(), 10_000);
+        assert_eq!((pad.press, pad.release), (12_000, 11_000));
+        assert_eq!(pad.event(), AttestationEvent::TouchPad { channel: 3 });
+
+        assert!(TouchPad::calibrate(3, [0u32; 0]).is_none());
+        assert!(TouchPad::calibrate(3, [0u32, 0]).is_none());
+        let saturated = TouchPad::calibrate(3, [u32::MAX]).unwrap();
+        assert_eq!(saturated.press, u32::MAX);
+    }
+
+    #[test]
+    fn test_touch_reported_once_after_debounce() {
+        let mut pad = pad();
+        assert!(!pad.update(11_999, 1_000));
+        assert!(!pad.update(12_000, 1_000));
+        assert!(!pad.update(12_500, 1_000 + DEBOUNCE_MS - 1));
+        assert!(pad.update(12_500, 1_000 + DEBOUNCE_MS));
+        assert!(!pad.update(12_500, 2_000));
+        assert!(!pad.update(12_500, 3_000));
+    }
+
+    #[test]
+    fn test_spike_and_noise_between_thresholds_ignored() {
+        let mut pad = pad();
+        // A spike shorter than the debounce window
+        assert!(!pad.update(13_000, 1_000));
+        assert!(!pad.update(10_000, 1_010));
+        assert!(!pad.update(13_000, 1_020));
+        assert!(!pad.update(13_000, 1_060));
+        assert!(pad.update(13_000, 1_070));
+
+        // Dips that stay above the release threshold do not end the touch
+        assert!(!pad.update(11_500, 1_200));
+        assert!(!pad.update(12_500, 1_300));
+        assert!(!pad.update(11_500, 1_400));
+
+        // Released and touched again
+        assert!(!pad.update(10_500, 1_500));
+        assert!(!pad.update(10_500, 1_500 + DEBOUNCE_MS));
+        assert!(!pad.update(12_500, 2_000));
+        assert!(pad.update(12_500, 2_000 + DEBOUNCE_MS));
+    }
+}
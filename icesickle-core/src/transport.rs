@@ -0,0 +1,145 @@
+//! Push framing for transports that deliver every attestation unasked
+//!
+//! The serial console prints attestations as text for people and log
+//! scrapers. Browser verifiers, phones and host apps want the bytes instead:
+//! the firmware pushes each attestation as a [`Message`] on a vendor HID
+//! interface that WebHID can open (feature `usb-hid`), as BLE notifications
+//! (feature `ble`) and as an NDEF record on an NFC tag (feature `nfc`). A
+//! message is
+//!
+//! ```text
+//! payload_len: u16 LE | payload (payload_len bytes) | public key (32) | signature (64)
+//! ```
+//!
+//! where the payload is exactly the bytes that were signed behind
+//! `attestation::SIGNING_DOMAIN` (`Attestation::payload_bytes`), so a
+//! verifier checks the signature over `SIGNING_DOMAIN || payload` with any
+//! Ed25519 implementation, WebCrypto included, and decodes the payload only
+//! if it wants the fields. A `sign-p256` build signs with ECDSA P-256
+//! instead (see `scheme`), which WebCrypto also checks.
+//!
+//! Messages travel in frames. Byte 0 is the frame's index within the
+//! message, 0 starting a new one; the rest is message data. A host that sees
+//! index 0 while reassembling drops the partial message and starts over.
+//! HID frames are [`REPORT_LEN`]-byte reports, zero-padded after the last
+//! byte; BLE frames fit the connection's MTU and are not padded. A tag holds
+//! one whole message and needs no frames.
+
+use crate::attestation::{Attestation, MAX_PAYLOAD_LEN};
+
+/// Report size
+pub const REPORT_LEN: usize = 64;
+
+/// Shortest frame: the index and two bytes of data
+pub const MIN_FRAME_LEN: usize = 3;
+
+/// Longest message
+pub const MAX_MESSAGE_LEN: usize = 2 + MAX_PAYLOAD_LEN + 32 + 64;
+
+/// Reports the longest message needs
+pub const MAX_REPORTS: usize = MAX_MESSAGE_LEN.div_ceil(REPORT_LEN - 1);
+
+/// Encoded message
+pub type Message = heapless::Vec<u8, MAX_MESSAGE_LEN>;
+
+/// One frame
+pub type Frame = heapless::Vec<u8, REPORT_LEN>;
+
+// Frame indexes fit a byte even at MIN_FRAME_LEN
+const _: () = assert!(MAX_MESSAGE_LEN.div_ceil(MIN_FRAME_LEN - 1) <= 256);
+
+/// Encode `attestation`
+pub fn message(attestation: &Attestation) -> Message {
+    let payload = attestation.payload_bytes();
+    let mut message = Message::new();
+    // Lengths are bounded by MAX_MESSAGE_LEN
+    let _ = message.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    let _ = message.extend_from_slice(&payload);
+    let _ = message.extend_from_slice(attestation.public_key_bytes());
+    let _ = message.extend_from_slice(attestation.signature_bytes());
+    message
+}
+
+/// Split `message` into frames of at most `frame_len` bytes, clamped to
+/// [`MIN_FRAME_LEN`]..=[`REPORT_LEN`]
+pub fn frames(message: &[u8], frame_len: usize) -> impl ExactSizeIterator<Item = Frame> + '_ {
+    let data_len = frame_len.clamp(MIN_FRAME_LEN, REPORT_LEN) - 1;
+    message.chunks(data_len).enumerate().map(|(index, data)| {
+        let mut frame = Frame::new();
+        // Indexes fit a byte (asserted below); data fits the frame
+        let _ = frame.push(index as u8);
+        let _ = frame.extend_from_slice(data);
+        frame
+    })
+}
+
+/// Split `message` into padded reports
+pub fn reports(message: &[u8]) -> impl ExactSizeIterator<Item = [u8; REPORT_LEN]> + '_ {
+    frames(message, REPORT_LEN).map(|frame| {
+        let mut report = [0u8; REPORT_LEN];
+        report[..frame.len()].copy_from_slice(&frame);
+        report
+    })
+}
+
+/// Payload, public key and signature of a reassembled message, ignoring
+/// padding after it; `None` if it is too short
+pub fn parse(message: &[u8]) -> Option<(&[u8], &[u8; 32], &[u8; 64])> {
+    let (len, rest) = message.split_first_chunk::<2>()?;
+    let len = u16::from_le_bytes(*len) as usize;
+    if rest.len() < len + 32 + 64 {
+        return None;
+    }
+    let (payload, rest) = rest.split_at(len);
+    let (public_key, rest) = rest.split_first_chunk::<32>()?;
+    let (signature, _) = rest.split_first_chunk::<64>()?;
+    Some((payload, public_key, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::{self, AttestationEvent};
+    use crate::entropy::HardwareRng;
+    use crate::hal::mock::{MockNoise, MockTimer};
+
+    #[test]
+    fn test_reports_reassemble() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let event = AttestationEvent::DataDigest {
+            gpio: 0,
+            sha256: [0xFF; 32],
+            len: u64::MAX,
+        };
+        let attestation = Attestation::create(&rng, &MockTimer::at(1_000), event).unwrap();
+        let message = message(&attestation);
+
+        let reports: Vec<_> = reports(&message).collect();
+        assert!(reports.len() <= MAX_REPORTS);
+        let mut received = Vec::new();
+        for (index, report) in reports.iter().enumerate() {
+            assert_eq!(report[0], index as u8);
+            received.extend_from_slice(&report[1..]);
+        }
+
+        // The host verifies the raw bytes without decoding them
+        let (payload, public_key, signature) = parse(&received).unwrap();
+        assert_eq!(payload, &attestation.payload_bytes()[..]);
+        let message = attestation::signed_message(payload);
+        assert!(attestation
+            .algorithm()
+            .verify(public_key, &message, signature));
+
+        assert_eq!(parse(&received[..message.len() - 1]), None);
+
+        // At the smallest BLE MTU (23) a notification carries 20 bytes
+        let frames: Vec<_> = frames(&message, 20).collect();
+        assert!(frames.iter().all(|frame| frame.len() <= 20));
+        let received: Vec<u8> = frames
+            .iter()
+            .flat_map(|frame| &frame[1..])
+            .copied()
+            .collect();
+        assert_eq!(received, &message[..]);
+    }
+}
@@ -0,0 +1,124 @@
+//! Differentially private usage counts
+//!
+//! Some operators want to know how much a device is used without collecting
+//! every attestation. With the firmware's `usage-stats` feature the device
+//! closes each window by signing a `UsageCount` attestation: the number of
+//! attestations it emitted in the window plus noise from the two-sided
+//! geometric mechanism (the discrete Laplace). Adding or removing any one
+//! attestation changes the probability of every reported count by at most a
+//! factor of e^ε, so the count is ε-differentially private.
+//!
+//! ε (in thousandths) and the window length are signed into the event, so a
+//! collector knows how noisy each count is without trusting anything else.
+//! The noise has mean 0 and variance 2α/(1-α)^2 with α = e^-ε ([`variance`]):
+//! summed over many windows, the counts stay unbiased while each window's
+//! stays deniable. A count can come out negative; it is reported as drawn,
+//! since clamping would bias sums.
+//!
+//! The guarantee is per attestation. Someone behind k attestations in one
+//! window is protected at kε; the cooldown bounds k.
+//!
+//! Noise comes from the hardware RNG: each geometric draw inverts its CDF
+//! on a 53-bit uniform. The output is an integer, which avoids the
+//! low-order-bit leaks of floating-point Laplace noise.
+
+use crate::entropy::HardwareRng;
+use crate::hal::EntropySource;
+
+/// `count` plus two-sided geometric noise for privacy parameter ε
+///
+/// `epsilon_milli` is ε in thousandths; 0 is treated as 1 (ε = 0.001), the
+/// noisiest count this can express.
+pub fn noisy_count<S: EntropySource>(rng: &HardwareRng<S>, count: u32, epsilon_milli: u16) -> i32 {
+    let noise = two_sided_geometric(alpha(epsilon_milli), || uniform(rng));
+    (i64::from(count) + noise).clamp(i32::MIN.into(), i32::MAX.into()) as i32
+}
+
+/// Variance of the noise added for `epsilon_milli`
+pub fn variance(epsilon_milli: u16) -> f64 {
+    let alpha = alpha(epsilon_milli);
+    2.0 * alpha / ((1.0 - alpha) * (1.0 - alpha))
+}
+
+/// α = e^-ε
+fn alpha(epsilon_milli: u16) -> f64 {
+    (-f64::from(epsilon_milli.max(1)) / 1_000.0).exp()
+}
+
+/// Uniform on (0, 1], from 53 random bits
+fn uniform<S: EntropySource>(rng: &HardwareRng<S>) -> f64 {
+    let mut bytes = [0u8; 8];
+    rng.fill_bytes(&mut bytes);
+    ((u64::from_le_bytes(bytes) >> 11) + 1) as f64 / (1u64 << 53) as f64
+}
+
+/// Failures before the first success, success probability 1 - α
+fn geometric(alpha: f64, uniform: f64) -> i64 {
+    (uniform.ln() / alpha.ln()).floor() as i64
+}
+
+/// Difference of two independent geometric draws
+fn two_sided_geometric(alpha: f64, mut uniform: impl FnMut() -> f64) -> i64 {
+    geometric(alpha, uniform()) - geometric(alpha, uniform())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::MockEntropy;
+
+    /// Deterministic uniforms on (0, 1] (xorshift64)
+    fn uniforms() -> impl FnMut() -> f64 {
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            ((state >> 11) + 1) as f64 / (1u64 << 53) as f64
+        }
+    }
+
+    #[test]
+    fn test_noise_distribution() {
+        const SAMPLES: usize = 200_000;
+        let alpha = alpha(1_000);
+        let mut uniform = uniforms();
+        let draws: Vec<i64> = (0..SAMPLES)
+            .map(|_| two_sided_geometric(alpha, &mut uniform))
+            .collect();
+
+        let mean = draws.iter().sum::<i64>() as f64 / SAMPLES as f64;
+        let var = draws
+            .iter()
+            .map(|&d| (d as f64 - mean).powi(2))
+            .sum::<f64>()
+            / SAMPLES as f64;
+        let zeros = draws.iter().filter(|&&d| d == 0).count() as f64 / SAMPLES as f64;
+        assert!(mean.abs() < 0.02, "mean {mean}");
+        assert!((var / variance(1_000) - 1.0).abs() < 0.03, "variance {var}");
+        // P(0) = (1 - α) / (1 + α)
+        assert!((zeros - (1.0 - alpha) / (1.0 + alpha)).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_noisy_count() {
+        // Identical draws cancel
+        let rng = HardwareRng::from_source(MockEntropy(0x5a)).unwrap();
+        assert_eq!(noisy_count(&rng, 42, 1_000), 42);
+        assert_eq!(noisy_count(&rng, u32::MAX, 1_000), i32::MAX);
+
+        // Smaller ε, more noise; ε = 0 is the smallest ε, not a division by 0
+        assert!(variance(100) > variance(1_000));
+        assert_eq!(variance(0), variance(1));
+        assert!(variance(0).is_finite());
+    }
+
+    #[test]
+    fn test_uniform_range() {
+        let low = HardwareRng::from_source(MockEntropy(0x01)).unwrap();
+        let high = HardwareRng::from_source(MockEntropy(0xFF)).unwrap();
+        assert!(uniform(&low) > 0.0);
+        assert_eq!(uniform(&high), 1.0);
+        assert_eq!(geometric(alpha(1_000), 1.0), 0);
+    }
+}
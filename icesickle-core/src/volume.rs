@@ -0,0 +1,401 @@
+//! Read-only FAT12 volume of recent attestations (feature `volume`)
+//!
+//! Builds the disk image the firmware serves over USB mass storage (feature
+//! `usb-msc`), so attestations can be copied off the device like files from
+//! a flash drive. Each record in the history becomes `att-<counter>.json`:
+//!
+//! ```text
+//! {"payloadVersion":12,
+//!  "event":{"type":"ButtonPress","postcard":"0000"},
+//!  "counter":7,"timestampMs":1234,"wallClock":null,"policy":0,
+//!  "suppressed":0,"challenge":null,"token":null,"bootNonce":"<hex>",
+//!  "context":null,"measurement":null,"algorithm":"Ed25519",
+//!  "publicKey":"<hex>","did":"did:key:z6Mk...","signature":"<hex>"}
+//! ```
+//!
+//! Field names follow the DSSE predicate (see `dsse`), plus the DID and the
+//! signature over the payload the fields encode. Files carry the
+//! attestation's UTC time when it has one, else 1980-01-01, FAT's epoch.
+//! Records signed without a counter (`policy`) are numbered by their place
+//! on the volume instead.
+//!
+//! The image is rebuilt whole rather than edited: one FAT, a root directory
+//! of [`ROOT_ENTRIES`] entries, and a fixed slot of [`FILE_SECTORS`]
+//! one-sector clusters per file. Nothing on the volume tells devices apart:
+//! the volume ID is zero and every unit has the same label.
+
+use core::fmt::Write;
+
+use crate::attestation::{JsonRecord, MAX_JSON_LEN};
+use crate::history::MAX_HISTORY;
+use crate::policy::Policy;
+use crate::protocol::AttestationRecord;
+use crate::wallclock::{self, WallTime};
+
+pub const SECTOR_LEN: usize = 512;
+
+/// Files on the volume: one per history record
+pub const MAX_FILES: usize = MAX_HISTORY;
+
+/// Sectors reserved for each file
+pub const FILE_SECTORS: usize = 2;
+
+/// Longest file (see the tests for the longest record)
+pub const MAX_FILE_LEN: usize = FILE_SECTORS * SECTOR_LEN;
+
+/// Root directory entries: the label, then up to three per file
+pub const ROOT_ENTRIES: usize = 64;
+
+const DIR_ENTRY_LEN: usize = 32;
+const FAT_SECTORS: usize = 1;
+const ROOT_SECTORS: usize = ROOT_ENTRIES * DIR_ENTRY_LEN / SECTOR_LEN;
+const DATA_SECTOR: usize = 1 + FAT_SECTORS + ROOT_SECTORS;
+
+/// Whole volume
+pub const SECTOR_COUNT: usize = DATA_SECTOR + MAX_FILES * FILE_SECTORS;
+
+pub const IMAGE_LEN: usize = SECTOR_COUNT * SECTOR_LEN;
+
+// Every cluster has a 12-bit FAT entry; every file a name of up to two long
+// entries and a short one
+const _: () = assert!((2 + MAX_FILES * FILE_SECTORS) * 3 / 2 <= FAT_SECTORS * SECTOR_LEN);
+const _: () = assert!(3 * MAX_FILES < ROOT_ENTRIES);
+const _: () = assert!(MAX_JSON_LEN <= MAX_FILE_LEN);
+
+/// Disk image, sector 0 first
+pub type Image = [u8; IMAGE_LEN];
+
+/// One attestation file
+pub type Json = JsonRecord;
+
+const LABEL: &[u8; 11] = b"ICESICKLE  ";
+const MEDIA: u8 = 0xF8;
+const END_OF_CHAIN: u16 = 0xFFF;
+
+const ATTR_READ_ONLY: u8 = 0x01;
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_LONG_NAME: u8 = 0x0F;
+
+/// Last long-name entry of a name (stored first)
+const LAST_LONG_ENTRY: u8 = 0x40;
+
+/// Characters per long-name entry, and their byte offsets in it
+const LONG_NAME_OFFSETS: [usize; 13] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// 1980-01-01, for files without a UTC time
+const FAT_EPOCH_DATE: u16 = (1 << 5) | 1;
+
+/// Build the volume holding `records` (the first [`MAX_FILES`] of them)
+pub fn build<'a>(records: impl IntoIterator<Item = &'a AttestationRecord>, image: &mut Image) {
+    image.fill(0);
+    let (boot, rest) = image.split_at_mut(SECTOR_LEN);
+    let (fat, rest) = rest.split_at_mut(FAT_SECTORS * SECTOR_LEN);
+    let (root, data) = rest.split_at_mut(ROOT_SECTORS * SECTOR_LEN);
+
+    boot_sector(boot);
+    set_fat(fat, 0, 0xF00 | u16::from(MEDIA));
+    set_fat(fat, 1, END_OF_CHAIN);
+
+    let mut entries = root.chunks_exact_mut(DIR_ENTRY_LEN);
+    if let Some(entry) = entries.next() {
+        entry[..11].copy_from_slice(LABEL);
+        entry[11] = ATTR_VOLUME_ID;
+        entry[24..26].copy_from_slice(&FAT_EPOCH_DATE.to_le_bytes());
+    }
+
+    for (slot, record) in records.into_iter().take(MAX_FILES).enumerate() {
+        let json = json(record);
+        let contents = &mut data[slot * MAX_FILE_LEN..][..MAX_FILE_LEN];
+        contents[..json.len()].copy_from_slice(json.as_bytes());
+
+        let first = 2 + slot * FILE_SECTORS;
+        let clusters = json.len().div_ceil(SECTOR_LEN);
+        for cluster in first..first + clusters {
+            let next = if cluster + 1 == first + clusters {
+                END_OF_CHAIN
+            } else {
+                cluster as u16 + 1
+            };
+            set_fat(fat, cluster, next);
+        }
+
+        // Short names are the counter in hex: unique, and valid 8.3. Omitted
+        // counters are all 0, so those files are numbered by slot instead
+        let number = if record.policy.contains(Policy::NO_COUNTER) {
+            slot as u32
+        } else {
+            record.counter
+        };
+        let mut short = [0u8; 11];
+        let _ = write!(Ascii(&mut short), "{:08X}JSO", number);
+        let mut long = heapless::String::<26>::new();
+        let _ = write!(long, "att-{}.json", number);
+        long_name(&long, &short, &mut entries);
+
+        if let Some(entry) = entries.next() {
+            let (date, time) = date_time(record.wall_clock);
+            entry[..11].copy_from_slice(&short);
+            entry[11] = ATTR_READ_ONLY;
+            entry[14..16].copy_from_slice(&time.to_le_bytes());
+            entry[16..18].copy_from_slice(&date.to_le_bytes());
+            entry[18..20].copy_from_slice(&date.to_le_bytes());
+            entry[22..24].copy_from_slice(&time.to_le_bytes());
+            entry[24..26].copy_from_slice(&date.to_le_bytes());
+            entry[26..28].copy_from_slice(&(first as u16).to_le_bytes());
+            entry[28..32].copy_from_slice(&(json.len() as u32).to_le_bytes());
+        }
+    }
+}
+
+/// The file for `record`: its JSON record
+pub fn json(record: &AttestationRecord) -> Json {
+    record.json()
+}
+
+fn boot_sector(sector: &mut [u8]) {
+    sector[..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+    sector[3..11].copy_from_slice(b"MSDOS5.0");
+    sector[11..13].copy_from_slice(&(SECTOR_LEN as u16).to_le_bytes());
+    sector[13] = 1; // sectors per cluster
+    sector[14..16].copy_from_slice(&1u16.to_le_bytes()); // reserved sectors
+    sector[16] = 1; // number of FATs
+    sector[17..19].copy_from_slice(&(ROOT_ENTRIES as u16).to_le_bytes());
+    sector[19..21].copy_from_slice(&(SECTOR_COUNT as u16).to_le_bytes());
+    sector[21] = MEDIA;
+    sector[22..24].copy_from_slice(&(FAT_SECTORS as u16).to_le_bytes());
+    sector[24..26].copy_from_slice(&1u16.to_le_bytes()); // sectors per track
+    sector[26..28].copy_from_slice(&1u16.to_le_bytes()); // heads
+    sector[36] = 0x80; // drive number
+    sector[38] = 0x29; // extended boot signature; volume ID stays zero
+    sector[43..54].copy_from_slice(LABEL);
+    sector[54..62].copy_from_slice(b"FAT12   ");
+    sector[510..].copy_from_slice(&[0x55, 0xAA]);
+}
+
+/// Set the 12-bit FAT entry of `cluster`
+fn set_fat(fat: &mut [u8], cluster: usize, value: u16) {
+    let at = cluster * 3 / 2;
+    if cluster.is_multiple_of(2) {
+        fat[at] = value as u8;
+        fat[at + 1] = (fat[at + 1] & 0xF0) | (value >> 8) as u8;
+    } else {
+        fat[at] = (fat[at] & 0x0F) | (value << 4) as u8;
+        fat[at + 1] = (value >> 4) as u8;
+    }
+}
+
+/// Write the long-name entries of `name`, last part first
+fn long_name<'a>(name: &str, short: &[u8; 11], entries: &mut impl Iterator<Item = &'a mut [u8]>) {
+    let checksum = short
+        .iter()
+        .fold(0u8, |sum, &b| sum.rotate_right(1).wrapping_add(b));
+    let chars = name.len();
+    let parts = chars.div_ceil(LONG_NAME_OFFSETS.len());
+    for part in (0..parts).rev() {
+        let Some(entry) = entries.next() else {
+            return;
+        };
+        entry[0] = (part + 1) as u8;
+        if part + 1 == parts {
+            entry[0] |= LAST_LONG_ENTRY;
+        }
+        entry[11] = ATTR_LONG_NAME;
+        entry[13] = checksum;
+        for (i, &offset) in LONG_NAME_OFFSETS.iter().enumerate() {
+            // Names are ASCII; NUL-terminated, then padded with 0xFFFF
+            let at = part * LONG_NAME_OFFSETS.len() + i;
+            let unit = match name.as_bytes().get(at) {
+                Some(&b) => u16::from(b),
+                None if at == chars => 0,
+                None => 0xFFFF,
+            };
+            entry[offset..offset + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+    }
+}
+
+/// FAT `(date, time)` of a file
+fn date_time(wall: Option<WallTime>) -> (u16, u16) {
+    let Some(unix_s) = wall.map(|w| w.unix_s) else {
+        return (FAT_EPOCH_DATE, 0);
+    };
+    let (year, month, day) = wallclock::civil_from_days(unix_s / 86_400);
+    if !(1980..=2107).contains(&year) {
+        return (FAT_EPOCH_DATE, 0);
+    }
+    let second = unix_s % 86_400;
+    let date = ((year - 1980) << 9) | (month << 5) | day;
+    let time = ((second / 3_600) << 11) | ((second / 60 % 60) << 5) | ((second % 60) / 2);
+    (date as u16, time as u16)
+}
+
+/// Bytes written into a fixed field
+struct Ascii<'a>(&'a mut [u8]);
+
+impl Write for Ascii<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let field = core::mem::take(&mut self.0);
+        if s.len() > field.len() {
+            return Err(core::fmt::Error);
+        }
+        let (head, tail) = field.split_at_mut(s.len());
+        head.copy_from_slice(s.as_bytes());
+        self.0 = tail;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::AttestationEvent;
+    use crate::auth::TokenProof;
+    use crate::context::{Context, MAX_CONTEXT_LEN};
+    use crate::scheme::Algorithm;
+    use crate::wallclock::TimeSource;
+
+    fn record(counter: u32) -> AttestationRecord {
+        AttestationRecord {
+            version: 12,
+            event: AttestationEvent::ButtonPress { gpio: 0 },
+            timestamp_ms: 1_234,
+            counter,
+            public_key: [0x11; 32],
+            signature: [0x22; 64],
+            wall_clock: None,
+            policy: Policy::NONE,
+            suppressed: 0,
+            challenge: None,
+            token: None,
+            boot_nonce: None,
+            algorithm: Algorithm::Ed25519,
+            context: None,
+            measurement: None,
+        }
+    }
+
+    fn sector(image: &Image, n: usize) -> &[u8] {
+        &image[n * SECTOR_LEN..][..SECTOR_LEN]
+    }
+
+    #[test]
+    fn test_json_layout() {
+        let json = json(&record(7));
+        assert!(json.starts_with(
+            "{\"payloadVersion\":12,\"event\":{\"type\":\"ButtonPress\",\"postcard\":\"0000\"},\
+             \"counter\":7,\"timestampMs\":1234,\"wallClock\":null,\"policy\":0,\"suppressed\":0,\
+             \"challenge\":null,\"token\":null,\"bootNonce\":null,\"context\":null,\
+             \"measurement\":null,\"algorithm\":\"Ed25519\","
+        ));
+        assert!(
+            json.contains(",\"did\":\"did:key:z6Mkfbt52NAcPcYKV36L6eWTnyfxyGrGrxvJBxF5pjjCctGQ\",")
+        );
+        assert!(json.ends_with("\"}\n"));
+    }
+
+    #[test]
+    fn test_longest_json_fits() {
+        let record = AttestationRecord {
+            event: AttestationEvent::DataDigest {
+                gpio: u8::MAX,
+                sha256: [0xFF; 32],
+                len: u64::MAX,
+            },
+            timestamp_ms: u64::MAX,
+            counter: u32::MAX,
+            wall_clock: Some(WallTime {
+                unix_s: u64::MAX,
+                source: TimeSource::Gps,
+                stale: false,
+            }),
+            policy: Policy::from_bits(u8::MAX),
+            suppressed: u32::MAX,
+            challenge: Some([0xFF; 32]),
+            token: Some(TokenProof::from_bytes(&[0xFF; 32])),
+            boot_nonce: Some([0xFF; 16]),
+            algorithm: Algorithm::Ed25519,
+            context: Some(Context::from_slice(&[0xFF; MAX_CONTEXT_LEN]).unwrap()),
+            measurement: Some([0xFF; 32]),
+            ..record(0)
+        };
+        assert!(json(&record).ends_with("\"}\n"));
+    }
+
+    #[test]
+    fn test_image_layout() {
+        let stamped = AttestationRecord {
+            wall_clock: Some(WallTime {
+                unix_s: 1_709_251_199,
+                source: TimeSource::Gps,
+                stale: false,
+            }),
+            ..record(4_294_967_295)
+        };
+        let mut image = [0u8; IMAGE_LEN];
+        build([&record(7), &stamped], &mut image);
+
+        let boot = sector(&image, 0);
+        assert_eq!(boot[510..], [0x55, 0xAA]);
+        assert_eq!(boot[19..21], (SECTOR_COUNT as u16).to_le_bytes());
+        assert_eq!(boot[54..62], *b"FAT12   ");
+
+        // Media entries, then a two-cluster chain filling each slot: a
+        // record is longer than one sector
+        let fat = sector(&image, 1);
+        assert_eq!(fat[..8], [0xF8, 0xFF, 0xFF, 0x03, 0xF0, 0xFF, 0x05, 0xF0]);
+
+        let root = sector(&image, 2);
+        assert_eq!(root[..11], *LABEL);
+        // "att-7.json": one long entry, then the short one
+        assert_eq!(root[32], 0x41);
+        assert_eq!(root[33..35], [b'a', 0]);
+        assert_eq!(root[64..75], *b"00000007JSO");
+        assert_eq!(root[64 + 26..64 + 28], [2, 0]);
+        let len = json(&record(7)).len();
+        assert_eq!(root[64 + 28..64 + 32], (len as u32).to_le_bytes());
+        // "att-4294967295.json": two long entries, the last part first
+        assert_eq!(root[96], 0x42);
+        assert_eq!(root[128], 0x01);
+        assert_eq!(root[160..171], *b"FFFFFFFFJSO");
+        // 2024-02-29 23:59:58
+        assert_eq!(root[160 + 22..160 + 26], [0x7D, 0xBF, 0x5D, 0x58]);
+        assert_eq!(root[160 + 26..160 + 28], [4, 0]);
+
+        assert!(len > SECTOR_LEN);
+        let data = &image[DATA_SECTOR * SECTOR_LEN..][..MAX_FILE_LEN];
+        assert_eq!(data[..len], *json(&record(7)).as_bytes());
+        assert_eq!(data[len], 0);
+    }
+
+    #[test]
+    fn test_omitted_counters_are_numbered_by_slot() {
+        let omitted = AttestationRecord {
+            policy: Policy::NO_COUNTER,
+            ..record(0)
+        };
+        let mut image = [0u8; IMAGE_LEN];
+        build([&omitted, &omitted], &mut image);
+
+        let root = sector(&image, 2);
+        assert_eq!(root[64..75], *b"00000000JSO");
+        assert_eq!(root[128..139], *b"00000001JSO");
+    }
+
+    #[test]
+    fn test_long_name_checksum() {
+        let mut root = [0u8; 2 * DIR_ENTRY_LEN];
+        let short = b"00000007JSO";
+        long_name(
+            "att-7.json",
+            short,
+            &mut root.chunks_exact_mut(DIR_ENTRY_LEN),
+        );
+        let expected = short.iter().fold(0u8, |sum, &b| {
+            ((sum & 1) << 7).wrapping_add(sum >> 1).wrapping_add(b)
+        });
+        assert_eq!(root[13], expected);
+        // NUL after the name, then padding
+        assert_eq!(root[22..26], [b'n', 0, 0, 0]);
+        assert_eq!(root[28..30], [0xFF, 0xFF]);
+    }
+}
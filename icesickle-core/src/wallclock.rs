@@ -0,0 +1,375 @@
+//! Wall-clock time for attestation payloads
+//!
+//! `timestamp_ms` counts from boot, which says nothing about *when* an event
+//! happened in the world. When the device has an external time reference,
+//! payloads also carry a [`WallTime`]: whole UTC seconds, the source they
+//! came from, and whether the time is stale. Without a reference the field
+//! is absent, and a time the device has reason to doubt is flagged, never
+//! passed off as good.
+//!
+//! Wall time is useful, not trusted: it is whatever the reference says,
+//! and a spoofed GPS signal or a reset RTC says anything (see
+//! THREAT_MODEL.md). `timestamp_ms` and the counter stay the monotonic
+//! reference for ordering within a boot; wall time only places boots
+//! relative to each other.
+//!
+//! The first source is GPS (firmware feature `gps`). The receiver's NMEA
+//! sentences name the second, and its PPS line marks exactly when that
+//! second began. [`PpsClock`] pairs the two: an RMC sentence names the most
+//! recent pulse, and time since that pulse comes from the monotonic clock.
+//! If pulses stop (lost fix, unplugged antenna) the clock holds over for
+//! [`HOLDOVER_MS`] and then reports nothing.
+//!
+//! The second source is a battery-backed RTC chip (firmware feature `rtc`,
+//! chips in `rtc`). Its registers count whole seconds, so [`RtcClock`]
+//! anchors on the moment the seconds register rolls over and interpolates
+//! from the monotonic clock between reads. Every [`RESYNC_MS`] it finds a new
+//! rollover. Comparing rollovers at least [`DRIFT_WINDOW_MS`] apart gives
+//! the RTC's rate against the internal timer, which corrects the
+//! interpolation. The time is marked stale when the chip reports that its
+//! oscillator stopped (battery lost, never set), when a rollover lands more
+//! than [`MAX_JUMP_MS`] from where the clock predicted it, when the two
+//! clocks disagree by more than [`MAX_DRIFT_PPM`], or when the RTC has not
+//! been read for [`RTC_HOLDOVER_MS`].
+//!
+//! The resolution can be reduced with [`quantize`]: an exact second is more
+//! precise, and more identifying, than many uses need.
+
+pub use icesickle_payload::wallclock::{quantize, TimeSource, WallTime};
+
+/// How long the clock keeps counting from the last named pulse
+pub const HOLDOVER_MS: u64 = 10_000;
+
+/// A named pulse must precede its sentence by less than this
+const MAX_SENTENCE_DELAY_MS: u64 = 1_000;
+
+/// How often the RTC is re-anchored
+pub const RESYNC_MS: u64 = 60_000;
+
+/// Shortest span a drift estimate is taken over
+pub const DRIFT_WINDOW_MS: u64 = 3_600_000;
+
+/// Largest believable rate difference between the RTC and the internal timer
+///
+/// A DS3231 is within 2 ppm and the ESP32-S3 crystal within 10 ppm; beyond
+/// this one of them is broken or the RTC was changed while running.
+pub const MAX_DRIFT_PPM: i64 = 100;
+
+/// Largest believable gap between a predicted and an observed rollover
+pub const MAX_JUMP_MS: u64 = 2_000;
+
+/// An RTC not read for this long is stale
+pub const RTC_HOLDOVER_MS: u64 = 10 * RESYNC_MS;
+
+/// UTC time disciplined by PPS edges and named by NMEA sentences
+#[derive(Debug, Default)]
+pub struct PpsClock {
+    last_pulse_ms: Option<u64>,
+    /// A pulse and the UTC second it began
+    anchor: Option<(u64, u64)>,
+}
+
+impl PpsClock {
+    pub const fn new() -> Self {
+        Self {
+            last_pulse_ms: None,
+            anchor: None,
+        }
+    }
+
+    /// A PPS rising edge, at monotonic time `at_ms`
+    pub fn pulse(&mut self, at_ms: u64) {
+        self.last_pulse_ms = Some(at_ms);
+    }
+
+    /// A sentence naming `unix_s` arrived at `now_ms`
+    pub fn sentence(&mut self, unix_s: u64, now_ms: u64) {
+        if let Some(pulse) = self.last_pulse_ms {
+            if now_ms.saturating_sub(pulse) < MAX_SENTENCE_DELAY_MS {
+                self.anchor = Some((pulse, unix_s));
+            }
+        }
+    }
+
+    /// Current UTC seconds, if the anchor is fresh enough
+    pub fn now(&self, now_ms: u64) -> Option<u64> {
+        let (pulse, unix_s) = self.anchor?;
+        let since = now_ms.checked_sub(pulse)?;
+        (since < HOLDOVER_MS).then_some(unix_s + since / 1000)
+    }
+}
+
+/// UTC time kept by an RTC chip, anchored on its seconds rollovers
+#[derive(Debug, Default)]
+pub struct RtcClock {
+    /// Last second read while looking for a rollover
+    hunt: Option<u64>,
+    /// Monotonic ms and RTC seconds at the first rollover since the last jump
+    first: Option<(u64, u64)>,
+    /// Monotonic ms and RTC seconds at the latest rollover
+    last: Option<(u64, u64)>,
+    /// RTC rate against the internal timer
+    drift_ppm: i64,
+    /// Oscillator stop, jump or implausible drift seen this boot
+    doubted: bool,
+}
+
+impl RtcClock {
+    pub const fn new() -> Self {
+        Self {
+            hunt: None,
+            first: None,
+            last: None,
+            drift_ppm: 0,
+            doubted: false,
+        }
+    }
+
+    /// True while the firmware should read the RTC
+    pub fn wants_read(&self, now_ms: u64) -> bool {
+        match self.last {
+            Some((at_ms, _)) => now_ms.saturating_sub(at_ms) >= RESYNC_MS,
+            None => true,
+        }
+    }
+
+    /// The RTC read `unix_s` at `now_ms`; `valid` is false if the chip
+    /// reports that its oscillator stopped since it was set
+    pub fn read(&mut self, unix_s: u64, valid: bool, now_ms: u64) {
+        if !valid {
+            self.doubted = true;
+        }
+        match self.hunt.replace(unix_s) {
+            Some(previous) if previous != unix_s => {
+                self.hunt = None;
+                self.rollover(now_ms, unix_s);
+            }
+            _ => {}
+        }
+    }
+
+    fn rollover(&mut self, at_ms: u64, unix_s: u64) {
+        if let Some(predicted_ms) = self.predict_ms(at_ms) {
+            if predicted_ms.abs_diff(unix_s * 1000) > MAX_JUMP_MS {
+                // The old anchors say nothing about the new time
+                self.doubted = true;
+                self.first = None;
+                self.drift_ppm = 0;
+            }
+        }
+        let (first_ms, first_s) = *self.first.get_or_insert((at_ms, unix_s));
+        self.last = Some((at_ms, unix_s));
+
+        let span_ms = at_ms - first_ms;
+        if span_ms >= DRIFT_WINDOW_MS {
+            let rtc_ms = (unix_s - first_s) as i64 * 1000;
+            self.drift_ppm = (rtc_ms - span_ms as i64) * 1_000_000 / span_ms as i64;
+            if self.drift_ppm.abs() > MAX_DRIFT_PPM {
+                self.doubted = true;
+            }
+        }
+    }
+
+    /// RTC time in ms at monotonic `now_ms`, drift-corrected
+    fn predict_ms(&self, now_ms: u64) -> Option<u64> {
+        let (at_ms, unix_s) = self.last?;
+        let elapsed = now_ms.checked_sub(at_ms)? as i64;
+        let corrected = elapsed + elapsed * self.drift_ppm / 1_000_000;
+        (unix_s * 1000).checked_add_signed(corrected)
+    }
+
+    /// Current RTC time, once a rollover has been seen
+    pub fn now(&self, now_ms: u64) -> Option<WallTime> {
+        let (at_ms, _) = self.last?;
+        Some(WallTime {
+            unix_s: self.predict_ms(now_ms)? / 1000,
+            source: TimeSource::Rtc,
+            stale: self.doubted || now_ms.saturating_sub(at_ms) >= RTC_HOLDOVER_MS,
+        })
+    }
+}
+
+/// UTC seconds from an NMEA RMC sentence with a valid fix
+///
+/// Accepts any talker (`$GPRMC`, `$GNRMC`, ...). The checksum must match and
+/// the status must be `A`; anything else is `None`.
+pub fn parse_rmc(line: &str) -> Option<u64> {
+    let (body, checksum) = line.trim_end().strip_prefix('$')?.split_once('*')?;
+    let expected = u8::from_str_radix(checksum, 16).ok()?;
+    if body.bytes().fold(0, |acc, b| acc ^ b) != expected {
+        return None;
+    }
+
+    let mut fields = body.split(',');
+    let talker = fields.next()?;
+    if talker.len() != 5 || !talker.ends_with("RMC") {
+        return None;
+    }
+    let time = fields.next()?;
+    if fields.next()? != "A" {
+        return None;
+    }
+    // latitude, N/S, longitude, E/W, speed, course, then the date
+    let date = fields.nth(6)?;
+
+    let hour = two_digits(time, 0).filter(|&h| h < 24)?;
+    let minute = two_digits(time, 2).filter(|&m| m < 60)?;
+    let second = two_digits(time, 4).filter(|&s| s < 61)?;
+    let day = two_digits(date, 0).filter(|d| (1..=31).contains(d))?;
+    let month = two_digits(date, 2).filter(|m| (1..=12).contains(m))?;
+    let yy = two_digits(date, 4)?;
+    // Two-digit years: GPS time starts in 1980
+    let year = if yy < 80 { 2000 + yy } else { 1900 + yy };
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+fn two_digits(field: &str, at: usize) -> Option<u64> {
+    let digits = field.get(at..at + 2)?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Days from 1970-01-01 to a proleptic Gregorian date (year >= 1970)
+pub(crate) fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    // Years start in March, so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian `(year, month, day)` of a day count from 1970-01-01
+#[cfg(any(test, feature = "volume", feature = "receipt"))]
+pub(crate) fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rmc() {
+        let line = "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A\r\n";
+        assert_eq!(parse_rmc(line), Some(764_426_119));
+        let leap = "$GNRMC,235959.00,A,3751.65,S,14507.36,E,000.0,360.0,290224,,,A*57";
+        assert_eq!(parse_rmc(leap), Some(1_709_251_199));
+
+        // Bad checksum, no fix, other sentence types
+        assert_eq!(parse_rmc(&line.replace("*6A", "*6B")), None);
+        assert_eq!(
+            parse_rmc("$GPRMC,123519,V,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*7D"),
+            None
+        );
+        assert_eq!(parse_rmc("$GPGGA,123519,4807.038,N*00"), None);
+    }
+
+    #[test]
+    fn test_civil_round_trip() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        // 2024-02-29, the leap day, and the day after
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(19_783), (2024, 3, 1));
+        for days in (0..200_000).step_by(97) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn test_pps_clock_anchors_and_expires() {
+        let mut clock = PpsClock::new();
+        assert_eq!(clock.now(0), None);
+
+        // A sentence with no recent pulse names nothing
+        clock.sentence(1_000, 500);
+        assert_eq!(clock.now(500), None);
+
+        clock.pulse(2_000);
+        clock.sentence(1_000, 2_400);
+        assert_eq!(clock.now(2_400), Some(1_000));
+        assert_eq!(clock.now(3_999), Some(1_001));
+        assert_eq!(clock.now(2_000 + HOLDOVER_MS - 1), Some(1_009));
+        assert_eq!(clock.now(2_000 + HOLDOVER_MS), None);
+    }
+
+    /// Read an RTC running `ppm` fast from `start_s`, every 10 ms until `until_ms`
+    fn run_rtc(clock: &mut RtcClock, start_s: u64, ppm: i64, from_ms: u64, until_ms: u64) {
+        for now_ms in (from_ms..until_ms).step_by(10) {
+            if clock.wants_read(now_ms) {
+                let rtc_ms = now_ms as i64 + now_ms as i64 * ppm / 1_000_000;
+                clock.read(start_s + rtc_ms as u64 / 1000, true, now_ms);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rtc_anchors_on_rollover() {
+        let mut clock = RtcClock::new();
+        clock.read(1_000, true, 0);
+        assert_eq!(clock.now(0), None);
+        clock.read(1_000, true, 500);
+        clock.read(1_001, true, 1_010);
+        assert!(!clock.wants_read(1_010));
+
+        let now = clock.now(2_000).unwrap();
+        assert_eq!(
+            (now.unix_s, now.source, now.stale),
+            (1_001, TimeSource::Rtc, false)
+        );
+        assert_eq!(clock.now(2_010).unwrap().unix_s, 1_002);
+        assert!(clock.now(1_010 + RTC_HOLDOVER_MS).unwrap().stale);
+    }
+
+    #[test]
+    fn test_rtc_drift_is_measured_and_bounded() {
+        // 50 ppm fast: plausible, corrected, not stale
+        let mut clock = RtcClock::new();
+        run_rtc(&mut clock, 1_000, 50, 0, DRIFT_WINDOW_MS + 2 * RESYNC_MS);
+        assert!((45..=55).contains(&clock.drift_ppm));
+        assert!(!clock.now(DRIFT_WINDOW_MS + 2 * RESYNC_MS).unwrap().stale);
+
+        // 500 ppm fast: one of the clocks is broken
+        let mut clock = RtcClock::new();
+        run_rtc(&mut clock, 1_000, 500, 0, DRIFT_WINDOW_MS + 2 * RESYNC_MS);
+        assert!(clock.now(DRIFT_WINDOW_MS + 2 * RESYNC_MS).unwrap().stale);
+    }
+
+    #[test]
+    fn test_rtc_jump_and_stopped_oscillator_are_stale() {
+        let mut clock = RtcClock::new();
+        run_rtc(&mut clock, 1_000, 0, 0, 2 * RESYNC_MS);
+        assert!(!clock.now(2 * RESYNC_MS).unwrap().stale);
+
+        // Someone set the RTC an hour ahead while it was running
+        run_rtc(&mut clock, 4_600, 0, 2 * RESYNC_MS, 4 * RESYNC_MS);
+        let now = clock.now(4 * RESYNC_MS).unwrap();
+        assert_eq!(now.unix_s, 4_600 + 4 * RESYNC_MS / 1000);
+        assert!(now.stale);
+
+        let mut clock = RtcClock::new();
+        clock.read(1_000, false, 0);
+        clock.read(1_001, false, 1_000);
+        assert!(clock.now(1_000).unwrap().stale);
+    }
+}
@@ -0,0 +1,146 @@
+//! Cross-witnessing between two devices
+//!
+//! Two IceSickles wired UART-to-UART (firmware feature `witness`) can vouch
+//! for each other's attestations, giving a verifier two independent pieces
+//! of hardware behind one physical event:
+//!
+//! 1. When a device emits an original attestation, it sends the public
+//!    record to its peer as [`PeerMessage::Attestation`], framed exactly like
+//!    the command protocol.
+//! 2. The peer checks the signature and signs a `Witness` attestation whose
+//!    event carries the record's [`binding`].
+//! 3. If one event presses both devices (a shared switch, two operators),
+//!    each ends up witnessing the other's attestation.
+//!
+//! Nothing is shared or kept between the devices. Every witness is signed
+//! with a fresh ephemeral key like any other attestation.
+//!
+//! Only originals are forwarded or witnessed ([`is_witnessable`]), so two
+//! devices can never bounce witnesses back and forth. Witnesses also have
+//! their own cooldown: a chattering peer cannot make the device sign faster
+//! than a button could.
+//!
+//! The peer link is not authenticated. A witness proves that this device
+//! received that attestation at that time, not which device produced it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::attestation::AttestationEvent;
+use crate::merkle;
+use crate::protocol::AttestationRecord;
+
+/// Device → device messages on the peer link
+///
+/// Variant order is the wire code: append-only, like the command protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerMessage {
+    /// A freshly emitted attestation to witness
+    Attestation(AttestationRecord),
+    /// An attestation passed up a relay chain after `hops` links (see
+    /// `relay`)
+    Relayed { record: AttestationRecord, hops: u8 },
+}
+
+impl PeerMessage {
+    /// The attestation carried
+    pub fn record(&self) -> &AttestationRecord {
+        match self {
+            PeerMessage::Attestation(record) | PeerMessage::Relayed { record, .. } => record,
+        }
+    }
+}
+
+/// What a `Witness` event commits to: the peer's public key and signature
+///
+/// The same hash as a window digest leaf (`merkle::leaf_hash`), so a
+/// collector needs only one helper for both.
+pub fn binding(record: &AttestationRecord) -> [u8; 32] {
+    merkle::leaf_hash(&record.public_key, &record.signature)
+}
+
+/// True for events that are forwarded to and witnessed by a peer
+pub fn is_witnessable(event: &AttestationEvent) -> bool {
+    matches!(
+        event,
+        AttestationEvent::ButtonPress { .. }
+            | AttestationEvent::DataDigest { .. }
+            | AttestationEvent::Presence { .. }
+            | AttestationEvent::Tamper
+            | AttestationEvent::CreditPulse { .. }
+            | AttestationEvent::KeypadEntry { .. }
+            | AttestationEvent::Sensor { .. }
+            | AttestationEvent::Boot { .. }
+            | AttestationEvent::GpioSnapshot { .. }
+            | AttestationEvent::TouchPad { .. }
+            | AttestationEvent::ButtonHold { .. }
+            | AttestationEvent::ButtonDoublePress { .. }
+            | AttestationEvent::BatchRoot { .. }
+            | AttestationEvent::TamperDetected { .. }
+            | AttestationEvent::DualPress { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::TokenProof;
+    use crate::context::{Context, MAX_CONTEXT_LEN};
+    use crate::policy::Policy;
+    use crate::protocol::{self, MAX_FRAME_LEN};
+    use crate::scheme::Algorithm;
+    use crate::wallclock::{TimeSource, WallTime};
+
+    #[test]
+    fn test_witnesses_are_not_witnessed() {
+        assert!(is_witnessable(&AttestationEvent::ButtonPress { gpio: 0 }));
+        assert!(!is_witnessable(&AttestationEvent::Witness {
+            peer: [0; 32]
+        }));
+        assert!(!is_witnessable(&AttestationEvent::WindowDigest {
+            root: [0; 32],
+            count: 0
+        }));
+        assert!(!is_witnessable(&AttestationEvent::UsageCount {
+            count: 0,
+            epsilon_milli: 1_000,
+            window_s: 0
+        }));
+    }
+
+    #[test]
+    fn test_largest_relayed_message_fits_a_frame() {
+        let message = PeerMessage::Relayed {
+            record: AttestationRecord {
+                version: u8::MAX,
+                event: AttestationEvent::DataDigest {
+                    gpio: u8::MAX,
+                    sha256: [0xFF; 32],
+                    len: u64::MAX,
+                },
+                timestamp_ms: u64::MAX,
+                counter: u32::MAX,
+                public_key: [0xFF; 32],
+                signature: [0xFF; 64],
+                wall_clock: Some(WallTime {
+                    unix_s: u64::MAX,
+                    source: TimeSource::Rtc,
+                    stale: true,
+                }),
+                policy: Policy::from_bits(u8::MAX),
+                suppressed: u32::MAX,
+                challenge: Some([0xFF; 32]),
+                token: Some(TokenProof::from_bytes(&[0xFF; 32])),
+                boot_nonce: Some([0xFF; 16]),
+                algorithm: Algorithm::Ed25519,
+                context: Some(Context::from_slice(&[0xFF; MAX_CONTEXT_LEN]).unwrap()),
+                measurement: Some([0xFF; 32]),
+            },
+            hops: u8::MAX,
+        };
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let frame = protocol::encode_frame(0, &message, &mut out).unwrap();
+        let len = frame.len() - 1;
+        let mut body = out;
+        assert_eq!(protocol::decode_frame(&mut body[..len]).2, Ok(message));
+    }
+}
@@ -0,0 +1,66 @@
+//! A refused attempt leaves the counter and the suppressed presses
+//!
+//! Both are process-wide, and the firmware's crypto workspace is only
+//! shared outside this crate's unit tests, so this runs in a test binary of
+//! its own: no other test signs meanwhile. The `mock` feature gives each
+//! thread a workspace of its own, so there is nothing to hold; a workspace
+//! test run that enables it for another crate's tests skips this one, and
+//! `cargo test -p icesickle-core` alone runs it.
+
+#![cfg(not(feature = "mock"))]
+
+use std::cell::Cell;
+
+use icesickle_core::attestation::{self, Attestation, AttestationEvent};
+use icesickle_core::entropy::HardwareRng;
+use icesickle_core::hal::{EntropySource, Timer};
+use icesickle_core::scrub::CRYPTO_WORKSPACE;
+use icesickle_core::IceSickleError;
+
+/// Xorshift noise: passes the health tests, nothing more
+struct Noise(Cell<u32>);
+
+impl EntropySource for Noise {
+    fn fill(&self, dest: &mut [u8]) {
+        for b in dest {
+            let mut x = self.0.get();
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            self.0.set(x);
+            *b = x as u8;
+        }
+    }
+}
+
+struct Clock;
+
+impl Timer for Clock {
+    fn now_ms(&self) -> u64 {
+        1_000
+    }
+
+    fn delay_ms(&self, _ms: u32) {}
+}
+
+#[test]
+fn test_busy_attempt_keeps_counter_and_suppressed() {
+    let rng = HardwareRng::from_source(Noise(Cell::new(0x9E37_79B9))).unwrap();
+    let event = AttestationEvent::ButtonPress { gpio: 0 };
+    let first = Attestation::create(&rng, &Clock, event).unwrap();
+    attestation::record_suppressed();
+    attestation::record_suppressed();
+    let count = attestation::count();
+
+    // Every slot held: the attempt is refused before anything is taken
+    let refused = CRYPTO_WORKSPACE
+        .with(|_| CRYPTO_WORKSPACE.with(|_| Attestation::create(&rng, &Clock, event).err()))
+        .flatten()
+        .flatten();
+    assert_eq!(refused, Some(IceSickleError::Busy));
+    assert_eq!(attestation::count(), count);
+
+    let next = Attestation::create(&rng, &Clock, event).unwrap();
+    assert_eq!(next.counter(), first.counter().wrapping_add(1));
+    assert_eq!(next.suppressed(), 2);
+}
@@ -0,0 +1,68 @@
+//! Signing on a registered SHA-512 backend
+//!
+//! `sha512::set_backend` is process-wide and only its first call counts,
+//! so this runs in a test binary of its own: no other test's hashes can
+//! land on the backend, and none of them depends on which registers first.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use ed25519_dalek::{Signer, SigningKey};
+use icesickle_core::scheme::{Ed25519, SignatureScheme};
+use icesickle_core::sha512::{self, Backend, Sha512};
+use sha2::digest::Update;
+use sha2::Digest;
+
+/// SHA-512("abc"), FIPS 180-2 appendix C.1
+const ABC: [u8; 64] = [
+    0xdd, 0xaf, 0x35, 0xa1, 0x93, 0x61, 0x7a, 0xba, 0xcc, 0x41, 0x73, 0x49, 0xae, 0x20, 0x41, 0x31,
+    0x12, 0xe6, 0xfa, 0x4e, 0x89, 0xa9, 0x7e, 0xa2, 0x0a, 0x9e, 0xee, 0xe6, 0x4b, 0x55, 0xd3, 0x9a,
+    0x21, 0x92, 0x99, 0x2a, 0x27, 0x4f, 0xc1, 0xa8, 0x36, 0xba, 0x3c, 0x23, 0xa3, 0xfe, 0xeb, 0xbd,
+    0x45, 0x4d, 0x44, 0x23, 0x64, 0x3c, 0xe8, 0x0e, 0x2a, 0x9a, 0xc9, 0x4f, 0xa5, 0x4c, 0xa4, 0x9f,
+];
+
+/// Software underneath, counting the computations started
+struct Counted {
+    hash: Mutex<Option<sha2::Sha512>>,
+    started: AtomicUsize,
+}
+
+impl Backend for Counted {
+    fn start(&self) -> bool {
+        let mut hash = self.hash.lock().unwrap();
+        if hash.is_some() {
+            return false;
+        }
+        *hash = Some(sha2::Sha512::default());
+        self.started.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    fn update(&self, data: &[u8]) {
+        let mut hash = self.hash.lock().unwrap();
+        Update::update(hash.as_mut().unwrap(), data);
+    }
+
+    fn finish(&self) -> [u8; 64] {
+        self.hash.lock().unwrap().take().unwrap().finalize().into()
+    }
+}
+
+static COUNTED: Counted = Counted {
+    hash: Mutex::new(None),
+    started: AtomicUsize::new(0),
+};
+
+#[test]
+fn test_backend_signs_plain_ed25519() {
+    sha512::set_backend(&COUNTED);
+    assert!(sha512::is_accelerated());
+
+    let key = SigningKey::from_bytes(&[0x07; 32]);
+    let signature = Ed25519::sign(&key, b"payload");
+    assert!(COUNTED.started.load(Ordering::SeqCst) >= 2);
+    assert_eq!(signature, key.sign(b"payload").to_bytes());
+    let public_key = Ed25519::public_key(&key);
+    assert!(Ed25519::verify(&public_key, b"payload", &signature));
+    assert_eq!(Sha512::default().chain_update(b"abc").finalize()[..], ABC);
+}
@@ -0,0 +1,4 @@
+// Bindings for the esp_tinyusb component (features `usb-hid`, `usb-msc`)
+#include "tinyusb.h"
+#include "class/hid/hid_device.h"
+#include "class/msc/msc_device.h"
@@ -0,0 +1,118 @@
+//! ATECC608 supplementary entropy (feature `atecc`)
+//!
+//! An ATECC608A/B on the shared I2C bus reseeds the key RNG's
+//! `MixedEntropy` (with `drbg`, its `Drbg`) once at boot, before the first attestation, then every
+//! [`RESEED_MS`]. Keys stay unpredictable as long as either the ESP32-S3
+//! TRNG or the ATECC608 is sound. See `icesickle_core::atecc` for what the
+//! driver will and will not send the chip.
+//!
+//! At boot the first Random is awaited; after that [`Atecc::poll`] sends it
+//! and collects the result on a later pass, so the event loop never waits
+//! for the chip. A chip that does not answer at boot, or whose RNG is not
+//! usable (configuration zone unlocked), is dropped with a warning: keys
+//! then come from the TRNG alone. Later failures are logged once and
+//! retried at the next reseed, and the last good seed stays mixed in.
+
+use icesickle_core::atecc;
+use icesickle_core::entropy::Reseed;
+use icesickle_core::hal::Timer;
+use icesickle_core::IceSickleError;
+use log::{info, warn};
+
+use crate::hal::{EspI2c, EspTimer};
+
+/// Interval between reseeds
+const RESEED_MS: u64 = 60_000;
+
+/// Give up on a Random the chip has not answered by then
+const RANDOM_TIMEOUT_MS: u64 = 100;
+
+enum State {
+    /// No usable chip
+    Absent,
+    /// Waiting to reseed
+    Idle { next_ms: u64 },
+    /// Random sent at `since_ms`
+    Busy { since_ms: u64 },
+}
+
+/// The ATECC608 found at boot, if any
+pub struct Atecc {
+    state: State,
+    /// Last reseed failed; logged once until one succeeds
+    failing: bool,
+}
+
+impl Atecc {
+    /// Probe `bus` for the chip and reseed `pool` from it
+    pub fn new(bus: &mut EspI2c<'_>, pool: &impl Reseed, now_ms: u64) -> Self {
+        let seeded = atecc::wake(bus, &EspTimer)
+            .and_then(|()| atecc::start_random(bus))
+            .and_then(|()| {
+                EspTimer.delay_ms(atecc::RANDOM_EXEC_MS as u32);
+                atecc::read_random(bus)
+            })
+            .map(|random| pool.reseed(&random));
+        let _ = atecc::sleep(bus);
+        let state = match seeded {
+            Ok(()) => {
+                info!(
+                    "ATECC608 at 0x{:02x} mixed into key entropy",
+                    atecc::ADDRESS
+                );
+                State::Idle {
+                    next_ms: now_ms + RESEED_MS,
+                }
+            }
+            Err(e) => {
+                warn!("No usable ATECC608 ({}); keys use the TRNG alone", e);
+                State::Absent
+            }
+        };
+        Self {
+            state,
+            failing: false,
+        }
+    }
+
+    /// Start or finish a reseed when one is due (never blocks for long)
+    pub fn poll(&mut self, bus: &mut EspI2c<'_>, pool: &impl Reseed, now_ms: u64) {
+        match self.state {
+            State::Absent => {}
+            State::Idle { next_ms } if now_ms >= next_ms => {
+                match atecc::wake(bus, &EspTimer).and_then(|()| atecc::start_random(bus)) {
+                    Ok(()) => self.state = State::Busy { since_ms: now_ms },
+                    Err(e) => self.failed(bus, e, now_ms),
+                }
+            }
+            State::Idle { .. } => {}
+            State::Busy { since_ms } if now_ms - since_ms >= atecc::RANDOM_EXEC_MS => {
+                match atecc::read_random(bus) {
+                    Ok(random) => {
+                        pool.reseed(&random);
+                        let _ = atecc::sleep(bus);
+                        self.failing = false;
+                        self.state = State::Idle {
+                            next_ms: now_ms + RESEED_MS,
+                        };
+                    }
+                    // Still executing: not acknowledged yet
+                    Err(_) if now_ms - since_ms < RANDOM_TIMEOUT_MS => {}
+                    Err(e) => self.failed(bus, e, now_ms),
+                }
+            }
+            State::Busy { .. } => {}
+        }
+    }
+
+    fn failed(&mut self, bus: &mut EspI2c<'_>, e: IceSickleError, now_ms: u64) {
+        if !self.failing {
+            warn!("ATECC608 reseed failed: {}", e);
+        }
+        self.failing = true;
+        let _ = atecc::sleep(bus);
+        self.state = State::Idle {
+            next_ms: now_ms + RESEED_MS,
+        };
+    }
+}
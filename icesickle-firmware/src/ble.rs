@@ -0,0 +1,337 @@
+//! BLE GATT attestation service (feature `ble`)
+//!
+//! Lets phone verifiers receive attestations without a cable. The device
+//! advertises [`SERVICE_UUID`]; a connected central that enables
+//! notifications on [`ATTESTATION_UUID`] is sent every attestation as
+//! `icesickle_core::transport` frames sized to the connection's MTU.
+//! Nothing else is exposed: the characteristic cannot be read or written,
+//! and there is no pairing or bonding.
+//!
+//! One central at a time, and nothing is advertised while it is connected.
+//! Attestations made while no central is subscribed are not kept (the USB
+//! HID push interface and `GET_HISTORY` have them).
+//!
+//! # Unlinkability
+//!
+//! The device advertises from a static random address drawn from the TRNG
+//! at boot and again after every disconnection, so no address follows the
+//! unit from one session to the next. Advertising data is the flags and the
+//! service UUID, the same on every unit: no name, TX power or manufacturer
+//! data.
+//!
+//! Bluetooth is compiled out of the default build; the feature needs the
+//! controller and the Bluedroid host from `sdkconfig.ble` (see the README).
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use enumset::{enum_set, EnumSet};
+use esp_idf_hal::modem::Modem;
+use esp_idf_svc::bt::ble::gap::{AdvConfiguration, BleGapEvent, EspBleGap};
+use esp_idf_svc::bt::ble::gatt::server::{ConnectionId, EspGatts, GattsEvent};
+use esp_idf_svc::bt::ble::gatt::{
+    AutoResponse, GattCharacteristic, GattDescriptor, GattId, GattInterface, GattServiceId,
+    GattStatus, Handle, Permission, Property,
+};
+use esp_idf_svc::bt::{self, BtDriver, BtStatus, BtUuid};
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
+use esp_idf_sys::{self as sys, esp, EspError};
+use log::{info, warn};
+
+use icesickle_core::attestation::Attestation;
+use icesickle_core::telemetry::{self, Counter};
+use icesickle_core::transport::{self, Message};
+
+/// Primary service
+pub const SERVICE_UUID: u128 = 0x3f0c5a1e_7b2d_4e8a_9c61_0b5f2e7d4a90;
+
+/// Notify-only attestation characteristic
+pub const ATTESTATION_UUID: u128 = 0x3f0c5a1e_7b2d_4e8a_9c61_0b5f2e7d4a91;
+
+/// Client Characteristic Configuration descriptor
+const CCCD_UUID: u16 = 0x2902;
+
+/// CCCD value enabling notifications
+const CCCD_NOTIFY: u16 = 0x0001;
+
+/// Our only GATT application
+const APP_ID: u16 = 0;
+
+/// ATT header bytes in a notification
+const ATT_HEADER_LEN: u16 = 3;
+
+/// MTU until the central negotiates another
+const DEFAULT_MTU: u16 = 23;
+
+/// Advertising interval bounds (0.625 ms units): 100-150 ms
+const ADV_INTERVAL_MIN: u16 = 160;
+const ADV_INTERVAL_MAX: u16 = 240;
+
+type Driver = BtDriver<'static, bt::Ble>;
+
+/// Message waiting for the main loop to notify it
+static PENDING: Mutex<Option<Message>> = Mutex::new(None);
+
+/// Queue `attestation` for the subscribed central, if any
+pub fn push(attestation: &Attestation) {
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending = Some(transport::message(attestation));
+    }
+}
+
+/// The connected central
+struct Central {
+    conn_id: ConnectionId,
+    mtu: u16,
+    subscribed: bool,
+}
+
+/// Handles learned while the service is registered
+#[derive(Default)]
+struct State {
+    gatt_if: Option<GattInterface>,
+    attestation: Option<Handle>,
+    cccd: Option<Handle>,
+    central: Option<Central>,
+}
+
+struct Server {
+    gap: EspBleGap<'static, bt::Ble, Arc<Driver>>,
+    gatts: EspGatts<'static, bt::Ble, Arc<Driver>>,
+    state: Mutex<State>,
+}
+
+/// Handle to the running service
+pub struct Ble {
+    server: Arc<Server>,
+}
+
+impl Ble {
+    /// Start the controller and host, register the service and advertise
+    pub fn new(modem: Modem, nvs: EspDefaultNvsPartition) -> Result<Self, EspError> {
+        let driver = Arc::new(Driver::new(modem, Some(nvs))?);
+        let server = Arc::new(Server {
+            gap: EspBleGap::new(driver.clone())?,
+            gatts: EspGatts::new(driver)?,
+            state: Mutex::new(State::default()),
+        });
+
+        // Callbacks run on the Bluedroid task
+        let gap_server = server.clone();
+        server.gap.subscribe(move |event| {
+            if let Err(e) = gap_server.on_gap_event(event) {
+                warn!("BLE GAP: {}", e);
+            }
+        })?;
+        let gatts_server = server.clone();
+        server.gatts.subscribe(move |(gatt_if, event)| {
+            if let Err(e) = gatts_server.on_gatts_event(gatt_if, event) {
+                warn!("BLE GATT: {}", e);
+            }
+        })?;
+        server.gatts.register_app(APP_ID)?;
+
+        Ok(Self { server })
+    }
+
+    /// Notify the pending attestation to the subscribed central
+    pub fn flush(&self) {
+        let Some(message) = PENDING.lock().ok().and_then(|mut pending| pending.take()) else {
+            return;
+        };
+        let state = self.server.state();
+        let (Some(gatt_if), Some(handle), Some(central)) =
+            (state.gatt_if, state.attestation, state.central.as_ref())
+        else {
+            return;
+        };
+        if !central.subscribed {
+            return;
+        }
+
+        let frame_len = central.mtu.saturating_sub(ATT_HEADER_LEN) as usize;
+        for frame in transport::frames(&message, frame_len) {
+            if let Err(e) = self
+                .server
+                .gatts
+                .notify(gatt_if, central.conn_id, handle, &frame)
+            {
+                warn!("BLE notification failed ({}) - attestation dropped", e);
+                telemetry::record(Counter::SinkError);
+                return;
+            }
+        }
+    }
+}
+
+impl Server {
+    fn state(&self) -> MutexGuard<'_, State> {
+        // Handles stay valid even if a callback panicked holding the lock
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn on_gap_event(&self, event: BleGapEvent) -> Result<(), EspError> {
+        if let BleGapEvent::AdvertisingConfigured(status) = event {
+            check_bt(status)?;
+            self.advertise()?;
+        }
+        Ok(())
+    }
+
+    fn on_gatts_event(&self, gatt_if: GattInterface, event: GattsEvent) -> Result<(), EspError> {
+        match event {
+            GattsEvent::ServiceRegistered { status, app_id } if app_id == APP_ID => {
+                check_gatt(status)?;
+                self.state().gatt_if = Some(gatt_if);
+                self.gap.set_adv_conf(&AdvConfiguration {
+                    include_name: false,
+                    include_txpower: false,
+                    // General discoverable, no BR/EDR
+                    flag: 0x06,
+                    service_uuid: Some(BtUuid::uuid128(SERVICE_UUID)),
+                    ..Default::default()
+                })?;
+                self.gatts.create_service(
+                    gatt_if,
+                    &GattServiceId {
+                        id: GattId {
+                            uuid: BtUuid::uuid128(SERVICE_UUID),
+                            inst_id: 0,
+                        },
+                        is_primary: true,
+                    },
+                    // Service, characteristic declaration and value, CCCD
+                    4,
+                )?;
+            }
+            GattsEvent::ServiceCreated {
+                status,
+                service_handle,
+                ..
+            } => {
+                check_gatt(status)?;
+                self.gatts.start_service(service_handle)?;
+                self.gatts.add_characteristic(
+                    service_handle,
+                    &GattCharacteristic {
+                        uuid: BtUuid::uuid128(ATTESTATION_UUID),
+                        permissions: EnumSet::empty(),
+                        properties: enum_set!(Property::Notify),
+                        max_len: transport::REPORT_LEN,
+                        auto_rsp: AutoResponse::ByApp,
+                    },
+                    &[],
+                )?;
+            }
+            GattsEvent::CharacteristicAdded {
+                status,
+                attr_handle,
+                service_handle,
+                ..
+            } => {
+                check_gatt(status)?;
+                self.state().attestation = Some(attr_handle);
+                self.gatts.add_descriptor(
+                    service_handle,
+                    &GattDescriptor {
+                        uuid: BtUuid::uuid16(CCCD_UUID),
+                        permissions: enum_set!(Permission::Read | Permission::Write),
+                    },
+                )?;
+            }
+            GattsEvent::DescriptorAdded {
+                status,
+                attr_handle,
+                ..
+            } => {
+                check_gatt(status)?;
+                self.state().cccd = Some(attr_handle);
+                info!("BLE attestation service registered");
+            }
+            GattsEvent::PeerConnected { conn_id, .. } => {
+                self.state().central = Some(Central {
+                    conn_id,
+                    mtu: DEFAULT_MTU,
+                    subscribed: false,
+                });
+                info!("BLE central connected");
+            }
+            GattsEvent::Mtu { conn_id, mtu } => {
+                if let Some(central) = self.state().central.as_mut() {
+                    if central.conn_id == conn_id {
+                        central.mtu = mtu;
+                    }
+                }
+            }
+            GattsEvent::Write {
+                conn_id,
+                trans_id,
+                handle,
+                need_rsp,
+                value,
+                ..
+            } => {
+                // Only the CCCD is writable
+                let mut state = self.state();
+                if state.cccd == Some(handle) {
+                    let enabled = value.len() == 2
+                        && u16::from_le_bytes([value[0], value[1]]) & CCCD_NOTIFY != 0;
+                    if let Some(central) = state.central.as_mut() {
+                        if central.conn_id == conn_id {
+                            central.subscribed = enabled;
+                        }
+                    }
+                }
+                drop(state);
+                if need_rsp {
+                    self.gatts
+                        .send_response(gatt_if, conn_id, trans_id, GattStatus::Ok, None)?;
+                }
+            }
+            GattsEvent::PeerDisconnected { .. } => {
+                self.state().central = None;
+                info!("BLE central disconnected");
+                // A new session starts from a new address
+                self.advertise()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Advertise, connectable, from a fresh static random address
+    fn advertise(&self) -> Result<(), EspError> {
+        let mut address = [0u8; 6];
+        // Not key material, so taken from the TRNG directly
+        unsafe {
+            sys::esp_fill_random(address.as_mut_ptr().cast(), address.len());
+        }
+        // Static random: the two top bits of the most significant byte set
+        address[0] |= 0xC0;
+        esp!(unsafe { sys::esp_ble_gap_set_rand_addr(address.as_mut_ptr()) })?;
+
+        let mut params = sys::esp_ble_adv_params_t {
+            adv_int_min: ADV_INTERVAL_MIN,
+            adv_int_max: ADV_INTERVAL_MAX,
+            adv_type: sys::esp_ble_adv_type_t_ADV_TYPE_IND,
+            own_addr_type: sys::esp_ble_addr_type_t_BLE_ADDR_TYPE_RANDOM,
+            channel_map: sys::esp_ble_adv_channel_t_ADV_CHNL_ALL,
+            adv_filter_policy: sys::esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_ANY_CON_ANY,
+            ..Default::default()
+        };
+        esp!(unsafe { sys::esp_ble_gap_start_advertising(&mut params) })
+    }
+}
+
+fn check_bt(status: BtStatus) -> Result<(), EspError> {
+    match status {
+        BtStatus::Success => Ok(()),
+        _ => Err(EspError::from_infallible::<{ sys::ESP_FAIL }>()),
+    }
+}
+
+fn check_gatt(status: GattStatus) -> Result<(), EspError> {
+    match status {
+        GattStatus::Ok => Ok(()),
+        _ => Err(EspError::from_infallible::<{ sys::ESP_FAIL }>()),
+    }
+}
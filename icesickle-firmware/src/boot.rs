@@ -0,0 +1,42 @@
+//! Boot attestation inputs (feature `boot-attestation`)
+//!
+//! The reset reason comes from `esp_reset_reason()`, and the firmware hash
+//! from the application descriptor: the SHA-256 of the ELF file, which the
+//! ESP-IDF startup log prints as `ELF file SHA256`.
+
+use esp_idf_sys::{
+    esp_app_get_description, esp_reset_reason, esp_reset_reason_t_ESP_RST_BROWNOUT,
+    esp_reset_reason_t_ESP_RST_DEEPSLEEP, esp_reset_reason_t_ESP_RST_EXT,
+    esp_reset_reason_t_ESP_RST_INT_WDT, esp_reset_reason_t_ESP_RST_PANIC,
+    esp_reset_reason_t_ESP_RST_POWERON, esp_reset_reason_t_ESP_RST_SW,
+    esp_reset_reason_t_ESP_RST_TASK_WDT, esp_reset_reason_t_ESP_RST_WDT,
+};
+use icesickle_core::boot::{BootReport, ResetReason};
+
+/// The boot event for this start-up
+pub fn report() -> BootReport {
+    BootReport::new(reset_reason(), fw_hash())
+}
+
+// bindgen keeps the C enum constants' names
+#[allow(non_upper_case_globals)]
+fn reset_reason() -> ResetReason {
+    // SAFETY: reads a value latched by the startup code
+    match unsafe { esp_reset_reason() } {
+        esp_reset_reason_t_ESP_RST_POWERON => ResetReason::PowerOn,
+        esp_reset_reason_t_ESP_RST_EXT => ResetReason::External,
+        esp_reset_reason_t_ESP_RST_SW => ResetReason::Software,
+        esp_reset_reason_t_ESP_RST_PANIC => ResetReason::Panic,
+        esp_reset_reason_t_ESP_RST_INT_WDT
+        | esp_reset_reason_t_ESP_RST_TASK_WDT
+        | esp_reset_reason_t_ESP_RST_WDT => ResetReason::Watchdog,
+        esp_reset_reason_t_ESP_RST_DEEPSLEEP => ResetReason::DeepSleep,
+        esp_reset_reason_t_ESP_RST_BROWNOUT => ResetReason::Brownout,
+        _ => ResetReason::Unknown,
+    }
+}
+
+fn fw_hash() -> [u8; 32] {
+    // SAFETY: the descriptor is a static in flash, valid for the whole run
+    unsafe { (*esp_app_get_description()).app_elf_sha256 }
+}
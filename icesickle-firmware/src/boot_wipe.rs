@@ -0,0 +1,40 @@
+//! RAM clearing at boot
+//!
+//! SRAM keeps its contents across a warm reset (panic, watchdog, software
+//! reset, deep-sleep wake), so a fresh boot can start on top of the previous
+//! run's secrets. The panic path scrubs what it can (`fatal.rs`); this closes
+//! the gap from the other side, before anything secret exists:
+//!
+//! - `.bss` is zeroed by ESP-IDF startup before `main`. The crypto
+//!   workspace lives in RTC fast memory instead, which a deep-sleep wake
+//!   does not reload, so it is scrubbed here explicitly.
+//! - `.noinit` is skipped by ESP-IDF on purpose; it is zeroed here.
+//!   IceSickle keeps nothing there that should outlive a reset.
+//! - The heap, which also holds every task stack from the previous run, is
+//!   filled with a poison pattern when it is initialised and again on every
+//!   free (`CONFIG_HEAP_POISONING_COMPREHENSIVE` in `sdkconfig.defaults`).
+//!
+//! A deep-sleep wake boots through the same path and is covered the same
+//! way.
+
+use icesickle_core::scrub;
+
+extern "C" {
+    /// `.noinit` bounds from the ESP-IDF linker script
+    static mut _noinit_start: u8;
+    static mut _noinit_end: u8;
+}
+
+/// Clear RAM the previous run may have left secrets in; first thing in `main`
+pub fn clear() -> usize {
+    scrub::CRYPTO_WORKSPACE.scrub();
+
+    // SAFETY: the linker script places `.noinit` between these symbols, and
+    // nothing has used it yet this boot
+    unsafe {
+        let start = core::ptr::addr_of_mut!(_noinit_start);
+        let end = core::ptr::addr_of_mut!(_noinit_end);
+        scrub::zero_range(start, end);
+        end as usize - start as usize
+    }
+}
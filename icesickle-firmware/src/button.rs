@@ -0,0 +1,144 @@
+//! Button input on the ESP32-S3
+//!
+//! The debounce state machine is `icesickle_core::button`, generic over the
+//! core HAL traits; this module builds it on ESP-IDF input pins. The
+//! ESP32-S3 devkit typically has a BOOT button on GPIO0 (active low).
+//! [`buttons`] watches every pin of a [`ButtonConfig`], each press reported
+//! with the GPIO it came from.
+//!
+//! With feature `gestures`, [`Gestures`] turns each button's presses into
+//! short presses, long presses and double presses (see
+//! `icesickle_core::gesture`).
+//!
+//! With feature `light-sleep` the buttons are also GPIO wake sources: the
+//! chip sleeps between events, a press wakes it, and the loop then polls
+//! and debounces the press as usual (see `sleep`).
+
+use esp_idf_hal::gpio::{AnyIOPin, PinDriver, Pull};
+#[cfg(feature = "light-sleep")]
+use esp_idf_sys::{self as sys, esp};
+pub use icesickle_core::button::{Button, Buttons, DEBOUNCE_MS};
+#[cfg(feature = "gestures")]
+use icesickle_core::gesture::{Gesture, Recognizer};
+use icesickle_core::Result;
+
+use crate::hal::{esp_err, EspPin, EspTimer};
+
+/// The buttons of a [`ButtonConfig`], on ESP-IDF pins and timer
+pub type EspButtons = Buttons<EspPin<'static, AnyIOPin>, EspTimer>;
+
+/// Compile-time assignment of trigger buttons
+pub struct ButtonConfig {
+    /// GPIOs with an active-low button each, not handed to any other
+    /// driver. When several are pressed at once, the first listed wins
+    pub pins: &'static [i32],
+}
+
+/// Set up each pin of `config` with the internal pull-up (assuming
+/// active-low buttons)
+pub fn buttons(config: &ButtonConfig) -> Result<EspButtons> {
+    let buttons = config
+        .pins
+        .iter()
+        .map(|&gpio| {
+            // Pins in the config are not handed to any other driver
+            let mut pin = PinDriver::input(unsafe { AnyIOPin::new(gpio) }).map_err(esp_err)?;
+            pin.set_pull(Pull::Up).map_err(esp_err)?;
+            Ok((gpio as u8, Button::with_timer(EspPin(pin), EspTimer)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Buttons::with_buttons(buttons))
+}
+
+/// Make a press on any button wake the chip from light sleep
+#[cfg(feature = "light-sleep")]
+pub fn enable_wakeup(buttons: &EspButtons) -> Result<()> {
+    for gpio in buttons.gpios() {
+        let low = sys::gpio_int_type_t_GPIO_INTR_LOW_LEVEL;
+        // SAFETY: plain ESP-IDF call on a pin these buttons own
+        esp!(unsafe { sys::gpio_wakeup_enable(i32::from(gpio), low) }).map_err(esp_err)?;
+    }
+    // SAFETY: enables the wake source configured above
+    esp!(unsafe { sys::esp_sleep_enable_gpio_wakeup() }).map_err(esp_err)
+}
+
+/// Gesture recognition for every button (feature `gestures`)
+#[cfg(feature = "gestures")]
+pub struct Gestures {
+    recognizers: Vec<(u8, Recognizer)>,
+}
+
+#[cfg(feature = "gestures")]
+impl Gestures {
+    pub fn new(config: &ButtonConfig) -> Self {
+        Self {
+            recognizers: config
+                .pins
+                .iter()
+                .map(|&gpio| (gpio as u8, Recognizer::new()))
+                .collect(),
+        }
+    }
+
+    /// Feed the latest edge from [`Buttons::poll_edge`], on every poll;
+    /// a finished gesture and its GPIO
+    pub fn update(&mut self, edge: Option<(u8, bool)>, now_ms: u64) -> Option<(u8, Gesture)> {
+        if let Some((gpio, down)) = edge {
+            let recognizer = self.recognizers.iter_mut().find(|(pin, _)| *pin == gpio);
+            if let Some((_, recognizer)) = recognizer {
+                let gesture = if down {
+                    recognizer.press(now_ms)
+                } else {
+                    recognizer.release(now_ms)
+                };
+                if let Some(gesture) = gesture {
+                    return Some((gpio, gesture));
+                }
+            }
+        }
+        self.recognizers
+            .iter_mut()
+            .find_map(|(gpio, recognizer)| recognizer.tick(now_ms).map(|g| (*gpio, g)))
+    }
+}
+
+// Run on the device by `cargo test` (see `target_test`); the debounce
+// tests themselves run on the host with `icesickle_core::button`
+#[cfg(test)]
+pub mod tests {
+    #[cfg(feature = "gestures")]
+    pub fn test_gestures_follow_edges() {
+        use super::*;
+        use icesickle_core::gesture::{DOUBLE_MS, HOLD_MS};
+        use icesickle_core::hal::mock::{MockPin, MockTimer};
+        use icesickle_core::hal::Timer;
+
+        let pin = MockPin::default();
+        let timer = MockTimer::at(1_000);
+        let mut buttons = Buttons::with_buttons(vec![(38, Button::with_timer(&pin, &timer))]);
+        let mut gestures = Gestures::new(&ButtonConfig { pins: &[38] });
+        let mut step = |low: bool, ms: u64| {
+            pin.set_low(low);
+            timer.advance(ms);
+            let edge = buttons.poll_edge().unwrap();
+            gestures.update(edge, timer.now_ms())
+        };
+
+        // Long press, reported on release
+        assert_eq!(step(true, 0), None);
+        let hold = Gesture::Hold { duration_ms: 1_500 };
+        assert_eq!(step(false, HOLD_MS), Some((38, hold)));
+
+        // Double press, reported as the second press lands
+        assert_eq!(step(true, DEBOUNCE_MS as u64), None);
+        assert_eq!(step(false, DEBOUNCE_MS as u64), None);
+        let double = Some((38, Gesture::DoublePress));
+        assert_eq!(step(true, DEBOUNCE_MS as u64), double);
+        assert_eq!(step(false, DEBOUNCE_MS as u64), None);
+
+        // Short press, reported once the double-press window is over
+        assert_eq!(step(true, DEBOUNCE_MS as u64), None);
+        assert_eq!(step(false, DEBOUNCE_MS as u64), None);
+        assert_eq!(step(false, DOUBLE_MS), Some((38, Gesture::Press)));
+    }
+}
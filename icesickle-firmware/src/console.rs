@@ -0,0 +1,63 @@
+//! Attestation output on the console UART
+//!
+//! Attestations used to be printed to stdout, whose UART0 console driver
+//! busy-waits on the TX FIFO: a verbose attestation with companion formats
+//! held the main loop for tens of milliseconds at 115200 baud, and presses
+//! in that time went undetected. Output now waits here instead, and
+//! `SerialPort` moves it into the same UART's TX FIFO as it empties, so
+//! emitting an attestation never blocks.
+//!
+//! Protocol frames go first: console text is only drained while no frame
+//! is queued, so a frame is never split by text. Each output is queued
+//! whole or not at all. Output that does not fit (a host that stopped
+//! reading) is logged and counted as a sink error, and the attestation
+//! still reaches the history and every other output.
+//!
+//! Log lines still go through the blocking console driver, which is why
+//! the per-field attestation log is at debug level. The TX FIFO is refilled
+//! from the event loop rather than by DMA: ESP-IDF 5.2 has no UART DMA
+//! driver (see docs/ROADMAP.md).
+
+use std::sync::Mutex;
+
+use icesickle_core::telemetry::{self, Counter};
+use log::warn;
+
+use crate::outbox::{Class, Outbox};
+
+/// Queued console output; holds a verbose attestation with every companion
+/// format
+const CONSOLE_LEN: usize = 4096;
+
+/// Output waiting for the console UART
+static CONSOLE: Mutex<Outbox<CONSOLE_LEN>> = Mutex::new(Outbox::new(CONSOLE_LEN));
+
+/// Queue `text` for the console
+pub fn write(text: &[u8]) {
+    write_parts(&[text]);
+}
+
+/// Queue one output given in parts, so it needs no buffer of its own
+pub fn write_parts(parts: &[&[u8]]) {
+    let Ok(mut console) = CONSOLE.lock() else {
+        return;
+    };
+    // Console output is all reliable; the watermark is never consulted
+    if let Err(e) = console.push_parts(parts, Class::Reliable) {
+        warn!("Attestation output dropped: {}", e);
+        telemetry::record(Counter::SinkError);
+    }
+}
+
+/// True when nothing is queued
+#[cfg(feature = "light-sleep")]
+pub fn is_empty() -> bool {
+    CONSOLE.lock().map_or(true, |console| console.is_empty())
+}
+
+/// Hand queued output to `write`, which returns how many bytes it accepted
+pub fn drain(write: impl FnMut(&[u8]) -> usize) {
+    if let Ok(mut console) = CONSOLE.lock() {
+        console.drain(write);
+    }
+}
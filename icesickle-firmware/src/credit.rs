@@ -0,0 +1,87 @@
+//! Coin/credit pulse input (feature `credit`)
+//!
+//! Coin acceptors pull their open-collector pulse output to ground once per
+//! credit unit. Edges on `CREDIT_PIN` are counted in an interrupt that stays
+//! armed (a raw ESP-IDF handler, not the HAL's one-shot `subscribe`), so
+//! pulses arriving while the loop is busy signing or waiting for a button
+//! release are still counted. Edges closer than `MIN_EDGE_GAP_US` to the
+//! previous one are contact bounce and ignored.
+//! `icesickle_core::credit::BurstCounter` turns the counts into bursts.
+
+use core::ffi::c_void;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use esp_idf_hal::gpio::{AnyIOPin, Input, InterruptType, PinDriver, Pull};
+use icesickle_core::credit::BurstCounter;
+use icesickle_core::Result;
+
+use crate::hal::esp_err;
+
+/// GPIO for the acceptor's pulse output (active low)
+pub const CREDIT_PIN: i32 = 6;
+
+/// Edges closer together than this are bounce
+const MIN_EDGE_GAP_US: u64 = 15_000;
+
+/// Edges counted since the last poll
+static EDGES: AtomicU32 = AtomicU32::new(0);
+
+/// Time of the last counted edge, µs since boot
+static LAST_EDGE_US: AtomicU64 = AtomicU64::new(0);
+
+/// Pulse input and its burst state
+pub struct Credit<'d> {
+    _pin: PinDriver<'d, AnyIOPin, Input>,
+    bursts: BurstCounter,
+}
+
+impl<'d> Credit<'d> {
+    pub fn new(mut pin: PinDriver<'d, AnyIOPin, Input>) -> Result<Self> {
+        pin.set_pull(Pull::Up).map_err(esp_err)?;
+        pin.set_interrupt_type(InterruptType::NegEdge)
+            .map_err(esp_err)?;
+        // SAFETY: plain ESP-IDF GPIO ISR calls on a pin this driver owns;
+        // the service may already be installed by the HAL, which is fine
+        unsafe {
+            let installed = esp_idf_sys::gpio_install_isr_service(0);
+            if installed != esp_idf_sys::ESP_ERR_INVALID_STATE as i32 {
+                esp_idf_sys::esp!(installed).map_err(esp_err)?;
+            }
+            esp_idf_sys::esp!(esp_idf_sys::gpio_isr_handler_add(
+                CREDIT_PIN,
+                Some(on_edge),
+                core::ptr::null_mut(),
+            ))
+            .map_err(esp_err)?;
+            esp_idf_sys::esp!(esp_idf_sys::gpio_intr_enable(CREDIT_PIN)).map_err(esp_err)?;
+        }
+
+        Ok(Self {
+            _pin: pin,
+            bursts: BurstCounter::new(),
+        })
+    }
+
+    /// Collect new edges; true once a finished burst is waiting
+    pub fn poll(&mut self, now_ms: u64) -> bool {
+        let edges = EDGES.swap(0, Ordering::Relaxed);
+        self.bursts
+            .add(edges, LAST_EDGE_US.load(Ordering::Relaxed) / 1000);
+        self.bursts.is_ready(now_ms)
+    }
+
+    /// Take the finished burst's pulse count
+    pub fn take(&mut self) -> u16 {
+        self.bursts.take()
+    }
+}
+
+/// GPIO ISR: count the edge unless it is bounce
+unsafe extern "C" fn on_edge(_arg: *mut c_void) {
+    let now = esp_idf_sys::esp_timer_get_time() as u64;
+    let last = LAST_EDGE_US.load(Ordering::Relaxed);
+    if now.saturating_sub(last) >= MIN_EDGE_GAP_US {
+        LAST_EDGE_US.store(now, Ordering::Relaxed);
+        EDGES.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,60 @@
+//! Debug interface lockdown
+//!
+//! A debugger attached during signing can read the key straight out of RAM,
+//! so release builds cut the JTAG paths at boot. The ESP32-S3 has two:
+//!
+//! - **Pad JTAG** (GPIO39-42). It is only routed when eFuses select it, and
+//!   only an eFuse (`DIS_PAD_JTAG`) disables it for good. Firmware just
+//!   reports the fuse.
+//! - **USB-Serial-JTAG**, the default JTAG source on an unfused chip. If its
+//!   fuse (`DIS_USB_JTAG`) is not burnt, release builds disable the
+//!   controller's USB pads. That cuts the bridge until the next reset; the
+//!   ROM bootloader turns the pads back on, so flashing over native USB
+//!   still works from download mode.
+//!
+//! Debug builds leave everything on. Burning the eFuses is a provisioning
+//! decision and is never done by the firmware.
+//!
+//! The console and command protocol run on UART0, which is unaffected.
+
+use icesickle_core::protocol::DebugInterfaces;
+use log::{info, warn};
+
+/// `USB_SERIAL_JTAG_CONF0_REG` (ESP32-S3 TRM, USB Serial/JTAG Controller)
+const USB_SERIAL_JTAG_CONF0_REG: usize = 0x6003_8018;
+
+/// `USB_SERIAL_JTAG_USB_PAD_ENABLE` in `CONF0`
+const USB_PAD_ENABLE: u32 = 1 << 14;
+
+/// Apply the lockdown for this build and report the result
+pub fn lock() -> DebugInterfaces {
+    let pad_jtag_fused =
+        efuse_bit(unsafe { core::ptr::addr_of!(esp_idf_sys::ESP_EFUSE_DIS_PAD_JTAG) });
+    let usb_jtag_fused =
+        efuse_bit(unsafe { core::ptr::addr_of!(esp_idf_sys::ESP_EFUSE_DIS_USB_JTAG) });
+
+    let usb_jtag_cut = !cfg!(debug_assertions) && !usb_jtag_fused;
+    if usb_jtag_cut {
+        // SAFETY: documented peripheral register; clearing the pad enable
+        // only detaches the (unused) USB-Serial-JTAG controller from the pads
+        unsafe {
+            let conf0 = USB_SERIAL_JTAG_CONF0_REG as *mut u32;
+            conf0.write_volatile(conf0.read_volatile() & !USB_PAD_ENABLE);
+        }
+    }
+
+    let state = DebugInterfaces {
+        pad_jtag_fused,
+        usb_jtag_fused,
+        usb_jtag_cut,
+    };
+    if cfg!(debug_assertions) {
+        warn!("Debug build - JTAG left enabled");
+    }
+    info!("Debug interfaces: {:?}", state);
+    state
+}
+
+fn efuse_bit<T>(field: *const T) -> bool {
+    unsafe { esp_idf_sys::esp_efuse_read_field_bit(field.cast_mut().cast()) }
+}
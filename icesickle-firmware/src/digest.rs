@@ -0,0 +1,106 @@
+//! Streaming hash-then-sign
+//!
+//! Lets a host attest data far larger than device RAM: it streams the data
+//! in chunks over the command protocol, the device hashes incrementally and
+//! shows a short fingerprint of the result, and the next button press signs
+//! an [`AttestationEvent::DataDigest`] binding that digest. Nothing but the
+//! running hash state is ever buffered.
+//!
+//! The operator is expected to compare the fingerprint against the one the
+//! host shows before pressing. Without that comparison the press proves only
+//! that *someone* approved *some* data.
+
+use icesickle_core::attestation::{self, AttestationEvent};
+use log::info;
+
+use crate::sha::Sha256;
+
+/// An armed digest expires if not approved within this time
+pub const APPROVAL_TIMEOUT_MS: u64 = 60_000;
+
+/// Bytes of digest shown as the fingerprint
+const FINGERPRINT_LEN: usize = 8;
+
+/// A finished digest waiting for a button press
+#[derive(Debug, Clone, Copy)]
+struct Armed {
+    sha256: [u8; 32],
+    len: u64,
+    armed_at_ms: u64,
+}
+
+/// Hash-then-sign state machine: Idle → Hashing → Armed → (press) → Idle
+#[derive(Default)]
+pub struct DigestSession {
+    hashing: Option<(Sha256, u64)>,
+    armed: Option<Armed>,
+}
+
+impl DigestSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new stream, discarding any partial or armed digest
+    pub fn begin(&mut self) {
+        self.armed = None;
+        self.hashing = Some((Sha256::new(), 0));
+    }
+
+    /// Hash the next chunk; false if no stream is open
+    pub fn update(&mut self, chunk: &[u8]) -> bool {
+        match &mut self.hashing {
+            Some((hasher, len)) => {
+                hasher.update(chunk);
+                *len += chunk.len() as u64;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Finish the stream and arm the digest for approval
+    pub fn finish(&mut self, now_ms: u64) -> Option<([u8; 32], u64)> {
+        let (hasher, len) = self.hashing.take()?;
+        let sha256 = hasher.finish();
+
+        info!(
+            "Data digest {}... ({} bytes) - press button to approve",
+            attestation::hex_encode::<{ 2 * FINGERPRINT_LEN }>(&sha256[..FINGERPRINT_LEN]),
+            len
+        );
+
+        self.armed = Some(Armed {
+            sha256,
+            len,
+            armed_at_ms: now_ms,
+        });
+        Some((sha256, len))
+    }
+
+    /// True if a digest is armed and still within its approval window
+    pub fn is_armed(&self, now_ms: u64) -> bool {
+        self.armed
+            .is_some_and(|armed| now_ms.saturating_sub(armed.armed_at_ms) < APPROVAL_TIMEOUT_MS)
+    }
+
+    /// Event for the next press: the armed digest if any, else a plain press
+    ///
+    /// Consumes the armed digest, so each approval signs exactly once.
+    pub fn take_event(&mut self, gpio: u8, now_ms: u64) -> AttestationEvent {
+        match self.armed.take() {
+            Some(armed) if now_ms.saturating_sub(armed.armed_at_ms) < APPROVAL_TIMEOUT_MS => {
+                AttestationEvent::DataDigest {
+                    gpio,
+                    sha256: armed.sha256,
+                    len: armed.len,
+                }
+            }
+            Some(_) => {
+                info!("Armed digest expired - attesting a plain button press");
+                AttestationEvent::ButtonPress { gpio }
+            }
+            None => AttestationEvent::ButtonPress { gpio },
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! QR code display (feature `display`)
+//!
+//! An SSD1306 OLED on the shared I2C bus shows each attestation as
+//! `icesickle_core::display` renders it: a QR code of the compact form,
+//! for a phone to scan. [`show`] renders the code from the output path,
+//! which has no bus; [`Display::poll`], called from the event loop, writes
+//! it a page at a time and blanks the screen after `display::SHOW_MS`. A
+//! newer attestation replaces one still being written. While the cooldown
+//! refuses presses, [`Display::countdown`] shows the seconds left beside
+//! the code.
+//!
+//! The display is one more output: a missing or failing screen is logged
+//! and counted as a sink error, and every other output carries on.
+
+use std::sync::Mutex;
+
+use icesickle_core::attestation::Attestation;
+use icesickle_core::display::{self, Frame, Ssd1306};
+use icesickle_core::protocol::AttestationRecord;
+use icesickle_core::telemetry::{self, Counter};
+use log::{info, warn};
+
+use crate::hal::EspI2c;
+
+/// Frame rendered by [`show`] and not yet handed to the screen
+static PENDING: Mutex<Option<Frame>> = Mutex::new(None);
+
+/// The screen found at boot, if any
+pub struct Display {
+    screen: Option<Ssd1306>,
+    /// Last write failed; logged once until a write succeeds
+    failing: bool,
+}
+
+impl Display {
+    /// Probe `bus` for the screen and set it up, dark
+    pub fn new(bus: &mut EspI2c<'_>) -> Self {
+        let screen = Ssd1306::new(display::DEFAULT_ADDRESS);
+        let screen = screen.probe(bus).then_some(screen);
+        match screen {
+            Some(_) => info!("SSD1306 display at 0x{:02x}", display::DEFAULT_ADDRESS),
+            None => warn!("No display found; attestations will not be shown"),
+        }
+        Self {
+            screen,
+            failing: false,
+        }
+    }
+
+    /// Count down to `until_ms`, when the cooldown ends
+    pub fn countdown(&mut self, until_ms: u64) {
+        if let Some(screen) = self.screen.as_mut() {
+            screen.countdown(until_ms);
+        }
+    }
+
+    /// Write the next part of a pending code, or blank an old one (never
+    /// blocks for long)
+    pub fn poll(&mut self, bus: &mut EspI2c<'_>, now_ms: u64) {
+        let Some(screen) = self.screen.as_mut() else {
+            return;
+        };
+        if let Some(frame) = PENDING.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            screen.show(frame);
+        }
+        match screen.poll(bus, now_ms) {
+            Ok(()) => self.failing = false,
+            Err(e) => {
+                if !self.failing {
+                    warn!("Display write failed: {}", e);
+                    telemetry::record(Counter::SinkError);
+                }
+                self.failing = true;
+            }
+        }
+    }
+}
+
+/// Render `attestation` for the next [`Display::poll`] to show
+pub fn show(attestation: &Attestation) {
+    let Some(frame) = display::render(&AttestationRecord::from(attestation)) else {
+        warn!("Attestation too long for the display");
+        telemetry::record(Counter::SinkError);
+        return;
+    };
+    *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = Some(frame);
+}
@@ -0,0 +1,116 @@
+//! ESP-NOW attestation broadcast (feature `espnow`)
+//!
+//! For rooms with a receiver nearby but nothing to pair with. Every
+//! attestation is sent as connectionless ESP-NOW frames (802.11 vendor
+//! action frames) to [`PEER`] on [`CHANNEL`], straight after signing. Each
+//! ESP-NOW frame carries one `icesickle_core::transport` frame of up to
+//! `REPORT_LEN` bytes, so a receiver reassembles messages as a WebHID page
+//! does, keeping one partial message per sender address.
+//!
+//! WiFi comes up in station mode only to carry ESP-NOW: no network is
+//! scanned for or joined, there is no IP stack, and no receive callback is
+//! registered, so nothing a neighbour sends reaches the firmware.
+//! Broadcast frames are neither acknowledged nor retried; a receiver that
+//! misses one loses that attestation (`GetHistory` has it).
+//!
+//! # Unlinkability
+//!
+//! The station address is a random, locally administered one drawn from
+//! the TRNG at every boot, so the factory MAC never goes on air and no
+//! address follows the unit across restarts. Within a boot the address is
+//! fixed: a listener can group that boot's attestations by it, even in
+//! builds whose payloads avoid linking them (`no-counter`).
+//!
+//! WiFi is compiled out of the default build; the feature needs it from
+//! `sdkconfig.espnow` (see the README).
+
+use std::sync::Mutex;
+
+use esp_idf_hal::modem::Modem;
+use esp_idf_svc::espnow::{EspNow, PeerInfo, BROADCAST};
+use esp_idf_svc::eventloop::EspSystemEventLoop;
+use esp_idf_svc::wifi::{ClientConfiguration, Configuration, WifiDriver};
+use esp_idf_sys::{self as sys, esp, EspError};
+use log::warn;
+
+use icesickle_core::attestation::Attestation;
+use icesickle_core::telemetry::{self, Counter};
+use icesickle_core::transport::{self, Message};
+
+/// Where attestations go: every receiver on the channel, or one receiver's
+/// station address. `SetConfig` cannot change it until a provisioning mode
+/// exists, so it is set here
+pub const PEER: [u8; 6] = BROADCAST;
+
+/// WiFi channel the receivers listen on
+pub const CHANNEL: u8 = 1;
+
+/// Message waiting for the main loop to send it
+static PENDING: Mutex<Option<Message>> = Mutex::new(None);
+
+/// Queue `attestation` for the next [`Sender::flush`]
+pub fn push(attestation: &Attestation) {
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending = Some(transport::message(attestation));
+    }
+}
+
+/// WiFi in ESP-NOW-only station mode
+pub struct Sender {
+    /// Keeps the radio up
+    _wifi: WifiDriver<'static>,
+    espnow: EspNow<'static>,
+}
+
+impl Sender {
+    /// Start WiFi from a fresh random address and register [`PEER`]
+    pub fn new(modem: Modem, sysloop: EspSystemEventLoop) -> Result<Self, EspError> {
+        // No NVS: nothing about the radio outlives the boot
+        let mut wifi = WifiDriver::new(modem, sysloop, None)?;
+        wifi.set_configuration(&Configuration::Client(ClientConfiguration::default()))?;
+
+        let mut address = [0u8; 6];
+        // Not key material, so taken from the TRNG directly
+        unsafe {
+            sys::esp_fill_random(address.as_mut_ptr().cast(), address.len());
+        }
+        // Locally administered unicast: bit 1 of the first byte set, bit 0
+        // clear
+        address[0] = (address[0] | 0x02) & !0x01;
+        // Only accepted while the interface is stopped
+        esp!(unsafe {
+            sys::esp_wifi_set_mac(sys::wifi_interface_t_WIFI_IF_STA, address.as_ptr())
+        })?;
+        wifi.start()?;
+        esp!(unsafe {
+            sys::esp_wifi_set_channel(CHANNEL, sys::wifi_second_chan_t_WIFI_SECOND_CHAN_NONE)
+        })?;
+
+        let espnow = EspNow::take()?;
+        espnow.add_peer(PeerInfo {
+            peer_addr: PEER,
+            channel: CHANNEL,
+            ifidx: sys::wifi_interface_t_WIFI_IF_STA,
+            encrypt: false,
+            ..Default::default()
+        })?;
+        Ok(Self {
+            _wifi: wifi,
+            espnow,
+        })
+    }
+
+    /// Send the pending attestation
+    pub fn flush(&self) {
+        let Some(message) = PENDING.lock().ok().and_then(|mut pending| pending.take()) else {
+            return;
+        };
+        for frame in transport::frames(&message, transport::REPORT_LEN) {
+            if let Err(e) = self.espnow.send(PEER, &frame) {
+                warn!("ESP-NOW send failed ({}) - attestation dropped", e);
+                telemetry::record(Counter::SinkError);
+                return;
+            }
+        }
+    }
+}
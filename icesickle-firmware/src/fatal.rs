@@ -0,0 +1,180 @@
+//! Fatal path: scrub secrets, record the fault, then reset
+//!
+//! A panic mid-signing would otherwise abort with the seed or the expanded
+//! key still on the stack, and SRAM survives a soft reset. Every fatal exit
+//! (a panic, or an error escaping the event loop) ends in
+//! [`scrub_and_restart`], which:
+//!
+//! 1. wipes the core crypto workspace (where the key seed is derived);
+//! 2. wipes the failing task's stack (the main task's, or with `signer-core`
+//!    the signer's) except a guard band around its own frame: below it lie
+//!    frames that already returned, above it the callers of the failure,
+//!    which are never resumed;
+//! 3. calls `esp_restart()`.
+//!
+//! A FreeRTOS stack overflow is routed the same way: the canary hook wipes
+//! the workspace and the overflowed stack before aborting. So is a hang:
+//! the main task feeds the task watchdog every loop ([`watch`], [`feed`]),
+//! and if it stops for `CONFIG_ESP_TASK_WDT_TIMEOUT_S` the watchdog's user
+//! hook wipes the workspace and the whole main stack (and the signer's)
+//! before ESP-IDF panics (`CONFIG_ESP_TASK_WDT_PANIC`) and resets.
+//!
+//! Each of these first stores a `Fault` (see `icesickle_core::fault`) in
+//! RTC memory, and the next boot prints it as a `FAULT` line ([`report`]).
+//!
+//! Only the panic location is logged, never the message: a message can
+//! format arbitrary values, including ones derived from key material.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use esp_idf_sys::esp;
+use icesickle_core::fault::{Fault, FaultKind, FAULT_WORDS};
+use icesickle_core::{scrub, IceSickleError};
+
+use crate::hal::esp_err;
+use crate::stack;
+
+/// Bytes left untouched on either side of the scrubbing frame
+const GUARD_BYTES: usize = 1024;
+
+/// The last fault, kept across the reset; ESP-IDF leaves `.rtc_noinit`
+/// alone at boot, so it holds noise after a power-on until written
+#[link_section = ".rtc_noinit"]
+static LAST_FAULT: [AtomicU32; FAULT_WORDS] = [const { AtomicU32::new(0) }; FAULT_WORDS];
+
+/// Record the main task's stack and install the panic hook
+///
+/// Call first thing in `main`, on the main task.
+pub fn install() {
+    stack::record();
+
+    std::panic::set_hook(Box::new(|info| {
+        let line = match info.location() {
+            Some(at) => {
+                log::error!("FATAL: panic at {}:{}", at.file(), at.line());
+                at.line()
+            }
+            None => {
+                log::error!("FATAL: panic");
+                0
+            }
+        };
+        store(FaultKind::Panic, line);
+        scrub_and_restart()
+    }));
+}
+
+/// Report an unrecoverable error, scrub and reset
+pub fn fatal(err: IceSickleError) -> ! {
+    log::error!("FATAL: {} (code {})", err, err.code());
+    store(FaultKind::Error, err.code().into());
+    scrub_and_restart()
+}
+
+/// The previous run's fault, if it died of one; cleared once read
+pub fn take_last() -> Option<Fault> {
+    let mut words = [0; FAULT_WORDS];
+    for (word, slot) in words.iter_mut().zip(&LAST_FAULT) {
+        *word = slot.swap(0, Ordering::Relaxed);
+    }
+    Fault::from_words(&words)
+}
+
+/// Print the previous run's fault as a `FAULT` line on the console
+pub fn report() {
+    if let Some(fault) = take_last() {
+        log::warn!(
+            "Previous run ended in a {} fault at {} ms",
+            fault.kind.name(),
+            fault.uptime_ms
+        );
+        crate::console::write(fault.fixed_line().as_bytes());
+    }
+}
+
+/// Subscribe the calling task (the main task) to the task watchdog
+pub fn watch() -> icesickle_core::Result<()> {
+    // SAFETY: subscribes the calling task to the watchdog ESP-IDF started
+    // at boot (`CONFIG_ESP_TASK_WDT_INIT`)
+    unsafe { esp!(esp_idf_sys::esp_task_wdt_add(core::ptr::null_mut())) }.map_err(esp_err)
+}
+
+/// Tell the task watchdog the main task is still running
+pub fn feed() {
+    // Only fails for a task that never subscribed, which `watch` rules out
+    unsafe { esp_idf_sys::esp_task_wdt_reset() };
+}
+
+/// Keep `kind` for the next boot; safe from interrupt context
+fn store(kind: FaultKind, code: u32) {
+    let uptime_ms = unsafe { esp_idf_sys::esp_timer_get_time() } as u64 / 1_000;
+    let fault = Fault {
+        kind,
+        code,
+        uptime_ms,
+    };
+    for (slot, word) in LAST_FAULT.iter().zip(fault.to_words()) {
+        slot.store(word, Ordering::Relaxed);
+    }
+}
+
+/// FreeRTOS stack overflow hook (canary check), replacing ESP-IDF's
+///
+/// Runs from the scheduler on the interrupt stack, so the overflowing task's
+/// stack can be wiped whole. No logging here: the logger is not safe in
+/// this context. `esp_system_abort` then takes ESP-IDF's panic path, which
+/// reports the task and resets.
+#[no_mangle]
+extern "C" fn vApplicationStackOverflowHook(
+    task: esp_idf_sys::TaskHandle_t,
+    _name: *mut core::ffi::c_char,
+) {
+    store(FaultKind::StackOverflow, 0);
+    scrub::CRYPTO_WORKSPACE.scrub();
+    if let Some((start, end)) = stack::bounds_of(task) {
+        // SAFETY: the overflowing task is switched out and never resumes
+        unsafe { scrub::zero_range(start as *mut u8, end as *mut u8) };
+    }
+    unsafe { esp_idf_sys::esp_system_abort(b"stack overflow\0".as_ptr().cast()) }
+}
+
+/// Task watchdog user hook, replacing ESP-IDF's empty one
+///
+/// Runs in the watchdog interrupt, just before ESP-IDF's panic handler. No
+/// logging here either; ESP-IDF prints the starved tasks and resets.
+#[no_mangle]
+extern "C" fn esp_task_wdt_isr_user_handler() {
+    store(FaultKind::Watchdog, 0);
+    scrub::CRYPTO_WORKSPACE.scrub();
+    for (start, end) in stack::main_bounds()
+        .into_iter()
+        .chain(stack::signer_bounds())
+    {
+        // SAFETY: the panic that follows halts both cores, so neither task
+        // resumes
+        unsafe { scrub::zero_range(start as *mut u8, end as *mut u8) };
+    }
+}
+
+#[inline(never)]
+fn scrub_and_restart() -> ! {
+    scrub::CRYPTO_WORKSPACE.scrub();
+
+    let marker = 0u8;
+    let sp = core::ptr::addr_of!(marker) as usize;
+
+    // Only the spans of the tasks that sign are known; a panic on another
+    // task (USB callbacks) still gets the workspace wiped
+    if let Some((start, end)) = stack::bounds(sp) {
+        let below = sp.saturating_sub(GUARD_BYTES).max(start);
+        let above = (sp + GUARD_BYTES).min(end);
+        // SAFETY: both ranges are inside this task's stack and outside
+        // this frame; nothing above it is ever returned to
+        unsafe {
+            scrub::zero_range(start as *mut u8, below as *mut u8);
+            scrub::zero_range(above as *mut u8, end as *mut u8);
+        }
+    }
+
+    unsafe { esp_idf_sys::esp_restart() }
+}
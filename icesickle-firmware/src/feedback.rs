@@ -0,0 +1,78 @@
+//! Status LED and piezo (feature `feedback`)
+//!
+//! A plain LED on GPIO38 ([`LED_PIN`], active high) and, with `buzzer`, an
+//! active piezo buzzer (one with its own oscillator) on GPIO48
+//! ([`BUZZER_PIN`], active high) show `icesickle_core::feedback` signals.
+//! The event loop calls [`Feedback::show`] from its result branches and
+//! [`Feedback::poll`] on every pass; `show` sets the pins at once, so the
+//! LED is lit before a signing that blocks the loop.
+//!
+//! Feedback is cosmetic: a pin that cannot be driven is logged once and
+//! otherwise ignored.
+
+use esp_idf_hal::gpio::{AnyOutputPin, Output, PinDriver};
+use icesickle_core::feedback::{self, Player, Signal};
+use icesickle_core::Result;
+use log::warn;
+
+use crate::hal::esp_err;
+
+/// Status LED GPIO
+pub const LED_PIN: i32 = 38;
+
+/// Piezo buzzer GPIO (feature `buzzer`)
+pub const BUZZER_PIN: i32 = 48;
+
+/// The pins and the signal playing on them
+pub struct Feedback<'d> {
+    led: PinDriver<'d, AnyOutputPin, Output>,
+    buzzer: Option<PinDriver<'d, AnyOutputPin, Output>>,
+    player: Player,
+    /// Last write failed; logged once until a write succeeds
+    failing: bool,
+}
+
+impl<'d> Feedback<'d> {
+    /// Take the pins, both driven low
+    pub fn new(
+        led: PinDriver<'d, AnyOutputPin, Output>,
+        buzzer: Option<PinDriver<'d, AnyOutputPin, Output>>,
+    ) -> Result<Self> {
+        let mut status = Self {
+            led,
+            buzzer,
+            player: Player::new(),
+            failing: false,
+        };
+        status.write(feedback::Output::DARK)?;
+        Ok(status)
+    }
+
+    /// Start `signal` now
+    pub fn show(&mut self, signal: Signal, now_ms: u64) {
+        self.player.play(signal, now_ms);
+        self.poll(now_ms);
+    }
+
+    /// Bring the pins up to date
+    pub fn poll(&mut self, now_ms: u64) {
+        let output = self.player.output(now_ms);
+        match self.write(output) {
+            Ok(()) => self.failing = false,
+            Err(e) => {
+                if !self.failing {
+                    warn!("Status feedback write failed: {}", e);
+                }
+                self.failing = true;
+            }
+        }
+    }
+
+    fn write(&mut self, output: feedback::Output) -> Result<()> {
+        self.led.set_level(output.led.into()).map_err(esp_err)?;
+        if let Some(buzzer) = self.buzzer.as_mut() {
+            buzzer.set_level(output.tone.into()).map_err(esp_err)?;
+        }
+        Ok(())
+    }
+}
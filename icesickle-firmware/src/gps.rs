@@ -0,0 +1,124 @@
+//! GPS time source (feature `gps`)
+//!
+//! A GPS module's NMEA output on UART2 (`NMEA_RX_PIN`) names each second,
+//! and its PPS line (`PPS_PIN`) marks when that second began. The PPS edge
+//! is timestamped in an interrupt so the event loop's polling interval does
+//! not blur it; `icesickle_core::wallclock::PpsClock` pairs edges with
+//! sentences.
+//!
+//! `EspTimer::wall_clock` reads the result, so every attestation signed
+//! while the receiver has a fix carries UTC time. Times are rounded down to
+//! [`QUANTUM_S`].
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use esp_idf_hal::delay::NON_BLOCK;
+use esp_idf_hal::gpio::{AnyIOPin, Input, InterruptType, PinDriver};
+use esp_idf_hal::uart::UartDriver;
+use icesickle_core::wallclock::{self, PpsClock, TimeSource, WallTime};
+use icesickle_core::Result;
+
+use crate::hal::esp_err;
+
+/// GPIO receiving the module's NMEA TX
+pub const NMEA_RX_PIN: i32 = 16;
+
+/// GPIO receiving the module's PPS output
+pub const PPS_PIN: i32 = 15;
+
+/// NMEA baud rate (the default of most modules)
+pub const NMEA_BAUD: u32 = 9_600;
+
+/// Resolution of signed UTC times; raise (e.g. to 60) to coarsen them
+const QUANTUM_S: u64 = 1;
+
+/// Longest NMEA sentence (82 characters by the standard)
+const MAX_SENTENCE_LEN: usize = 82;
+
+/// Last PPS edge in µs since boot (0 = none since the last poll)
+static PPS_EDGE_US: AtomicU64 = AtomicU64::new(0);
+
+static CLOCK: Mutex<PpsClock> = Mutex::new(PpsClock::new());
+
+/// NMEA reader and PPS interrupt
+pub struct Gps<'d> {
+    uart: UartDriver<'d>,
+    pps: PinDriver<'d, AnyIOPin, Input>,
+    line: [u8; MAX_SENTENCE_LEN],
+    len: usize,
+    overflowed: bool,
+}
+
+impl<'d> Gps<'d> {
+    pub fn new(uart: UartDriver<'d>, mut pps: PinDriver<'d, AnyIOPin, Input>) -> Result<Self> {
+        pps.set_interrupt_type(InterruptType::PosEdge)
+            .map_err(esp_err)?;
+        // SAFETY: the callback only reads the timer and stores an atomic
+        unsafe {
+            pps.subscribe(|| {
+                let now = esp_idf_sys::esp_timer_get_time() as u64;
+                PPS_EDGE_US.store(now, Ordering::Relaxed);
+            })
+            .map_err(esp_err)?;
+        }
+        pps.enable_interrupt().map_err(esp_err)?;
+
+        Ok(Self {
+            uart,
+            pps,
+            line: [0; MAX_SENTENCE_LEN],
+            len: 0,
+            overflowed: false,
+        })
+    }
+
+    /// Feed new PPS edges and NMEA sentences to the clock (never blocks)
+    pub fn poll(&mut self, now_ms: u64) {
+        let mut clock = CLOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let edge_us = PPS_EDGE_US.swap(0, Ordering::Relaxed);
+        if edge_us != 0 {
+            clock.pulse(edge_us / 1000);
+        }
+        // The driver disarms the interrupt after each edge
+        let _ = self.pps.enable_interrupt();
+
+        let mut byte = [0u8; 1];
+        while let Ok(1) = self.uart.read(&mut byte, NON_BLOCK) {
+            match byte[0] {
+                b'\n' => {
+                    let len = core::mem::take(&mut self.len);
+                    if core::mem::take(&mut self.overflowed) {
+                        continue;
+                    }
+                    let unix_s = core::str::from_utf8(&self.line[..len])
+                        .ok()
+                        .and_then(wallclock::parse_rmc);
+                    if let Some(unix_s) = unix_s {
+                        clock.sentence(unix_s, now_ms);
+                    }
+                }
+                b => {
+                    if self.len < MAX_SENTENCE_LEN {
+                        self.line[self.len] = b;
+                        self.len += 1;
+                    } else {
+                        // Not NMEA; dropped at the next line end
+                        self.overflowed = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Current UTC time, if the receiver has a fix (for `EspTimer`)
+pub fn wall_clock(now_ms: u64) -> Option<WallTime> {
+    let clock = CLOCK.lock().unwrap_or_else(|e| e.into_inner());
+    clock.now(now_ms).map(|unix_s| WallTime {
+        unix_s: wallclock::quantize(unix_s, QUANTUM_S),
+        source: TimeSource::Gps,
+        stale: false,
+    })
+}
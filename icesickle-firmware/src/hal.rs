@@ -0,0 +1,144 @@
+//! ESP32 implementations of the core HAL traits
+//!
+//! Zero-cost wrappers around the ESP-IDF calls behind
+//! [`icesickle_core::hal`]. Nothing else in the firmware should need to
+//! reach the timer, RNG, input pins or I2C bus directly.
+
+use esp_idf_hal::gpio::{self, Input, PinDriver};
+#[cfg(feature = "i2c")]
+use esp_idf_hal::i2c::{I2c, I2cConfig, I2cDriver};
+#[cfg(feature = "i2c")]
+use esp_idf_hal::peripheral::Peripheral;
+#[cfg(feature = "i2c")]
+use esp_idf_hal::units::Hertz;
+use esp_idf_sys::EspError;
+#[cfg(feature = "i2c")]
+use icesickle_core::hal::I2cBus;
+use icesickle_core::hal::{EntropySource, InputPin, Timer};
+#[cfg(any(feature = "gps", feature = "rtc"))]
+use icesickle_core::wallclock::WallTime;
+use icesickle_core::IceSickleError;
+
+/// Map an ESP-IDF driver error into the crate error type
+///
+/// For `map_err`: the orphan rule rules out a `From` impl here.
+pub fn esp_err(e: EspError) -> IceSickleError {
+    IceSickleError::Gpio(e.code())
+}
+
+/// An ESP-IDF GPIO configured as input
+pub struct EspPin<'d, P: esp_idf_hal::gpio::Pin>(pub PinDriver<'d, P, Input>);
+
+impl<P: esp_idf_hal::gpio::Pin> InputPin for EspPin<'_, P> {
+    fn is_low(&self) -> bool {
+        self.0.is_low()
+    }
+}
+
+/// ESP-IDF high-resolution timer and FreeRTOS delay
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EspTimer;
+
+impl Timer for EspTimer {
+    fn now_ms(&self) -> u64 {
+        // ESP-IDF advances esp_timer by the time spent in light sleep, so
+        // this keeps counting through it (feature `light-sleep`)
+        unsafe { esp_idf_sys::esp_timer_get_time() as u64 / 1000 }
+    }
+
+    fn delay_ms(&self, ms: u32) {
+        esp_idf_hal::delay::FreeRtos::delay_ms(ms);
+    }
+
+    /// GPS while it has a fix, else the RTC
+    #[cfg(any(feature = "gps", feature = "rtc"))]
+    fn wall_clock(&self) -> Option<WallTime> {
+        let now_ms = self.now_ms();
+        #[cfg(feature = "gps")]
+        let wall = crate::gps::wall_clock(now_ms);
+        #[cfg(not(feature = "gps"))]
+        let wall = None;
+        #[cfg(feature = "rtc")]
+        let wall = wall.or_else(|| crate::rtc::wall_clock(now_ms));
+        wall
+    }
+}
+
+/// ESP32 hardware TRNG via `esp_fill_random()`
+///
+/// Absent in `test-vectors` builds, so seeded and hardware entropy can never
+/// both be linked in.
+#[cfg(not(feature = "test-vectors"))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EspEntropy;
+
+#[cfg(not(feature = "test-vectors"))]
+impl EntropySource for EspEntropy {
+    fn fill(&self, dest: &mut [u8]) {
+        unsafe {
+            esp_idf_sys::esp_fill_random(dest.as_mut_ptr() as *mut _, dest.len());
+        }
+    }
+}
+
+/// GPIO for I2C data (sensors, RTC, ATECC608 and display share the bus)
+#[cfg(feature = "i2c")]
+pub const I2C_SDA_PIN: i32 = 1;
+
+/// GPIO for I2C clock
+#[cfg(feature = "i2c")]
+pub const I2C_SCL_PIN: i32 = 2;
+
+/// Standard-mode I2C, which every supported device speaks
+#[cfg(feature = "i2c")]
+const I2C_HZ: u32 = 100_000;
+
+/// ESP-IDF I2C master; transfers give up after `I2C_TIMEOUT_MS`
+#[cfg(feature = "i2c")]
+pub struct EspI2c<'d>(pub I2cDriver<'d>);
+
+/// Longest a single I2C transfer may block the event loop
+#[cfg(feature = "i2c")]
+const I2C_TIMEOUT_MS: u64 = 10;
+
+#[cfg(feature = "i2c")]
+impl<'d> EspI2c<'d> {
+    pub fn new(
+        i2c: impl Peripheral<P = impl I2c> + 'd,
+        sda: impl Peripheral<P = impl gpio::InputPin + gpio::OutputPin> + 'd,
+        scl: impl Peripheral<P = impl gpio::InputPin + gpio::OutputPin> + 'd,
+    ) -> icesickle_core::Result<Self> {
+        let config = I2cConfig::new().baudrate(Hertz(I2C_HZ));
+        Ok(Self(
+            I2cDriver::new(i2c, sda, scl, &config).map_err(esp_err)?,
+        ))
+    }
+
+    fn timeout() -> u32 {
+        esp_idf_hal::delay::TickType::new_millis(I2C_TIMEOUT_MS).ticks()
+    }
+}
+
+#[cfg(feature = "i2c")]
+impl I2cBus for EspI2c<'_> {
+    fn write(&mut self, address: u8, bytes: &[u8]) -> icesickle_core::Result<()> {
+        self.0
+            .write(address, bytes, Self::timeout())
+            .map_err(esp_err)
+    }
+
+    fn read(&mut self, address: u8, buf: &mut [u8]) -> icesickle_core::Result<()> {
+        self.0.read(address, buf, Self::timeout()).map_err(esp_err)
+    }
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buf: &mut [u8],
+    ) -> icesickle_core::Result<()> {
+        self.0
+            .write_read(address, bytes, buf, Self::timeout())
+            .map_err(esp_err)
+    }
+}
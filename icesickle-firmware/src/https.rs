@@ -0,0 +1,224 @@
+//! HTTPS push to a verifier endpoint (feature `https-push`)
+//!
+//! For deployments that want the device itself to deliver attestations.
+//! The WiFi network and the endpoint are baked in at build time from
+//! `ICESICKLE_WIFI_SSID`, `ICESICKLE_WIFI_PASSWORD` and `ICESICKLE_PUSH_URL`,
+//! which must be an `https://` URL. Every attestation is queued as a POST
+//! body in a RAM-only `icesickle_core::delivery` queue: the record as the
+//! JSON `icesickle_core::volume` writes (`application/json`), or with
+//! `https-push-cwt` its CBOR Web Token (`application/cwt`). A network thread
+//! POSTs them over TLS, checking the server against ESP-IDF's CA bundle,
+//! and retries with backoff while the network or the endpoint is down.
+//!
+//! The thread only sends: a response is read for its status and nothing in
+//! it reaches the signing path, which never waits on the network. A 4xx
+//! status other than 408 and 429 means the endpoint will never take the
+//! body, so it is discarded rather than retried.
+//!
+//! WiFi is compiled out of the default build; the feature needs it from
+//! `sdkconfig.https` (see the README). Turning it on changes the radio and
+//! entropy assumptions in THREAT_MODEL.md.
+
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use embedded_svc::http::client::Client;
+use embedded_svc::http::Method;
+use embedded_svc::io::Write;
+use esp_idf_hal::modem::Modem;
+use esp_idf_svc::eventloop::EspSystemEventLoop;
+use esp_idf_svc::http::client::{Configuration as HttpConfiguration, EspHttpConnection};
+use esp_idf_svc::io::EspIOError;
+use esp_idf_svc::wifi::{AuthMethod, BlockingWifi, ClientConfiguration, Configuration, EspWifi};
+use esp_idf_sys::{self as sys, EspError};
+use log::{info, warn};
+
+use icesickle_core::attestation::Attestation;
+use icesickle_core::delivery::{self, Delivery};
+use icesickle_core::hal::Timer;
+use icesickle_core::telemetry::{self, Counter};
+
+use crate::hal::EspTimer;
+
+const SSID: &str = env!(
+    "ICESICKLE_WIFI_SSID",
+    "`https-push` needs ICESICKLE_WIFI_SSID at build time (see the README)"
+);
+
+/// Empty for an open network
+const PASSWORD: &str = env!(
+    "ICESICKLE_WIFI_PASSWORD",
+    "`https-push` needs ICESICKLE_WIFI_PASSWORD at build time (see the README)"
+);
+
+/// Verifier endpoint
+pub const URL: &str = env!(
+    "ICESICKLE_PUSH_URL",
+    "`https-push` needs ICESICKLE_PUSH_URL at build time (see the README)"
+);
+
+const _: () = assert!(
+    SSID.len() <= 32,
+    "ICESICKLE_WIFI_SSID is longer than 32 bytes"
+);
+const _: () = assert!(
+    PASSWORD.len() <= 64,
+    "ICESICKLE_WIFI_PASSWORD is longer than 64 bytes"
+);
+const _: () = assert!(is_https(URL), "ICESICKLE_PUSH_URL must be an https:// URL");
+
+#[cfg(not(feature = "https-push-cwt"))]
+const CONTENT_TYPE: &str = "application/json";
+#[cfg(feature = "https-push-cwt")]
+const CONTENT_TYPE: &str = "application/cwt";
+
+/// Bodies kept while the endpoint is unreachable
+const QUEUE_LEN: usize = 8;
+
+/// Pause between looks at an empty or waiting queue
+const IDLE: Duration = Duration::from_millis(200);
+
+/// Connection and response timeout
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Network thread stack: room for a TLS handshake
+const STACK_SIZE: usize = 12 * 1024;
+
+/// Bodies waiting for the network thread
+static QUEUE: Mutex<Delivery<Vec<u8>, QUEUE_LEN>> = Mutex::new(Delivery::new());
+
+/// Queue `attestation` for the network thread
+pub fn push(attestation: &Attestation) {
+    #[cfg(not(feature = "https-push-cwt"))]
+    let body = {
+        let record = icesickle_core::protocol::AttestationRecord::from(attestation);
+        icesickle_core::volume::json(&record).as_bytes().to_vec()
+    };
+    #[cfg(feature = "https-push-cwt")]
+    let Some(body) = icesickle_core::cwt::render(attestation).map(|token| token.to_vec()) else {
+        warn!("CWT too long - attestation not pushed");
+        telemetry::record(Counter::SinkError);
+        return;
+    };
+    if !queue().push(body) {
+        warn!("HTTPS push queue full - oldest attestation dropped");
+        telemetry::record(Counter::SinkError);
+    }
+}
+
+/// Bring WiFi up on `modem` and start the network thread
+pub fn start(modem: Modem, sysloop: EspSystemEventLoop) -> Result<(), EspError> {
+    // No NVS: the credentials live in the image, and nothing else is kept
+    let wifi = EspWifi::new(modem, sysloop.clone(), None)?;
+    let mut wifi = BlockingWifi::wrap(wifi, sysloop)?;
+    wifi.set_configuration(&Configuration::Client(ClientConfiguration {
+        // Lengths are checked above
+        ssid: SSID.try_into().unwrap_or_default(),
+        password: PASSWORD.try_into().unwrap_or_default(),
+        auth_method: if PASSWORD.is_empty() {
+            AuthMethod::None
+        } else {
+            AuthMethod::WPA2Personal
+        },
+        ..Default::default()
+    }))?;
+    wifi.start()?;
+
+    thread::Builder::new()
+        .name("https-push".into())
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(wifi))
+        .map_err(|_| EspError::from_infallible::<{ sys::ESP_ERR_NO_MEM }>())?;
+    Ok(())
+}
+
+/// Keep WiFi joined and POST due bodies, forever
+fn run(mut wifi: BlockingWifi<EspWifi<'static>>) {
+    let mut connect_retry_ms = delivery::FIRST_RETRY_MS;
+    loop {
+        if !wifi.is_connected().unwrap_or(false) {
+            if let Err(e) = wifi.connect().and_then(|()| wifi.wait_netif_up()) {
+                warn!(
+                    "WiFi join failed ({}) - retrying in {} s",
+                    e,
+                    connect_retry_ms / 1_000
+                );
+                thread::sleep(Duration::from_millis(connect_retry_ms));
+                connect_retry_ms = (connect_retry_ms * 2).min(delivery::MAX_RETRY_MS);
+                continue;
+            }
+            info!("WiFi joined");
+            connect_retry_ms = delivery::FIRST_RETRY_MS;
+        }
+
+        // Copied out, so the output path is never kept waiting on the lock
+        let now_ms = EspTimer.now_ms();
+        let due = queue()
+            .due(now_ms)
+            .map(|(ticket, body)| (ticket, body.clone()));
+        let Some((ticket, body)) = due else {
+            thread::sleep(IDLE);
+            continue;
+        };
+
+        let result = post(&body);
+        let mut pending = queue();
+        match result {
+            Ok(status) if (200..300).contains(&status) => pending.delivered(ticket),
+            Ok(status) if (400..500).contains(&status) && status != 408 && status != 429 => {
+                warn!("Endpoint refused attestation ({}) - dropped", status);
+                telemetry::record(Counter::SinkError);
+                pending.discard(ticket);
+            }
+            Ok(status) => {
+                warn!("Endpoint answered {} - retrying", status);
+                pending.failed(EspTimer.now_ms());
+            }
+            Err(e) => {
+                warn!("HTTPS push failed ({}) - retrying", e);
+                pending.failed(EspTimer.now_ms());
+            }
+        }
+    }
+}
+
+/// POST `body` to [`URL`]; the response status
+fn post(body: &[u8]) -> Result<u16, EspIOError> {
+    let connection = EspHttpConnection::new(&HttpConfiguration {
+        timeout: Some(TIMEOUT),
+        crt_bundle_attach: Some(sys::esp_crt_bundle_attach),
+        ..Default::default()
+    })?;
+    let mut client = Client::wrap(connection);
+    let length = body.len().to_string();
+    let headers = [
+        ("Content-Type", CONTENT_TYPE),
+        ("Content-Length", length.as_str()),
+    ];
+    let mut request = client.request(Method::Post, URL, &headers)?;
+    request.write_all(body)?;
+    request.flush()?;
+    Ok(request.submit()?.status())
+}
+
+fn queue() -> MutexGuard<'static, Delivery<Vec<u8>, QUEUE_LEN>> {
+    // The queue stays consistent even if a holder panicked
+    QUEUE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `url` starts with `https://`
+const fn is_https(url: &str) -> bool {
+    let (url, scheme) = (url.as_bytes(), b"https://");
+    if url.len() < scheme.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < scheme.len() {
+        if url[i] != scheme[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
@@ -0,0 +1,105 @@
+//! 4x4 matrix keypad (feature `keypad`)
+//!
+//! Rows (`ROW_PINS`) are outputs, idle high; columns (`COL_PINS`) are inputs
+//! with pull-ups. A scan pulls one row low at a time and reads which column
+//! follows it. Polled from the event loop like the button: a key counts once
+//! it has been held for `DEBOUNCE_MS`, and again only after a release.
+//!
+//! Keys go straight into an `icesickle_core::keypad::Entry`, which never
+//! leaves the code anywhere but its own zeroized buffer.
+
+use esp_idf_hal::delay::Ets;
+use esp_idf_hal::gpio::{AnyIOPin, AnyOutputPin, Input, Output, PinDriver, Pull};
+use icesickle_core::keypad::{Entry, KEYMAP};
+use icesickle_core::Result;
+
+use crate::hal::esp_err;
+
+/// Row GPIOs, top to bottom
+pub const ROW_PINS: [i32; 4] = [7, 8, 9, 10];
+
+/// Column GPIOs, left to right
+pub const COL_PINS: [i32; 4] = [11, 12, 13, 14];
+
+/// A key must be held this long to count
+const DEBOUNCE_MS: u64 = 30;
+
+/// Time for the columns to follow a row change
+const SETTLE_US: u32 = 5;
+
+/// Keypad matrix and the entry being keyed
+pub struct Keypad<'d> {
+    rows: [PinDriver<'d, AnyOutputPin, Output>; 4],
+    cols: [PinDriver<'d, AnyIOPin, Input>; 4],
+    entry: Entry,
+    /// Key currently held and since when
+    held: Option<(u8, u64)>,
+    /// The held key has been passed to the entry
+    counted: bool,
+}
+
+impl<'d> Keypad<'d> {
+    pub fn new(
+        mut rows: [PinDriver<'d, AnyOutputPin, Output>; 4],
+        mut cols: [PinDriver<'d, AnyIOPin, Input>; 4],
+    ) -> Result<Self> {
+        for row in &mut rows {
+            row.set_high().map_err(esp_err)?;
+        }
+        for col in &mut cols {
+            col.set_pull(Pull::Up).map_err(esp_err)?;
+        }
+
+        Ok(Self {
+            rows,
+            cols,
+            entry: Entry::new(),
+            held: None,
+            counted: false,
+        })
+    }
+
+    /// Scan for keys; true once an entry has been completed with `#`
+    pub fn poll(&mut self, now_ms: u64) -> Result<bool> {
+        let key = self.scan()?;
+        if key != self.held.map(|(held, _)| held) {
+            self.held = key.map(|key| (key, now_ms));
+            self.counted = false;
+            return Ok(false);
+        }
+
+        match self.held {
+            Some((key, since_ms))
+                if !self.counted && now_ms.saturating_sub(since_ms) >= DEBOUNCE_MS =>
+            {
+                self.counted = true;
+                Ok(self.entry.press(key))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Hash the completed entry under the verifier's challenge
+    pub fn finish(&mut self, challenge: &[u8; 32]) -> [u8; 32] {
+        self.entry.finish(challenge)
+    }
+
+    /// Discard the entry so far
+    pub fn clear(&mut self) {
+        self.entry.clear();
+    }
+
+    /// The first key found held, if any
+    fn scan(&mut self) -> Result<Option<u8>> {
+        for (r, row) in self.rows.iter_mut().enumerate() {
+            row.set_low().map_err(esp_err)?;
+            Ets::delay_us(SETTLE_US);
+            let col = self.cols.iter().position(|col| col.is_low());
+            row.set_high().map_err(esp_err)?;
+            if let Some(c) = col {
+                return Ok(Some(KEYMAP[r][c]));
+            }
+        }
+        Ok(None)
+    }
+}
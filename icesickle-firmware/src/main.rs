@@ -0,0 +1,1764 @@
+//! IceSickle - Hardware-assisted ephemeral attestation device
+//!
+//! When a physical event occurs (button press), the device:
+//! 1. Generates a fresh keypair from hardware RNG (Ed25519, or P-256)
+//! 2. Signs an attestation payload containing the event + timestamp
+//! 3. Outputs the signature + public key
+//! 4. Zeroizes the private key (never persisted, never reused)
+//!
+//! This crate is the ESP32-S3 shell around `icesickle-core`: peripherals,
+//! transports and the event loop. The signing logic itself lives in core.
+//!
+//! `cargo test` builds it with `cfg(test)`, running the on-target tests
+//! (`target_test`) instead of the event loop.
+
+// The event loop is compiled but unused in test builds
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+
+#[cfg(all(
+    feature = "minimal",
+    any(
+        feature = "instrument",
+        feature = "usb-hid",
+        feature = "usb-msc",
+        feature = "ble",
+        feature = "espnow",
+        feature = "https-push",
+        feature = "display",
+        feature = "nfc",
+        feature = "batch",
+        feature = "openpgp",
+        feature = "sshsig",
+        feature = "dsse",
+        feature = "cwt",
+        feature = "cose",
+        feature = "receipt"
+    )
+))]
+compile_error!(
+    "the `minimal` profile cannot be combined with `instrument`, `usb-hid`, `usb-msc`, `ble`, `espnow`, `https-push`, `display`, `nfc`, `batch`, `openpgp`, `sshsig`, `dsse`, `cwt`, `cose` or `receipt`"
+);
+
+#[cfg(all(feature = "usb-hid", feature = "usb-msc"))]
+compile_error!("`usb-hid` and `usb-msc` both need the native USB port");
+
+#[cfg(all(feature = "random-counter", feature = "no-counter"))]
+compile_error!("`random-counter` and `no-counter` are alternatives");
+
+#[cfg(any(
+    all(feature = "receipt", feature = "witness"),
+    all(feature = "receipt", feature = "relay"),
+    all(feature = "receipt", feature = "binary-stream"),
+    all(feature = "relay", feature = "witness"),
+    all(feature = "relay", feature = "binary-stream"),
+    all(feature = "witness", feature = "binary-stream")
+))]
+compile_error!("`receipt`, `relay`, `witness` and `binary-stream` all need UART1");
+
+#[cfg(all(feature = "touch", feature = "extra-buttons"))]
+compile_error!("`touch` and `extra-buttons` both need GPIO3");
+
+#[cfg(all(
+    feature = "light-sleep",
+    any(
+        feature = "usb-hid",
+        feature = "usb-msc",
+        feature = "ble",
+        feature = "espnow",
+        feature = "https-push",
+        feature = "gps",
+        feature = "credit",
+        feature = "keypad",
+        feature = "touch",
+        feature = "display",
+        feature = "nfc",
+        feature = "receipt",
+        feature = "binary-stream",
+        feature = "relay",
+        feature = "witness"
+    )
+))]
+compile_error!(
+    "`light-sleep` cannot be combined with USB, BLE, WiFi, UART1 peers or inputs and outputs polled by the loop"
+);
+
+#[cfg(all(feature = "atecc", feature = "test-vectors"))]
+compile_error!("`atecc` mixes chip entropy into keys that `test-vectors` makes reproducible");
+
+#[cfg(all(feature = "drbg", feature = "test-vectors"))]
+compile_error!("`drbg` mixes noise into keys that `test-vectors` makes reproducible");
+
+#[cfg(all(feature = "measurement", any(feature = "display", feature = "receipt")))]
+compile_error!(
+    "`measurement` makes the largest record too long for the `display` and `receipt` QR codes"
+);
+
+#[cfg(all(feature = "drbg", feature = "keypad"))]
+compile_error!("`drbg` and `keypad` both need GPIO9");
+
+#[cfg(all(feature = "gestures", feature = "presence"))]
+compile_error!("`gestures` and `presence` both give holding the button a meaning");
+
+#[cfg(all(
+    feature = "dual-control",
+    any(feature = "gestures", feature = "presence")
+))]
+compile_error!("`dual-control` signs only pairs of presses, not gestures or presence");
+
+#[cfg(all(
+    feature = "p256",
+    any(
+        feature = "openpgp",
+        feature = "sshsig",
+        feature = "dsse",
+        feature = "cwt",
+        feature = "cose"
+    )
+))]
+compile_error!("the companion formats are Ed25519-only and cannot be combined with `p256`");
+
+#[cfg(all(feature = "hw-sha512", feature = "p256"))]
+compile_error!("`hw-sha512` speeds up Ed25519 signing, which `p256` replaces");
+
+#[cfg(all(
+    feature = "signer-core",
+    any(feature = "atecc", feature = "drbg", feature = "test-vectors")
+))]
+compile_error!(
+    "`signer-core` signs presses from the TRNG alone, not `atecc`, `drbg` or `test-vectors`"
+);
+
+#[cfg(all(feature = "feedback", feature = "light-sleep"))]
+compile_error!("`feedback` patterns need the loop awake, which `light-sleep` prevents");
+
+#[cfg(all(feature = "ble", not(esp_idf_bt_enabled)))]
+compile_error!("`ble` needs Bluetooth enabled in ESP-IDF: layer `sdkconfig.ble` (see the README)");
+
+#[cfg(all(feature = "espnow", not(esp_idf_esp_wifi_enabled)))]
+compile_error!("`espnow` needs WiFi enabled in ESP-IDF: layer `sdkconfig.espnow` (see the README)");
+
+#[cfg(all(feature = "https-push", not(esp_idf_esp_wifi_enabled)))]
+compile_error!(
+    "`https-push` needs WiFi enabled in ESP-IDF: layer `sdkconfig.https` (see the README)"
+);
+
+#[cfg(any(
+    all(feature = "ble", feature = "espnow"),
+    all(feature = "ble", feature = "https-push"),
+    all(feature = "espnow", feature = "https-push")
+))]
+compile_error!("`ble`, `espnow` and `https-push` all need the radio");
+
+#[cfg(feature = "atecc")]
+mod atecc;
+#[cfg(feature = "ble")]
+mod ble;
+#[cfg(feature = "boot-attestation")]
+mod boot;
+mod boot_wipe;
+mod button;
+mod console;
+#[cfg(feature = "credit")]
+mod credit;
+mod debug_lock;
+mod digest;
+#[cfg(feature = "display")]
+mod display;
+#[cfg(feature = "espnow")]
+mod espnow;
+mod fatal;
+#[cfg(feature = "feedback")]
+mod feedback;
+#[cfg(feature = "gps")]
+mod gps;
+mod hal;
+#[cfg(feature = "https-push")]
+mod https;
+#[cfg(feature = "keypad")]
+mod keypad;
+#[cfg(feature = "measurement")]
+mod measure;
+mod memory;
+#[cfg(feature = "nfc")]
+mod nfc;
+#[cfg(feature = "drbg")]
+mod noise;
+mod outbox;
+#[cfg(any(feature = "witness", feature = "relay"))]
+mod peer;
+#[cfg(feature = "receipt")]
+mod printer;
+#[cfg(feature = "rtc")]
+mod rtc;
+#[cfg(feature = "sensors")]
+mod sensors;
+mod serial;
+mod sha;
+mod signer;
+#[cfg(feature = "light-sleep")]
+mod sleep;
+#[cfg(feature = "gpio-snapshot")]
+mod snapshot;
+mod stack;
+#[cfg(feature = "binary-stream")]
+mod stream;
+#[cfg(feature = "tamper")]
+mod tamper;
+#[cfg(test)]
+mod target_test;
+#[cfg(feature = "touch")]
+mod touch;
+#[cfg(feature = "usage-stats")]
+mod usage;
+#[cfg(feature = "usb-hid")]
+mod usb_hid;
+#[cfg(feature = "usb-msc")]
+mod usb_msc;
+#[cfg(feature = "window-digest")]
+mod window;
+
+#[cfg(any(feature = "keypad", feature = "feedback"))]
+use esp_idf_hal::gpio::AnyOutputPin;
+use esp_idf_hal::gpio::{AnyIOPin, PinDriver};
+use esp_idf_hal::peripherals::Peripherals;
+use esp_idf_hal::uart::{self, UartDriver};
+use esp_idf_hal::units::Hertz;
+use esp_idf_svc::log::EspLogger;
+use log::{debug, info, warn};
+
+use icesickle_core::attestation::{self, Attestation, AttestationEvent};
+use icesickle_core::auth::{Token, TokenJar};
+#[cfg(feature = "batch")]
+use icesickle_core::batch::{self, Batch, Press};
+use icesickle_core::challenge::{PressChallenge, CHALLENGE_WINDOW_MS};
+use icesickle_core::context::{PressContext, CONTEXT_WINDOW_MS};
+use icesickle_core::cooldown::{Backoff, Cooldown, CooldownPolicy, CooldownResult, COOLDOWN_MS};
+#[cfg(feature = "cose")]
+use icesickle_core::cose;
+#[cfg(feature = "usb-hid")]
+use icesickle_core::ctaphid;
+#[cfg(feature = "cwt")]
+use icesickle_core::cwt;
+#[cfg(feature = "dsse")]
+use icesickle_core::dsse;
+#[cfg(feature = "dual-control")]
+use icesickle_core::dual::{DualControl, DualPress};
+#[cfg(feature = "drbg")]
+use icesickle_core::entropy::Drbg;
+use icesickle_core::entropy::HardwareRng;
+#[cfg(all(feature = "atecc", not(feature = "drbg")))]
+use icesickle_core::entropy::MixedEntropy;
+#[cfg(feature = "test-vectors")]
+use icesickle_core::entropy::{SeededEntropy, TEST_VECTOR_SEED};
+#[cfg(feature = "feedback")]
+use icesickle_core::feedback::Signal;
+#[cfg(feature = "gestures")]
+use icesickle_core::gesture::Gesture;
+use icesickle_core::hal::Timer;
+use icesickle_core::harden::{self, Flow};
+use icesickle_core::history::History;
+use icesickle_core::instrument::{self, Phase};
+#[cfg(feature = "keypad")]
+use icesickle_core::keypad::Challenge;
+#[cfg(feature = "openpgp")]
+use icesickle_core::openpgp;
+#[cfg(feature = "presence")]
+use icesickle_core::presence::Presence;
+use icesickle_core::protocol::{
+    self, AttestationRecord, DebugInterfaces, ErrorCode, Heartbeat, LinkErrors, Request, Response,
+    Status, Version,
+};
+use icesickle_core::redact::Redactor;
+use icesickle_core::session::Session;
+#[cfg(feature = "sshsig")]
+use icesickle_core::sshsig;
+use icesickle_core::state::{Event, Machine, State};
+use icesickle_core::telemetry::{self, Counter};
+#[cfg(any(feature = "witness", feature = "relay"))]
+use icesickle_core::witness::PeerMessage;
+use icesickle_core::IceSickleError;
+
+use crate::button::ButtonConfig;
+#[cfg(feature = "gestures")]
+use crate::button::Gestures;
+use crate::digest::DigestSession;
+#[cfg(not(feature = "test-vectors"))]
+use crate::hal::EspEntropy;
+#[cfg(feature = "i2c")]
+use crate::hal::EspI2c;
+use crate::hal::{esp_err, EspTimer};
+use crate::serial::SerialPort;
+use crate::signer::Job;
+#[cfg(feature = "signer-core")]
+use crate::signer::Signer;
+
+/// Attestation trigger buttons, each press signed with its own GPIO
+/// Default: GPIO0 (BOOT button on most ESP32-S3 devkits); `extra-buttons`
+/// adds external buttons on GPIO3 and GPIO46
+const BUTTONS: ButtonConfig = ButtonConfig {
+    pins: &[
+        0,
+        #[cfg(feature = "extra-buttons")]
+        3,
+        #[cfg(feature = "extra-buttons")]
+        46,
+    ],
+};
+
+/// With `dual-control`, the longest time between the two presses of a pair
+#[cfg(feature = "dual-control")]
+const DUAL_WINDOW_MS: u64 = 3_000;
+
+/// Source behind every key: the hardware TRNG (with `atecc`, hashed with a
+/// seed from the ATECC608; with `drbg`, conditioned by a DRBG that the
+/// ATECC608 and board noise reseed), or in `test-vectors` builds a public
+/// seed, making every signature reproducible and worthless
+#[cfg(not(any(feature = "test-vectors", feature = "atecc", feature = "drbg")))]
+type Entropy = EspEntropy;
+#[cfg(all(feature = "atecc", not(feature = "drbg")))]
+type Entropy = MixedEntropy<EspEntropy>;
+#[cfg(feature = "drbg")]
+type Entropy = Drbg<EspEntropy>;
+#[cfg(feature = "test-vectors")]
+type Entropy = SeededEntropy;
+
+/// Baud rate of the command protocol UART
+const SERIAL_BAUD: u32 = 115_200;
+
+/// CPU clock, for converting instrumented cycle counts to microseconds
+const CPU_MHZ: u32 = esp_idf_sys::CONFIG_ESP_DEFAULT_CPU_FREQ_MHZ;
+
+/// Platform logger behind the redaction layer; nothing logs around it
+static LOGGER: Redactor<EspLogger> = Redactor::new(EspLogger::new());
+
+/// Cooldown between attestations. With `cooldown-backoff`, each press it
+/// refuses doubles it, up to a minute, until 10 s pass without a press.
+/// Raise `burst` to let that many presses through back to back after a
+/// quiet spell
+const COOLDOWN_POLICY: CooldownPolicy = CooldownPolicy {
+    base_ms: COOLDOWN_MS,
+    backoff: if cfg!(feature = "cooldown-backoff") {
+        Some(Backoff {
+            max_ms: 60_000,
+            quiet_ms: 10_000,
+        })
+    } else {
+        None
+    },
+    burst: 1,
+};
+
+/// The cooldown's token bucket: when it was last empty (see `Cooldown`)
+///
+/// Kept in RTC slow memory, away from the main SRAM the rest of the state
+/// lives in. The bootloader reloads it on every reset except a deep-sleep
+/// wake, so it still restarts from zero each boot.
+#[link_section = ".rtc.data"]
+static COOLDOWN: Cooldown = Cooldown::with_policy(COOLDOWN_POLICY);
+
+/// Separate cooldown for witnessing, so a peer's attestation made at the
+/// same moment as ours is not refused, while a chattering peer still is
+#[cfg(feature = "witness")]
+#[link_section = ".rtc.data"]
+static WITNESS_COOLDOWN: Cooldown = Cooldown::new();
+
+fn main() {
+    // Initialize ESP-IDF
+    esp_idf_sys::link_patches();
+    // Remnants of the previous run go before anything else happens
+    let cleared = boot_wipe::clear();
+    log::set_logger(&LOGGER).expect("logger already installed");
+    // EspLogger filters by its own per-tag levels
+    log::set_max_level(log::LevelFilter::Trace);
+    debug!("Cleared {} bytes of .noinit", cleared);
+    fatal::install();
+
+    #[cfg(test)]
+    target_test::run();
+
+    // Errors escaping the event loop are fatal: scrub and reset
+    #[cfg(not(test))]
+    if let Err(e) = run() {
+        fatal::fatal(e);
+    }
+}
+
+fn run() -> icesickle_core::Result<()> {
+    info!("IceSickle v{} starting", env!("CARGO_PKG_VERSION"));
+
+    // A `FAULT` line for hosts if the previous run died
+    fatal::report();
+
+    // Close JTAG before anything secret exists (release builds)
+    let debug_state = debug_lock::lock();
+
+    let peripherals = Peripherals::take().map_err(esp_err)?;
+
+    // Initialize hardware RNG
+    #[cfg(not(any(feature = "test-vectors", feature = "atecc", feature = "drbg")))]
+    let rng = HardwareRng::from_source(EspEntropy)?;
+    #[cfg(all(feature = "atecc", not(feature = "drbg")))]
+    let rng = HardwareRng::from_source(MixedEntropy::new(EspEntropy))?;
+    #[cfg(feature = "drbg")]
+    let rng = HardwareRng::from_source(Drbg::new(EspEntropy))?;
+    #[cfg(not(feature = "test-vectors"))]
+    info!("Hardware RNG initialized");
+    #[cfg(feature = "test-vectors")]
+    let rng = HardwareRng::from_source(SeededEntropy::new(TEST_VECTOR_SEED))?;
+    #[cfg(feature = "test-vectors")]
+    warn!("TEST-VECTOR BUILD: keys come from a public seed and prove nothing");
+
+    // Board noise reseeding the key DRBG, before the first attestation
+    #[cfg(feature = "drbg")]
+    let mut noise = noise::Noise::new(rng.source(), EspTimer.now_ms())?;
+    #[cfg(feature = "drbg")]
+    info!(
+        "Key DRBG reseeded from GPIO{} noise and timer jitter",
+        noise::NOISE_GPIO
+    );
+
+    // Draw this boot's nonce now, so no press waits on it
+    let boot_nonce = icesickle_core::policy::ACTIVE.boot_nonce(|| attestation::boot_nonce(&rng));
+    if let Some(nonce) = boot_nonce {
+        info!("Boot nonce {}", attestation::hex_encode::<32>(&nonce));
+    }
+
+    // Hash for signing on the SHA peripheral, once it gives a known answer
+    #[cfg(feature = "hw-sha512")]
+    if sha::accelerate_sha512() {
+        info!("Signing SHA-512 on the hardware accelerator");
+    } else {
+        warn!("SHA accelerator failed its self-test - signing SHA-512 in software");
+    }
+
+    // Measure the running image once; every payload from here on signs it
+    #[cfg(feature = "measurement")]
+    {
+        let measurement = measure::running_image()?;
+        attestation::set_measurement(measurement);
+        info!(
+            "Firmware measurement {}",
+            attestation::hex_encode::<64>(&measurement)
+        );
+    }
+
+    // Presses sign on core 1 from here on, with an RNG of their own
+    #[cfg(feature = "signer-core")]
+    let signer = Signer::start()?;
+    #[cfg(feature = "signer-core")]
+    info!("Signing presses on core 1");
+
+    // Initialize the trigger buttons
+    let mut buttons = button::buttons(&BUTTONS)?;
+    info!("Buttons initialized on GPIO {:?}", BUTTONS.pins);
+    #[cfg(feature = "gestures")]
+    let mut gestures = Gestures::new(&BUTTONS);
+    #[cfg(feature = "dual-control")]
+    let mut dual = DualControl::new(DUAL_WINDOW_MS);
+
+    // Status LED, and the piezo with `buzzer`
+    #[cfg(feature = "buzzer")]
+    let buzzer =
+        Some(PinDriver::output(AnyOutputPin::from(peripherals.pins.gpio48)).map_err(esp_err)?);
+    #[cfg(all(feature = "feedback", not(feature = "buzzer")))]
+    let buzzer = None;
+    #[cfg(feature = "feedback")]
+    let mut status = feedback::Feedback::new(
+        PinDriver::output(AnyOutputPin::from(peripherals.pins.gpio38)).map_err(esp_err)?,
+        buzzer,
+    )?;
+    #[cfg(feature = "feedback")]
+    info!("Status LED on GPIO{}", feedback::LED_PIN);
+    #[cfg(feature = "buzzer")]
+    info!("Buzzer on GPIO{}", feedback::BUZZER_PIN);
+
+    // Sleep between events: the buttons, the timer and UART0 wake the chip
+    #[cfg(feature = "light-sleep")]
+    {
+        button::enable_wakeup(&buttons)?;
+        sleep::init()?;
+        info!("Light sleep enabled");
+    }
+
+    // Interlock/switch inputs whose levels are signed with each press
+    #[cfg(feature = "gpio-snapshot")]
+    let snapshot_pins = snapshot::SnapshotPins::new()?;
+    #[cfg(feature = "gpio-snapshot")]
+    info!("GPIO snapshot of {:?}", snapshot::SNAPSHOT_PINS);
+
+    // Command protocol on UART0 (the devkit's USB-UART bridge, GPIO43/44)
+    let uart = UartDriver::new(
+        peripherals.uart0,
+        peripherals.pins.gpio43,
+        peripherals.pins.gpio44,
+        Option::<AnyIOPin>::None,
+        Option::<AnyIOPin>::None,
+        &uart::config::Config::default().baudrate(Hertz(SERIAL_BAUD)),
+    )
+    .map_err(esp_err)?;
+    let mut port = SerialPort::new(uart);
+    info!("Command protocol v{} ready", protocol::PROTOCOL_VERSION);
+
+    // CTAPHID user-presence interface on the native USB port
+    #[cfg(feature = "usb-hid")]
+    let usb = usb_hid::UsbHid::new().map_err(esp_err)?;
+    #[cfg(feature = "usb-hid")]
+    let mut ctap = ctaphid::CtapHid::new();
+    #[cfg(feature = "usb-hid")]
+    info!("USB HID (CTAPHID and attestation push) interfaces ready");
+
+    // Read-only attestation volume on the native USB port
+    #[cfg(feature = "usb-msc")]
+    let mut msc = usb_msc::UsbMsc::new().map_err(esp_err)?;
+    #[cfg(feature = "usb-msc")]
+    info!("USB mass-storage volume ready");
+
+    // Settings partition, for the tamper latch and the Bluetooth stack
+    #[cfg(any(feature = "tamper", feature = "ble"))]
+    let nvs = esp_idf_svc::nvs::EspDefaultNvsPartition::take().map_err(esp_err)?;
+
+    // BLE GATT attestation service
+    #[cfg(feature = "ble")]
+    let ble = ble::Ble::new(peripherals.modem, nvs.clone()).map_err(esp_err)?;
+    #[cfg(feature = "ble")]
+    info!("BLE attestation service starting");
+
+    // ESP-NOW attestation broadcast, WiFi up for nothing else
+    #[cfg(feature = "espnow")]
+    let espnow = espnow::Sender::new(
+        peripherals.modem,
+        esp_idf_svc::eventloop::EspSystemEventLoop::take().map_err(esp_err)?,
+    )
+    .map_err(esp_err)?;
+    #[cfg(feature = "espnow")]
+    info!(
+        "ESP-NOW attestation broadcast on channel {}",
+        espnow::CHANNEL
+    );
+
+    // HTTPS push to the verifier endpoint, from its own thread
+    #[cfg(feature = "https-push")]
+    https::start(
+        peripherals.modem,
+        esp_idf_svc::eventloop::EspSystemEventLoop::take().map_err(esp_err)?,
+    )
+    .map_err(esp_err)?;
+    #[cfg(feature = "https-push")]
+    info!("HTTPS push to {}", https::URL);
+
+    // Coin/credit acceptor pulse input
+    #[cfg(feature = "credit")]
+    let mut credit = credit::Credit::new(
+        PinDriver::input(AnyIOPin::from(peripherals.pins.gpio6)).map_err(esp_err)?,
+    )?;
+    #[cfg(feature = "credit")]
+    info!("Credit pulse input on GPIO{}", credit::CREDIT_PIN);
+
+    // Capacitive touch pad, calibrated untouched
+    #[cfg(feature = "touch")]
+    let mut touch = touch::Touch::new()?;
+
+    // I2C bus shared by the sensors, the RTC, the ATECC608, the display and
+    // the NFC tag
+    #[cfg(feature = "i2c")]
+    let mut i2c = EspI2c::new(
+        peripherals.i2c0,
+        peripherals.pins.gpio1,
+        peripherals.pins.gpio2,
+    )?;
+    #[cfg(feature = "i2c")]
+    info!(
+        "I2C bus on GPIO{}/GPIO{}",
+        hal::I2C_SDA_PIN,
+        hal::I2C_SCL_PIN
+    );
+
+    // I2C sensors; drivers that do not answer their probe are dropped
+    #[cfg(feature = "sensors")]
+    let mut sensors = sensors::init(&mut i2c, EspTimer.now_ms());
+
+    // Battery-backed RTC time reference, on the same bus
+    #[cfg(feature = "rtc")]
+    let mut rtc = rtc::Rtc::new(&mut i2c);
+
+    // Second RNG mixed into every key, seeded before the first attestation
+    #[cfg(feature = "atecc")]
+    let mut atecc = atecc::Atecc::new(&mut i2c, rng.source(), EspTimer.now_ms());
+
+    // OLED showing each attestation as a QR code, on the same bus
+    #[cfg(feature = "display")]
+    let mut screen = display::Display::new(&mut i2c);
+
+    // NFC tag holding each attestation for a phone tap, on the same bus
+    #[cfg(feature = "nfc")]
+    let mut tag = nfc::Tag::new(&mut i2c);
+
+    // Procedure-code keypad: rows GPIO7-10, columns GPIO11-14
+    #[cfg(feature = "keypad")]
+    let mut keypad = keypad::Keypad::new(
+        [
+            PinDriver::output(AnyOutputPin::from(peripherals.pins.gpio7)).map_err(esp_err)?,
+            PinDriver::output(AnyOutputPin::from(peripherals.pins.gpio8)).map_err(esp_err)?,
+            PinDriver::output(AnyOutputPin::from(peripherals.pins.gpio9)).map_err(esp_err)?,
+            PinDriver::output(AnyOutputPin::from(peripherals.pins.gpio10)).map_err(esp_err)?,
+        ],
+        [
+            PinDriver::input(AnyIOPin::from(peripherals.pins.gpio11)).map_err(esp_err)?,
+            PinDriver::input(AnyIOPin::from(peripherals.pins.gpio12)).map_err(esp_err)?,
+            PinDriver::input(AnyIOPin::from(peripherals.pins.gpio13)).map_err(esp_err)?,
+            PinDriver::input(AnyIOPin::from(peripherals.pins.gpio14)).map_err(esp_err)?,
+        ],
+    )?;
+    #[cfg(feature = "keypad")]
+    info!(
+        "Keypad on GPIO{:?} (rows) and GPIO{:?} (columns)",
+        keypad::ROW_PINS,
+        keypad::COL_PINS
+    );
+
+    // GPS time reference: NMEA on UART2, PPS edges by interrupt
+    #[cfg(feature = "gps")]
+    let mut gps = gps::Gps::new(
+        UartDriver::new(
+            peripherals.uart2,
+            peripherals.pins.gpio21,
+            peripherals.pins.gpio16,
+            Option::<AnyIOPin>::None,
+            Option::<AnyIOPin>::None,
+            &uart::config::Config::default().baudrate(Hertz(gps::NMEA_BAUD)),
+        )
+        .map_err(esp_err)?,
+        PinDriver::input(AnyIOPin::from(peripherals.pins.gpio15)).map_err(esp_err)?,
+    )?;
+    #[cfg(feature = "gps")]
+    info!(
+        "GPS time on GPIO{} (PPS GPIO{})",
+        gps::NMEA_RX_PIN,
+        gps::PPS_PIN
+    );
+
+    // Cross-witness link to a second device, or relay chain link, on UART1
+    #[cfg(any(feature = "witness", feature = "relay"))]
+    let peer = peer::PeerLink::new(
+        UartDriver::new(
+            peripherals.uart1,
+            peripherals.pins.gpio17,
+            peripherals.pins.gpio18,
+            Option::<AnyIOPin>::None,
+            Option::<AnyIOPin>::None,
+            &uart::config::Config::default().baudrate(Hertz(SERIAL_BAUD)),
+        )
+        .map_err(esp_err)?,
+    );
+    #[cfg(any(feature = "witness", feature = "relay"))]
+    info!(
+        "Peer link on GPIO{}/GPIO{}",
+        peer::PEER_TX_PIN,
+        peer::PEER_RX_PIN
+    );
+
+    // Receipt printer on UART1; GPIO40 is claimed as RX but not connected
+    #[cfg(feature = "receipt")]
+    printer::init(
+        UartDriver::new(
+            peripherals.uart1,
+            peripherals.pins.gpio39,
+            peripherals.pins.gpio40,
+            Option::<AnyIOPin>::None,
+            Option::<AnyIOPin>::None,
+            &uart::config::Config::default().baudrate(Hertz(printer::PRINTER_BAUD)),
+        )
+        .map_err(esp_err)?,
+    );
+    #[cfg(feature = "receipt")]
+    info!("Receipt printer on GPIO{}", printer::PRINTER_TX_PIN);
+
+    // Binary attestation stream on UART1, transmit-only like the printer
+    #[cfg(feature = "binary-stream")]
+    stream::init(
+        UartDriver::new(
+            peripherals.uart1,
+            peripherals.pins.gpio39,
+            peripherals.pins.gpio40,
+            Option::<AnyIOPin>::None,
+            Option::<AnyIOPin>::None,
+            &uart::config::Config::default().baudrate(Hertz(SERIAL_BAUD)),
+        )
+        .map_err(esp_err)?,
+    );
+    #[cfg(feature = "binary-stream")]
+    info!("Attestation stream on GPIO{}", stream::STREAM_TX_PIN);
+
+    // Enclosure tamper loop and its persistent lockout latch
+    #[cfg(feature = "tamper")]
+    let (mut tamper, locked_out) = tamper::Tamper::new(
+        PinDriver::input(peripherals.pins.gpio4).map_err(esp_err)?,
+        PinDriver::input(peripherals.pins.gpio5).map_err(esp_err)?,
+        nvs,
+    )?;
+    #[cfg(feature = "tamper")]
+    info!("Tamper loop armed on GPIO{}", tamper::TAMPER_PIN);
+
+    // Set once the host has completed the HELLO exchange
+    let mut greeted = false;
+
+    // Optional encrypted command session (keys zeroized on drop)
+    let mut session: Option<Session> = None;
+
+    // Host data awaiting hash-then-sign approval
+    let mut digest = DigestSession::new();
+
+    // Verifier challenge for the next button press to sign
+    let mut press_challenge = PressChallenge::new();
+
+    // Application context for the next button press to sign
+    let mut press_context = PressContext::new();
+
+    // Authorization tokens for presses to spend, in RAM only
+    let mut tokens = TokenJar::new();
+
+    // Verifier challenge salting the next keypad entry
+    #[cfg(feature = "keypad")]
+    let mut challenge = Challenge::new();
+
+    // Presses awaiting one batch attestation
+    #[cfg(feature = "batch")]
+    let mut batch = Batch::new();
+
+    let mut last_heartbeat_ms = 0;
+
+    // Last host request; the device stays awake for a while after one
+    #[cfg(feature = "light-sleep")]
+    let mut last_request_ms = 0;
+
+    // Continuous-presence series while the button is held or tapped
+    #[cfg(feature = "presence")]
+    let mut presence = Presence::new();
+    // Button whose GPIO the series is signed with: the one last held
+    #[cfg(feature = "presence")]
+    let mut presence_gpio = BUTTONS.pins[0] as u8;
+
+    // Where each attestation goes after the console: the history (public
+    // fields of recent attestations, for GET_LAST_ATTESTATION and
+    // GET_HISTORY), the window's Merkle accumulator and the peer link
+    let mut outputs = Outputs {
+        history: History::new(),
+        #[cfg(feature = "window-digest")]
+        window: window::Window::new(EspTimer.now_ms()),
+        #[cfg(any(feature = "witness", feature = "relay"))]
+        peer,
+    };
+
+    // Attestation count at the start of this usage window
+    #[cfg(feature = "usage-stats")]
+    let mut usage = usage::Usage::new(EspTimer.now_ms());
+
+    // Signed record of this start-up, due shortly after boot
+    #[cfg(feature = "boot-attestation")]
+    let mut boot_report = boot::report();
+
+    // Explicit device state; every change is logged
+    let mut device = Machine::new();
+    #[cfg(feature = "tamper")]
+    if locked_out {
+        warn!(
+            "Tamper lockout latched - fit the provisioning jumper (GPIO{}) and reset",
+            tamper::PROVISION_PIN
+        );
+        device.handle(Event::Tampered);
+    }
+
+    // From here on a hung loop trips the task watchdog (see `fatal`)
+    fatal::watch()?;
+
+    // Main event loop
+    info!("Entering event loop - press button to generate attestation");
+
+    loop {
+        fatal::feed();
+
+        // Serve any pending host commands (never blocks)
+        let now_ms = EspTimer.now_ms();
+        #[cfg(feature = "gps")]
+        gps.poll(now_ms);
+        #[cfg(feature = "rtc")]
+        rtc.poll(&mut i2c, now_ms);
+        #[cfg(feature = "atecc")]
+        atecc.poll(&mut i2c, rng.source(), now_ms);
+        #[cfg(feature = "drbg")]
+        noise.poll(rng.source(), now_ms);
+        #[cfg(feature = "display")]
+        screen.poll(&mut i2c, now_ms);
+        #[cfg(feature = "nfc")]
+        tag.poll(&mut i2c, now_ms);
+        #[cfg(feature = "feedback")]
+        status.poll(now_ms);
+        #[cfg(feature = "receipt")]
+        printer::flush();
+        #[cfg(feature = "binary-stream")]
+        stream::flush();
+        #[cfg(feature = "usb-msc")]
+        msc.refresh(&outputs.history);
+        while let Some((seq, request)) = port.poll(now_ms) {
+            #[cfg(feature = "light-sleep")]
+            {
+                last_request_ms = now_ms;
+            }
+            let mut ctx = CommandContext {
+                rng: &rng,
+                now_ms,
+                history: &outputs.history,
+                digest: &mut digest,
+                press_challenge: &mut press_challenge,
+                press_context: &mut press_context,
+                tokens: &mut tokens,
+                #[cfg(feature = "keypad")]
+                challenge: &mut challenge,
+                #[cfg(feature = "batch")]
+                batch: &mut batch,
+                link: port.errors(),
+                debug: debug_state,
+                tampered: device.state() == State::Lockout,
+            };
+            let response = serve_request(request, &mut greeted, &mut session, &mut ctx);
+            if let Err(e) = port.send(seq, &response) {
+                warn!("Failed to send response: {}", e);
+                telemetry::record(Counter::SinkError);
+            }
+        }
+        if session.as_ref().is_some_and(|s| s.expired(now_ms)) {
+            info!("Command session idle - keys zeroized");
+            session = None;
+        }
+
+        // Serve CTAPHID traffic and keep any pending presence request alive
+        #[cfg(feature = "usb-hid")]
+        {
+            while let Some(report) = usb.poll_report() {
+                ctap.handle_report(&usb, &mut &rng, &report, now_ms);
+            }
+            ctap.tick(&usb, now_ms);
+            usb.flush_push();
+        }
+
+        // Notify the last attestation to a subscribed BLE central
+        #[cfg(feature = "ble")]
+        ble.flush();
+
+        // Send the last attestation over ESP-NOW
+        #[cfg(feature = "espnow")]
+        espnow.flush();
+
+        #[cfg(feature = "usb-hid")]
+        let presence_pending = ctap.presence_pending();
+        #[cfg(not(feature = "usb-hid"))]
+        let presence_pending = false;
+        let digest_armed = digest.is_armed(now_ms);
+        let challenge_armed = press_challenge.is_armed(now_ms);
+        device.handle(Event::Tick {
+            cooldown_ready: cooldown_remaining_ms() == 0,
+            armed: digest_armed || challenge_armed || presence_pending,
+        });
+
+        // Unsigned liveness report for collectors, once a host has said HELLO
+        if greeted && now_ms.saturating_sub(last_heartbeat_ms) >= protocol::HEARTBEAT_INTERVAL_MS {
+            last_heartbeat_ms = now_ms;
+            let entropy_ok = rng.is_healthy();
+            if !entropy_ok {
+                telemetry::record(Counter::RngHealth);
+                device.handle(Event::Failed(IceSickleError::Rng));
+                #[cfg(feature = "feedback")]
+                status.show(Signal::Fault(IceSickleError::Rng), now_ms);
+            }
+            let heartbeat = Heartbeat {
+                uptime_ms: now_ms,
+                cooldown_remaining_ms: cooldown_remaining_ms(),
+                queue_depth: u8::from(digest_armed)
+                    + u8::from(challenge_armed)
+                    + u8::from(presence_pending),
+                entropy_ok,
+            };
+            if let Err(e) = port.notify(&Response::Heartbeat(heartbeat)) {
+                warn!("Failed to send heartbeat: {}", e);
+            }
+        }
+
+        // Tamper: wipe first, latch, then sign the evidence
+        #[cfg(feature = "tamper")]
+        if tamper.poll_triggered() && device.state() != State::Lockout {
+            warn!("Tamper detected - wiping secrets and locking out");
+            // Nothing core 1 holds is signed now, and it is idle before
+            // the crypto workspace is scrubbed
+            #[cfg(feature = "signer-core")]
+            signer.cancel();
+            wipe_secrets(&mut session, &mut digest, &mut tokens);
+            #[cfg(feature = "keypad")]
+            keypad.clear();
+            device.handle(Event::Tampered);
+            tamper.latch();
+
+            let event = AttestationEvent::TamperDetected {
+                gpio: tamper::TAMPER_PIN as u8,
+            };
+            outputs.emit(&rng, event);
+        }
+
+        // Close the digest window: device-initiated, so no cooldown, but
+        // nothing is signed in lockout
+        #[cfg(feature = "window-digest")]
+        if device.state() != State::Lockout {
+            if let Some(event) = outputs.window.close_due(now_ms) {
+                info!("Digest window closed - signing window digest");
+                outputs.emit(&rng, event);
+            }
+        }
+
+        // Close the usage window: device-initiated like the window digest
+        #[cfg(feature = "usage-stats")]
+        if device.state() != State::Lockout {
+            if let Some(event) = usage.close_due(&rng, now_ms) {
+                info!("Usage window closed - signing usage count");
+                outputs.emit(&rng, event);
+            }
+        }
+
+        // Announce this boot: device-initiated like the window digest, so no
+        // cooldown, and dropped in lockout
+        #[cfg(feature = "boot-attestation")]
+        if let Some(event) = boot_report.due(now_ms) {
+            if device.state() == State::Lockout {
+                info!("Boot attestation skipped - device locked out");
+            } else {
+                info!("Signing boot attestation");
+                outputs.emit(&rng, event);
+            }
+        }
+
+        // Sign a finished credit burst. A burst stays pending until the
+        // cooldown allows it, so no payment goes unrecorded; the wait is
+        // not a press, so it does not back the cooldown off.
+        #[cfg(feature = "credit")]
+        if credit.poll(now_ms) && device.state() != State::Lockout && cooldown_remaining_ms() == 0 {
+            match COOLDOWN.gate(&EspTimer) {
+                Ok(()) => {
+                    let count = credit.take();
+                    info!("Credit burst of {} pulses - generating attestation", count);
+                    outputs.emit(&rng, AttestationEvent::CreditPulse { count });
+                }
+                Err(IceSickleError::Cooldown { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Sign the batch. Like a credit burst, it waits until the cooldown
+        // allows it, and the wait does not back the cooldown off.
+        #[cfg(feature = "batch")]
+        if batch.close_requested()
+            && device.state() != State::Lockout
+            && cooldown_remaining_ms() == 0
+        {
+            match COOLDOWN.gate(&EspTimer) {
+                Ok(()) => {
+                    info!("Signing batch of {} presses", batch.presses().len());
+                    if outputs.emit(&rng, batch.event()).is_some() {
+                        output_batch_leaves(batch.presses());
+                        batch.clear();
+                        #[cfg(feature = "feedback")]
+                        status.show(Signal::Success, EspTimer.now_ms());
+                    }
+                }
+                Err(IceSickleError::Cooldown { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Sign a sensor threshold crossing. Like a credit burst, an event
+        // waits until the cooldown allows it.
+        #[cfg(feature = "sensors")]
+        if sensors.poll(&mut i2c, now_ms)
+            && device.state() != State::Lockout
+            && cooldown_remaining_ms() == 0
+        {
+            match COOLDOWN.gate(&EspTimer) {
+                Ok(()) => {
+                    if let Some(event) = sensors.take() {
+                        info!("Sensor threshold crossed - generating attestation");
+                        outputs.emit(&rng, event);
+                    }
+                }
+                Err(IceSickleError::Cooldown { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Sign a touch on the pad. Like a button press, a touch the cooldown
+        // refuses is dropped and counted as suppressed.
+        #[cfg(feature = "touch")]
+        if let Some(event) = touch.poll(now_ms)? {
+            if device.state() != State::Lockout {
+                match COOLDOWN.gate(&EspTimer) {
+                    Ok(()) => {
+                        info!("Touch pad touched - generating attestation");
+                        outputs.emit(&rng, event);
+                    }
+                    Err(IceSickleError::Cooldown { remaining_ms }) => {
+                        refuse_cooldown(remaining_ms);
+                        #[cfg(feature = "feedback")]
+                        status.show(Signal::Cooldown { remaining_ms }, now_ms);
+                        #[cfg(feature = "display")]
+                        screen.countdown(now_ms + remaining_ms);
+                        info!("Cooldown active - touch ignored ({}ms)", remaining_ms);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        // Sign a completed keypad entry: only its hash under the verifier's
+        // challenge, never the code. Each challenge allows one attempt.
+        #[cfg(feature = "keypad")]
+        if keypad.poll(now_ms)? {
+            if device.state() != State::Lockout {
+                match challenge.take(now_ms) {
+                    None => warn!("Keypad entry discarded - no verifier challenge armed"),
+                    Some(salt) => match COOLDOWN.gate(&EspTimer) {
+                        Ok(()) => {
+                            info!("Keypad entry completed - generating attestation");
+                            let event = AttestationEvent::KeypadEntry {
+                                hash: keypad.finish(&salt),
+                            };
+                            outputs.emit(&rng, event);
+                        }
+                        Err(IceSickleError::Cooldown { remaining_ms }) => {
+                            refuse_cooldown(remaining_ms);
+                            #[cfg(feature = "feedback")]
+                            status.show(Signal::Cooldown { remaining_ms }, now_ms);
+                            #[cfg(feature = "display")]
+                            screen.countdown(now_ms + remaining_ms);
+                            info!(
+                                "Cooldown active - keypad entry discarded ({}ms)",
+                                remaining_ms
+                            );
+                        }
+                        Err(e) => return Err(e),
+                    },
+                }
+            }
+            keypad.clear();
+        }
+
+        // Witness a peer's attestation; originals only, never in lockout
+        #[cfg(feature = "witness")]
+        if let Some(PeerMessage::Attestation(record)) = outputs.peer.poll() {
+            if device.state() == State::Lockout
+                || !icesickle_core::witness::is_witnessable(&record.event)
+            {
+                debug!("Peer attestation not witnessed");
+            } else {
+                match WITNESS_COOLDOWN.gate(&EspTimer) {
+                    Ok(()) => {
+                        let event = AttestationEvent::Witness {
+                            peer: icesickle_core::witness::binding(&record),
+                        };
+                        outputs.emit(&rng, event);
+                    }
+                    Err(IceSickleError::Cooldown { remaining_ms }) => {
+                        telemetry::record(Counter::CooldownRejected);
+                        info!(
+                            "Witness cooldown active - peer attestation dropped ({}ms)",
+                            remaining_ms
+                        );
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        // Output an attestation relayed up the chain and pass it on, as
+        // signed by the device it came from
+        #[cfg(feature = "relay")]
+        if let Some(PeerMessage::Relayed { record, hops }) = outputs.peer.poll() {
+            output_relayed(&record, hops);
+            if let Err(e) = outputs.peer.forward(&record, hops) {
+                warn!("Failed to forward relayed attestation: {}", e);
+            }
+        }
+
+        // With gestures, a press counts once its gesture is known
+        #[cfg(feature = "gestures")]
+        let gesture = gestures.update(buttons.poll_edge()?, now_ms);
+        // With batching, a short press joins the batch and a long press
+        // closes it; neither is signed on its own
+        #[cfg(feature = "batch")]
+        let gesture = match gesture {
+            Some((gpio, Gesture::Press)) if device.state().accepts_press() => {
+                let press = Press {
+                    gpio,
+                    uptime_ms: now_ms,
+                };
+                if batch.add(press) {
+                    info!(
+                        "Press on GPIO{} batched ({} of {})",
+                        gpio,
+                        batch.presses().len(),
+                        batch::MAX_PRESSES
+                    );
+                    output_batch_press(batch.presses().len() - 1, &press);
+                } else {
+                    warn!("Batch full - long-press to sign it");
+                }
+                None
+            }
+            Some((_, Gesture::Hold { .. })) if device.state().accepts_press() => {
+                if !batch.request_close() {
+                    info!("Batch empty - nothing to sign");
+                }
+                None
+            }
+            other => other,
+        };
+        // With dual control, only the second press of a pair counts
+        #[cfg(feature = "dual-control")]
+        let dual_press = match buttons.poll_edge()? {
+            Some((gpio, true)) => {
+                let pair = dual.press(gpio, now_ms);
+                if pair.is_none() {
+                    info!(
+                        "Press on GPIO{} - waiting {} ms for a second button",
+                        gpio, DUAL_WINDOW_MS
+                    );
+                }
+                pair
+            }
+            _ => {
+                if let Some(gpio) = dual.tick(now_ms) {
+                    warn!("No second button after GPIO{} - press dropped", gpio);
+                }
+                None
+            }
+        };
+        #[cfg(feature = "gestures")]
+        let pressed = gesture.map(|(gpio, _)| gpio);
+        #[cfg(feature = "dual-control")]
+        let pressed = dual_press.map(|pair| pair.gpio_b);
+        #[cfg(not(any(feature = "gestures", feature = "dual-control")))]
+        let pressed = buttons.poll_pressed()?;
+        // Levels at the press itself, before anything else runs
+        #[cfg(feature = "gpio-snapshot")]
+        let snapshot = pressed.map(|_| snapshot_pins.capture());
+        // GPIO of the series when a presence interval comes due
+        #[cfg(feature = "presence")]
+        let presence_due = {
+            if let Some(gpio) = buttons.held() {
+                presence.touch(now_ms);
+                presence_gpio = gpio;
+            }
+            (pressed.is_none() && presence.take_due(now_ms)).then_some(presence_gpio)
+        };
+        #[cfg(not(feature = "presence"))]
+        let presence_due: Option<u8> = None;
+
+        // The press signed on this pass, if any
+        #[cfg(not(feature = "signer-core"))]
+        let mut signed = None;
+
+        if let Some(gpio) = pressed.or(presence_due) {
+            // Counts the checks passed on the way to signing (see `harden`);
+            // a glitch reported by any of them is fatal
+            let mut flow = Flow::new();
+            let state = device.state();
+            if !harden::decide(|| state.accepts_press())? {
+                warn!("Press ignored in state {:?}", state);
+            } else {
+                flow.step();
+                // Check cooldown before generating attestation
+                match COOLDOWN.gate(&EspTimer) {
+                    Ok(()) => {
+                        flow.step();
+                        if presence_due.is_some() {
+                            info!("Presence interval elapsed - generating attestation");
+                        } else if cfg!(feature = "dual-control") {
+                            info!("Second button on GPIO{} - generating attestation", gpio);
+                        } else {
+                            info!("Press on GPIO{} - generating attestation", gpio);
+                        }
+                        let signing = device.handle(Event::Pressed);
+                        #[cfg(feature = "feedback")]
+                        status.show(Signal::Signing, EspTimer.now_ms());
+                        if harden::decide(|| signing == State::Signing)? {
+                            flow.step();
+                        }
+                        flow.expect(3)?;
+
+                        // A hold, double press or pair is signed as itself;
+                        // only a plain press approves pending host data
+                        #[cfg(feature = "gestures")]
+                        let gesture_event = gesture
+                            .map(|(_, gesture)| gesture)
+                            .filter(|gesture| *gesture != Gesture::Press)
+                            .map(|gesture| gesture.event(gpio));
+                        #[cfg(feature = "dual-control")]
+                        let gesture_event = dual_press.map(DualPress::event);
+                        #[cfg(not(any(feature = "gestures", feature = "dual-control")))]
+                        let gesture_event: Option<AttestationEvent> = None;
+                        let event = match (presence_due, gesture_event) {
+                            (Some(_), _) => AttestationEvent::Presence { gpio },
+                            (None, Some(event)) => event,
+                            (None, None) => digest.take_event(gpio, EspTimer.now_ms()),
+                        };
+                        #[cfg(feature = "gpio-snapshot")]
+                        let event = match (event, snapshot) {
+                            (AttestationEvent::ButtonPress { gpio }, Some(snapshot)) => {
+                                snapshot.event(gpio)
+                            }
+                            (event, _) => event,
+                        };
+                        // A press answers the verifier's live challenge, signs
+                        // the host's live context and spends a token while
+                        // any remain; a presence interval does none of these
+                        let (challenge, context, token) = if presence_due.is_some() {
+                            (None, None, None)
+                        } else {
+                            let now_ms = EspTimer.now_ms();
+                            (
+                                press_challenge.take(now_ms),
+                                press_context.take(now_ms),
+                                tokens.take(),
+                            )
+                        };
+                        if token.is_some() {
+                            info!("Spending authorization token ({} left)", tokens.remaining());
+                        }
+                        let job = Job {
+                            event,
+                            challenge,
+                            token,
+                            context,
+                        };
+                        #[cfg(not(feature = "signer-core"))]
+                        {
+                            signed = Some(job.sign(&rng));
+                        }
+                        // Core 0 goes on; the result arrives on a later pass
+                        #[cfg(feature = "signer-core")]
+                        if let Err(e) = signer.submit(job) {
+                            warn!("Attestation failed: {}", e);
+                            device.handle(Event::Failed(e));
+                            #[cfg(feature = "feedback")]
+                            status.show(Signal::Fault(e), EspTimer.now_ms());
+                            #[cfg(feature = "usb-hid")]
+                            ctap.fail(&usb);
+                        }
+                    }
+                    Err(IceSickleError::Cooldown { remaining_ms }) => {
+                        device.handle(Event::Blocked);
+                        refuse_cooldown(remaining_ms);
+                        #[cfg(feature = "feedback")]
+                        status.show(Signal::Cooldown { remaining_ms }, EspTimer.now_ms());
+                        #[cfg(feature = "display")]
+                        screen.countdown(EspTimer.now_ms() + remaining_ms);
+                        info!(
+                            "Cooldown active - wait {}ms (cooldown {}ms)",
+                            remaining_ms,
+                            COOLDOWN.current_ms(&EspTimer)
+                        );
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        // With `signer-core`, whatever core 1 has finished since
+        #[cfg(feature = "signer-core")]
+        let signed = signer.poll();
+        match signed {
+            // Signed before a tamper or fault: never output
+            Some(_) if matches!(device.state(), State::Lockout | State::Fault(_)) => {
+                warn!("Dropping an attestation finished after lockout or fault");
+                #[cfg(feature = "usb-hid")]
+                ctap.fail(&usb);
+            }
+            Some(Ok(attestation)) => {
+                device.handle(Event::Signed);
+                let span = instrument::start(Phase::Output);
+                output_attestation(&attestation);
+                span.finish();
+                instrument::log_last(CPU_MHZ);
+
+                #[cfg(feature = "usb-hid")]
+                ctap.complete(&usb, &AttestationRecord::from(&attestation));
+                outputs.pass_on(&attestation);
+                device.handle(Event::Emitted);
+                #[cfg(feature = "feedback")]
+                status.show(Signal::Success, EspTimer.now_ms());
+            }
+            // Every crypto workspace slot was held: drop the press, not the
+            // device
+            Some(Err(IceSickleError::Busy)) => {
+                warn!("Press refused: {}", IceSickleError::Busy);
+                device.handle(Event::Refused);
+                #[cfg(feature = "usb-hid")]
+                ctap.fail(&usb);
+            }
+            Some(Err(e)) => {
+                warn!("Attestation failed: {}", e);
+                device.handle(Event::Failed(e));
+                #[cfg(feature = "feedback")]
+                status.show(Signal::Fault(e), EspTimer.now_ms());
+                #[cfg(feature = "usb-hid")]
+                ctap.fail(&usb);
+            }
+            None => {}
+        }
+
+        // Debounce. In presence mode a held button must not block the loop,
+        // gestures are made of releases, the two buttons of a pair may be
+        // held together, and with `signer-core` nothing blocks the loop;
+        // `poll_pressed` and `poll_edge` debounce the release on their own.
+        #[cfg(not(any(
+            feature = "presence",
+            feature = "gestures",
+            feature = "dual-control",
+            feature = "signer-core"
+        )))]
+        if let Some(gpio) = pressed {
+            // A long hold is not a hang: keep the watchdog fed
+            while buttons.is_held(gpio) {
+                fatal::feed();
+                EspTimer.delay_ms(10);
+            }
+            buttons.wait_release(gpio)?;
+        }
+
+        // Sleep until the next event when nothing needs the loop
+        #[cfg(feature = "light-sleep")]
+        let slept = device.state() == State::Idle
+            && session.is_none()
+            && buttons.held().is_none()
+            && now_ms.saturating_sub(last_request_ms) >= sleep::AWAKE_AFTER_REQUEST_MS
+            && port.is_idle(EspTimer.now_ms())
+            && sleep::light_sleep();
+        #[cfg(not(feature = "light-sleep"))]
+        let slept = false;
+
+        // Small delay to prevent busy-spinning
+        if !slept {
+            EspTimer.delay_ms(10);
+        }
+    }
+}
+
+/// Where this device's attestations go after the console
+struct Outputs {
+    history: History,
+    #[cfg(feature = "window-digest")]
+    window: window::Window,
+    #[cfg(any(feature = "witness", feature = "relay"))]
+    peer: peer::PeerLink<'static>,
+}
+
+impl Outputs {
+    /// Sign a device-initiated `event`, output it and pass it on, then wipe
+    /// what signing left on the stack; a failure is logged and dropped
+    ///
+    /// Every event source but presses comes through here. Nothing checks
+    /// the cooldown or lockout: the caller has.
+    fn emit(&mut self, rng: &HardwareRng<Entropy>, event: AttestationEvent) -> Option<Attestation> {
+        let emitted = match Attestation::create(rng, &EspTimer, event) {
+            Ok(attestation) => {
+                output_attestation(&attestation);
+                self.pass_on(&attestation);
+                Some(attestation)
+            }
+            Err(e) => {
+                warn!("Attestation failed: {}", e);
+                None
+            }
+        };
+        stack::scrub_dead();
+        memory::log();
+        emitted
+    }
+
+    /// Add an output attestation to the window digest (unless it closes
+    /// one), send it to the peer if the link carries it and keep it in the
+    /// history
+    fn pass_on(&mut self, attestation: &Attestation) {
+        #[cfg(feature = "window-digest")]
+        if !matches!(attestation.event(), AttestationEvent::WindowDigest { .. }) {
+            self.window.add(attestation);
+        }
+        let record = AttestationRecord::from(attestation);
+        #[cfg(any(feature = "witness", feature = "relay"))]
+        if peer::carries(&record.event) {
+            if let Err(e) = self.peer.send(&record) {
+                warn!("Failed to send attestation to peer: {}", e);
+            }
+        }
+        self.history.push(record);
+    }
+}
+
+/// Device state the command handlers read or update
+struct CommandContext<'a> {
+    rng: &'a HardwareRng<Entropy>,
+    now_ms: u64,
+    history: &'a History,
+    digest: &'a mut DigestSession,
+    press_challenge: &'a mut PressChallenge,
+    press_context: &'a mut PressContext,
+    tokens: &'a mut TokenJar,
+    #[cfg(feature = "keypad")]
+    challenge: &'a mut Challenge,
+    #[cfg(feature = "batch")]
+    batch: &'a mut Batch,
+    link: LinkErrors,
+    debug: DebugInterfaces,
+    /// In tamper lockout
+    tampered: bool,
+}
+
+/// Apply HELLO and session handling around a host request
+///
+/// Nothing but `Hello` is served until a host has negotiated versions. While
+/// a session is active only sealed requests (or a new handshake) are
+/// accepted, so a host cannot be downgraded to plaintext mid-session.
+fn serve_request(
+    request: Request,
+    greeted: &mut bool,
+    session: &mut Option<Session>,
+    ctx: &mut CommandContext,
+) -> Response {
+    match request {
+        Request::Hello {
+            protocol_version,
+            payload_version,
+        } => {
+            // A new HELLO starts over: any session from a previous host ends
+            *session = None;
+            let response = protocol::negotiate(protocol_version, payload_version);
+            *greeted = matches!(response, Response::Hello { .. });
+            response
+        }
+        _ if !*greeted => Response::Error(ErrorCode::HelloRequired),
+        Request::Handshake { host_public } => {
+            // Any previous session is dropped (and zeroized) here
+            *session = None;
+            match Session::respond(ctx.rng, &host_public, ctx.now_ms) {
+                Ok((new_session, device_public)) => {
+                    *session = Some(new_session);
+                    Response::Handshake { device_public }
+                }
+                Err(code) => Response::Error(code),
+            }
+        }
+        Request::EndSession => {
+            *session = None;
+            Response::Ok
+        }
+        Request::Sealed(sealed) => {
+            let Some(active) = session.as_mut() else {
+                return Response::Error(ErrorCode::NoSession);
+            };
+            let inner = match active.open(&sealed, ctx.now_ms) {
+                // Sessions do not nest
+                Ok(
+                    Request::Hello { .. }
+                    | Request::Handshake { .. }
+                    | Request::Sealed(_)
+                    | Request::EndSession,
+                ) => Response::Error(ErrorCode::Malformed),
+                Ok(inner) => handle_request(inner, ctx),
+                Err(code) => Response::Error(code),
+            };
+            match active.seal(&inner) {
+                Ok(sealed) => Response::Sealed(sealed),
+                Err(code) => Response::Error(code),
+            }
+        }
+        _ if session.is_some() => Response::Error(ErrorCode::SessionRequired),
+        // A token is a secret: never accepted in the clear
+        Request::LoadToken { .. } => Response::Error(ErrorCode::SessionRequired),
+        request => handle_request(request, ctx),
+    }
+}
+
+/// Answer a single (plaintext) host request
+fn handle_request(request: Request, ctx: &mut CommandContext) -> Response {
+    match request {
+        Request::GetStatus => Response::Status(Status {
+            uptime_ms: ctx.now_ms,
+            attestations: attestation::count(),
+            cooldown_remaining_ms: cooldown_remaining_ms(),
+            timing: cfg!(feature = "instrument").then(instrument::last),
+            link: ctx.link,
+            telemetry: telemetry::snapshot(),
+            debug: ctx.debug,
+            memory: memory::snapshot(),
+            cooldown_ms: COOLDOWN.current_ms(&EspTimer),
+            tampered: ctx.tampered,
+            cooldown_level: COOLDOWN.level(&EspTimer),
+        }),
+        Request::GetLastAttestation => match ctx.history.last() {
+            Some(record) => Response::Attestation(record.clone()),
+            None => Response::Error(ErrorCode::NotFound),
+        },
+        Request::GetHistory { counter } => match ctx.history.since(counter) {
+            Some(record) => Response::Attestation(record.clone()),
+            None => Response::Error(ErrorCode::NotFound),
+        },
+        #[cfg(feature = "keypad")]
+        Request::SetChallenge { challenge } => {
+            ctx.challenge.arm(challenge, ctx.now_ms);
+            Response::Ok
+        }
+        // Defined in the wire format; wired up as the features land
+        #[cfg(not(feature = "keypad"))]
+        Request::SetChallenge { .. } => Response::Error(ErrorCode::Unsupported),
+        Request::LoadToken { token } => match Token::parse(&token) {
+            None => Response::Error(ErrorCode::Malformed),
+            Some(token) if ctx.tokens.load(token) => {
+                info!(
+                    "Authorization token loaded ({} held)",
+                    ctx.tokens.remaining()
+                );
+                Response::Ok
+            }
+            Some(_) => Response::Error(ErrorCode::InvalidState),
+        },
+        // No provisioning mode exists yet, so configuration is always locked
+        Request::SetConfig { .. } => Response::Error(ErrorCode::Locked),
+        Request::DigestBegin => {
+            ctx.digest.begin();
+            Response::Ok
+        }
+        Request::DigestUpdate { chunk } => {
+            if ctx.digest.update(&chunk) {
+                Response::Ok
+            } else {
+                Response::Error(ErrorCode::InvalidState)
+            }
+        }
+        Request::DigestFinish => match ctx.digest.finish(ctx.now_ms) {
+            Some((sha256, len)) => Response::Digest { sha256, len },
+            None => Response::Error(ErrorCode::InvalidState),
+        },
+        Request::ArmChallenge { challenge } => {
+            ctx.press_challenge.arm(challenge, ctx.now_ms);
+            info!(
+                "Verifier challenge armed - press button within {}s",
+                CHALLENGE_WINDOW_MS / 1_000
+            );
+            Response::Ok
+        }
+        Request::GetVersion => Response::Version(Version::new(env!("CARGO_PKG_VERSION"))),
+        Request::SetContext { context } => {
+            ctx.press_context.arm(context, ctx.now_ms);
+            info!(
+                "Application context armed - press button within {}s",
+                CONTEXT_WINDOW_MS / 1_000
+            );
+            Response::Ok
+        }
+        #[cfg(feature = "batch")]
+        Request::SignBatch => {
+            if ctx.batch.request_close() {
+                Response::Ok
+            } else {
+                Response::Error(ErrorCode::NotFound)
+            }
+        }
+        #[cfg(not(feature = "batch"))]
+        Request::SignBatch => Response::Error(ErrorCode::Unsupported),
+        // HELLO and session control are handled by `serve_request`
+        Request::Hello { .. }
+        | Request::Handshake { .. }
+        | Request::Sealed(_)
+        | Request::EndSession => Response::Error(ErrorCode::Malformed),
+    }
+}
+
+/// Drop every volatile secret the device holds
+///
+/// Anything new that holds secrets (pre-generated keys, say) must be cleared
+/// here as well. With `signer-core`, cancel the signer first
+/// (`Signer::cancel`): the workspace scrub must not race a seed being
+/// derived on core 1.
+#[cfg(feature = "tamper")]
+fn wipe_secrets(session: &mut Option<Session>, digest: &mut DigestSession, tokens: &mut TokenJar) {
+    // Session keys zeroize on drop
+    *session = None;
+    *digest = DigestSession::new();
+    tokens.clear();
+    icesickle_core::scrub::CRYPTO_WORKSPACE.scrub();
+    stack::scrub_dead();
+}
+
+/// Milliseconds until the cooldown allows another attestation (0 = ready)
+fn cooldown_remaining_ms() -> u64 {
+    match COOLDOWN.check(&EspTimer) {
+        CooldownResult::Ready => 0,
+        CooldownResult::Wait { remaining_ms } => remaining_ms,
+    }
+}
+
+/// Count and report a press the cooldown refused: on the console, where
+/// attestations go, as `{"cooldown_remaining_ms":<ms>}`
+fn refuse_cooldown(remaining_ms: u64) {
+    use std::fmt::Write;
+
+    telemetry::record(Counter::CooldownRejected);
+    attestation::record_suppressed();
+    // On the stack, as the `minimal` output path needs: the longest u64 fits
+    let mut line = heapless::String::<48>::new();
+    let _ = writeln!(line, "{{\"cooldown_remaining_ms\":{}}}", remaining_ms);
+    console::write(line.as_bytes());
+}
+
+/// Output the attestation on the console, stream it with `binary-stream`,
+/// push it over USB HID with `usb-hid`, BLE with `ble`, ESP-NOW with
+/// `espnow`, HTTPS with `https-push` and NFC with `nfc`, and print or show
+/// it with `receipt` and `display`
+#[cfg(not(feature = "minimal"))]
+fn output_attestation(attestation: &Attestation) {
+    #[cfg(any(
+        feature = "openpgp",
+        feature = "sshsig",
+        feature = "dsse",
+        feature = "cwt",
+        feature = "cose"
+    ))]
+    use std::fmt::Write;
+
+    debug!("=== ATTESTATION ===");
+    debug!("Event: {:?}", attestation.event());
+    debug!("Timestamp: {}", attestation.timestamp_ms());
+    if let Some(wall) = attestation.wall_clock() {
+        debug!("UTC: {} ({:?})", wall.unix_s, wall.source);
+    }
+    if let Some(challenge) = attestation.challenge() {
+        debug!("Challenge: {}", attestation::hex_encode::<64>(&challenge));
+    }
+    if let Some(token) = attestation.token() {
+        debug!(
+            "Token: {}",
+            attestation::hex_encode::<64>(&token.to_bytes())
+        );
+    }
+    if let Some(nonce) = attestation.boot_nonce() {
+        debug!("Boot nonce: {}", attestation::hex_encode::<32>(&nonce));
+    }
+    if let Some(context) = attestation.context() {
+        debug!("Context: {}", attestation::hex_encode::<64>(context));
+    }
+    debug!("Algorithm: {}", attestation.algorithm().name());
+    debug!("Public Key: {}", attestation.public_key_hex());
+    debug!("DID: {}", attestation.public_key_did());
+    debug!("Signature: {}", attestation.signature_hex());
+
+    // Machine-readable output: the JSON record, every signed field by name,
+    // so `icesickle-verify` checks it
+    let mut out = String::new();
+    out.push_str(&AttestationRecord::from(attestation).json());
+
+    // Copies for existing tools (key, then signature), then the signed bytes
+    #[cfg(feature = "openpgp")]
+    if let Some(armored) = openpgp::render(attestation) {
+        out.push_str(&armored.public_key);
+        out.push_str(&armored.signature);
+    }
+    #[cfg(feature = "sshsig")]
+    if let Some(signed) = sshsig::render(attestation) {
+        out.push_str(&signed.public_key);
+        out.push_str(&signed.signature);
+    }
+    #[cfg(feature = "dsse")]
+    if let Some(enveloped) = dsse::render(attestation) {
+        out.push_str(&enveloped.public_key);
+        out.push_str(&enveloped.envelope);
+    }
+    #[cfg(feature = "cwt")]
+    if let Some(token) = cwt::render(attestation) {
+        let _ = writeln!(out, "CWT {}", icesickle_core::companion::base64(&token));
+    }
+    #[cfg(feature = "cose")]
+    if let Some(sign1) = cose::render(attestation) {
+        let _ = writeln!(out, "COSE {}", icesickle_core::companion::base64(&sign1));
+    }
+    #[cfg(any(feature = "openpgp", feature = "sshsig", feature = "dsse"))]
+    let _ = writeln!(
+        out,
+        "PAYLOAD {}",
+        icesickle_core::companion::base64(&attestation.payload_bytes())
+    );
+    console::write(out.as_bytes());
+    #[cfg(feature = "binary-stream")]
+    stream::push(attestation);
+
+    #[cfg(feature = "usb-hid")]
+    usb_hid::push(attestation);
+    #[cfg(feature = "ble")]
+    ble::push(attestation);
+    #[cfg(feature = "espnow")]
+    espnow::push(attestation);
+    #[cfg(feature = "https-push")]
+    https::push(attestation);
+    #[cfg(feature = "nfc")]
+    nfc::push(attestation);
+
+    #[cfg(feature = "receipt")]
+    printer::print(attestation);
+    #[cfg(feature = "display")]
+    display::show(attestation);
+}
+
+/// Output the attestation as one fixed-format line (`minimal` profile), and
+/// stream it with `binary-stream`
+#[cfg(feature = "minimal")]
+fn output_attestation(attestation: &Attestation) {
+    console::write(attestation.fixed_line().as_bytes());
+    #[cfg(feature = "binary-stream")]
+    stream::push(attestation);
+}
+
+/// Output a press as it joins the batch: `BATCH-PRESS <index> <gpio>
+/// <uptime_ms> <leaf>`
+#[cfg(feature = "batch")]
+fn output_batch_press(index: usize, press: &Press) {
+    console::write(
+        format!(
+            "BATCH-PRESS {} {} {} {}\n",
+            index,
+            press.gpio,
+            press.uptime_ms,
+            attestation::hex_encode::<64>(&press.leaf())
+        )
+        .as_bytes(),
+    );
+}
+
+/// Output the leaves a batch attestation's root covers, in tree order:
+/// `BATCH-LEAF <index> <leaf>`, one per line
+#[cfg(feature = "batch")]
+fn output_batch_leaves(presses: &[Press]) {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    for (index, press) in presses.iter().enumerate() {
+        let leaf = attestation::hex_encode::<64>(&press.leaf());
+        let _ = writeln!(out, "BATCH-LEAF {} {}", index, leaf);
+    }
+    console::write(out.as_bytes());
+}
+
+/// Output an attestation relayed from another device: `RELAY <hops> `, then
+/// its fixed-format line
+#[cfg(feature = "relay")]
+fn output_relayed(record: &AttestationRecord, hops: u8) {
+    use std::fmt::Write;
+
+    // "RELAY 255 " at most
+    let mut prefix = heapless::String::<10>::new();
+    let _ = write!(prefix, "RELAY {} ", hops);
+    console::write_parts(&[prefix.as_bytes(), record.fixed_line().as_bytes()]);
+}
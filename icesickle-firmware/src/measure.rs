@@ -0,0 +1,26 @@
+//! Firmware measurement (feature `measurement`)
+//!
+//! The SHA-256 of the running app image, as ESP-IDF computes it over the
+//! partition: the digest esptool appends to the image, which
+//! `esptool.py image_info` prints as the validation hash. This is the
+//! image's own hash, not the ELF's that the boot attestation carries, so a
+//! verifier can compute it from the `.bin` it flashed.
+
+use esp_idf_sys::{esp, esp_ota_get_running_partition, esp_partition_get_sha256};
+
+use crate::hal::esp_err;
+
+/// Hash the running image; reads the whole partition, so call once at boot
+pub fn running_image() -> icesickle_core::Result<[u8; 32]> {
+    let mut sha256 = [0u8; 32];
+    // SAFETY: the running partition's descriptor is static for the whole
+    // run, and the digest buffer is 32 bytes
+    unsafe {
+        esp!(esp_partition_get_sha256(
+            esp_ota_get_running_partition(),
+            sha256.as_mut_ptr()
+        ))
+    }
+    .map_err(esp_err)?;
+    Ok(sha256)
+}
@@ -0,0 +1,61 @@
+//! Stack and heap high-water marks
+//!
+//! `GetStatus` reports, and a debug line after every attestation logs, how
+//! close each task has come to the end of its stack and how low free heap
+//! has run since boot. The marks size the stacks for features that sign or
+//! draw more than today's, and a mark that keeps falling on a device that
+//! has been up for weeks points at a leak.
+//!
+//! FreeRTOS fills every stack with a pattern when the task is created, so
+//! the untouched depth can be read back at any time; ESP-IDF keeps the
+//! heap's low watermark itself. Tasks are looked up by their ESP-IDF names,
+//! and one this build does not run (TinyUSB without a USB feature, the
+//! signer without `signer-core`) is left out. The signing task's mark is
+//! also checked after each attestation (`stack::MIN_HEADROOM_BYTES`).
+
+use core::ffi::CStr;
+
+use icesickle_core::protocol::{Memory, StackMark, Task, MAX_TASKS};
+use log::debug;
+
+/// Reported tasks and their ESP-IDF names
+const TASKS: [(Task, &CStr); MAX_TASKS] = [
+    (Task::Main, c"main"),
+    (Task::Timer, c"esp_timer"),
+    (Task::Ipc0, c"ipc0"),
+    (Task::Ipc1, c"ipc1"),
+    (Task::Usb, c"TinyUSB"),
+    (Task::Signer, c"signer"),
+];
+
+/// Current marks
+pub fn snapshot() -> Memory {
+    let mut memory = Memory {
+        heap_free: unsafe { esp_idf_sys::esp_get_free_heap_size() },
+        heap_min_free: unsafe { esp_idf_sys::esp_get_minimum_free_heap_size() },
+        ..Memory::default()
+    };
+    for (task, name) in TASKS {
+        let handle = unsafe { esp_idf_sys::xTaskGetHandle(name.as_ptr()) };
+        if handle.is_null() {
+            continue;
+        }
+        // ESP-IDF stacks are byte-addressed, so the mark is in bytes
+        let headroom = unsafe { esp_idf_sys::uxTaskGetStackHighWaterMark(handle) };
+        // One slot per task
+        let _ = memory.stacks.push(StackMark { task, headroom });
+    }
+    memory
+}
+
+/// Log the marks at debug level
+pub fn log() {
+    if !log::log_enabled!(log::Level::Debug) {
+        return;
+    }
+    let memory = snapshot();
+    debug!(
+        "Heap free {} (low {}), stack headroom {:?}",
+        memory.heap_free, memory.heap_min_free, memory.stacks
+    );
+}
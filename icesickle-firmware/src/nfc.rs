@@ -0,0 +1,77 @@
+//! NFC tag output (feature `nfc`)
+//!
+//! An ST25DV dynamic NFC tag on the shared I2C bus holds each attestation
+//! as `icesickle_core::nfc` lays it out: one NDEF record of the push
+//! message, for a phone to read with a tap. [`push`] encodes the message
+//! from the output path, which has no bus; [`Tag::poll`], called from the
+//! event loop, writes it a row at a time and clears the tag [`EXPIRY_MS`]
+//! after it went up. A newer attestation replaces one still being written.
+//!
+//! The tag is one more output: a missing or failing tag is logged and
+//! counted as a sink error, and every other output carries on.
+
+use std::sync::Mutex;
+
+use icesickle_core::attestation::Attestation;
+use icesickle_core::nfc::{self, St25dv};
+use icesickle_core::telemetry::{self, Counter};
+use icesickle_core::transport::{self, Message};
+use log::{info, warn};
+
+use crate::hal::EspI2c;
+
+/// How long an attestation stays on the tag. `SetConfig` cannot change it
+/// until a provisioning mode exists, so it is set here
+pub const EXPIRY_MS: u64 = nfc::DEFAULT_EXPIRY_MS;
+
+/// Message encoded by [`push`] and not yet handed to the tag
+static PENDING: Mutex<Option<Message>> = Mutex::new(None);
+
+/// The tag found at boot, if any
+pub struct Tag {
+    tag: Option<St25dv>,
+    /// Last write failed; logged once until a write succeeds
+    failing: bool,
+}
+
+impl Tag {
+    /// Probe `bus` for the tag; it is cleared from the first poll
+    pub fn new(bus: &mut EspI2c<'_>) -> Self {
+        let tag = St25dv::new(nfc::DEFAULT_ADDRESS, EXPIRY_MS);
+        let tag = tag.probe(bus).then_some(tag);
+        match tag {
+            Some(_) => info!("ST25DV NFC tag at 0x{:02x}", nfc::DEFAULT_ADDRESS),
+            None => warn!("No NFC tag found; attestations will not be written to it"),
+        }
+        Self {
+            tag,
+            failing: false,
+        }
+    }
+
+    /// Write the next row of a pending attestation, or clear an expired
+    /// one (never blocks for long)
+    pub fn poll(&mut self, bus: &mut EspI2c<'_>, now_ms: u64) {
+        let Some(tag) = self.tag.as_mut() else {
+            return;
+        };
+        if let Some(message) = PENDING.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            tag.show(&message);
+        }
+        match tag.poll(bus, now_ms) {
+            Ok(()) => self.failing = false,
+            Err(e) => {
+                if !self.failing {
+                    warn!("NFC tag write failed: {}", e);
+                    telemetry::record(Counter::SinkError);
+                }
+                self.failing = true;
+            }
+        }
+    }
+}
+
+/// Encode `attestation` for the next [`Tag::poll`] to write
+pub fn push(attestation: &Attestation) {
+    *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = Some(transport::message(attestation));
+}
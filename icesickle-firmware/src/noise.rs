@@ -0,0 +1,107 @@
+//! Secondary entropy for the key DRBG (feature `drbg`)
+//!
+//! Two sources besides the TRNG, extracted into `icesickle_core::entropy::Drbg`
+//! at boot, before the first attestation, and every [`RESEED_MS`] after:
+//!
+//! - ADC noise: GPIO9 ([`NOISE_GPIO`]), left floating, picks up thermal and
+//!   coupled noise, so the low bits of its conversions wander.
+//! - Timer jitter: the CPU cycles a 1 µs ROM busy-wait takes vary with
+//!   cache, bus and interrupt timing.
+//!
+//! Neither is credited with any entropy, and neither needs to be trusted:
+//! keys stay as strong as the TRNG whatever they contribute, and no longer
+//! depend on the TRNG alone while either varies. Leave the pin unconnected.
+
+use esp_idf_sys::{self as sys, esp};
+use icesickle_core::entropy::Reseed;
+use icesickle_core::Result;
+use log::{debug, warn};
+
+use crate::hal::esp_err;
+
+/// Floating pin sampled for ADC noise (ADC1 channel 8)
+pub const NOISE_GPIO: i32 = 9;
+
+const NOISE_CHANNEL: sys::adc_channel_t = sys::adc_channel_t_ADC_CHANNEL_8;
+
+/// Interval between reseeds
+const RESEED_MS: u64 = 60_000;
+
+/// ADC reads and jitter measurements per reseed
+const SAMPLES: usize = 64;
+
+/// The ADC unit and the reseed schedule
+pub struct Noise {
+    adc: sys::adc_oneshot_unit_handle_t,
+    next_ms: u64,
+}
+
+impl Noise {
+    /// Claim ADC1 and reseed `pool` for the first time
+    pub fn new(pool: &impl Reseed, now_ms: u64) -> Result<Self> {
+        let mut adc = core::ptr::null_mut();
+        let unit = sys::adc_oneshot_unit_init_cfg_t {
+            unit_id: sys::adc_unit_t_ADC_UNIT_1,
+            ..Default::default()
+        };
+        let channel = sys::adc_oneshot_chan_cfg_t {
+            atten: sys::adc_atten_t_ADC_ATTEN_DB_12,
+            bitwidth: sys::adc_bitwidth_t_ADC_BITWIDTH_DEFAULT,
+        };
+        // SAFETY: plain ESP-IDF oneshot ADC calls; nothing else in the
+        // firmware uses ADC1
+        unsafe {
+            esp!(sys::adc_oneshot_new_unit(&unit, &mut adc)).map_err(esp_err)?;
+            esp!(sys::adc_oneshot_config_channel(
+                adc,
+                NOISE_CHANNEL,
+                &channel
+            ))
+            .map_err(esp_err)?;
+        }
+        let mut noise = Self {
+            adc,
+            next_ms: now_ms,
+        };
+        noise.poll(pool, now_ms);
+        Ok(noise)
+    }
+
+    /// Reseed `pool` when one is due
+    pub fn poll(&mut self, pool: &impl Reseed, now_ms: u64) {
+        if now_ms < self.next_ms {
+            return;
+        }
+        self.next_ms = now_ms + RESEED_MS;
+
+        let mut samples = [0u8; 2 * SAMPLES];
+        let mut unread = 0;
+        for pair in samples.chunks_exact_mut(2) {
+            let mut raw = 0;
+            // SAFETY: `raw` outlives the call, which only writes through it
+            match esp!(unsafe { sys::adc_oneshot_read(self.adc, NOISE_CHANNEL, &mut raw) }) {
+                Ok(()) => pair[0] = raw as u8,
+                Err(_) => unread += 1,
+            }
+            let start = cycle_count();
+            // SAFETY: a ROM busy-wait, with no side effects
+            unsafe { sys::esp_rom_delay_us(1) };
+            pair[1] = cycle_count().wrapping_sub(start) as u8;
+        }
+        if unread == SAMPLES {
+            warn!("ADC noise unreadable; DRBG reseeded from timer jitter alone");
+        }
+        pool.reseed(&samples);
+        debug!("DRBG reseeded from ADC noise and timer jitter");
+    }
+}
+
+/// Read the Xtensa CCOUNT register
+fn cycle_count() -> u32 {
+    let count: u32;
+    // SAFETY: reads a special register, touching no memory
+    unsafe {
+        core::arch::asm!("rsr.ccount {0}", out(reg) count, options(nomem, nostack));
+    }
+    count
+}
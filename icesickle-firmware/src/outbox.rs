@@ -0,0 +1,165 @@
+//! Bounded output buffering with backpressure
+//!
+//! Encoded frames wait here until the sink (UART TX FIFO) accepts them, so a
+//! slow or stalled host never blocks the main loop. Memory use is fixed:
+//!
+//! - Below the high watermark every frame is accepted.
+//! - Above it, [`Class::Droppable`] frames (heartbeats) are refused and
+//!   counted, keeping the remaining space for replies.
+//! - A [`Class::Reliable`] frame that does not fit at all is refused with an
+//!   error the caller must handle; it is never dropped silently.
+//!
+//! Callers should also stop taking new requests while the outbox is above
+//! the watermark, which pushes backpressure onto the host: its requests sit
+//! unanswered in the receive buffer until it drains our output.
+
+/// How a frame may be treated under backpressure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    /// Replies and anything carrying a signature
+    Reliable,
+    /// Periodic status that the next report supersedes
+    Droppable,
+}
+
+/// Reasons a frame was not queued
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refused {
+    /// Droppable frame skipped above the high watermark
+    Congested,
+    /// Not enough space left for the frame
+    Full,
+}
+
+impl core::fmt::Display for Refused {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Refused::Congested => f.write_str("output congested"),
+            Refused::Full => f.write_str("output buffer full"),
+        }
+    }
+}
+
+impl std::error::Error for Refused {}
+
+/// Byte FIFO of encoded frames with a high watermark
+pub struct Outbox<const N: usize> {
+    buf: heapless::Deque<u8, N>,
+    high_watermark: usize,
+}
+
+impl<const N: usize> Outbox<N> {
+    pub const fn new(high_watermark: usize) -> Self {
+        Self {
+            buf: heapless::Deque::new(),
+            high_watermark,
+        }
+    }
+
+    /// True when only reliable frames are being accepted
+    pub fn is_congested(&self) -> bool {
+        self.buf.len() >= self.high_watermark
+    }
+
+    /// True when everything queued has been handed to the sink
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Queue a whole frame, or nothing
+    pub fn push(&mut self, frame: &[u8], class: Class) -> Result<(), Refused> {
+        self.push_parts(&[frame], class)
+    }
+
+    /// Queue a frame given as consecutive parts: all of them, or nothing
+    pub fn push_parts(&mut self, parts: &[&[u8]], class: Class) -> Result<(), Refused> {
+        if class == Class::Droppable && self.is_congested() {
+            return Err(Refused::Congested);
+        }
+        let len: usize = parts.iter().map(|part| part.len()).sum();
+        if N - self.buf.len() < len {
+            return Err(Refused::Full);
+        }
+        for &b in parts.iter().flat_map(|part| part.iter()) {
+            // Space was checked above
+            let _ = self.buf.push_back(b);
+        }
+        Ok(())
+    }
+
+    /// Hand queued bytes to `write`, which returns how many it accepted
+    ///
+    /// Stops as soon as the sink accepts less than it was offered.
+    pub fn drain(&mut self, mut write: impl FnMut(&[u8]) -> usize) {
+        while !self.buf.is_empty() {
+            let (front, _) = self.buf.as_slices();
+            let offered = front.len();
+            let accepted = write(front).min(offered);
+            for _ in 0..accepted {
+                self.buf.pop_front();
+            }
+            if accepted < offered {
+                break;
+            }
+        }
+    }
+}
+
+// Run on the device by `cargo test` (see `target_test`)
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub fn test_droppable_frames_refused_above_watermark() {
+        let mut outbox = Outbox::<16>::new(8);
+        assert_eq!(outbox.push(&[1; 8], Class::Droppable), Ok(()));
+        assert_eq!(
+            outbox.push(&[2; 4], Class::Droppable),
+            Err(Refused::Congested)
+        );
+        assert_eq!(outbox.push(&[3; 8], Class::Reliable), Ok(()));
+        assert_eq!(outbox.push(&[4; 1], Class::Reliable), Err(Refused::Full));
+    }
+
+    pub fn test_parts_queued_whole_or_not_at_all() {
+        let mut outbox = Outbox::<8>::new(8);
+        assert_eq!(
+            outbox.push_parts(&[&[1; 4], &[2; 5]], Class::Reliable),
+            Err(Refused::Full)
+        );
+        assert!(outbox.is_empty());
+        assert_eq!(
+            outbox.push_parts(&[&[1; 3], &[2; 5]], Class::Reliable),
+            Ok(())
+        );
+
+        let mut sent = Vec::new();
+        outbox.drain(|bytes| {
+            sent.extend_from_slice(bytes);
+            bytes.len()
+        });
+        assert_eq!(sent, [1, 1, 1, 2, 2, 2, 2, 2]);
+    }
+
+    pub fn test_drain_stops_when_sink_stalls() {
+        let mut outbox = Outbox::<16>::new(12);
+        outbox.push(&[1, 2, 3, 4, 5, 6], Class::Reliable).unwrap();
+
+        let mut sent = Vec::new();
+        outbox.drain(|bytes| {
+            let n = bytes.len().min(4);
+            sent.extend_from_slice(&bytes[..n]);
+            n
+        });
+        assert_eq!(sent, [1, 2, 3, 4]);
+        assert!(!outbox.is_empty());
+
+        outbox.drain(|bytes| {
+            sent.extend_from_slice(bytes);
+            bytes.len()
+        });
+        assert_eq!(sent, [1, 2, 3, 4, 5, 6]);
+        assert!(!outbox.is_congested());
+        assert!(outbox.is_empty());
+    }
+}
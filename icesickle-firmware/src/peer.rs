@@ -0,0 +1,146 @@
+//! Peer link for cross-witnessing and relay chains (features `witness`,
+//! `relay`)
+//!
+//! A second UART, wired TX-to-RX (and ground) to another IceSickle. Frames
+//! use the command protocol's codec and carry `witness::PeerMessage`; see
+//! `icesickle_core::witness` and `icesickle_core::relay` for what is
+//! exchanged and why. In a relay chain TX goes to the next device toward
+//! the head and RX comes from the previous one.
+//!
+//! Reads never block, like the command port. Damaged, oversized or
+//! unverifiable frames are logged and dropped: there is no host on this
+//! link to report errors to.
+
+use esp_idf_hal::delay::NON_BLOCK;
+use esp_idf_hal::uart::UartDriver;
+use icesickle_core::attestation::{self, AttestationEvent};
+use icesickle_core::protocol::{self, AttestationRecord, MAX_FRAME_LEN};
+#[cfg(feature = "relay")]
+use icesickle_core::relay::Relay;
+use icesickle_core::witness::{self, PeerMessage};
+use icesickle_core::IceSickleError;
+use log::warn;
+
+use crate::hal::esp_err;
+
+/// Peer link TX (to the peer's RX)
+pub const PEER_TX_PIN: i32 = 17;
+
+/// Peer link RX (from the peer's TX)
+pub const PEER_RX_PIN: i32 = 18;
+
+/// Whether this device's own attestation of `event` goes to the peer
+///
+/// A relay chain carries everything but witness statements; a witness peer
+/// only gets what it can witness (`witness::is_witnessable`).
+pub fn carries(event: &AttestationEvent) -> bool {
+    if cfg!(feature = "relay") {
+        !matches!(event, AttestationEvent::Witness { .. })
+    } else {
+        witness::is_witnessable(event)
+    }
+}
+
+/// Cross-witnessing or relay endpoint on a UART
+pub struct PeerLink<'d> {
+    uart: UartDriver<'d>,
+    buf: [u8; MAX_FRAME_LEN],
+    len: usize,
+    overflowed: bool,
+    #[cfg(feature = "relay")]
+    relay: Relay,
+}
+
+impl<'d> PeerLink<'d> {
+    pub fn new(uart: UartDriver<'d>) -> Self {
+        Self {
+            uart,
+            buf: [0; MAX_FRAME_LEN],
+            len: 0,
+            overflowed: false,
+            #[cfg(feature = "relay")]
+            relay: Relay::new(),
+        }
+    }
+
+    /// Send one of this device's attestations to the peer
+    pub fn send(&mut self, record: &AttestationRecord) -> icesickle_core::Result<()> {
+        #[cfg(feature = "relay")]
+        self.relay.sent(record);
+        #[cfg(feature = "relay")]
+        let message = PeerMessage::Relayed {
+            record: record.clone(),
+            hops: 1,
+        };
+        #[cfg(not(feature = "relay"))]
+        let message = PeerMessage::Attestation(record.clone());
+        self.write(&message)
+    }
+
+    /// Pass on a relayed record that arrived after `hops` links
+    #[cfg(feature = "relay")]
+    pub fn forward(&mut self, record: &AttestationRecord, hops: u8) -> icesickle_core::Result<()> {
+        self.write(&PeerMessage::Relayed {
+            record: record.clone(),
+            hops: hops + 1,
+        })
+    }
+
+    fn write(&mut self, message: &PeerMessage) -> icesickle_core::Result<()> {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let frame = protocol::encode_frame(0, message, &mut out)?;
+        let written = self.uart.write(frame).map_err(esp_err)?;
+        if written < frame.len() {
+            return Err(IceSickleError::Sink);
+        }
+        Ok(())
+    }
+
+    /// The next verified peer message, if one has arrived
+    ///
+    /// With `relay`, records already seen or past the hop limit are dropped
+    /// here.
+    pub fn poll(&mut self) -> Option<PeerMessage> {
+        let mut byte = [0u8; 1];
+        while let Ok(1) = self.uart.read(&mut byte, NON_BLOCK) {
+            if byte[0] != 0 {
+                if self.len < MAX_FRAME_LEN {
+                    self.buf[self.len] = byte[0];
+                    self.len += 1;
+                } else {
+                    self.overflowed = true;
+                }
+                continue;
+            }
+
+            let len = core::mem::take(&mut self.len);
+            if core::mem::take(&mut self.overflowed) {
+                warn!("Peer frame too long - dropped");
+                continue;
+            }
+            if len == 0 {
+                continue;
+            }
+            let message = match protocol::decode_frame(&mut self.buf[..len]).2 {
+                Ok(message) => message,
+                Err(code) => {
+                    warn!("Bad peer frame ({:?}) - dropped", code);
+                    continue;
+                }
+            };
+            if !attestation::verify(message.record()) {
+                warn!("Peer attestation failed verification - dropped");
+                continue;
+            }
+            #[cfg(feature = "relay")]
+            if let PeerMessage::Relayed { record, hops } = &message {
+                if let Err(reason) = self.relay.accept(record, *hops) {
+                    warn!("Relayed attestation dropped ({:?})", reason);
+                    continue;
+                }
+            }
+            return Some(message);
+        }
+        None
+    }
+}
@@ -0,0 +1,83 @@
+//! Thermal receipt printer (feature `receipt`)
+//!
+//! An ESC/POS serial printer on UART1, driven transmit-only from
+//! `PRINTER_TX_PIN`. Each attestation is printed as
+//! `icesickle_core::receipt` renders it: a summary and a QR code of the
+//! fixed-format line.
+//!
+//! Print jobs wait in an outbox and move into the UART TX FIFO as it
+//! empties ([`flush`], called from the event loop), so a slow printer never
+//! stalls the device. The outbox holds two receipts. A printer that falls
+//! further behind (out of paper, unplugged) loses the receipts that do not
+//! fit; each is logged and counted as a sink error, and the attestation
+//! still goes to every other output.
+
+use std::sync::Mutex;
+
+use esp_idf_hal::uart::UartDriver;
+use icesickle_core::attestation::Attestation;
+use icesickle_core::receipt::{self, MAX_RECEIPT_LEN};
+use icesickle_core::telemetry::{self, Counter};
+use log::warn;
+
+use crate::outbox::{Class, Outbox};
+
+/// GPIO driving the printer's RX
+pub const PRINTER_TX_PIN: i32 = 39;
+
+/// Serial rate (the factory setting of most ESC/POS printers)
+pub const PRINTER_BAUD: u32 = 9_600;
+
+/// Queued print jobs
+const OUTBOX_LEN: usize = 2 * MAX_RECEIPT_LEN;
+
+/// The printer, once [`init`] has run
+static PRINTER: Mutex<Option<Printer>> = Mutex::new(None);
+
+struct Printer {
+    uart: UartDriver<'static>,
+    outbox: Outbox<OUTBOX_LEN>,
+}
+
+impl Printer {
+    fn flush(&mut self) {
+        let uart = &self.uart;
+        self.outbox.drain(|bytes| uart.write_nb(bytes).unwrap_or(0));
+    }
+}
+
+/// Print receipts on `uart`
+pub fn init(uart: UartDriver<'static>) {
+    if let Ok(mut printer) = PRINTER.lock() {
+        *printer = Some(Printer {
+            uart,
+            // Print jobs are all reliable; the watermark is never consulted
+            outbox: Outbox::new(OUTBOX_LEN),
+        });
+    }
+}
+
+/// Queue a receipt for `attestation`
+pub fn print(attestation: &Attestation) {
+    let Ok(mut printer) = PRINTER.lock() else {
+        return;
+    };
+    let Some(printer) = printer.as_mut() else {
+        return;
+    };
+    let job = receipt::render(attestation);
+    if let Err(e) = printer.outbox.push(&job, Class::Reliable) {
+        warn!("Receipt not printed: {}", e);
+        telemetry::record(Counter::SinkError);
+    }
+    printer.flush();
+}
+
+/// Move queued print jobs into the UART TX FIFO without blocking
+pub fn flush() {
+    if let Ok(mut printer) = PRINTER.lock() {
+        if let Some(printer) = printer.as_mut() {
+            printer.flush();
+        }
+    }
+}
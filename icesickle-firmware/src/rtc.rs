@@ -0,0 +1,77 @@
+//! RTC time source (feature `rtc`)
+//!
+//! A battery-backed DS3231 or PCF8563 on the shared I2C bus gives absolute
+//! time with no radio and no fix. [`Rtc::poll`] reads the chip whenever
+//! `icesickle_core::wallclock::RtcClock` wants a read: every poll while it
+//! looks for a seconds rollover, then once per `RESYNC_MS`.
+//!
+//! `EspTimer::wall_clock` falls back to this clock when GPS has no fix.
+//! Times are rounded down to [`QUANTUM_S`], and carry the clock's stale
+//! flag. The firmware never writes the chip; set it before fitting.
+
+use std::sync::Mutex;
+
+use icesickle_core::rtc::RtcChip;
+use icesickle_core::wallclock::{self, RtcClock, WallTime};
+use log::{info, warn};
+
+use crate::hal::EspI2c;
+
+/// Resolution of signed UTC times; raise (e.g. to 60) to coarsen them
+const QUANTUM_S: u64 = 1;
+
+static CLOCK: Mutex<RtcClock> = Mutex::new(RtcClock::new());
+
+/// The RTC chip found at boot, if any
+pub struct Rtc {
+    chip: Option<RtcChip>,
+    /// Last read failed; logged once until a read succeeds
+    failing: bool,
+}
+
+impl Rtc {
+    /// Probe `bus` for a supported chip
+    pub fn new(bus: &mut EspI2c<'_>) -> Self {
+        let chip = RtcChip::probe(bus);
+        match chip {
+            Some(chip) => info!("RTC {} at 0x{:02x}", chip.name(), chip.address()),
+            None => warn!("No RTC found; wall-clock time needs another source"),
+        }
+        Self {
+            chip,
+            failing: false,
+        }
+    }
+
+    /// Read the chip if the clock wants a read (never blocks for long)
+    pub fn poll(&mut self, bus: &mut EspI2c<'_>, now_ms: u64) {
+        let Some(chip) = self.chip else {
+            return;
+        };
+        let mut clock = CLOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if !clock.wants_read(now_ms) {
+            return;
+        }
+        match chip.read(bus) {
+            Ok((unix_s, valid)) => {
+                self.failing = false;
+                clock.read(unix_s, valid, now_ms);
+            }
+            Err(e) => {
+                if !self.failing {
+                    warn!("RTC read failed: {}", e);
+                }
+                self.failing = true;
+            }
+        }
+    }
+}
+
+/// Current UTC time, once the RTC has been anchored (for `EspTimer`)
+pub fn wall_clock(now_ms: u64) -> Option<WallTime> {
+    let clock = CLOCK.lock().unwrap_or_else(|e| e.into_inner());
+    clock.now(now_ms).map(|wall| WallTime {
+        unix_s: wallclock::quantize(wall.unix_s, QUANTUM_S),
+        ..wall
+    })
+}
@@ -0,0 +1,27 @@
+//! I2C sensors (feature `sensors`)
+//!
+//! Sensors share the I2C bus on `hal::I2C_SDA_PIN`/`hal::I2C_SCL_PIN`.
+//! [`init`] probes every driver listed below and keeps the ones that answer,
+//! with each driver's default thresholds. To add a sensor, implement
+//! `icesickle_core::sensor::SensorDriver` for it and add one `register`
+//! call here; `register_with` overrides the thresholds.
+
+use icesickle_core::sensor::{sht31, vl53l0x, Sensors};
+
+use crate::hal::EspI2c;
+
+/// Register every sensor that answers on `bus`
+pub fn init(bus: &mut EspI2c<'_>, now_ms: u64) -> Sensors {
+    let mut sensors = Sensors::new();
+    sensors.register(
+        bus,
+        now_ms,
+        Box::new(sht31::Sht31::new(sht31::DEFAULT_ADDRESS)),
+    );
+    sensors.register(
+        bus,
+        now_ms,
+        Box::new(vl53l0x::Vl53l0x::new(vl53l0x::DEFAULT_ADDRESS)),
+    );
+    sensors
+}
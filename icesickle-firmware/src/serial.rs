@@ -0,0 +1,225 @@
+//! Serial command port
+//!
+//! Owns the UART used for the command protocol and moves bytes between it
+//! and the frame codec in `protocol.rs`. Reads never block: the main loop
+//! polls this between button checks, so a silent or slow host cannot delay
+//! press detection.
+//!
+//! The port also implements the link-level recovery rules: it drops partial
+//! frames when the link goes quiet, keeps the error counters reported in
+//! `GET_STATUS`, and answers retransmitted requests from a cache of the last
+//! [`MAX_IN_FLIGHT`] responses so that a lost response never causes a
+//! command to run twice, even with pipelined requests.
+//!
+//! Output goes through a bounded [`Outbox`] drained without blocking. When a
+//! host stops reading, heartbeats are skipped first and new requests are
+//! left unread until it catches up; a reply that still cannot be queued is
+//! reported to the caller as an error. Attestation output queued in
+//! `console` shares the UART and is drained after the frames.
+
+use esp_idf_hal::delay::NON_BLOCK;
+use esp_idf_hal::uart::UartDriver;
+use icesickle_core::protocol::{
+    self, FrameReader, LinkErrors, Request, Response, FRAME_TIMEOUT_MS, MAX_FRAME_LEN,
+    MAX_IN_FLIGHT,
+};
+use icesickle_core::IceSickleError;
+use log::warn;
+
+use crate::console;
+use crate::outbox::{Class, Outbox, Refused};
+
+/// A repeated frame older than this is treated as a new request
+const RETRANSMIT_WINDOW_MS: u64 = 2_000;
+
+/// Output buffer size; holds several maximum-size frames
+const OUTBOX_LEN: usize = 4 * (MAX_FRAME_LEN + 1);
+
+/// Above this much queued output only replies are accepted
+const OUTBOX_HIGH_WATERMARK: usize = 2 * (MAX_FRAME_LEN + 1);
+
+/// A recent response, kept for retransmissions
+struct Cached {
+    seq: u8,
+    crc: u16,
+    at_ms: u64,
+    frame: [u8; MAX_FRAME_LEN + 1],
+    len: usize,
+}
+
+/// Command protocol endpoint on a UART
+pub struct SerialPort<'d> {
+    uart: UartDriver<'d>,
+    reader: FrameReader,
+    last_byte_ms: u64,
+    errors: LinkErrors,
+    cached: heapless::Deque<Cached, { MAX_IN_FLIGHT as usize }>,
+    outbox: Outbox<OUTBOX_LEN>,
+}
+
+impl<'d> SerialPort<'d> {
+    pub fn new(uart: UartDriver<'d>) -> Self {
+        Self {
+            uart,
+            reader: FrameReader::new(),
+            last_byte_ms: 0,
+            errors: LinkErrors::default(),
+            cached: heapless::Deque::new(),
+            outbox: Outbox::new(OUTBOX_HIGH_WATERMARK),
+        }
+    }
+
+    /// Link error counters since boot
+    pub fn errors(&self) -> LinkErrors {
+        self.errors
+    }
+
+    /// Consume pending input without blocking
+    ///
+    /// Returns as soon as one new request completes, leaving any further
+    /// bytes in the UART buffer for the next call. Damaged frames are
+    /// answered with an error and retransmissions with the cached response
+    /// here; neither is returned to the caller. Queued output is flushed
+    /// first, and no input is read while the outbox is congested.
+    pub fn poll(&mut self, now_ms: u64) -> Option<(u8, Request)> {
+        let mut byte = [0u8; 1];
+
+        self.flush();
+        if self.outbox.is_congested() {
+            return None;
+        }
+
+        if now_ms.saturating_sub(self.last_byte_ms) >= FRAME_TIMEOUT_MS
+            && self.reader.discard_partial()
+        {
+            self.errors.timeout = self.errors.timeout.saturating_add(1);
+        }
+
+        while let Ok(1) = self.uart.read(&mut byte, NON_BLOCK) {
+            self.last_byte_ms = now_ms;
+            let Some(inbound) = self.reader.push(byte[0]) else {
+                continue;
+            };
+
+            match inbound.request {
+                Ok(request) => {
+                    if self.resend_cached(inbound.seq, inbound.crc, now_ms) {
+                        self.errors.retransmit = self.errors.retransmit.saturating_add(1);
+                        continue;
+                    }
+                    // Remembered now so the response can be cached against it
+                    if self.cached.is_full() {
+                        self.cached.pop_front();
+                    }
+                    let _ = self.cached.push_back(Cached {
+                        seq: inbound.seq,
+                        crc: inbound.crc,
+                        at_ms: now_ms,
+                        frame: [0; MAX_FRAME_LEN + 1],
+                        len: 0,
+                    });
+                    return Some((inbound.seq, request));
+                }
+                Err(code) => {
+                    self.errors.record(code);
+                    if let Err(e) = self.send_uncached(inbound.seq, &Response::Error(code)) {
+                        warn!("Failed to send error response: {}", e);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// True once all queued output, console text included, has left the
+    /// UART and the link has been quiet for a frame timeout (so no frame
+    /// is half read); the device may then sleep
+    #[cfg(feature = "light-sleep")]
+    pub fn is_idle(&mut self, now_ms: u64) -> bool {
+        self.flush();
+        self.outbox.is_empty()
+            && console::is_empty()
+            && now_ms.saturating_sub(self.last_byte_ms) >= FRAME_TIMEOUT_MS
+            && self.uart.wait_tx_done(NON_BLOCK).is_ok()
+    }
+
+    /// Queue the response to the request last returned by [`Self::poll`]
+    pub fn send(&mut self, seq: u8, response: &Response) -> icesickle_core::Result<()> {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let len = protocol::encode_frame(seq, response, &mut out)?.len();
+        self.queue(&out[..len], Class::Reliable)
+            .map_err(|_| IceSickleError::Sink)?;
+
+        let pending = self
+            .cached
+            .iter_mut()
+            .rev()
+            .find(|c| c.seq == seq && c.len == 0);
+        if let Some(cached) = pending {
+            cached.frame = out;
+            cached.len = len;
+        }
+        Ok(())
+    }
+
+    /// Queue an unsolicited frame (heartbeats); never cached or retransmitted
+    ///
+    /// Skipped, and counted in [`LinkErrors::dropped`], while the host is not
+    /// keeping up with output.
+    pub fn notify(&mut self, response: &Response) -> icesickle_core::Result<()> {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let frame = protocol::encode_frame(0, response, &mut out)?;
+        match self.queue(frame, Class::Droppable) {
+            Err(Refused::Congested) => {
+                self.errors.dropped = self.errors.dropped.saturating_add(1);
+                Ok(())
+            }
+            result => result.map_err(|_| IceSickleError::Sink),
+        }
+    }
+
+    fn send_uncached(&mut self, seq: u8, response: &Response) -> icesickle_core::Result<()> {
+        let mut out = [0u8; MAX_FRAME_LEN + 1];
+        let frame = protocol::encode_frame(seq, response, &mut out)?;
+        self.queue(frame, Class::Reliable)
+            .map_err(|_| IceSickleError::Sink)
+    }
+
+    /// Queue a frame and push as much output as the UART will take
+    fn queue(&mut self, frame: &[u8], class: Class) -> Result<(), Refused> {
+        let result = self.outbox.push(frame, class);
+        self.flush();
+        result
+    }
+
+    /// Move queued output into the UART TX FIFO without blocking
+    fn flush(&mut self) {
+        let uart = &self.uart;
+        self.outbox.drain(|bytes| uart.write_nb(bytes).unwrap_or(0));
+        if self.outbox.is_empty() {
+            console::drain(|bytes| uart.write_nb(bytes).unwrap_or(0));
+        }
+    }
+
+    /// Requeue the cached response if `(seq, crc)` repeats a recent request
+    fn resend_cached(&mut self, seq: u8, crc: u16, now_ms: u64) -> bool {
+        let Some(cached) = self.cached.iter().rev().find(|c| {
+            c.seq == seq
+                && c.crc == crc
+                && c.len > 0
+                && now_ms.saturating_sub(c.at_ms) < RETRANSMIT_WINDOW_MS
+        }) else {
+            return false;
+        };
+
+        if let Err(e) = self
+            .outbox
+            .push(&cached.frame[..cached.len], Class::Reliable)
+        {
+            warn!("Failed to resend response: {}", e);
+        }
+        self.flush();
+        true
+    }
+}
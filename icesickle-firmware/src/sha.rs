@@ -0,0 +1,163 @@
+//! SHA-256 via ESP-IDF mbedTLS
+//!
+//! ESP-IDF routes `mbedtls_sha256_*` to the ESP32-S3 SHA peripheral
+//! (`CONFIG_MBEDTLS_HARDWARE_SHA`, on by default), so hashing large inputs
+//! costs little CPU time and no extra code size beyond what mbedTLS already
+//! brings in.
+//!
+//! With `hw-sha512` the peripheral also runs the SHA-512 inside Ed25519
+//! signing: [`accelerate_sha512`] registers [`SHA512`] as the backend of
+//! `icesickle_core::sha512` once it hashes a known answer correctly, and
+//! signing stays in software otherwise.
+
+#[cfg(feature = "hw-sha512")]
+use core::cell::UnsafeCell;
+#[cfg(feature = "hw-sha512")]
+use core::mem::MaybeUninit;
+#[cfg(feature = "hw-sha512")]
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use esp_idf_sys::{
+    mbedtls_sha256_context, mbedtls_sha256_finish, mbedtls_sha256_free, mbedtls_sha256_init,
+    mbedtls_sha256_starts, mbedtls_sha256_update,
+};
+#[cfg(feature = "hw-sha512")]
+use esp_idf_sys::{
+    mbedtls_sha512_context, mbedtls_sha512_finish, mbedtls_sha512_free, mbedtls_sha512_init,
+    mbedtls_sha512_starts, mbedtls_sha512_update,
+};
+#[cfg(feature = "hw-sha512")]
+use icesickle_core::sha512::{self, Backend};
+
+/// Incremental SHA-256
+pub struct Sha256 {
+    // Boxed so the context never moves after initialization
+    ctx: Box<mbedtls_sha256_context>,
+}
+
+impl Sha256 {
+    pub fn new() -> Self {
+        let mut ctx = Box::<mbedtls_sha256_context>::default();
+        unsafe {
+            mbedtls_sha256_init(&mut *ctx);
+            // Only fails on a bad `is224` argument
+            mbedtls_sha256_starts(&mut *ctx, 0);
+        }
+        Self { ctx }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        unsafe {
+            mbedtls_sha256_update(&mut *self.ctx, data.as_ptr(), data.len());
+        }
+    }
+
+    pub fn finish(mut self) -> [u8; 32] {
+        let mut digest = [0u8; 32];
+        unsafe {
+            mbedtls_sha256_finish(&mut *self.ctx, digest.as_mut_ptr());
+        }
+        digest
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Sha256 {
+    fn drop(&mut self) {
+        unsafe { mbedtls_sha256_free(&mut *self.ctx) }
+    }
+}
+
+/// SHA-512("abc"), FIPS 180-2 appendix C.1
+#[cfg(feature = "hw-sha512")]
+const ABC_SHA512: [u8; 64] = [
+    0xdd, 0xaf, 0x35, 0xa1, 0x93, 0x61, 0x7a, 0xba, 0xcc, 0x41, 0x73, 0x49, 0xae, 0x20, 0x41, 0x31,
+    0x12, 0xe6, 0xfa, 0x4e, 0x89, 0xa9, 0x7e, 0xa2, 0x0a, 0x9e, 0xee, 0xe6, 0x4b, 0x55, 0xd3, 0x9a,
+    0x21, 0x92, 0x99, 0x2a, 0x27, 0x4f, 0xc1, 0xa8, 0x36, 0xba, 0x3c, 0x23, 0xa3, 0xfe, 0xeb, 0xbd,
+    0x45, 0x4d, 0x44, 0x23, 0x64, 0x3c, 0xe8, 0x0e, 0x2a, 0x9a, 0xc9, 0x4f, 0xa5, 0x4c, 0xa4, 0x9f,
+];
+
+/// The peripheral's SHA-512 (feature `hw-sha512`)
+///
+/// Every signature's two hashes reuse one static context rather than
+/// allocating their own; a hash that finds it held runs in software (see
+/// `icesickle_core::sha512`).
+#[cfg(feature = "hw-sha512")]
+pub struct Sha512Peripheral {
+    ctx: UnsafeCell<MaybeUninit<mbedtls_sha512_context>>,
+    busy: AtomicBool,
+}
+
+// The context is only touched by whoever claimed `busy`
+#[cfg(feature = "hw-sha512")]
+unsafe impl Sync for Sha512Peripheral {}
+
+/// The one SHA-512 context signing uses
+#[cfg(feature = "hw-sha512")]
+pub static SHA512: Sha512Peripheral = Sha512Peripheral {
+    ctx: UnsafeCell::new(MaybeUninit::uninit()),
+    busy: AtomicBool::new(false),
+};
+
+#[cfg(feature = "hw-sha512")]
+impl Sha512Peripheral {
+    fn ctx(&self) -> *mut mbedtls_sha512_context {
+        self.ctx.get().cast()
+    }
+}
+
+#[cfg(feature = "hw-sha512")]
+impl Backend for Sha512Peripheral {
+    fn start(&self) -> bool {
+        if self.busy.swap(true, Ordering::Acquire) {
+            return false;
+        }
+        unsafe {
+            mbedtls_sha512_init(self.ctx());
+            // Only fails on a bad `is384` argument
+            mbedtls_sha512_starts(self.ctx(), 0);
+        }
+        true
+    }
+
+    fn update(&self, data: &[u8]) {
+        unsafe {
+            mbedtls_sha512_update(self.ctx(), data.as_ptr(), data.len());
+        }
+    }
+
+    fn finish(&self) -> [u8; 64] {
+        let mut digest = [0u8; 64];
+        let mut blank = [0u8; 64];
+        unsafe {
+            mbedtls_sha512_finish(self.ctx(), digest.as_mut_ptr());
+            // The nonce hash's last block held the secret prefix in the
+            // peripheral's registers: hash a block of nothing over it
+            mbedtls_sha512_starts(self.ctx(), 0);
+            mbedtls_sha512_finish(self.ctx(), blank.as_mut_ptr());
+            mbedtls_sha512_free(self.ctx());
+        }
+        self.busy.store(false, Ordering::Release);
+        digest
+    }
+}
+
+/// Sign on the peripheral from now on, if it hashes a known answer
+/// correctly; false leaves signing in software
+#[cfg(feature = "hw-sha512")]
+pub fn accelerate_sha512() -> bool {
+    if !SHA512.start() {
+        return false;
+    }
+    SHA512.update(b"abc");
+    if SHA512.finish() != ABC_SHA512 {
+        return false;
+    }
+    sha512::set_backend(&SHA512);
+    true
+}
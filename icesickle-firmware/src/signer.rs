@@ -0,0 +1,235 @@
+//! Press signing, on the event loop or on core 1 (feature `signer-core`)
+//!
+//! Deriving a key and signing take long enough to stall the event loop: a
+//! second button is not seen, serial commands wait and the LED stops. With
+//! `signer-core` a press is handed through a bounded queue to [`Signer`], a
+//! task pinned to core 1, and the loop on core 0 goes on polling inputs,
+//! serving the command protocol and driving feedback; it outputs the result
+//! on whichever pass it arrives ([`Signer::poll`]). Without the feature the
+//! loop calls [`Job::sign`] itself, as before.
+//!
+//! The signer owns a `HardwareRng` over the TRNG, so the two cores share
+//! nothing they sign with, and derives its key seed in a crypto workspace
+//! slot of its own (`icesickle_core::scrub`). Its stack is recorded like
+//! the main task's: scrubbed after every job, wiped by the fatal paths and
+//! reported by `GetStatus`. The device refuses presses while one is being
+//! signed, so the queue never holds more than that one; a job it cannot
+//! take fails rather than waits.
+//!
+//! Tamper must not let core 1 finish what it was given: [`Signer::cancel`]
+//! drops the queued job unsigned (its token, challenge and context wiped),
+//! waits for the one being signed so the crypto workspace can be scrubbed
+//! with no seed in it, and discards every result. The loop also drops any
+//! result that reaches it in lockout or fault.
+//!
+//! Only presses go to core 1. Device-initiated attestations (tamper,
+//! windows, boot, credit, batches, sensors, touch, keypad, witness) still
+//! sign on the event loop, alongside it.
+
+#[cfg(feature = "signer-core")]
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "signer-core")]
+use std::sync::mpsc::{self, Receiver, SyncSender};
+#[cfg(feature = "signer-core")]
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+#[cfg(feature = "signer-core")]
+use std::thread;
+
+#[cfg(feature = "signer-core")]
+use esp_idf_hal::cpu::Core;
+#[cfg(feature = "signer-core")]
+use esp_idf_hal::task::thread::ThreadSpawnConfiguration;
+#[cfg(feature = "signer-core")]
+use esp_idf_sys::{self as sys, EspError};
+use icesickle_core::attestation::{Attestation, AttestationEvent};
+use icesickle_core::auth::Token;
+use icesickle_core::context::Context;
+use icesickle_core::entropy::HardwareRng;
+use icesickle_core::hal::EntropySource;
+use icesickle_core::scrub;
+#[cfg(feature = "signer-core")]
+use icesickle_core::IceSickleError;
+use icesickle_core::Result;
+use log::{debug, warn};
+
+#[cfg(feature = "signer-core")]
+use crate::fatal;
+use crate::hal::EspTimer;
+#[cfg(feature = "signer-core")]
+use crate::hal::{esp_err, EspEntropy};
+use crate::{memory, stack};
+
+/// Signer task stack, sized like the main task's (`stack::MIN_HEADROOM_BYTES`)
+#[cfg(feature = "signer-core")]
+const STACK_SIZE: usize = 16 * 1024;
+
+/// A press to sign, with what the event loop gathered for it
+pub struct Job {
+    pub event: AttestationEvent,
+    pub challenge: Option<[u8; 32]>,
+    pub token: Option<Token>,
+    pub context: Option<Context>,
+}
+
+impl Job {
+    /// Sign on the calling task, then wipe what signing left on its stack
+    pub fn sign<S: EntropySource>(mut self, rng: &HardwareRng<S>) -> Result<Attestation> {
+        let created = Attestation::create_authorized(
+            rng,
+            &EspTimer,
+            self.event,
+            self.challenge.take(),
+            self.token.take(),
+            self.context.take(),
+        );
+        // Wipe what key generation and signing spilled below us
+        let scrubbed = stack::scrub_dead();
+        debug!("Scrubbed {} bytes of dead stack", scrubbed);
+        let headroom = stack::headroom();
+        if headroom < stack::MIN_HEADROOM_BYTES {
+            warn!("Signing stack headroom down to {} bytes", headroom);
+        }
+        memory::log();
+        created
+    }
+}
+
+impl Drop for Job {
+    // A job dropped unsigned keeps nothing the host gave it; the token
+    // zeroizes itself
+    fn drop(&mut self) {
+        if let Some(challenge) = &mut self.challenge {
+            scrub::zero(challenge);
+        }
+        if let Some(context) = &mut self.context {
+            scrub::zero(context);
+        }
+    }
+}
+
+/// The task signing presses on core 1
+#[cfg(feature = "signer-core")]
+pub struct Signer {
+    shared: Arc<Shared>,
+    done: Receiver<Result<Attestation>>,
+}
+
+/// What the event loop and the signer share
+#[cfg(feature = "signer-core")]
+struct Shared {
+    /// The job waiting for core 1, if any
+    queued: Mutex<Option<Job>>,
+    /// Signalled when a job is queued
+    ready: Condvar,
+    /// A job has been taken and is being signed; set under `queued`'s lock,
+    /// so a job is always either queued or busy
+    busy: AtomicBool,
+}
+
+#[cfg(feature = "signer-core")]
+impl Shared {
+    fn queued(&self) -> MutexGuard<'_, Option<Job>> {
+        // The slot stays consistent even if a holder panicked
+        self.queued.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(feature = "signer-core")]
+impl Signer {
+    /// Start the signer on core 1, with an RNG of its own
+    pub fn start() -> Result<Self> {
+        let rng = HardwareRng::from_source(EspEntropy)?;
+        let shared = Arc::new(Shared {
+            queued: Mutex::new(None),
+            ready: Condvar::new(),
+            busy: AtomicBool::new(false),
+        });
+        // Room for the job being signed and the one queued behind it, so
+        // the signer never waits on the loop
+        let (finished, done) = mpsc::sync_channel(2);
+
+        ThreadSpawnConfiguration {
+            name: Some(b"signer\0"),
+            stack_size: STACK_SIZE,
+            pin_to_core: Some(Core::Core1),
+            ..Default::default()
+        }
+        .set()
+        .map_err(esp_err)?;
+        let spawned = {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn(move || run(rng, &shared, finished))
+        };
+        // Threads spawned later (`https-push`) go back to the defaults
+        ThreadSpawnConfiguration::default().set().map_err(esp_err)?;
+        spawned.map_err(|_| esp_err(EspError::from_infallible::<{ sys::ESP_ERR_NO_MEM }>()))?;
+        Ok(Self { shared, done })
+    }
+
+    /// Queue `job` for core 1; fails with `Sink` if a job is already queued
+    pub fn submit(&self, job: Job) -> Result<()> {
+        let mut queued = self.shared.queued();
+        if queued.is_some() {
+            return Err(IceSickleError::Sink);
+        }
+        *queued = Some(job);
+        self.shared.ready.notify_one();
+        Ok(())
+    }
+
+    /// A finished job's result, without waiting
+    pub fn poll(&self) -> Option<Result<Attestation>> {
+        self.done.try_recv().ok()
+    }
+
+    /// Drop the queued job unsigned, wait until nothing is being signed and
+    /// discard every result (tamper)
+    ///
+    /// Once this returns no seed is being derived in the crypto workspace,
+    /// and nothing core 1 was given comes back.
+    pub fn cancel(&self) {
+        // Dropping the job wipes its token, challenge and context
+        let cancelled = self.shared.queued().take();
+        if cancelled.is_some() {
+            warn!("Dropped a queued press unsigned");
+        }
+        drop(cancelled);
+        loop {
+            // Idle means its result, if any, is already in the channel
+            let idle = !self.shared.busy.load(Ordering::Acquire);
+            while self.done.try_recv().is_ok() {
+                warn!("Discarded a press signed before the wipe");
+            }
+            if idle {
+                return;
+            }
+            fatal::feed();
+            thread::yield_now();
+        }
+    }
+}
+
+/// Sign jobs in order, forever
+#[cfg(feature = "signer-core")]
+fn run(rng: HardwareRng<EspEntropy>, shared: &Shared, finished: SyncSender<Result<Attestation>>) {
+    stack::record_signer(STACK_SIZE);
+    loop {
+        let job = {
+            let mut queued = shared.queued();
+            loop {
+                if let Some(job) = queued.take() {
+                    shared.busy.store(true, Ordering::Relaxed);
+                    break job;
+                }
+                queued = shared.ready.wait(queued).unwrap_or_else(|e| e.into_inner());
+            }
+        };
+        let sent = finished.send(job.sign(&rng));
+        shared.busy.store(false, Ordering::Release);
+        if sent.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Light sleep between events (feature `light-sleep`)
+//!
+//! On battery, a loop polling every 10 ms keeps the CPU awake for nothing.
+//! With this feature the loop puts the chip into light sleep whenever it
+//! is idle: no button held, no host request armed, no session open and all
+//! output out of the UART. The buttons are level wake sources (see
+//! `button::enable_wakeup`), so a press wakes the chip within about a
+//! millisecond and is debounced, signed and output as usual before it
+//! sleeps again. The timer wakes it every [`WAKE_INTERVAL_MS`] for the
+//! periodic work (heartbeats, window digests, presence series, sensor
+//! polls), and the console UART wakes it for host commands.
+//!
+//! Light sleep keeps RAM and CPU state, so the loop carries on where it
+//! stopped with its keys, tokens and counters intact. ESP-IDF advances
+//! `esp_timer` by the time slept, so timestamps and the cooldown measure
+//! real elapsed time.
+//!
+//! The UART drops the characters that wake it, so the first frame a host
+//! sends to a sleeping device is lost and gets through when the host
+//! retransmits it (see `protocol`). After each request the device stays
+//! awake for [`AWAKE_AFTER_REQUEST_MS`], so the rest of an exchange is not
+//! cut up the same way.
+
+use esp_idf_sys::{self as sys, esp};
+use icesickle_core::Result;
+use log::debug;
+
+use crate::hal::esp_err;
+
+/// Longest sleep; periodic work runs at least this often
+pub const WAKE_INTERVAL_MS: u64 = 1_000;
+
+/// Time awake after a host request, for the rest of the exchange
+pub const AWAKE_AFTER_REQUEST_MS: u64 = 5_000;
+
+/// UART0: the console and command port
+const CONSOLE_UART: sys::uart_port_t = 0;
+
+/// Rising edges on RX that wake the chip (the fewest the UART allows)
+const UART_WAKE_EDGES: i32 = 3;
+
+/// Arm the timer and the console UART as wake sources
+pub fn init() -> Result<()> {
+    // SAFETY: plain ESP-IDF sleep configuration calls, made once at boot
+    unsafe {
+        esp!(sys::esp_sleep_enable_timer_wakeup(WAKE_INTERVAL_MS * 1000)).map_err(esp_err)?;
+        esp!(sys::uart_set_wakeup_threshold(
+            CONSOLE_UART,
+            UART_WAKE_EDGES
+        ))
+        .map_err(esp_err)?;
+        esp!(sys::esp_sleep_enable_uart_wakeup(CONSOLE_UART)).map_err(esp_err)?;
+    }
+    Ok(())
+}
+
+/// Sleep until a wake source fires; false if the chip did not sleep
+pub fn light_sleep() -> bool {
+    // SAFETY: returns once a wake source armed in `init` or by the buttons
+    // fires, with RAM and peripherals as they were
+    match esp!(unsafe { sys::esp_light_sleep_start() }) {
+        Ok(()) => true,
+        Err(e) => {
+            debug!("Light sleep refused: {}", e);
+            false
+        }
+    }
+}
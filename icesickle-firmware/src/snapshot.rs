@@ -0,0 +1,44 @@
+//! Interlock and switch inputs sampled at each press (feature
+//! `gpio-snapshot`)
+//!
+//! The GPIOs in [`SNAPSHOT_PINS`] are read the moment a press is detected,
+//! and the press is signed as `GpioSnapshot` with their levels instead of
+//! `ButtonPress` (see `icesickle_core::snapshot`). Inputs have the internal
+//! pull-up, so an open switch to ground reads high. Approvals of host data
+//! and presence attestations are signed as before.
+
+use esp_idf_hal::gpio::{AnyIOPin, PinDriver, Pull};
+use icesickle_core::snapshot::Snapshot;
+use icesickle_core::Result;
+
+use crate::hal::{esp_err, EspPin};
+
+/// Sampled GPIOs: pins no other feature uses. 41 and 42 double as pad JTAG,
+/// which is only routed on chips fused for it (see `debug_lock`). Any free
+/// input below 64 can be added
+pub const SNAPSHOT_PINS: [i32; 3] = [41, 42, 47];
+
+/// The configured inputs
+pub struct SnapshotPins {
+    pins: Vec<(u8, EspPin<'static, AnyIOPin>)>,
+}
+
+impl SnapshotPins {
+    pub fn new() -> Result<Self> {
+        let pins = SNAPSHOT_PINS
+            .iter()
+            .map(|&gpio| {
+                // Pins listed here are not handed to any other driver
+                let mut pin = PinDriver::input(unsafe { AnyIOPin::new(gpio) }).map_err(esp_err)?;
+                pin.set_pull(Pull::Up).map_err(esp_err)?;
+                Ok((gpio as u8, EspPin(pin)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { pins })
+    }
+
+    /// Current levels
+    pub fn capture(&self) -> Snapshot {
+        Snapshot::capture(self.pins.iter().map(|(gpio, pin)| (*gpio, pin)))
+    }
+}
@@ -0,0 +1,138 @@
+//! Signing task stack bounds and dead-stack scrubbing
+//!
+//! `ZeroizeOnDrop` clears the signing key struct, but not the copies the
+//! compiler spills to the stack during key derivation and signing: those
+//! stay in the dead region below the signing frame until something deeper
+//! happens to overwrite them. [`scrub_dead`] zeros that region after every
+//! attestation.
+//!
+//! The region's depth is measured rather than guessed: FreeRTOS fills task
+//! stacks with a pattern and `uxTaskGetStackHighWaterMark` reports how much
+//! of it was never touched, so everything between the deepest point ever
+//! reached and the current frame is wiped. That is a superset of what
+//! signing used.
+//!
+//! Two tasks sign: the main task, and with `signer-core` the signer on core
+//! 1 (`signer.rs`). Each records its own stack ([`record`],
+//! [`record_signer`]), and the scrubbing and the fatal paths cover both.
+//!
+//! The same mark sizes the stack: after each attestation [`headroom`] is
+//! checked against [`MIN_HEADROOM_BYTES`]. Overflow itself is caught by the
+//! FreeRTOS canary (`CONFIG_FREERTOS_CHECK_STACKOVERFLOW_CANARY`), whose
+//! hook (`fatal.rs`) wipes the stack before aborting.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use icesickle_core::scrub;
+
+/// Bytes left below the scrubbing frame for register-window spills
+const SPILL_GUARD_BYTES: usize = 64;
+
+/// Untouched stack that must remain after the deepest attestation so far
+///
+/// `CONFIG_ESP_MAIN_TASK_STACK_SIZE` and the signer's stack are sized from
+/// this: raise them when the warning below appears (new signature backends
+/// are the usual cause).
+pub const MIN_HEADROOM_BYTES: u32 = 4096;
+
+/// A task's stack `[start, end)` and handle, once recorded
+struct Recorded {
+    start: AtomicUsize,
+    end: AtomicUsize,
+    task: AtomicUsize,
+}
+
+impl Recorded {
+    const fn new() -> Self {
+        Self {
+            start: AtomicUsize::new(0),
+            end: AtomicUsize::new(0),
+            task: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record the calling task, whose stack is `size` bytes
+    fn store(&self, size: usize) {
+        let start = unsafe { esp_idf_sys::pxTaskGetStackStart(core::ptr::null_mut()) } as usize;
+        self.start.store(start, Ordering::Relaxed);
+        self.end.store(start + size, Ordering::Relaxed);
+        let task = unsafe { esp_idf_sys::xTaskGetCurrentTaskHandle() } as usize;
+        self.task.store(task, Ordering::Relaxed);
+    }
+
+    fn span(&self) -> Option<(usize, usize)> {
+        let start = self.start.load(Ordering::Relaxed);
+        (start != 0).then(|| (start, self.end.load(Ordering::Relaxed)))
+    }
+}
+
+static MAIN: Recorded = Recorded::new();
+static SIGNER: Recorded = Recorded::new();
+
+/// Record the main task's stack; call from `main`, on the main task
+pub fn record() {
+    MAIN.store(esp_idf_sys::CONFIG_ESP_MAIN_TASK_STACK_SIZE as usize);
+}
+
+/// Record the signer's stack of `size` bytes; call first thing on it
+pub fn record_signer(size: usize) {
+    SIGNER.store(size);
+}
+
+/// Stack `[start, end)` of `task`, if it is one that signs
+pub fn bounds_of(task: esp_idf_sys::TaskHandle_t) -> Option<(usize, usize)> {
+    [&MAIN, &SIGNER]
+        .into_iter()
+        .filter(|recorded| {
+            let known = recorded.task.load(Ordering::Relaxed);
+            known != 0 && task as usize == known
+        })
+        .find_map(Recorded::span)
+}
+
+/// Main task stack `[start, end)`, once recorded
+pub fn main_bounds() -> Option<(usize, usize)> {
+    MAIN.span()
+}
+
+/// Signer stack `[start, end)`, once its task runs
+pub fn signer_bounds() -> Option<(usize, usize)> {
+    SIGNER.span()
+}
+
+/// Bytes of the calling task's stack never touched since it started
+pub fn headroom() -> u32 {
+    // ESP-IDF stacks are byte-addressed, so the mark is in bytes
+    unsafe { esp_idf_sys::uxTaskGetStackHighWaterMark(core::ptr::null_mut()) }
+}
+
+/// Stack `[start, end)` of a task that signs, if `sp` lies inside it
+pub fn bounds(sp: usize) -> Option<(usize, usize)> {
+    [main_bounds(), signer_bounds()]
+        .into_iter()
+        .flatten()
+        .find(|&(start, end)| start < sp && sp < end)
+}
+
+/// Zero the stack between the high-water mark and this frame
+///
+/// Returns the number of bytes wiped (0 when not on a task that signs).
+#[inline(never)]
+pub fn scrub_dead() -> usize {
+    let marker = 0u8;
+    let sp = core::ptr::addr_of!(marker) as usize;
+    let Some((start, _)) = bounds(sp) else {
+        return 0;
+    };
+
+    let deepest = start + headroom() as usize;
+    let top = sp.saturating_sub(SPILL_GUARD_BYTES);
+    if deepest >= top {
+        return 0;
+    }
+
+    // SAFETY: [deepest, top) is inside this task's stack and below this
+    // frame's spill area, so only frames that have returned live there
+    unsafe { scrub::zero_range(deepest as *mut u8, top as *mut u8) };
+    top - deepest
+}
@@ -0,0 +1,92 @@
+//! Binary attestation stream (feature `binary-stream`)
+//!
+//! UART1, driven transmit-only from [`STREAM_TX_PIN`], carries every
+//! attestation as an `icesickle_core::stream` frame and nothing else: no
+//! log lines, no console text, no command protocol. A host reads it with a
+//! second USB-UART adapter and checks it with `icesickle-verify --stream`.
+//!
+//! The native USB port's CDC endpoint would save the adapter, but it
+//! belongs to the USB-Serial-JTAG controller, whose pads release builds
+//! cut at boot (see `debug_lock.rs`).
+//!
+//! Frames wait in an outbox and move into the UART TX FIFO as it empties
+//! ([`flush`], called from the event loop), so a slow or absent reader
+//! never stalls the device. A frame that does not fit is logged and
+//! counted as a sink error, and the attestation still goes to every other
+//! output.
+
+use std::sync::Mutex;
+
+use esp_idf_hal::uart::UartDriver;
+use icesickle_core::attestation::Attestation;
+use icesickle_core::protocol::AttestationRecord;
+use icesickle_core::stream::{self, MAX_FRAME_LEN};
+use icesickle_core::telemetry::{self, Counter};
+use log::warn;
+
+use crate::outbox::{Class, Outbox};
+
+/// GPIO driving the reader's RX
+pub const STREAM_TX_PIN: i32 = 39;
+
+/// Queued frames
+const OUTBOX_LEN: usize = 4 * (MAX_FRAME_LEN + 1);
+
+/// The stream, once [`init`] has run
+static STREAM: Mutex<Option<Stream>> = Mutex::new(None);
+
+struct Stream {
+    uart: UartDriver<'static>,
+    outbox: Outbox<OUTBOX_LEN>,
+}
+
+impl Stream {
+    fn flush(&mut self) {
+        let uart = &self.uart;
+        self.outbox.drain(|bytes| uart.write_nb(bytes).unwrap_or(0));
+    }
+}
+
+/// Stream attestations on `uart`
+pub fn init(uart: UartDriver<'static>) {
+    if let Ok(mut port) = STREAM.lock() {
+        *port = Some(Stream {
+            uart,
+            // Frames are all reliable; the watermark is never consulted
+            outbox: Outbox::new(OUTBOX_LEN),
+        });
+    }
+}
+
+/// Queue a frame of `attestation`
+pub fn push(attestation: &Attestation) {
+    let Ok(mut port) = STREAM.lock() else {
+        return;
+    };
+    let Some(port) = port.as_mut() else {
+        return;
+    };
+    let mut out = [0u8; MAX_FRAME_LEN + 1];
+    let frame = match stream::encode(&AttestationRecord::from(attestation), &mut out) {
+        Ok(frame) => frame,
+        Err(e) => {
+            warn!("Attestation not streamed: {}", e);
+            telemetry::record(Counter::SinkError);
+            return;
+        }
+    };
+    if let Err(e) = port.outbox.push(frame, Class::Reliable) {
+        warn!("Attestation not streamed: {}", e);
+        telemetry::record(Counter::SinkError);
+    }
+    port.flush();
+}
+
+/// Move queued frames into the UART TX FIFO without blocking
+pub fn flush() {
+    if let Ok(mut port) = STREAM.lock() {
+        if let Some(port) = port.as_mut() {
+            port.flush();
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Tamper response (feature `tamper`)
+//!
+//! A normally-closed enclosure switch ties `TAMPER_PIN` to ground; opening
+//! the case (or cutting the loop) lets the pull-up win. When that happens
+//! the event loop:
+//!
+//! 1. wipes every volatile secret (`wipe_secrets` in `main.rs`);
+//! 2. signs and emits a `TamperDetected { gpio }` attestation, bypassing
+//!    the cooldown;
+//! 3. latches the lockout in NVS and enters `State::Lockout`, which
+//!    `GetStatus` reports as `tampered`.
+//!
+//! The latch survives resets and power cycles, so pulling the battery does
+//! not clear it. Only booting with the provisioning jumper fitted
+//! (`PROVISION_PIN` to ground) does. The flag is a single bit that every
+//! unit shares, so it is not an identifier.
+//!
+//! The feature is opt-in: with no switch fitted the pin floats high and
+//! would trip immediately.
+
+use esp_idf_hal::gpio::{Input, InputPin, OutputPin, PinDriver, Pull};
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use icesickle_core::hal::InputPin as _;
+use icesickle_core::Result;
+use log::{info, warn};
+
+use crate::hal::{esp_err, EspPin};
+
+/// GPIO for the normally-closed tamper loop
+pub const TAMPER_PIN: i32 = 4;
+
+/// GPIO for the provisioning jumper (fitted = pulled to ground)
+pub const PROVISION_PIN: i32 = 5;
+
+/// Consecutive open readings before the tamper fires (glitch filter)
+const TRIGGER_POLLS: u8 = 2;
+
+const NVS_NAMESPACE: &str = "icesickle";
+const NVS_LOCKOUT_KEY: &str = "lockout";
+
+/// Tamper loop input plus the persistent lockout latch
+pub struct Tamper<'d, P: esp_idf_hal::gpio::Pin> {
+    pin: EspPin<'d, P>,
+    nvs: EspNvs<NvsDefault>,
+    open_polls: u8,
+}
+
+impl<'d, P> Tamper<'d, P>
+where
+    P: InputPin + OutputPin,
+{
+    /// Configure the tamper input and resolve the latch against the jumper
+    ///
+    /// Returns the monitor and whether the device must stay locked out.
+    pub fn new<J: InputPin + OutputPin>(
+        mut pin: PinDriver<'d, P, Input>,
+        mut jumper: PinDriver<'_, J, Input>,
+        partition: EspDefaultNvsPartition,
+    ) -> Result<(Self, bool)> {
+        pin.set_pull(Pull::Up).map_err(esp_err)?;
+        jumper.set_pull(Pull::Up).map_err(esp_err)?;
+        let mut nvs = EspNvs::new(partition, NVS_NAMESPACE, true).map_err(esp_err)?;
+
+        let latched = nvs.get_u8(NVS_LOCKOUT_KEY).map_err(esp_err)?.is_some();
+        let locked = if latched && jumper.is_low() {
+            nvs.remove(NVS_LOCKOUT_KEY).map_err(esp_err)?;
+            info!("Provisioning jumper fitted - tamper lockout cleared");
+            false
+        } else {
+            latched
+        };
+
+        let tamper = Self {
+            pin: EspPin(pin),
+            nvs,
+            open_polls: 0,
+        };
+        Ok((tamper, locked))
+    }
+
+    /// True once the loop has read open on enough consecutive polls
+    pub fn poll_triggered(&mut self) -> bool {
+        if self.pin.is_low() {
+            self.open_polls = 0;
+            return false;
+        }
+        self.open_polls = self.open_polls.saturating_add(1);
+        self.open_polls == TRIGGER_POLLS
+    }
+
+    /// Persist the lockout so it survives resets and power loss
+    pub fn latch(&mut self) {
+        if let Err(e) = self.nvs.set_u8(NVS_LOCKOUT_KEY, 1) {
+            // Still locked for this boot; the state machine never leaves Lockout
+            warn!("Failed to latch tamper lockout: {}", e);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! On-target tests, run by `cargo test` on a connected devkit
+//!
+//! The firmware binary has no libtest harness (`harness = false`): libtest
+//! expects to exit the process when it is done and, under `panic_abort`,
+//! cannot survive a failing test. `cargo test -p icesickle-firmware`
+//! instead builds the image with `cfg(test)`, and `main` calls [`run`]
+//! where it would start the event loop. The runner's `espflash` flashes
+//! it, and the results arrive on the console in libtest's format.
+//!
+//! Cases run in order on the main task. A failing assertion prints its
+//! message and `test result: FAILED`, then takes ESP-IDF's abort path,
+//! which resets and runs the cases again; a passing run prints
+//! `test result: ok` and leaves the device idle. `cargo xtask target-test`
+//! flashes, stops at the first result line and exits with it.
+//!
+//! Cases with mocks check the logic on the target's compiler and memory
+//! layout; the rest drive the real timer and TRNG.
+
+use std::io::Write;
+
+use icesickle_core::cooldown::{Cooldown, CooldownResult, COOLDOWN_MS};
+use icesickle_core::entropy::HardwareRng;
+use icesickle_core::hal::mock::{MockEntropy, MockPin, MockTimer};
+use icesickle_core::hal::Timer;
+use icesickle_core::IceSickleError;
+
+use crate::button::{self, Button};
+#[cfg(not(feature = "test-vectors"))]
+use crate::hal::EspEntropy;
+use crate::hal::EspTimer;
+use crate::outbox;
+#[cfg(feature = "hw-sha512")]
+use crate::sha;
+#[cfg(feature = "signer-core")]
+use crate::signer::{Job, Signer};
+
+/// Every case, in run order
+const CASES: &[(&str, fn())] = &[
+    #[cfg(feature = "gestures")]
+    (
+        "button::gestures_follow_edges",
+        button::tests::test_gestures_follow_edges,
+    ),
+    (
+        "button::debounce_on_hardware_timer",
+        debounce_on_hardware_timer,
+    ),
+    ("cooldown::boundaries", cooldown_boundaries),
+    ("cooldown::on_hardware_timer", cooldown_on_hardware_timer),
+    (
+        "entropy::stuck_source_flagged",
+        entropy_stuck_source_flagged,
+    ),
+    #[cfg(not(feature = "test-vectors"))]
+    ("entropy::trng_healthy", entropy_trng_healthy),
+    (
+        "outbox::droppable_frames_refused_above_watermark",
+        outbox::tests::test_droppable_frames_refused_above_watermark,
+    ),
+    (
+        "outbox::drain_stops_when_sink_stalls",
+        outbox::tests::test_drain_stops_when_sink_stalls,
+    ),
+    (
+        "outbox::parts_queued_whole_or_not_at_all",
+        outbox::tests::test_parts_queued_whole_or_not_at_all,
+    ),
+    #[cfg(feature = "hw-sha512")]
+    (
+        "sha::sha512_matches_and_benchmarks",
+        sha512_matches_and_benchmarks,
+    ),
+    #[cfg(feature = "signer-core")]
+    (
+        "signer::signs_beside_the_loop",
+        signer_signs_beside_the_loop,
+    ),
+    #[cfg(feature = "signer-core")]
+    (
+        "signer::cancel_returns_nothing",
+        signer_cancel_returns_nothing,
+    ),
+];
+
+/// Run every case and report in libtest's format
+pub fn run() {
+    std::panic::set_hook(Box::new(|info| {
+        println!("FAILED\n\n{}\n\ntest result: FAILED", info);
+        let _ = std::io::stdout().flush();
+    }));
+
+    println!("\nrunning {} tests", CASES.len());
+    for (name, case) in CASES {
+        print!("test {} ... ", name);
+        let _ = std::io::stdout().flush();
+        case();
+        println!("ok");
+    }
+    println!("\ntest result: ok. {} passed; 0 failed", CASES.len());
+}
+
+/// The debounce window measured by the real timer
+fn debounce_on_hardware_timer() {
+    let pin = MockPin::default();
+    let mut button = Button::with_timer(&pin, EspTimer);
+
+    pin.set_low(true);
+    assert!(button.poll_pressed().unwrap());
+
+    // Bounce well inside the window
+    EspTimer.delay_ms(10);
+    pin.set_low(false);
+    assert!(!button.poll_pressed().unwrap());
+    pin.set_low(true);
+    assert!(!button.poll_pressed().unwrap());
+
+    // Release and press again once the window has passed
+    EspTimer.delay_ms(button::DEBOUNCE_MS + 20);
+    pin.set_low(false);
+    assert!(!button.poll_pressed().unwrap());
+    EspTimer.delay_ms(button::DEBOUNCE_MS + 20);
+    pin.set_low(true);
+    assert!(button.poll_pressed().unwrap());
+}
+
+/// One millisecond either side of the cooldown
+fn cooldown_boundaries() {
+    let timer = MockTimer::at(10_000);
+    let cooldown = Cooldown::new();
+    cooldown.gate(&timer).unwrap();
+
+    timer.advance(COOLDOWN_MS - 1);
+    assert_eq!(
+        cooldown.check(&timer),
+        CooldownResult::Wait { remaining_ms: 1 }
+    );
+    assert_eq!(
+        cooldown.gate(&timer),
+        Err(IceSickleError::Cooldown { remaining_ms: 1 })
+    );
+
+    timer.advance(1);
+    assert_eq!(cooldown.check(&timer), CooldownResult::Ready);
+    cooldown.gate(&timer).unwrap();
+    assert!(cooldown.gate(&timer).is_err());
+}
+
+/// The cooldown measured by the real timer
+fn cooldown_on_hardware_timer() {
+    let cooldown = Cooldown::new();
+    // A fresh cooldown counts from uptime 0, which may not have passed yet
+    EspTimer.delay_ms(COOLDOWN_MS as u32);
+    cooldown.gate(&EspTimer).unwrap();
+    assert!(cooldown.gate(&EspTimer).is_err());
+
+    EspTimer.delay_ms(COOLDOWN_MS as u32 / 2);
+    assert!(matches!(
+        cooldown.check(&EspTimer),
+        CooldownResult::Wait { .. }
+    ));
+    // One tick of slack for the FreeRTOS delay
+    EspTimer.delay_ms(COOLDOWN_MS as u32 / 2 + 10);
+    cooldown.gate(&EspTimer).unwrap();
+}
+
+/// A constant source passes the zero check and fails the health check
+fn entropy_stuck_source_flagged() {
+    assert!(HardwareRng::from_source(MockEntropy(0)).is_err());
+    let rng = HardwareRng::from_source(MockEntropy(0x5a)).unwrap();
+    assert!(!rng.is_healthy());
+}
+
+/// The TRNG passes the boot and heartbeat checks, many times over
+#[cfg(not(feature = "test-vectors"))]
+fn entropy_trng_healthy() {
+    let rng = HardwareRng::from_source(EspEntropy).unwrap();
+    for _ in 0..1_000 {
+        assert!(rng.is_healthy());
+    }
+}
+
+/// The peripheral's SHA-512 and signatures match software's; prints how
+/// long each takes
+#[cfg(feature = "hw-sha512")]
+fn sha512_matches_and_benchmarks() {
+    use icesickle_core::scheme::{Ed25519, SignatureScheme};
+    use icesickle_core::sha512::Sha512;
+    use sha2::Digest;
+
+    const ROUNDS: i64 = 32;
+
+    /// Mean time of `f`, kept from being optimized away
+    fn micros<T>(mut f: impl FnMut() -> T) -> i64 {
+        let start = unsafe { esp_idf_sys::esp_timer_get_time() };
+        for _ in 0..ROUNDS {
+            std::hint::black_box(f());
+        }
+        (unsafe { esp_idf_sys::esp_timer_get_time() } - start) / ROUNDS
+    }
+
+    // A payload's worth, then a block-heavy message
+    for len in [200, 4096] {
+        let message = vec![0xa5; len];
+        let software = || Sha512::software().chain_update(&message).finalize();
+        let hardware = || Sha512::on(&sha::SHA512).chain_update(&message).finalize();
+        assert_eq!(software(), hardware());
+        let (soft_us, hard_us) = (micros(software), micros(hardware));
+        print!(
+            "sha512 {} B: {} us software, {} us hardware; ",
+            len, soft_us, hard_us
+        );
+    }
+
+    // Cases run before `main` registers the backend: this signs in software
+    let key = Ed25519::from_seed(&[0x07; 32]).unwrap();
+    let message = [0xa5; 200];
+    let expected = Ed25519::sign(&key, &message);
+    let soft_us = micros(|| Ed25519::sign(&key, &message));
+    assert!(sha::accelerate_sha512());
+    assert_eq!(Ed25519::sign(&key, &message), expected);
+    let hard_us = micros(|| Ed25519::sign(&key, &message));
+    print!(
+        "sign: {} us software, {} us hardware ... ",
+        soft_us, hard_us
+    );
+}
+
+/// A press signs on core 1 while this task signs too, each in its own
+/// workspace slot, and both verify
+#[cfg(feature = "signer-core")]
+fn signer_signs_beside_the_loop() {
+    use icesickle_core::attestation::{self, AttestationEvent};
+    use icesickle_core::protocol::AttestationRecord;
+
+    let press = |gpio| Job {
+        event: AttestationEvent::ButtonPress { gpio },
+        challenge: None,
+        token: None,
+        context: None,
+    };
+    let signer = Signer::start().unwrap();
+    signer.submit(press(0)).unwrap();
+    let rng = HardwareRng::from_source(EspEntropy).unwrap();
+    let here = press(3).sign(&rng).unwrap();
+
+    let deadline = EspTimer.now_ms() + 1_000;
+    let there = loop {
+        if let Some(created) = signer.poll() {
+            break created.unwrap();
+        }
+        assert!(EspTimer.now_ms() < deadline, "signer never answered");
+        EspTimer.delay_ms(1);
+    };
+    assert!(attestation::verify(&AttestationRecord::from(&here)));
+    assert!(attestation::verify(&AttestationRecord::from(&there)));
+
+    let task = unsafe { esp_idf_sys::xTaskGetHandle(c"signer".as_ptr()) };
+    assert!(!task.is_null());
+    assert_eq!(unsafe { esp_idf_sys::xTaskGetCoreID(task) }, 1);
+    assert!(crate::stack::signer_bounds().is_some());
+}
+
+/// Jobs running or queued when tamper cancels them never come back
+#[cfg(feature = "signer-core")]
+fn signer_cancel_returns_nothing() {
+    use icesickle_core::attestation::AttestationEvent;
+
+    let press = |gpio| Job {
+        event: AttestationEvent::ButtonPress { gpio },
+        challenge: Some([0x42; 32]),
+        token: None,
+        context: None,
+    };
+    let signer = Signer::start().unwrap();
+    signer.submit(press(0)).unwrap();
+    // Taken by core 1 or still queued; either way cancelled
+    let _ = signer.submit(press(1));
+    signer.cancel();
+
+    EspTimer.delay_ms(200);
+    assert!(signer.poll().is_none());
+}
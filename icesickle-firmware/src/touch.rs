@@ -0,0 +1,76 @@
+//! Capacitive touch pad trigger (feature `touch`)
+//!
+//! A copper pad, or a wire to one behind the enclosure wall, on touch
+//! channel [`TOUCH_CHANNEL`]. The touch sensor's FSM measures the channel
+//! on a timer in the background; [`Touch::poll`] reads its latest raw count
+//! and `icesickle_core::touch::TouchPad` turns counts into touches.
+//!
+//! The pad is calibrated while the device boots, from readings over about
+//! 160 ms: keep fingers off it until "Touch pad calibrated" is logged.
+
+use esp_idf_sys::{self as sys, esp};
+use icesickle_core::attestation::AttestationEvent;
+use icesickle_core::hal::Timer;
+use icesickle_core::touch::{TouchPad, CALIBRATION_SAMPLES};
+use icesickle_core::{IceSickleError, Result};
+use log::info;
+
+use crate::hal::{esp_err, EspTimer};
+
+/// Touch channel of the pad. Channel n is GPIO n; 3 is the only one no
+/// other feature uses
+pub const TOUCH_CHANNEL: u8 = 3;
+
+/// Time between calibration readings, a few measurement cycles
+const SAMPLE_INTERVAL_MS: u32 = 10;
+
+/// The pad and its calibration
+pub struct Touch {
+    pad: TouchPad,
+}
+
+impl Touch {
+    /// Start the touch sensor and calibrate the pad (blocks for the
+    /// calibration readings)
+    pub fn new() -> Result<Self> {
+        let channel = sys::touch_pad_t::from(TOUCH_CHANNEL);
+        // SAFETY: plain ESP-IDF touch driver calls; nothing else in the
+        // firmware uses the touch sensor
+        unsafe {
+            esp!(sys::touch_pad_init()).map_err(esp_err)?;
+            esp!(sys::touch_pad_config(channel)).map_err(esp_err)?;
+            esp!(sys::touch_pad_set_fsm_mode(
+                sys::touch_fsm_mode_t_TOUCH_FSM_MODE_TIMER
+            ))
+            .map_err(esp_err)?;
+            esp!(sys::touch_pad_fsm_start()).map_err(esp_err)?;
+        }
+
+        let mut samples = [0u32; CALIBRATION_SAMPLES];
+        for sample in &mut samples {
+            EspTimer.delay_ms(SAMPLE_INTERVAL_MS);
+            *sample = read(channel)?;
+        }
+        let pad = TouchPad::calibrate(TOUCH_CHANNEL, samples).ok_or(IceSickleError::Sensor)?;
+        info!(
+            "Touch pad calibrated on channel {} (baseline {})",
+            TOUCH_CHANNEL,
+            pad.baseline()
+        );
+        Ok(Self { pad })
+    }
+
+    /// Read the pad; its event once per touch
+    pub fn poll(&mut self, now_ms: u64) -> Result<Option<AttestationEvent>> {
+        let raw = read(sys::touch_pad_t::from(TOUCH_CHANNEL))?;
+        Ok(self.pad.update(raw, now_ms).then(|| self.pad.event()))
+    }
+}
+
+/// Latest raw count the FSM measured on `channel`
+fn read(channel: sys::touch_pad_t) -> Result<u32> {
+    let mut raw = 0u32;
+    // SAFETY: `raw` outlives the call, which only writes through it
+    esp!(unsafe { sys::touch_pad_read_raw_data(channel, &mut raw) }).map_err(esp_err)?;
+    Ok(raw)
+}
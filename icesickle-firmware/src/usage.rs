@@ -0,0 +1,58 @@
+//! Usage count scheduling (feature `usage-stats`)
+//!
+//! Once [`WINDOW_MS`] has passed, [`Usage::close_due`] hands back the
+//! `UsageCount` event for the window: the number of attestations emitted
+//! in it, noised at [`EPSILON_MILLI`]. See `icesickle_core::usage` for the
+//! mechanism and what the guarantee covers.
+//!
+//! Counts come from the attestation counter, so a window covers every
+//! attestation since the last usage count, except that count itself.
+//! Windows run on uptime like digest windows: a reset ends the open window
+//! without a count.
+
+use icesickle_core::attestation::{self, AttestationEvent};
+use icesickle_core::entropy::HardwareRng;
+use icesickle_core::hal::EntropySource;
+use icesickle_core::usage;
+
+/// Length of a usage window
+pub const WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Privacy parameter ε, in thousandths
+pub const EPSILON_MILLI: u16 = 1_000;
+
+/// The open window: the attestation count and uptime it started at
+pub struct Usage {
+    start_count: u32,
+    start_ms: u64,
+}
+
+impl Usage {
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            start_count: attestation::count(),
+            start_ms: now_ms,
+        }
+    }
+
+    /// The event closing the window, once it has run its length
+    pub fn close_due<S: EntropySource>(
+        &mut self,
+        rng: &HardwareRng<S>,
+        now_ms: u64,
+    ) -> Option<AttestationEvent> {
+        if now_ms.saturating_sub(self.start_ms) < WINDOW_MS {
+            return None;
+        }
+        let count = attestation::count();
+        let event = AttestationEvent::UsageCount {
+            count: usage::noisy_count(rng, count.wrapping_sub(self.start_count), EPSILON_MILLI),
+            epsilon_milli: EPSILON_MILLI,
+            window_s: (WINDOW_MS / 1_000) as u32,
+        };
+        // The count about to be signed belongs to no window
+        self.start_count = count.wrapping_add(1);
+        self.start_ms = now_ms;
+        Some(event)
+    }
+}
@@ -0,0 +1,286 @@
+//! Native USB HID device (feature `usb-hid`)
+//!
+//! Brings up the ESP32-S3 USB-OTG peripheral through TinyUSB with two HID
+//! interfaces:
+//!
+//! - Interface 0, FIDO usage page, 64-byte input and output reports. Host
+//!   reports are queued from the TinyUSB task and drained by the main loop;
+//!   `ctaphid.rs` does all protocol work.
+//! - Interface 1, vendor usage page, input reports only. Every attestation
+//!   is pushed here in `icesickle_core::transport` framing, for host apps and
+//!   WebHID pages. Browsers refuse to open FIDO-page interfaces, hence the
+//!   second one.
+//!
+//! Receive backpressure is the queue bound: reports arriving while the queue
+//! is full are dropped and the host's CTAPHID timeout recovers. Sends wait a
+//! bounded time for the IN endpoint. Pushed reports wait in their own queue
+//! until the host reads them; when nobody does, the oldest messages make room
+//! for new ones.
+//!
+//! The device descriptor deliberately has no serial number string. USB hosts
+//! log and expose serial numbers, which would give every unit a stable,
+//! linkable identifier.
+
+use core::ffi::c_char;
+use std::sync::Mutex;
+
+use esp_idf_sys::tinyusb as tusb;
+use esp_idf_sys::{esp, EspError};
+use log::warn;
+
+use icesickle_core::attestation::Attestation;
+use icesickle_core::ctaphid::{ReportSink, REPORT_LEN};
+use icesickle_core::hal::Timer;
+use icesickle_core::telemetry::{self, Counter};
+use icesickle_core::transport::{self, MAX_REPORTS};
+
+use crate::hal::EspTimer;
+
+/// Espressif's VID with a PID from its test range
+const USB_VID: u16 = 0x303A;
+const USB_PID: u16 = 0x8150;
+
+/// Reports buffered between the TinyUSB task and the main loop
+const RX_QUEUE_LEN: usize = 8;
+
+/// Longest wait for the host to collect a report before dropping it
+const SEND_TIMEOUT_MS: u32 = 50;
+
+/// Pushed reports held for the host: the four longest messages
+const PUSH_QUEUE_LEN: usize = 4 * MAX_REPORTS;
+
+/// HID instances, in interface order
+const CTAPHID_INSTANCE: u8 = 0;
+const PUSH_INSTANCE: u8 = 1;
+
+/// HID report descriptor: FIDO usage page, 64-byte in/out reports
+static REPORT_DESCRIPTOR: [u8; 34] = [
+    0x06, 0xD0, 0xF1, // Usage Page (FIDO Alliance)
+    0x09, 0x01, // Usage (CTAPHID)
+    0xA1, 0x01, // Collection (Application)
+    0x09, 0x20, //   Usage (Input Report Data)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x26, 0xFF, 0x00, //   Logical Maximum (255)
+    0x75, 0x08, //   Report Size (8)
+    0x95, 0x40, //   Report Count (64)
+    0x81, 0x02, //   Input (Data, Var, Abs)
+    0x09, 0x21, //   Usage (Output Report Data)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x26, 0xFF, 0x00, //   Logical Maximum (255)
+    0x75, 0x08, //   Report Size (8)
+    0x95, 0x40, //   Report Count (64)
+    0x91, 0x02, //   Output (Data, Var, Abs)
+    0xC0, // End Collection
+];
+
+/// Push report descriptor: vendor usage page, 64-byte input reports
+static PUSH_REPORT_DESCRIPTOR: [u8; 21] = [
+    0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+    0x09, 0x01, // Usage (Attestation Push)
+    0xA1, 0x01, // Collection (Application)
+    0x09, 0x02, //   Usage (Message Data)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x26, 0xFF, 0x00, //   Logical Maximum (255)
+    0x75, 0x08, //   Report Size (8)
+    0x95, 0x40, //   Report Count (64)
+    0x81, 0x02, //   Input (Data, Var, Abs)
+    0xC0, // End Collection
+];
+
+/// Configuration descriptor: CTAPHID with interrupt IN + OUT, push with IN
+#[rustfmt::skip]
+static CONFIG_DESCRIPTOR: [u8; 66] = [
+    // Configuration: 66 bytes total, 2 interfaces, bus powered, 100 mA
+    9, 0x02, 66, 0, 2, 1, 0, 0x80, 50,
+    // Interface 0: HID, 2 endpoints, no boot protocol
+    9, 0x04, 0, 0, 2, 0x03, 0, 0, 0,
+    // HID 1.11, one report descriptor
+    9, 0x21, 0x11, 0x01, 0, 1, 0x22, REPORT_DESCRIPTOR.len() as u8, 0,
+    // Endpoint 0x01 OUT, interrupt, 64 bytes, 5 ms
+    7, 0x05, 0x01, 0x03, 64, 0, 5,
+    // Endpoint 0x81 IN, interrupt, 64 bytes, 5 ms
+    7, 0x05, 0x81, 0x03, 64, 0, 5,
+    // Interface 1: HID, 1 endpoint, no boot protocol
+    9, 0x04, 1, 0, 1, 0x03, 0, 0, 0,
+    // HID 1.11, one report descriptor
+    9, 0x21, 0x11, 0x01, 0, 1, 0x22, PUSH_REPORT_DESCRIPTOR.len() as u8, 0,
+    // Endpoint 0x82 IN, interrupt, 64 bytes, 10 ms
+    7, 0x05, 0x82, 0x03, 64, 0, 10,
+];
+
+/// Device descriptor (TinyUSB keeps a pointer to it, hence `static`)
+static DEVICE_DESCRIPTOR: tusb::tusb_desc_device_t = tusb::tusb_desc_device_t {
+    bLength: core::mem::size_of::<tusb::tusb_desc_device_t>() as u8,
+    bDescriptorType: 0x01,
+    bcdUSB: 0x0200,
+    bDeviceClass: 0,
+    bDeviceSubClass: 0,
+    bDeviceProtocol: 0,
+    bMaxPacketSize0: 64,
+    idVendor: USB_VID,
+    idProduct: USB_PID,
+    bcdDevice: 0x0100,
+    iManufacturer: 1,
+    iProduct: 2,
+    iSerialNumber: 0, // no serial number, see module docs
+    bNumConfigurations: 1,
+};
+
+/// String descriptor table (index 0 is the language ID)
+struct StringTable([*const c_char; 3]);
+
+// The pointers reference 'static, immutable C string literals
+unsafe impl Sync for StringTable {}
+
+static STRINGS: StringTable = StringTable([
+    c"\x09\x04".as_ptr(), // English (US)
+    c"IceSickle".as_ptr(),
+    c"IceSickle attestation device".as_ptr(),
+]);
+
+static RX_QUEUE: Mutex<heapless::Deque<[u8; REPORT_LEN], RX_QUEUE_LEN>> =
+    Mutex::new(heapless::Deque::new());
+
+/// Pushed reports, each message's reports in order
+static PUSH_QUEUE: Mutex<heapless::Deque<[u8; REPORT_LEN], PUSH_QUEUE_LEN>> =
+    Mutex::new(heapless::Deque::new());
+
+/// Queue `attestation` for the push interface, evicting the oldest messages
+/// if the host has not kept up
+pub fn push(attestation: &Attestation) {
+    let message = transport::message(attestation);
+    let Ok(mut queue) = PUSH_QUEUE.lock() else {
+        return;
+    };
+    let reports = transport::reports(&message);
+    let needed = reports.len();
+    if queue.capacity() - queue.len() < needed {
+        warn!("USB HID push interface not read - oldest attestations dropped");
+        telemetry::record(Counter::SinkError);
+        while queue.capacity() - queue.len() < needed {
+            // Pop the oldest message: its first report, then the rest
+            queue.pop_front();
+            while queue.front().is_some_and(|report| report[0] != 0) {
+                queue.pop_front();
+            }
+        }
+    }
+    for report in reports {
+        let _ = queue.push_back(report);
+    }
+}
+
+/// Handle to the installed HID interface
+pub struct UsbHid {
+    _private: (),
+}
+
+impl UsbHid {
+    /// Install the TinyUSB driver with the HID descriptors above
+    pub fn new() -> Result<Self, EspError> {
+        let config = tusb::tinyusb_config_t {
+            device_descriptor: &DEVICE_DESCRIPTOR,
+            string_descriptor: STRINGS.0.as_ptr() as *mut *const c_char,
+            string_descriptor_count: STRINGS.0.len() as _,
+            external_phy: false,
+            __bindgen_anon_1: tusb::tinyusb_config_t__bindgen_ty_1 {
+                configuration_descriptor: CONFIG_DESCRIPTOR.as_ptr(),
+            },
+            ..Default::default()
+        };
+
+        esp!(unsafe { tusb::tinyusb_driver_install(&config) })?;
+
+        Ok(Self { _private: () })
+    }
+
+    /// Take the next report received from the host, if any
+    pub fn poll_report(&self) -> Option<[u8; REPORT_LEN]> {
+        RX_QUEUE.lock().ok()?.pop_front()
+    }
+
+    /// Hand pushed reports to the push endpoint while it has room
+    pub fn flush_push(&self) {
+        let Ok(mut queue) = PUSH_QUEUE.lock() else {
+            return;
+        };
+        while unsafe { tusb::tud_hid_n_ready(PUSH_INSTANCE) } {
+            let Some(report) = queue.pop_front() else {
+                return;
+            };
+            unsafe {
+                tusb::tud_hid_n_report(PUSH_INSTANCE, 0, report.as_ptr().cast(), REPORT_LEN as u16);
+            }
+        }
+    }
+}
+
+impl ReportSink for UsbHid {
+    fn send_report(&self, report: &[u8; REPORT_LEN]) {
+        // Wait (boundedly) for the host to collect the previous report. The IN
+        // endpoint holds one report, so a host that stops polling costs us at
+        // most SEND_TIMEOUT_MS per report rather than stalling the main loop.
+        let mut waited_ms = 0;
+        while !unsafe { tusb::tud_hid_n_ready(CTAPHID_INSTANCE) } {
+            if waited_ms >= SEND_TIMEOUT_MS {
+                warn!("USB HID host not reading - report dropped");
+                telemetry::record(Counter::SinkError);
+                return;
+            }
+            EspTimer.delay_ms(1);
+            waited_ms += 1;
+        }
+        unsafe {
+            tusb::tud_hid_n_report(
+                CTAPHID_INSTANCE,
+                0,
+                report.as_ptr().cast(),
+                REPORT_LEN as u16,
+            );
+        }
+    }
+}
+
+// --- TinyUSB callbacks (called from the TinyUSB task) ---
+
+#[no_mangle]
+extern "C" fn tud_hid_descriptor_report_cb(instance: u8) -> *const u8 {
+    match instance {
+        PUSH_INSTANCE => PUSH_REPORT_DESCRIPTOR.as_ptr(),
+        _ => REPORT_DESCRIPTOR.as_ptr(),
+    }
+}
+
+#[no_mangle]
+extern "C" fn tud_hid_get_report_cb(
+    _instance: u8,
+    _report_id: u8,
+    _report_type: tusb::hid_report_type_t,
+    _buffer: *mut u8,
+    _reqlen: u16,
+) -> u16 {
+    // Control-pipe GET_REPORT is not used by CTAPHID or the push interface
+    0
+}
+
+#[no_mangle]
+extern "C" fn tud_hid_set_report_cb(
+    instance: u8,
+    _report_id: u8,
+    _report_type: tusb::hid_report_type_t,
+    buffer: *const u8,
+    bufsize: u16,
+) {
+    // The push interface takes no output reports
+    if instance != CTAPHID_INSTANCE || buffer.is_null() || bufsize as usize != REPORT_LEN {
+        return;
+    }
+
+    let mut report = [0u8; REPORT_LEN];
+    report.copy_from_slice(unsafe { core::slice::from_raw_parts(buffer, REPORT_LEN) });
+
+    // A full queue drops the report; the host's CTAPHID timeout recovers
+    if let Ok(mut queue) = RX_QUEUE.lock() {
+        let _ = queue.push_back(report);
+    }
+}
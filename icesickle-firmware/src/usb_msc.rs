@@ -0,0 +1,246 @@
+//! Read-only USB mass-storage volume (feature `usb-msc`)
+//!
+//! Brings up the ESP32-S3 USB-OTG peripheral through TinyUSB as a single
+//! SCSI mass-storage interface serving `icesickle_core::volume`: a small
+//! FAT12 disk in RAM with one `.json` file per attestation in the history.
+//! Hosts mount it like a flash drive, so proofs can be copied off without
+//! any tooling.
+//!
+//! The main loop rebuilds the image whenever the history gains a record
+//! ([`UsbMsc::refresh`]), and the next TEST UNIT READY reports a medium
+//! change so the host drops its cached directory and reads the new one.
+//! Writes are refused with a write-protect sense; the volume is also
+//! reported read-only, so hosts mount it that way. The disk lives only in
+//! RAM and starts empty each boot, like the history.
+//!
+//! The callbacks below serve the image directly, so esp_tinyusb's own
+//! flash/SD storage backend is never linked in. As with `usb_hid`, the
+//! device descriptor has no serial number string, and the volume carries
+//! nothing unit-specific (see `volume`).
+
+use core::ffi::{c_char, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use esp_idf_sys::tinyusb as tusb;
+use esp_idf_sys::{esp, EspError};
+
+use icesickle_core::history::History;
+use icesickle_core::volume::{self, Image, IMAGE_LEN, SECTOR_COUNT, SECTOR_LEN};
+
+/// Espressif's VID with a PID from its test range (next to `usb_hid`'s)
+const USB_VID: u16 = 0x303A;
+const USB_PID: u16 = 0x8151;
+
+/// SCSI sense keys and additional sense codes
+const SENSE_ILLEGAL_REQUEST: u8 = 0x05;
+const SENSE_UNIT_ATTENTION: u8 = 0x06;
+const SENSE_DATA_PROTECT: u8 = 0x07;
+const ASC_INVALID_COMMAND: u8 = 0x20;
+const ASC_WRITE_PROTECTED: u8 = 0x27;
+const ASC_MEDIUM_CHANGED: u8 = 0x28;
+
+/// SCSI PREVENT ALLOW MEDIUM REMOVAL: nothing to lock, always accepted
+const SCSI_PREVENT_ALLOW_MEDIUM_REMOVAL: u8 = 0x1E;
+
+/// Configuration descriptor: one mass-storage interface, bulk IN + OUT
+#[rustfmt::skip]
+static CONFIG_DESCRIPTOR: [u8; 32] = [
+    // Configuration: 32 bytes total, 1 interface, bus powered, 100 mA
+    9, 0x02, 32, 0, 1, 1, 0, 0x80, 50,
+    // Interface 0: mass storage, SCSI transparent, bulk-only, 2 endpoints
+    9, 0x04, 0, 0, 2, 0x08, 0x06, 0x50, 0,
+    // Endpoint 0x01 OUT, bulk, 64 bytes
+    7, 0x05, 0x01, 0x02, 64, 0, 0,
+    // Endpoint 0x81 IN, bulk, 64 bytes
+    7, 0x05, 0x81, 0x02, 64, 0, 0,
+];
+
+/// Device descriptor (TinyUSB keeps a pointer to it, hence `static`)
+static DEVICE_DESCRIPTOR: tusb::tusb_desc_device_t = tusb::tusb_desc_device_t {
+    bLength: core::mem::size_of::<tusb::tusb_desc_device_t>() as u8,
+    bDescriptorType: 0x01,
+    bcdUSB: 0x0200,
+    bDeviceClass: 0,
+    bDeviceSubClass: 0,
+    bDeviceProtocol: 0,
+    bMaxPacketSize0: 64,
+    idVendor: USB_VID,
+    idProduct: USB_PID,
+    bcdDevice: 0x0100,
+    iManufacturer: 1,
+    iProduct: 2,
+    iSerialNumber: 0, // no serial number, see module docs
+    bNumConfigurations: 1,
+};
+
+/// String descriptor table (index 0 is the language ID)
+struct StringTable([*const c_char; 3]);
+
+// The pointers reference 'static, immutable C string literals
+unsafe impl Sync for StringTable {}
+
+static STRINGS: StringTable = StringTable([
+    c"\x09\x04".as_ptr(), // English (US)
+    c"IceSickle".as_ptr(),
+    c"IceSickle attestation volume".as_ptr(),
+]);
+
+/// The disk as the host sees it
+static IMAGE: Mutex<Image> = Mutex::new([0; IMAGE_LEN]);
+
+/// Set when the image is rebuilt; cleared once the host has been told
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Handle to the installed mass-storage interface
+pub struct UsbMsc {
+    /// Public key of the newest record on the volume (unique, unlike the
+    /// counter when it is omitted)
+    published: Option<[u8; 32]>,
+}
+
+impl UsbMsc {
+    /// Format the empty volume and install the TinyUSB driver
+    pub fn new() -> Result<Self, EspError> {
+        if let Ok(mut image) = IMAGE.lock() {
+            volume::build([], &mut image);
+        }
+
+        let config = tusb::tinyusb_config_t {
+            device_descriptor: &DEVICE_DESCRIPTOR,
+            string_descriptor: STRINGS.0.as_ptr() as *mut *const c_char,
+            string_descriptor_count: STRINGS.0.len() as _,
+            external_phy: false,
+            __bindgen_anon_1: tusb::tinyusb_config_t__bindgen_ty_1 {
+                configuration_descriptor: CONFIG_DESCRIPTOR.as_ptr(),
+            },
+            ..Default::default()
+        };
+
+        esp!(unsafe { tusb::tinyusb_driver_install(&config) })?;
+
+        Ok(Self { published: None })
+    }
+
+    /// Rebuild the volume if `history` has gained a record since the last
+    /// rebuild
+    pub fn refresh(&mut self, history: &History) {
+        let newest = history.last().map(|record| record.public_key);
+        if newest == self.published {
+            return;
+        }
+        if let Ok(mut image) = IMAGE.lock() {
+            volume::build(history.iter(), &mut image);
+            self.published = newest;
+            CHANGED.store(true, Ordering::Release);
+        }
+    }
+}
+
+/// Record why the last command failed, for the host's REQUEST SENSE
+fn set_sense(lun: u8, key: u8, code: u8) {
+    unsafe {
+        tusb::tud_msc_set_sense(lun, key, code, 0);
+    }
+}
+
+// --- TinyUSB callbacks (called from the TinyUSB task) ---
+
+#[no_mangle]
+extern "C" fn tud_msc_inquiry_cb(
+    _lun: u8,
+    vendor_id: *mut u8,
+    product_id: *mut u8,
+    product_rev: *mut u8,
+) {
+    // Fixed-width, space-padded ASCII fields
+    unsafe {
+        core::ptr::copy_nonoverlapping(b"IceSickl".as_ptr(), vendor_id, 8);
+        core::ptr::copy_nonoverlapping(b"Attestations    ".as_ptr(), product_id, 16);
+        core::ptr::copy_nonoverlapping(b"1.0 ".as_ptr(), product_rev, 4);
+    }
+}
+
+#[no_mangle]
+extern "C" fn tud_msc_test_unit_ready_cb(lun: u8) -> bool {
+    if CHANGED.swap(false, Ordering::Acquire) {
+        set_sense(lun, SENSE_UNIT_ATTENTION, ASC_MEDIUM_CHANGED);
+        return false;
+    }
+    true
+}
+
+#[no_mangle]
+extern "C" fn tud_msc_capacity_cb(_lun: u8, block_count: *mut u32, block_size: *mut u16) {
+    unsafe {
+        *block_count = SECTOR_COUNT as u32;
+        *block_size = SECTOR_LEN as u16;
+    }
+}
+
+#[no_mangle]
+extern "C" fn tud_msc_start_stop_cb(
+    _lun: u8,
+    _power_condition: u8,
+    _start: bool,
+    _load_eject: bool,
+) -> bool {
+    // Nothing to spin up or down
+    true
+}
+
+#[no_mangle]
+extern "C" fn tud_msc_is_writable_cb(_lun: u8) -> bool {
+    false
+}
+
+#[no_mangle]
+extern "C" fn tud_msc_read10_cb(
+    _lun: u8,
+    lba: u32,
+    offset: u32,
+    buffer: *mut c_void,
+    bufsize: u32,
+) -> i32 {
+    let start = lba as usize * SECTOR_LEN + offset as usize;
+    let len = bufsize as usize;
+    let Ok(image) = IMAGE.lock() else {
+        return -1;
+    };
+    let Some(bytes) = image.get(start..start + len) else {
+        return -1;
+    };
+    unsafe {
+        core::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer.cast::<u8>(), len);
+    }
+    len as i32
+}
+
+#[no_mangle]
+extern "C" fn tud_msc_write10_cb(
+    lun: u8,
+    _lba: u32,
+    _offset: u32,
+    _buffer: *mut u8,
+    _bufsize: u32,
+) -> i32 {
+    set_sense(lun, SENSE_DATA_PROTECT, ASC_WRITE_PROTECTED);
+    -1
+}
+
+#[no_mangle]
+extern "C" fn tud_msc_scsi_cb(
+    lun: u8,
+    scsi_cmd: *const u8,
+    _buffer: *mut c_void,
+    _bufsize: u16,
+) -> i32 {
+    // Commands TinyUSB does not answer itself
+    match unsafe { *scsi_cmd } {
+        SCSI_PREVENT_ALLOW_MEDIUM_REMOVAL => 0,
+        _ => {
+            set_sense(lun, SENSE_ILLEGAL_REQUEST, ASC_INVALID_COMMAND);
+            -1
+        }
+    }
+}
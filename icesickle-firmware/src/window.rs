@@ -0,0 +1,59 @@
+//! Window digest scheduling (feature `window-digest`)
+//!
+//! Every attestation the device emits is added to the current window's
+//! Merkle accumulator. Once [`WINDOW_MS`] has passed, [`Window::close_due`]
+//! hands back the `WindowDigest` event that closes it, and a new window
+//! starts. See `icesickle_core::merkle` for the tree and what a collector
+//! checks.
+//!
+//! Windows run on uptime, so a reset ends the current window without a
+//! digest; the attestation counter restarting shows the collector why.
+//! The length is a build-time constant until there is a provisioning mode
+//! to unlock `SetConfig`.
+
+use icesickle_core::attestation::{Attestation, AttestationEvent};
+use icesickle_core::merkle::{self, Accumulator};
+use log::warn;
+
+/// Length of a digest window
+pub const WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// The open window: its accumulator and when it started
+pub struct Window {
+    acc: Accumulator,
+    start_ms: u64,
+}
+
+impl Window {
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            acc: Accumulator::new(),
+            start_ms: now_ms,
+        }
+    }
+
+    /// Add an emitted attestation to the open window
+    pub fn add(&mut self, attestation: &Attestation) {
+        let leaf = merkle::leaf_hash(
+            attestation.public_key_bytes(),
+            attestation.signature_bytes(),
+        );
+        if !self.acc.push(leaf) {
+            warn!("Digest window full - attestation not covered");
+        }
+    }
+
+    /// The event closing the window, once it has run its length
+    pub fn close_due(&mut self, now_ms: u64) -> Option<AttestationEvent> {
+        if now_ms.saturating_sub(self.start_ms) < WINDOW_MS {
+            return None;
+        }
+        let event = AttestationEvent::WindowDigest {
+            root: self.acc.root(),
+            count: self.acc.count(),
+        };
+        self.acc.reset();
+        self.start_ms = now_ms;
+        Some(event)
+    }
+}
@@ -0,0 +1,703 @@
+//! Attestation payloads and records, and checking them
+//!
+//! The payload is what an attestation signs: the event, its times and
+//! counter, and every field later payload versions added, postcard-encoded
+//! behind [`SIGNING_DOMAIN`]. An [`AttestationRecord`] is a payload with the
+//! public key and signature; it renders as a fixed-format line, a compact
+//! binary form or compact text, and parses back from each, and [`verify`]
+//! checks one. All of it works in fixed buffers.
+//!
+//! Making attestations is `icesickle_core::attestation`.
+
+use core::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
+
+use crate::auth::TokenProof;
+use crate::base45;
+use crate::boot::ResetReason;
+use crate::context::{Context, MAX_CONTEXT_LEN};
+use crate::ct;
+use crate::policy::Policy;
+use crate::scheme::Algorithm;
+use crate::wallclock::{TimeSource, WallTime};
+
+/// Current payload format version
+pub const PAYLOAD_VERSION: u8 = 12;
+
+/// Prefix of every attestation signature's message (payload version 12)
+///
+/// The signature covers `SIGNING_DOMAIN || payload` rather than the bare
+/// postcard bytes, so it cannot pass for a signature over the same bytes
+/// in another protocol, the companion formats included. Earlier payloads
+/// were signed bare, and no longer verify.
+pub const SIGNING_DOMAIN: &[u8] = b"IceSickle-attestation-v2";
+
+/// First payload version signed behind [`SIGNING_DOMAIN`]
+pub const SIGNING_DOMAIN_VERSION: u8 = 12;
+
+/// Upper bound on an encoded payload (largest event plus maximal varints)
+pub const MAX_PAYLOAD_LEN: usize = 240;
+
+/// Encoded payload bytes
+pub type PayloadBytes = heapless::Vec<u8, MAX_PAYLOAD_LEN>;
+
+/// Upper bound on a signed message: the domain prefix and a payload
+pub const MAX_SIGNED_LEN: usize = SIGNING_DOMAIN.len() + MAX_PAYLOAD_LEN;
+
+/// What an attestation signature covers (see [`signed_message`])
+pub type SignedMessage = heapless::Vec<u8, MAX_SIGNED_LEN>;
+
+/// Hex-encoded public key
+pub type PublicKeyHex = heapless::String<64>;
+
+/// Hex-encoded signature
+pub type SignatureHex = heapless::String<128>;
+
+/// Fixed-format output line (see [`AttestationRecord::fixed_line`])
+pub type FixedLine = heapless::String<672>;
+
+/// Upper bound on the compact form: payload, key and signature
+pub const MAX_COMPACT_LEN: usize = MAX_PAYLOAD_LEN + 32 + 64;
+
+/// Compact binary form (see [`AttestationRecord::compact`])
+pub type Compact = heapless::Vec<u8, MAX_COMPACT_LEN>;
+
+/// Start of the compact text form, which is otherwise base45
+pub const COMPACT_TEXT_PREFIX: &str = "ICESICKLE:";
+
+/// Compact text form (see [`AttestationRecord::compact_text`]): the prefix
+/// and 1.5 characters a byte
+pub type CompactText = heapless::String<528>;
+
+/// Upper bound on an encoded event
+pub const MAX_EVENT_LEN: usize = 48;
+
+/// Upper bound on the JSON record (longest event, largest numbers)
+pub const MAX_JSON_LEN: usize = 1024;
+
+/// JSON record, one line (see [`AttestationRecord::json`])
+pub type JsonRecord = heapless::String<MAX_JSON_LEN>;
+
+/// Events that can trigger an attestation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttestationEvent {
+    /// Physical button press
+    ButtonPress { gpio: u8 },
+    /// Future: other physical events (switch, sensor threshold, etc.)
+    Unknown,
+    /// Button press approving host-streamed data, identified by its SHA-256
+    DataDigest {
+        gpio: u8,
+        sha256: [u8; 32],
+        len: u64,
+    },
+    /// Enclosure tamper switch opened, as signed before
+    /// [`AttestationEvent::TamperDetected`] named the GPIO
+    Tamper,
+    /// Periodic evidence of continued presence (see `presence`)
+    Presence { gpio: u8 },
+    /// Closes a window: Merkle root over its attestations (see `merkle`)
+    WindowDigest { root: [u8; 32], count: u32 },
+    /// Vouches for a peer device's attestation (see `witness`)
+    Witness { peer: [u8; 32] },
+    /// A coin/credit pulse burst of `count` pulses (see `credit`)
+    CreditPulse { count: u16 },
+    /// Salted hash of a code keyed at the device (see `keypad`)
+    KeypadEntry { hash: [u8; 32] },
+    /// An I2C sensor reading crossed a threshold (see `sensor`)
+    Sensor { sensor: u8, channel: u8, value: i32 },
+    /// The device started up (see `boot`)
+    Boot {
+        reset_reason: ResetReason,
+        fw_hash: [u8; 32],
+    },
+    /// Attestations in a window plus calibrated noise (see `usage`)
+    UsageCount {
+        count: i32,
+        epsilon_milli: u16,
+        window_s: u32,
+    },
+    /// Button press with the levels of other GPIOs at that instant (see
+    /// `snapshot`)
+    GpioSnapshot { gpio: u8, mask: u64, levels: u64 },
+    /// Capacitive touch pad touched (see `touch`)
+    TouchPad { channel: u8 },
+    /// Button held for `duration_ms` (see `gesture`)
+    ButtonHold { gpio: u8, duration_ms: u32 },
+    /// Button pressed twice in quick succession (see `gesture`)
+    ButtonDoublePress { gpio: u8 },
+    /// Merkle root over a batch of `count` presses (see `batch`)
+    BatchRoot { root: [u8; 32], count: u16 },
+    /// Tamper loop on `gpio` opened; signed on the way into lockout (see
+    /// the firmware's `tamper`)
+    TamperDetected { gpio: u8 },
+    /// Presses on two buttons, `skew_ms` apart (see `dual`)
+    DualPress {
+        gpio_a: u8,
+        gpio_b: u8,
+        skew_ms: u32,
+    },
+}
+
+impl AttestationEvent {
+    /// The variant name, for human-readable and JSON outputs
+    ///
+    /// An appended variant is appended to the DSSE predicate schema too.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AttestationEvent::ButtonPress { .. } => "ButtonPress",
+            AttestationEvent::Unknown => "Unknown",
+            AttestationEvent::DataDigest { .. } => "DataDigest",
+            AttestationEvent::Tamper => "Tamper",
+            AttestationEvent::Presence { .. } => "Presence",
+            AttestationEvent::WindowDigest { .. } => "WindowDigest",
+            AttestationEvent::Witness { .. } => "Witness",
+            AttestationEvent::CreditPulse { .. } => "CreditPulse",
+            AttestationEvent::KeypadEntry { .. } => "KeypadEntry",
+            AttestationEvent::Sensor { .. } => "Sensor",
+            AttestationEvent::Boot { .. } => "Boot",
+            AttestationEvent::UsageCount { .. } => "UsageCount",
+            AttestationEvent::GpioSnapshot { .. } => "GpioSnapshot",
+            AttestationEvent::TouchPad { .. } => "TouchPad",
+            AttestationEvent::ButtonHold { .. } => "ButtonHold",
+            AttestationEvent::ButtonDoublePress { .. } => "ButtonDoublePress",
+            AttestationEvent::BatchRoot { .. } => "BatchRoot",
+            AttestationEvent::TamperDetected { .. } => "TamperDetected",
+            AttestationEvent::DualPress { .. } => "DualPress",
+        }
+    }
+}
+
+/// The payload that gets signed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestationPayload {
+    /// Protocol version (for future compatibility)
+    pub version: u8,
+    /// The triggering event
+    pub event: AttestationEvent,
+    /// Milliseconds since device boot
+    pub timestamp_ms: u64,
+    /// Monotonic counter (survives soft resets within a power cycle); may be
+    /// offset or omitted (see `policy`)
+    pub counter: u32,
+    /// UTC time, when an external reference is available (version 2; the
+    /// stale flag is version 3)
+    pub wall_clock: Option<WallTime>,
+    /// Privacy trade-offs applied to the fields above (version 4)
+    pub policy: Policy,
+    /// Presses refused by the cooldown since the previous attestation
+    /// (version 5)
+    pub suppressed: u32,
+    /// Verifier challenge this attestation answers (version 6; see
+    /// `challenge`)
+    pub challenge: Option<[u8; 32]>,
+    /// Authorization token spent on this attestation (version 7; see
+    /// `auth`)
+    pub token: Option<TokenProof>,
+    /// Random nonce drawn once per boot (version 8; see `boot_nonce`)
+    pub boot_nonce: Option<[u8; 16]>,
+    /// Signature scheme of the key (version 9; see `scheme`)
+    pub algorithm: Algorithm,
+    /// Application context the host set for this press (version 10; see
+    /// `context`)
+    pub context: Option<Context>,
+    /// SHA-256 of the running firmware image (version 11; see
+    /// `set_measurement`)
+    pub measurement: Option<[u8; 32]>,
+}
+
+impl AttestationPayload {
+    /// The payload a record's signature covers
+    pub fn of(record: &AttestationRecord) -> Self {
+        Self {
+            version: record.version,
+            event: record.event,
+            timestamp_ms: record.timestamp_ms,
+            counter: record.counter,
+            wall_clock: record.wall_clock,
+            policy: record.policy,
+            suppressed: record.suppressed,
+            challenge: record.challenge,
+            token: record.token,
+            boot_nonce: record.boot_nonce,
+            algorithm: record.algorithm,
+            context: record.context.clone(),
+            measurement: record.measurement,
+        }
+    }
+}
+
+/// Public fields of an attestation, sufficient to rebuild and verify the
+/// signed payload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestationRecord {
+    pub version: u8,
+    pub event: AttestationEvent,
+    pub timestamp_ms: u64,
+    pub counter: u32,
+    pub public_key: [u8; 32],
+    #[serde(with = "BigArray")]
+    pub signature: [u8; 64],
+    /// Signed UTC time, if the device had a reference (payload version 2;
+    /// the stale flag is version 3)
+    pub wall_clock: Option<WallTime>,
+    /// Privacy trade-offs applied to the fields above (payload version 4)
+    pub policy: Policy,
+    /// Presses refused by the cooldown since the previous attestation
+    /// (payload version 5)
+    pub suppressed: u32,
+    /// Verifier challenge the attestation answers (payload version 6)
+    pub challenge: Option<[u8; 32]>,
+    /// Authorization token the attestation spent (payload version 7)
+    pub token: Option<TokenProof>,
+    /// Random nonce of the boot the attestation was made in (payload
+    /// version 8)
+    pub boot_nonce: Option<[u8; 16]>,
+    /// Signature scheme of the public key (payload version 9)
+    pub algorithm: Algorithm,
+    /// Application context the press was set for (payload version 10)
+    pub context: Option<Context>,
+    /// SHA-256 of the firmware image that made it (payload version 11)
+    pub measurement: Option<[u8; 32]>,
+}
+
+impl AttestationRecord {
+    /// Single-line rendering that needs no `core::fmt` machinery
+    ///
+    /// `ATT <version> <counter> <timestamp_ms> <wall_clock> <policy> <suppressed> <challenge> <token> <boot_nonce> <algorithm> <context> <measurement> <event> <public_key> <signature>`
+    /// followed by a newline. Numbers are decimal; `wall_clock` is
+    /// `<unix_s>:<source>:<stale>` (source as its wire code, stale as 0 or 1)
+    /// or `-`; `policy` is its bits; `challenge` is hex or `-`; `token` is
+    /// the hex of the proof's nonce then tag, or `-`; `boot_nonce` is hex or
+    /// `-`; `algorithm` is its wire code; `context` and `measurement` are hex
+    /// or `-`; `event` is the hex of its postcard encoding, so every signed
+    /// field is recoverable.
+    pub fn fixed_line(&self) -> FixedLine {
+        let mut event_buf = [0u8; MAX_EVENT_LEN];
+        let event = postcard::to_slice(&self.event, &mut event_buf).map_or(&[][..], |b| &*b);
+
+        let mut line = FixedLine::new();
+        // Capacity covers the longest event, so none of these pushes fail
+        let _ = line.push_str("ATT ");
+        push_decimal(&mut line, self.version.into());
+        let _ = line.push(' ');
+        push_decimal(&mut line, self.counter.into());
+        let _ = line.push(' ');
+        push_decimal(&mut line, self.timestamp_ms);
+        let _ = line.push(' ');
+        match self.wall_clock {
+            Some(wall) => {
+                push_decimal(&mut line, wall.unix_s);
+                let _ = line.push(':');
+                push_decimal(&mut line, wall.source as u64);
+                let _ = line.push(':');
+                push_decimal(&mut line, wall.stale.into());
+            }
+            None => {
+                let _ = line.push('-');
+            }
+        }
+        let _ = line.push(' ');
+        push_decimal(&mut line, self.policy.bits().into());
+        let _ = line.push(' ');
+        push_decimal(&mut line, self.suppressed.into());
+        let _ = line.push(' ');
+        match &self.challenge {
+            Some(challenge) => {
+                let _ = line.push_str(&hex_encode::<64>(challenge));
+            }
+            None => {
+                let _ = line.push('-');
+            }
+        }
+        let _ = line.push(' ');
+        match &self.token {
+            Some(token) => {
+                let _ = line.push_str(&hex_encode::<64>(&token.to_bytes()));
+            }
+            None => {
+                let _ = line.push('-');
+            }
+        }
+        let _ = line.push(' ');
+        match &self.boot_nonce {
+            Some(nonce) => {
+                let _ = line.push_str(&hex_encode::<32>(nonce));
+            }
+            None => {
+                let _ = line.push('-');
+            }
+        }
+        let _ = line.push(' ');
+        push_decimal(&mut line, self.algorithm as u64);
+        let _ = line.push(' ');
+        match &self.context {
+            Some(context) => {
+                let _ = line.push_str(&hex_encode::<64>(context));
+            }
+            None => {
+                let _ = line.push('-');
+            }
+        }
+        let _ = line.push(' ');
+        match &self.measurement {
+            Some(measurement) => {
+                let _ = line.push_str(&hex_encode::<64>(measurement));
+            }
+            None => {
+                let _ = line.push('-');
+            }
+        }
+        let _ = line.push(' ');
+        let _ = line.push_str(&hex_encode::<{ 2 * MAX_EVENT_LEN }>(event));
+        let _ = line.push(' ');
+        let _ = line.push_str(&hex_encode::<64>(&self.public_key));
+        let _ = line.push(' ');
+        let _ = line.push_str(&hex_encode::<128>(&self.signature));
+        let _ = line.push('\n');
+        line
+    }
+
+    /// Parse a fixed-format line back into the record it renders; `None`
+    /// if any field is missing or malformed. The trailing newline is
+    /// optional.
+    pub fn from_fixed_line(line: &str) -> Option<Self> {
+        let mut fields = line.trim_end().split(' ');
+        if fields.next()? != "ATT" {
+            return None;
+        }
+        let version = fields.next()?.parse().ok()?;
+        let counter = fields.next()?.parse().ok()?;
+        let timestamp_ms = fields.next()?.parse().ok()?;
+        let wall_clock = match fields.next()? {
+            "-" => None,
+            wall => {
+                let mut parts = wall.split(':');
+                let unix_s = parts.next()?.parse().ok()?;
+                let source = match parts.next()? {
+                    "0" => TimeSource::Gps,
+                    "1" => TimeSource::Rtc,
+                    _ => return None,
+                };
+                let stale = match parts.next()? {
+                    "0" => false,
+                    "1" => true,
+                    _ => return None,
+                };
+                if parts.next().is_some() {
+                    return None;
+                }
+                Some(WallTime {
+                    unix_s,
+                    source,
+                    stale,
+                })
+            }
+        };
+        let policy = Policy::from_bits(fields.next()?.parse().ok()?);
+        let suppressed = fields.next()?.parse().ok()?;
+        let challenge = match fields.next()? {
+            "-" => None,
+            challenge => Some(hex_decode_array(challenge)?),
+        };
+        let token = match fields.next()? {
+            "-" => None,
+            token => Some(TokenProof::from_bytes(&hex_decode_array(token)?)),
+        };
+        let boot_nonce = match fields.next()? {
+            "-" => None,
+            nonce => Some(hex_decode_array(nonce)?),
+        };
+        let algorithm = match fields.next()? {
+            "0" => Algorithm::Ed25519,
+            "1" => Algorithm::P256,
+            _ => return None,
+        };
+        let context = match fields.next()? {
+            "-" => None,
+            context => {
+                let mut bytes = [0u8; MAX_CONTEXT_LEN];
+                let len = hex_decode(context, &mut bytes)?;
+                Some(Context::from_slice(&bytes[..len]).ok()?)
+            }
+        };
+        let measurement = match fields.next()? {
+            "-" => None,
+            measurement => Some(hex_decode_array(measurement)?),
+        };
+        let mut event_buf = [0u8; MAX_EVENT_LEN];
+        let event_len = hex_decode(fields.next()?, &mut event_buf)?;
+        let event = postcard::from_bytes(&event_buf[..event_len]).ok()?;
+        let public_key = hex_decode_array(fields.next()?)?;
+        let signature = hex_decode_array(fields.next()?)?;
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            version,
+            event,
+            timestamp_ms,
+            counter,
+            public_key,
+            signature,
+            wall_clock,
+            policy,
+            suppressed,
+            challenge,
+            token,
+            boot_nonce,
+            algorithm,
+            context,
+            measurement,
+        })
+    }
+
+    /// The signed payload bytes, then the public key, then the signature
+    ///
+    /// The payload's encoding delimits itself, so the form needs no lengths
+    /// or separators: it is 96 bytes longer than the payload.
+    pub fn compact(&self) -> Compact {
+        let mut payload_buf = [0u8; MAX_PAYLOAD_LEN];
+        let payload = postcard::to_slice(&AttestationPayload::of(self), &mut payload_buf)
+            .map_or(&[][..], |b| &*b);
+
+        let mut out = Compact::new();
+        // Capacity covers the largest payload, so none of these fail
+        let _ = out.extend_from_slice(payload);
+        let _ = out.extend_from_slice(&self.public_key);
+        let _ = out.extend_from_slice(&self.signature);
+        out
+    }
+
+    /// Parse the compact form; `None` if the payload does not decode or is
+    /// not followed by exactly a key and a signature
+    pub fn from_compact(bytes: &[u8]) -> Option<Self> {
+        let (payload, rest) = postcard::take_from_bytes::<AttestationPayload>(bytes).ok()?;
+        if rest.len() != 32 + 64 {
+            return None;
+        }
+        let (public_key, signature) = rest.split_at(32);
+        Some(Self {
+            version: payload.version,
+            event: payload.event,
+            timestamp_ms: payload.timestamp_ms,
+            counter: payload.counter,
+            public_key: public_key.try_into().ok()?,
+            signature: signature.try_into().ok()?,
+            wall_clock: payload.wall_clock,
+            policy: payload.policy,
+            suppressed: payload.suppressed,
+            challenge: payload.challenge,
+            token: payload.token,
+            boot_nonce: payload.boot_nonce,
+            algorithm: payload.algorithm,
+            context: payload.context,
+            measurement: payload.measurement,
+        })
+    }
+
+    /// [`COMPACT_TEXT_PREFIX`], then the compact form in base45: QR
+    /// alphanumeric characters only, so it fits a smaller symbol than the
+    /// fixed-format line and scans back unchanged
+    pub fn compact_text(&self) -> CompactText {
+        let mut text = CompactText::new();
+        let _ = text.push_str(COMPACT_TEXT_PREFIX);
+        base45::encode(&self.compact(), &mut text);
+        text
+    }
+
+    /// Parse the compact text form (surrounding whitespace is ignored)
+    pub fn from_compact_text(text: &str) -> Option<Self> {
+        let text = text.trim().strip_prefix(COMPACT_TEXT_PREFIX)?;
+        let mut bytes = [0u8; MAX_COMPACT_LEN];
+        let len = base45::decode(text, &mut bytes)?;
+        Self::from_compact(&bytes[..len])
+    }
+
+    /// The record as one line of JSON, every signed field by name
+    ///
+    /// `payloadVersion`, `event` (`type` is its name, `postcard` the hex of
+    /// its encoding, which is what the signature covers), `counter`,
+    /// `timestampMs`, `wallClock`, `policy`, `suppressed`, `challenge`,
+    /// `token`, `bootNonce`, `context`, `measurement` and `algorithm`, then
+    /// `publicKey`, `did` and `signature`. Absent fields are `null`; field
+    /// names follow the DSSE predicate.
+    pub fn json(&self) -> JsonRecord {
+        let mut event_buf = [0u8; MAX_EVENT_LEN];
+        let event = postcard::to_slice(&self.event, &mut event_buf).map_or(&[][..], |b| &*b);
+
+        let mut out = JsonRecord::new();
+        let _ = write!(
+            out,
+            "{{\"payloadVersion\":{},\
+             \"event\":{{\"type\":\"{}\",\"postcard\":\"{}\"}},\
+             \"counter\":{},\"timestampMs\":{},\"wallClock\":",
+            self.version,
+            self.event.name(),
+            hex_encode::<{ 2 * MAX_EVENT_LEN }>(event),
+            self.counter,
+            self.timestamp_ms,
+        );
+        let _ = match self.wall_clock {
+            Some(wall) => write!(
+                out,
+                "{{\"unixS\":{},\"source\":\"{}\",\"stale\":{}}}",
+                wall.unix_s,
+                match wall.source {
+                    TimeSource::Gps => "gps",
+                    TimeSource::Rtc => "rtc",
+                },
+                wall.stale
+            ),
+            None => write!(out, "null"),
+        };
+        let _ = write!(
+            out,
+            ",\"policy\":{},\"suppressed\":{},\"challenge\":",
+            self.policy.bits(),
+            self.suppressed,
+        );
+        let _ = match &self.challenge {
+            Some(challenge) => write!(out, "\"{}\"", hex_encode::<64>(challenge)),
+            None => write!(out, "null"),
+        };
+        let _ = write!(out, ",\"token\":");
+        let _ = match &self.token {
+            Some(token) => write!(out, "\"{}\"", hex_encode::<64>(&token.to_bytes())),
+            None => write!(out, "null"),
+        };
+        let _ = write!(out, ",\"bootNonce\":");
+        let _ = match &self.boot_nonce {
+            Some(nonce) => write!(out, "\"{}\"", hex_encode::<32>(nonce)),
+            None => write!(out, "null"),
+        };
+        let _ = write!(out, ",\"context\":");
+        let _ = match &self.context {
+            Some(context) => write!(out, "\"{}\"", hex_encode::<64>(context)),
+            None => write!(out, "null"),
+        };
+        let _ = write!(out, ",\"measurement\":");
+        let _ = match &self.measurement {
+            Some(measurement) => write!(out, "\"{}\"", hex_encode::<64>(measurement)),
+            None => write!(out, "null"),
+        };
+        let _ = write!(out, ",\"algorithm\":\"{}\"", self.algorithm.name());
+        let _ = writeln!(
+            out,
+            ",\"publicKey\":\"{}\",\"did\":\"{}\",\"signature\":\"{}\"}}",
+            hex_encode::<64>(&self.public_key),
+            self.algorithm.did_key(&self.public_key),
+            hex_encode::<128>(&self.signature),
+        );
+        out
+    }
+}
+
+/// Append `n` in decimal
+fn push_decimal<const N: usize>(out: &mut heapless::String<N>, mut n: u64) {
+    let mut digits = [0u8; 20];
+    let mut i = digits.len();
+    loop {
+        i -= 1;
+        digits[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    for &d in &digits[i..] {
+        let _ = out.push(d as char);
+    }
+}
+
+/// Check a received attestation's signature over its re-encoded payload
+///
+/// For attestations from another device (see `witness`) and the host
+/// verifier; this device's own are correct by construction.
+pub fn verify(record: &AttestationRecord) -> bool {
+    let mut payload_buf = [0u8; MAX_PAYLOAD_LEN];
+    let Ok(payload_bytes) = postcard::to_slice(&AttestationPayload::of(record), &mut payload_buf)
+    else {
+        return false;
+    };
+    record.algorithm.verify(
+        &record.public_key,
+        &signed_message(payload_bytes),
+        &record.signature,
+    )
+}
+
+/// The message an attestation signature covers: [`SIGNING_DOMAIN`], then
+/// the encoded `payload`
+pub fn signed_message(payload: &[u8]) -> SignedMessage {
+    let mut message = SignedMessage::new();
+    // Capacity covers the largest payload; a longer one was never signed
+    let _ = message.extend_from_slice(SIGNING_DOMAIN);
+    let _ = message.extend_from_slice(payload);
+    message
+}
+
+/// Decode hex (either case) into `out`, returning the bytes written; `None`
+/// for an odd length, a non-hex digit or more bytes than `out` holds
+///
+/// For public data from hosts and other devices; not constant time.
+pub fn hex_decode(hex: &str, out: &mut [u8]) -> Option<usize> {
+    let hex = hex.as_bytes();
+    if !hex.len().is_multiple_of(2) || hex.len() / 2 > out.len() {
+        return None;
+    }
+    for (byte, pair) in out.iter_mut().zip(hex.chunks_exact(2)) {
+        let high = (pair[0] as char).to_digit(16)?;
+        let low = (pair[1] as char).to_digit(16)?;
+        *byte = ((high << 4) | low) as u8;
+    }
+    Some(hex.len() / 2)
+}
+
+/// Decode hex of exactly `N` bytes
+pub fn hex_decode_array<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    let mut out = [0u8; N];
+    (hex_decode(hex, &mut out)? == N).then_some(out)
+}
+
+/// Simple hex encoding (no external dependency, no allocation)
+///
+/// Output is truncated to whole bytes that fit in `N` characters. Digits are
+/// computed in constant time (see `ct`), never looked up by secret index.
+pub fn hex_encode<const N: usize>(bytes: &[u8]) -> heapless::String<N> {
+    let mut s = heapless::String::new();
+    for &b in bytes.iter().take(N / 2) {
+        // Capacity for both characters is guaranteed by take(N / 2)
+        let _ = s.push(ct::hex_digit(b >> 4) as char);
+        let _ = s.push(ct::hex_digit(b) as char);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_encode() {
+        assert_eq!(hex_encode::<8>(&[0xde, 0xad, 0xbe, 0xef]), "deadbeef");
+        assert_eq!(hex_encode::<4>(&[0x00, 0xff]), "00ff");
+        assert_eq!(hex_encode::<3>(&[0x00, 0xff]), "00");
+    }
+
+    #[test]
+    fn test_hex_decode() {
+        let mut out = [0u8; 4];
+        assert_eq!(hex_decode("DEadbeef", &mut out), Some(4));
+        assert_eq!(out, [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(hex_decode("00ff", &mut out), Some(2));
+        assert_eq!(hex_decode("0", &mut out), None);
+        assert_eq!(hex_decode("0g", &mut out), None);
+        assert_eq!(hex_decode("0011223344", &mut out), None);
+        assert_eq!(hex_decode_array::<2>("00ff"), Some([0x00, 0xff]));
+        assert_eq!(hex_decode_array::<2>("ff"), None);
+    }
+}
@@ -0,0 +1,55 @@
+//! Token proofs, as a payload carries them
+//!
+//! How tokens are issued, loaded and spent is `icesickle_core::auth`.
+
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::ct;
+
+/// Domain separation for token proof tags
+const PROOF_INFO: &[u8] = b"IceSickle token proof v1";
+
+/// Signed evidence that an attestation spent a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenProof {
+    /// The token's nonce, which the issuer redeems once
+    pub nonce: [u8; 16],
+    /// HKDF-SHA256 of the token key over the nonce and public key
+    pub tag: [u8; 16],
+}
+
+impl TokenProof {
+    /// Nonce then tag, as the fixed line and companions render it
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[..16].copy_from_slice(&self.nonce);
+        bytes[16..].copy_from_slice(&self.tag);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let (nonce, tag) = bytes.split_at(16);
+        Self {
+            nonce: nonce.try_into().unwrap_or_default(),
+            tag: tag.try_into().unwrap_or_default(),
+        }
+    }
+
+    /// Check the tag under `key`, the issuer's VOPRF output for the nonce,
+    /// for the attestation signed by `public_key` (constant time)
+    pub fn verify(&self, key: &[u8; 32], public_key: &[u8; 32]) -> bool {
+        ct::ct_eq(&tag(key, &self.nonce, public_key), &self.tag)
+    }
+}
+
+/// HKDF-SHA256 of `key` over the nonce and `public_key`, truncated to 16
+/// bytes
+pub fn tag(key: &[u8; 32], nonce: &[u8; 16], public_key: &[u8; 32]) -> [u8; 16] {
+    let mut tag = [0u8; 16];
+    // 16 bytes is far below HKDF's output limit, so expansion cannot fail
+    let _ = Hkdf::<Sha256>::new(None, key)
+        .expand_multi_info(&[PROOF_INFO, nonce, public_key], &mut tag);
+    tag
+}
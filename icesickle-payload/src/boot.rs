@@ -0,0 +1,28 @@
+//! Reset causes, as a `Boot` event carries them
+//!
+//! When the device signs its boot attestation is `icesickle_core::boot`.
+
+use serde::{Deserialize, Serialize};
+
+/// Why the chip last reset
+///
+/// Variant order is the wire code: append-only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResetReason {
+    /// Not reported, or a cause not listed here
+    Unknown,
+    /// Power applied
+    PowerOn,
+    /// Reset pin
+    External,
+    /// Restart requested by the firmware (including the fatal-error reset)
+    Software,
+    /// Exception or abort
+    Panic,
+    /// Interrupt, task or hardware watchdog
+    Watchdog,
+    /// Wake from deep sleep
+    DeepSleep,
+    /// Supply voltage dropped
+    Brownout,
+}
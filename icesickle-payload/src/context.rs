@@ -0,0 +1,9 @@
+//! Application context, as a payload carries it
+//!
+//! How a host arms it for a press is `icesickle_core::context`.
+
+/// Longest context: a SHA-256 digest
+pub const MAX_CONTEXT_LEN: usize = 32;
+
+/// Context bytes
+pub type Context = heapless::Vec<u8, MAX_CONTEXT_LEN>;
@@ -0,0 +1,62 @@
+//! Constant-time helpers
+//!
+//! The serial link is attacker-accessible, so anything that compares or
+//! encodes secret-dependent bytes must not leak through timing. These
+//! helpers do no data-dependent branching or indexing:
+//!
+//! - [`ct_eq`] compares equal-length byte slices by accumulating XORs;
+//!   only the lengths (which are public) affect the running time.
+//! - [`hex_digit`] maps a nibble to its hex character arithmetically
+//!   instead of through a lookup table.
+//!
+//! Audit of comparison paths (keep this list current):
+//!
+//! | Path | Secret? | Handling |
+//! |------|---------|----------|
+//! | Hex output (`attestation::hex_encode`) | Encodes signatures, keys, digests | [`hex_digit`] |
+//! | Session AEAD tag | Yes | Checked inside `chacha20poly1305` (constant time) |
+//! | Frame CRC, CTAPHID channel IDs, `seq` | No: attacker-chosen framing | Plain `==` |
+//! | RNG sanity checks (`entropy.rs`) | Samples are discarded, never used as keys | Plain `==` |
+//!
+//! Token verification, challenge comparison and self-verification checks
+//! must use [`ct_eq`] when they are added.
+
+use core::hint::black_box;
+
+/// True if `a` and `b` are equal, in time independent of their contents
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    black_box(diff) == 0
+}
+
+/// Lowercase hex character for the low nibble of `n`, without branching
+pub fn hex_digit(n: u8) -> u8 {
+    let n = n & 0x0f;
+    // 0xff when n > 9, else 0: the sign of (9 - n) as a wider integer
+    let above_nine = ((9i16 - n as i16) >> 8) as u8;
+    b'0' + n + (above_nine & (b'a' - b'0' - 10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ct_eq() {
+        assert!(ct_eq(b"token", b"token"));
+        assert!(!ct_eq(b"token", b"tokem"));
+        assert!(!ct_eq(b"token", b"tokens"));
+        assert!(ct_eq(b"", b""));
+    }
+
+    #[test]
+    fn test_hex_digit_matches_table() {
+        let table = b"0123456789abcdef";
+        for n in 0..=255u8 {
+            assert_eq!(hex_digit(n), table[(n & 0x0f) as usize]);
+        }
+    }
+}
@@ -0,0 +1,25 @@
+//! IceSickle payloads - what an attestation signs, and how to check it
+//!
+//! The payload types, their canonical postcard encoding, the record forms
+//! (fixed line, compact, compact text) and [`attestation::verify`], with
+//! everything they name: [`wallclock::WallTime`], [`policy::Policy`],
+//! [`boot::ResetReason`], [`scheme::Algorithm`], [`auth::TokenProof`] and
+//! [`context::Context`]. Nothing here needs `std` or an allocator: buffers
+//! are fixed arrays and `heapless` collections, so a microcontroller can
+//! check a peer's attestations without an OS.
+//!
+//! `icesickle-core` re-exports every item at its old path and signs with
+//! it, so the device's encoder and every verifier run the same code.
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod attestation;
+pub mod auth;
+pub mod base45;
+pub mod boot;
+pub mod context;
+pub mod ct;
+pub mod multibase;
+pub mod policy;
+pub mod scheme;
+pub mod wallclock;
@@ -0,0 +1,110 @@
+//! Multibase encoding and `did:key` names for the ephemeral key
+//!
+//! A `did:key` DID is the key itself: `did:key:` followed by the multibase
+//! base58btc form (prefix `z`) of the multicodec `ed25519-pub` header and
+//! the 32 key bytes. Decentralized-identity tooling resolves it to the key
+//! without any registry, so structured outputs can name the one-time signer
+//! in a form that tooling already understands. It names one attestation's
+//! key, never the device: every attestation gets a new DID. A P-256 key
+//! (see `scheme`) uses the `p256-pub` header and its SEC1 compressed form.
+
+use crate::scheme;
+
+/// Multibase prefix for base58btc
+pub const BASE58BTC: char = 'z';
+
+/// Multicodec `ed25519-pub`, as its unsigned-varint header
+const ED25519_PUB: [u8; 2] = [0xED, 0x01];
+
+/// Multicodec `p256-pub`, as its unsigned-varint header
+const P256_PUB: [u8; 2] = [0x80, 0x24];
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// `did:key:z6Mk...` (56 characters for an Ed25519 key, 57 for P-256)
+pub type DidKey = heapless::String<64>;
+
+/// The `did:key` DID of an Ed25519 public key
+pub fn did_key(public_key: &[u8; 32]) -> DidKey {
+    let mut bytes = [0u8; 34];
+    bytes[..2].copy_from_slice(&ED25519_PUB);
+    bytes[2..].copy_from_slice(public_key);
+
+    let mut did = DidKey::new();
+    let _ = did.push_str("did:key:");
+    let _ = did.push(BASE58BTC);
+    let _ = did.push_str(&base58btc::<48>(&bytes));
+    did
+}
+
+/// The `did:key` DID of an x-only P-256 public key (even y)
+pub fn did_key_p256(x: &[u8; 32]) -> DidKey {
+    let mut bytes = [0u8; 35];
+    bytes[..2].copy_from_slice(&P256_PUB);
+    bytes[2..].copy_from_slice(&scheme::compressed(x));
+
+    let mut did = DidKey::new();
+    let _ = did.push_str("did:key:");
+    let _ = did.push(BASE58BTC);
+    let _ = did.push_str(&base58btc::<48>(&bytes));
+    did
+}
+
+/// Bitcoin-alphabet base58, without the multibase prefix
+///
+/// `N` must cover the encoding (about 1.37 characters per byte); digits
+/// beyond it are dropped.
+pub fn base58btc<const N: usize>(bytes: &[u8]) -> heapless::String<N> {
+    // Base-58 digits of the whole input, least significant first
+    let mut digits = heapless::Vec::<u8, N>::new();
+    for &byte in bytes {
+        let mut carry = u32::from(byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            let _ = digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let mut out = heapless::String::new();
+    // Each leading zero byte is a leading '1'
+    for _ in bytes.iter().take_while(|&&b| b == 0) {
+        let _ = out.push('1');
+    }
+    for &digit in digits.iter().rev() {
+        let _ = out.push(ALPHABET[usize::from(digit)] as char);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base58btc() {
+        assert_eq!(base58btc::<16>(b""), "");
+        assert_eq!(base58btc::<16>(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(base58btc::<16>(&[0, 0, 1]), "112");
+    }
+
+    #[test]
+    fn test_did_key() {
+        let did = did_key(&[0x11; 32]);
+        assert_eq!(
+            did,
+            "did:key:z6Mkfbt52NAcPcYKV36L6eWTnyfxyGrGrxvJBxF5pjjCctGQ"
+        );
+        assert_eq!(did.len(), 56);
+
+        let did = did_key_p256(&[0x11; 32]);
+        assert_eq!(
+            did,
+            "did:key:zDnaeRab54jF3Ne4r8s97jx1aze9FhVzChsLGQtYFH8Ce7M2g"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Privacy policy flags carried in the payload
+//!
+//! Which trade-offs each flag makes, and how a build picks them, is
+//! `icesickle_core::policy`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::wallclock::{self, WallTime};
+
+/// Bucket that times are rounded down to under [`Policy::COARSE_TIME`]
+pub const TIME_QUANTUM_MS: u64 = 10_000;
+
+/// Privacy trade-offs a payload was signed under, as bit flags
+///
+/// Bits are append-only; a verifier that meets a bit it does not know
+/// cannot say how the payload's fields were altered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policy(u8);
+
+impl Policy {
+    /// No field altered
+    pub const NONE: Policy = Policy(0);
+
+    /// Times rounded down to [`TIME_QUANTUM_MS`]
+    pub const COARSE_TIME: Policy = Policy(1 << 0);
+
+    /// Counter started at a random per-boot offset
+    pub const RANDOM_COUNTER: Policy = Policy(1 << 1);
+
+    /// Counter omitted (signed as 0)
+    pub const NO_COUNTER: Policy = Policy(1 << 2);
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub const fn union(self, other: Policy) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Policy) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// `timestamp_ms` as this policy signs it
+    pub fn timestamp_ms(self, timestamp_ms: u64) -> u64 {
+        if self.contains(Policy::COARSE_TIME) {
+            timestamp_ms - timestamp_ms % TIME_QUANTUM_MS
+        } else {
+            timestamp_ms
+        }
+    }
+
+    /// The counter this policy signs for the `sequence`th attestation since
+    /// boot; `offset` is this boot's random offset, drawn only if needed
+    pub fn counter(self, sequence: u32, offset: impl FnOnce() -> u32) -> u32 {
+        if self.contains(Policy::NO_COUNTER) {
+            0
+        } else if self.contains(Policy::RANDOM_COUNTER) {
+            offset().wrapping_add(sequence)
+        } else {
+            sequence
+        }
+    }
+
+    /// The boot nonce this policy signs; `nonce` is drawn only if needed
+    pub fn boot_nonce(self, nonce: impl FnOnce() -> [u8; 16]) -> Option<[u8; 16]> {
+        if self.contains(Policy::NO_COUNTER) {
+            None
+        } else {
+            Some(nonce())
+        }
+    }
+
+    /// `wall_clock` as this policy signs it
+    pub fn wall_clock(self, wall_clock: Option<WallTime>) -> Option<WallTime> {
+        if !self.contains(Policy::COARSE_TIME) {
+            return wall_clock;
+        }
+        wall_clock.map(|wall| WallTime {
+            unix_s: wallclock::quantize(wall.unix_s, TIME_QUANTUM_MS / 1_000),
+            ..wall
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallclock::TimeSource;
+
+    #[test]
+    fn test_coarse_time() {
+        let wall = Some(WallTime {
+            unix_s: 1_709_251_199,
+            source: TimeSource::Gps,
+            stale: true,
+        });
+        assert_eq!(Policy::NONE.timestamp_ms(12_345), 12_345);
+        assert_eq!(Policy::NONE.wall_clock(wall), wall);
+
+        let coarse = Policy::COARSE_TIME;
+        assert_eq!(coarse.timestamp_ms(9_999), 0);
+        assert_eq!(coarse.timestamp_ms(12_345), 10_000);
+        assert_eq!(
+            coarse.wall_clock(wall),
+            Some(WallTime {
+                unix_s: 1_709_251_190,
+                source: TimeSource::Gps,
+                stale: true,
+            })
+        );
+        assert_eq!(coarse.wall_clock(None), None);
+    }
+
+    #[test]
+    fn test_counter_modes() {
+        assert_eq!(Policy::NONE.counter(5, || unreachable!()), 5);
+        assert_eq!(Policy::RANDOM_COUNTER.counter(5, || 1_000), 1_005);
+        assert_eq!(Policy::RANDOM_COUNTER.counter(5, || u32::MAX), 4);
+        assert_eq!(Policy::NO_COUNTER.counter(5, || unreachable!()), 0);
+
+        assert_eq!(Policy::NONE.boot_nonce(|| [7; 16]), Some([7; 16]));
+        assert_eq!(Policy::RANDOM_COUNTER.boot_nonce(|| [7; 16]), Some([7; 16]));
+        assert_eq!(Policy::NO_COUNTER.boot_nonce(|| unreachable!()), None);
+    }
+
+    #[test]
+    fn test_flags() {
+        assert!(Policy::NONE.contains(Policy::NONE));
+        assert!(!Policy::NONE.contains(Policy::COARSE_TIME));
+        assert_eq!(Policy::from_bits(1), Policy::COARSE_TIME);
+        assert_eq!(Policy::NONE.union(Policy::COARSE_TIME).bits(), 1);
+    }
+}
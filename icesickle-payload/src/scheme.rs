@@ -0,0 +1,122 @@
+//! Signature algorithms a payload can name
+//!
+//! The payload names the algorithm its ephemeral key signs with as an
+//! [`Algorithm`] (payload version 9), and [`Algorithm::verify`] checks a
+//! signature under it. Ed25519 is checked with `verify_strict`; P-256 keys
+//! are carried as their x-coordinate with y even, rebuilt here as the SEC1
+//! compressed point `0x02 || x` ([`compressed`]). P-256 needs feature
+//! `p256`; without it every P-256 signature is refused.
+//!
+//! Making keys and signing with them is `icesickle_core::scheme`.
+
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+
+use crate::multibase::{self, DidKey};
+
+/// Encoded public key length, in every scheme
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// Encoded signature length, in every scheme
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Signature algorithm named in the payload
+///
+/// Variants are append-only: their index is the wire code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    /// Ed25519 (code 0)
+    #[default]
+    Ed25519,
+    /// ECDSA P-256 with SHA-256, x-only key with even y (code 1)
+    P256,
+}
+
+impl Algorithm {
+    /// Display name
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Ed25519 => "Ed25519",
+            Algorithm::P256 => "P-256",
+        }
+    }
+
+    /// Check `signature` over `message` under `public_key`; false for an
+    /// algorithm this build cannot check
+    pub fn verify(
+        self,
+        public_key: &[u8; PUBLIC_KEY_LEN],
+        message: &[u8],
+        signature: &[u8; SIGNATURE_LEN],
+    ) -> bool {
+        match self {
+            Algorithm::Ed25519 => verify_ed25519(public_key, message, signature),
+            #[cfg(feature = "p256")]
+            Algorithm::P256 => verify_p256(public_key, message, signature),
+            #[cfg(not(feature = "p256"))]
+            Algorithm::P256 => false,
+        }
+    }
+
+    /// The `did:key` DID of `public_key` (see [`multibase`])
+    pub fn did_key(self, public_key: &[u8; PUBLIC_KEY_LEN]) -> DidKey {
+        match self {
+            Algorithm::Ed25519 => multibase::did_key(public_key),
+            Algorithm::P256 => multibase::did_key_p256(public_key),
+        }
+    }
+}
+
+/// Check an Ed25519 signature; false for a malformed key
+pub fn verify_ed25519(
+    public_key: &[u8; PUBLIC_KEY_LEN],
+    message: &[u8],
+    signature: &[u8; SIGNATURE_LEN],
+) -> bool {
+    let Ok(public_key) = VerifyingKey::from_bytes(public_key) else {
+        return false;
+    };
+    let signature = ed25519_dalek::Signature::from_bytes(signature);
+    public_key.verify_strict(message, &signature).is_ok()
+}
+
+/// Check an ECDSA P-256 signature over SHA-256 under an x-only key; false
+/// for a malformed key or signature
+#[cfg(feature = "p256")]
+pub fn verify_p256(
+    public_key: &[u8; PUBLIC_KEY_LEN],
+    message: &[u8],
+    signature: &[u8; SIGNATURE_LEN],
+) -> bool {
+    use p256::ecdsa::signature::Verifier;
+
+    let Ok(public_key) = p256::ecdsa::VerifyingKey::from_sec1_bytes(&compressed(public_key)) else {
+        return false;
+    };
+    let Ok(signature) = p256::ecdsa::Signature::from_slice(signature) else {
+        return false;
+    };
+    public_key.verify(message, &signature).is_ok()
+}
+
+/// SEC1 tag of a compressed point with even y
+pub const SEC1_EVEN: u8 = 0x02;
+
+/// SEC1 compressed form of an x-only P-256 key
+pub fn compressed(x: &[u8; PUBLIC_KEY_LEN]) -> [u8; PUBLIC_KEY_LEN + 1] {
+    let mut point = [SEC1_EVEN; PUBLIC_KEY_LEN + 1];
+    point[1..].copy_from_slice(x);
+    point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_codes() {
+        assert_eq!(postcard::to_allocvec(&Algorithm::Ed25519).unwrap(), [0]);
+        assert_eq!(postcard::to_allocvec(&Algorithm::P256).unwrap(), [1]);
+        assert!(postcard::from_bytes::<Algorithm>(&[2]).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+//! Wall-clock time as a payload carries it
+//!
+//! Whole UTC seconds, the source they came from and whether the device
+//! doubted them. How the device keeps the time (GPS with PPS, an RTC chip)
+//! is `icesickle_core::wallclock`.
+
+use serde::{Deserialize, Serialize};
+
+/// Where a wall-clock time came from
+///
+/// Variant order is the wire code: append-only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeSource {
+    /// GPS receiver, PPS-disciplined
+    Gps,
+    /// Battery-backed RTC chip, interpolated by the internal timer
+    Rtc,
+}
+
+/// UTC time carried in a payload alongside the boot-relative timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WallTime {
+    /// Seconds since the Unix epoch, possibly quantized
+    pub unix_s: u64,
+    pub source: TimeSource,
+    /// The source gave reason to doubt this time
+    pub stale: bool,
+}
+
+/// Round `unix_s` down to a multiple of `quantum_s` (0 or 1: unchanged)
+pub fn quantize(unix_s: u64, quantum_s: u64) -> u64 {
+    if quantum_s <= 1 {
+        unix_s
+    } else {
+        unix_s - unix_s % quantum_s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantize() {
+        assert_eq!(quantize(1_709_251_199, 0), 1_709_251_199);
+        assert_eq!(quantize(1_709_251_199, 60), 1_709_251_140);
+    }
+}
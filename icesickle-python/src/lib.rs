@@ -0,0 +1,200 @@
+//! Python bindings of the verifier: `import icesickle`
+//!
+//! Python pipelines get the verifier's own parse and verify code, not a
+//! re-implementation of the payload encoding: [`parse_line`] is
+//! `icesickle_verify::record::parse_line`, [`verify`] is
+//! `icesickle_payload::attestation::verify` (through its re-export in core)
+//! and [`check`] is the check `icesickle-verify` makes of every record.
+//!
+//! ```python
+//! import icesickle
+//!
+//! for line in capture:
+//!     record = icesickle.parse_line(line)
+//!     if record is not None:
+//!         icesickle.check(record, challenge=expected)
+//! ```
+//!
+//! A malformed record, and a record that fails its check, raise
+//! `ValueError` with the reason the command would print.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use icesickle_core::attestation;
+use icesickle_core::protocol::AttestationRecord;
+use icesickle_verify::record;
+
+/// One attestation, as parsed from device output
+#[pyclass(frozen, name = "Record", module = "icesickle")]
+pub struct Record(AttestationRecord);
+
+#[pymethods]
+impl Record {
+    #[getter]
+    fn payload_version(&self) -> u8 {
+        self.0.version
+    }
+
+    /// The event's type name, e.g. `ButtonPress`
+    #[getter]
+    fn event(&self) -> &'static str {
+        self.0.event.name()
+    }
+
+    #[getter]
+    fn counter(&self) -> u32 {
+        self.0.counter
+    }
+
+    #[getter]
+    fn timestamp_ms(&self) -> u64 {
+        self.0.timestamp_ms
+    }
+
+    /// `Ed25519` or `P-256`
+    #[getter]
+    fn algorithm(&self) -> &'static str {
+        self.0.algorithm.name()
+    }
+
+    #[getter]
+    fn public_key(&self) -> &[u8] {
+        &self.0.public_key
+    }
+
+    #[getter]
+    fn signature(&self) -> &[u8] {
+        &self.0.signature
+    }
+
+    #[getter]
+    fn challenge(&self) -> Option<&[u8]> {
+        self.0.challenge.as_ref().map(|c| &c[..])
+    }
+
+    #[getter]
+    fn boot_nonce(&self) -> Option<&[u8]> {
+        self.0.boot_nonce.as_ref().map(|n| &n[..])
+    }
+
+    #[getter]
+    fn context(&self) -> Option<&[u8]> {
+        self.0.context.as_deref()
+    }
+
+    #[getter]
+    fn measurement(&self) -> Option<&[u8]> {
+        self.0.measurement.as_ref().map(|m| &m[..])
+    }
+
+    /// The record as a JSON line, as the console prints it
+    fn json(&self) -> String {
+        self.0.json().trim_end().to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Record(event={}, counter={}, payload_version={})",
+            self.0.event.name(),
+            self.0.counter,
+            self.0.version
+        )
+    }
+}
+
+/// The attestation on a line of device output, `None` if the line holds
+/// none; raises `ValueError` if it holds a malformed one
+#[pyfunction]
+fn parse_line(line: &str) -> PyResult<Option<Record>> {
+    match record::parse_line(line) {
+        Some(Ok(parsed)) => Ok(Some(Record(parsed))),
+        Some(Err(reason)) => Err(PyValueError::new_err(reason)),
+        None => Ok(None),
+    }
+}
+
+/// Whether the signature verifies over the re-encoded payload; no other
+/// check is made
+#[pyfunction]
+fn verify(record: &Record) -> bool {
+    attestation::verify(&record.0)
+}
+
+/// The checks `icesickle-verify` makes: the signature, the signing domain,
+/// and with `challenge` (32 bytes) that the record answers it. Raises
+/// `ValueError` with the reason if any fails
+#[pyfunction]
+#[pyo3(signature = (record, challenge = None))]
+fn check(record: &Record, challenge: Option<&[u8]>) -> PyResult<()> {
+    let challenge: Option<[u8; 32]> = match challenge {
+        Some(bytes) => Some(
+            bytes
+                .try_into()
+                .map_err(|_| PyValueError::new_err("challenge must be 32 bytes"))?,
+        ),
+        None => None,
+    };
+    record::check(&record.0, challenge.as_ref()).map_err(PyValueError::new_err)
+}
+
+#[pymodule]
+fn icesickle(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Record>()?;
+    module.add_function(wrap_pyfunction!(parse_line, module)?)?;
+    module.add_function(wrap_pyfunction!(verify, module)?)?;
+    module.add_function(wrap_pyfunction!(check, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icesickle_core::attestation::{Attestation, AttestationEvent};
+    use icesickle_core::entropy::HardwareRng;
+    use icesickle_core::hal::mock::{MockNoise, MockTimer};
+
+    fn json(challenge: Option<[u8; 32]>) -> String {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5d)).unwrap();
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let attestation =
+            Attestation::create_with_challenge(&rng, &MockTimer::at(1_000), event, challenge)
+                .unwrap();
+        AttestationRecord::from(&attestation).json().to_string()
+    }
+
+    #[test]
+    fn test_parse_and_check() {
+        let record = parse_line(&json(Some([0x42; 32]))).unwrap().unwrap();
+        assert!(verify(&record));
+        assert_eq!(record.event(), "ButtonPress");
+        assert_eq!(record.challenge(), Some(&[0x42; 32][..]));
+        assert!(check(&record, None).is_ok());
+        assert!(check(&record, Some(&[0x42; 32])).is_ok());
+
+        assert!(parse_line("I (1234) icesickle: Button press detected")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_failures_raise() {
+        Python::initialize();
+        Python::attach(|py| {
+            let forged = json(None).replace("\"counter\":", "\"counter\":1");
+            let record = parse_line(&forged).unwrap().unwrap();
+            assert!(!verify(&record));
+            let err = check(&record, None).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert_eq!(err.value(py).to_string(), "signature does not verify");
+
+            let record = parse_line(&json(Some([0x42; 32]))).unwrap().unwrap();
+            let err = check(&record, Some(&[0x43; 32])).unwrap_err();
+            assert_eq!(err.value(py).to_string(), "answers a different challenge");
+            let err = check(&record, Some(&[0x42; 16])).unwrap_err();
+            assert_eq!(err.value(py).to_string(), "challenge must be 32 bytes");
+
+            assert!(parse_line("ATT 12 garbage").is_err());
+        });
+    }
+}
@@ -0,0 +1,54 @@
+"""Tests of the Python bindings against the known-answer corpus.
+
+Run against an installed module (``pip install ./icesickle-python``), with
+the corpus from ``cargo vectors > vectors.jsonl``:
+
+    ICESICKLE_VECTORS=vectors.jsonl python -m unittest discover icesickle-python/tests
+"""
+
+import json
+import os
+import unittest
+
+import icesickle
+
+
+def vectors():
+    with open(os.environ["ICESICKLE_VECTORS"], encoding="utf-8") as corpus:
+        return [json.loads(line) for line in corpus if line.strip()]
+
+
+class TestVectors(unittest.TestCase):
+    def test_every_form_verifies(self):
+        for vector in vectors():
+            forms = [vector["fixedLine"], vector["compactText"], json.dumps(vector["record"])]
+            for line in forms:
+                with self.subTest(vector=vector["name"], line=line[:16]):
+                    record = icesickle.parse_line(line)
+                    self.assertTrue(icesickle.verify(record))
+                    icesickle.check(record)
+                    self.assertEqual(record.event, vector["record"]["event"]["type"])
+                    self.assertEqual(record.public_key.hex(), vector["publicKey"])
+
+    def test_forgery_raises(self):
+        line = vectors()[0]["fixedLine"].replace("ATT 12 ", "ATT 12 9", 1)
+        record = icesickle.parse_line(line)
+        self.assertFalse(icesickle.verify(record))
+        with self.assertRaisesRegex(ValueError, "signature does not verify"):
+            icesickle.check(record)
+
+    def test_challenge(self):
+        record = icesickle.parse_line(vectors()[0]["fixedLine"])
+        with self.assertRaisesRegex(ValueError, "answers no challenge"):
+            icesickle.check(record, challenge=bytes(32))
+        with self.assertRaisesRegex(ValueError, "32 bytes"):
+            icesickle.check(record, challenge=b"short")
+
+    def test_other_lines(self):
+        self.assertIsNone(icesickle.parse_line("I (1234) icesickle: Button press detected"))
+        with self.assertRaises(ValueError):
+            icesickle.parse_line("ATT 12 garbage")
+
+
+if __name__ == "__main__":
+    unittest.main()
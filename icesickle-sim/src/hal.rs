@@ -0,0 +1,50 @@
+//! Host implementations of the core HAL traits
+//!
+//! Stand-ins for the firmware's `hal.rs`: `std::time` for the ESP timer and
+//! the OS RNG for `esp_fill_random()`. Nothing else in the simulator
+//! should need to reach the clock or RNG directly.
+
+use std::time::Instant;
+
+use icesickle_core::hal::{EntropySource, Timer};
+use rand_core::{OsRng, RngCore};
+
+/// Monotonic clock from process start, which stands in for boot
+#[derive(Debug, Clone, Copy)]
+pub struct StdTimer {
+    boot: Instant,
+}
+
+impl StdTimer {
+    pub fn new() -> Self {
+        Self {
+            boot: Instant::now(),
+        }
+    }
+}
+
+impl Default for StdTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer for StdTimer {
+    fn now_ms(&self) -> u64 {
+        self.boot.elapsed().as_millis() as u64
+    }
+
+    fn delay_ms(&self, ms: u32) {
+        std::thread::sleep(std::time::Duration::from_millis(u64::from(ms)));
+    }
+}
+
+/// The operating system's RNG (`getrandom`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill(&self, dest: &mut [u8]) {
+        OsRng.fill_bytes(dest);
+    }
+}
@@ -0,0 +1,196 @@
+//! Host simulator: `icesickle-sim < presses`
+//!
+//! Runs the device's press-to-attestation path on a development machine, so
+//! payload and transport changes can be tried without flashing a devkit.
+//! The hardware comes in through the core HAL traits (see `hal`): each line
+//! on stdin is a button press, the OS RNG stands in for the TRNG and
+//! `std::time` for the ESP timer. An empty line presses GPIO0 (BOOT); a
+//! number presses that GPIO.
+//!
+//! Attestations are written to stdout as fixed-format lines (`ATT ...`),
+//! which `icesickle-verify` reads, so `cargo sim | cargo verify` checks
+//! every one; status goes to stderr. The cooldown, counter, suppressed-press
+//! count and boot nonce behave as on the device, since they are the
+//! device's code.
+//!
+//! Keys come from the OS rather than the device's TRNG: a simulated
+//! attestation verifies, but shows only that this program ran.
+//!
+//! Built with `test-vectors`, `icesickle-sim --vectors` writes a corpus of
+//! known-answer vectors instead (see `vectors`).
+
+mod hal;
+#[cfg(feature = "test-vectors")]
+mod vectors;
+
+use std::io::{BufRead, Write};
+use std::process::ExitCode;
+
+use icesickle_core::attestation::{self, hex_encode, Attestation, AttestationEvent};
+use icesickle_core::cooldown::Cooldown;
+use icesickle_core::entropy::HardwareRng;
+use icesickle_core::hal::{EntropySource, Timer};
+use icesickle_core::policy;
+use icesickle_core::IceSickleError;
+
+use crate::hal::{OsEntropy, StdTimer};
+
+#[cfg(not(feature = "test-vectors"))]
+const USAGE: &str = "usage: icesickle-sim < presses";
+#[cfg(feature = "test-vectors")]
+const USAGE: &str = "usage: icesickle-sim < presses | icesickle-sim --vectors";
+
+/// GPIO of the devkit's BOOT button, pressed by an empty line
+const BOOT_GPIO: u8 = 0;
+
+fn main() -> ExitCode {
+    match simulate() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("icesickle-sim: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn simulate() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    #[cfg(feature = "test-vectors")]
+    if args == ["--vectors"] {
+        return emit_vectors();
+    }
+    if !args.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    eprintln!("IceSickle simulator v{}", env!("CARGO_PKG_VERSION"));
+
+    let rng = HardwareRng::from_source(OsEntropy).map_err(|e| e.to_string())?;
+    let timer = StdTimer::new();
+    if let Some(nonce) = policy::ACTIVE.boot_nonce(|| attestation::boot_nonce(&rng)) {
+        eprintln!("Boot nonce: {}", hex_encode::<32>(&nonce));
+    }
+    eprintln!("Press Enter to attest (or type a GPIO number); end input to stop");
+
+    let (stdin, stdout) = (std::io::stdin().lock(), std::io::stdout().lock());
+    let count = run(stdin, stdout, &rng, &timer, &Cooldown::new())?;
+    eprintln!("{} attestations", count);
+    Ok(())
+}
+
+/// Write the known-answer corpus to stdout
+#[cfg(feature = "test-vectors")]
+fn emit_vectors() -> Result<(), String> {
+    let corpus = vectors::corpus()?;
+    let mut stdout = std::io::stdout().lock();
+    for vector in &corpus {
+        vectors::write(vector, &mut stdout).map_err(|e| e.to_string())?;
+    }
+    eprintln!("{} vectors", corpus.len());
+    Ok(())
+}
+
+/// Attest each press read from `presses` to `out`, as the device's event
+/// loop does; the number of attestations
+fn run<S: EntropySource>(
+    presses: impl BufRead,
+    mut out: impl Write,
+    rng: &HardwareRng<S>,
+    timer: &impl Timer,
+    cooldown: &Cooldown,
+) -> Result<usize, String> {
+    let mut count = 0;
+    for line in presses.lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let gpio = match line.trim() {
+            "" => BOOT_GPIO,
+            number => match number.parse() {
+                Ok(gpio) => gpio,
+                Err(_) => {
+                    eprintln!("Not a GPIO number: {:?}", number);
+                    continue;
+                }
+            },
+        };
+
+        match cooldown.gate(timer) {
+            Ok(()) => {
+                eprintln!("Press on GPIO{} - generating attestation", gpio);
+                let event = AttestationEvent::ButtonPress { gpio };
+                let attestation =
+                    Attestation::create(rng, timer, event).map_err(|e| e.to_string())?;
+                out.write_all(attestation.fixed_line().as_bytes())
+                    .and_then(|()| out.flush())
+                    .map_err(|e| e.to_string())?;
+                count += 1;
+            }
+            Err(IceSickleError::Cooldown { remaining_ms }) => {
+                eprintln!("Cooldown active - {} ms remaining", remaining_ms);
+                attestation::record_suppressed();
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icesickle_core::hal::mock::{MockNoise, MockTimer};
+    use icesickle_core::protocol::AttestationRecord;
+
+    fn simulate(presses: &str) -> (usize, String) {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let timer = MockTimer::at(10_000);
+        let mut out = Vec::new();
+        let count = run(presses.as_bytes(), &mut out, &rng, &timer, &Cooldown::new()).unwrap();
+        (count, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_press_output_verifies() {
+        let (count, out) = simulate("38\n");
+        assert_eq!(count, 1);
+        let record = AttestationRecord::from_fixed_line(out.trim_end()).unwrap();
+        assert!(attestation::verify(&record));
+        assert_eq!(record.event, AttestationEvent::ButtonPress { gpio: 38 });
+    }
+
+    #[test]
+    fn test_cooldown_and_junk_lines_skipped() {
+        // The second press lands inside the cooldown; the last is not a GPIO
+        let (count, out) = simulate("\n\nboot\n");
+        assert_eq!(count, 1);
+        assert_eq!(out.lines().count(), 1);
+        let record = AttestationRecord::from_fixed_line(out.trim_end()).unwrap();
+        let boot = AttestationEvent::ButtonPress { gpio: BOOT_GPIO };
+        assert_eq!(record.event, boot);
+    }
+
+    #[cfg(feature = "test-vectors")]
+    #[test]
+    fn test_vectors_verify_in_every_form() {
+        let corpus = vectors::corpus().unwrap();
+        assert_eq!(corpus.len(), 24);
+        for vector in &corpus {
+            let record = &vector.record;
+            assert!(attestation::verify(record), "{}", vector.name);
+            let line = record.fixed_line();
+            assert_eq!(
+                AttestationRecord::from_fixed_line(&line).as_ref(),
+                Some(record)
+            );
+            let text = record.compact_text();
+            assert_eq!(
+                AttestationRecord::from_compact_text(&text).as_ref(),
+                Some(record)
+            );
+
+            let mut out = Vec::new();
+            vectors::write(vector, &mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            assert_eq!(out.lines().count(), 1);
+            assert!(out.contains(line.trim_end()));
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Known-answer test vectors: `icesickle-sim --vectors > vectors.jsonl`
+//!
+//! Signs one attestation per case below with the seeded ChaCha20 entropy
+//! (feature `test-vectors`) and writes each as a line of JSON, for
+//! third-party verifiers to check themselves against:
+//!
+//! ```text
+//! {"name":..,"seed":..,"payload":..,"publicKey":..,"signature":..,"fixedLine":..,"compact":..,"compactText":..,"record":{..}}
+//! ```
+//!
+//! `payload` is the postcard payload in hex, which the signature covers
+//! behind `SIGNING_DOMAIN`; `fixedLine`, `compact` (hex) and `compactText`
+//! are the record's output forms, and `record` is its JSON record. The
+//! cases cover every event and each optional payload field: wall clock,
+//! challenge, token, context and measurement. A build with `sign-p256`
+//! signs the same cases with P-256.
+//!
+//! Each case draws its key from [`TEST_VECTOR_SEED`] with the case index
+//! folded into the last byte. The boot nonce is drawn once, by the first
+//! case, and counters run on from it, so the corpus reproduces only as a
+//! whole run. Every key is public: these attestations prove nothing.
+
+use std::io::Write;
+
+use icesickle_core::attestation::{self, hex_encode, Attestation, AttestationEvent};
+use icesickle_core::auth::{Token, TOKEN_LEN};
+use icesickle_core::boot::ResetReason;
+use icesickle_core::context::Context;
+use icesickle_core::entropy::{HardwareRng, SeededEntropy, TEST_VECTOR_SEED};
+use icesickle_core::hal::Timer;
+use icesickle_core::protocol::AttestationRecord;
+use icesickle_core::wallclock::{TimeSource, WallTime};
+
+/// Signed firmware measurement of the last case
+const MEASUREMENT: [u8; 32] = [0x4d; 32];
+
+/// A clock stopped at one instant, with an optional wall clock
+struct FixedClock {
+    now_ms: u64,
+    wall_clock: Option<WallTime>,
+}
+
+impl Timer for FixedClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms
+    }
+
+    fn delay_ms(&self, _ms: u32) {}
+
+    fn wall_clock(&self) -> Option<WallTime> {
+        self.wall_clock
+    }
+}
+
+/// What one vector signs, beyond its event
+#[derive(Default)]
+struct Extras {
+    wall_clock: Option<WallTime>,
+    challenge: Option<[u8; 32]>,
+    token: bool,
+    context: Option<&'static [u8]>,
+    measurement: bool,
+}
+
+/// One signed vector
+pub struct Vector {
+    pub name: &'static str,
+    pub seed: [u8; 32],
+    pub record: AttestationRecord,
+    pub payload: attestation::PayloadBytes,
+}
+
+/// The cases, in signing order; measurement is last because it stays
+/// signed into every later payload
+fn cases() -> Vec<(&'static str, AttestationEvent, Extras)> {
+    use AttestationEvent::*;
+
+    let press = ButtonPress { gpio: 0 };
+    vec![
+        ("button-press", press, Extras::default()),
+        (
+            "data-digest",
+            DataDigest {
+                gpio: 0,
+                sha256: [0x11; 32],
+                len: 4096,
+            },
+            Extras::default(),
+        ),
+        ("tamper", Tamper, Extras::default()),
+        ("presence", Presence { gpio: 0 }, Extras::default()),
+        (
+            "window-digest",
+            WindowDigest {
+                root: [0x22; 32],
+                count: 17,
+            },
+            Extras::default(),
+        ),
+        ("witness", Witness { peer: [0x33; 32] }, Extras::default()),
+        ("credit-pulse", CreditPulse { count: 4 }, Extras::default()),
+        (
+            "keypad-entry",
+            KeypadEntry { hash: [0x44; 32] },
+            Extras::default(),
+        ),
+        (
+            "sensor",
+            Sensor {
+                sensor: 1,
+                channel: 0,
+                value: -1250,
+            },
+            Extras::default(),
+        ),
+        (
+            "boot",
+            Boot {
+                reset_reason: ResetReason::PowerOn,
+                fw_hash: [0x55; 32],
+            },
+            Extras::default(),
+        ),
+        (
+            "usage-count",
+            UsageCount {
+                count: 42,
+                epsilon_milli: 1000,
+                window_s: 86_400,
+            },
+            Extras::default(),
+        ),
+        (
+            "gpio-snapshot",
+            GpioSnapshot {
+                gpio: 0,
+                mask: 0x0000_8600_0000_0000,
+                levels: 0x0000_0200_0000_0000,
+            },
+            Extras::default(),
+        ),
+        ("touch-pad", TouchPad { channel: 3 }, Extras::default()),
+        (
+            "button-hold",
+            ButtonHold {
+                gpio: 0,
+                duration_ms: 1_800,
+            },
+            Extras::default(),
+        ),
+        (
+            "button-double-press",
+            ButtonDoublePress { gpio: 0 },
+            Extras::default(),
+        ),
+        (
+            "batch-root",
+            BatchRoot {
+                root: [0x66; 32],
+                count: 3,
+            },
+            Extras::default(),
+        ),
+        (
+            "tamper-detected",
+            TamperDetected { gpio: 4 },
+            Extras::default(),
+        ),
+        (
+            "dual-press",
+            DualPress {
+                gpio_a: 0,
+                gpio_b: 3,
+                skew_ms: 850,
+            },
+            Extras::default(),
+        ),
+        (
+            "wall-clock-gps",
+            press,
+            Extras {
+                wall_clock: Some(WallTime {
+                    unix_s: 1_760_000_000,
+                    source: TimeSource::Gps,
+                    stale: false,
+                }),
+                ..Extras::default()
+            },
+        ),
+        (
+            "wall-clock-rtc-stale",
+            press,
+            Extras {
+                wall_clock: Some(WallTime {
+                    unix_s: 1_760_000_000,
+                    source: TimeSource::Rtc,
+                    stale: true,
+                }),
+                ..Extras::default()
+            },
+        ),
+        (
+            "challenge",
+            press,
+            Extras {
+                challenge: Some([0x77; 32]),
+                ..Extras::default()
+            },
+        ),
+        (
+            "token",
+            press,
+            Extras {
+                token: true,
+                ..Extras::default()
+            },
+        ),
+        (
+            "context",
+            press,
+            Extras {
+                context: Some(&b"door-2"[..]),
+                ..Extras::default()
+            },
+        ),
+        (
+            "measurement",
+            press,
+            Extras {
+                measurement: true,
+                ..Extras::default()
+            },
+        ),
+    ]
+}
+
+/// Sign every case
+pub fn corpus() -> Result<Vec<Vector>, String> {
+    cases()
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, event, extras))| {
+            let mut seed = TEST_VECTOR_SEED;
+            seed[31] ^= index as u8;
+            let rng =
+                HardwareRng::from_source(SeededEntropy::new(seed)).map_err(|e| e.to_string())?;
+            let clock = FixedClock {
+                now_ms: 10_000 + 1_000 * index as u64,
+                wall_clock: extras.wall_clock,
+            };
+            let token = if extras.token {
+                Token::parse(&[0x88; TOKEN_LEN])
+            } else {
+                None
+            };
+            let context = extras.context.map(|bytes| {
+                let mut context = Context::new();
+                let _ = context.extend_from_slice(bytes);
+                context
+            });
+            if extras.measurement {
+                attestation::set_measurement(MEASUREMENT);
+            }
+
+            let attestation = Attestation::create_authorized(
+                &rng,
+                &clock,
+                event,
+                extras.challenge,
+                token,
+                context,
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(Vector {
+                name,
+                seed,
+                record: AttestationRecord::from(&attestation),
+                payload: attestation.payload_bytes(),
+            })
+        })
+        .collect()
+}
+
+/// Write `vector` as one line of JSON
+pub fn write(vector: &Vector, mut out: impl Write) -> std::io::Result<()> {
+    let record = &vector.record;
+    writeln!(
+        out,
+        "{{\"name\":\"{}\",\"seed\":\"{}\",\"payload\":\"{}\",\"publicKey\":\"{}\",\
+         \"signature\":\"{}\",\"fixedLine\":\"{}\",\"compact\":\"{}\",\
+         \"compactText\":\"{}\",\"record\":{}}}",
+        vector.name,
+        hex_encode::<64>(&vector.seed),
+        hex_encode::<{ 2 * attestation::MAX_PAYLOAD_LEN }>(&vector.payload),
+        hex_encode::<64>(&record.public_key),
+        hex_encode::<128>(&record.signature),
+        record.fixed_line().trim_end(),
+        hex_encode::<{ 2 * attestation::MAX_COMPACT_LEN }>(&record.compact()),
+        record.compact_text(),
+        record.json().trim_end(),
+    )
+}
@@ -0,0 +1,371 @@
+//! Host side of challenge-response
+//!
+//! [`Client`] drives the command protocol over a serial port, or any other
+//! byte port: `Hello`, then `ArmChallenge` with a challenge fresh from the
+//! OS, then `GetLastAttestation` every [`POLL_INTERVAL`] until the device
+//! returns the attestation that answers it. That attestation is accepted
+//! only if its signature verifies and it arrived within
+//! [`CHALLENGE_WINDOW_MS`] of arming: the press came after the challenge
+//! existed, and not long after.
+//!
+//! Reads must time out rather than block, returning no bytes (or
+//! `WouldBlock`/`TimedOut`) while the device is quiet; `icesickle-verify
+//! --arm` sets the port up that way with `stty`. A request left unanswered
+//! for [`RESPONSE_TIMEOUT`] is resent as the same frame, which the device
+//! answers from its cache instead of running the command twice. Console
+//! text shares the UART, and a frame it runs into is recovered the same
+//! way.
+//!
+//! Only plaintext requests are sent: the challenge is no secret, and
+//! neither is the attestation.
+
+use std::io::{ErrorKind, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand_core::{OsRng, RngCore};
+
+use icesickle_core::attestation::{self, PAYLOAD_VERSION};
+use icesickle_core::challenge::CHALLENGE_WINDOW_MS;
+use icesickle_core::protocol::{
+    self, AttestationRecord, ErrorCode, Request, Response, MAX_FRAME_LEN, PROTOCOL_VERSION,
+};
+
+/// How long a request waits for its response before it is resent
+pub const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Interval between `GetLastAttestation` polls while waiting for the press
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Sends of one request before the device is given up on
+const ATTEMPTS: usize = 3;
+
+/// Received bytes kept while no delimiter arrives (console text)
+const MAX_PENDING: usize = 4 * (MAX_FRAME_LEN + 1);
+
+/// A challenge of 32 random bytes from the OS
+pub fn fresh_challenge() -> [u8; 32] {
+    let mut challenge = [0u8; 32];
+    OsRng.fill_bytes(&mut challenge);
+    challenge
+}
+
+/// Command protocol endpoint on the host side of `port`
+pub struct Client<P> {
+    port: P,
+    /// Sequence number of the last request; 0 is left to heartbeats
+    seq: u8,
+    /// Received bytes not yet split into frames
+    pending: Vec<u8>,
+}
+
+impl<P: Read + Write> Client<P> {
+    pub fn new(port: P) -> Self {
+        Self {
+            port,
+            seq: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Exchange `Hello`; the device refuses every other request before it
+    pub fn hello(&mut self) -> Result<(), String> {
+        // A lone delimiter drops whatever partial frame the device holds
+        self.send(&[0])?;
+        let hello = Request::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            payload_version: PAYLOAD_VERSION,
+        };
+        match self.call(&hello)? {
+            Response::Hello { .. } => Ok(()),
+            other => Err(refused("Hello", &other)),
+        }
+    }
+
+    /// Arm `challenge` and wait for the press that answers it
+    ///
+    /// Returns the attestation once its signature verifies. Fails if none
+    /// answers within the challenge window.
+    pub fn answer(&mut self, challenge: [u8; 32]) -> Result<AttestationRecord, String> {
+        match self.call(&Request::ArmChallenge { challenge })? {
+            Response::Ok => {}
+            other => return Err(refused("ArmChallenge", &other)),
+        }
+        let armed = Instant::now();
+        let window = Duration::from_millis(CHALLENGE_WINDOW_MS);
+
+        while armed.elapsed() < window {
+            match self.call(&Request::GetLastAttestation)? {
+                Response::Attestation(record) if record.challenge == Some(challenge) => {
+                    if armed.elapsed() >= window {
+                        break;
+                    }
+                    if !attestation::verify(&record) {
+                        return Err("the answering attestation does not verify".to_string());
+                    }
+                    return Ok(record);
+                }
+                // An earlier attestation, or none yet this boot
+                Response::Attestation(_) | Response::Error(ErrorCode::NotFound) => {}
+                other => return Err(refused("GetLastAttestation", &other)),
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        Err(format!(
+            "no press answered the challenge within {} s",
+            CHALLENGE_WINDOW_MS / 1_000
+        ))
+    }
+
+    /// Send `request` and return its response, resending on silence
+    fn call(&mut self, request: &Request) -> Result<Response, String> {
+        self.seq = self.seq.wrapping_add(1).max(1);
+        let mut buf = [0u8; MAX_FRAME_LEN + 1];
+        let frame =
+            protocol::encode_frame(self.seq, request, &mut buf).map_err(|e| e.to_string())?;
+
+        for _ in 0..ATTEMPTS {
+            self.send(frame)?;
+            if let Some(response) = self.receive(self.seq)? {
+                return Ok(response);
+            }
+        }
+        Err(format!("no response after {} attempts", ATTEMPTS))
+    }
+
+    fn send(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.port
+            .write_all(bytes)
+            .and_then(|()| self.port.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    /// The response to `seq`; `None` after [`RESPONSE_TIMEOUT`], or once
+    /// the device reports a damaged request
+    fn receive(&mut self, seq: u8) -> Result<Option<Response>, String> {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        loop {
+            while let Some(end) = self.pending.iter().position(|&b| b == 0) {
+                let mut frame: Vec<u8> = self.pending.drain(..=end).collect();
+                frame.pop();
+                if frame.is_empty() {
+                    continue;
+                }
+                match protocol::decode_frame::<Response>(&mut frame) {
+                    (_, _, Ok(Response::Heartbeat(_))) => {}
+                    // Its `seq` cannot be trusted, so whatever it was, resend
+                    (
+                        _,
+                        _,
+                        Ok(Response::Error(
+                            ErrorCode::Checksum | ErrorCode::Malformed | ErrorCode::FrameTooLong,
+                        )),
+                    ) => return Ok(None),
+                    (got, _, Ok(response)) if got == seq => return Ok(Some(response)),
+                    // A reply to an earlier request, or text run into a frame
+                    _ => {}
+                }
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+
+            let mut chunk = [0u8; 256];
+            let read = match self.port.read(&mut chunk) {
+                Ok(read) => read,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+                    ) =>
+                {
+                    0
+                }
+                Err(e) => return Err(e.to_string()),
+            };
+            if read == 0 {
+                thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+            if self.pending.len() + read > MAX_PENDING {
+                self.pending.clear();
+            }
+            self.pending.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+fn refused(request: &str, response: &Response) -> String {
+    match response {
+        Response::Error(code) => format!("{} refused: {:?}", request, code),
+        _ => format!("unexpected reply to {}", request),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icesickle_core::attestation::{Attestation, AttestationEvent};
+    use icesickle_core::entropy::HardwareRng;
+    use icesickle_core::hal::mock::{MockNoise, MockTimer};
+    use icesickle_core::protocol::{FrameReader, Heartbeat};
+
+    /// A device that answers the requests this client sends
+    #[derive(Default)]
+    struct Device {
+        reader: FrameReader,
+        outbox: Vec<u8>,
+        armed: Option<[u8; 32]>,
+        last: Option<AttestationRecord>,
+        /// The press comes at this poll of `GetLastAttestation`
+        press_at_poll: usize,
+        polls: usize,
+        /// Answered with the challenge's signature damaged
+        forge: bool,
+        /// Requests whose response is lost on the way back
+        drop: usize,
+        requests: Vec<Request>,
+    }
+
+    impl Device {
+        fn press(&mut self) {
+            let rng = HardwareRng::from_source(MockNoise::new(0x33)).unwrap();
+            let event = AttestationEvent::ButtonPress { gpio: 0 };
+            let attestation = Attestation::create_with_challenge(
+                &rng,
+                &MockTimer::at(1_000),
+                event,
+                self.armed.take(),
+            )
+            .unwrap();
+            let mut record = AttestationRecord::from(&attestation);
+            if self.forge {
+                record.signature[0] ^= 1;
+            }
+            self.last = Some(record);
+        }
+
+        fn answer(&mut self, request: Request) -> Response {
+            self.requests.push(request.clone());
+            match request {
+                Request::Hello { .. } => Response::Hello {
+                    protocol_version: PROTOCOL_VERSION,
+                    min_protocol_version: PROTOCOL_VERSION,
+                    payload_version: PAYLOAD_VERSION,
+                    max_in_flight: 4,
+                },
+                Request::ArmChallenge { challenge } => {
+                    self.armed = Some(challenge);
+                    Response::Ok
+                }
+                Request::GetLastAttestation => {
+                    if self.polls == self.press_at_poll {
+                        self.press();
+                    }
+                    self.polls += 1;
+                    match &self.last {
+                        Some(record) => Response::Attestation(record.clone()),
+                        None => Response::Error(ErrorCode::NotFound),
+                    }
+                }
+                _ => Response::Error(ErrorCode::Unsupported),
+            }
+        }
+    }
+
+    impl Write for Device {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            for &byte in bytes {
+                let Some(inbound) = self.reader.push(byte) else {
+                    continue;
+                };
+                let response = self.answer(inbound.request.unwrap());
+                if self.drop > 0 {
+                    self.drop -= 1;
+                    continue;
+                }
+                // Heartbeats interleave with replies
+                let heartbeat = Response::Heartbeat(Heartbeat {
+                    uptime_ms: 1_000,
+                    cooldown_remaining_ms: 0,
+                    queue_depth: self.armed.is_some() as u8,
+                    entropy_ok: true,
+                });
+                let mut buf = [0u8; MAX_FRAME_LEN + 1];
+                let frame = protocol::encode_frame(0, &heartbeat, &mut buf).unwrap();
+                self.outbox.extend_from_slice(frame);
+                let frame = protocol::encode_frame(inbound.seq, &response, &mut buf).unwrap();
+                self.outbox.extend_from_slice(frame);
+            }
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for Device {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(self.outbox.len());
+            buf[..len].copy_from_slice(&self.outbox[..len]);
+            self.outbox.drain(..len);
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_press_answers_the_challenge() {
+        let mut client = Client::new(Device::default());
+        client.hello().unwrap();
+        let challenge = fresh_challenge();
+        let record = client.answer(challenge).unwrap();
+        assert_eq!(record.challenge, Some(challenge));
+        assert!(matches!(client.port.requests[0], Request::Hello { .. }));
+        assert_eq!(client.port.requests[1], Request::ArmChallenge { challenge });
+    }
+
+    #[test]
+    fn test_earlier_attestation_is_not_an_answer() {
+        let mut device = Device {
+            press_at_poll: 1,
+            ..Device::default()
+        };
+        // Made before the challenge was armed
+        device.press();
+        let earlier = device.last.clone().unwrap();
+
+        let mut client = Client::new(device);
+        client.hello().unwrap();
+        let record = client.answer([0x5a; 32]).unwrap();
+        assert_ne!(record, earlier);
+        assert_eq!(record.challenge, Some([0x5a; 32]));
+        assert_eq!(client.port.polls, 2);
+    }
+
+    #[test]
+    fn test_forged_answer_refused() {
+        let device = Device {
+            forge: true,
+            ..Device::default()
+        };
+        let mut client = Client::new(device);
+        client.hello().unwrap();
+        assert!(client
+            .answer([0x5a; 32])
+            .unwrap_err()
+            .contains("does not verify"));
+    }
+
+    #[test]
+    fn test_lost_response_resent() {
+        let device = Device {
+            drop: 1,
+            ..Device::default()
+        };
+        let mut client = Client::new(device);
+        client.hello().unwrap();
+        assert_eq!(client.port.requests.len(), 2);
+        assert_eq!(client.port.requests[0], client.port.requests[1]);
+    }
+}
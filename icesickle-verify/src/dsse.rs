@@ -0,0 +1,437 @@
+//! DSSE envelopes, as the device prints them with feature `dsse`
+//!
+//! [`read_envelope`] takes the envelope's line and returns the in-toto
+//! [`Statement`] inside once it holds up:
+//!
+//! - the payload type, statement type and predicate type are IceSickle's;
+//! - the predicate is valid against [`SCHEMA`], the JSON Schema of the
+//!   predicate type, which ships with this crate;
+//! - the envelope's `keyid` is the `did:key` DID of the predicate's public
+//!   key, and the signature over DSSE's pre-authentication encoding
+//!   verifies under that key.
+//!
+//! The statement's subject is the attestation's payload, by SHA-256;
+//! [`Statement::covers`] checks it against the bytes of the `PAYLOAD` line
+//! printed after the envelope.
+//!
+//! The schema is checked by a validator of the keywords it uses, not a
+//! general JSON Schema implementation: a keyword it does not know fails
+//! validation rather than passing unchecked.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use icesickle_core::attestation::hex_decode_array;
+use icesickle_core::dsse::{PAYLOAD_TYPE, PREDICATE_TYPE};
+use icesickle_core::scheme::Algorithm;
+
+/// JSON Schema of the predicate (`physical-event/v1`)
+pub const SCHEMA: &str = include_str!("../schemas/physical-event-v1.json");
+
+/// Statement type
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+
+/// A statement read from an envelope whose signature verified
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    /// SHA-256 of the payload the statement is about (`payload.bin`)
+    pub payload_sha256: [u8; 32],
+    /// The predicate, valid against [`SCHEMA`]
+    pub predicate: Value,
+}
+
+impl Statement {
+    /// The event's type name
+    pub fn event(&self) -> &str {
+        self.predicate["event"]["type"].as_str().unwrap_or_default()
+    }
+
+    pub fn counter(&self) -> u64 {
+        self.predicate["counter"].as_u64().unwrap_or_default()
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        match self.predicate["algorithm"].as_str() {
+            Some("P-256") => Algorithm::P256,
+            _ => Algorithm::Ed25519,
+        }
+    }
+
+    /// The challenge it answers (payload version 6)
+    pub fn challenge(&self) -> Option<[u8; 32]> {
+        hex_decode_array(self.predicate["challenge"].as_str()?)
+    }
+
+    /// Whether `payload` is the statement's subject
+    pub fn covers(&self, payload: &[u8]) -> bool {
+        <[u8; 32]>::from(Sha256::digest(payload)) == self.payload_sha256
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    payload_type: String,
+    payload: String,
+    signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Deserialize)]
+struct EnvelopeSignature {
+    keyid: String,
+    sig: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InTotoStatement {
+    #[serde(rename = "_type")]
+    statement_type: String,
+    subject: Vec<InTotoSubject>,
+    predicate_type: String,
+    predicate: Value,
+}
+
+#[derive(Deserialize)]
+struct InTotoSubject {
+    digest: InTotoDigest,
+}
+
+#[derive(Deserialize)]
+struct InTotoDigest {
+    sha256: String,
+}
+
+/// The statement in the envelope on `line`, once its types, its predicate
+/// and its signature hold up
+pub fn read_envelope(line: &str) -> Result<Statement, String> {
+    let envelope: Envelope =
+        serde_json::from_str(line.trim()).map_err(|e| format!("bad envelope: {}", e))?;
+    if envelope.payload_type != PAYLOAD_TYPE {
+        return Err(format!(
+            "payload type {} is not in-toto",
+            envelope.payload_type
+        ));
+    }
+    let [signature] = &envelope.signatures[..] else {
+        return Err("the envelope must hold exactly one signature".to_string());
+    };
+    let body = STANDARD
+        .decode(&envelope.payload)
+        .map_err(|_| "bad envelope payload base64")?;
+    let statement: InTotoStatement =
+        serde_json::from_slice(&body).map_err(|e| format!("bad statement: {}", e))?;
+    if statement.statement_type != STATEMENT_TYPE {
+        return Err(format!(
+            "statement type {} is not in-toto v1",
+            statement.statement_type
+        ));
+    }
+    if statement.predicate_type != PREDICATE_TYPE {
+        return Err(format!(
+            "predicate type {} is not IceSickle's",
+            statement.predicate_type
+        ));
+    }
+    let [subject] = &statement.subject[..] else {
+        return Err("the statement must have exactly one subject".to_string());
+    };
+    let payload_sha256 = hex_decode_array(&subject.digest.sha256).ok_or("bad subject digest")?;
+    validate(&statement.predicate)?;
+
+    let statement = Statement {
+        payload_sha256,
+        predicate: statement.predicate,
+    };
+    // Valid against the schema, so 64 hex digits
+    let public_key = hex_decode_array(
+        statement.predicate["publicKey"]
+            .as_str()
+            .unwrap_or_default(),
+    )
+    .ok_or("bad public key hex")?;
+    let algorithm = statement.algorithm();
+    if signature.keyid != algorithm.did_key(&public_key).as_str() {
+        return Err("keyid is not the DID of the predicate's key".to_string());
+    }
+    let sig: [u8; 64] = STANDARD
+        .decode(&signature.sig)
+        .ok()
+        .and_then(|sig| sig.try_into().ok())
+        .ok_or("bad signature base64")?;
+    if !algorithm.verify(&public_key, &pae(&body), &sig) {
+        return Err("signature does not verify".to_string());
+    }
+    Ok(statement)
+}
+
+/// Check `predicate` against [`SCHEMA`]
+pub fn validate(predicate: &Value) -> Result<(), String> {
+    let schema: Value = serde_json::from_str(SCHEMA).map_err(|e| format!("bad schema: {}", e))?;
+    check_schema(&schema, predicate, "predicate")
+}
+
+/// DSSE's pre-authentication encoding of `body`: what the key signed
+fn pae(body: &[u8]) -> Vec<u8> {
+    let mut pae = format!(
+        "DSSEv1 {} {} {} ",
+        PAYLOAD_TYPE.len(),
+        PAYLOAD_TYPE,
+        body.len()
+    )
+    .into_bytes();
+    pae.extend_from_slice(body);
+    pae
+}
+
+/// Check `value`, found at `at`, against `schema`
+fn check_schema(schema: &Value, value: &Value, at: &str) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        return Err(format!("{}: schema is not an object", at));
+    };
+    for (keyword, rule) in schema {
+        match keyword.as_str() {
+            // Annotations
+            "$schema" | "$id" | "title" | "description" => {}
+            "type" => {
+                let name = rule.as_str().ok_or("bad schema type")?;
+                if !has_type(value, name) {
+                    return Err(format!("{}: not of type {}", at, name));
+                }
+            }
+            "enum" => {
+                if !rule
+                    .as_array()
+                    .is_some_and(|allowed| allowed.contains(value))
+                {
+                    return Err(format!("{}: {} is not an allowed value", at, value));
+                }
+            }
+            "minimum" | "maximum" => {
+                let (Some(n), Some(bound)) = (integer(value), integer(rule)) else {
+                    continue;
+                };
+                if (keyword == "minimum" && n < bound) || (keyword == "maximum" && n > bound) {
+                    return Err(format!("{}: {} is out of range", at, n));
+                }
+            }
+            "pattern" => {
+                let Some(text) = value.as_str() else {
+                    continue;
+                };
+                let pattern = Regex::new(rule.as_str().ok_or("bad schema pattern")?)
+                    .map_err(|e| format!("bad schema pattern: {}", e))?;
+                if !pattern.is_match(text) {
+                    return Err(format!("{}: does not match {}", at, pattern));
+                }
+            }
+            "required" => {
+                let Some(object) = value.as_object() else {
+                    continue;
+                };
+                for name in rule.as_array().ok_or("bad schema required")? {
+                    let name = name.as_str().ok_or("bad schema required")?;
+                    if !object.contains_key(name) {
+                        return Err(format!("{}: {} is missing", at, name));
+                    }
+                }
+            }
+            "properties" => {
+                let Some(object) = value.as_object() else {
+                    continue;
+                };
+                for (name, property) in rule.as_object().ok_or("bad schema properties")? {
+                    if let Some(field) = object.get(name) {
+                        check_schema(property, field, &format!("{}.{}", at, name))?;
+                    }
+                }
+            }
+            "additionalProperties" => {
+                if *rule != Value::Bool(false) {
+                    return Err("schema keyword additionalProperties is not supported \
+                                other than false"
+                        .to_string());
+                }
+                let Some(object) = value.as_object() else {
+                    continue;
+                };
+                let known = schema.get("properties").and_then(Value::as_object);
+                if let Some(name) = object
+                    .keys()
+                    .find(|name| !known.is_some_and(|known| known.contains_key(*name)))
+                {
+                    return Err(format!("{}: {} is not allowed", at, name));
+                }
+            }
+            "oneOf" => {
+                let matches = rule
+                    .as_array()
+                    .ok_or("bad schema oneOf")?
+                    .iter()
+                    .filter(|option| check_schema(option, value, at).is_ok())
+                    .count();
+                if matches != 1 {
+                    return Err(format!(
+                        "{}: matches {} of its options, not one",
+                        at, matches
+                    ));
+                }
+            }
+            other => return Err(format!("schema keyword {} is not supported", other)),
+        }
+    }
+    Ok(())
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+fn integer(value: &Value) -> Option<i128> {
+    value
+        .as_i64()
+        .map(i128::from)
+        .or_else(|| value.as_u64().map(i128::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icesickle_core::attestation::{Attestation, AttestationEvent};
+    use icesickle_core::dsse;
+    use icesickle_core::entropy::HardwareRng;
+    use icesickle_core::hal::mock::{MockNoise, MockTimer};
+
+    fn attestation() -> Attestation {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5e)).unwrap();
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        Attestation::create_with_challenge(&rng, &MockTimer::at(1_000), event, Some([0x42; 32]))
+            .unwrap()
+    }
+
+    /// `envelope` with its statement replaced by `statement`, signature kept
+    fn resealed(envelope: &str, statement: &str) -> String {
+        let value: Value = serde_json::from_str(envelope).unwrap();
+        let old = value["payload"].as_str().unwrap();
+        envelope.replace(old, &STANDARD.encode(statement))
+    }
+
+    fn statement_of(envelope: &str) -> String {
+        let value: Value = serde_json::from_str(envelope).unwrap();
+        let body = STANDARD.decode(value["payload"].as_str().unwrap()).unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn test_device_envelope_reads() {
+        let attestation = attestation();
+        let envelope = dsse::render(&attestation).unwrap().envelope;
+        let statement = read_envelope(&envelope).unwrap();
+        assert_eq!(statement.event(), "ButtonPress");
+        assert_eq!(statement.counter(), u64::from(attestation.counter()));
+        assert_eq!(statement.algorithm(), Algorithm::Ed25519);
+        assert_eq!(statement.challenge(), Some([0x42; 32]));
+        assert!(statement.covers(&attestation.payload_bytes()));
+        assert!(!statement.covers(b"another payload"));
+    }
+
+    #[test]
+    fn test_tampered_envelope_refused() {
+        let envelope = dsse::render(&attestation()).unwrap().envelope;
+        let statement = statement_of(&envelope);
+
+        let forged = resealed(
+            &envelope,
+            &statement.replace("\"counter\":", "\"counter\":1"),
+        );
+        assert_eq!(
+            read_envelope(&forged).unwrap_err(),
+            "signature does not verify"
+        );
+
+        let renamed = envelope.replace("\"keyid\":\"did:key:z", "\"keyid\":\"did:key:y");
+        assert!(read_envelope(&renamed).unwrap_err().contains("keyid"));
+
+        let retyped = envelope.replace("in-toto+json", "json");
+        assert!(read_envelope(&retyped).is_err());
+        assert!(read_envelope("{}").is_err());
+    }
+
+    #[test]
+    fn test_schema_violations_refused() {
+        let envelope = dsse::render(&attestation()).unwrap().envelope;
+        let statement = statement_of(&envelope);
+
+        // Refused by the schema before the signature is looked at
+        for (from, to, reason) in [
+            (
+                "\"counter\":",
+                "\"extra\":1,\"counter\":",
+                "extra is not allowed",
+            ),
+            (
+                "\"type\":\"ButtonPress\"",
+                "\"type\":\"Press\"",
+                "not an allowed value",
+            ),
+            ("\"policy\":0", "\"policy\":256", "out of range"),
+            (
+                "\"challenge\":\"42",
+                "\"challenge\":\"4",
+                "matches 0 of its options",
+            ),
+            (
+                "\"wallClock\":null",
+                "\"wallClock\":{}",
+                "matches 0 of its options",
+            ),
+            (
+                "\"postcard\":\"0000\"",
+                "\"postcard\":\"00A0\"",
+                "does not match",
+            ),
+            ("\"counter\":", "\"count\":", "count is not allowed"),
+        ] {
+            let broken = resealed(&envelope, &statement.replace(from, to));
+            let error = read_envelope(&broken).unwrap_err();
+            assert!(error.contains(reason), "{}: {}", to, error);
+        }
+    }
+
+    #[test]
+    fn test_schema_keywords_supported() {
+        // Every keyword in the schema is one the validator checks, so an
+        // edit to the schema cannot go unenforced
+        fn walk(schema: &Value) {
+            for (keyword, rule) in schema.as_object().unwrap() {
+                let alone = Value::Object([(keyword.clone(), rule.clone())].into_iter().collect());
+                if let Err(error) = check_schema(&alone, &Value::Null, "predicate") {
+                    assert!(!error.contains("not supported"), "{}", error);
+                }
+                match keyword.as_str() {
+                    "properties" => rule.as_object().unwrap().values().for_each(walk),
+                    "oneOf" => rule.as_array().unwrap().iter().for_each(walk),
+                    _ => {}
+                }
+            }
+        }
+        walk(&serde_json::from_str(SCHEMA).unwrap());
+        assert_eq!(
+            validate(&Value::Null).unwrap_err(),
+            "predicate: not of type object"
+        );
+    }
+}
@@ -0,0 +1,11 @@
+//! The parts of the host verifier a collector embeds; the
+//! `icesickle-verify` command is `main.rs`
+//!
+//! `record` and `replay` build for `wasm32-unknown-unknown` too (see
+//! `icesickle-wasm`); `challenge` talks to a serial port, so it does not.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod challenge;
+pub mod dsse;
+pub mod record;
+pub mod replay;
@@ -0,0 +1,336 @@
+//! Host verifier: `icesickle-verify [--challenge <hex>] [--stream] [--replay | --replay-file <FILE>] [--window <s>] [FILE...]`
+//! or `icesickle-verify --arm <PORT> [--replay | --replay-file <FILE>] [--window <s>]`
+//!
+//! Reads device output from the files given, or from stdin, and checks
+//! every attestation in it. Three forms carry every signed field:
+//!
+//! - fixed-format lines, `ATT ...` (feature `minimal`) and `RELAY <hops>
+//!   ATT ...` (feature `relay`);
+//! - compact text, `ICESICKLE:...`, as scanned from the display's QR code
+//!   (feature `display`);
+//! - JSON records, one per line, as the console prints them by default and
+//!   the USB drive's files hold them (feature `usb-msc`).
+//!
+//! Each record is rebuilt into an `AttestationRecord` and checked with
+//! `icesickle_core::attestation::verify`, which re-encodes the payload with
+//! the device's own code. DSSE envelopes (feature `dsse`) are read as
+//! well, and their statements checked for the signature and against the
+//! predicate's schema (see `dsse`). Other lines (logs, the other companion
+//! formats) are skipped.
+//!
+//! With `--stream`, the input is instead a capture of the binary stream
+//! (feature `binary-stream`): `icesickle_core::stream` frames, each checked
+//! for its CRC-32 and then verified the same way. A damaged frame counts as
+//! a failure.
+//!
+//! With `--challenge`, an attestation is only accepted if it answers that
+//! challenge (see `ArmChallenge`). The exit status is failure if any
+//! attestation fails, or if none was found.
+//!
+//! With `--arm`, the input is instead the device on serial port `PORT`
+//! (Linux; set up with `stty`): a fresh challenge is armed over the command
+//! protocol, and the attestation of the next press is read back and
+//! checked like any other, answering that challenge (see `challenge`).
+//!
+//! With `--replay`, an attestation already accepted in this run is refused
+//! as a replay (see `replay`); with `--replay-file`, the entries are kept
+//! in that file and refused across runs too. Entries are kept for the
+//! `--window` (a day by default), and an attestation whose wall clock is
+//! older than the window is refused as stale.
+//!
+//! An attestation that spent an authorization token is reported with its
+//! proof (nonce then tag, hex) for the token issuer to redeem. Checking the
+//! tag takes the issuer's secret, so it is not checked here.
+//!
+//! Counters restart at every boot, so each attestation is also reported
+//! with its boot nonce (payload version 8): counters order attestations
+//! only among those with the same nonce.
+//!
+//! Ed25519 and P-256 signatures are both checked, by the algorithm the
+//! payload names (payload version 9); each `OK` line names it.
+//!
+//! Application context a host set for the press (payload version 10) is
+//! reported in hex. What it means is up to the application that set it, so
+//! it is not interpreted here.
+//!
+//! A firmware measurement (payload version 11) is reported in hex too, for
+//! comparison with the published builds' image hashes.
+//!
+//! Signatures cover the payload behind the domain prefix
+//! `IceSickle-attestation-v2` (payload version 12). Earlier payloads were
+//! signed bare, where a signature over the same bytes in another protocol
+//! would pass, so they are refused.
+
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, ExitCode};
+
+use icesickle_core::attestation::{hex_decode_array, hex_encode};
+use icesickle_core::challenge::CHALLENGE_WINDOW_MS;
+use icesickle_core::protocol::AttestationRecord;
+use icesickle_core::stream;
+use icesickle_verify::challenge::{self, Client};
+use icesickle_verify::dsse::{self, Statement};
+use icesickle_verify::record::{check, parse_line};
+use icesickle_verify::replay::{FileStore, MemoryStore, ReplayCache, ReplayStore};
+
+const USAGE: &str = "usage: icesickle-verify [--challenge <hex>] [--stream] [--replay | --replay-file <FILE>] [--window <s>] [FILE...]
+       icesickle-verify --arm <PORT> [--replay | --replay-file <FILE>] [--window <s>]";
+
+/// How long `--replay` remembers an attestation unless `--window` says
+const DEFAULT_WINDOW_S: u64 = 86_400;
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(message) => {
+            eprintln!("icesickle-verify: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// True if every attestation found verified, and there was at least one
+fn run(args: Vec<String>) -> Result<bool, String> {
+    let mut challenge = None;
+    let mut binary = false;
+    let mut replay = None;
+    let mut window_s = DEFAULT_WINDOW_S;
+    let mut arm = None;
+    let mut paths = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--challenge" => {
+                let hex = args.next().ok_or(USAGE)?;
+                challenge = Some(hex_decode_array(&hex).ok_or("challenge must be 64 hex digits")?);
+            }
+            "--stream" => binary = true,
+            "--arm" => arm = Some(args.next().ok_or(USAGE)?),
+            "--replay" => replay = Some(None),
+            "--replay-file" => replay = Some(Some(args.next().ok_or(USAGE)?)),
+            "--window" => {
+                let seconds = args.next().ok_or(USAGE)?;
+                window_s = seconds.parse().map_err(|_| "window must be in seconds")?;
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => paths.push(arg),
+        }
+    }
+    if arm.is_some() && (challenge.is_some() || binary || !paths.is_empty()) {
+        return Err(USAGE.to_string());
+    }
+    if paths.is_empty() {
+        paths.push("-".to_string());
+    }
+
+    let mut tally = Tally::default();
+    if let Some(file) = replay {
+        let store: Box<dyn ReplayStore> = match file {
+            Some(path) => Box::new(FileStore::open(&path).map_err(|e| format!("{}: {}", path, e))?),
+            None => Box::new(MemoryStore::default()),
+        };
+        tally.replay = Some(ReplayCache::new(store, window_s));
+    }
+    if let Some(port) = arm {
+        let fresh = challenge::fresh_challenge();
+        let answer = arm_challenge(&port, fresh);
+        tally.report(&format!("{}:challenge", port), answer, Some(&fresh));
+        println!("{} valid, {} failed", tally.valid, tally.failed);
+        return Ok(tally.failed == 0);
+    }
+    for path in &paths {
+        let mut reader: Box<dyn BufRead> = if path == "-" {
+            Box::new(std::io::stdin().lock())
+        } else {
+            let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+            Box::new(BufReader::new(file))
+        };
+        if binary {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .map_err(|e| format!("{}: {}", path, e))?;
+            for (index, frame) in frames(&mut bytes).enumerate() {
+                let at = format!("{}:frame {}", path, index + 1);
+                let parsed = stream::decode(frame).map_err(|e| format!("damaged frame ({:?})", e));
+                tally.report(&at, parsed, challenge.as_ref());
+            }
+            continue;
+        }
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", path, e))?;
+            let at = format!("{}:{}", path, index + 1);
+            if line.trim_start().starts_with("{\"payloadType\"") {
+                tally.report_statement(&at, dsse::read_envelope(&line), challenge.as_ref());
+                continue;
+            }
+            let Some(parsed) = parse_line(&line) else {
+                continue;
+            };
+            tally.report(&at, parsed, challenge.as_ref());
+        }
+    }
+
+    println!("{} valid, {} failed", tally.valid, tally.failed);
+    Ok(tally.failed == 0 && tally.valid > 0)
+}
+
+#[derive(Default)]
+struct Tally {
+    valid: usize,
+    failed: usize,
+    replay: Option<ReplayCache<Box<dyn ReplayStore>>>,
+}
+
+impl Tally {
+    /// Check a record found `at`, and print and count the outcome
+    fn report(
+        &mut self,
+        at: &str,
+        parsed: Result<AttestationRecord, String>,
+        challenge: Option<&[u8; 32]>,
+    ) {
+        let result = parsed.and_then(|record| {
+            check(&record, challenge)?;
+            // Only once the signature holds, so a forgery claims nothing
+            if let Some(replay) = &mut self.replay {
+                replay.admit(&record, now_s())?;
+            }
+            Ok(record)
+        });
+        match result {
+            Ok(record) => {
+                self.valid += 1;
+                println!(
+                    "OK   {} {} counter {} (payload version {}, {})",
+                    at,
+                    record.event.name(),
+                    record.counter,
+                    record.version,
+                    record.algorithm.name()
+                );
+                if let Some(nonce) = record.boot_nonce {
+                    println!("     boot {}", hex_encode::<32>(&nonce));
+                }
+                if let Some(token) = record.token {
+                    println!("     token {}", hex_encode::<64>(&token.to_bytes()));
+                }
+                if let Some(context) = &record.context {
+                    println!("     context {}", hex_encode::<64>(context));
+                }
+                if let Some(measurement) = record.measurement {
+                    println!("     firmware {}", hex_encode::<64>(&measurement));
+                }
+            }
+            Err(reason) => {
+                self.failed += 1;
+                println!("FAIL {} {}", at, reason);
+            }
+        }
+    }
+
+    /// Print and count the outcome for a DSSE statement found `at`
+    fn report_statement(
+        &mut self,
+        at: &str,
+        read: Result<Statement, String>,
+        challenge: Option<&[u8; 32]>,
+    ) {
+        let result = read.and_then(|statement| match (challenge, statement.challenge()) {
+            (Some(expected), Some(answered)) if answered != *expected => {
+                Err("answers a different challenge".to_string())
+            }
+            (Some(_), None) => Err("answers no challenge".to_string()),
+            _ => Ok(statement),
+        });
+        match result {
+            Ok(statement) => {
+                self.valid += 1;
+                println!(
+                    "OK   {} DSSE statement {} counter {} ({})",
+                    at,
+                    statement.event(),
+                    statement.counter(),
+                    statement.algorithm().name()
+                );
+            }
+            Err(reason) => {
+                self.failed += 1;
+                println!("FAIL {} {}", at, reason);
+            }
+        }
+    }
+}
+
+/// Arm `challenge` on the device at `port` and read back its answer
+fn arm_challenge(port: &str, challenge: [u8; 32]) -> Result<AttestationRecord, String> {
+    // Raw bytes, and reads that return empty after half a second of silence
+    let stty = Command::new("stty")
+        .args([
+            "-F", port, "115200", "raw", "-echo", "min", "0", "time", "5",
+        ])
+        .status()
+        .map_err(|e| format!("stty: {}", e))?;
+    if !stty.success() {
+        return Err(format!("stty could not set up {}", port));
+    }
+    let device = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(port)
+        .map_err(|e| format!("{}: {}", port, e))?;
+
+    let mut client = Client::new(device);
+    client.hello()?;
+    println!(
+        "Challenge {} armed - press the button within {}s",
+        hex_encode::<64>(&challenge),
+        CHALLENGE_WINDOW_MS / 1_000
+    );
+    client.answer(challenge)
+}
+
+/// Unix seconds by the host's clock
+fn now_s() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// The frames in a binary stream capture, split at their delimiters; a
+/// partial frame at either end of the capture is one more damaged frame
+fn frames(bytes: &mut [u8]) -> impl Iterator<Item = &mut [u8]> {
+    bytes
+        .split_mut(|&b| b == 0)
+        .filter(|frame| !frame.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icesickle_core::attestation::{Attestation, AttestationEvent};
+    use icesickle_core::entropy::HardwareRng;
+    use icesickle_core::hal::mock::{MockNoise, MockTimer};
+
+    #[test]
+    fn test_stream_frames_verify() {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let attestation = Attestation::create(&rng, &MockTimer::at(1_000), event).unwrap();
+        let record = AttestationRecord::from(&attestation);
+        let mut out = [0u8; stream::MAX_FRAME_LEN + 1];
+        let frame = stream::encode(&record, &mut out).unwrap();
+
+        // Joined mid-frame, then two whole frames
+        let mut capture = frame[frame.len() / 2..].to_vec();
+        capture.extend_from_slice(frame);
+        capture.extend_from_slice(frame);
+        let decoded: Vec<_> = frames(&mut capture).map(stream::decode).collect();
+        assert_eq!(decoded.len(), 3);
+        assert!(decoded[0].is_err());
+        assert_eq!(decoded[1], Ok(record.clone()));
+        assert_eq!(check(decoded[2].as_ref().unwrap(), None), Ok(()));
+    }
+}
@@ -0,0 +1,288 @@
+//! Device output to `AttestationRecord`s, and the checks `icesickle-verify`
+//! makes of them
+//!
+//! [`parse_line`] takes any line of device output: fixed-format lines (and
+//! relayed ones), compact text as scanned from a QR code, or JSON records.
+//! Each rebuilds the record field by field, and [`check`] verifies it with
+//! `icesickle_core::attestation::verify`, which re-encodes the payload with
+//! the device's own code. Nothing here touches the OS, so the WASM build
+//! (`icesickle-wasm`) and the Python bindings (`icesickle-python`) check
+//! records the same way.
+
+use serde::Deserialize;
+
+use icesickle_core::attestation::{
+    self, hex_decode, hex_decode_array, COMPACT_TEXT_PREFIX, MAX_EVENT_LEN,
+};
+use icesickle_core::auth::TokenProof;
+use icesickle_core::context::{Context, MAX_CONTEXT_LEN};
+use icesickle_core::policy::Policy;
+use icesickle_core::protocol::AttestationRecord;
+use icesickle_core::scheme::Algorithm;
+use icesickle_core::wallclock::{TimeSource, WallTime};
+
+/// The attestation on `line`; `None` if the line is not one
+pub fn parse_line(line: &str) -> Option<Result<AttestationRecord, String>> {
+    let line = line.trim();
+    if line.starts_with("ATT ") {
+        return Some(fixed_line(line));
+    }
+    if let Some(rest) = line.strip_prefix("RELAY ") {
+        let (_hops, fixed) = rest.split_once(' ')?;
+        return Some(fixed_line(fixed));
+    }
+    if line.starts_with(COMPACT_TEXT_PREFIX) {
+        return Some(compact_text(line));
+    }
+    if line.starts_with('{') && line.contains("\"payloadVersion\"") {
+        return Some(json_record(line));
+    }
+    None
+}
+
+fn fixed_line(line: &str) -> Result<AttestationRecord, String> {
+    AttestationRecord::from_fixed_line(line).ok_or_else(|| "malformed fixed line".to_string())
+}
+
+fn compact_text(line: &str) -> Result<AttestationRecord, String> {
+    AttestationRecord::from_compact_text(line).ok_or_else(|| "malformed compact text".to_string())
+}
+
+/// Signature, then the challenge if one is required
+pub fn check(record: &AttestationRecord, challenge: Option<&[u8; 32]>) -> Result<(), String> {
+    if record.version < attestation::SIGNING_DOMAIN_VERSION {
+        return Err(format!(
+            "payload version {} is signed without the domain prefix",
+            record.version
+        ));
+    }
+    if !attestation::verify(record) {
+        return Err("signature does not verify".to_string());
+    }
+    match (challenge, &record.challenge) {
+        (Some(expected), Some(answered)) if answered != expected => {
+            Err("answers a different challenge".to_string())
+        }
+        (Some(_), None) => Err("answers no challenge".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// A JSON record as rendered by `AttestationRecord::json`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonRecord {
+    payload_version: u8,
+    event: JsonEvent,
+    counter: u32,
+    timestamp_ms: u64,
+    wall_clock: Option<JsonWallClock>,
+    policy: u8,
+    /// Payload version 5
+    #[serde(default)]
+    suppressed: u32,
+    /// Payload version 6
+    #[serde(default)]
+    challenge: Option<String>,
+    /// Payload version 7
+    #[serde(default)]
+    token: Option<String>,
+    /// Payload version 8
+    #[serde(default)]
+    boot_nonce: Option<String>,
+    /// Payload version 9; Ed25519 before
+    #[serde(default)]
+    algorithm: Option<String>,
+    /// Payload version 10
+    #[serde(default)]
+    context: Option<String>,
+    /// Payload version 11
+    #[serde(default)]
+    measurement: Option<String>,
+    public_key: String,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct JsonEvent {
+    #[serde(rename = "type")]
+    name: String,
+    postcard: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonWallClock {
+    unix_s: u64,
+    source: String,
+    stale: bool,
+}
+
+fn json_record(line: &str) -> Result<AttestationRecord, String> {
+    let json: JsonRecord = serde_json::from_str(line).map_err(|e| format!("bad JSON: {}", e))?;
+
+    // The type name is not signed; the postcard encoding is
+    let mut event_buf = [0u8; MAX_EVENT_LEN];
+    let len = hex_decode(&json.event.postcard, &mut event_buf).ok_or("bad event hex")?;
+    let event: attestation::AttestationEvent =
+        postcard::from_bytes(&event_buf[..len]).map_err(|e| format!("bad event: {}", e))?;
+    if event.name() != json.event.name {
+        return Err(format!(
+            "event type {} does not match its encoding ({})",
+            json.event.name,
+            event.name()
+        ));
+    }
+
+    let wall_clock = match json.wall_clock {
+        Some(wall) => Some(WallTime {
+            unix_s: wall.unix_s,
+            source: match wall.source.as_str() {
+                "gps" => TimeSource::Gps,
+                "rtc" => TimeSource::Rtc,
+                other => return Err(format!("unknown time source {}", other)),
+            },
+            stale: wall.stale,
+        }),
+        None => None,
+    };
+    let challenge = match json.challenge {
+        Some(hex) => Some(hex_decode_array(&hex).ok_or("bad challenge hex")?),
+        None => None,
+    };
+    let token = match json.token {
+        Some(hex) => Some(TokenProof::from_bytes(
+            &hex_decode_array(&hex).ok_or("bad token hex")?,
+        )),
+        None => None,
+    };
+    let boot_nonce = match json.boot_nonce {
+        Some(hex) => Some(hex_decode_array(&hex).ok_or("bad boot nonce hex")?),
+        None => None,
+    };
+    let algorithm = match json.algorithm.as_deref() {
+        None | Some("Ed25519") => Algorithm::Ed25519,
+        Some("P-256") => Algorithm::P256,
+        Some(other) => return Err(format!("unknown algorithm {}", other)),
+    };
+    let context = match json.context {
+        Some(hex) => {
+            let mut bytes = [0u8; MAX_CONTEXT_LEN];
+            let len = hex_decode(&hex, &mut bytes).ok_or("bad context hex")?;
+            Some(Context::from_slice(&bytes[..len]).map_err(|_| "bad context hex")?)
+        }
+        None => None,
+    };
+    let measurement = match json.measurement {
+        Some(hex) => Some(hex_decode_array(&hex).ok_or("bad measurement hex")?),
+        None => None,
+    };
+
+    Ok(AttestationRecord {
+        version: json.payload_version,
+        event,
+        timestamp_ms: json.timestamp_ms,
+        counter: json.counter,
+        public_key: hex_decode_array(&json.public_key).ok_or("bad public key hex")?,
+        signature: hex_decode_array(&json.signature).ok_or("bad signature hex")?,
+        wall_clock,
+        policy: Policy::from_bits(json.policy),
+        suppressed: json.suppressed,
+        challenge,
+        token,
+        boot_nonce,
+        algorithm,
+        context,
+        measurement,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icesickle_core::attestation::{Attestation, AttestationEvent};
+    use icesickle_core::auth::{Token, TOKEN_LEN};
+    use icesickle_core::entropy::HardwareRng;
+    use icesickle_core::hal::mock::{MockNoise, MockTimer};
+    use icesickle_core::volume;
+
+    fn attestation(challenge: Option<[u8; 32]>) -> Attestation {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5a)).unwrap();
+        let event = AttestationEvent::DataDigest {
+            gpio: 0,
+            sha256: [0xFF; 32],
+            len: 4_096,
+        };
+        let token = Token::parse(&[0x24; TOKEN_LEN]);
+        let timer = MockTimer::at(1_000);
+        let context = Context::from_slice(b"approve #42").ok();
+        Attestation::create_authorized(&rng, &timer, event, challenge, token, context).unwrap()
+    }
+
+    #[test]
+    fn test_json_record_verifies() {
+        let record = AttestationRecord::from(&attestation(Some([0x42; 32])));
+        let json = volume::json(&record);
+        let parsed = parse_line(&json).unwrap().unwrap();
+        assert_eq!(parsed, record);
+        assert!(parsed.token.is_some());
+        assert!(parsed.boot_nonce.is_some());
+        assert!(parsed.context.is_some());
+        assert_eq!(check(&parsed, Some(&[0x42; 32])), Ok(()));
+        assert!(check(&parsed, Some(&[0x43; 32])).is_err());
+
+        let measured = AttestationRecord {
+            measurement: Some([0x05; 32]),
+            ..record.clone()
+        };
+        assert_eq!(parse_line(&volume::json(&measured)).unwrap(), Ok(measured));
+
+        // A field changed after signing
+        let forged = json.replace("\"counter\":", "\"counter\":1");
+        let forged = parse_line(&forged).unwrap().unwrap();
+        assert!(check(&forged, None).is_err());
+
+        // An unsigned type name that disagrees with the encoding
+        let renamed = json.replace("DataDigest", "ButtonPress");
+        assert!(parse_line(&renamed).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_console_output_verifies() {
+        // The default firmware prints each attestation as its JSON record
+        let attestation = attestation(None);
+        let line = AttestationRecord::from(&attestation).json();
+        let record = parse_line(&line).unwrap().unwrap();
+        assert_eq!(record, AttestationRecord::from(&attestation));
+        assert_eq!(check(&record, None), Ok(()));
+    }
+
+    #[test]
+    fn test_fixed_lines_verify() {
+        let attestation = attestation(None);
+        let line = attestation.fixed_line();
+        let record = parse_line(&line).unwrap().unwrap();
+        assert_eq!(check(&record, None), Ok(()));
+        assert!(check(&record, Some(&[0x42; 32])).is_err());
+
+        let bare = AttestationRecord {
+            version: attestation::SIGNING_DOMAIN_VERSION - 1,
+            ..record.clone()
+        };
+        assert!(check(&bare, None).unwrap_err().contains("domain prefix"));
+
+        let relayed = format!("RELAY 2 {}", line);
+        assert_eq!(parse_line(&relayed).unwrap(), Ok(record.clone()));
+
+        let scanned = record.compact_text();
+        assert_eq!(parse_line(&scanned).unwrap(), Ok(record));
+        assert!(parse_line(&scanned[..scanned.len() - 1]).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_other_lines_are_skipped() {
+        assert!(parse_line("I (1234) icesickle: Button press detected").is_none());
+        assert!(parse_line("{\"event\":\"ButtonPress { gpio: 0 }\",\"ts\":1}").is_none());
+        assert!(parse_line("").is_none());
+    }
+}
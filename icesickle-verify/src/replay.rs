@@ -0,0 +1,339 @@
+//! Replay protection for collectors
+//!
+//! A valid attestation stays valid: anyone who saw one can submit it again.
+//! [`ReplayCache`] refuses an attestation already accepted within its
+//! freshness window, by what makes each one unique:
+//!
+//! - its public key. Every key is ephemeral, so a key seen twice is the
+//!   same attestation. The signature is left out of the entry on purpose:
+//!   a P-256 signature still verifies with `s` negated, which would make a
+//!   replay look new;
+//! - the challenge it answers (payload version 6), which is answered once;
+//! - its counter within its boot (payload version 8). Counters restart at
+//!   every boot, so they are keyed by boot nonce. Attestations signed under
+//!   `no-counter` carry neither, and are tracked by key, and by challenge
+//!   if they answer one.
+//!
+//! Entries are forgotten once older than the window, so an attestation
+//! whose wall clock is older than that is refused as stale: the cache could
+//! no longer tell whether it was seen. One without a wall clock cannot be
+//! dated here; pair it with a challenge, which the issuer expires.
+//!
+//! Where entries live is a [`ReplayStore`]: [`MemoryStore`] for one
+//! process, [`FileStore`] for a collector that restarts.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use icesickle_core::attestation::{hex_decode_array, hex_encode};
+use icesickle_core::policy::Policy;
+use icesickle_core::protocol::AttestationRecord;
+
+/// Something about an attestation that no other attestation shares
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Entry {
+    /// Its ephemeral public key
+    Key([u8; 32]),
+    /// The challenge it answers
+    Challenge([u8; 32]),
+    /// Its counter, within the boot the nonce names
+    Counter([u8; 16], u32),
+}
+
+impl Entry {
+    /// The entries `record` claims, most specific first
+    pub fn of(record: &AttestationRecord) -> Vec<Entry> {
+        let mut entries = vec![Entry::Key(record.public_key)];
+        if let Some(challenge) = record.challenge {
+            entries.push(Entry::Challenge(challenge));
+        }
+        let counted = !record.policy.contains(Policy::NO_COUNTER);
+        if let Some(nonce) = record.boot_nonce.filter(|_| counted) {
+            entries.push(Entry::Counter(nonce, record.counter));
+        }
+        entries
+    }
+
+    /// Why `record` is refused when this entry was already seen
+    fn reason(&self) -> &'static str {
+        match self {
+            Entry::Key(_) => "already seen",
+            Entry::Challenge(_) => "answers a challenge already answered",
+            Entry::Counter(..) => "repeats a counter already seen in its boot",
+        }
+    }
+}
+
+/// Where a [`ReplayCache`] keeps its entries, with when each was seen
+/// (Unix seconds)
+pub trait ReplayStore {
+    /// When `entry` was seen, if it was
+    fn seen_at(&self, entry: &Entry) -> Option<u64>;
+
+    /// Remember `entry` as seen at `now_s`
+    fn insert(&mut self, entry: Entry, now_s: u64) -> std::io::Result<()>;
+
+    /// Forget entries seen before `before_s`
+    fn prune(&mut self, before_s: u64) -> std::io::Result<()>;
+}
+
+impl<S: ReplayStore + ?Sized> ReplayStore for Box<S> {
+    fn seen_at(&self, entry: &Entry) -> Option<u64> {
+        (**self).seen_at(entry)
+    }
+
+    fn insert(&mut self, entry: Entry, now_s: u64) -> std::io::Result<()> {
+        (**self).insert(entry, now_s)
+    }
+
+    fn prune(&mut self, before_s: u64) -> std::io::Result<()> {
+        (**self).prune(before_s)
+    }
+}
+
+/// Entries in memory, lost when the process ends
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: HashMap<Entry, u64>,
+}
+
+impl ReplayStore for MemoryStore {
+    fn seen_at(&self, entry: &Entry) -> Option<u64> {
+        self.entries.get(entry).copied()
+    }
+
+    fn insert(&mut self, entry: Entry, now_s: u64) -> std::io::Result<()> {
+        self.entries.insert(entry, now_s);
+        Ok(())
+    }
+
+    fn prune(&mut self, before_s: u64) -> std::io::Result<()> {
+        self.entries.retain(|_, seen_s| *seen_s >= before_s);
+        Ok(())
+    }
+}
+
+/// Entries in memory and appended to a file, one per line, so they
+/// survive a restart
+///
+/// Lines are `<seen_s> key <hex>`, `<seen_s> challenge <hex>` and
+/// `<seen_s> counter <boot nonce hex> <counter>`. Pruning rewrites the file
+/// without the expired lines.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    file: File,
+    memory: MemoryStore,
+}
+
+impl FileStore {
+    /// Open or create the store at `path`, loading the entries in it
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut memory = MemoryStore::default();
+        if let Ok(file) = File::open(&path) {
+            for (index, line) in BufReader::new(file).lines().enumerate() {
+                let line = line?;
+                let (entry, seen_s) = parse_entry(&line).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("{}:{}: malformed entry", path.display(), index + 1),
+                    )
+                })?;
+                memory.insert(entry, seen_s)?;
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self { path, file, memory })
+    }
+}
+
+impl ReplayStore for FileStore {
+    fn seen_at(&self, entry: &Entry) -> Option<u64> {
+        self.memory.seen_at(entry)
+    }
+
+    fn insert(&mut self, entry: Entry, now_s: u64) -> std::io::Result<()> {
+        writeln!(self.file, "{}", format_entry(&entry, now_s))?;
+        self.memory.insert(entry, now_s)
+    }
+
+    fn prune(&mut self, before_s: u64) -> std::io::Result<()> {
+        let count = self.memory.entries.len();
+        self.memory.prune(before_s)?;
+        if self.memory.entries.len() == count {
+            return Ok(());
+        }
+
+        // Write the survivors beside the file, then swap it in
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let mut out = File::create(&temp)?;
+        for (entry, seen_s) in &self.memory.entries {
+            writeln!(out, "{}", format_entry(entry, *seen_s))?;
+        }
+        out.sync_all()?;
+        std::fs::rename(&temp, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+}
+
+fn format_entry(entry: &Entry, seen_s: u64) -> String {
+    match entry {
+        Entry::Key(key) => format!("{} key {}", seen_s, hex_encode::<64>(key)),
+        Entry::Challenge(challenge) => {
+            format!("{} challenge {}", seen_s, hex_encode::<64>(challenge))
+        }
+        Entry::Counter(nonce, counter) => {
+            format!("{} counter {} {}", seen_s, hex_encode::<32>(nonce), counter)
+        }
+    }
+}
+
+fn parse_entry(line: &str) -> Option<(Entry, u64)> {
+    let mut fields = line.split(' ');
+    let seen_s = fields.next()?.parse().ok()?;
+    let entry = match fields.next()? {
+        "key" => Entry::Key(hex_decode_array(fields.next()?)?),
+        "challenge" => Entry::Challenge(hex_decode_array(fields.next()?)?),
+        "counter" => Entry::Counter(
+            hex_decode_array(fields.next()?)?,
+            fields.next()?.parse().ok()?,
+        ),
+        _ => return None,
+    };
+    fields.next().is_none().then_some((entry, seen_s))
+}
+
+/// Refuses attestations already accepted within the last `window_s`
+/// seconds
+#[derive(Debug)]
+pub struct ReplayCache<S> {
+    store: S,
+    window_s: u64,
+}
+
+impl<S: ReplayStore> ReplayCache<S> {
+    pub fn new(store: S, window_s: u64) -> Self {
+        Self { store, window_s }
+    }
+
+    /// Accept `record` at `now_s` (Unix seconds) and remember it, or say
+    /// why it is refused
+    ///
+    /// Check the signature first: a forged record would otherwise claim
+    /// entries, and the genuine attestation would then be refused.
+    pub fn admit(&mut self, record: &AttestationRecord, now_s: u64) -> Result<(), String> {
+        let oldest_s = now_s.saturating_sub(self.window_s);
+        self.store.prune(oldest_s).map_err(|e| e.to_string())?;
+
+        if let Some(wall) = record.wall_clock {
+            if wall.unix_s < oldest_s {
+                return Err(format!(
+                    "signed at {}, before the {} s replay window",
+                    wall.unix_s, self.window_s
+                ));
+            }
+        }
+
+        let entries = Entry::of(record);
+        if let Some(entry) = entries.iter().find(|e| self.store.seen_at(e).is_some()) {
+            return Err(entry.reason().to_string());
+        }
+        for entry in entries {
+            self.store.insert(entry, now_s).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icesickle_core::attestation::{Attestation, AttestationEvent};
+    use icesickle_core::entropy::HardwareRng;
+    use icesickle_core::hal::mock::{MockNoise, MockTimer};
+    use icesickle_core::wallclock::{TimeSource, WallTime};
+
+    const WINDOW_S: u64 = 600;
+    const NOW_S: u64 = 1_760_000_000;
+
+    fn record(seed: u8, challenge: Option<[u8; 32]>) -> AttestationRecord {
+        let rng = HardwareRng::from_source(MockNoise::new(seed)).unwrap();
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let attestation =
+            Attestation::create_with_challenge(&rng, &MockTimer::at(1_000), event, challenge)
+                .unwrap();
+        AttestationRecord::from(&attestation)
+    }
+
+    #[test]
+    fn test_replays_refused_until_the_window_passes() {
+        let mut cache = ReplayCache::new(MemoryStore::default(), WINDOW_S);
+        let first = record(0x11, None);
+        assert_eq!(cache.admit(&first, NOW_S), Ok(()));
+        assert_eq!(
+            cache.admit(&first, NOW_S + 1),
+            Err("already seen".to_string())
+        );
+
+        // A second attestation of the same boot with the first one's counter
+        let cloned = AttestationRecord {
+            public_key: [0xAB; 32],
+            ..first.clone()
+        };
+        assert!(cache
+            .admit(&cloned, NOW_S + 2)
+            .unwrap_err()
+            .contains("counter"));
+
+        assert_eq!(cache.admit(&record(0x12, None), NOW_S + 3), Ok(()));
+        assert_eq!(cache.admit(&first, NOW_S + WINDOW_S + 1), Ok(()));
+    }
+
+    #[test]
+    fn test_challenge_answered_once() {
+        let mut cache = ReplayCache::new(MemoryStore::default(), WINDOW_S);
+        assert_eq!(cache.admit(&record(0x21, Some([0x42; 32])), NOW_S), Ok(()));
+        let again = cache.admit(&record(0x22, Some([0x42; 32])), NOW_S);
+        assert!(again.unwrap_err().contains("challenge"));
+    }
+
+    #[test]
+    fn test_wall_clock_outside_window_is_stale() {
+        let mut cache = ReplayCache::new(MemoryStore::default(), WINDOW_S);
+        let mut old = record(0x31, None);
+        old.wall_clock = Some(WallTime {
+            unix_s: NOW_S - WINDOW_S - 1,
+            source: TimeSource::Gps,
+            stale: false,
+        });
+        assert!(cache.admit(&old, NOW_S).unwrap_err().contains("window"));
+    }
+
+    #[test]
+    fn test_file_store_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("icesickle-replay-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let first = record(0x41, Some([0x07; 32]));
+        let second = record(0x42, None);
+
+        let mut cache = ReplayCache::new(FileStore::open(&path).unwrap(), WINDOW_S);
+        assert_eq!(cache.admit(&first, NOW_S), Ok(()));
+        assert_eq!(cache.admit(&second, NOW_S + WINDOW_S), Ok(()));
+        drop(cache);
+
+        let mut cache = ReplayCache::new(FileStore::open(&path).unwrap(), WINDOW_S);
+        assert!(cache.admit(&first, NOW_S + 1).is_err());
+        // Pruning the first rewrites the file; the second is still there
+        assert_eq!(cache.admit(&first, NOW_S + WINDOW_S + 1), Ok(()));
+        drop(cache);
+
+        let mut cache = ReplayCache::new(FileStore::open(&path).unwrap(), WINDOW_S);
+        assert!(cache.admit(&second, NOW_S + WINDOW_S + 2).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,43 @@
+// Browser-side verification of IceSickle attestations, over the WASM build
+// of the verifier (`icesickle-wasm`). `pkg/` is wasm-bindgen's output:
+//
+//   cargo build --release -p icesickle-wasm --target wasm32-unknown-unknown
+//   wasm-bindgen --target web --out-dir icesickle-wasm/js/pkg \
+//       target/wasm32-unknown-unknown/release/icesickle_wasm.wasm
+//
+// Each attestation is checked by the code `icesickle-verify` runs, so a
+// page accepts exactly what the command accepts.
+
+import init, { verifyText as verifyJson } from "./pkg/icesickle_wasm.js";
+
+let ready;
+
+/**
+ * Load the module, once. `source` is the `.wasm` as a URL, a `Response` or
+ * its bytes; by default it is fetched from next to the glue.
+ */
+export function load(source) {
+  ready ??= init(source === undefined ? undefined : { module_or_path: source });
+  return ready;
+}
+
+/**
+ * Check every attestation in `text`: device output read over WebUSB, a
+ * file, or the text of a scanned QR code. Other lines are skipped.
+ *
+ * Resolves to one outcome per attestation found, in input order:
+ * `{ line, ok, reason }` for one that failed, and for one that parsed also
+ * `{ event, counter, payloadVersion, algorithm, publicKey }`. With
+ * `challenge` (64 hex digits), each must answer it. Rejects if the
+ * challenge is malformed.
+ */
+export async function verifyText(text, { challenge } = {}) {
+  await load();
+  return JSON.parse(verifyJson(text, challenge));
+}
+
+/** The outcome for the one attestation a QR code holds, or null if none */
+export async function verifyScan(text, options) {
+  const [outcome] = await verifyText(text.trim(), options);
+  return outcome ?? null;
+}
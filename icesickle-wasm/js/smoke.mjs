@@ -0,0 +1,56 @@
+// Smoke test of the WASM verifier under Node: every vector of the
+// known-answer corpus must verify in each of its forms through the JS
+// wrapper, and a forged one must fail.
+//
+//   cargo vectors > vectors.jsonl
+//   node icesickle-wasm/js/smoke.mjs vectors.jsonl
+
+import { readFileSync } from "node:fs";
+import { load, verifyText, verifyScan } from "./icesickle.js";
+
+const [corpus] = process.argv.slice(2);
+if (!corpus) {
+  console.error("usage: node smoke.mjs <vectors.jsonl>");
+  process.exit(2);
+}
+
+await load(readFileSync(new URL("./pkg/icesickle_wasm_bg.wasm", import.meta.url)));
+
+const vectors = readFileSync(corpus, "utf8")
+  .split("\n")
+  .filter((line) => line.trim())
+  .map((line) => JSON.parse(line));
+let failed = 0;
+const fail = (message) => {
+  console.error(`FAIL ${message}`);
+  failed += 1;
+};
+
+for (const vector of vectors) {
+  const forms = {
+    fixedLine: vector.fixedLine,
+    compactText: vector.compactText,
+    record: JSON.stringify(vector.record),
+  };
+  for (const [form, text] of Object.entries(forms)) {
+    const outcome = await verifyScan(text);
+    if (!outcome?.ok) {
+      fail(`${vector.name} ${form}: ${outcome?.reason ?? "not found"}`);
+    }
+  }
+}
+
+const forged = vectors[0].fixedLine.replace(/^ATT (\d+) (\d+)/, "ATT $1 9$2");
+const outcome = await verifyScan(forged);
+if (outcome?.ok !== false) {
+  fail("a forged fixed line verified");
+}
+try {
+  await verifyText(vectors[0].fixedLine, { challenge: "42" });
+  fail("a malformed challenge was accepted");
+} catch {
+  // Thrown by the WASM side, as it should be
+}
+
+console.log(`${vectors.length} vectors, ${failed} failed`);
+process.exit(failed === 0 && vectors.length > 0 ? 0 : 1);
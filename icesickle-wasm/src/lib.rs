@@ -0,0 +1,171 @@
+//! Browser-side verification: `icesickle-verify`'s record parsers and checks,
+//! built for `wasm32-unknown-unknown`
+//!
+//! A page hands over device output, as read over WebUSB or scanned from the
+//! display's QR code, and gets back one [`Outcome`] per attestation in it.
+//! Lines are read by `icesickle_verify::record::parse_line` and checked by
+//! `record::check`, exactly as `icesickle-verify` reads a file: fixed lines,
+//! compact text and JSON records are found, anything else is skipped.
+//!
+//! The wasm32 build exports [`verify_json`] to JS as `verifyText`, and
+//! `js/icesickle.js` wraps it. Replay protection is left to the collector
+//! the page submits to: it needs a store that outlives the page.
+
+use serde::Serialize;
+
+use icesickle_core::attestation::{hex_decode_array, hex_encode};
+use icesickle_verify::record::{check, parse_line};
+
+/// What became of one attestation in the input
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outcome {
+    /// 1-based line of the input it was found on
+    pub line: usize,
+    pub ok: bool,
+    /// Why it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The rest only once the record parsed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_version: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// Check every attestation in `text`; `challenge`, if given, is the 64 hex
+/// digits each must answer
+pub fn verify_text(text: &str, challenge: Option<&str>) -> Result<Vec<Outcome>, String> {
+    let challenge: Option<[u8; 32]> = match challenge {
+        Some(hex) => Some(hex_decode_array(hex).ok_or("challenge must be 64 hex digits")?),
+        None => None,
+    };
+
+    let mut outcomes = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let Some(parsed) = parse_line(line) else {
+            continue;
+        };
+        let outcome = match parsed {
+            Ok(record) => {
+                let result = check(&record, challenge.as_ref());
+                Outcome {
+                    line: index + 1,
+                    ok: result.is_ok(),
+                    reason: result.err(),
+                    event: Some(record.event.name()),
+                    counter: Some(record.counter),
+                    payload_version: Some(record.version),
+                    algorithm: Some(record.algorithm.name()),
+                    public_key: Some(hex_encode::<64>(&record.public_key).to_string()),
+                }
+            }
+            Err(reason) => Outcome {
+                line: index + 1,
+                ok: false,
+                reason: Some(reason),
+                event: None,
+                counter: None,
+                payload_version: None,
+                algorithm: None,
+                public_key: None,
+            },
+        };
+        outcomes.push(outcome);
+    }
+    Ok(outcomes)
+}
+
+/// [`verify_text`], with the outcomes as a JSON array
+pub fn verify_json(text: &str, challenge: Option<&str>) -> Result<String, String> {
+    let outcomes = verify_text(text, challenge)?;
+    serde_json::to_string(&outcomes).map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+mod bindings {
+    use wasm_bindgen::prelude::*;
+
+    /// `verify_json`; an `Err` is thrown as a string
+    #[wasm_bindgen(js_name = verifyText)]
+    pub fn verify_text(text: &str, challenge: Option<String>) -> Result<String, String> {
+        super::verify_json(text, challenge.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icesickle_core::attestation::{Attestation, AttestationEvent};
+    use icesickle_core::entropy::HardwareRng;
+    use icesickle_core::hal::mock::{MockNoise, MockTimer};
+    use icesickle_core::protocol::AttestationRecord;
+
+    fn record(challenge: Option<[u8; 32]>) -> AttestationRecord {
+        let rng = HardwareRng::from_source(MockNoise::new(0x5c)).unwrap();
+        let event = AttestationEvent::ButtonPress { gpio: 0 };
+        let attestation =
+            Attestation::create_with_challenge(&rng, &MockTimer::at(1_000), event, challenge)
+                .unwrap();
+        AttestationRecord::from(&attestation)
+    }
+
+    #[test]
+    fn test_every_form_verifies() {
+        let record = record(None);
+        let text = [
+            "I (1234) icesickle: Button press detected",
+            record.json().trim_end(),
+            record.fixed_line().trim_end(),
+            record.compact_text().trim_end(),
+        ]
+        .join("\n");
+        let outcomes = verify_text(&text, None).unwrap();
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(
+            outcomes.iter().map(|o| o.line).collect::<Vec<_>>(),
+            [2, 3, 4]
+        );
+        assert!(outcomes.iter().all(|o| o.ok));
+        assert_eq!(outcomes[0].event, Some("ButtonPress"));
+        assert_eq!(outcomes[0].counter, Some(record.counter));
+    }
+
+    #[test]
+    fn test_failures_are_reported() {
+        let record = record(Some([0x42; 32]));
+        let json = record.json().trim_end().to_string();
+        let forged = json.replace("\"counter\":", "\"counter\":1");
+        let text = format!("{}\n{}\nATT 12 garbage\n", json, forged);
+
+        let outcomes = verify_text(&text, Some(&"42".repeat(32))).unwrap();
+        assert!(outcomes[0].ok);
+        assert_eq!(
+            outcomes[1].reason.as_deref(),
+            Some("signature does not verify")
+        );
+        assert!(!outcomes[2].ok);
+        assert_eq!(outcomes[2].event, None);
+
+        let other = verify_text(&text, Some(&"43".repeat(32))).unwrap();
+        assert_eq!(
+            other[0].reason.as_deref(),
+            Some("answers a different challenge")
+        );
+        assert!(verify_text(&text, Some("42")).is_err());
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let json = verify_json(&record(None).fixed_line(), None).unwrap();
+        assert!(json.starts_with("[{\"line\":1,\"ok\":true,\"event\":\"ButtonPress\""));
+        assert!(!json.contains("reason"));
+        assert_eq!(verify_json("no attestations", None).unwrap(), "[]");
+    }
+}
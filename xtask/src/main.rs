@@ -0,0 +1,447 @@
+//! Host-side development tasks: `cargo xtask <task>`
+//!
+//! - `size [--minimal]`: build the release firmware (optionally the
+//!   `minimal` profile), report its flash footprint by section and fail if
+//!   it exceeds the budget in `size-budget.txt`.
+//! - `target-test [--port <port>]`: build the on-target tests, flash them
+//!   to a connected devkit with `espflash`, echo the console until the
+//!   result line and exit with the result.
+//! - `hil --gpio-chip <chip> --gpio-line <line> [--port <port>] [--presses
+//!   <n>]`: flash the release firmware the same way, press the button
+//!   through a host GPIO line (`gpioset`, libgpiod v2) wired to the
+//!   devkit's button pin, capture the JSON record each press prints and
+//!   check them all with `icesickle-verify`.
+//!
+//! The host's line is driven open drain: low for a press, and released
+//! otherwise, so the board's pull-up keeps the pin high and the button
+//! still works by hand. It must be released while the devkit resets, as
+//! GPIO0 held low at reset enters the ROM bootloader.
+//!
+//! The size check is meant to be run locally before a change lands, not
+//! only in CI: flash growth is easiest to fix in the change that caused it.
+//! The footprint is the sum of loadable ELF sections (`SHF_ALLOC` and not
+//! `NOBITS`), i.e. everything the bootloader copies out of flash.
+
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitCode, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: cargo xtask size [--minimal]
+       cargo xtask target-test [--port <port>]
+       cargo xtask hil --gpio-chip <chip> --gpio-line <line> [--port <port>] [--presses <n>]";
+
+const FIRMWARE_TARGET: &str = "xtensa-esp32s3-espidf";
+const FIRMWARE_BIN: &str = "icesickle";
+const HOST_TARGET: &str = "x86_64-unknown-linux-gnu";
+const BUDGET_FILE: &str = "size-budget.txt";
+
+/// Sections listed individually in the report
+const TOP_SECTIONS: usize = 8;
+
+/// Console line the firmware prints as its event loop starts
+const READY_LINE: &str = "Entering event loop";
+
+/// How long the flashed firmware gets to reach its event loop
+const BOOT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long the button is held for one press
+const PRESS_MS: u64 = 200;
+
+/// Wait before each press: well past the cooldown (`COOLDOWN_MS`, 1 s)
+/// and the gesture windows, so no press is refused or merged
+const PRESS_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How long a press gets to print its attestation
+const ATTESTATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+const DEFAULT_PRESSES: usize = 3;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("size") => size(args[1..].iter().any(|a| a == "--minimal")),
+        Some("target-test") => target_test(option(&args[1..], "--port")),
+        Some("hil") => hil(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("xtask: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask lives inside the workspace")
+        .to_path_buf()
+}
+
+fn size(minimal: bool) -> Result<(), String> {
+    let root = workspace_root();
+    let profile = if minimal { "minimal" } else { "default" };
+
+    let mut build = Command::new(env!("CARGO"));
+    build
+        .current_dir(&root)
+        .args(["build", "--release", "-p", "icesickle-firmware"]);
+    if minimal {
+        build.args(["--features", "minimal"]);
+    }
+    let status = build.status().map_err(|e| format!("cargo: {}", e))?;
+    if !status.success() {
+        return Err("firmware build failed".to_string());
+    }
+
+    let elf_path = root
+        .join("target")
+        .join(FIRMWARE_TARGET)
+        .join("release")
+        .join(FIRMWARE_BIN);
+    let elf = std::fs::read(&elf_path).map_err(|e| format!("{}: {}", elf_path.display(), e))?;
+    let mut sections = flash_sections(&elf)?;
+    sections.sort_by_key(|s| std::cmp::Reverse(s.1));
+    let total: u64 = sections.iter().map(|(_, size)| size).sum();
+
+    let budget_path = root.join(BUDGET_FILE);
+    let budgets = std::fs::read_to_string(&budget_path)
+        .map_err(|e| format!("{}: {}", budget_path.display(), e))?;
+    let budget = parse_budget(&budgets, profile)
+        .ok_or_else(|| format!("no `{}` entry in {}", profile, BUDGET_FILE))?;
+
+    println!("Flash footprint ({} profile):", profile);
+    for (name, size) in sections.iter().take(TOP_SECTIONS) {
+        println!("  {:<24} {:>9}", name, size);
+    }
+    println!("  {:<24} {:>9}", "total", total);
+    println!("  {:<24} {:>9}", "budget", budget);
+
+    if total > budget {
+        return Err(format!("over budget by {} bytes", total - budget));
+    }
+    println!("  {:<24} {:>9}", "headroom", budget - total);
+    Ok(())
+}
+
+/// The value after `name` in `args`
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|at| args.get(at + 1))
+        .map(String::as_str)
+}
+
+fn target_test(port: Option<&str>) -> Result<(), String> {
+    let root = workspace_root();
+
+    let build = Command::new(env!("CARGO"))
+        .current_dir(&root)
+        .args(["test", "-p", "icesickle-firmware", "--no-run"])
+        .arg("--message-format=json-render-diagnostics")
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("cargo: {}", e))?;
+    if !build.status.success() {
+        return Err("firmware test build failed".to_string());
+    }
+    let messages = String::from_utf8_lossy(&build.stdout);
+    let elf = messages
+        .lines()
+        .find_map(|line| json_string(line, "executable"))
+        .ok_or("cargo reported no test executable")?;
+
+    let mut monitor = flash_and_monitor(&elf, port)?;
+
+    // The device prints no result if it hangs; interrupt with Ctrl-C
+    let console = BufReader::new(monitor.stdout.take().expect("stdout is piped"));
+    let mut passed = None;
+    for line in console.lines() {
+        let line = line.map_err(|e| format!("espflash: {}", e))?;
+        println!("{}", line);
+        if let Some(at) = line.find("test result: ") {
+            passed = Some(line[at..].starts_with("test result: ok"));
+            break;
+        }
+    }
+    let _ = monitor.kill();
+    let _ = monitor.wait();
+
+    match passed {
+        Some(true) => Ok(()),
+        Some(false) => Err("on-target tests failed".to_string()),
+        None => Err("console closed before a test result".to_string()),
+    }
+}
+
+/// Flash `elf` with `espflash` and monitor the console, on its stdout
+fn flash_and_monitor(elf: impl AsRef<OsStr>, port: Option<&str>) -> Result<Child, String> {
+    let mut flash = Command::new("espflash");
+    flash.args(["flash", "--monitor", "--non-interactive"]);
+    if let Some(port) = port {
+        flash.args(["--port", port]);
+    }
+    flash
+        .arg(elf)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("espflash: {}", e))
+}
+
+fn hil(args: &[String]) -> Result<(), String> {
+    let chip = option(args, "--gpio-chip").ok_or(USAGE)?;
+    let line = option(args, "--gpio-line").ok_or(USAGE)?;
+    let presses = match option(args, "--presses") {
+        Some(n) => n.parse().map_err(|_| "presses must be a number")?,
+        None => DEFAULT_PRESSES,
+    };
+    let root = workspace_root();
+
+    // Both builds first, so a failing one costs no flashing
+    for (what, build) in [
+        (
+            "firmware",
+            &["build", "--release", "-p", "icesickle-firmware"][..],
+        ),
+        (
+            "verifier",
+            &["build", "-p", "icesickle-verify", "--target", HOST_TARGET][..],
+        ),
+    ] {
+        let status = Command::new(env!("CARGO"))
+            .current_dir(&root)
+            .args(build)
+            .status()
+            .map_err(|e| format!("cargo: {}", e))?;
+        if !status.success() {
+            return Err(format!("{} build failed", what));
+        }
+    }
+    let target = root.join("target");
+    let elf = target
+        .join(FIRMWARE_TARGET)
+        .join("release")
+        .join(FIRMWARE_BIN);
+    let verifier = target
+        .join(HOST_TARGET)
+        .join("debug")
+        .join("icesickle-verify");
+
+    let mut monitor = flash_and_monitor(&elf, option(args, "--port"))?;
+    // Read on a thread of its own, so every wait can time out
+    let console = BufReader::new(monitor.stdout.take().expect("stdout is piped"));
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in console.lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let records = press_and_capture(&receiver, chip, line, presses);
+    let _ = monitor.kill();
+    let _ = monitor.wait();
+    let records = records?;
+
+    let capture = target.join("hil-attestations.txt");
+    std::fs::write(&capture, records.join("\n") + "\n")
+        .map_err(|e| format!("{}: {}", capture.display(), e))?;
+    let status = Command::new(&verifier)
+        .arg(&capture)
+        .status()
+        .map_err(|e| format!("{}: {}", verifier.display(), e))?;
+    if !status.success() {
+        return Err(format!("attestations in {} failed", capture.display()));
+    }
+    Ok(())
+}
+
+/// Press the button `presses` times once the firmware is up, and return
+/// the JSON record each press printed
+fn press_and_capture(
+    console: &Receiver<String>,
+    chip: &str,
+    line: &str,
+    presses: usize,
+) -> Result<Vec<String>, String> {
+    wait_for(console, BOOT_TIMEOUT, |l| l.contains(READY_LINE))
+        .ok_or("the firmware did not reach its event loop")?;
+    let mut records = Vec::new();
+    for press in 1..=presses {
+        thread::sleep(PRESS_INTERVAL);
+        press_button(chip, line)?;
+        let record = wait_for(console, ATTESTATION_TIMEOUT, is_record)
+            .ok_or_else(|| format!("press {} printed no attestation", press))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Hold the button down for [`PRESS_MS`] through GPIO `line` of `chip`,
+/// then release it
+fn press_button(chip: &str, line: &str) -> Result<(), String> {
+    let status = Command::new("gpioset")
+        .args(["--chip", chip, "--drive", "open-drain", "--toggle"])
+        .arg(format!("{}ms,0", PRESS_MS))
+        .arg(format!("{}=0", line))
+        .status()
+        .map_err(|e| format!("gpioset: {}", e))?;
+    if !status.success() {
+        return Err(format!("gpioset could not drive {} line {}", chip, line));
+    }
+    Ok(())
+}
+
+/// Echo console lines until one is `found`, and return it; `None` once
+/// `timeout` passes or the console closes
+fn wait_for(
+    console: &Receiver<String>,
+    timeout: Duration,
+    found: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let left = deadline.checked_duration_since(Instant::now())?;
+        let line = console.recv_timeout(left).ok()?;
+        println!("{}", line);
+        if found(&line) {
+            return Some(line);
+        }
+    }
+}
+
+/// A JSON record, as the console prints each attestation
+fn is_record(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with('{') && line.contains("\"payloadVersion\"")
+}
+
+/// String value of `"key":"..."` in one line of cargo's JSON messages
+fn json_string(line: &str, key: &str) -> Option<String> {
+    let start = line.find(&format!("\"{}\":\"", key))? + key.len() + 4;
+    let mut value = String::new();
+    let mut chars = line[start..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => value.push(chars.next()?),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// Find `profile = <bytes>` in the budget file (`#` comments, `_` separators)
+fn parse_budget(text: &str, profile: &str) -> Option<u64> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim() == profile)
+        .and_then(|(_, value)| value.trim().replace('_', "").parse().ok())
+}
+
+/// Name and size of every loadable section of a little-endian ELF32 image
+fn flash_sections(elf: &[u8]) -> Result<Vec<(String, u64)>, String> {
+    const SHT_NOBITS: u32 = 8;
+    const SHF_ALLOC: u32 = 0x2;
+
+    if elf.get(..4) != Some(b"\x7fELF".as_slice()) || elf.get(4) != Some(&1) {
+        return Err("firmware image is not an ELF32 file".to_string());
+    }
+    let u16_at = |at: usize| {
+        elf.get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u32_at = |at: usize| {
+        elf.get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let truncated = || "truncated ELF header".to_string();
+
+    let shoff = u32_at(0x20).ok_or_else(truncated)? as usize;
+    let shentsize = u16_at(0x2e).ok_or_else(truncated)? as usize;
+    let shnum = u16_at(0x30).ok_or_else(truncated)? as usize;
+    let shstrndx = u16_at(0x32).ok_or_else(truncated)? as usize;
+
+    let header = |index: usize| shoff + index * shentsize;
+    let names_at = u32_at(header(shstrndx) + 16).ok_or_else(truncated)? as usize;
+
+    let mut sections = Vec::new();
+    for index in 0..shnum {
+        let at = header(index);
+        let (Some(name), Some(kind), Some(flags), Some(size)) =
+            (u32_at(at), u32_at(at + 4), u32_at(at + 8), u32_at(at + 20))
+        else {
+            return Err("truncated section table".to_string());
+        };
+        if flags & SHF_ALLOC == 0 || kind == SHT_NOBITS || size == 0 {
+            continue;
+        }
+        let name = elf
+            .get(names_at + name as usize..)
+            .and_then(|rest| rest.split(|&b| b == 0).next())
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+            .unwrap_or_default();
+        sections.push((name, u64::from(size)));
+    }
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_budget() {
+        let text = "# comment\ndefault = 1_150_000\nminimal=950000 # tight\n";
+        assert_eq!(parse_budget(text, "default"), Some(1_150_000));
+        assert_eq!(parse_budget(text, "minimal"), Some(950_000));
+        assert_eq!(parse_budget(text, "debug"), None);
+    }
+
+    #[test]
+    fn test_json_string() {
+        let line = r#"{"reason":"compiler-artifact","executable":"/t/a b\\c\"d"}"#;
+        assert_eq!(
+            json_string(line, "executable").as_deref(),
+            Some(r#"/t/a b\c"d"#)
+        );
+        assert_eq!(json_string(r#"{"executable":null}"#, "executable"), None);
+        assert_eq!(json_string(r#"{"executable":"/t/cut"#, "executable"), None);
+    }
+
+    #[test]
+    fn test_wait_for_record() {
+        let (sender, receiver) = mpsc::channel();
+        for line in [
+            "I (1234) icesickle: Button press detected",
+            "{\"payloadVersion\":12,\"counter\":0}",
+            "after",
+        ] {
+            sender.send(line.to_string()).unwrap();
+        }
+        let found = wait_for(&receiver, Duration::from_secs(1), is_record);
+        assert_eq!(
+            found.as_deref(),
+            Some("{\"payloadVersion\":12,\"counter\":0}")
+        );
+        assert_eq!(
+            wait_for(&receiver, Duration::from_millis(10), is_record),
+            None
+        );
+        drop(sender);
+        assert_eq!(wait_for(&receiver, Duration::from_secs(1), is_record), None);
+    }
+
+    #[test]
+    fn test_rejects_non_elf32() {
+        assert!(flash_sections(b"not an elf").is_err());
+        assert!(flash_sections(b"\x7fELF\x02").is_err());
+    }
+}